@@ -1,4 +1,40 @@
 fn main() {
+  #[cfg(target_os = "macos")]
+  if std::env::var("CARGO_CFG_TARGET_OS").as_deref() == Ok("ios") {
+    link_storage_plugin();
+  }
   tauri_build::build();
 }
 
+/// Builds the Swift half of the storage plugin (src/scan/ios.rs) into the
+/// app, against the copy of Tauri's Swift API it's given in `.tauri`, as a
+/// plugin crate's build would.
+#[cfg(target_os = "macos")]
+fn link_storage_plugin() {
+  use std::path::Path;
+
+  fn copy(from: &Path, to: &Path) -> std::io::Result<()> {
+    std::fs::create_dir_all(to)?;
+    for entry in std::fs::read_dir(from)? {
+      let entry = entry?;
+      let name = entry.file_name();
+      if name == ".build" || name == "Package.resolved" || name == "Tests" {
+        continue;
+      }
+      if entry.file_type()?.is_dir() {
+        copy(&entry.path(), &to.join(&name))?;
+      } else {
+        std::fs::copy(entry.path(), to.join(&name))?;
+      }
+    }
+    Ok(())
+  }
+
+  println!("cargo:rerun-if-changed=ios/Sources");
+  let api = std::env::var("DEP_TAURI_IOS_LIBRARY_PATH")
+    .expect("tauri didn't say where its Swift API is");
+  let target = Path::new(".tauri").join("tauri-api");
+  let _ = std::fs::remove_dir_all(&target);
+  copy(Path::new(&api), &target).expect("couldn't copy Tauri's Swift API");
+  tauri_utils::build::link_apple_library("space-usage-storage", "ios");
+}
@@ -0,0 +1,270 @@
+package com.example.space_usage_app
+
+import android.Manifest
+import android.app.Activity
+import android.app.AppOpsManager
+import android.app.usage.StorageStatsManager
+import android.content.Context
+import android.content.Intent
+import android.content.pm.PackageManager
+import android.net.Uri
+import android.os.Build
+import android.os.Process
+import android.os.storage.StorageManager
+import android.provider.DocumentsContract
+import android.provider.MediaStore
+import android.provider.Settings
+import androidx.activity.result.ActivityResult
+import app.tauri.PermissionState
+import app.tauri.annotation.ActivityCallback
+import app.tauri.annotation.Command
+import app.tauri.annotation.InvokeArg
+import app.tauri.annotation.Permission
+import app.tauri.annotation.PermissionCallback
+import app.tauri.annotation.TauriPlugin
+import app.tauri.plugin.Invoke
+import app.tauri.plugin.JSArray
+import app.tauri.plugin.JSObject
+import app.tauri.plugin.Plugin
+
+// The Kotlin half of the Android scans (src-tauri/src/scan/android.rs):
+// listings come back as `{ entries: [{ path, size, modified, dir }], problems }`
+// with `/`-separated paths under what was listed and times in seconds.
+//
+// The app's manifest has to ask for READ_MEDIA_IMAGES, READ_MEDIA_VIDEO,
+// READ_MEDIA_AUDIO, READ_EXTERNAL_STORAGE (maxSdkVersion 32),
+// PACKAGE_USAGE_STATS and QUERY_ALL_PACKAGES for all of this to work.
+
+@InvokeArg
+class ListTreeArgs {
+  lateinit var uri: String
+}
+
+@TauriPlugin(
+  permissions = [
+    // Android 13 split the storage permission by kind of media.
+    Permission(
+      strings = [
+        Manifest.permission.READ_MEDIA_IMAGES,
+        Manifest.permission.READ_MEDIA_VIDEO,
+        Manifest.permission.READ_MEDIA_AUDIO,
+      ],
+      alias = "media",
+    ),
+    Permission(strings = [Manifest.permission.READ_EXTERNAL_STORAGE], alias = "storage"),
+  ]
+)
+class StoragePlugin(private val activity: Activity) : Plugin(activity) {
+  private val mediaAlias = if (Build.VERSION.SDK_INT >= 33) "media" else "storage"
+
+  @Command
+  fun pickFolder(invoke: Invoke) {
+    startActivityForResult(invoke, Intent(Intent.ACTION_OPEN_DOCUMENT_TREE), "folderPicked")
+  }
+
+  @ActivityCallback
+  fun folderPicked(invoke: Invoke, result: ActivityResult) {
+    val uri = result.data?.data
+    if (result.resultCode != Activity.RESULT_OK || uri == null) {
+      invoke.reject("No folder was picked")
+      return
+    }
+    // Kept across restarts, so the folder can be scanned again later.
+    activity.contentResolver.takePersistableUriPermission(uri, Intent.FLAG_GRANT_READ_URI_PERMISSION)
+    invoke.resolve(folder(uri))
+  }
+
+  @Command
+  fun persistedFolders(invoke: Invoke) {
+    val folders = JSArray()
+    for (permission in activity.contentResolver.persistedUriPermissions) {
+      if (permission.isReadPermission) folders.put(folder(permission.uri))
+    }
+    val result = JSObject()
+    result.put("folders", folders)
+    invoke.resolve(result)
+  }
+
+  @Command
+  fun openUsageAccess(invoke: Invoke) {
+    activity.startActivity(Intent(Settings.ACTION_USAGE_ACCESS_SETTINGS))
+    invoke.resolve()
+  }
+
+  @Command
+  fun listTree(invoke: Invoke) {
+    val args = invoke.parseArgs(ListTreeArgs::class.java)
+    val tree = Uri.parse(args.uri)
+    Thread { invoke.resolve(walkTree(tree)) }.start()
+  }
+
+  @Command
+  fun listShared(invoke: Invoke) {
+    if (getPermissionState(mediaAlias) != PermissionState.GRANTED) {
+      requestPermissionForAlias(mediaAlias, invoke, "mediaPermission")
+      return
+    }
+    Thread { invoke.resolve(listMediaStore()) }.start()
+  }
+
+  @PermissionCallback
+  fun mediaPermission(invoke: Invoke) {
+    if (getPermissionState(mediaAlias) != PermissionState.GRANTED) {
+      invoke.reject("Access to photos, videos and audio was declined")
+      return
+    }
+    Thread { invoke.resolve(listMediaStore()) }.start()
+  }
+
+  private fun folder(uri: Uri): JSObject {
+    val document = DocumentsContract.buildDocumentUriUsingTree(uri, DocumentsContract.getTreeDocumentId(uri))
+    val projection = arrayOf(DocumentsContract.Document.COLUMN_DISPLAY_NAME)
+    val name = activity.contentResolver.query(document, projection, null, null, null)?.use { cursor ->
+      if (cursor.moveToFirst()) cursor.getString(0) else null
+    }
+    val folder = JSObject()
+    folder.put("uri", uri.toString())
+    folder.put("name", name ?: uri.lastPathSegment ?: uri.toString())
+    return folder
+  }
+
+  private fun entry(path: String, size: Long, modified: Long?, dir: Boolean): JSObject {
+    val entry = JSObject()
+    entry.put("path", path)
+    entry.put("size", size)
+    if (modified != null && modified > 0) entry.put("modified", modified)
+    entry.put("dir", dir)
+    return entry
+  }
+
+  private fun problem(path: String, message: String, denied: Boolean): JSObject {
+    val problem = JSObject()
+    problem.put("path", path)
+    problem.put("message", message)
+    problem.put("denied", denied)
+    return problem
+  }
+
+  private fun listing(entries: JSArray, problems: JSArray): JSObject {
+    val listing = JSObject()
+    listing.put("entries", entries)
+    listing.put("problems", problems)
+    return listing
+  }
+
+  /** Everything under a picked folder, one child query per folder. */
+  private fun walkTree(tree: Uri): JSObject {
+    val entries = JSArray()
+    val problems = JSArray()
+    val projection = arrayOf(
+      DocumentsContract.Document.COLUMN_DOCUMENT_ID,
+      DocumentsContract.Document.COLUMN_DISPLAY_NAME,
+      DocumentsContract.Document.COLUMN_MIME_TYPE,
+      DocumentsContract.Document.COLUMN_SIZE,
+      DocumentsContract.Document.COLUMN_LAST_MODIFIED,
+    )
+    val pending = ArrayDeque<Pair<String, String>>()
+    pending.add(Pair(DocumentsContract.getTreeDocumentId(tree), ""))
+    while (pending.isNotEmpty()) {
+      val (id, prefix) = pending.removeFirst()
+      val children = DocumentsContract.buildChildDocumentsUriUsingTree(tree, id)
+      try {
+        val cursor = activity.contentResolver.query(children, projection, null, null, null) ?: continue
+        try {
+          while (cursor.moveToNext()) {
+            val name = cursor.getString(1)?.replace('/', '∕') ?: continue
+            val path = if (prefix.isEmpty()) name else "$prefix/$name"
+            val dir = cursor.getString(2) == DocumentsContract.Document.MIME_TYPE_DIR
+            val size = if (cursor.isNull(3)) 0L else cursor.getLong(3)
+            val modified = if (cursor.isNull(4)) null else cursor.getLong(4) / 1000
+            entries.put(entry(path, if (dir) 0L else size, modified, dir))
+            if (dir) pending.add(Pair(cursor.getString(0), path))
+          }
+        } finally {
+          cursor.close()
+        }
+      } catch (e: SecurityException) {
+        problems.put(problem(prefix, e.message ?: "Access denied", true))
+      } catch (e: Exception) {
+        problems.put(problem(prefix, e.message ?: e.toString(), false))
+      }
+    }
+    return listing(entries, problems)
+  }
+
+  /**
+   * Shared storage as MediaStore knows it. Since Android 11 other apps'
+   * files that aren't media stay out of sight; what each app takes is added
+   * under `Apps` when usage access is granted.
+   */
+  private fun listMediaStore(): JSObject {
+    val entries = JSArray()
+    val problems = JSArray()
+    val files = MediaStore.Files.getContentUri("external")
+    val pathColumn =
+      if (Build.VERSION.SDK_INT >= 29) MediaStore.MediaColumns.RELATIVE_PATH else MediaStore.MediaColumns.DATA
+    val projection = arrayOf(
+      pathColumn,
+      MediaStore.MediaColumns.DISPLAY_NAME,
+      MediaStore.MediaColumns.SIZE,
+      MediaStore.MediaColumns.DATE_MODIFIED,
+    )
+    // Folders are rows too, without a type.
+    val selection = "${MediaStore.MediaColumns.MIME_TYPE} IS NOT NULL"
+    try {
+      val cursor = activity.contentResolver.query(files, projection, selection, null, null)
+      try {
+        while (cursor != null && cursor.moveToNext()) {
+          val name = cursor.getString(1) ?: continue
+          val path = if (Build.VERSION.SDK_INT >= 29) {
+            (cursor.getString(0) ?: "") + name
+          } else {
+            cursor.getString(0)?.removePrefix("/storage/emulated/0/") ?: continue
+          }
+          val modified = if (cursor.isNull(3)) null else cursor.getLong(3)
+          entries.put(entry(path, cursor.getLong(2), modified, false))
+        }
+      } finally {
+        cursor?.close()
+      }
+    } catch (e: SecurityException) {
+      problems.put(problem("", e.message ?: "Access denied", true))
+    }
+    if (hasUsageAccess()) {
+      listApps(entries)
+    } else {
+      problems.put(problem("Apps", "Apps aren't included without usage access", true))
+    }
+    return listing(entries, problems)
+  }
+
+  private fun hasUsageAccess(): Boolean {
+    val appOps = activity.getSystemService(Context.APP_OPS_SERVICE) as AppOpsManager
+    val mode = if (Build.VERSION.SDK_INT >= 29) {
+      appOps.unsafeCheckOpNoThrow(AppOpsManager.OPSTR_GET_USAGE_STATS, Process.myUid(), activity.packageName)
+    } else {
+      @Suppress("DEPRECATION")
+      appOps.checkOpNoThrow(AppOpsManager.OPSTR_GET_USAGE_STATS, Process.myUid(), activity.packageName)
+    }
+    return mode == AppOpsManager.MODE_ALLOWED
+  }
+
+  /** The app, data and cache of each installed app, as files under `Apps`. */
+  private fun listApps(entries: JSArray) {
+    val stats = activity.getSystemService(Context.STORAGE_STATS_SERVICE) as StorageStatsManager
+    val packages = activity.packageManager
+    for (app in packages.getInstalledApplications(PackageManager.GET_META_DATA)) {
+      val usage = try {
+        stats.queryStatsForPackage(StorageManager.UUID_DEFAULT, app.packageName, Process.myUserHandle())
+      } catch (e: Exception) {
+        continue
+      }
+      val label = packages.getApplicationLabel(app).toString().replace('/', '∕')
+      val folder = "Apps/$label"
+      // Data includes the cache, which is shown apart.
+      entries.put(entry("$folder/App", usage.appBytes, null, false))
+      entries.put(entry("$folder/Data", usage.dataBytes - usage.cacheBytes, null, false))
+      entries.put(entry("$folder/Cache", usage.cacheBytes, null, false))
+    }
+  }
+}
@@ -0,0 +1,28 @@
+// swift-tools-version:5.3
+// The Swift half of the storage plugin, linked into the app by build.rs.
+
+import PackageDescription
+
+let package = Package(
+  name: "space-usage-storage",
+  platforms: [
+    .iOS(.v14)
+  ],
+  products: [
+    .library(
+      name: "space-usage-storage",
+      type: .static,
+      targets: ["space-usage-storage"])
+  ],
+  dependencies: [
+    .package(name: "Tauri", path: "../.tauri/tauri-api")
+  ],
+  targets: [
+    .target(
+      name: "space-usage-storage",
+      dependencies: [
+        .byName(name: "Tauri")
+      ],
+      path: "Sources")
+  ]
+)
@@ -0,0 +1,128 @@
+import Foundation
+import Tauri
+import UIKit
+import UniformTypeIdentifiers
+import WebKit
+
+// The Swift half of the iOS scans (src-tauri/src/scan/ios.rs). Folders
+// picked in Files are kept as bookmarks in the user defaults, by id; a
+// location comes back as `{ id, name, path }`.
+
+class IdArgs: Decodable {
+  let id: String
+}
+
+class StoragePlugin: Plugin, UIDocumentPickerDelegate {
+  private let bookmarksKey = "storage.bookmarks"
+  private var picking: Invoke?
+  // The folders being scanned, for their access to be given back.
+  private var open: [String: URL] = [:]
+
+  @objc public func pickFolder(_ invoke: Invoke) {
+    DispatchQueue.main.async {
+      let picker = UIDocumentPickerViewController(forOpeningContentTypes: [.folder])
+      picker.delegate = self
+      self.picking = invoke
+      self.manager.viewController?.present(picker, animated: true)
+    }
+  }
+
+  public func documentPicker(
+    _ controller: UIDocumentPickerViewController, didPickDocumentsAt urls: [URL]
+  ) {
+    guard let invoke = picking else { return }
+    picking = nil
+    guard let url = urls.first else {
+      invoke.reject("No folder was picked")
+      return
+    }
+    // A bookmark can only be made while the folder is open to the app.
+    let opened = url.startAccessingSecurityScopedResource()
+    defer {
+      if opened { url.stopAccessingSecurityScopedResource() }
+    }
+    do {
+      let id = UUID().uuidString
+      var bookmarks = storedBookmarks()
+      bookmarks[id] = try url.bookmarkData()
+      UserDefaults.standard.set(bookmarks, forKey: bookmarksKey)
+      invoke.resolve(location(id, url))
+    } catch {
+      invoke.reject(error.localizedDescription)
+    }
+  }
+
+  public func documentPickerWasCancelled(_ controller: UIDocumentPickerViewController) {
+    picking?.reject("No folder was picked")
+    picking = nil
+  }
+
+  @objc public func listBookmarks(_ invoke: Invoke) {
+    var locations: [JsonObject] = []
+    for id in storedBookmarks().keys.sorted() {
+      if let url = try? resolve(id) { locations.append(location(id, url)) }
+    }
+    invoke.resolve(["locations": locations])
+  }
+
+  @objc public func forget(_ invoke: Invoke) throws {
+    let args = try invoke.parseArgs(IdArgs.self)
+    var bookmarks = storedBookmarks()
+    bookmarks.removeValue(forKey: args.id)
+    UserDefaults.standard.set(bookmarks, forKey: bookmarksKey)
+    invoke.resolve()
+  }
+
+  @objc public func startAccess(_ invoke: Invoke) throws {
+    let args = try invoke.parseArgs(IdArgs.self)
+    do {
+      let url = try resolve(args.id)
+      guard url.startAccessingSecurityScopedResource() else {
+        invoke.reject("The folder can't be opened; pick it again")
+        return
+      }
+      open[args.id]?.stopAccessingSecurityScopedResource()
+      open[args.id] = url
+      invoke.resolve(["path": url.path])
+    } catch {
+      invoke.reject(error.localizedDescription)
+    }
+  }
+
+  @objc public func stopAccess(_ invoke: Invoke) throws {
+    let args = try invoke.parseArgs(IdArgs.self)
+    open.removeValue(forKey: args.id)?.stopAccessingSecurityScopedResource()
+    invoke.resolve()
+  }
+
+  private func storedBookmarks() -> [String: Data] {
+    return UserDefaults.standard.dictionary(forKey: bookmarksKey) as? [String: Data] ?? [:]
+  }
+
+  /// Where the bookmark `id` points now, renewing it if the folder moved.
+  private func resolve(_ id: String) throws -> URL {
+    guard let data = storedBookmarks()[id] else {
+      throw NSError(
+        domain: "storage", code: 1,
+        userInfo: [NSLocalizedDescriptionKey: "The folder was forgotten"])
+    }
+    var stale = false
+    let url = try URL(resolvingBookmarkData: data, bookmarkDataIsStale: &stale)
+    if stale, let renewed = try? url.bookmarkData() {
+      var bookmarks = storedBookmarks()
+      bookmarks[id] = renewed
+      UserDefaults.standard.set(bookmarks, forKey: bookmarksKey)
+    }
+    return url
+  }
+
+  private func location(_ id: String, _ url: URL) -> JsonObject {
+    let name = FileManager.default.displayName(atPath: url.path)
+    return ["id": id, "name": name.isEmpty ? url.lastPathComponent : name, "path": url.path]
+  }
+}
+
+@_cdecl("init_plugin_storage")
+func initPlugin() -> Plugin {
+  return StoragePlugin()
+}
@@ -0,0 +1,72 @@
+//! OS-level gates on what a scan can read, beyond file permissions.
+
+use serde::Serialize;
+
+#[derive(Clone, Serialize)]
+pub struct DiskAccess {
+  /// Whether macOS Full Disk Access is granted; `None` on other platforms, or
+  /// when no protected path could be probed.
+  pub full_disk_access: Option<bool>,
+  /// The protected path whose readability decided `full_disk_access`.
+  pub probed_path: Option<String>,
+}
+
+/// Files only readable with Full Disk Access. Without it, opening them fails
+/// with EPERM ("Operation not permitted") rather than EACCES.
+#[cfg(target_os = "macos")]
+const PROTECTED_PATHS: [&str; 3] = [
+  "/Library/Application Support/com.apple.TCC/TCC.db",
+  "~/Library/Application Support/com.apple.TCC/TCC.db",
+  "~/Library/Safari/Bookmarks.plist",
+];
+
+/// Reports whether the app may read privacy-protected folders (Mail, Messages,
+/// Safari...), which otherwise show up as permission errors in scans.
+#[tauri::command]
+pub async fn check_disk_access() -> Result<DiskAccess, String> {
+  #[cfg(target_os = "macos")]
+  for path in PROTECTED_PATHS {
+    let path = match path.strip_prefix("~/") {
+      Some(rest) => match std::env::var_os("HOME") {
+        Some(home) => std::path::PathBuf::from(home).join(rest),
+        None => continue,
+      },
+      None => std::path::PathBuf::from(path),
+    };
+    let granted = match std::fs::File::open(&path) {
+      Ok(_) => true,
+      Err(e) if e.raw_os_error() == Some(libc::EPERM) => false,
+      // Missing here, or unreadable for ordinary reasons: try the next one.
+      Err(_) => continue,
+    };
+    return Ok(DiskAccess {
+      full_disk_access: Some(granted),
+      probed_path: Some(path.to_string_lossy().to_string()),
+    });
+  }
+  Ok(DiskAccess {
+    full_disk_access: None,
+    probed_path: None,
+  })
+}
+
+/// Opens the Full Disk Access list in System Settings, where the user can
+/// grant it to the app.
+#[tauri::command]
+pub async fn open_disk_access_settings() -> Result<(), String> {
+  #[cfg(target_os = "macos")]
+  {
+    std::process::Command::new("open")
+      .arg("x-apple.systempreferences:com.apple.preference.security?Privacy_AllFiles")
+      .status()
+      .map_err(|e| e.to_string())
+      .and_then(|status| {
+        status
+          .success()
+          .then_some(())
+          .ok_or_else(|| "System Settings didn't open".to_string())
+      })
+  }
+  #[cfg(not(target_os = "macos"))]
+  Err("Full Disk Access only exists on macOS".to_string())
+}
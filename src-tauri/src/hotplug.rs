@@ -0,0 +1,160 @@
+//! Tells the window when volumes come and go, so the drive list stays
+//! current and a USB stick can be scanned as soon as it's plugged in. The OS
+//! says something changed (mountinfo on Linux, the filesystem filter of
+//! kqueue on macOS, `WM_DEVICECHANGE` on Windows); what changed comes from
+//! comparing the volume list before and after.
+
+use crate::volume::{volumes, VolumeInfo};
+use serde::Serialize;
+use std::{sync::mpsc, time::Duration};
+use tauri::{AppHandle, Emitter};
+
+/// Mounting one drive often comes as several changes in a row.
+const SETTLE: Duration = Duration::from_millis(500);
+
+#[derive(Clone, Serialize)]
+pub struct VolumesChangedEvent {
+  pub mounted: Vec<VolumeInfo>,
+  pub unmounted: Vec<VolumeInfo>,
+  /// Every volume now, as `list_volumes` has them.
+  pub volumes: Vec<VolumeInfo>,
+}
+
+/// Starts emitting `volumes_changed` for as long as the app runs.
+pub fn watch(app: AppHandle) {
+  let (changed, changes) = mpsc::channel();
+  std::thread::spawn(move || listen(changed));
+  std::thread::spawn(move || {
+    let mut known = volumes();
+    while changes.recv().is_ok() {
+      while changes.recv_timeout(SETTLE).is_ok() {}
+      let mut now = volumes();
+      now.sort_by(|a, b| a.mount_point.cmp(&b.mount_point));
+      let is_in = |list: &[VolumeInfo], volume: &VolumeInfo| {
+        list.iter().any(|other| other.mount_point == volume.mount_point)
+      };
+      let mounted: Vec<VolumeInfo> = now.iter().filter(|v| !is_in(&known, v)).cloned().collect();
+      let unmounted: Vec<VolumeInfo> = known.iter().filter(|v| !is_in(&now, v)).cloned().collect();
+      if mounted.is_empty() && unmounted.is_empty() {
+        continue;
+      }
+      known = now.clone();
+      let _ = app.emit("volumes_changed", VolumesChangedEvent { mounted, unmounted, volumes: now });
+    }
+  });
+}
+
+/// The kernel flags a mountinfo reader to `poll` each time the mount table
+/// changes, whoever mounted what; no need for udev.
+#[cfg(target_os = "linux")]
+fn listen(changed: mpsc::Sender<()>) {
+  use std::os::fd::AsRawFd;
+  let Ok(file) = std::fs::File::open("/proc/self/mountinfo") else {
+    return;
+  };
+  let mut fd = libc::pollfd {
+    fd: file.as_raw_fd(),
+    events: libc::POLLPRI,
+    revents: 0,
+  };
+  loop {
+    if unsafe { libc::poll(&mut fd, 1, -1) } < 0 {
+      if std::io::Error::last_os_error().kind() == std::io::ErrorKind::Interrupted {
+        continue;
+      }
+      return;
+    }
+    if fd.revents & (libc::POLLPRI | libc::POLLERR) != 0 && changed.send(()).is_err() {
+      return;
+    }
+  }
+}
+
+/// kqueue reports filesystem events with `EVFILT_FS`; the mounts and
+/// unmounts among them are what DiskArbitration announces too.
+#[cfg(target_os = "macos")]
+fn listen(changed: mpsc::Sender<()>) {
+  // From sys/mount.h.
+  const VQ_MOUNT: u32 = 0x0008;
+  const VQ_UNMOUNT: u32 = 0x0010;
+  let queue = unsafe { libc::kqueue() };
+  if queue < 0 {
+    return;
+  }
+  let mut event: libc::kevent = unsafe { std::mem::zeroed() };
+  event.filter = libc::EVFILT_FS;
+  event.flags = libc::EV_ADD | libc::EV_CLEAR;
+  if unsafe { libc::kevent(queue, &event, 1, std::ptr::null_mut(), 0, std::ptr::null()) } < 0 {
+    unsafe { libc::close(queue) };
+    return;
+  }
+  loop {
+    let mut fired: libc::kevent = unsafe { std::mem::zeroed() };
+    let n = unsafe { libc::kevent(queue, std::ptr::null(), 0, &mut fired, 1, std::ptr::null()) };
+    if n < 0 && std::io::Error::last_os_error().kind() != std::io::ErrorKind::Interrupted {
+      break;
+    }
+    if n > 0 && fired.fflags & (VQ_MOUNT | VQ_UNMOUNT) != 0 && changed.send(()).is_err() {
+      break;
+    }
+  }
+  unsafe { libc::close(queue) };
+}
+
+/// Where the window procedure below sends; there's one listener per process.
+#[cfg(windows)]
+static CHANGED: std::sync::OnceLock<mpsc::Sender<()>> = std::sync::OnceLock::new();
+
+/// Volume arrivals and removals, network drives among them, are broadcast
+/// as `WM_DEVICECHANGE` to top-level windows only, so an invisible one is
+/// made to get them.
+#[cfg(windows)]
+fn listen(changed: mpsc::Sender<()>) {
+  use windows_sys::Win32::{
+    Foundation::{HWND, LPARAM, LRESULT, WPARAM},
+    UI::WindowsAndMessaging::{
+      CreateWindowExW, DefWindowProcW, DispatchMessageW, GetMessageW, RegisterClassW,
+      DBT_DEVICEARRIVAL, DBT_DEVICEREMOVECOMPLETE, MSG, WM_DEVICECHANGE, WNDCLASSW,
+    },
+  };
+  unsafe extern "system" fn window_proc(
+    window: HWND,
+    message: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+  ) -> LRESULT {
+    let event = wparam as u32;
+    if message == WM_DEVICECHANGE
+      && (event == DBT_DEVICEARRIVAL || event == DBT_DEVICEREMOVECOMPLETE)
+    {
+      if let Some(changed) = CHANGED.get() {
+        let _ = changed.send(());
+      }
+    }
+    unsafe { DefWindowProcW(window, message, wparam, lparam) }
+  }
+  if CHANGED.set(changed).is_err() {
+    return;
+  }
+  let class_name = crate::volume::to_wide(std::path::Path::new("SpaceUsageVolumeWatch"));
+  let class = WNDCLASSW {
+    lpfnWndProc: Some(window_proc),
+    lpszClassName: class_name.as_ptr(),
+    ..Default::default()
+  };
+  let null = std::ptr::null_mut();
+  let window = unsafe {
+    RegisterClassW(&class);
+    CreateWindowExW(0, class_name.as_ptr(), std::ptr::null(), 0, 0, 0, 0, 0, null, null, null, null)
+  };
+  if window.is_null() {
+    return;
+  }
+  let mut message: MSG = unsafe { std::mem::zeroed() };
+  while unsafe { GetMessageW(&mut message, window, 0, 0) } > 0 {
+    unsafe { DispatchMessageW(&message) };
+  }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+fn listen(_changed: mpsc::Sender<()>) {}
@@ -1,20 +1,222 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+mod access;
+mod hotplug;
+mod monitor;
+mod paths;
+mod priority;
 mod scan;
+mod volume;
 
-use scan::{cancel_scan, start_multi_scan, start_scan, ScanManager};
+use access::{check_disk_access, open_disk_access_settings};
+use monitor::{get_space_monitor, start_space_monitor, stop_space_monitor, SpaceMonitor};
+use scan::{
+  agent::start_agent_scan,
+  alerts::{add_watch_rule, list_watch_rules, remove_watch_rule, Alerts},
+  android::{
+    list_android_folders, open_android_usage_access, pick_android_folder, start_android_scan,
+  },
+  artifacts::{get_dev_artifacts, remove_dev_artifacts},
+  browsers::get_browser_storage,
+  caches::{clear_package_cache, get_package_caches},
+  cancel_all_scans, cancel_scan, canonicalize_path,
+  cleanable::get_cleanable_locations,
+  cloud::{connect_cloud_drive, disconnect_cloud_drive, list_cloud_drives, start_cloud_scan},
+  compare::compare_scans,
+  disk_image::inspect_disk_image,
+  docker::{docker_prune, get_container_usage},
+  duplicates::{get_duplicate_dirs, get_duplicate_files},
+  elevated::start_elevated_scan,
+  empty::{get_empty_dirs, remove_empty_dirs},
+  expand::expand_node,
+  export::export_scan,
+  games::get_games,
+  get_scan_status,
+  git::get_git_repos,
+  history::{delete_history_scan, get_history_scan, list_scan_history, History},
+  images::get_similar_images,
+  import::import_scan,
+  ios::{forget_ios_folder, list_ios_locations, pick_ios_folder, start_ios_scan},
+  links::get_broken_symlinks,
+  list_active_scans,
+  owners::get_owner_stats,
+  pdf::generate_report,
+  purgeable::{get_purgeable_space, thin_local_snapshots},
+  query::{get_children, get_node, get_node_path},
+  rclone::{list_rclone_remotes, start_rclone_scan},
+  refresh::{refresh_scan, rescan_node},
+  s3::{forget_s3_secret, start_s3_scan},
+  sandboxed::{get_app_storage, list_old_revisions},
+  scan_fixed_drives,
+  schedule::{
+    add_scheduled_scan, list_scheduled_scans, remove_scheduled_scan, set_scheduled_scan_enabled,
+    Scheduler,
+  },
+  set_max_concurrent_scans,
+  shadow_copies::get_shadow_storage,
+  smb::{disconnect_smb_share, forget_smb_password, list_smb_shares, start_smb_scan},
+  ssh::{forget_ssh_password, start_ssh_scan},
+  start_multi_scan, start_scan,
+  stats::{
+    get_category_stats, get_extension_stats, get_old_files, get_top_dirs_by_count, get_top_files,
+  },
+  system_files::{disable_hibernation, get_system_files},
+  trash::{empty_trash, get_trash_usage},
+  trends::get_growth_trends,
+  watch::{unwatch_scan, watch_scan, Watches},
+  webdav::{forget_webdav_password, start_webdav_scan},
+  wsl::{get_wsl_usage, list_wsl_distros, scan_wsl_distro},
+  ScanManager,
+};
+use std::time::Duration;
+use tauri::Manager;
+use volume::list_volumes;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
   tauri::Builder::default()
     .plugin(tauri_plugin_dialog::init())
+    .plugin(tauri_plugin_notification::init())
+    .plugin(scan::mobile::init())
     .manage(ScanManager::default())
-    .invoke_handler(tauri::generate_handler![start_scan, start_multi_scan, cancel_scan])
-    .run(tauri::generate_context!())
-    .expect("error while running tauri application");
+    .manage(SpaceMonitor::default())
+    .manage(History::default())
+    .manage(Scheduler::default())
+    .manage(Alerts::default())
+    .manage(Watches::default())
+    .setup(|app| {
+      hotplug::watch(app.handle().clone());
+      app.state::<Alerts>().load(app.handle());
+      app.state::<Scheduler>().start(app.handle().clone());
+      Ok(())
+    })
+    .invoke_handler(tauri::generate_handler![
+      start_scan,
+      start_multi_scan,
+      scan_fixed_drives,
+      cancel_scan,
+      cancel_all_scans,
+      get_scan_status,
+      list_active_scans,
+      set_max_concurrent_scans,
+      canonicalize_path,
+      start_elevated_scan,
+      start_agent_scan,
+      get_owner_stats,
+      get_extension_stats,
+      get_category_stats,
+      get_top_files,
+      get_top_dirs_by_count,
+      get_old_files,
+      get_empty_dirs,
+      remove_empty_dirs,
+      export_scan,
+      import_scan,
+      generate_report,
+      list_scan_history,
+      get_history_scan,
+      delete_history_scan,
+      compare_scans,
+      get_growth_trends,
+      list_scheduled_scans,
+      add_scheduled_scan,
+      set_scheduled_scan_enabled,
+      remove_scheduled_scan,
+      list_watch_rules,
+      add_watch_rule,
+      remove_watch_rule,
+      refresh_scan,
+      rescan_node,
+      expand_node,
+      get_children,
+      get_node,
+      get_node_path,
+      watch_scan,
+      unwatch_scan,
+      get_broken_symlinks,
+      get_duplicate_files,
+      get_duplicate_dirs,
+      get_similar_images,
+      inspect_disk_image,
+      get_git_repos,
+      get_dev_artifacts,
+      remove_dev_artifacts,
+      get_package_caches,
+      clear_package_cache,
+      get_container_usage,
+      docker_prune,
+      list_wsl_distros,
+      get_wsl_usage,
+      scan_wsl_distro,
+      get_app_storage,
+      list_old_revisions,
+      get_games,
+      get_browser_storage,
+      get_cleanable_locations,
+      get_trash_usage,
+      empty_trash,
+      get_system_files,
+      disable_hibernation,
+      get_shadow_storage,
+      get_purgeable_space,
+      thin_local_snapshots,
+      list_volumes,
+      list_smb_shares,
+      start_smb_scan,
+      disconnect_smb_share,
+      forget_smb_password,
+      start_ssh_scan,
+      forget_ssh_password,
+      start_s3_scan,
+      forget_s3_secret,
+      start_webdav_scan,
+      forget_webdav_password,
+      list_rclone_remotes,
+      start_rclone_scan,
+      list_cloud_drives,
+      connect_cloud_drive,
+      disconnect_cloud_drive,
+      start_cloud_scan,
+      pick_android_folder,
+      list_android_folders,
+      open_android_usage_access,
+      start_android_scan,
+      list_ios_locations,
+      pick_ios_folder,
+      forget_ios_folder,
+      start_ios_scan,
+      start_space_monitor,
+      stop_space_monitor,
+      get_space_monitor,
+      check_disk_access,
+      open_disk_access_settings
+    ])
+    .build(tauri::generate_context!())
+    .expect("error while building tauri application")
+    .run(|app, event| {
+      // Stop scan threads before the process goes away so they don't keep
+      // walking the disk after the window is closed.
+      if let tauri::RunEvent::Exit = event {
+        app.state::<SpaceMonitor>().stop();
+        app.state::<Scheduler>().stop();
+        app.state::<ScanManager>().shutdown(Duration::from_secs(2));
+      }
+    });
 }
 
 fn main() {
+  // Run by ssh as its password prompt, to hand it the password and exit.
+  if let Some(code) = scan::ssh::answer_askpass() {
+    std::process::exit(code);
+  }
+  // An elevated scan helper runs one scan headless and never opens a window.
+  if let Some(code) = scan::elevated::run_helper_from_args() {
+    std::process::exit(code);
+  }
+  // Neither does an agent, which scans for an app on another machine.
+  if let Some(code) = scan::agent::run_from_args() {
+    std::process::exit(code);
+  }
   run();
 }
 
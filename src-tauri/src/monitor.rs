@@ -0,0 +1,164 @@
+//! Opt-in watch on free space. Once turned on it looks at every local volume
+//! every so often and, when one falls below the threshold, emits
+//! `low_space` and shows a desktop notification suggesting a scan of it.
+//! Each volume warns once until it has room again.
+
+use parking_lot::{Condvar, Mutex};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashSet, sync::Arc, time::Duration};
+use tauri::{AppHandle, Emitter, State};
+use tauri_plugin_notification::NotificationExt;
+
+/// How often volumes are looked at unless the settings say otherwise.
+const DEFAULT_INTERVAL_SECS: u64 = 300;
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct MonitorSettings {
+  /// Seconds between looks; at least 10.
+  #[serde(default = "default_interval")]
+  pub interval_secs: u64,
+  /// Low when fewer bytes than this are free.
+  pub min_free_bytes: Option<u64>,
+  /// Low when less than this share of the volume, in percent, is free.
+  pub min_free_percent: Option<f64>,
+}
+
+fn default_interval() -> u64 {
+  DEFAULT_INTERVAL_SECS
+}
+
+impl MonitorSettings {
+  fn is_low(&self, free: u64, total: u64) -> bool {
+    let below_bytes = self.min_free_bytes.is_some_and(|min| free < min);
+    let percent = free as f64 * 100.0 / total as f64;
+    below_bytes || self.min_free_percent.is_some_and(|min| percent < min)
+  }
+}
+
+#[derive(Clone, Serialize)]
+pub struct LowSpaceEvent {
+  pub mount_point: String,
+  pub label: Option<String>,
+  pub free_bytes: u64,
+  pub total_bytes: u64,
+}
+
+#[derive(Default)]
+struct Watch {
+  /// `None` while the monitor is off.
+  settings: Option<MonitorSettings>,
+  /// A thread is watching; it clears this when it sees the monitor is off.
+  running: bool,
+}
+
+/// The thread waits on `wake` between looks, so a change is seen right away.
+#[derive(Default)]
+pub struct SpaceMonitor {
+  watch: Arc<Mutex<Watch>>,
+  wake: Arc<Condvar>,
+}
+
+impl SpaceMonitor {
+  /// Swaps in `settings`, starting the thread if none is running.
+  fn configure(&self, app: AppHandle, settings: Option<MonitorSettings>) {
+    let mut watch = self.watch.lock();
+    watch.settings = settings;
+    self.wake.notify_all();
+    if watch.settings.is_some() && !watch.running {
+      watch.running = true;
+      let (shared, wake) = (self.watch.clone(), self.wake.clone());
+      std::thread::spawn(move || run(app, shared, wake));
+    }
+  }
+
+  /// Stops the thread, for app exit.
+  pub fn stop(&self) {
+    self.watch.lock().settings = None;
+    self.wake.notify_all();
+  }
+}
+
+fn run(app: AppHandle, watch: Arc<Mutex<Watch>>, wake: Arc<Condvar>) {
+  // Volumes already warned about; one leaves once it's above the threshold.
+  let mut low = HashSet::new();
+  loop {
+    let current = watch.lock().settings.clone();
+    let Some(current) = current else {
+      watch.lock().running = false;
+      return;
+    };
+    for volume in crate::volume::volumes() {
+      if volume.network || volume.total_bytes == 0 {
+        continue;
+      }
+      if !current.is_low(volume.free_bytes, volume.total_bytes) {
+        low.remove(&volume.mount_point);
+        continue;
+      }
+      if low.insert(volume.mount_point.clone()) {
+        warn(&app, volume);
+      }
+    }
+    let mut guard = watch.lock();
+    if guard.settings.is_none() {
+      guard.running = false;
+      return;
+    }
+    let interval = Duration::from_secs(current.interval_secs.max(10));
+    wake.wait_for(&mut guard, interval);
+  }
+}
+
+fn warn(app: &AppHandle, volume: crate::volume::VolumeInfo) {
+  let name = volume.label.clone().unwrap_or_else(|| volume.mount_point.clone());
+  let body = format!(
+    "{:.1} GB left of {:.1} GB. Scan it to see what's taking the space.",
+    volume.free_bytes as f64 / 1e9,
+    volume.total_bytes as f64 / 1e9
+  );
+  // The event carries it to the window either way.
+  let notification = app.notification().builder().title(format!("{} is almost full", name));
+  let _ = notification.body(body).show();
+  let _ = app.emit(
+    "low_space",
+    LowSpaceEvent {
+      mount_point: volume.mount_point,
+      label: volume.label,
+      free_bytes: volume.free_bytes,
+      total_bytes: volume.total_bytes,
+    },
+  );
+}
+
+/// Turns the free-space monitor on with `settings`, or changes them if it's
+/// running. At least one threshold has to be given.
+#[tauri::command]
+pub async fn start_space_monitor(
+  app: AppHandle,
+  state: State<'_, SpaceMonitor>,
+  settings: MonitorSettings,
+) -> Result<(), String> {
+  if settings.min_free_bytes.is_none() && settings.min_free_percent.is_none() {
+    return Err("Set a number of bytes or a percentage to warn below".to_string());
+  }
+  state.configure(app, Some(settings));
+  Ok(())
+}
+
+/// Turns the free-space monitor off.
+#[tauri::command]
+pub async fn stop_space_monitor(
+  app: AppHandle,
+  state: State<'_, SpaceMonitor>,
+) -> Result<(), String> {
+  state.configure(app, None);
+  Ok(())
+}
+
+/// The settings of the free-space monitor, or `None` when it's off.
+#[tauri::command]
+pub async fn get_space_monitor(
+  state: State<'_, SpaceMonitor>,
+) -> Result<Option<MonitorSettings>, String> {
+  Ok(state.watch.lock().settings.clone())
+}
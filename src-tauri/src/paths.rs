@@ -0,0 +1,96 @@
+//! Turning the paths users type or paste into scan roots.
+
+use std::path::PathBuf;
+
+/// Cleans up a typed path without resolving it on disk: surrounding whitespace
+/// and quotes go, relative paths become absolute, and `~` is the home
+/// directory. On Windows slashes become backslashes, a bare drive (`D:`) means
+/// its root, and UNC paths must name both a server and a share.
+pub fn normalize(input: &str) -> Result<PathBuf, String> {
+  let trimmed = input.trim().trim_matches('"').trim();
+  if trimmed.is_empty() {
+    return Err("Path is empty".to_string());
+  }
+  #[cfg(windows)]
+  let path = windows_form(trimmed)?;
+  #[cfg(unix)]
+  let path = expand_home(trimmed);
+  #[cfg(not(any(unix, windows)))]
+  let path = PathBuf::from(trimmed);
+  std::path::absolute(&path).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+/// `normalize`, then resolved on disk: the path must exist, and symlinks and
+/// `..` are followed. On Windows the result is in its plain form (`C:\Users`,
+/// `\\server\share\dir`) rather than the `\\?\` form the OS resolves to, and a
+/// mapped network drive comes back as its share.
+pub fn canonicalize(input: &str) -> Result<PathBuf, String> {
+  let path = normalize(input)?;
+  let resolved = std::fs::canonicalize(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+  #[cfg(windows)]
+  let resolved = strip_verbatim(resolved);
+  Ok(resolved)
+}
+
+/// Whether `path` is on a network share (`\\server\share\...`).
+#[cfg(windows)]
+pub fn is_unc(path: &std::path::Path) -> bool {
+  use std::path::{Component, Prefix};
+  matches!(
+    path.components().next(),
+    Some(Component::Prefix(p)) if matches!(p.kind(), Prefix::UNC(..) | Prefix::VerbatimUNC(..))
+  )
+}
+
+#[cfg(windows)]
+fn windows_form(path: &str) -> Result<PathBuf, String> {
+  let path = path.replace('/', "\\");
+  // `D:` alone is the current directory on D:, which is never what a user means.
+  if path.len() == 2 && path.ends_with(':') {
+    return Ok(PathBuf::from(path + "\\"));
+  }
+  if let Some(rest) = path.strip_prefix(r"\\") {
+    let device = rest.starts_with(r"?\") || rest.starts_with(r".\");
+    if !device && rest.split('\\').filter(|part| !part.is_empty()).count() < 2 {
+      return Err(format!("{} needs a server and a share, like \\\\server\\share", path));
+    }
+  }
+  Ok(PathBuf::from(path))
+}
+
+/// `\\?\C:\dir` as `C:\dir` and `\\?\UNC\server\share` as `\\server\share`,
+/// as long as the plain form can still be opened.
+#[cfg(windows)]
+fn strip_verbatim(path: PathBuf) -> PathBuf {
+  let text = path.to_string_lossy().into_owned();
+  // Past MAX_PATH only the verbatim form works with every API.
+  if text.len() >= 260 {
+    return path;
+  }
+  if let Some(rest) = text.strip_prefix(r"\\?\UNC\") {
+    return PathBuf::from(format!(r"\\{}", rest));
+  }
+  match text.strip_prefix(r"\\?\") {
+    Some(rest) if rest.as_bytes().get(1) == Some(&b':') => PathBuf::from(rest),
+    _ => path,
+  }
+}
+
+#[cfg(unix)]
+fn expand_home(path: &str) -> PathBuf {
+  let rest = match path.strip_prefix('~') {
+    Some(rest) if rest.is_empty() || rest.starts_with('/') => rest.trim_start_matches('/'),
+    _ => return PathBuf::from(path),
+  };
+  match home_dir() {
+    Some(home) if rest.is_empty() => home,
+    Some(home) => home.join(rest),
+    None => PathBuf::from(path),
+  }
+}
+
+/// The user's home directory, from `HOME` (`USERPROFILE` on Windows).
+pub fn home_dir() -> Option<PathBuf> {
+  let name = if cfg!(windows) { "USERPROFILE" } else { "HOME" };
+  std::env::var_os(name).filter(|home| !home.is_empty()).map(PathBuf::from)
+}
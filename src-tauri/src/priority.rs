@@ -0,0 +1,46 @@
+//! Best-effort lowering of CPU and I/O priority for background scans.
+
+/// Drops the calling thread to idle CPU and I/O priority. Failures are ignored:
+/// the scan just runs at normal priority.
+#[cfg(target_os = "linux")]
+pub fn lower_current_thread() {
+  // IOPRIO_WHO_PROCESS with a thread id targets just that thread.
+  const IOPRIO_WHO_PROCESS: libc::c_int = 1;
+  const IOPRIO_CLASS_IDLE: libc::c_int = 3;
+  const IOPRIO_CLASS_SHIFT: libc::c_int = 13;
+
+  unsafe {
+    let tid = libc::gettid();
+    // On Linux, nice values are per thread when addressed by tid.
+    libc::setpriority(libc::PRIO_PROCESS, tid as libc::id_t, 19);
+    libc::syscall(
+      libc::SYS_ioprio_set,
+      IOPRIO_WHO_PROCESS,
+      tid,
+      IOPRIO_CLASS_IDLE << IOPRIO_CLASS_SHIFT,
+    );
+  }
+}
+
+#[cfg(target_os = "macos")]
+pub fn lower_current_thread() {
+  // The background QoS class throttles both CPU scheduling and disk I/O.
+  unsafe {
+    libc::pthread_set_qos_class_self_np(libc::qos_class_t::QOS_CLASS_BACKGROUND, 0);
+  }
+}
+
+#[cfg(windows)]
+pub fn lower_current_thread() {
+  use windows_sys::Win32::System::Threading::{
+    GetCurrentThread, SetThreadPriority, THREAD_MODE_BACKGROUND_BEGIN,
+  };
+  // Background mode lowers the thread's CPU priority and sets its I/O
+  // priority hint to very low.
+  unsafe {
+    SetThreadPriority(GetCurrentThread(), THREAD_MODE_BACKGROUND_BEGIN);
+  }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+pub fn lower_current_thread() {}
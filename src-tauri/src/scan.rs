@@ -1,35 +1,312 @@
+use artifacts::DevProject;
+use category::{CategoryTotals, FileCategory};
+use crate::volume::VolumeUsage;
+use duplicates::Duplicates;
+use empty::{EmptyDir, EmptyDirs};
+use games::Game;
+use git::GitRepo;
+use gitignore::{IgnoreMode, IgnoreRules, IgnoreTotals};
+use images::SimilarImages;
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use links::BrokenLink;
+use object_tree::StorageClassTotal;
+use owners::{OwnerNames, OwnerTotals};
 use parking_lot::Mutex;
 use rayon::prelude::*;
-use serde::Serialize;
+use reconcile::UnaccountedPart;
+use serde::{Deserialize, Serialize};
 use std::{
-  collections::HashMap,
+  collections::{HashMap, HashSet, VecDeque},
+  io::Write,
+  panic::AssertUnwindSafe,
   path::{Path, PathBuf},
   sync::{
     atomic::{AtomicBool, AtomicU64, Ordering},
-    Arc,
+    Arc, OnceLock,
   },
-  time::Instant,
+  time::{Duration, Instant},
 };
+use stats::{CategoryStat, DirCount, ExtensionStat, FileEntry, OldFiles, ScanStats, SizeBucket};
 use tauri::{AppHandle, Emitter, Manager, State};
 use uuid::Uuid;
 
-#[derive(Clone, Serialize)]
+pub mod agent;
+pub mod alerts;
+pub mod android;
+pub mod artifacts;
+pub mod browsers;
+pub mod caches;
+mod category;
+pub mod cleanable;
+pub mod cloud;
+pub mod compare;
+mod credentials;
+pub mod disk_image;
+pub mod docker;
+pub mod duplicates;
+pub mod elevated;
+pub mod empty;
+pub mod expand;
+pub mod export;
+pub mod games;
+pub mod git;
+pub mod gitignore;
+pub mod history;
+mod http;
+pub mod images;
+pub mod import;
+pub mod ios;
+mod iso;
+pub mod links;
+#[cfg(target_os = "linux")]
+mod linux;
+#[cfg(target_os = "macos")]
+mod macos;
+#[cfg(windows)]
+mod mft;
+pub mod mobile;
+mod object_tree;
+pub mod owners;
+pub mod pdf;
+pub mod purgeable;
+pub mod query;
+pub mod rclone;
+mod reconcile;
+pub mod refresh;
+pub mod s3;
+pub mod sandboxed;
+pub mod schedule;
+pub mod shadow_copies;
+pub mod smb;
+pub mod ssh;
+pub mod stats;
+pub mod system_files;
+pub mod trash;
+pub mod trends;
+#[cfg(windows)]
+mod usn;
+pub mod watch;
+pub mod webdav;
+#[cfg(windows)]
+mod windows;
+pub mod wsl;
+
+#[derive(Clone, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum NodeKind {
   File,
   Dir,
+  /// Synthetic node standing in for several entries of its parent: small
+  /// entries merged by `min_node_size`, or the tail cut by `top_children`.
+  Other,
 }
 
-#[derive(Clone, Serialize)]
+/// What an entry is besides its own contents, if anything.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LinkKind {
+  #[default]
+  None,
+  Symlink,
+  /// A Windows directory junction.
+  Junction,
+  /// Where another filesystem is mounted (a mounted volume folder on Windows).
+  MountPoint,
+}
+
+impl LinkKind {
+  fn is_none(&self) -> bool {
+    *self == LinkKind::None
+  }
+}
+
+/// Deserialized from what it serializes to, as the scan history stores it.
+#[derive(Clone, Serialize, Deserialize)]
 pub struct ScanNode {
+  /// Numbers the nodes of a kept scan, parents before their children, for
+  /// `get_node_path` and the query commands. 0 in trees that aren't kept.
+  #[serde(default)]
+  pub id: u64,
   pub name: String,
-  pub path: String,
   pub kind: NodeKind,
   pub size: u64,
+  /// Bytes actually allocated on disk (cluster-rounded, compression/sparse aware).
+  pub allocated: u64,
   #[serde(skip_serializing_if = "Option::is_none")]
   pub children: Option<Vec<ScanNode>>,
+  /// Number of entries merged into an `Other` node.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub item_count: Option<u64>,
+  /// Files anywhere below a directory or inside an `Other` node, including
+  /// the levels past `max_depth`.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub file_count: Option<u64>,
+  /// Subdirectories anywhere below a directory or inside an `Other` node.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub dir_count: Option<u64>,
+  /// Last modification of a file, in seconds since the Unix epoch.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub mtime: Option<i64>,
+  /// Latest and earliest `mtime` of the files anywhere below a directory or
+  /// inside an `Other` node; unset when there are none.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub newest_mtime: Option<i64>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub oldest_mtime: Option<i64>,
+  /// Account name of the owner of a file, when the scan tracks owners.
   #[serde(skip_serializing_if = "Option::is_none")]
-  pub omitted_children: Option<u64>,
+  pub owner: Option<String>,
+  /// What kind of file this is, from its extension.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub category: Option<FileCategory>,
+  /// Bytes per category of the files below a directory or inside an `Other`
+  /// node.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub categories: Option<CategoryTotals>,
+  /// Bytes of hidden entries left out of this directory's subtree (see
+  /// `ScanOptions::track_hidden_bytes`).
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub hidden_bytes: Option<u64>,
+  /// A cloud placeholder (OneDrive and the like) whose data isn't stored
+  /// locally: `size` is its logical size and `allocated` is 0.
+  #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+  pub online_only: bool,
+  /// An NTFS-compressed file: `size` is its uncompressed length and
+  /// `allocated` what it takes on disk compressed.
+  #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+  pub compressed: bool,
+  /// A file with unallocated holes (VM disks, torrents, databases), so
+  /// `allocated` is well below `size`. Windows marks such files; elsewhere it
+  /// is judged from the two sizes, which filesystem compression also shrinks.
+  #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+  pub sparse: bool,
+  /// Set for links and mount points. An unfollowed link is a leaf of its own
+  /// size; a followed one, or a mount point, holds the contents it leads to.
+  #[serde(default, skip_serializing_if = "LinkKind::is_none")]
+  pub link_kind: LinkKind,
+  /// `ATTR_*` bits, from the Windows attributes or the Unix mode of the entry.
+  pub attributes: u32,
+}
+
+// Bits of `ScanNode::attributes`.
+const ATTR_HIDDEN: u32 = 1 << 0;
+/// Windows only.
+#[cfg(windows)]
+const ATTR_SYSTEM: u32 = 1 << 1;
+const ATTR_READONLY: u32 = 1 << 2;
+const ATTR_COMPRESSED: u32 = 1 << 3;
+const ATTR_SPARSE: u32 = 1 << 4;
+/// Cloud placeholders, as in `online_only`.
+const ATTR_OFFLINE: u32 = 1 << 5;
+/// Files with an execute bit on Unix, and with an executable extension on
+/// Windows.
+const ATTR_EXECUTABLE: u32 = 1 << 6;
+
+impl ScanNode {
+  /// The (files, directories) this node adds to the counts of its parent.
+  fn counts(&self) -> (u64, u64) {
+    let files = self.file_count.unwrap_or(0);
+    let dirs = self.dir_count.unwrap_or(0);
+    match self.kind {
+      NodeKind::File => (1, 0),
+      NodeKind::Dir => (files, dirs + 1),
+      NodeKind::Other => (files, dirs),
+    }
+  }
+
+  /// (oldest, newest) modification time of the files in and below this node.
+  fn mtimes(&self) -> MtimeRange {
+    match self.kind {
+      NodeKind::File => self.mtime.map(|t| (t, t)),
+      _ => self.oldest_mtime.zip(self.newest_mtime),
+    }
+  }
+
+  /// The node for `path` in the tree under this one, the node of `at`,
+  /// unless it was folded into an aggregate node or is past the depth limit.
+  fn find(&self, at: &Path, path: &Path) -> Option<&ScanNode> {
+    let mut node = self;
+    for name in path.strip_prefix(at).ok()?.components() {
+      node = node.child(&name.as_os_str().to_string_lossy())?;
+    }
+    Some(node)
+  }
+
+  /// The child named `name`, unless it has no node of its own.
+  fn child(&self, name: &str) -> Option<&ScanNode> {
+    self
+      .children
+      .iter()
+      .flatten()
+      .filter(|child| !matches!(child.kind, NodeKind::Other))
+      .find(|child| child.name == name)
+  }
+
+  /// Bytes per category in and below this node.
+  fn category_totals(&self) -> CategoryTotals {
+    let mut totals = self.categories.unwrap_or_default();
+    if let Some(category) = self.category {
+      totals.add(category, self.size);
+    }
+    totals
+  }
+
+  fn set_mtimes(&mut self, range: MtimeRange) {
+    self.oldest_mtime = range.map(|(oldest, _)| oldest);
+    self.newest_mtime = range.map(|(_, newest)| newest);
+  }
+
+  /// Sets `attributes` to `bits` plus those of the flags set on the node.
+  fn set_attributes(&mut self, bits: u32) {
+    let flags = [
+      (self.compressed, ATTR_COMPRESSED),
+      (self.sparse, ATTR_SPARSE),
+      (self.online_only, ATTR_OFFLINE),
+    ];
+    self.attributes = flags
+      .into_iter()
+      .filter(|&(set, _)| set)
+      .fold(bits, |acc, (_, bit)| acc | bit);
+  }
+}
+
+/// Which size figure orders children in the tree.
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SizeMode {
+  #[default]
+  Apparent,
+  Allocated,
+}
+
+/// How a scan enumerates the tree.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ScanBackend {
+  /// The fastest backend that works for the root, falling back to the walker.
+  #[default]
+  Auto,
+  /// Portable directory walker, without platform fast paths.
+  Walker,
+  /// Read the NTFS Master File Table directly (Windows, elevated, local NTFS
+  /// volumes only); falls back to the walker when that isn't possible.
+  Mft,
+}
+
+impl SizeMode {
+  fn of(self, node: &ScanNode) -> u64 {
+    match self {
+      SizeMode::Apparent => node.size,
+      SizeMode::Allocated => node.allocated,
+    }
+  }
+
+  fn of_sizes(self, sizes: FileSizes) -> u64 {
+    match self {
+      SizeMode::Apparent => sizes.apparent,
+      SizeMode::Allocated => sizes.allocated,
+    }
+  }
 }
 
 #[derive(Clone, Serialize)]
@@ -39,363 +316,2726 @@ pub struct ScanProgressEvent {
   pub scanned_bytes: u64,
   #[serde(skip_serializing_if = "Option::is_none")]
   pub current_path: Option<String>,
+  pub elapsed_ms: u64,
+  /// Smoothed over the last few seconds, so bursts don't make it jumpy.
+  pub entries_per_sec: f64,
+  pub bytes_per_sec: f64,
+  /// Only known when the scan has an expected total to measure against.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub eta_ms: Option<u64>,
+  /// Used bytes of the volume, when the scan root is a volume root.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub estimated_total_bytes: Option<u64>,
+  /// scanned_bytes as a share of estimated_total_bytes, 0–100.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub percent: Option<f64>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScanErrorKind {
+  PermissionDenied,
+  NotFound,
+  /// The storage (usually a network share) stopped answering in time.
+  IoTimeout,
+  /// A followed symlink leads back to one of its ancestors.
+  FilesystemLoop,
+  Other,
+}
+
+impl ScanErrorKind {
+  fn of(err: &std::io::Error) -> Self {
+    // ERROR_SEM_TIMEOUT: how Windows reports an unresponsive SMB server.
+    #[cfg(windows)]
+    if err.raw_os_error() == Some(121) {
+      return ScanErrorKind::IoTimeout;
+    }
+    match err.kind() {
+      std::io::ErrorKind::PermissionDenied => ScanErrorKind::PermissionDenied,
+      std::io::ErrorKind::NotFound => ScanErrorKind::NotFound,
+      std::io::ErrorKind::TimedOut => ScanErrorKind::IoTimeout,
+      _ => ScanErrorKind::Other,
+    }
+  }
+}
+
+/// Emitted as `scan_error` when an entry can't be read, while the scan goes on.
+#[derive(Clone, Serialize)]
+pub struct ScanErrorEvent {
+  pub scan_id: String,
+  pub path: String,
+  pub kind: ScanErrorKind,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub os_code: Option<i32>,
+  pub message: String,
+}
+
+#[derive(Clone, Serialize)]
+pub struct ErrorCategory {
+  pub kind: ScanErrorKind,
+  pub count: u64,
+  /// The first few paths that failed this way.
+  pub sample_paths: Vec<String>,
+}
+
+#[derive(Clone, Default, Serialize)]
+pub struct ErrorSummary {
+  pub total: u64,
+  /// Most frequent first.
+  pub categories: Vec<ErrorCategory>,
 }
 
 #[derive(Clone, Serialize)]
 pub struct ScanDoneEvent {
   pub scan_id: String,
-  pub root: ScanNode,
+  /// Where the root is; nodes only carry their names.
+  pub root_path: String,
+  /// The root alone: `get_children` pages through the tree, which stays with
+  /// the kept results. A scan relayed from an elevated helper or an agent
+  /// sends its whole tree, as nothing is kept for it here.
+  pub root: query::NodeSummary,
+  /// The first `MAX_ERROR_MESSAGES` error messages; see `error_summary` for totals.
   pub errors: Vec<String>,
+  pub error_summary: ErrorSummary,
+  /// Entries skipped because they matched an exclude pattern.
+  pub excluded_entries: u64,
+  /// Files left out because their extension isn't in `include_extensions`.
+  pub filtered_entries: u64,
+  /// Directories not entered because they live on another filesystem.
+  pub skipped_mounts: Vec<String>,
+  /// The extensions taking the most bytes; `get_extension_stats` has them all.
+  pub top_extensions: Vec<ExtensionStat>,
+  /// Files and bytes per logarithmic size bucket, from under 4 KiB to over
+  /// 1 GiB.
+  pub size_histogram: Vec<SizeBucket>,
+  /// Bytes and files the ignore files ignore and don't, unless
+  /// `ignore_mode` is `Off`.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub ignore_totals: Option<IgnoreTotals>,
+  /// For the root of a volume, how much of the space the volume reports as
+  /// used the tree doesn't hold. `unaccounted_parts` says what some of it
+  /// is; folders that couldn't be read (see `error_summary`) and excluded
+  /// entries explain more.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub unaccounted_bytes: Option<u64>,
+  #[serde(skip_serializing_if = "Vec::is_empty")]
+  pub unaccounted_parts: Vec<UnaccountedPart>,
+  /// Bytes and objects per storage class, largest first, for a bucket.
+  #[serde(skip_serializing_if = "Vec::is_empty")]
+  pub storage_classes: Vec<StorageClassTotal>,
+  /// The scan was cancelled and `root` only covers what was walked until then.
+  pub partial: bool,
+  pub scanned_entries: u64,
+  pub scanned_bytes: u64,
+}
+
+/// Emitted as `scan_failed` instead of `scan_done` when a scan can't finish:
+/// its thread panicked, or its elevated helper never ran.
+#[derive(Clone, Serialize)]
+pub struct ScanFailedEvent {
+  pub scan_id: String,
+  pub root: String,
+  pub message: String,
+}
+
+/// Emitted as `scan_cancelled` once the scan thread notices a cancel request;
+/// a partial `scan_done` follows when the walk has unwound.
+#[derive(Clone, Serialize)]
+pub struct ScanCancelledEvent {
+  pub scan_id: String,
+  pub elapsed_ms: u64,
+  pub scanned_entries: u64,
+  pub scanned_bytes: u64,
+}
+
+/// Emitted as `background_error` when something fails away from any command
+/// that could return it, such as writing a finished scan to the history.
+#[derive(Clone, Serialize)]
+pub struct BackgroundErrorEvent {
+  pub message: String,
+}
+
+/// Tells the UI about `message` through `background_error`.
+fn report_error(app: &AppHandle, message: String) {
+  let _ = app.emit("background_error", BackgroundErrorEvent { message });
 }
 
+const MAX_ERROR_MESSAGES: usize = 200;
+const MAX_ERROR_SAMPLES: usize = 10;
+/// How far short of its length a file's allocation must fall to count as
+/// sparse where the OS doesn't say; small files stored inside their inode
+/// stay below it.
+const SPARSE_MIN_HOLE: u64 = 64 * 1024;
+
+/// Errors of one scan, grouped by kind and capped so that scanning a whole
+/// system drive as a normal user doesn't ship tens of thousands of strings.
 #[derive(Default)]
+struct ErrorLog {
+  messages: Vec<String>,
+  summary: ErrorSummary,
+}
+
+impl ErrorLog {
+  fn push(&mut self, kind: ScanErrorKind, path: Option<&str>, message: String) {
+    self.summary.total += 1;
+    if self.messages.len() < MAX_ERROR_MESSAGES {
+      self.messages.push(message);
+    }
+    let idx = match self.summary.categories.iter().position(|c| c.kind == kind) {
+      Some(idx) => idx,
+      None => {
+        self.summary.categories.push(ErrorCategory {
+          kind,
+          count: 0,
+          sample_paths: Vec::new(),
+        });
+        self.summary.categories.len() - 1
+      }
+    };
+    let category = &mut self.summary.categories[idx];
+    category.count += 1;
+    if let Some(path) = path {
+      if category.sample_paths.len() < MAX_ERROR_SAMPLES {
+        category.sample_paths.push(path.to_string());
+      }
+    }
+  }
+
+  fn finish(&mut self) -> (Vec<String>, ErrorSummary) {
+    let mut summary = std::mem::take(&mut self.summary);
+    summary.categories.sort_by_key(|c| std::cmp::Reverse(c.count));
+    (std::mem::take(&mut self.messages), summary)
+  }
+}
+
+/// Knobs for a scan. Every field has a default, so the frontend only sends the
+/// ones it wants to change and new options never break the command signature.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScanOptions {
+  /// Directory levels materialized as nodes; deeper levels are only summed.
+  /// All of them by default, as the tree stays here and is read a page at a
+  /// time.
+  pub max_depth: u32,
+  /// Children kept per directory (largest first); 0, the default, keeps all
+  /// of them.
+  pub top_children: u32,
+  pub follow_symlinks: bool,
+  /// Also descend into Windows junctions when following symlinks. Off by
+  /// default: the ones Windows puts in every profile (`Application Data`,
+  /// `My Documents`...) lead back into folders scanned anyway.
+  pub follow_junctions: bool,
+  /// Count each physical file once, however many hardlinks point at it.
+  pub dedupe_hardlinks: bool,
+  pub size_mode: SizeMode,
+  /// Glob patterns for entries to skip, matched against the full path or the
+  /// bare file name (e.g. `**/node_modules`, `C:\Windows\WinSxS`, `*.iso`).
+  pub exclude: Vec<String>,
+  /// When non-empty, only files with one of these extensions (case-insensitive,
+  /// with or without the leading dot) are counted. Directories are always walked.
+  pub include_extensions: Vec<String>,
+  /// Children smaller than this (in the active size mode) are merged into a
+  /// single `Other` node per directory; 0 disables merging.
+  pub min_node_size: u64,
+  /// In a directory that also has subdirectories, merge its own files into
+  /// one `Other` node named `[Files]`, so they take a single child slot.
+  pub group_files: bool,
+  /// Stay on the root's filesystem: don't descend into other mounts/volumes.
+  pub same_filesystem: bool,
+  /// Include hidden entries: dotfiles on Unix, HIDDEN/SYSTEM attributes on Windows.
+  pub include_hidden: bool,
+  /// When hidden entries are skipped, still total their bytes into each
+  /// directory's `hidden_bytes` so nothing silently disappears.
+  pub track_hidden_bytes: bool,
+  /// Worker threads in this scan's own thread pool; 0 uses one per CPU.
+  pub threads: usize,
+  /// Run at idle CPU and I/O priority so a full-drive scan doesn't make the
+  /// machine unusable.
+  pub background: bool,
+  pub backend: ScanBackend,
+  /// Look up the owner of every file, for `get_owner_stats`. Costs a lookup
+  /// per file on Windows, where the MFT backend is then not used.
+  pub track_owners: bool,
+  /// After the walk, hash the files that share their size with another to
+  /// find duplicate files and directories, for `get_duplicate_files` and
+  /// `get_duplicate_dirs`.
+  pub find_duplicates: bool,
+  /// Threads hashing files for `find_duplicates`; 0 uses the scan's own.
+  /// Hashing is mostly waiting on the disk, so more than the CPU count can
+  /// help on SSDs, and fewer keeps a spinning disk from seeking.
+  pub hash_threads: usize,
+  /// After the walk, decode the images found to group those that look alike
+  /// though their bytes differ, for `get_similar_images`.
+  pub find_similar_images: bool,
+  /// Read `.gitignore` and `.ignore` files on the way down, and leave out
+  /// what they ignore or keep only that. `ScanDoneEvent::ignore_totals` has
+  /// both sides either way.
+  pub ignore_mode: IgnoreMode,
+  /// Store the whole tree in the scan history along with its totals, so it
+  /// can be looked at again after the app is closed.
+  pub history_tree: bool,
+  /// Once done, keep watching the root and walk again whatever changes in
+  /// it, emitting `node_changed` (see `watch`).
+  pub watch: bool,
+}
+
+impl Default for ScanOptions {
+  fn default() -> Self {
+    Self {
+      max_depth: u32::MAX,
+      top_children: 0,
+      follow_symlinks: false,
+      follow_junctions: false,
+      dedupe_hardlinks: true,
+      size_mode: SizeMode::Apparent,
+      exclude: Vec::new(),
+      include_extensions: Vec::new(),
+      min_node_size: 0,
+      group_files: false,
+      same_filesystem: false,
+      include_hidden: true,
+      track_hidden_bytes: false,
+      threads: 0,
+      background: false,
+      backend: ScanBackend::Auto,
+      track_owners: false,
+      find_duplicates: false,
+      hash_threads: 0,
+      find_similar_images: false,
+      ignore_mode: IgnoreMode::Off,
+      history_tree: false,
+      watch: false,
+    }
+  }
+}
+
+#[derive(Clone, Serialize)]
+pub struct ScanQueuedEvent {
+  pub scan_id: String,
+  pub root: String,
+  /// 1-based position in the wait queue.
+  pub position: usize,
+}
+
+#[derive(Clone, Serialize)]
+pub struct ScanStartedEvent {
+  pub scan_id: String,
+  pub root: String,
+}
+
+#[derive(Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ScanState {
+  /// Waiting for a free slot under the concurrency limit.
+  Queued,
+  Running,
+  /// Cancel was requested; the walk is winding down and will still emit scan_done.
+  Cancelling,
+}
+
+#[derive(Clone, Serialize)]
+pub struct ScanStatus {
+  pub scan_id: String,
+  pub root: String,
+  pub state: ScanState,
+  pub elapsed_ms: u64,
+  pub scanned_entries: u64,
+  pub scanned_bytes: u64,
+}
+
+const DEFAULT_MAX_CONCURRENT_SCANS: usize = 2;
+/// Worker threads for a scan of a network filesystem that doesn't set its
+/// own. Listing a share waits on round trips rather than the CPU, so more
+/// requests in flight help, up to what a server takes well.
+const NETWORK_SCAN_THREADS: usize = 16;
+/// Finished scans whose results are kept for the commands that query them.
+const MAX_KEPT_RESULTS: usize = 8;
+
 pub struct ScanManager {
   scans: Mutex<HashMap<String, Arc<ScanControl>>>,
+  queue: Mutex<ScanQueue>,
+  // The latest finished scans, oldest first.
+  results: Mutex<VecDeque<KeptScan>>,
+  // Set on app exit; no new scans are accepted after that.
+  closing: AtomicBool,
+}
+
+impl Default for ScanManager {
+  fn default() -> Self {
+    Self {
+      scans: Mutex::default(),
+      queue: Mutex::new(ScanQueue {
+        running: 0,
+        max_concurrent: DEFAULT_MAX_CONCURRENT_SCANS,
+        pending: VecDeque::new(),
+      }),
+      results: Mutex::default(),
+      closing: AtomicBool::new(false),
+    }
+  }
+}
+
+/// The results of a finished scan, kept for the commands that read them.
+struct KeptScan {
+  scan_id: String,
+  results: Arc<ScanResults>,
+  // Held by whatever walks part of the tree again, from reading the results
+  // to putting what it found in them, so two such updates can't cross.
+  updating: Arc<Mutex<()>>,
+}
+
+/// Scans beyond the concurrency limit wait here, in submission order.
+struct ScanQueue {
+  running: usize,
+  max_concurrent: usize,
+  pending: VecDeque<ScanJob>,
+}
+
+/// What a finished scan leaves behind besides its scan_done event.
+#[derive(Clone)]
+struct ScanResults {
+  // Path of the root node.
+  root: String,
+  // The whole tree as the walk built it, or as `refresh_scan` last brought
+  // it up to date, its nodes numbered.
+  tree: ScanNode,
+  // Where each node of `tree` is, by id.
+  index: query::NodeIndex,
+  options: ScanOptions,
+  // Walked on this machine, so parts of it can be walked again.
+  local: bool,
+  // How far the change journal of the volume had got when the walk started;
+  // `None` unless it could be read.
+  #[cfg(windows)]
+  journal: Option<usn::Position>,
+  // Cancelled before the walk was through.
+  partial: bool,
+  error_summary: ErrorSummary,
+  // The volume holding a local root, as it was when the scan ended.
+  volume: Option<VolumeUsage>,
+  // Per-owner totals of every directory node by path; `None` unless the scan
+  // tracked owners.
+  owners: Option<HashMap<String, OwnerTotals>>,
+  // Largest first.
+  extensions: Vec<ExtensionStat>,
+  categories: Vec<CategoryStat>,
+  top_files: Vec<FileEntry>,
+  top_dirs: Vec<DirCount>,
+  old_files: OldFiles,
+  empty_dirs: Vec<EmptyDir>,
+  broken_links: Vec<BrokenLink>,
+  // `None` unless the scan looked for duplicates.
+  duplicates: Option<Duplicates>,
+  // `None` unless the scan looked for similar images.
+  similar_images: Option<Vec<SimilarImages>>,
+  git_repos: Vec<GitRepo>,
+  dev_artifacts: Vec<DevProject>,
+  games: Vec<Game>,
+}
+
+impl ScanResults {
+  /// Results with nothing but `tree`, rooted at `root`, for a tree that
+  /// wasn't walked just now, such as one stored in the history.
+  fn of_tree(root: String, mut tree: ScanNode) -> Self {
+    let mut index = query::NodeIndex::default();
+    index.number(&mut tree);
+    let options = ScanOptions::default();
+    let stats = ScanStats::new(options.size_mode);
+    ScanResults {
+      root,
+      tree,
+      index,
+      options,
+      local: false,
+      #[cfg(windows)]
+      journal: None,
+      partial: false,
+      error_summary: ErrorSummary::default(),
+      volume: None,
+      owners: None,
+      extensions: stats.extensions(),
+      categories: stats.categories(),
+      top_files: stats.largest(),
+      top_dirs: stats.fullest(),
+      old_files: stats.old_files(),
+      empty_dirs: Vec::new(),
+      broken_links: Vec::new(),
+      duplicates: None,
+      similar_images: None,
+      git_repos: Vec::new(),
+      dev_artifacts: Vec::new(),
+      games: Vec::new(),
+    }
+  }
+}
+
+/// Everything needed to run a registered scan once it gets a slot.
+struct ScanJob {
+  scan_id: String,
+  root_path: PathBuf,
+  options: ScanOptions,
+  filters: ScanFilters,
+  control: Arc<ScanControl>,
+  source: ScanSource,
+}
+
+/// Where a scan reads its tree from.
+enum ScanSource {
+  /// The filesystems of this machine.
+  Local,
+  /// A server listed over SSH; the root path is only what it's shown as.
+  Ssh(ssh::Remote),
+  /// The keys of an S3 bucket, shown as `s3://bucket/folder`.
+  S3(s3::Bucket),
+  /// A WebDAV folder, shown as its URL.
+  WebDav(webdav::Server),
+  /// A folder of an rclone remote, shown as `remote:path`.
+  Rclone(rclone::Remote),
+  /// A Google Drive or OneDrive, shown as `gdrive://account`.
+  Cloud(cloud::Drive),
+  /// Android's shared storage, or a folder picked in it, listed by the OS.
+  #[cfg(target_os = "android")]
+  Android(android::Listing),
+  /// A folder picked in Files, held open while it's walked like a local one.
+  #[cfg(target_os = "ios")]
+  Ios(ios::Access),
+  /// An ncdu export or `du` output, shown at the path it was captured at.
+  Import(import::Capture),
+  /// A WSL distribution by name, listed from inside it and shown at its
+  /// `\\wsl$` share.
+  Wsl(String),
+}
+
+impl ScanSource {
+  /// Whether the root is a path on this machine, whose volume can be asked
+  /// how much it holds.
+  fn is_local(&self) -> bool {
+    match self {
+      ScanSource::Local => true,
+      #[cfg(target_os = "ios")]
+      ScanSource::Ios(_) => true,
+      _ => false,
+    }
+  }
+}
+
+impl ScanManager {
+  /// Releases a running slot and starts as many queued scans as now fit.
+  fn finish_scan(&self, app: &AppHandle, scan_id: &str) {
+    self.scans.lock().remove(scan_id);
+    let mut queue = self.queue.lock();
+    queue.running = queue.running.saturating_sub(1);
+    start_queued(app, &mut queue);
+  }
+
+  fn keep_results(&self, scan_id: &str, results: ScanResults) {
+    let mut kept = self.results.lock();
+    if kept.len() >= MAX_KEPT_RESULTS {
+      kept.pop_front();
+    }
+    kept.push_back(KeptScan {
+      scan_id: scan_id.to_string(),
+      results: Arc::new(results),
+      updating: Arc::default(),
+    });
+  }
+
+  /// The results of a finished scan, while it's among the ones kept.
+  fn results(&self, scan_id: &str) -> Result<Arc<ScanResults>, String> {
+    let kept = self.results.lock();
+    kept
+      .iter()
+      .find(|kept| kept.scan_id == scan_id)
+      .map(|kept| Arc::clone(&kept.results))
+      .ok_or_else(|| "No results for this scan".to_string())
+  }
+
+  /// The lock to hold while updating the kept results of `scan_id`.
+  fn updating(&self, scan_id: &str) -> Result<Arc<Mutex<()>>, String> {
+    let kept = self.results.lock();
+    kept
+      .iter()
+      .find(|kept| kept.scan_id == scan_id)
+      .map(|kept| Arc::clone(&kept.updating))
+      .ok_or_else(|| "No results for this scan".to_string())
+  }
+
+  /// Runs `update` on the kept results of `scan_id`. They're changed where
+  /// they are, unless a command is still reading them; that one goes on with
+  /// the results as they were.
+  fn update_results<T>(
+    &self,
+    scan_id: &str,
+    update: impl FnOnce(&mut ScanResults) -> T,
+  ) -> Result<T, String> {
+    let mut kept = self.results.lock();
+    let kept = kept
+      .iter_mut()
+      .find(|kept| kept.scan_id == scan_id)
+      .ok_or_else(|| "No results for this scan".to_string())?;
+    Ok(update(Arc::make_mut(&mut kept.results)))
+  }
+
+  /// Drops queued scans, cancels running ones and waits up to `timeout` for
+  /// their threads to wind down. Returns whether they all finished in time.
+  pub fn shutdown(&self, timeout: Duration) -> bool {
+    self.closing.store(true, Ordering::Relaxed);
+    let pending = std::mem::take(&mut self.queue.lock().pending);
+    {
+      let mut scans = self.scans.lock();
+      for job in &pending {
+        scans.remove(&job.scan_id);
+      }
+      for ctrl in scans.values() {
+        ctrl.cancel.store(true, Ordering::Relaxed);
+      }
+    }
+
+    let deadline = Instant::now() + timeout;
+    while self.queue.lock().running > 0 {
+      if Instant::now() >= deadline {
+        return false;
+      }
+      std::thread::sleep(Duration::from_millis(20));
+    }
+    true
+  }
+}
+
+fn start_queued(app: &AppHandle, queue: &mut ScanQueue) {
+  while queue.running < queue.max_concurrent {
+    let Some(job) = queue.pending.pop_front() else {
+      break;
+    };
+    queue.running += 1;
+    run_scan(app.clone(), job);
+  }
+}
+
+/// Per-scan handle shared between the manager and the scan thread.
+struct ScanControl {
+  cancel: AtomicBool,
+  root: PathBuf,
+  // Set when the scan leaves the queue and starts walking.
+  started: OnceLock<Instant>,
+  // Total bytes the walk is expected to cover, when known up front.
+  expected_bytes: OnceLock<u64>,
+  scanned_entries: AtomicU64,
+  scanned_bytes: AtomicU64,
+}
+
+impl ScanControl {
+  fn new(root: PathBuf) -> Self {
+    Self {
+      cancel: AtomicBool::new(false),
+      root,
+      started: OnceLock::new(),
+      expected_bytes: OnceLock::new(),
+      scanned_entries: AtomicU64::new(0),
+      scanned_bytes: AtomicU64::new(0),
+    }
+  }
+
+  fn elapsed_ms(&self) -> u64 {
+    self
+      .started
+      .get()
+      .map_or(0, |started| started.elapsed().as_millis() as u64)
+  }
+
+  fn status(&self, scan_id: &str) -> ScanStatus {
+    ScanStatus {
+      scan_id: scan_id.to_string(),
+      root: self.root.to_string_lossy().to_string(),
+      state: if self.cancel.load(Ordering::Relaxed) {
+        ScanState::Cancelling
+      } else if self.started.get().is_none() {
+        ScanState::Queued
+      } else {
+        ScanState::Running
+      },
+      elapsed_ms: self.elapsed_ms(),
+      scanned_entries: self.scanned_entries.load(Ordering::Relaxed),
+      scanned_bytes: self.scanned_bytes.load(Ordering::Relaxed),
+    }
+  }
+}
+
+/// Entry filters compiled once per scan from its options.
+struct ScanFilters {
+  exclude: GlobSet,
+  excluded: AtomicU64,
+  // Lowercased, without the dot; empty means every file is counted.
+  extensions: HashSet<String>,
+  filtered: AtomicU64,
+  // Device of the scan root when `same_filesystem` is on.
+  root_device: Option<u64>,
+  skipped_mounts: Mutex<Vec<String>>,
+}
+
+impl ScanFilters {
+  /// The exclude patterns of `options`, or why one of them is invalid.
+  fn exclude_set(options: &ScanOptions) -> Result<GlobSet, String> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in &options.exclude {
+      let glob = GlobBuilder::new(pattern)
+        .case_insensitive(cfg!(windows))
+        .build()
+        .map_err(|e| format!("Invalid exclude pattern {}: {}", pattern, e))?;
+      builder.add(glob);
+    }
+    builder.build().map_err(|e| e.to_string())
+  }
+
+  fn new(options: &ScanOptions, root: &Path) -> Result<Self, String> {
+    let root_device = if options.same_filesystem {
+      let md = std::fs::metadata(root).map_err(|e| format!("{}: {}", root.to_string_lossy(), e))?;
+      device_id(root, &md)
+    } else {
+      None
+    };
+
+    Ok(Self {
+      exclude: Self::exclude_set(options)?,
+      excluded: AtomicU64::new(0),
+      extensions: options
+        .include_extensions
+        .iter()
+        .map(|ext| ext.trim_start_matches('.').to_lowercase())
+        .filter(|ext| !ext.is_empty())
+        .collect(),
+      filtered: AtomicU64::new(0),
+      root_device,
+      skipped_mounts: Mutex::new(Vec::new()),
+    })
+  }
+
+  /// Whether directory `path` sits on another filesystem than the scan root;
+  /// such mount points are recorded.
+  fn crosses_device(&self, path: &Path, md: &std::fs::Metadata) -> bool {
+    self.root_device.is_some() && self.crosses(path, device_id(path, md))
+  }
+
+  /// Like `crosses_device`, for a directory whose device is already known.
+  fn crosses(&self, path: &Path, device: Option<u64>) -> bool {
+    let Some(root_device) = self.root_device else {
+      return false;
+    };
+    let crosses = device.is_some_and(|dev| dev != root_device);
+    if crosses {
+      self
+        .skipped_mounts
+        .lock()
+        .push(path.to_string_lossy().to_string());
+    }
+    crosses
+  }
+
+  /// Whether a file passes the extension filter; rejected files are counted.
+  fn includes_file(&self, path: &Path) -> bool {
+    if self.extensions.is_empty() {
+      return true;
+    }
+    let hit = path
+      .extension()
+      .is_some_and(|ext| self.extensions.contains(&ext.to_string_lossy().to_lowercase()));
+    if !hit {
+      self.filtered.fetch_add(1, Ordering::Relaxed);
+    }
+    hit
+  }
+
+  /// Whether `path` matches an exclude pattern; matches are counted.
+  fn excludes(&self, path: &Path) -> bool {
+    if self.exclude.is_empty() {
+      return false;
+    }
+    let hit = self.exclude.is_match(path)
+      || path
+        .file_name()
+        .is_some_and(|name| self.exclude.is_match(name));
+    if hit {
+      self.excluded.fetch_add(1, Ordering::Relaxed);
+    }
+    hit
+  }
+}
+
+/// Where a scan reports to: the app's windows, or a file of JSON lines
+/// (`{"event": ..., "payload": ...}`) that an elevated helper writes for the
+/// app to relay.
+enum EventSink {
+  App(AppHandle),
+  Lines(Mutex<std::fs::File>),
+  /// The connection of a remote agent, shared by the scans it runs.
+  Frames(Arc<Mutex<Box<dyn Write + Send>>>),
+  /// Drops every event, for walks whose results are returned directly.
+  Quiet,
+}
+
+impl EventSink {
+  fn emit<S: Serialize + Clone>(&self, event: &str, payload: S) {
+    match self {
+      EventSink::App(app) => {
+        let _ = app.emit(event, payload);
+      }
+      EventSink::Lines(out) => {
+        let line = serde_json::json!({ "event": event, "payload": payload });
+        let _ = writeln!(out.lock(), "{}", line);
+      }
+      EventSink::Frames(out) => {
+        let event = agent::Reply::Event {
+          event: event.to_string(),
+          payload,
+        };
+        let _ = agent::write_frame(&mut **out.lock(), &event);
+      }
+      EventSink::Quiet => {}
+    }
+  }
+}
+
+/// State shared by every worker of one running scan.
+struct ScanContext<'a> {
+  events: &'a EventSink,
+  scan_id: &'a str,
+  options: &'a ScanOptions,
+  control: &'a ScanControl,
+  filters: ScanFilters,
+  errors: Mutex<ErrorLog>,
+  // Physical files already counted; `None` when hardlink dedup is disabled.
+  seen_files: Option<Mutex<HashSet<FileId>>>,
+  // Directories already entered; `None` unless symlinks are followed.
+  seen_dirs: Option<Mutex<HashSet<FileId>>>,
+  // Where filesystems are mounted, to label those directories.
+  mount_points: HashSet<PathBuf>,
+  // `None` unless owners are tracked.
+  owners: Option<OwnerNames>,
+  // Per-owner totals of the directory nodes built so far, by path.
+  owner_totals: Mutex<HashMap<String, OwnerTotals>>,
+  // Figures over every file counted into the tree.
+  stats: Mutex<ScanStats>,
+  // Every directory walked, until it's known whether it's empty.
+  empty_dirs: Mutex<EmptyDirs>,
+  // Links found whose targets don't resolve.
+  broken_links: Mutex<Vec<BrokenLink>>,
+  // Every non-empty file counted, with its size, when duplicates are looked
+  // for.
+  listed_files: Option<Mutex<Vec<(PathBuf, u64)>>>,
+  // Every image file counted, with its size, when similar images are looked
+  // for.
+  listed_images: Option<Mutex<Vec<(PathBuf, u64)>>>,
+  // `.git` directories the walk came across.
+  git_dirs: Mutex<Vec<PathBuf>>,
+  // Directories named like regenerable ones, such as `node_modules`.
+  artifact_dirs: Mutex<Vec<PathBuf>>,
+  // Steam and GOG manifests the walk came across.
+  game_manifests: Mutex<Vec<PathBuf>>,
+  // Set unless `ignore_mode` is `Off`.
+  ignores: Option<IgnoreRules>,
+  last_emit_ms: AtomicU64,
+  rates: Mutex<RateTracker>,
+  // Set once scan_cancelled has been emitted.
+  cancel_acked: AtomicBool,
+  // Dedicated workers so one huge scan can't starve the others; `None` falls
+  // back to the global rayon pool.
+  pool: Option<Arc<rayon::ThreadPool>>,
+}
+
+impl<'a> ScanContext<'a> {
+  fn new(
+    events: &'a EventSink,
+    scan_id: &'a str,
+    options: &'a ScanOptions,
+    control: &'a ScanControl,
+    filters: ScanFilters,
+  ) -> Self {
+    let mut errors = ErrorLog::default();
+    let short_id = scan_id.chars().take(8).collect::<String>();
+    let mut builder = rayon::ThreadPoolBuilder::new()
+      .num_threads(options.threads)
+      .thread_name(move |i| format!("scan-{}-{}", short_id, i));
+    if options.background {
+      builder = builder.start_handler(|_| crate::priority::lower_current_thread());
+    }
+    let pool = builder
+      .build()
+      .map_err(|e| {
+        let message = format!("Using the shared thread pool: {}", e);
+        errors.push(ScanErrorKind::Other, None, message);
+      })
+      .ok()
+      .map(Arc::new);
+
+    Self {
+      events,
+      scan_id,
+      options,
+      control,
+      filters,
+      errors: Mutex::new(errors),
+      seen_files: options.dedupe_hardlinks.then(|| Mutex::new(HashSet::new())),
+      seen_dirs: options.follow_symlinks.then(|| Mutex::new(HashSet::new())),
+      mount_points: crate::volume::mount_points().into_iter().collect(),
+      owners: options.track_owners.then(OwnerNames::default),
+      owner_totals: Mutex::default(),
+      stats: Mutex::new(ScanStats::new(options.size_mode)),
+      empty_dirs: Mutex::default(),
+      broken_links: Mutex::default(),
+      listed_files: options.find_duplicates.then(Mutex::default),
+      listed_images: options.find_similar_images.then(Mutex::default),
+      git_dirs: Mutex::default(),
+      artifact_dirs: Mutex::default(),
+      game_manifests: Mutex::default(),
+      ignores: IgnoreRules::new(options.ignore_mode),
+      last_emit_ms: AtomicU64::new(0),
+      rates: Mutex::new(RateTracker::default()),
+      cancel_acked: AtomicBool::new(false),
+      pool,
+    }
+  }
+
+  /// Runs `f` on this scan's thread pool, so its parallel iterators use it.
+  fn install<R: Send>(&self, f: impl FnOnce() -> R + Send) -> R {
+    match &self.pool {
+      Some(pool) => pool.install(f),
+      None => f(),
+    }
+  }
+
+  /// Whether the scan was cancelled; the first worker to notice tells the UI.
+  fn cancelled(&self) -> bool {
+    if !self.control.cancel.load(Ordering::Relaxed) {
+      return false;
+    }
+    if !self.cancel_acked.swap(true, Ordering::Relaxed) {
+      self.events.emit(
+        "scan_cancelled",
+        ScanCancelledEvent {
+          scan_id: self.scan_id.to_string(),
+          elapsed_ms: self.control.elapsed_ms(),
+          scanned_entries: self.control.scanned_entries.load(Ordering::Relaxed),
+          scanned_bytes: self.control.scanned_bytes.load(Ordering::Relaxed),
+        },
+      );
+    }
+    true
+  }
+
+  fn skips_hidden(&self, entry: &std::fs::DirEntry) -> bool {
+    !self.options.include_hidden && is_hidden(entry)
+  }
+
+  /// The mode to walk an entry found in `mode` in, after the ignore rules.
+  fn ignore_mode(&self, path: &Path, is_dir: bool, mode: WalkMode) -> WalkMode {
+    match &self.ignores {
+      Some(ignores) if mode.counted() && ignores.sets_aside(path, is_dir) => WalkMode::Aside,
+      _ => mode,
+    }
+  }
+
+  /// Records a failure to read `path` and reports it to the UI right away.
+  fn record_error(
+    &self,
+    path: &Path,
+    kind: ScanErrorKind,
+    os_code: Option<i32>,
+    err: &dyn std::fmt::Display,
+  ) {
+    let path = path.to_string_lossy().to_string();
+    let message = err.to_string();
+    self
+      .errors
+      .lock()
+      .push(kind, Some(&path), format!("{}: {}", path, message));
+    self.events.emit(
+      "scan_error",
+      ScanErrorEvent {
+        scan_id: self.scan_id.to_string(),
+        path,
+        kind,
+        os_code,
+        message,
+      },
+    );
+  }
+
+  fn record_io_error(&self, path: &Path, err: &std::io::Error) {
+    self.record_error(path, ScanErrorKind::of(err), err.raw_os_error(), err);
+  }
+
+  /// The kind of the link at `path`, which its unfollowed metadata says is one.
+  #[cfg(windows)]
+  fn link_kind(&self, path: &Path) -> LinkKind {
+    match windows::reparse_tag(path) {
+      Some(tag) => self.reparse_link_kind(path, tag),
+      None => LinkKind::Symlink,
+    }
+  }
+
+  #[cfg(not(windows))]
+  fn link_kind(&self, _path: &Path) -> LinkKind {
+    LinkKind::Symlink
+  }
+
+  /// The kind of a Windows link from its reparse tag. Junctions and mounted
+  /// volume folders share a tag.
+  #[cfg(windows)]
+  fn reparse_link_kind(&self, path: &Path, tag: u32) -> LinkKind {
+    use windows_sys::Win32::System::SystemServices::IO_REPARSE_TAG_MOUNT_POINT;
+    match tag {
+      IO_REPARSE_TAG_MOUNT_POINT if self.mount_points.contains(path) => LinkKind::MountPoint,
+      IO_REPARSE_TAG_MOUNT_POINT => LinkKind::Junction,
+      _ => LinkKind::Symlink,
+    }
+  }
+
+  /// Notes the link at `path`, found while reading in `mode`, if its target
+  /// doesn't resolve.
+  fn check_link(&self, path: &Path, mode: WalkMode) {
+    if let Err(e) = std::fs::metadata(path) {
+      self.note_broken_link(path, &e, mode);
+    }
+  }
+
+  /// Notes the link at `path` as broken if `err`, from resolving it, says its
+  /// target is missing.
+  fn note_broken_link(&self, path: &Path, err: &std::io::Error, mode: WalkMode) {
+    use std::io::ErrorKind;
+    let missing = matches!(err.kind(), ErrorKind::NotFound | ErrorKind::NotADirectory);
+    if !missing || !mode.counted() {
+      return;
+    }
+    let target = std::fs::read_link(path)
+      .map(|target| target.to_string_lossy().to_string())
+      .unwrap_or_default();
+    self.broken_links.lock().push(BrokenLink {
+      path: path.to_string_lossy().to_string(),
+      target,
+    });
+  }
+
+  /// The owner of a file with the Unix `uid`, when owners are tracked.
+  #[cfg(unix)]
+  fn owner_of_uid(&self, uid: u32) -> Option<Arc<str>> {
+    self.owners.as_ref().map(|names| names.of_uid(uid))
+  }
+
+  /// The owner of the file at `path`, when owners are tracked.
+  #[cfg(windows)]
+  fn owner_of_path(&self, path: &Path) -> Option<Arc<str>> {
+    self.owners.as_ref().map(|names| names.of_path(path))
+  }
+
+  #[cfg(unix)]
+  fn metadata_owner(&self, _path: &Path, md: &std::fs::Metadata) -> Option<Arc<str>> {
+    use std::os::unix::fs::MetadataExt;
+    self.owner_of_uid(md.uid())
+  }
+
+  #[cfg(windows)]
+  fn metadata_owner(&self, path: &Path, _md: &std::fs::Metadata) -> Option<Arc<str>> {
+    self.owner_of_path(path)
+  }
+
+  #[cfg(not(any(unix, windows)))]
+  fn metadata_owner(&self, _path: &Path, _md: &std::fs::Metadata) -> Option<Arc<str>> {
+    None
+  }
+
+  /// Whether a link of `kind` is descended into.
+  fn follows(&self, kind: LinkKind) -> bool {
+    self.options.follow_symlinks && (kind != LinkKind::Junction || self.options.follow_junctions)
+  }
+
+  /// The label of a walked directory: a mount point, or a followed link.
+  fn dir_link_kind(&self, path: &Path) -> LinkKind {
+    if self.mount_points.contains(path) {
+      return LinkKind::MountPoint;
+    }
+    // Without following, every directory walked is a real one.
+    if !self.options.follow_symlinks {
+      return LinkKind::None;
+    }
+    match std::fs::symlink_metadata(path) {
+      Ok(md) if md.file_type().is_symlink() => self.link_kind(path),
+      _ => LinkKind::None,
+    }
+  }
+
+  /// Whether the directory at `path` is entered for the first time. Only
+  /// followed symlinks can lead back to a directory; a repeat visit is
+  /// recorded as a loop and skipped.
+  fn first_visit(&self, path: &Path, md: &std::fs::Metadata) -> bool {
+    self.seen_dirs.is_none() || self.first_visit_of(path, dir_id(path, md))
+  }
+
+  /// Like `first_visit`, for a directory whose identity is already known.
+  fn first_visit_of(&self, path: &Path, id: Option<FileId>) -> bool {
+    let (Some(seen), Some(id)) = (&self.seen_dirs, id) else {
+      return true;
+    };
+    if seen.lock().insert(id) {
+      return true;
+    }
+    self.record_error(
+      path,
+      ScanErrorKind::FilesystemLoop,
+      None,
+      &"Directory already visited through another path",
+    );
+    false
+  }
+
+  fn metadata(&self, path: &Path) -> std::io::Result<std::fs::Metadata> {
+    if self.options.follow_symlinks {
+      std::fs::metadata(path)
+    } else {
+      std::fs::symlink_metadata(path)
+    }
+  }
+
+  /// Sizes a file contributes to the scan: its real sizes the first time a
+  /// physical file is seen, zero for further hardlinks to it.
+  fn counted_sizes(&self, path: &Path, md: &std::fs::Metadata) -> FileSizes {
+    let info = physical_info(path, md, self.seen_files.is_some());
+    let sizes = FileSizes {
+      apparent: md.len(),
+      allocated: info.allocated,
+    };
+    self.count_once(sizes, info.id)
+  }
+
+  /// `sizes` for the first hardlink to the physical file `id`, zero for the
+  /// others. Files without an id are always counted.
+  fn count_once(&self, sizes: FileSizes, id: Option<FileId>) -> FileSizes {
+    if let (Some(seen), Some(id)) = (&self.seen_files, id) {
+      if !seen.lock().insert(id) {
+        return FileSizes::default();
+      }
+    }
+    sizes
+  }
+}
+
+#[derive(Clone, Copy, Default)]
+struct FileSizes {
+  apparent: u64,
+  allocated: u64,
+}
+
+impl FileSizes {
+  fn add(&mut self, other: FileSizes) {
+    self.apparent = self.apparent.saturating_add(other.apparent);
+    self.allocated = self.allocated.saturating_add(other.allocated);
+  }
+}
+
+/// What a file summed into its directory adds besides its sizes.
+struct FileFacts {
+  category: FileCategory,
+  mtime: Option<i64>,
+  // Last access, as far as the volume keeps it up to date.
+  atime: Option<i64>,
+  // Set when owners are tracked.
+  owner: Option<Arc<str>>,
+}
+
+/// (device, inode) on Unix, (volume serial, file index) on Windows.
+type FileId = (u64, u64);
+
+/// (oldest, newest) file modification time, in seconds since the Unix epoch.
+type MtimeRange = Option<(i64, i64)>;
+
+fn widen_mtimes(range: MtimeRange, other: MtimeRange) -> MtimeRange {
+  match (range, other) {
+    (Some((a0, a1)), Some((b0, b1))) => Some((a0.min(b0), a1.max(b1))),
+    (range, None) => range,
+    (None, other) => other,
+  }
+}
+
+/// Seconds since the Unix epoch of the last modification in `md`.
+fn file_mtime(md: &std::fs::Metadata) -> Option<i64> {
+  md.modified().ok().map(unix_seconds)
+}
+
+fn unix_seconds(time: std::time::SystemTime) -> i64 {
+  match time.duration_since(std::time::UNIX_EPOCH) {
+    Ok(since) => since.as_secs() as i64,
+    Err(before) => -(before.duration().as_secs() as i64),
+  }
+}
+
+/// A Windows FILETIME (100 ns ticks since 1601) in seconds since the Unix
+/// epoch; `None` for the zero time of a file that never had one.
+#[cfg(windows)]
+fn filetime_to_unix(ticks: i64) -> Option<i64> {
+  const UNIX_EPOCH_SECS: i64 = 11_644_473_600;
+  (ticks > 0).then(|| ticks / 10_000_000 - UNIX_EPOCH_SECS)
+}
+
+/// On-disk facts about a file that portable metadata doesn't carry.
+struct PhysicalInfo {
+  /// Set only for files with more than one hardlink, and only when asked for.
+  id: Option<FileId>,
+  allocated: u64,
+}
+
+#[cfg(unix)]
+fn physical_info(_path: &Path, md: &std::fs::Metadata, want_id: bool) -> PhysicalInfo {
+  use std::os::unix::fs::MetadataExt;
+  PhysicalInfo {
+    id: (want_id && md.nlink() > 1).then(|| (md.dev(), md.ino())),
+    // st_blocks is always in 512-byte units, whatever the filesystem block size.
+    allocated: md.blocks().saturating_mul(512),
+  }
+}
+
+#[cfg(windows)]
+fn physical_info(path: &Path, md: &std::fs::Metadata, want_id: bool) -> PhysicalInfo {
+  use std::os::windows::io::AsRawHandle;
+  use windows_sys::Win32::Storage::FileSystem::{
+    FileStandardInfo, GetFileInformationByHandle, GetFileInformationByHandleEx,
+    BY_HANDLE_FILE_INFORMATION, FILE_STANDARD_INFO,
+  };
+
+  // Nothing of a placeholder is stored locally, and opening one can download it.
+  if is_online_only(md) {
+    return PhysicalInfo {
+      id: None,
+      allocated: 0,
+    };
+  }
+  let fallback = PhysicalInfo {
+    id: None,
+    allocated: md.len(),
+  };
+  let Ok(file) = open_for_query(path) else {
+    return fallback;
+  };
+  let handle = file.as_raw_handle();
+
+  let mut standard = FILE_STANDARD_INFO::default();
+  let ok = unsafe {
+    GetFileInformationByHandleEx(
+      handle,
+      FileStandardInfo,
+      &mut standard as *mut _ as *mut _,
+      std::mem::size_of::<FILE_STANDARD_INFO>() as u32,
+    )
+  };
+  if ok == 0 {
+    return fallback;
+  }
+
+  let mut id = None;
+  if want_id && standard.NumberOfLinks > 1 {
+    let mut info = BY_HANDLE_FILE_INFORMATION::default();
+    if unsafe { GetFileInformationByHandle(handle, &mut info) } != 0 {
+      let index = ((info.nFileIndexHigh as u64) << 32) | info.nFileIndexLow as u64;
+      id = Some((info.dwVolumeSerialNumber as u64, index));
+    }
+  }
+  // The allocation size of a compressed file is that of its uncompressed data.
+  let allocated = match is_compressed(md) {
+    true => compressed_size(path),
+    false => None,
+  };
+  PhysicalInfo {
+    id,
+    allocated: allocated.unwrap_or(standard.AllocationSize.max(0) as u64),
+  }
+}
+
+#[cfg(not(any(unix, windows)))]
+fn physical_info(_path: &Path, md: &std::fs::Metadata, _want_id: bool) -> PhysicalInfo {
+  PhysicalInfo {
+    id: None,
+    allocated: md.len(),
+  }
+}
+
+/// Attributes of cloud-sync placeholders (OneDrive, Dropbox, ...) whose data
+/// is only online. Reading one downloads it; with RECALL_ON_OPEN, so does
+/// opening it.
+#[cfg(windows)]
+const ONLINE_ONLY_ATTRIBUTES: u32 = {
+  use windows_sys::Win32::Storage::FileSystem::{
+    FILE_ATTRIBUTE_OFFLINE, FILE_ATTRIBUTE_RECALL_ON_DATA_ACCESS, FILE_ATTRIBUTE_RECALL_ON_OPEN,
+  };
+  FILE_ATTRIBUTE_OFFLINE | FILE_ATTRIBUTE_RECALL_ON_OPEN | FILE_ATTRIBUTE_RECALL_ON_DATA_ACCESS
+};
+
+#[cfg(windows)]
+fn is_online_only(md: &std::fs::Metadata) -> bool {
+  use std::os::windows::fs::MetadataExt;
+  md.file_attributes() & ONLINE_ONLY_ATTRIBUTES != 0
+}
+
+#[cfg(not(windows))]
+fn is_online_only(_md: &std::fs::Metadata) -> bool {
+  false
+}
+
+#[cfg(windows)]
+fn is_compressed(md: &std::fs::Metadata) -> bool {
+  use std::os::windows::fs::MetadataExt;
+  use windows_sys::Win32::Storage::FileSystem::FILE_ATTRIBUTE_COMPRESSED;
+  md.file_attributes() & FILE_ATTRIBUTE_COMPRESSED != 0
+}
+
+#[cfg(not(windows))]
+fn is_compressed(_md: &std::fs::Metadata) -> bool {
+  false
+}
+
+#[cfg(windows)]
+fn is_sparse(md: &std::fs::Metadata) -> bool {
+  use std::os::windows::fs::MetadataExt;
+  use windows_sys::Win32::Storage::FileSystem::FILE_ATTRIBUTE_SPARSE_FILE;
+  md.file_attributes() & FILE_ATTRIBUTE_SPARSE_FILE != 0
+}
+
+#[cfg(not(windows))]
+fn is_sparse(_md: &std::fs::Metadata) -> bool {
+  false
+}
+
+/// `ScanNode::attributes` of the entry at `path`.
+#[cfg(windows)]
+fn metadata_attributes(path: &Path, md: &std::fs::Metadata) -> u32 {
+  use std::os::windows::fs::MetadataExt;
+  windows_attributes(path, md.file_attributes())
+}
+
+#[cfg(unix)]
+fn metadata_attributes(path: &Path, md: &std::fs::Metadata) -> u32 {
+  use std::os::unix::fs::MetadataExt;
+  let bits = unix_attributes(path, md.is_file().then(|| md.mode()));
+  #[cfg(target_os = "macos")]
+  let bits = bits | {
+    use std::os::macos::fs::MetadataExt;
+    macos_flag_attributes(md.st_flags())
+  };
+  bits
+}
+
+#[cfg(not(any(unix, windows)))]
+fn metadata_attributes(_path: &Path, md: &std::fs::Metadata) -> u32 {
+  match md.permissions().readonly() {
+    true => ATTR_READONLY,
+    false => 0,
+  }
+}
+
+/// Extensions Windows runs as programs (the default PATHEXT).
+#[cfg(windows)]
+const EXECUTABLE_EXTENSIONS: [&str; 4] = ["exe", "com", "bat", "cmd"];
+
+/// `ScanNode::attributes` from Windows file attributes.
+#[cfg(windows)]
+fn windows_attributes(path: &Path, attributes: u32) -> u32 {
+  use windows_sys::Win32::Storage::FileSystem::{
+    FILE_ATTRIBUTE_COMPRESSED, FILE_ATTRIBUTE_DIRECTORY, FILE_ATTRIBUTE_HIDDEN,
+    FILE_ATTRIBUTE_READONLY, FILE_ATTRIBUTE_SPARSE_FILE, FILE_ATTRIBUTE_SYSTEM,
+  };
+  let mapping = [
+    (FILE_ATTRIBUTE_HIDDEN, ATTR_HIDDEN),
+    (FILE_ATTRIBUTE_SYSTEM, ATTR_SYSTEM),
+    (FILE_ATTRIBUTE_READONLY, ATTR_READONLY),
+    (FILE_ATTRIBUTE_COMPRESSED, ATTR_COMPRESSED),
+    (FILE_ATTRIBUTE_SPARSE_FILE, ATTR_SPARSE),
+    (ONLINE_ONLY_ATTRIBUTES, ATTR_OFFLINE),
+  ];
+  let mut bits = mapping
+    .into_iter()
+    .filter(|&(windows, _)| attributes & windows != 0)
+    .fold(0, |acc, (_, bit)| acc | bit);
+  let executable = attributes & FILE_ATTRIBUTE_DIRECTORY == 0
+    && path.extension().is_some_and(|ext| {
+      let ext = ext.to_string_lossy();
+      EXECUTABLE_EXTENSIONS.iter().any(|e| ext.eq_ignore_ascii_case(e))
+    });
+  if executable {
+    bits |= ATTR_EXECUTABLE;
+  }
+  bits
+}
+
+/// `ScanNode::attributes` from the mode of a regular file, or from the name
+/// alone for anything else: dotfiles are hidden, and permission bits mean
+/// something else on directories and nothing on symlinks.
+#[cfg(unix)]
+fn unix_attributes(path: &Path, file_mode: Option<u32>) -> u32 {
+  let mut bits = 0;
+  if path
+    .file_name()
+    .is_some_and(|name| name.as_encoded_bytes().starts_with(b"."))
+  {
+    bits |= ATTR_HIDDEN;
+  }
+  if let Some(mode) = file_mode {
+    if mode & 0o222 == 0 {
+      bits |= ATTR_READONLY;
+    }
+    if mode & 0o111 != 0 {
+      bits |= ATTR_EXECUTABLE;
+    }
+  }
+  bits
+}
+
+/// `ScanNode::attributes` from the BSD file flags macOS keeps besides the mode.
+#[cfg(target_os = "macos")]
+fn macos_flag_attributes(flags: u32) -> u32 {
+  let mapping = [
+    (libc::UF_HIDDEN, ATTR_HIDDEN),
+    (libc::UF_IMMUTABLE, ATTR_READONLY),
+    (libc::UF_COMPRESSED, ATTR_COMPRESSED),
+  ];
+  mapping
+    .into_iter()
+    .filter(|&(flag, _)| flags & flag != 0)
+    .fold(0, |acc, (_, bit)| acc | bit)
+}
+
+/// Bytes an NTFS-compressed file takes on disk, which neither its length nor
+/// its allocation size tell.
+#[cfg(windows)]
+fn compressed_size(path: &Path) -> Option<u64> {
+  use windows_sys::Win32::{
+    Foundation::{GetLastError, NO_ERROR},
+    Storage::FileSystem::{GetCompressedFileSizeW, INVALID_FILE_SIZE},
+  };
+  let wide = crate::volume::to_wide(path);
+  let mut high = 0u32;
+  let low = unsafe { GetCompressedFileSizeW(wide.as_ptr(), &mut high) };
+  // INVALID_FILE_SIZE is also a valid low word, so only the error code tells.
+  if low == INVALID_FILE_SIZE && unsafe { GetLastError() } != NO_ERROR {
+    return None;
+  }
+  Some(((high as u64) << 32) | low as u64)
+}
+
+/// Identifies the filesystem `path` lives on: st_dev on Unix, the volume serial
+/// number on Windows.
+#[cfg(unix)]
+fn device_id(_path: &Path, md: &std::fs::Metadata) -> Option<u64> {
+  use std::os::unix::fs::MetadataExt;
+  Some(md.dev())
+}
+
+#[cfg(windows)]
+fn device_id(path: &Path, md: &std::fs::Metadata) -> Option<u64> {
+  dir_id(path, md).map(|(volume, _)| volume)
+}
+
+#[cfg(not(any(unix, windows)))]
+fn device_id(_path: &Path, _md: &std::fs::Metadata) -> Option<u64> {
+  None
+}
+
+/// Identity of the directory `path` resolves to, after following links.
+#[cfg(unix)]
+fn dir_id(_path: &Path, md: &std::fs::Metadata) -> Option<FileId> {
+  use std::os::unix::fs::MetadataExt;
+  Some((md.dev(), md.ino()))
+}
+
+#[cfg(windows)]
+fn dir_id(path: &Path, _md: &std::fs::Metadata) -> Option<FileId> {
+  use std::os::windows::{fs::OpenOptionsExt, io::AsRawHandle};
+  use windows_sys::Win32::Storage::FileSystem::{
+    GetFileInformationByHandle, BY_HANDLE_FILE_INFORMATION, FILE_FLAG_BACKUP_SEMANTICS,
+  };
+
+  // Unlike open_for_query, follow the path so a mounted-folder reparse point
+  // reports the volume it leads to.
+  let file = std::fs::OpenOptions::new()
+    .access_mode(0)
+    .custom_flags(FILE_FLAG_BACKUP_SEMANTICS)
+    .open(path)
+    .ok()?;
+  let mut info = BY_HANDLE_FILE_INFORMATION::default();
+  if unsafe { GetFileInformationByHandle(file.as_raw_handle(), &mut info) } == 0 {
+    return None;
+  }
+  let index = ((info.nFileIndexHigh as u64) << 32) | info.nFileIndexLow as u64;
+  Some((info.dwVolumeSerialNumber as u64, index))
+}
+
+#[cfg(not(any(unix, windows)))]
+fn dir_id(_path: &Path, _md: &std::fs::Metadata) -> Option<FileId> {
+  None
+}
+
+/// Opens `path` (file or directory, without following a final reparse point)
+/// for metadata queries only, so locked files can still be inspected.
+#[cfg(windows)]
+fn open_for_query(path: &Path) -> std::io::Result<std::fs::File> {
+  use std::os::windows::fs::OpenOptionsExt;
+  use windows_sys::Win32::Storage::FileSystem::{
+    FILE_FLAG_BACKUP_SEMANTICS, FILE_FLAG_OPEN_REPARSE_POINT,
+  };
+  std::fs::OpenOptions::new()
+    .access_mode(0)
+    .custom_flags(FILE_FLAG_BACKUP_SEMANTICS | FILE_FLAG_OPEN_REPARSE_POINT)
+    .open(path)
+}
+
+#[tauri::command]
+pub async fn start_multi_scan(
+  app: AppHandle,
+  state: State<'_, ScanManager>,
+  paths: Vec<String>,
+  options: ScanOptions,
+) -> Result<Vec<String>, String> {
+  // Checked before any scan starts, as a bad pattern fails every path.
+  ScanFilters::exclude_set(&options)?;
+  let mut scan_ids = Vec::new();
+
+  for path_str in paths {
+    let Ok(root_path) = crate::paths::normalize(&path_str) else {
+      continue;
+    };
+    if !root_path.exists() {
+      continue; // Skip invalid paths, but continue with others
+    }
+
+    // The scans already started still need their ids returned.
+    match spawn_scan(&app, &state, root_path, options.clone()) {
+      Ok(scan_id) => scan_ids.push(scan_id),
+      Err(e) => report_error(&app, e),
+    }
+  }
+
+  Ok(scan_ids)
+}
+
+#[derive(Clone, Serialize)]
+pub struct DriveScan {
+  pub scan_id: String,
+  pub volume: crate::volume::VolumeInfo,
+}
+
+/// Scans every fixed local volume at once, for an overview of the whole
+/// machine: removable and network volumes are left out. Each scan stays on
+/// its own volume and, unless `options` says otherwise, counts allocated
+/// size, so it can be held against the volume's used space. A volume whose
+/// scan can't start is reported through `background_error`.
+#[tauri::command]
+pub async fn scan_fixed_drives(
+  app: AppHandle,
+  state: State<'_, ScanManager>,
+  options: Option<ScanOptions>,
+) -> Result<Vec<DriveScan>, String> {
+  let mut options = options.unwrap_or(ScanOptions {
+    size_mode: SizeMode::Allocated,
+    ..ScanOptions::default()
+  });
+  // Volumes mounted inside another are scanned on their own.
+  options.same_filesystem = true;
+  ScanFilters::exclude_set(&options)?;
+  let mut volumes = crate::volume::volumes();
+  volumes.retain(|volume| !volume.removable && !volume.network);
+  volumes.sort_by(|a, b| a.mount_point.cmp(&b.mount_point));
+  let mut scans = Vec::new();
+  for volume in volumes {
+    let root_path = PathBuf::from(&volume.mount_point);
+    match spawn_scan(&app, &state, root_path, options.clone()) {
+      Ok(scan_id) => scans.push(DriveScan { scan_id, volume }),
+      Err(e) => report_error(&app, e),
+    }
+  }
+  Ok(scans)
+}
+
+#[tauri::command]
+pub async fn start_scan(
+  app: AppHandle,
+  state: State<'_, ScanManager>,
+  path: String,
+  options: ScanOptions,
+) -> Result<String, String> {
+  let root_path = crate::paths::normalize(&path)?;
+  if !root_path.exists() {
+    return Err("Path does not exist".to_string());
+  }
+
+  spawn_scan(&app, &state, root_path, options)
+}
+
+/// Validates a typed path and returns the form a scan should be started with:
+/// absolute, existing, symlinks resolved, and drive or UNC paths without the
+/// `\\?\` prefix.
+#[tauri::command]
+pub async fn canonicalize_path(path: String) -> Result<String, String> {
+  crate::paths::canonicalize(&path).map(|p| p.to_string_lossy().to_string())
+}
+
+#[tauri::command]
+pub async fn cancel_scan(state: State<'_, ScanManager>, scan_id: String) -> Result<(), String> {
+  let scans = state.scans.lock();
+  if let Some(ctrl) = scans.get(&scan_id) {
+    ctrl.cancel.store(true, Ordering::Relaxed);
+    Ok(())
+  } else {
+    Err("Scan not found".to_string())
+  }
+}
+
+/// Cancels every running scan and returns the ids that were signalled.
+#[tauri::command]
+pub async fn cancel_all_scans(state: State<'_, ScanManager>) -> Result<Vec<String>, String> {
+  let scans = state.scans.lock();
+  for ctrl in scans.values() {
+    ctrl.cancel.store(true, Ordering::Relaxed);
+  }
+  Ok(scans.keys().cloned().collect())
+}
+
+#[tauri::command]
+pub async fn get_scan_status(
+  state: State<'_, ScanManager>,
+  scan_id: String,
+) -> Result<ScanStatus, String> {
+  let scans = state.scans.lock();
+  scans
+    .get(&scan_id)
+    .map(|ctrl| ctrl.status(&scan_id))
+    .ok_or_else(|| "Scan not found".to_string())
+}
+
+#[tauri::command]
+pub async fn list_active_scans(state: State<'_, ScanManager>) -> Result<Vec<ScanStatus>, String> {
+  let scans = state.scans.lock();
+  Ok(scans.iter().map(|(id, ctrl)| ctrl.status(id)).collect())
+}
+
+/// Sets how many scans may walk the disk at once; further scans wait in a queue.
+#[tauri::command]
+pub async fn set_max_concurrent_scans(
+  app: AppHandle,
+  state: State<'_, ScanManager>,
+  limit: u32,
+) -> Result<(), String> {
+  let mut queue = state.queue.lock();
+  queue.max_concurrent = (limit as usize).max(1);
+  start_queued(&app, &mut queue);
+  Ok(())
+}
+
+/// Runs `work` on a thread meant for blocking, for commands that walk the
+/// disk or wait on another process, so the async runtime's workers aren't
+/// held up meanwhile.
+async fn blocking<T: Send + 'static>(
+  work: impl FnOnce() -> Result<T, String> + Send + 'static,
+) -> Result<T, String> {
+  tauri::async_runtime::spawn_blocking(work).await.map_err(|e| e.to_string())?
+}
+
+/// Registers a scan and either starts it or queues it behind the concurrency
+/// limit. Returns the new scan id.
+fn spawn_scan(
+  app: &AppHandle,
+  manager: &ScanManager,
+  root_path: PathBuf,
+  options: ScanOptions,
+) -> Result<String, String> {
+  spawn_scan_from(app, manager, root_path, options, ScanSource::Local)
+}
+
+fn spawn_scan_from(
+  app: &AppHandle,
+  manager: &ScanManager,
+  root_path: PathBuf,
+  mut options: ScanOptions,
+  source: ScanSource,
+) -> Result<String, String> {
+  if manager.closing.load(Ordering::Relaxed) {
+    return Err("The app is shutting down".to_string());
+  }
+  if options.threads == 0 && crate::volume::is_network(&root_path) {
+    options.threads = NETWORK_SCAN_THREADS;
+  }
+  let filters = ScanFilters::new(&options, &root_path)?;
+  let scan_id = Uuid::new_v4().to_string();
+  let control = Arc::new(ScanControl::new(root_path.clone()));
+
+  manager
+    .scans
+    .lock()
+    .insert(scan_id.clone(), Arc::clone(&control));
+
+  let job = ScanJob {
+    scan_id: scan_id.clone(),
+    root_path,
+    options,
+    filters,
+    control,
+    source,
+  };
+  let mut queue = manager.queue.lock();
+  if queue.running < queue.max_concurrent {
+    queue.running += 1;
+    run_scan(app.clone(), job);
+  } else {
+    let _ = app.emit(
+      "scan_queued",
+      ScanQueuedEvent {
+        scan_id: scan_id.clone(),
+        root: job.root_path.to_string_lossy().to_string(),
+        position: queue.pending.len() + 1,
+      },
+    );
+    queue.pending.push_back(job);
+  }
+
+  Ok(scan_id)
+}
+
+/// Runs a scan on a background thread (don’t block the command thread).
+fn run_scan(app: AppHandle, job: ScanJob) {
+  let ScanJob {
+    scan_id: scan_id_for_thread,
+    root_path,
+    options,
+    filters,
+    control,
+    source,
+  } = job;
+  let _ = control.started.set(Instant::now());
+  let _ = app.emit(
+    "scan_started",
+    ScanStartedEvent {
+      scan_id: scan_id_for_thread.clone(),
+      root: root_path.to_string_lossy().to_string(),
+    },
+  );
+
+  std::thread::spawn(move || {
+    let events = EventSink::App(app.clone());
+    let results = execute_scan(
+      &events,
+      &scan_id_for_thread,
+      &root_path,
+      &options,
+      &control,
+      filters,
+      &source,
+    );
+
+    // cleanup, and hand the slot to the next queued scan
+    if let Some(results) = &results {
+      history::record(&app, &source, results, &options, control.elapsed_ms());
+      alerts::check(&app, results);
+    }
+    schedule::finished(&app, &scan_id_for_thread, results.as_ref());
+    if let Some(state) = app.try_state::<ScanManager>() {
+      if let Some(results) = results {
+        let watch = options.watch && results.local && !results.partial;
+        state.keep_results(&scan_id_for_thread, results);
+        if watch {
+          if let Err(e) = watch::start(&app, &scan_id_for_thread, &root_path) {
+            report_error(&app, e);
+          }
+        }
+      }
+      state.finish_scan(&app, &scan_id_for_thread);
+    }
+  });
+}
+
+/// Walks `root_path` on the calling thread and reports the outcome through
+/// `events`: scan_done, or scan_failed if the walk panicked. Returns what the
+/// scan leaves behind unless it failed.
+fn execute_scan(
+  events: &EventSink,
+  scan_id: &str,
+  root_path: &Path,
+  options: &ScanOptions,
+  control: &ScanControl,
+  filters: ScanFilters,
+  source: &ScanSource,
+) -> Option<ScanResults> {
+  // A panic anywhere in the walk must still end the scan for the UI and
+  // free its slot, or the frontend waits forever.
+  let outcome = std::panic::catch_unwind(AssertUnwindSafe(|| {
+    if options.background {
+      crate::priority::lower_current_thread();
+    }
+    #[cfg(windows)]
+    windows::enable_backup_privilege();
+    // A whole volume should add up to roughly its used space, which gives the
+    // UI a real progress bar.
+    if source.is_local() && crate::volume::is_volume_root(root_path) {
+      if let Some(usage) = crate::volume::usage(root_path) {
+        let _ = control.expected_bytes.set(usage.used_bytes());
+      }
+    }
+    // Noted before the walk, so what changes during it is caught up on too.
+    #[cfg(windows)]
+    let journal = matches!(source, ScanSource::Local).then(|| usn::position(root_path)).flatten();
+    let ctx = ScanContext::new(events, scan_id, options, control, filters);
+
+    // If cancelled, we still emit done with whatever we computed.
+    let (root, storage_classes) = match source {
+      ScanSource::Local => (ctx.install(|| scan_root(&ctx, root_path)), Vec::new()),
+      ScanSource::Ssh(remote) => (ssh::scan(&ctx, remote, root_path), Vec::new()),
+      ScanSource::S3(bucket) => s3::scan(&ctx, bucket, root_path),
+      ScanSource::WebDav(server) => (webdav::scan(&ctx, server, root_path), Vec::new()),
+      ScanSource::Rclone(remote) => rclone::scan(&ctx, remote, root_path),
+      ScanSource::Cloud(drive) => (cloud::scan(&ctx, drive, root_path), Vec::new()),
+      #[cfg(target_os = "android")]
+      ScanSource::Android(listing) => (android::scan(&ctx, listing, root_path), Vec::new()),
+      #[cfg(target_os = "ios")]
+      ScanSource::Ios(_) => (ctx.install(|| scan_root(&ctx, root_path)), Vec::new()),
+      ScanSource::Import(capture) => (import::scan(&ctx, capture, root_path), Vec::new()),
+      ScanSource::Wsl(name) => (wsl::scan(&ctx, name, root_path), Vec::new()),
+    };
+    let duplicates = ctx.listed_files.as_ref().map(|files| {
+      let files = std::mem::take(&mut *files.lock());
+      ctx.install(|| duplicates::find(&ctx, root_path, files))
+    });
+    let similar_images = ctx.listed_images.as_ref().map(|images| {
+      let images = std::mem::take(&mut *images.lock());
+      ctx.install(|| images::find(&ctx, images))
+    });
+    let git_dirs = std::mem::take(&mut *ctx.git_dirs.lock());
+    let git_repos = ctx.install(|| git::report(&ctx, &root, root_path, git_dirs));
+    let artifact_dirs = std::mem::take(&mut *ctx.artifact_dirs.lock());
+    let dev_artifacts = ctx.install(|| artifacts::report(&ctx, &root, root_path, artifact_dirs));
+    let games = games::report(&root, root_path, std::mem::take(&mut *ctx.game_manifests.lock()));
+    // A cancelled scan falls short of the volume anyway.
+    let reconciled = (source.is_local() && !ctx.cancelled())
+      .then(|| reconcile::reconcile(root_path, &root));
+    let (unaccounted_bytes, unaccounted_parts) = match reconciled.flatten() {
+      Some((bytes, parts)) => (Some(bytes), parts),
+      None => (None, Vec::new()),
+    };
+
+    let (errors, error_summary) = ctx.errors.lock().finish();
+    let skipped_mounts = std::mem::take(&mut *ctx.filters.skipped_mounts.lock());
+    let mut root = root;
+    let mut index = query::NodeIndex::default();
+    index.number(&mut root);
+    let results = {
+      let stats = ctx.stats.lock();
+      ScanResults {
+        root: root_path.to_string_lossy().to_string(),
+        tree: root,
+        index,
+        options: options.clone(),
+        local: matches!(source, ScanSource::Local),
+        #[cfg(windows)]
+        journal,
+        partial: ctx.cancelled(),
+        error_summary: error_summary.clone(),
+        volume: source.is_local().then(|| crate::volume::usage(root_path)).flatten(),
+        owners: ctx.owners.is_some().then(|| std::mem::take(&mut *ctx.owner_totals.lock())),
+        extensions: stats.extensions(),
+        categories: stats.categories(),
+        top_files: stats.largest(),
+        top_dirs: stats.fullest(),
+        old_files: stats.old_files(),
+        empty_dirs: ctx.empty_dirs.lock().finish(),
+        broken_links: {
+          let mut links = std::mem::take(&mut *ctx.broken_links.lock());
+          links.sort_by(|a, b| a.path.cmp(&b.path));
+          links
+        },
+        duplicates,
+        similar_images,
+        git_repos,
+        dev_artifacts,
+        games,
+      }
+    };
+    let top_extensions = stats::extension_summary(&results.extensions);
+    let size_histogram = ctx.stats.lock().histogram();
+    let root = match events {
+      EventSink::App(_) => query::NodeSummary::of(&results.tree),
+      _ => query::NodeSummary::whole(results.tree.clone()),
+    };
+    let done = ScanDoneEvent {
+      scan_id: scan_id.to_string(),
+      root_path: results.root.clone(),
+      root,
+      errors,
+      error_summary,
+      excluded_entries: ctx.filters.excluded.load(Ordering::Relaxed),
+      filtered_entries: ctx.filters.filtered.load(Ordering::Relaxed),
+      skipped_mounts,
+      top_extensions,
+      size_histogram,
+      ignore_totals: ctx.ignores.as_ref().map(IgnoreRules::totals),
+      unaccounted_bytes,
+      unaccounted_parts,
+      storage_classes,
+      partial: ctx.cancelled(),
+      scanned_entries: ctx.control.scanned_entries.load(Ordering::Relaxed),
+      scanned_bytes: ctx.control.scanned_bytes.load(Ordering::Relaxed),
+    };
+    (done, results)
+  }));
+  match outcome {
+    Ok((done, results)) => {
+      events.emit("scan_done", done);
+      Some(results)
+    }
+    Err(payload) => {
+      events.emit(
+        "scan_failed",
+        ScanFailedEvent {
+          scan_id: scan_id.to_string(),
+          root: root_path.to_string_lossy().to_string(),
+          message: panic_message(payload.as_ref()),
+        },
+      );
+      None
+    }
+  }
+}
+
+fn panic_message(payload: &(dyn std::any::Any + Send)) -> String {
+  if let Some(s) = payload.downcast_ref::<&str>() {
+    s.to_string()
+  } else if let Some(s) = payload.downcast_ref::<String>() {
+    s.clone()
+  } else {
+    "The scan thread panicked".to_string()
+  }
+}
+
+/// A directory that gets a node of its own, in the arena of `scan_tree`.
+struct PendingDir {
+  path: PathBuf,
+  parent: Option<usize>,
+  // At the depth limit: reported with totals only, no children.
+  truncated: bool,
+  children: Vec<ScanNode>,
+  // What the walk below a truncated directory added up to, and how many files
+  // and directories that was.
+  totals: FileSizes,
+  files: u64,
+  dirs: u64,
+  mtimes: MtimeRange,
+  categories: CategoryTotals,
+  // Per-owner totals of the files summed into it, when owners are tracked.
+  owners: OwnerTotals,
+  // Bytes of hidden entries below it that aren't under one of its children.
+  hidden: u64,
+  // `ScanNode::attributes` of the directory itself.
+  attributes: u32,
+}
+
+impl PendingDir {
+  fn new(path: PathBuf, parent: Option<usize>, truncated: bool, attributes: u32) -> Self {
+    Self {
+      path,
+      parent,
+      truncated,
+      children: Vec::new(),
+      totals: FileSizes::default(),
+      files: 0,
+      dirs: 0,
+      mtimes: None,
+      categories: CategoryTotals::default(),
+      owners: OwnerTotals::new(),
+      hidden: 0,
+      attributes,
+    }
+  }
+}
+
+/// What a walked directory contributes to the node it is credited to.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum WalkMode {
+  /// Its entries become children of the node.
+  Build,
+  /// Below the depth limit: its entries only add to the node's totals.
+  Total,
+  /// A skipped hidden subtree: its entries only add to the node's hidden bytes.
+  Hidden,
+  /// Set aside by `ignore_mode`: its entries only add to the ignore totals.
+  Aside,
+}
+
+impl WalkMode {
+  /// Whether entries read in this mode count towards the tree and its stats.
+  fn counted(self) -> bool {
+    !matches!(self, WalkMode::Hidden | WalkMode::Aside)
+  }
+}
+
+/// A directory waiting to be read, credited to `dirs[node]` of `scan_tree`.
+struct DirTask {
+  path: PathBuf,
+  depth: u32,
+  node: usize,
+  mode: WalkMode,
+  // Its id in `ScanContext::empty_dirs`.
+  id: usize,
+}
+
+/// What reading one directory turned up. Subdirectories carry a key of the
+/// backend that found them (the walker needs none).
+#[derive(Default)]
+struct DirVisit<K = ()> {
+  nodes: Vec<ScanNode>,
+  // Entries directly inside that aren't hidden and left out.
+  entries: u64,
+  // Every name the listing returned, walked or not; `None` when it failed
+  // part of the way.
+  listed: Option<u64>,
+  // (key, path, attributes, role) per subdirectory.
+  subdirs: Vec<(K, PathBuf, u32, DirRole)>,
+  totals: FileSizes,
+  // Files summed into `totals`, their modification times, categories and
+  // owners.
+  files: u64,
+  mtimes: MtimeRange,
+  categories: CategoryTotals,
+  owners: OwnerTotals,
+  hidden: u64,
+}
+
+impl<K> DirVisit<K> {
+  /// Adds an entry found while reading in `mode`.
+  fn add(&mut self, entry: ChildEntry<K>, mode: WalkMode) {
+    if mode.counted() && !matches!(entry, ChildEntry::Skipped) {
+      self.entries += 1;
+    }
+    match entry {
+      ChildEntry::Node(node) => self.nodes.push(*node),
+      ChildEntry::File(sizes, _) if mode == WalkMode::Hidden => self.hidden += sizes.apparent,
+      ChildEntry::File(..) if mode == WalkMode::Aside => {}
+      ChildEntry::File(sizes, facts) => {
+        self.totals.add(sizes);
+        self.files += 1;
+        self.mtimes = widen_mtimes(self.mtimes, facts.mtime.map(|t| (t, t)));
+        self.categories.add(facts.category, sizes.apparent);
+        if let Some(owner) = facts.owner {
+          owners::add_file(&mut self.owners, &owner, sizes);
+        }
+      }
+      ChildEntry::Dir(key, p, attributes, role) => self.subdirs.push((key, p, attributes, role)),
+      ChildEntry::Skipped => {}
+    }
+  }
+}
+
+/// How a subdirectory found by `visit_dir` gets walked.
+enum DirRole {
+  /// Gets its own node with children.
+  Node,
+  /// Gets its own node with totals only.
+  Truncated,
+  /// Walked on behalf of the node of its parent, in the given mode.
+  Credit(WalkMode),
+}
+
+enum ChildEntry<K = ()> {
+  Node(Box<ScanNode>),
+  /// A file summed into its directory.
+  File(FileSizes, FileFacts),
+  /// A subdirectory still to be read, with its `ScanNode::attributes`.
+  Dir(K, PathBuf, u32, DirRole),
+  Skipped,
+}
+
+impl DirRole {
+  /// Role of a subdirectory at `depth` found while reading in `mode`.
+  fn of(ctx: &ScanContext, mode: WalkMode, depth: u32) -> Self {
+    match mode {
+      WalkMode::Build if depth >= ctx.options.max_depth => DirRole::Truncated,
+      WalkMode::Build => DirRole::Node,
+      mode => DirRole::Credit(mode),
+    }
+  }
+}
+
+/// Scans `root` with the backend the options ask for.
+fn scan_root(ctx: &ScanContext, root: &Path) -> ScanNode {
+  #[cfg(windows)]
+  if ctx.options.backend != ScanBackend::Walker {
+    match mft::scan(ctx, root) {
+      Ok(node) => return node,
+      // Auto quietly uses the walker wherever the MFT can't be read.
+      Err(e) if ctx.options.backend == ScanBackend::Mft => ctx.record_error(
+        root,
+        ScanErrorKind::Other,
+        None,
+        &format!("MFT scan unavailable, walking directories instead: {}", e),
+      ),
+      Err(_) => {}
+    }
+  }
+  scan_tree(ctx, root)
+}
+
+/// Walks the tree under `root` and builds its node in a single pass: the levels
+/// past `max_depth` and skipped hidden subtrees are read by the same walk and
+/// only summed into the node they belong to, so filters, cancellation and
+/// progress behave the same at every depth. After a cancel the directories not
+/// read yet are left out.
+fn scan_tree(ctx: &ScanContext, root: &Path) -> ScanNode {
+  let md = match ctx.metadata(root) {
+    Ok(m) => m,
+    Err(e) => {
+      ctx.record_io_error(root, &e);
+      return empty_dir_node(root);
+    }
+  };
+  // The root is always reported, even if it's a single filtered-out file.
+  if !md.is_dir() {
+    return counted_file_node(ctx, root, &md);
+  }
+  ctx.first_visit(root, &md);
+  ctx.control.scanned_entries.fetch_add(1, Ordering::Relaxed);
+  maybe_emit_progress(ctx, root);
+
+  let truncated = ctx.options.max_depth == 0;
+  let attributes = metadata_attributes(root, &md);
+  let dirs = Mutex::new(vec![PendingDir::new(root.to_path_buf(), None, truncated, attributes)]);
+  let task = DirTask {
+    path: root.to_path_buf(),
+    depth: 0,
+    node: 0,
+    mode: if truncated { WalkMode::Total } else { WalkMode::Build },
+    id: ctx.empty_dirs.lock().add(root.to_path_buf(), None),
+  };
+  rayon::scope(|scope| walk_dir(ctx, &dirs, scope, task));
+  assemble_tree(ctx, dirs.into_inner())
+}
+
+/// Turns the arena of a finished walk into the root's node. Children always
+/// come after their parent in the arena, so walking it backwards finishes every
+/// directory before the one containing it.
+fn assemble_tree(ctx: &ScanContext, mut dirs: Vec<PendingDir>) -> ScanNode {
+  while let Some(mut dir) = dirs.pop() {
+    if ctx.owners.is_some() {
+      let mut owners = std::mem::take(&mut dir.owners);
+      for child in &dir.children {
+        if let (NodeKind::File, Some(owner)) = (&child.kind, &child.owner) {
+          let sizes = FileSizes {
+            apparent: child.size,
+            allocated: child.allocated,
+          };
+          owners::add_file(&mut owners, owner, sizes);
+        }
+      }
+      if let Some(parent) = dir.parent {
+        owners::merge(&mut dirs[parent].owners, &owners);
+      }
+      let path = dir.path.to_string_lossy().to_string();
+      ctx.owner_totals.lock().insert(path, owners);
+    }
+    let mut node = if dir.truncated {
+      truncated_dir_node(&dir)
+    } else {
+      dir_node(ctx, &dir.path, dir.children, dir.hidden)
+    };
+    node.link_kind = ctx.dir_link_kind(&dir.path);
+    node.set_attributes(dir.attributes);
+    match dir.parent {
+      Some(parent) => dirs[parent].children.push(node),
+      None => return node,
+    }
+  }
+  unreachable!("the arena starts with the root")
 }
 
-struct ScanControl {
-  cancel: AtomicBool,
+/// One unit of the walk: reads the directory of `task`, files what it found
+/// into the arena and spawns a task per subdirectory. Idle workers steal those
+/// tasks, so one slow directory never holds up the rest, and the stack stays
+/// flat however deep the tree goes.
+fn walk_dir<'s>(
+  ctx: &'s ScanContext,
+  dirs: &'s Mutex<Vec<PendingDir>>,
+  scope: &rayon::Scope<'s>,
+  task: DirTask,
+) {
+  if ctx.cancelled() {
+    return;
+  }
+  let visit = visit_dir(ctx, &task);
+  let subtasks = record_visit(ctx, &mut dirs.lock(), &task, visit);
+  for ((), subtask) in subtasks {
+    scope.spawn(move |scope| walk_dir(ctx, dirs, scope, subtask));
+  }
 }
 
-impl ScanControl {
-  fn new() -> Self {
-    Self {
-      cancel: AtomicBool::new(false),
+/// Files what reading the directory of `task` turned up into the arena and
+/// returns the tasks for its subdirectories, keyed like `visit.subdirs`.
+fn record_visit<K>(
+  ctx: &ScanContext,
+  dirs: &mut Vec<PendingDir>,
+  task: &DirTask,
+  visit: DirVisit<K>,
+) -> Vec<(K, DirTask)> {
+  if task.mode.counted() {
+    ctx.stats.lock().add_dir(&task.path, visit.entries);
+  }
+  let dir = &mut dirs[task.node];
+  dir.children.extend(visit.nodes);
+  dir.totals.add(visit.totals);
+  dir.files += visit.files;
+  dir.mtimes = widen_mtimes(dir.mtimes, visit.mtimes);
+  dir.categories.merge(&visit.categories);
+  owners::merge(&mut dir.owners, &visit.owners);
+  dir.hidden += visit.hidden;
+  let occupied = visit.listed != Some(visit.subdirs.len() as u64);
+  let mut empty_dirs = ctx.empty_dirs.lock();
+  let mut subtasks = Vec::with_capacity(visit.subdirs.len());
+  for (key, path, attributes, role) in visit.subdirs {
+    match path.file_name() {
+      Some(name) if name == ".git" => ctx.git_dirs.lock().push(path.clone()),
+      Some(name) if artifacts::is_candidate(name) => ctx.artifact_dirs.lock().push(path.clone()),
+      _ => {}
     }
+    let (node, mode) = match role {
+      DirRole::Node | DirRole::Truncated => {
+        let truncated = matches!(role, DirRole::Truncated);
+        dirs.push(PendingDir::new(path.clone(), Some(task.node), truncated, attributes));
+        let mode = if truncated { WalkMode::Total } else { WalkMode::Build };
+        (dirs.len() - 1, mode)
+      }
+      DirRole::Credit(mode) => {
+        if mode.counted() {
+          dirs[task.node].dirs += 1;
+        }
+        (task.node, mode)
+      }
+    };
+    let subtask = DirTask {
+      id: empty_dirs.add(path.clone(), Some(task.id)),
+      path,
+      depth: task.depth + 1,
+      node,
+      mode,
+    };
+    subtasks.push((key, subtask));
   }
+  empty_dirs.listed(task.id, occupied);
+  subtasks
 }
 
-#[tauri::command]
-pub async fn start_multi_scan(
-  app: AppHandle,
-  state: State<'_, ScanManager>,
-  paths: Vec<String>,
-  max_depth: u32,
-  top_children: u32,
-) -> Result<Vec<String>, String> {
-  let mut scan_ids = Vec::new();
-
-  for path_str in paths {
-    let root_path = PathBuf::from(&path_str);
-    if !root_path.exists() {
-      continue; // Skip invalid paths, but continue with others
+/// Reads the directory of `task` and sorts its entries into finished nodes,
+/// sizes to credit and subdirectories to walk next.
+fn visit_dir(ctx: &ScanContext, task: &DirTask) -> DirVisit {
+  #[cfg(target_os = "linux")]
+  if ctx.options.backend != ScanBackend::Walker && linux::available() {
+    return linux::visit_dir(ctx, task);
+  }
+  #[cfg(target_os = "macos")]
+  if ctx.options.backend != ScanBackend::Walker {
+    if let Some(visit) = macos::visit_dir(ctx, task) {
+      return visit;
     }
+  }
+  #[cfg(windows)]
+  if ctx.options.backend != ScanBackend::Walker {
+    if let Some(visit) = windows::visit_dir(ctx, task) {
+      return visit;
+    }
+  }
 
-    let scan_id = Uuid::new_v4().to_string();
-    let control = Arc::new(ScanControl::new());
-
-    state
-      .scans
-      .lock()
-      .insert(scan_id.clone(), Arc::clone(&control));
-
-    let scan_id_for_thread = scan_id.clone();
-    let app_clone = app.clone();
-
-    std::thread::spawn(move || {
-      let scanned_entries = Arc::new(AtomicU64::new(0));
-      let scanned_bytes = Arc::new(AtomicU64::new(0));
-      let errors = Arc::new(Mutex::new(Vec::<String>::new()));
-
-      let scan_start = Instant::now();
-      let last_emit_ms = Arc::new(AtomicU64::new(0));
-
-      let root = scan_path(
-        &app_clone,
-        &scan_id_for_thread,
-        &root_path,
-        0,
-        max_depth,
-        top_children,
-        &control,
-        Arc::clone(&scanned_entries),
-        Arc::clone(&scanned_bytes),
-        Arc::clone(&errors),
-        scan_start,
-        Arc::clone(&last_emit_ms),
-      );
+  let read_dir = match std::fs::read_dir(&task.path) {
+    Ok(rd) => rd,
+    Err(e) => {
+      // Locked folders like System Volume Information only open with backup
+      // semantics, which the walker doesn't use, even for an elevated scan.
+      #[cfg(windows)]
+      if ctx.options.backend == ScanBackend::Walker
+        && e.kind() == std::io::ErrorKind::PermissionDenied
+      {
+        if let Some(visit) = windows::visit_dir(ctx, task) {
+          return visit;
+        }
+      }
+      ctx.record_io_error(&task.path, &e);
+      return DirVisit::default();
+    }
+  };
 
-      let root = match root {
-        Ok(r) => r,
-        Err(e) => {
-          errors.lock().push(e);
-          ScanNode {
-            name: display_name(&root_path),
-            path: root_path.to_string_lossy().to_string(),
-            kind: NodeKind::Dir,
-            size: scanned_bytes.load(Ordering::Relaxed),
-            children: Some(vec![]),
-            omitted_children: None,
+  let mut children = Vec::<(std::fs::DirEntry, WalkMode)>::new();
+  let mut listed = Some(0);
+  for ent in read_dir {
+    match ent {
+      Ok(e) => {
+        listed = listed.map(|n| n + 1);
+        if ctx.filters.excludes(&e.path()) {
+          continue;
+        }
+        if task.mode != WalkMode::Hidden && ctx.skips_hidden(&e) {
+          if ctx.options.track_hidden_bytes {
+            children.push((e, WalkMode::Hidden));
           }
+        } else {
+          let is_dir = e.file_type().is_ok_and(|t| t.is_dir());
+          let mode = ctx.ignore_mode(&e.path(), is_dir, task.mode);
+          children.push((e, mode));
         }
-      };
-
-      let done = ScanDoneEvent {
-        scan_id: scan_id_for_thread.clone(),
-        root,
-        errors: errors.lock().clone(),
-      };
-      let _ = app_clone.emit("scan_done", done);
-
-      if let Some(state) = app_clone.try_state::<ScanManager>() {
-        state.scans.lock().remove(&scan_id_for_thread);
       }
-    });
-
-    scan_ids.push(scan_id);
+      Err(e) => {
+        ctx.record_io_error(&task.path, &e);
+        listed = None;
+      }
+    }
   }
 
-  Ok(scan_ids)
+  let entries: Vec<(ChildEntry, WalkMode)> = children
+    .into_par_iter()
+    .map(|(e, mode)| (visit_child(ctx, &e, task.depth + 1, mode), mode))
+    .collect();
+  let mut visit = DirVisit {
+    listed,
+    ..Default::default()
+  };
+  for (entry, mode) in entries {
+    visit.add(entry, mode);
+  }
+  visit
 }
 
-#[tauri::command]
-pub async fn start_scan(
-  app: AppHandle,
-  state: State<'_, ScanManager>,
-  path: String,
-  max_depth: u32,
-  top_children: u32,
-) -> Result<String, String> {
-  let root_path = PathBuf::from(path);
-  if !root_path.exists() {
-    return Err("Path does not exist".to_string());
+/// Classifies one directory entry at `depth` that is walked in `mode`. The
+/// entry type comes from the directory listing; an entry is only stat'ed when
+/// its size, device or identity is actually needed, and on Windows even that
+/// is served from the listing.
+fn visit_child(
+  ctx: &ScanContext,
+  entry: &std::fs::DirEntry,
+  depth: u32,
+  mode: WalkMode,
+) -> ChildEntry {
+  if ctx.cancelled() {
+    return ChildEntry::Skipped;
   }
-
-  let scan_id = Uuid::new_v4().to_string();
-  let control = Arc::new(ScanControl::new());
-
-  state
-    .scans
-    .lock()
-    .insert(scan_id.clone(), Arc::clone(&control));
-
-  // Run scan on a background thread (don’t block the command thread).
-  let scan_id_for_thread = scan_id.clone();
-  std::thread::spawn(move || {
-    let scanned_entries = Arc::new(AtomicU64::new(0));
-    let scanned_bytes = Arc::new(AtomicU64::new(0));
-    let errors = Arc::new(Mutex::new(Vec::<String>::new()));
-
-    let scan_start = Instant::now();
-    let last_emit_ms = Arc::new(AtomicU64::new(0));
-
-    let root = scan_path(
-      &app,
-      &scan_id_for_thread,
-      &root_path,
-      0,
-      max_depth,
-      top_children,
-      &control,
-      Arc::clone(&scanned_entries),
-      Arc::clone(&scanned_bytes),
-      Arc::clone(&errors),
-      scan_start,
-      Arc::clone(&last_emit_ms),
-    );
-
-    // If cancelled, we still emit done with whatever we computed (or empty root).
-    let root = match root {
-      Ok(r) => r,
+  let path = entry.path();
+  let file_type = match entry.file_type() {
+    Ok(t) => t,
+    Err(e) => return unreadable_child(ctx, path, &e, mode),
+  };
+  let link_kind = match file_type.is_symlink() {
+    true => ctx.link_kind(&path),
+    false => LinkKind::None,
+  };
+  // A followed link takes the type of its target, which takes a stat.
+  let mut md = None;
+  if !link_kind.is_none() && ctx.follows(link_kind) {
+    match std::fs::metadata(&path) {
+      Ok(m) => md = Some(m),
       Err(e) => {
-        errors.lock().push(e);
-        ScanNode {
-          name: display_name(&root_path),
-          path: root_path.to_string_lossy().to_string(),
-          kind: NodeKind::Dir,
-          size: scanned_bytes.load(Ordering::Relaxed),
-          children: Some(vec![]),
-          omitted_children: None,
-        }
+        ctx.note_broken_link(&path, &e, mode);
+        return unreadable_child(ctx, path, &e, mode);
       }
-    };
+    }
+  } else if !link_kind.is_none() {
+    ctx.check_link(&path, mode);
+  }
 
-    let done = ScanDoneEvent {
-      scan_id: scan_id_for_thread.clone(),
-      root,
-      errors: errors.lock().clone(),
+  let is_dir = md.as_ref().map_or(file_type.is_dir(), |m| m.is_dir());
+  if !is_dir {
+    if !ctx.filters.includes_file(&path) {
+      return ChildEntry::Skipped;
+    }
+    let md = match md.map_or_else(|| entry.metadata(), Ok) {
+      Ok(m) => m,
+      Err(e) => return unreadable_child(ctx, path, &e, mode),
     };
-    let _ = app.emit("scan_done", done);
+    return match mode {
+      WalkMode::Build => {
+        let mut node = counted_file_node(ctx, &path, &md);
+        node.link_kind = link_kind;
+        ChildEntry::Node(Box::new(node))
+      }
+      _ => {
+        let facts = file_facts(ctx, &path, &md);
+        ChildEntry::File(count_file(ctx, &path, &md, &facts, mode), facts)
+      }
+    };
+  }
 
-    // cleanup
-    if let Some(state) = app.try_state::<ScanManager>() {
-      state.scans.lock().remove(&scan_id_for_thread);
+  let attributes = dir_entry_attributes(entry, &path, md.as_ref());
+  if ctx.filters.root_device.is_some() || ctx.seen_dirs.is_some() {
+    let md = match md.map_or_else(|| entry.metadata(), Ok) {
+      Ok(m) => m,
+      Err(e) => return unreadable_child(ctx, path, &e, mode),
+    };
+    if ctx.filters.crosses_device(&path, &md) || !ctx.first_visit(&path, &md) {
+      return ChildEntry::Skipped;
     }
-  });
+  }
+  ctx.control.scanned_entries.fetch_add(1, Ordering::Relaxed);
+  maybe_emit_progress(ctx, &path);
+  ChildEntry::Dir((), path, attributes, DirRole::of(ctx, mode, depth))
+}
 
-  Ok(scan_id)
+/// `ScanNode::attributes` of a subdirectory found by the walker, from its
+/// metadata if that was already read. Windows serves it from the listing;
+/// elsewhere only the name is used rather than another stat.
+#[cfg(windows)]
+fn dir_entry_attributes(
+  entry: &std::fs::DirEntry,
+  path: &Path,
+  md: Option<&std::fs::Metadata>,
+) -> u32 {
+  match md {
+    Some(md) => metadata_attributes(path, md),
+    None => entry.metadata().map_or(0, |md| metadata_attributes(path, &md)),
+  }
 }
 
-#[tauri::command]
-pub async fn cancel_scan(state: State<'_, ScanManager>, scan_id: String) -> Result<(), String> {
-  let scans = state.scans.lock();
-  if let Some(ctrl) = scans.get(&scan_id) {
-    ctrl.cancel.store(true, Ordering::Relaxed);
-    Ok(())
-  } else {
-    Err("Scan not found".to_string())
+#[cfg(unix)]
+fn dir_entry_attributes(
+  _entry: &std::fs::DirEntry,
+  path: &Path,
+  md: Option<&std::fs::Metadata>,
+) -> u32 {
+  match md {
+    Some(md) => metadata_attributes(path, md),
+    None => unix_attributes(path, None),
   }
 }
 
-fn scan_path(
-  app: &AppHandle,
-  scan_id: &str,
+#[cfg(not(any(unix, windows)))]
+fn dir_entry_attributes(
+  _entry: &std::fs::DirEntry,
   path: &Path,
-  depth: u32,
-  max_depth: u32,
-  top_children: u32,
-  control: &Arc<ScanControl>,
-  scanned_entries: Arc<AtomicU64>,
-  scanned_bytes: Arc<AtomicU64>,
-  errors: Arc<Mutex<Vec<String>>>,
-  scan_start: Instant,
-  last_emit_ms: Arc<AtomicU64>,
-) -> Result<ScanNode, String> {
-  if control.cancel.load(Ordering::Relaxed) {
-    return Err("cancelled".to_string());
-  }
-
-  // Resolve metadata early
-  let md = match std::fs::symlink_metadata(path) {
-    Ok(m) => m,
-    Err(e) => return Err(format!("{}: {}", path.to_string_lossy(), e)),
-  };
+  md: Option<&std::fs::Metadata>,
+) -> u32 {
+  md.map_or(0, |md| metadata_attributes(path, md))
+}
 
-  let is_dir = md.is_dir();
-  if !is_dir {
-    let sz = md.len();
-    scanned_entries.fetch_add(1, Ordering::Relaxed);
-    scanned_bytes.fetch_add(sz, Ordering::Relaxed);
-    maybe_emit_progress(
-      app,
-      scan_id,
-      scanned_entries.load(Ordering::Relaxed),
-      scanned_bytes.load(Ordering::Relaxed),
-      Some(path.to_string_lossy().to_string()),
-      scan_start,
-      &last_emit_ms,
-    );
-    return Ok(ScanNode {
-      name: display_name(path),
-      path: path.to_string_lossy().to_string(),
-      kind: NodeKind::File,
-      size: sz,
-      children: None,
-      omitted_children: None,
-    });
+/// A subdirectory reported by a native listing, with its (device, inode) when
+/// the listing had to look them up, and its `ScanNode::attributes`.
+#[cfg(any(target_os = "linux", target_os = "macos", windows))]
+fn listed_dir(
+  ctx: &ScanContext,
+  path: PathBuf,
+  id: Option<FileId>,
+  attributes: u32,
+  depth: u32,
+  mode: WalkMode,
+) -> ChildEntry {
+  if ctx.cancelled() {
+    return ChildEntry::Skipped;
+  }
+  if let Some(id) = id {
+    if ctx.filters.crosses(&path, Some(id.0)) || !ctx.first_visit_of(&path, Some(id)) {
+      return ChildEntry::Skipped;
+    }
   }
+  ctx.control.scanned_entries.fetch_add(1, Ordering::Relaxed);
+  maybe_emit_progress(ctx, &path);
+  ChildEntry::Dir((), path, attributes, DirRole::of(ctx, mode, depth))
+}
 
-  // Dir
-  scanned_entries.fetch_add(1, Ordering::Relaxed);
-  maybe_emit_progress(
-    app,
-    scan_id,
-    scanned_entries.load(Ordering::Relaxed),
-    scanned_bytes.load(Ordering::Relaxed),
-    Some(path.to_string_lossy().to_string()),
-    scan_start,
-    &last_emit_ms,
-  );
+/// A file reported by a native listing that already passed the extension
+/// filter. `id` is only set for files with more than one link.
+#[cfg(any(target_os = "linux", target_os = "macos", windows))]
+fn listed_file(
+  ctx: &ScanContext,
+  path: &Path,
+  sizes: FileSizes,
+  id: Option<FileId>,
+  facts: FileFacts,
+  mode: WalkMode,
+) -> ChildEntry {
+  let sizes = ctx.count_once(sizes, id);
+  tally_file(ctx, path, sizes, &facts, mode);
+  match mode {
+    WalkMode::Build => {
+      let mut node = file_node(path, sizes);
+      node.mtime = facts.mtime;
+      node.owner = facts.owner.map(|owner| owner.to_string());
+      ChildEntry::Node(Box::new(node))
+    }
+    _ => ChildEntry::File(sizes, facts),
+  }
+}
 
-  if depth >= max_depth {
-    // If we stop at depth, still compute accurate total size, but do not attach children.
-    let size = compute_total_size(
-      app,
-      scan_id,
-      path,
-      control,
-      Arc::clone(&scanned_entries),
-      Arc::clone(&scanned_bytes),
-      Arc::clone(&errors),
-      scan_start,
-      &last_emit_ms,
-    );
-    return Ok(ScanNode {
-      name: display_name(path),
-      path: path.to_string_lossy().to_string(),
-      kind: NodeKind::Dir,
-      size,
-      children: None,
-      omitted_children: None,
-    });
+/// Records an entry that couldn't be inspected; it shows up empty in the tree.
+fn unreadable_child(
+  ctx: &ScanContext,
+  path: PathBuf,
+  err: &std::io::Error,
+  mode: WalkMode,
+) -> ChildEntry {
+  ctx.record_io_error(&path, err);
+  match mode {
+    WalkMode::Build => ChildEntry::Node(Box::new(empty_dir_node(&path))),
+    _ => ChildEntry::Skipped,
   }
+}
 
-  let read_dir = match std::fs::read_dir(path) {
-    Ok(rd) => rd,
-    Err(e) => {
-      errors
-        .lock()
-        .push(format!("{}: {}", path.to_string_lossy(), e));
-      return Ok(ScanNode {
-        name: display_name(path),
-        path: path.to_string_lossy().to_string(),
-        kind: NodeKind::Dir,
-        size: 0,
-        children: Some(vec![]),
-        omitted_children: None,
-      });
-    }
-  };
+/// Counts a file towards the scan's progress and returns the sizes it adds.
+fn count_file(
+  ctx: &ScanContext,
+  path: &Path,
+  md: &std::fs::Metadata,
+  facts: &FileFacts,
+  mode: WalkMode,
+) -> FileSizes {
+  let sizes = ctx.counted_sizes(path, md);
+  tally_file(ctx, path, sizes, facts, mode);
+  sizes
+}
 
-  let mut child_paths = Vec::<PathBuf>::new();
-  for ent in read_dir {
-    match ent {
-      Ok(e) => child_paths.push(e.path()),
-      Err(e) => errors.lock().push(format!("{}: {}", path.to_string_lossy(), e)),
+/// Counts a file read in `mode` towards the scan's progress and the ignore
+/// totals, and, unless it is hidden or set aside, towards its `ScanStats`.
+fn tally_file(
+  ctx: &ScanContext,
+  path: &Path,
+  sizes: FileSizes,
+  facts: &FileFacts,
+  mode: WalkMode,
+) {
+  ctx.control.scanned_entries.fetch_add(1, Ordering::Relaxed);
+  ctx.control.scanned_bytes.fetch_add(sizes.apparent, Ordering::Relaxed);
+  if let Some(ignores) = ctx.ignores.as_ref().filter(|_| mode != WalkMode::Hidden) {
+    ignores.count(mode == WalkMode::Aside, sizes.apparent);
+  }
+  if mode.counted() {
+    ctx.stats.lock().add_file(path, sizes, facts);
+    if let Some(files) = ctx.listed_files.as_ref().filter(|_| sizes.apparent > 0) {
+      files.lock().push((path.to_path_buf(), sizes.apparent));
+    }
+    let image = facts.category == FileCategory::Image && sizes.apparent > 0;
+    if let Some(images) = ctx.listed_images.as_ref().filter(|_| image) {
+      images.lock().push((path.to_path_buf(), sizes.apparent));
+    }
+    if games::is_manifest(path) {
+      ctx.game_manifests.lock().push(path.to_path_buf());
     }
   }
+  maybe_emit_progress(ctx, path);
+}
 
-  let mut children: Vec<ScanNode> = child_paths
-    .into_par_iter()
-    .map(|p| {
-    scan_path(
-      app,
-      scan_id,
-      &p,
-      depth + 1,
-      max_depth,
-      top_children,
-      control,
-      Arc::clone(&scanned_entries),
-      Arc::clone(&scanned_bytes),
-      Arc::clone(&errors),
-        scan_start,
-        Arc::clone(&last_emit_ms),
-    )
-    .unwrap_or_else(|e| {
-      errors.lock().push(e);
-      ScanNode {
-        name: display_name(&p),
-        path: p.to_string_lossy().to_string(),
-        kind: NodeKind::Dir,
-        size: 0,
-        children: Some(vec![]),
-        omitted_children: None,
-      }
-    })
-  })
-  .collect();
+/// Counts the file at `path` and returns its node.
+fn counted_file_node(ctx: &ScanContext, path: &Path, md: &std::fs::Metadata) -> ScanNode {
+  let facts = file_facts(ctx, path, md);
+  let mut node = file_node(path, count_file(ctx, path, md, &facts, WalkMode::Build));
+  node.online_only = is_online_only(md);
+  node.compressed = is_compressed(md);
+  node.sparse |= is_sparse(md);
+  node.set_attributes(metadata_attributes(path, md));
+  node.mtime = facts.mtime;
+  node.owner = facts.owner.map(|owner| owner.to_string());
+  node
+}
+
+fn file_facts(ctx: &ScanContext, path: &Path, md: &std::fs::Metadata) -> FileFacts {
+  FileFacts {
+    category: FileCategory::of(path),
+    mtime: file_mtime(md),
+    atime: md.accessed().ok().map(unix_seconds),
+    owner: ctx.metadata_owner(path, md),
+  }
+}
+
+fn file_node(path: &Path, sizes: FileSizes) -> ScanNode {
+  ScanNode {
+    id: 0,
+    name: display_name(path),
+    kind: NodeKind::File,
+    size: sizes.apparent,
+    allocated: sizes.allocated,
+    children: None,
+    item_count: None,
+    file_count: None,
+    dir_count: None,
+    mtime: None,
+    newest_mtime: None,
+    oldest_mtime: None,
+    owner: None,
+    category: Some(FileCategory::of(path)),
+    categories: None,
+    hidden_bytes: None,
+    online_only: false,
+    compressed: false,
+    sparse: cfg!(unix) && sizes.allocated.saturating_add(SPARSE_MIN_HOLE) <= sizes.apparent,
+    link_kind: LinkKind::None,
+    attributes: 0,
+  }
+}
+
+/// A directory at the depth limit: accurate totals, but no children attached.
+fn truncated_dir_node(dir: &PendingDir) -> ScanNode {
+  ScanNode {
+    id: 0,
+    name: display_name(&dir.path),
+    kind: NodeKind::Dir,
+    size: dir.totals.apparent,
+    allocated: dir.totals.allocated,
+    children: None,
+    item_count: None,
+    file_count: Some(dir.files),
+    dir_count: Some(dir.dirs),
+    mtime: None,
+    newest_mtime: dir.mtimes.map(|(_, newest)| newest),
+    oldest_mtime: dir.mtimes.map(|(oldest, _)| oldest),
+    owner: None,
+    category: None,
+    categories: Some(dir.categories),
+    hidden_bytes: (dir.hidden > 0).then_some(dir.hidden),
+    online_only: false,
+    compressed: false,
+    sparse: false,
+    link_kind: LinkKind::None,
+    attributes: 0,
+  }
+}
 
-  children.sort_by(|a, b| b.size.cmp(&a.size));
+/// Finishes a walked directory: folds its files, small and surplus children
+/// into aggregate nodes, sorts the rest and sums the sizes. `hidden` counts the
+/// hidden entries directly inside it.
+fn dir_node(ctx: &ScanContext, path: &Path, mut children: Vec<ScanNode>, hidden: u64) -> ScanNode {
+  let size_mode = ctx.options.size_mode;
+  if ctx.options.group_files {
+    let (files, mut kept): (Vec<_>, Vec<_>) = children
+      .into_iter()
+      .partition(|c| matches!(c.kind, NodeKind::File));
+    if files.len() > 1 && !kept.is_empty() {
+      kept.push(other_node("[Files]".to_string(), &files));
+    } else {
+      kept.extend(files);
+    }
+    children = kept;
+  }
+  let min_node_size = ctx.options.min_node_size;
+  if min_node_size > 0 {
+    let (small, mut kept): (Vec<_>, Vec<_>) = children
+      .into_iter()
+      .partition(|c| size_mode.of(c) < min_node_size);
+    // Replacing a single entry with a synthetic one would only hide it.
+    if small.len() > 1 {
+      let name = format!("({} small items)", small.len());
+      kept.push(other_node(name, &small));
+    } else {
+      kept.extend(small);
+    }
+    children = kept;
+  }
+  children.sort_by_key(|c| std::cmp::Reverse(size_mode.of(c)));
 
-  let mut omitted: u64 = 0;
-  if top_children > 0 && (children.len() as u32) > top_children {
-    omitted = (children.len() as u32 - top_children) as u64;
-    children.truncate(top_children as usize);
+  // Truncated children live on as one aggregate node so the listed sizes
+  // still add up to the parent's.
+  let top_children = ctx.options.top_children as usize;
+  if top_children > 0 && children.len() > top_children {
+    let rest = children.split_off(top_children);
+    let name = format!("({} more items)", rest.len());
+    children.push(other_node(name, &rest));
   }
 
   let size = children.iter().map(|c| c.size).sum::<u64>();
+  let allocated = children.iter().map(|c| c.allocated).sum::<u64>();
+  let hidden = hidden + children.iter().filter_map(|c| c.hidden_bytes).sum::<u64>();
+  let (files, dirs) = total_counts(&children);
+  let mtimes = children.iter().fold(None, |range, c| widen_mtimes(range, c.mtimes()));
+  let categories = category_totals(&children);
 
-  Ok(ScanNode {
+  let mut node = ScanNode {
+    id: 0,
     name: display_name(path),
-    path: path.to_string_lossy().to_string(),
     kind: NodeKind::Dir,
     size,
+    allocated,
     children: Some(children),
-    omitted_children: if omitted > 0 { Some(omitted) } else { None },
+    item_count: None,
+    file_count: Some(files),
+    dir_count: Some(dirs),
+    mtime: None,
+    newest_mtime: None,
+    oldest_mtime: None,
+    owner: None,
+    category: None,
+    categories: Some(categories),
+    hidden_bytes: (hidden > 0).then_some(hidden),
+    online_only: false,
+    compressed: false,
+    sparse: false,
+    link_kind: LinkKind::None,
+    attributes: 0,
+  };
+  node.set_mtimes(mtimes);
+  node
+}
+
+/// Placeholder for an entry that couldn't be read.
+fn empty_dir_node(path: &Path) -> ScanNode {
+  ScanNode {
+    id: 0,
+    name: display_name(path),
+    kind: NodeKind::Dir,
+    size: 0,
+    allocated: 0,
+    children: Some(vec![]),
+    item_count: None,
+    file_count: Some(0),
+    dir_count: Some(0),
+    mtime: None,
+    newest_mtime: None,
+    oldest_mtime: None,
+    owner: None,
+    category: None,
+    categories: Some(CategoryTotals::default()),
+    hidden_bytes: None,
+    online_only: false,
+    compressed: false,
+    sparse: false,
+    link_kind: LinkKind::None,
+    attributes: 0,
+  }
+}
+
+/// Synthetic child carrying the combined sizes of `items`.
+fn other_node(name: String, items: &[ScanNode]) -> ScanNode {
+  let hidden = items.iter().filter_map(|c| c.hidden_bytes).sum::<u64>();
+  let (files, dirs) = total_counts(items);
+  let mtimes = items.iter().fold(None, |range, c| widen_mtimes(range, c.mtimes()));
+  let categories = category_totals(items);
+  let mut node = ScanNode {
+    name,
+    id: 0,
+    kind: NodeKind::Other,
+    size: items.iter().map(|c| c.size).sum(),
+    allocated: items.iter().map(|c| c.allocated).sum(),
+    children: None,
+    item_count: Some(items.len() as u64),
+    file_count: Some(files),
+    dir_count: Some(dirs),
+    mtime: None,
+    newest_mtime: None,
+    oldest_mtime: None,
+    owner: None,
+    category: None,
+    categories: Some(categories),
+    hidden_bytes: (hidden > 0).then_some(hidden),
+    online_only: false,
+    compressed: false,
+    sparse: false,
+    link_kind: LinkKind::None,
+    attributes: 0,
+  };
+  node.set_mtimes(mtimes);
+  node
+}
+
+fn category_totals(nodes: &[ScanNode]) -> CategoryTotals {
+  nodes.iter().fold(CategoryTotals::default(), |mut totals, node| {
+    totals.merge(&node.category_totals());
+    totals
   })
 }
 
+/// Files and directories in and below `nodes`.
+fn total_counts(nodes: &[ScanNode]) -> (u64, u64) {
+  nodes.iter().map(ScanNode::counts).fold((0, 0), |(files, dirs), (f, d)| (files + f, dirs + d))
+}
+
 fn display_name(path: &Path) -> String {
   path
     .file_name()
@@ -403,25 +3043,147 @@ fn display_name(path: &Path) -> String {
     .unwrap_or_else(|| path.to_string_lossy().to_string())
 }
 
-fn maybe_emit_progress(
-  app: &AppHandle,
-  scan_id: &str,
-  scanned_entries: u64,
-  scanned_bytes: u64,
-  current_path: Option<String>,
-  scan_start: Instant,
-  last_emit_ms: &AtomicU64,
-) {
+/// Bytes of the files below `path`, from its node under `root` where the
+/// tree has one and from reading the directory where not. Hidden bytes left
+/// out of the tree count.
+fn subtree_bytes(root: &ScanNode, at: &Path, path: &Path) -> u64 {
+  match root.find(at, path) {
+    Some(node) => node.size + node.hidden_bytes.unwrap_or(0),
+    None => dir_bytes(path),
+  }
+}
+
+/// The directories in `dir`, links left out, by name.
+fn subdirs(dir: &Path) -> Vec<PathBuf> {
+  let mut dirs: Vec<PathBuf> = std::fs::read_dir(dir)
+    .into_iter()
+    .flatten()
+    .flatten()
+    .filter(|entry| entry.file_type().is_ok_and(|kind| kind.is_dir()))
+    .map(|entry| entry.path())
+    .collect();
+  dirs.sort();
+  dirs
+}
+
+/// Bytes of the files below `dir`, read from disk.
+fn dir_bytes(dir: &Path) -> u64 {
+  dir_usage(dir).0
+}
+
+/// Bytes and number of the files below `dir`, read from disk.
+fn dir_usage(dir: &Path) -> (u64, u64) {
+  let (mut bytes, mut files) = (0, 0);
+  visit_files(dir, &mut |_, size| {
+    bytes += size;
+    files += 1;
+  });
+  (bytes, files)
+}
+
+/// Calls `visit` with every file below `dir` and its size, without following
+/// links. Entries that can't be read are skipped.
+fn visit_files(dir: &Path, visit: &mut dyn FnMut(&Path, u64)) {
+  let Ok(entries) = std::fs::read_dir(dir) else {
+    return;
+  };
+  for entry in entries.flatten() {
+    let Ok(file_type) = entry.file_type() else {
+      continue;
+    };
+    let path = entry.path();
+    if file_type.is_dir() {
+      visit_files(&path, visit);
+    } else if let Ok(md) = entry.metadata() {
+      visit(&path, md.len());
+    }
+  }
+}
+
+/// Runs `command` and returns its output, or its error output if it fails.
+fn command_output(command: &mut std::process::Command) -> Result<String, String> {
+  let output = command.output().map_err(|e| e.to_string())?;
+  if !output.status.success() {
+    let message = String::from_utf8_lossy(&output.stderr).trim().to_string();
+    return Err(match message.is_empty() {
+      true => format!("{}", output.status),
+      false => message,
+    });
+  }
+  Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// A command for `program`, which on Windows is often a batch file only the
+/// shell finds.
+fn tool_command(program: &str) -> std::process::Command {
+  #[cfg(windows)]
+  {
+    use std::os::windows::process::CommandExt;
+    const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+    let mut command = std::process::Command::new("cmd");
+    command.args(["/C", program]).creation_flags(CREATE_NO_WINDOW);
+    command
+  }
+  #[cfg(not(windows))]
+  std::process::Command::new(program)
+}
+
+/// Exponential moving average of scan throughput, fed at each progress emit.
+#[derive(Default)]
+struct RateTracker {
+  last_ms: u64,
+  last_entries: u64,
+  last_bytes: u64,
+  entries_per_sec: f64,
+  bytes_per_sec: f64,
+}
+
+impl RateTracker {
+  // Time constant of the average: older samples fade out over a few seconds.
+  const SMOOTHING_MS: f64 = 3000.0;
+
+  fn sample(&mut self, now_ms: u64, entries: u64, bytes: u64) {
+    let dt_ms = now_ms.saturating_sub(self.last_ms);
+    if dt_ms == 0 {
+      return;
+    }
+    let secs = dt_ms as f64 / 1000.0;
+    let entries_rate = entries.saturating_sub(self.last_entries) as f64 / secs;
+    let bytes_rate = bytes.saturating_sub(self.last_bytes) as f64 / secs;
+    if self.last_ms == 0 {
+      self.entries_per_sec = entries_rate;
+      self.bytes_per_sec = bytes_rate;
+    } else {
+      let alpha = 1.0 - (-(dt_ms as f64) / Self::SMOOTHING_MS).exp();
+      self.entries_per_sec += alpha * (entries_rate - self.entries_per_sec);
+      self.bytes_per_sec += alpha * (bytes_rate - self.bytes_per_sec);
+    }
+    self.last_ms = now_ms;
+    self.last_entries = entries;
+    self.last_bytes = bytes;
+  }
+
+  fn eta_ms(&self, done_bytes: u64, total_bytes: u64) -> Option<u64> {
+    if self.bytes_per_sec < 1.0 {
+      return None;
+    }
+    let remaining = total_bytes.saturating_sub(done_bytes) as f64;
+    Some((remaining / self.bytes_per_sec * 1000.0) as u64)
+  }
+}
+
+fn maybe_emit_progress(ctx: &ScanContext, current_path: &Path) {
   // Throttle UI updates (especially for network drives).
   // This must be thread-safe because scanning happens in parallel.
-  let now_ms = scan_start.elapsed().as_millis() as u64;
+  let now_ms = ctx.control.elapsed_ms();
   let min_delta = 120u64;
   loop {
-    let prev = last_emit_ms.load(Ordering::Relaxed);
+    let prev = ctx.last_emit_ms.load(Ordering::Relaxed);
     if now_ms.saturating_sub(prev) < min_delta {
       return;
     }
-    if last_emit_ms
+    if ctx
+      .last_emit_ms
       .compare_exchange(prev, now_ms, Ordering::Relaxed, Ordering::Relaxed)
       .is_ok()
     {
@@ -429,69 +3191,42 @@ fn maybe_emit_progress(
     }
   }
 
+  let scanned_entries = ctx.control.scanned_entries.load(Ordering::Relaxed);
+  let scanned_bytes = ctx.control.scanned_bytes.load(Ordering::Relaxed);
+  let mut rates = ctx.rates.lock();
+  rates.sample(now_ms, scanned_entries, scanned_bytes);
+
+  let expected_bytes = ctx.control.expected_bytes.get().copied();
   let payload = ScanProgressEvent {
-    scan_id: scan_id.to_string(),
+    scan_id: ctx.scan_id.to_string(),
     scanned_entries,
     scanned_bytes,
-    current_path,
+    current_path: Some(current_path.to_string_lossy().to_string()),
+    elapsed_ms: now_ms,
+    entries_per_sec: rates.entries_per_sec,
+    bytes_per_sec: rates.bytes_per_sec,
+    eta_ms: expected_bytes.and_then(|total| rates.eta_ms(scanned_bytes, total)),
+    estimated_total_bytes: expected_bytes,
+    percent: expected_bytes
+      .filter(|&total| total > 0)
+      .map(|total| (scanned_bytes as f64 / total as f64 * 100.0).min(100.0)),
   };
-  let _ = app.emit("scan_progress", payload);
+  drop(rates);
+  ctx.events.emit("scan_progress", payload);
 }
 
-fn compute_total_size(
-  app: &AppHandle,
-  scan_id: &str,
-  path: &Path,
-  control: &Arc<ScanControl>,
-  scanned_entries: Arc<AtomicU64>,
-  scanned_bytes: Arc<AtomicU64>,
-  errors: Arc<Mutex<Vec<String>>>,
-  scan_start: Instant,
-  last_emit_ms: &AtomicU64,
-) -> u64 {
-  let mut total: u64 = 0;
-  for entry in jwalk::WalkDir::new(path)
-    .follow_links(false)
-    .into_iter()
-  {
-    if control.cancel.load(Ordering::Relaxed) {
-      break;
-    }
-    let entry = match entry {
-      Ok(e) => e,
-      Err(e) => {
-        errors.lock().push(format!("{}: {}", path.to_string_lossy(), e));
-        continue;
-      }
-    };
-
-    let md = match entry.metadata() {
-      Ok(m) => m,
-      Err(e) => {
-        errors
-          .lock()
-          .push(format!("{}: {}", entry.path().to_string_lossy(), e));
-        continue;
-      }
-    };
-
-    scanned_entries.fetch_add(1, Ordering::Relaxed);
-    if md.is_file() {
-      let sz = md.len();
-      total = total.saturating_add(sz);
-      scanned_bytes.fetch_add(sz, Ordering::Relaxed);
-    }
-
-    maybe_emit_progress(
-      app,
-      scan_id,
-      scanned_entries.load(Ordering::Relaxed),
-      scanned_bytes.load(Ordering::Relaxed),
-      Some(entry.path().to_string_lossy().to_string()),
-      scan_start,
-      last_emit_ms,
-    );
-  }
-  total
+/// Dotfiles on Unix; entries with the HIDDEN or SYSTEM attribute on Windows.
+#[cfg(windows)]
+fn is_hidden(entry: &std::fs::DirEntry) -> bool {
+  use std::os::windows::fs::MetadataExt;
+  use windows_sys::Win32::Storage::FileSystem::{FILE_ATTRIBUTE_HIDDEN, FILE_ATTRIBUTE_SYSTEM};
+  // Served from the directory listing, without another call into the filesystem.
+  entry
+    .metadata()
+    .is_ok_and(|md| md.file_attributes() & (FILE_ATTRIBUTE_HIDDEN | FILE_ATTRIBUTE_SYSTEM) != 0)
 }
 
+#[cfg(not(windows))]
+fn is_hidden(entry: &std::fs::DirEntry) -> bool {
+  entry.file_name().to_string_lossy().starts_with('.')
+}
@@ -0,0 +1,467 @@
+//! The scan engine as a headless agent on another machine, driven by the
+//! desktop app as if its scans ran here. `space-usage --agent` serves one
+//! session on stdin and stdout, which is how the app starts it over SSH;
+//! `--agent --listen host:port` serves any number over TCP, as a service,
+//! for clients that know the token in `SPACE_USAGE_AGENT_TOKEN`. TCP isn't
+//! encrypted: on a network that isn't trusted, go through SSH.
+//!
+//! Messages are MessagePack, each after its length as 4 big-endian bytes.
+//! The app says `hello` with the token, asks for `scan`s and `cancel`s, and
+//! the agent sends back the events a local scan would emit.
+
+use super::{
+  elevated::relay_event,
+  execute_scan,
+  ssh::{check_host, ssh_command, ssh_failure},
+  EventSink, ScanControl, ScanFailedEvent, ScanFilters, ScanManager, ScanOptions, ScanSource,
+  ScanStartedEvent,
+};
+use parking_lot::Mutex;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+  collections::HashMap,
+  io::{self, BufReader, Read, Write},
+  net::{TcpListener, TcpStream, ToSocketAddrs},
+  path::PathBuf,
+  process::{Child, Stdio},
+  sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+  },
+  time::{Duration, Instant},
+};
+use tauri::{AppHandle, Emitter, Manager, State};
+use uuid::Uuid;
+
+const AGENT_FLAG: &str = "--agent";
+const LISTEN_FLAG: &str = "--listen";
+const TOKEN_VAR: &str = "SPACE_USAGE_AGENT_TOKEN";
+
+/// Bumped whenever a message changes shape; both sides must agree.
+const PROTOCOL_VERSION: u32 = 1;
+
+/// The largest message taken in, well above the tree of a huge scan.
+const MAX_FRAME: usize = 1 << 30;
+
+/// The largest hello taken in, read before anyone is known to have the token.
+const MAX_HELLO: usize = 4 << 10;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// From the app to the agent.
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Request {
+  Hello {
+    version: u32,
+    token: Option<String>,
+  },
+  Scan {
+    scan_id: String,
+    root: PathBuf,
+    options: ScanOptions,
+  },
+  Cancel {
+    scan_id: String,
+  },
+}
+
+/// From the agent to the app.
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub(super) enum Reply<P> {
+  Hello { version: u32 },
+  /// The hello wasn't accepted; the agent hangs up after it.
+  Refused { message: String },
+  /// One event of a scan, named as the app emits it.
+  Event { event: String, payload: P },
+}
+
+/// Where to find an agent: listening at `address`, or started over SSH.
+#[derive(Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct AgentTarget {
+  /// `host:port` of an agent started with `--listen`.
+  pub address: Option<String>,
+  /// What that agent has in `SPACE_USAGE_AGENT_TOKEN`.
+  pub token: Option<String>,
+  /// Or a server to run the agent on over SSH, signing in with keys or the
+  /// SSH agent.
+  pub ssh_host: Option<String>,
+  pub ssh_port: Option<u16>,
+  pub ssh_user: Option<String>,
+  /// As `SshTarget::trust_new_host`.
+  pub ssh_trust_new_host: bool,
+  /// How to start it there; `space-usage --agent` when not given.
+  pub command: Option<String>,
+  /// The folder to scan, as the agent's machine sees it.
+  pub path: String,
+}
+
+pub(super) fn write_frame<T: Serialize>(out: &mut dyn Write, message: &T) -> io::Result<()> {
+  let bytes = rmp_serde::to_vec_named(message).map_err(io::Error::other)?;
+  let len = u32::try_from(bytes.len()).map_err(io::Error::other)?;
+  out.write_all(&len.to_be_bytes())?;
+  out.write_all(&bytes)?;
+  out.flush()
+}
+
+/// Reads one message of up to `max` bytes.
+fn read_frame<T: DeserializeOwned>(input: &mut impl Read, max: usize) -> io::Result<T> {
+  let mut len = [0; 4];
+  input.read_exact(&mut len)?;
+  let len = u32::from_be_bytes(len) as usize;
+  if len > max {
+    return Err(io::Error::new(io::ErrorKind::InvalidData, "message too large"));
+  }
+  let mut bytes = vec![0; len];
+  input.read_exact(&mut bytes)?;
+  rmp_serde::from_slice(&bytes).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// Runs the agent when the process was started as one, and returns its
+/// exit code; `None` for a normal app start.
+pub fn run_from_args() -> Option<i32> {
+  let mut args = std::env::args().skip(1);
+  if args.next()? != AGENT_FLAG {
+    return None;
+  }
+  let result = match (args.next().as_deref(), args.next()) {
+    (None, _) => {
+      let out: Box<dyn Write + Send> = Box::new(io::stdout());
+      // SSH already made sure who's on the other end.
+      serve(io::stdin(), out, None)
+    }
+    (Some(LISTEN_FLAG), Some(address)) => listen(&address),
+    _ => Err(format!("Usage: space-usage {} [{} host:port]", AGENT_FLAG, LISTEN_FLAG)),
+  };
+  Some(match result {
+    Ok(()) => 0,
+    Err(e) => {
+      eprintln!("{}", e);
+      1
+    }
+  })
+}
+
+/// Serves every client that connects to `address`, each on a thread.
+fn listen(address: &str) -> Result<(), String> {
+  let token = std::env::var(TOKEN_VAR)
+    .ok()
+    .filter(|token| !token.is_empty())
+    .ok_or_else(|| format!("Set {} to the token clients must give", TOKEN_VAR))?;
+  let listener = TcpListener::bind(address).map_err(|e| format!("{}: {}", address, e))?;
+  eprintln!("Listening on {}", address);
+  for stream in listener.incoming() {
+    let Ok(stream) = stream else {
+      continue;
+    };
+    let token = token.clone();
+    std::thread::spawn(move || {
+      let peer = stream.peer_addr().map(|peer| peer.to_string()).unwrap_or_default();
+      let _ = stream.set_nodelay(true);
+      let out = match stream.try_clone() {
+        Ok(out) => Box::new(out) as Box<dyn Write + Send>,
+        Err(_) => return,
+      };
+      if let Err(e) = serve(stream, out, Some(&token)) {
+        eprintln!("{}: {}", peer, e);
+      }
+    });
+  }
+  Ok(())
+}
+
+/// One session: the hello, then scans until the app hangs up, which
+/// cancels what's still running.
+fn serve(input: impl Read, out: Box<dyn Write + Send>, token: Option<&str>) -> Result<(), String> {
+  let mut input = BufReader::new(input);
+  let out = Arc::new(Mutex::new(out));
+  let send = |reply: &Reply<()>| write_frame(&mut **out.lock(), reply).map_err(|e| e.to_string());
+  let refusal = match read_frame::<Request>(&mut input, MAX_HELLO).map_err(|e| e.to_string())? {
+    Request::Hello { version, .. } if version != PROTOCOL_VERSION => Some(format!(
+      "The agent speaks version {} of the protocol, the app {}; update the older one",
+      PROTOCOL_VERSION, version
+    )),
+    // Hashed first so the comparison takes as long whatever was given.
+    Request::Hello { token: given, .. } => token
+      .filter(|token| Sha256::digest(given.unwrap_or_default()) != Sha256::digest(token))
+      .map(|_| "The agent's token is different".to_string()),
+    _ => Some("Expected a hello".to_string()),
+  };
+  if let Some(message) = refusal {
+    send(&Reply::Refused {
+      message: message.clone(),
+    })?;
+    return Err(message);
+  }
+  send(&Reply::Hello {
+    version: PROTOCOL_VERSION,
+  })?;
+
+  let events = Arc::new(EventSink::Frames(Arc::clone(&out)));
+  let scans: Arc<Mutex<HashMap<String, Arc<ScanControl>>>> = Arc::default();
+  // Ends when the app hangs up, or sends what can't be read.
+  while let Ok(request) = read_frame::<Request>(&mut input, MAX_FRAME) {
+    match request {
+      Request::Scan {
+        scan_id,
+        root,
+        options,
+      } => {
+        let events = Arc::clone(&events);
+        let scans = Arc::clone(&scans);
+        std::thread::spawn(move || run_scan(&events, &scans, scan_id, root, options));
+      }
+      Request::Cancel { scan_id } => {
+        if let Some(control) = scans.lock().get(&scan_id) {
+          control.cancel.store(true, Ordering::Relaxed);
+        }
+      }
+      Request::Hello { .. } => {}
+    }
+  }
+  for control in scans.lock().values() {
+    control.cancel.store(true, Ordering::Relaxed);
+  }
+  Ok(())
+}
+
+/// Runs one scan of a session, as the elevated helper does.
+fn run_scan(
+  events: &EventSink,
+  scans: &Mutex<HashMap<String, Arc<ScanControl>>>,
+  scan_id: String,
+  root: PathBuf,
+  options: ScanOptions,
+) {
+  let filters = match ScanFilters::new(&options, &root) {
+    Ok(filters) => filters,
+    Err(message) => {
+      let failed = ScanFailedEvent {
+        scan_id,
+        root: root.to_string_lossy().to_string(),
+        message,
+      };
+      events.emit("scan_failed", failed);
+      return;
+    }
+  };
+  let control = Arc::new(ScanControl::new(root.clone()));
+  let _ = control.started.set(Instant::now());
+  scans.lock().insert(scan_id.clone(), Arc::clone(&control));
+  execute_scan(events, &scan_id, &root, &options, &control, filters, &ScanSource::Local);
+  scans.lock().remove(&scan_id);
+}
+
+/// A connection to an agent, and the `ssh` carrying it, if any.
+struct Link {
+  input: BufReader<Box<dyn Read + Send>>,
+  out: Mutex<Box<dyn Write + Send>>,
+  ssh: Option<Child>,
+  /// What `ssh` says, read on a thread of its own.
+  ssh_errors: Option<std::thread::JoinHandle<String>>,
+}
+
+impl Link {
+  fn open(target: &AgentTarget) -> Result<Link, String> {
+    if let Some(address) = target.address.as_deref().map(str::trim).filter(|a| !a.is_empty()) {
+      let addresses = address.to_socket_addrs().map_err(|e| format!("{}: {}", address, e))?;
+      let mut last_error = None;
+      for socket in addresses {
+        match TcpStream::connect_timeout(&socket, Duration::from_secs(15)) {
+          Ok(stream) => {
+            let _ = stream.set_nodelay(true);
+            let out = stream.try_clone().map_err(|e| e.to_string())?;
+            return Ok(Link {
+              input: BufReader::new(Box::new(stream)),
+              out: Mutex::new(Box::new(out)),
+              ssh: None,
+              ssh_errors: None,
+            });
+          }
+          Err(e) => last_error = Some(e),
+        }
+      }
+      let reason = last_error.map_or("no address".to_string(), |e| e.to_string());
+      return Err(format!("Can't reach the agent at {}: {}", address, reason));
+    }
+    let host = target.ssh_host.as_deref().map(str::trim).filter(|host| !host.is_empty());
+    let host = host.ok_or("Enter an agent's address, or a server to start one on")?;
+    check_host(host)?;
+    let user = target.ssh_user.as_deref().map(str::trim).filter(|user| !user.is_empty());
+    let command = target.command.as_deref().map(str::trim).filter(|c| !c.is_empty());
+    let mut ssh = ssh_command(target.ssh_port, user, target.ssh_trust_new_host);
+    ssh.args(["-o", "BatchMode=yes"]).arg(host).arg("--");
+    ssh.arg(command.unwrap_or("space-usage --agent"));
+    ssh.stdin(Stdio::piped()).stdout(Stdio::piped()).stderr(Stdio::piped());
+    let mut child = ssh.spawn().map_err(|e| format!("ssh: {}", e))?;
+    let (Some(stdin), Some(stdout)) = (child.stdin.take(), child.stdout.take()) else {
+      let _ = child.kill();
+      return Err("ssh: no pipes to the agent".to_string());
+    };
+    let ssh_errors = child.stderr.take().map(|mut stderr| {
+      std::thread::spawn(move || {
+        let mut text = String::new();
+        let _ = stderr.read_to_string(&mut text);
+        text
+      })
+    });
+    Ok(Link {
+      input: BufReader::new(Box::new(stdout)),
+      out: Mutex::new(Box::new(stdin)),
+      ssh: Some(child),
+      ssh_errors,
+    })
+  }
+
+  /// Hangs up, and returns what `ssh` said about the connection.
+  fn close(mut self) -> String {
+    if let Some(child) = self.ssh.as_mut() {
+      let _ = child.kill();
+      let _ = child.wait();
+    }
+    let errors = self.ssh_errors.take().and_then(|thread| thread.join().ok());
+    errors.unwrap_or_default()
+  }
+}
+
+fn dropped(e: io::Error) -> String {
+  match e.kind() {
+    io::ErrorKind::UnexpectedEof => "The agent hung up".to_string(),
+    _ => format!("The connection to the agent broke: {}", e),
+  }
+}
+
+/// Asks the agent at the other end of `link` for the scan, and relays its
+/// events until the last one, sending a cancel if the scan is cancelled
+/// here.
+fn exchange(
+  app: &AppHandle,
+  link: &mut Link,
+  token: Option<String>,
+  request: &Request,
+  control: &ScanControl,
+) -> Result<(), String> {
+  let Link { input, out, .. } = link;
+  let send = |request: &Request| write_frame(&mut **out.lock(), request).map_err(dropped);
+  send(&Request::Hello {
+    version: PROTOCOL_VERSION,
+    token,
+  })?;
+  match read_frame::<Reply<serde_json::Value>>(input, MAX_FRAME).map_err(dropped)? {
+    Reply::Hello { version } if version == PROTOCOL_VERSION => {}
+    Reply::Hello { version } => {
+      return Err(format!(
+        "The agent speaks version {} of the protocol, the app {}; update the older one",
+        version, PROTOCOL_VERSION
+      ))
+    }
+    Reply::Refused { message } => return Err(message),
+    Reply::Event { .. } => return Err("The agent didn't say hello".to_string()),
+  }
+  send(request)?;
+  let Request::Scan { scan_id, .. } = request else {
+    return Ok(());
+  };
+  let finished = AtomicBool::new(false);
+  std::thread::scope(|scope| {
+    scope.spawn(|| {
+      while !finished.load(Ordering::Relaxed) {
+        if control.cancel.load(Ordering::Relaxed) {
+          let _ = send(&Request::Cancel {
+            scan_id: scan_id.clone(),
+          });
+          return;
+        }
+        std::thread::sleep(POLL_INTERVAL);
+      }
+    });
+    let result = loop {
+      match read_frame::<Reply<serde_json::Value>>(input, MAX_FRAME) {
+        Ok(Reply::Event { event, payload }) => {
+          if relay_event(app, control, &event, payload) {
+            break Ok(());
+          }
+        }
+        Ok(_) => {}
+        Err(e) => break Err(dropped(e)),
+      }
+    };
+    finished.store(true, Ordering::Relaxed);
+    result
+  })
+}
+
+/// Starts a scan of `target.path` on a remote agent and returns its scan
+/// id. Its events arrive as a local scan's do, and cancel_scan reaches the
+/// agent; one that can't be reached ends the scan with `scan_failed`. Like
+/// elevated scans, these don't count against the concurrency limit.
+#[tauri::command]
+pub async fn start_agent_scan(
+  app: AppHandle,
+  state: State<'_, ScanManager>,
+  target: AgentTarget,
+  options: Option<ScanOptions>,
+) -> Result<String, String> {
+  if state.closing.load(Ordering::Relaxed) {
+    return Err("The app is shutting down".to_string());
+  }
+  let root = match target.path.trim() {
+    "" => return Err("Enter the folder to scan, as the agent's machine sees it".to_string()),
+    path => PathBuf::from(path),
+  };
+  let options = options.unwrap_or_default();
+  // The patterns are checked here; the folder is the agent's to look at.
+  let mut checked = options.clone();
+  checked.same_filesystem = false;
+  ScanFilters::new(&checked, &root)?;
+  let scan_id = Uuid::new_v4().to_string();
+  let request = Request::Scan {
+    scan_id: scan_id.clone(),
+    root: root.clone(),
+    options,
+  };
+
+  let control = Arc::new(ScanControl::new(root.clone()));
+  let _ = control.started.set(Instant::now());
+  state
+    .scans
+    .lock()
+    .insert(scan_id.clone(), Arc::clone(&control));
+  let _ = app.emit(
+    "scan_started",
+    ScanStartedEvent {
+      scan_id: scan_id.clone(),
+      root: root.to_string_lossy().to_string(),
+    },
+  );
+
+  let thread_id = scan_id.clone();
+  std::thread::spawn(move || {
+    let token = target.token.clone().filter(|token| !token.is_empty());
+    let relayed = Link::open(&target).and_then(|mut link| {
+      let result = exchange(&app, &mut link, token, &request, &control);
+      let said = link.close();
+      result.map_err(|e| match said.trim() {
+        "" => e,
+        said => format!("ssh: {}", ssh_failure(said)),
+      })
+    });
+    if let Err(message) = relayed {
+      let _ = app.emit(
+        "scan_failed",
+        ScanFailedEvent {
+          scan_id: thread_id.clone(),
+          root: root.to_string_lossy().to_string(),
+          message,
+        },
+      );
+    }
+    if let Some(state) = app.try_state::<ScanManager>() {
+      state.scans.lock().remove(&thread_id);
+    }
+  });
+  Ok(scan_id)
+}
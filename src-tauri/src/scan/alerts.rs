@@ -0,0 +1,217 @@
+//! Rules that watch the size of a folder ("tell me if Videos grows by more
+//! than 20 GB or passes 500 GB"), checked against every scan that covers it,
+//! scheduled or not, as long as it walked this machine without filters.
+//! Growth is counted from the size the first of those scans found. A rule
+//! that trips emits `watch_alert` and shows a desktop notification, then
+//! stays quiet until the folder is back under its limits.
+//!
+//! Rules are kept in `watch_rules.json` in the app's config folder.
+
+use super::{report_error, IgnoreMode, ScanResults};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::{
+  path::{Path, PathBuf},
+  time::{SystemTime, UNIX_EPOCH},
+};
+use tauri::{AppHandle, Emitter, Manager, State};
+use tauri_plugin_notification::NotificationExt;
+use uuid::Uuid;
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct WatchRule {
+  pub id: String,
+  pub path: String,
+  /// Trips when the folder has grown by more than this since `baseline`.
+  pub max_growth: Option<u64>,
+  /// Trips when the folder holds more than this.
+  pub max_size: Option<u64>,
+  /// The size the first scan after the rule was made found, and when.
+  #[serde(default)]
+  pub baseline: Option<u64>,
+  #[serde(default)]
+  pub baseline_time: Option<i64>,
+  /// The latest size seen.
+  #[serde(default)]
+  pub size: Option<u64>,
+  /// Over a limit since the last alert.
+  #[serde(default)]
+  pub tripped: bool,
+}
+
+impl WatchRule {
+  /// What's over the limits at `size`, if anything.
+  fn breach(&self, size: u64) -> Option<String> {
+    let growth = size.saturating_sub(self.baseline.unwrap_or(size));
+    match (self.max_size, self.max_growth) {
+      (Some(max), _) if size > max => Some(format!("holds {:.1} GB", size as f64 / 1e9)),
+      (_, Some(max)) if growth > max => Some(format!("grew by {:.1} GB", growth as f64 / 1e9)),
+      _ => None,
+    }
+  }
+}
+
+#[derive(Clone, Serialize)]
+pub struct WatchAlertEvent {
+  pub rule_id: String,
+  pub path: String,
+  pub size: u64,
+  /// Since the rule's baseline.
+  pub growth: i64,
+  pub reason: String,
+}
+
+#[derive(Default)]
+struct Rules {
+  rules: Vec<WatchRule>,
+  /// Where `rules` is saved; `None` until loaded.
+  file: Option<PathBuf>,
+  /// Why the saved rules couldn't be read, until `list_watch_rules` passes
+  /// it on.
+  load_error: Option<String>,
+}
+
+impl Rules {
+  fn save(&self) -> Result<(), String> {
+    let Some(file) = &self.file else {
+      return Err("Watch rules haven't been loaded".to_string());
+    };
+    let json = serde_json::to_string_pretty(&self.rules).map_err(|e| e.to_string())?;
+    std::fs::write(file, json).map_err(|e| format!("{}: {e}", file.display()))
+  }
+}
+
+#[derive(Default)]
+pub struct Alerts {
+  rules: Mutex<Rules>,
+}
+
+impl Alerts {
+  /// Reads the saved rules.
+  pub fn load(&self, app: &AppHandle) {
+    let dir = match app.path().app_config_dir() {
+      Ok(dir) => dir,
+      Err(e) => {
+        self.rules.lock().load_error = Some(format!("No config folder for watch rules: {}", e));
+        return;
+      }
+    };
+    let _ = std::fs::create_dir_all(&dir);
+    let file = dir.join("watch_rules.json");
+    let mut rules = self.rules.lock();
+    if let Ok(json) = std::fs::read_to_string(&file) {
+      match serde_json::from_str(&json) {
+        Ok(saved) => rules.rules = saved,
+        Err(e) => rules.load_error = Some(format!("{}: {}", file.display(), e)),
+      }
+    }
+    rules.file = Some(file);
+  }
+}
+
+/// Checks the rules on the folders `results` covers and alerts for those
+/// that trip.
+pub(super) fn check(app: &AppHandle, results: &ScanResults) {
+  // Imports, remote listings and filtered scans don't have the size the
+  // folder takes here; they'd move the baseline or clear a tripped rule.
+  let options = &results.options;
+  let filtered = !options.exclude.is_empty()
+    || !options.include_extensions.is_empty()
+    || options.ignore_mode != IgnoreMode::Off;
+  if results.partial || !results.local || filtered {
+    return;
+  }
+  let Some(alerts) = app.try_state::<Alerts>() else {
+    return;
+  };
+  let mut rules = alerts.rules.lock();
+  let now = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs() as i64);
+  let mut changed = false;
+  for rule in rules.rules.iter_mut() {
+    let Some(node) = results.tree.find(Path::new(&results.root), Path::new(&rule.path)) else {
+      continue;
+    };
+    changed = true;
+    if rule.baseline.is_none() {
+      rule.baseline = Some(node.size);
+      rule.baseline_time = Some(now);
+    }
+    rule.size = Some(node.size);
+    let Some(reason) = rule.breach(node.size) else {
+      rule.tripped = false;
+      continue;
+    };
+    if rule.tripped {
+      continue;
+    }
+    rule.tripped = true;
+    let event = WatchAlertEvent {
+      rule_id: rule.id.clone(),
+      path: rule.path.clone(),
+      size: node.size,
+      growth: node.size as i64 - rule.baseline.unwrap_or(node.size) as i64,
+      reason,
+    };
+    let title = format!("{} {}", event.path, event.reason);
+    let body = "Scan it to see what's taking the space.";
+    let _ = app.notification().builder().title(title).body(body).show();
+    let _ = app.emit("watch_alert", event);
+  }
+  if changed {
+    if let Err(e) = rules.save() {
+      report_error(app, e);
+    }
+  }
+}
+
+/// The watch rules, in the order they were added. Errs once if the saved
+/// rules couldn't be read at startup.
+#[tauri::command]
+pub async fn list_watch_rules(state: State<'_, Alerts>) -> Result<Vec<WatchRule>, String> {
+  let mut rules = state.rules.lock();
+  match rules.load_error.take() {
+    Some(e) => Err(e),
+    None => Ok(rules.rules.clone()),
+  }
+}
+
+/// Watches the folder at `path`: alerts once it grows by more than
+/// `max_growth` bytes from what the next scan of it finds, or holds more
+/// than `max_size`. At least one of the two has to be given.
+#[tauri::command]
+pub async fn add_watch_rule(
+  state: State<'_, Alerts>,
+  path: String,
+  max_growth: Option<u64>,
+  max_size: Option<u64>,
+) -> Result<WatchRule, String> {
+  if max_growth.is_none() && max_size.is_none() {
+    return Err("Set how much growth or what size to alert at".to_string());
+  }
+  let path = crate::paths::canonicalize(&path)?;
+  let rule = WatchRule {
+    id: Uuid::new_v4().to_string(),
+    path: path.to_string_lossy().to_string(),
+    max_growth,
+    max_size,
+    baseline: None,
+    baseline_time: None,
+    size: None,
+    tripped: false,
+  };
+  let mut rules = state.rules.lock();
+  rules.rules.push(rule.clone());
+  rules.save()?;
+  Ok(rule)
+}
+
+#[tauri::command]
+pub async fn remove_watch_rule(state: State<'_, Alerts>, id: String) -> Result<(), String> {
+  let mut rules = state.rules.lock();
+  let before = rules.rules.len();
+  rules.rules.retain(|rule| rule.id != id);
+  if rules.rules.len() == before {
+    return Err("No such watch rule".to_string());
+  }
+  rules.save()
+}
@@ -0,0 +1,192 @@
+//! Shared storage on Android, where scoped storage hides most of
+//! `/storage/emulated/0` from a walk. Folders the user picks through the
+//! Storage Access Framework are listed with DocumentsContract; the whole of
+//! shared storage through MediaStore, which sees every media file, plus
+//! what each app takes once the user grants usage access. The listing is
+//! done by `StoragePlugin.kt` and comes back flat, with paths, for the tree
+//! to be built from.
+
+use super::{ScanManager, ScanOptions};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, State};
+#[cfg(target_os = "android")]
+use {
+  super::{
+    empty_dir_node, maybe_emit_progress, mobile,
+    object_tree::{self, Object, ObjectTree},
+    spawn_scan_from, ScanContext, ScanErrorKind, ScanNode, ScanSource,
+  },
+  serde_json::json,
+  std::{
+    path::{Path, PathBuf},
+    sync::atomic::Ordering,
+  },
+  tauri::{plugin::PluginHandle, Wry},
+};
+
+/// What scans of the whole of shared storage are shown as.
+#[cfg(target_os = "android")]
+const SHARED_STORAGE: &str = "/storage/emulated/0";
+
+#[cfg(not(target_os = "android"))]
+const ANDROID_ONLY: &str = "Only on Android";
+
+/// A folder the user picked, which the app may read from now on.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct AndroidFolder {
+  /// The tree URI, `content://…/tree/primary%3ADownload`.
+  pub uri: String,
+  pub name: String,
+}
+
+#[cfg(target_os = "android")]
+#[derive(Deserialize)]
+struct Folders {
+  folders: Vec<AndroidFolder>,
+}
+
+/// One entry of a listing.
+#[cfg(target_os = "android")]
+#[derive(Deserialize)]
+struct Entry {
+  /// `/`-separated, under the folder listed.
+  path: String,
+  #[serde(default)]
+  size: u64,
+  /// In seconds since the Unix epoch.
+  modified: Option<i64>,
+  #[serde(default)]
+  dir: bool,
+}
+
+/// What couldn't be listed, or was left out.
+#[cfg(target_os = "android")]
+#[derive(Deserialize)]
+struct Problem {
+  #[serde(default)]
+  path: String,
+  message: String,
+  #[serde(default)]
+  denied: bool,
+}
+
+#[cfg(target_os = "android")]
+#[derive(Deserialize)]
+struct Listed {
+  entries: Vec<Entry>,
+  #[serde(default)]
+  problems: Vec<Problem>,
+}
+
+/// What to list: a picked folder, or all of shared storage when `uri` is
+/// `None`.
+#[cfg(target_os = "android")]
+pub(super) struct Listing {
+  bridge: PluginHandle<Wry>,
+  uri: Option<String>,
+}
+
+/// Has the Kotlin side list `listing` and builds its tree under `root`.
+/// Nothing arrives until the listing is done, so progress comes at the end.
+#[cfg(target_os = "android")]
+pub(super) fn scan(ctx: &ScanContext, listing: &Listing, root: &Path) -> ScanNode {
+  let listed: Result<Listed, String> = match &listing.uri {
+    Some(uri) => mobile::call(&listing.bridge, "listTree", json!({ "uri": uri })),
+    None => mobile::call(&listing.bridge, "listShared", json!({})),
+  };
+  let listed = match listed {
+    Ok(listed) => listed,
+    Err(e) => {
+      ctx.record_error(root, ScanErrorKind::Other, None, &e);
+      return empty_dir_node(root);
+    }
+  };
+  let root_text = root.to_string_lossy();
+  for problem in listed.problems {
+    let kind = match problem.denied {
+      true => ScanErrorKind::PermissionDenied,
+      false => ScanErrorKind::Other,
+    };
+    let at = format!("{}/{}", root_text, problem.path);
+    ctx.record_error(Path::new(at.trim_end_matches('/')), kind, None, &problem.message);
+  }
+  let mut tree = ObjectTree::default();
+  for entry in listed.entries {
+    if ctx.cancelled() {
+      break;
+    }
+    if entry.dir {
+      tree.folder(&entry.path);
+      continue;
+    }
+    ctx.control.scanned_entries.fetch_add(1, Ordering::Relaxed);
+    ctx.control.scanned_bytes.fetch_add(entry.size, Ordering::Relaxed);
+    maybe_emit_progress(ctx, Path::new(&format!("{}/{}", root_text, entry.path)));
+    let object = Object {
+      key: String::new(),
+      size: entry.size,
+      mtime: entry.modified,
+      class: None,
+    };
+    tree.insert(&entry.path, object);
+  }
+  object_tree::build(ctx, tree, &root_text).0
+}
+
+/// Asks the user for a folder with the system picker, and keeps the access
+/// they grant to it across restarts.
+#[tauri::command]
+#[cfg_attr(not(target_os = "android"), allow(unused_variables))]
+pub async fn pick_android_folder(app: AppHandle) -> Result<AndroidFolder, String> {
+  #[cfg(target_os = "android")]
+  return mobile::call(&mobile::bridge(&app)?, "pickFolder", json!({}));
+  #[cfg(not(target_os = "android"))]
+  Err(ANDROID_ONLY.to_string())
+}
+
+/// The folders picked so far that the app can still read.
+#[tauri::command]
+#[cfg_attr(not(target_os = "android"), allow(unused_variables))]
+pub async fn list_android_folders(app: AppHandle) -> Result<Vec<AndroidFolder>, String> {
+  #[cfg(target_os = "android")]
+  return mobile::call::<Folders>(&mobile::bridge(&app)?, "persistedFolders", json!({}))
+    .map(|folders| folders.folders);
+  #[cfg(not(target_os = "android"))]
+  Err(ANDROID_ONLY.to_string())
+}
+
+/// Opens the system's usage access list, where the app can be allowed to
+/// see how much storage each app takes.
+#[tauri::command]
+#[cfg_attr(not(target_os = "android"), allow(unused_variables))]
+pub async fn open_android_usage_access(app: AppHandle) -> Result<(), String> {
+  #[cfg(target_os = "android")]
+  return mobile::call::<serde_json::Value>(&mobile::bridge(&app)?, "openUsageAccess", json!({}))
+    .map(drop);
+  #[cfg(not(target_os = "android"))]
+  Err(ANDROID_ONLY.to_string())
+}
+
+/// Scans a picked folder, by its tree `uri`, or all of shared storage when
+/// there's none; asks for the media permissions for that first. Returns
+/// the scan id; the tree arrives as for a local scan.
+#[tauri::command]
+#[cfg_attr(not(target_os = "android"), allow(unused_variables))]
+pub async fn start_android_scan(
+  app: AppHandle,
+  state: State<'_, ScanManager>,
+  uri: Option<String>,
+  options: Option<ScanOptions>,
+) -> Result<String, String> {
+  #[cfg(target_os = "android")]
+  {
+    let bridge = mobile::bridge(&app)?;
+    let mut options = options.unwrap_or_default();
+    options.same_filesystem = false;
+    let root = PathBuf::from(uri.as_deref().unwrap_or(SHARED_STORAGE));
+    let listing = Listing { bridge, uri };
+    spawn_scan_from(&app, &state, root, options, ScanSource::Android(listing))
+  }
+  #[cfg(not(target_os = "android"))]
+  Err(ANDROID_ONLY.to_string())
+}
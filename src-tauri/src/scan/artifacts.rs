@@ -0,0 +1,198 @@
+//! Directories that builds and package managers regenerate (dependencies,
+//! build output, virtualenvs, caches), grouped by the project they belong
+//! to, and removing them in a batch.
+
+use super::{blocking, empty::RemovedDirs, subtree_bytes, ScanContext, ScanManager, ScanNode};
+use rayon::prelude::*;
+use serde::Serialize;
+use std::{
+  collections::{HashMap, HashSet},
+  path::{Path, PathBuf},
+};
+use tauri::State;
+
+/// Names of the directories the walk notes for a closer look.
+const CANDIDATE_NAMES: [&str; 8] =
+  ["node_modules", "target", ".venv", "venv", "__pycache__", ".gradle", "dist", "build"];
+/// Build files that mark the root of a project.
+const PROJECT_FILES: [&str; 10] = [
+  "package.json",
+  "Cargo.toml",
+  "pom.xml",
+  "build.gradle",
+  "build.gradle.kts",
+  "settings.gradle",
+  "settings.gradle.kts",
+  "pyproject.toml",
+  "setup.py",
+  "CMakeLists.txt",
+];
+const GRADLE_FILES: [&str; 4] =
+  ["build.gradle", "build.gradle.kts", "settings.gradle", "settings.gradle.kts"];
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ArtifactKind {
+  NodeModules,
+  CargoTarget,
+  MavenTarget,
+  Virtualenv,
+  Pycache,
+  Gradle,
+  BuildOutput,
+}
+
+#[derive(Clone, Serialize)]
+pub struct ArtifactDir {
+  pub path: String,
+  pub kind: ArtifactKind,
+  pub bytes: u64,
+}
+
+/// A project's regenerable directories, largest first.
+#[derive(Clone, Serialize)]
+pub struct DevProject {
+  pub path: String,
+  pub bytes: u64,
+  pub artifacts: Vec<ArtifactDir>,
+}
+
+/// Whether the walk should note the directory named `name`.
+pub(super) fn is_candidate(name: &std::ffi::OsStr) -> bool {
+  CANDIDATE_NAMES.iter().any(|candidate| name == *candidate)
+}
+
+/// Groups the candidate directories the walk noted that really are
+/// regenerable by project, the most bytes first. Candidates inside another
+/// one, like the `node_modules` of a dependency, are counted with it.
+pub(super) fn report(
+  ctx: &ScanContext,
+  root: &ScanNode,
+  root_path: &Path,
+  mut dirs: Vec<PathBuf>,
+) -> Vec<DevProject> {
+  dirs.sort();
+  let mut outermost: Vec<(PathBuf, ArtifactKind)> = Vec::new();
+  for dir in dirs {
+    if outermost.last().is_some_and(|(last, _)| dir.starts_with(last)) {
+      continue;
+    }
+    if let Some(kind) = kind_of(&dir) {
+      outermost.push((dir, kind));
+    }
+  }
+
+  let artifacts: Vec<(PathBuf, ArtifactDir)> = outermost
+    .into_par_iter()
+    .filter_map(|(dir, kind)| {
+      if ctx.cancelled() {
+        return None;
+      }
+      let artifact = ArtifactDir {
+        path: dir.to_string_lossy().to_string(),
+        kind,
+        bytes: subtree_bytes(root, root_path, &dir),
+      };
+      Some((project_of(&dir, root_path), artifact))
+    })
+    .collect();
+
+  let mut by_project: HashMap<PathBuf, Vec<ArtifactDir>> = HashMap::new();
+  for (project, artifact) in artifacts {
+    by_project.entry(project).or_default().push(artifact);
+  }
+  let mut projects: Vec<DevProject> = by_project
+    .into_iter()
+    .map(|(path, mut artifacts)| {
+      artifacts.sort_by(|a, b| b.bytes.cmp(&a.bytes).then(a.path.cmp(&b.path)));
+      DevProject {
+        path: path.to_string_lossy().to_string(),
+        bytes: artifacts.iter().map(|artifact| artifact.bytes).sum(),
+        artifacts,
+      }
+    })
+    .collect();
+  projects.sort_by(|a, b| b.bytes.cmp(&a.bytes).then(a.path.cmp(&b.path)));
+  projects
+}
+
+/// What the candidate directory `dir` holds, or `None` when its name is all
+/// it has in common with one: a `build` folder of documents, say.
+fn kind_of(dir: &Path) -> Option<ArtifactKind> {
+  let name = dir.file_name()?.to_str()?;
+  let parent = dir.parent()?;
+  let beside = |files: &[&str]| files.iter().any(|file| parent.join(file).exists());
+  match name {
+    "node_modules" => Some(ArtifactKind::NodeModules),
+    "__pycache__" => Some(ArtifactKind::Pycache),
+    "target" if beside(&["Cargo.toml"]) => Some(ArtifactKind::CargoTarget),
+    "target" if beside(&["pom.xml"]) => Some(ArtifactKind::MavenTarget),
+    ".venv" | "venv" if dir.join("pyvenv.cfg").is_file() => Some(ArtifactKind::Virtualenv),
+    ".gradle" if beside(&GRADLE_FILES) => Some(ArtifactKind::Gradle),
+    "dist" | "build" if beside(&PROJECT_FILES) => Some(ArtifactKind::BuildOutput),
+    _ => None,
+  }
+}
+
+/// The closest directory above `dir`, up to the scan's `root`, with a build
+/// file or a `.git`; the parent of `dir` when none has.
+fn project_of(dir: &Path, root: &Path) -> PathBuf {
+  let parent = dir.parent().unwrap_or(dir);
+  parent
+    .ancestors()
+    .take_while(|ancestor| ancestor.starts_with(root))
+    .find(|ancestor| {
+      let has = |file: &str| ancestor.join(file).exists();
+      has(".git") || PROJECT_FILES.iter().any(|file| has(file))
+    })
+    .unwrap_or(parent)
+    .to_path_buf()
+}
+
+/// Regenerable directories of a finished scan grouped by project, the most
+/// bytes first. Hidden ones, like `.venv`, are only listed when the walk
+/// came across them: with hidden entries left out, that takes
+/// `track_hidden_bytes`.
+#[tauri::command]
+pub async fn get_dev_artifacts(
+  state: State<'_, ScanManager>,
+  scan_id: String,
+) -> Result<Vec<DevProject>, String> {
+  Ok(state.results(&scan_id)?.dev_artifacts.clone())
+}
+
+/// Removes directories `get_dev_artifacts` reported for a scan, with
+/// everything inside them. Links inside are removed, not followed.
+#[tauri::command]
+pub async fn remove_dev_artifacts(
+  state: State<'_, ScanManager>,
+  scan_id: String,
+  paths: Vec<String>,
+) -> Result<RemovedDirs, String> {
+  let reported: HashSet<String> = state
+    .results(&scan_id)?
+    .dev_artifacts
+    .iter()
+    .flat_map(|project| &project.artifacts)
+    .map(|artifact| artifact.path.clone())
+    .collect();
+  // A build folder can hold gigabytes in many thousands of files.
+  blocking(move || {
+    let mut outcome = RemovedDirs {
+      removed: Vec::new(),
+      errors: Vec::new(),
+    };
+    for path in paths {
+      if !reported.contains(&path) {
+        outcome.errors.push(format!("{}: not a developer artifact of the scan", path));
+        continue;
+      }
+      match std::fs::remove_dir_all(&path) {
+        Ok(()) => outcome.removed.push(path),
+        Err(e) => outcome.errors.push(format!("{}: {}", path, e)),
+      }
+    }
+    Ok(outcome)
+  })
+  .await
+}
@@ -0,0 +1,245 @@
+//! Storage of the profiles of Chrome, Edge, Firefox and Safari, from where
+//! each browser keeps them on this OS. Caches, site data (IndexedDB, local
+//! storage, the Cache API) and service workers are told apart, since they
+//! can be cleared from the browser without losing bookmarks or passwords.
+
+use super::{blocking, dir_bytes, display_name as file_name, subdirs};
+use rayon::prelude::*;
+use serde::Serialize;
+use std::{
+  collections::HashMap,
+  path::{Path, PathBuf},
+};
+
+/// The disk caches of a Chrome or Edge profile.
+const CHROMIUM_CACHES: [&str; 3] = ["Cache", "Code Cache", "GPUCache"];
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Browser {
+  Chrome,
+  Edge,
+  Firefox,
+  Safari,
+}
+
+#[derive(Clone, Serialize)]
+pub struct BrowserProfile {
+  pub browser: Browser,
+  /// The name the browser shows for it, when it keeps one.
+  pub name: String,
+  pub path: String,
+  pub cache_bytes: u64,
+  pub site_data_bytes: u64,
+  /// Firefox and Safari keep service worker caches with site data.
+  pub service_worker_bytes: u64,
+  /// Everything else of the profile: history, extensions, settings.
+  pub other_bytes: u64,
+}
+
+/// Where the parts of one profile are.
+struct ProfileDirs {
+  browser: Browser,
+  name: String,
+  path: PathBuf,
+  caches: Vec<PathBuf>,
+  site_data: Vec<PathBuf>,
+  service_workers: Vec<PathBuf>,
+}
+
+/// The profiles of the browsers installed for the current user, the most
+/// bytes first.
+#[tauri::command]
+pub async fn get_browser_storage() -> Result<Vec<BrowserProfile>, String> {
+  blocking(|| {
+    let Some(home) = crate::paths::home_dir() else {
+      return Ok(Vec::new());
+    };
+    let mut profiles = Vec::new();
+    for browser in [Browser::Chrome, Browser::Edge] {
+      profiles.extend(chromium_profiles(browser, &home));
+    }
+    profiles.extend(firefox_profiles(&home));
+    if cfg!(target_os = "macos") {
+      profiles.push(safari_profile(&home));
+    }
+    let mut measured: Vec<BrowserProfile> =
+      profiles.into_par_iter().filter(|profile| profile.path.is_dir()).map(measure).collect();
+    measured.sort_by_key(|profile| {
+      std::cmp::Reverse(
+        profile.cache_bytes
+          + profile.site_data_bytes
+          + profile.service_worker_bytes
+          + profile.other_bytes,
+      )
+    });
+    Ok(measured)
+  })
+  .await
+}
+
+fn measure(profile: ProfileDirs) -> BrowserProfile {
+  let mut inside = 0;
+  let mut sum = |dirs: &[PathBuf]| {
+    let mut bytes = 0;
+    for dir in dirs {
+      let dir_total = dir_bytes(dir);
+      if dir.starts_with(&profile.path) {
+        inside += dir_total;
+      }
+      bytes += dir_total;
+    }
+    bytes
+  };
+  let cache_bytes = sum(&profile.caches);
+  let site_data_bytes = sum(&profile.site_data);
+  let service_worker_bytes = sum(&profile.service_workers);
+  BrowserProfile {
+    browser: profile.browser,
+    name: profile.name,
+    path: profile.path.to_string_lossy().to_string(),
+    cache_bytes,
+    site_data_bytes,
+    service_worker_bytes,
+    other_bytes: dir_bytes(&profile.path).saturating_sub(inside),
+  }
+}
+
+/// The per-user configuration and cache directories of Linux.
+fn xdg(var: &str, home: &Path, fallback: &str) -> PathBuf {
+  std::env::var_os(var)
+    .filter(|dir| !dir.is_empty())
+    .map(PathBuf::from)
+    .unwrap_or_else(|| home.join(fallback))
+}
+
+fn local_app_data(home: &Path) -> PathBuf {
+  std::env::var_os("LOCALAPPDATA")
+    .map(PathBuf::from)
+    .unwrap_or_else(|| home.join("AppData").join("Local"))
+}
+
+/// Chrome and Edge keep profiles (`Default`, `Profile 1`...) in a user data
+/// directory, with their disk cache split off on macOS and Linux.
+fn chromium_profiles(browser: Browser, home: &Path) -> Vec<ProfileDirs> {
+  let (vendor, mac_name, linux_name) = match browser {
+    Browser::Edge => ("Microsoft/Edge", "Microsoft Edge", "microsoft-edge"),
+    _ => ("Google/Chrome", "Google/Chrome", "google-chrome"),
+  };
+  let library = home.join("Library");
+  let (user_data, cache_root) = if cfg!(windows) {
+    let user_data = local_app_data(home).join(vendor).join("User Data");
+    (user_data.clone(), user_data)
+  } else if cfg!(target_os = "macos") {
+    (
+      library.join("Application Support").join(mac_name),
+      library.join("Caches").join(mac_name),
+    )
+  } else {
+    (
+      xdg("XDG_CONFIG_HOME", home, ".config").join(linux_name),
+      xdg("XDG_CACHE_HOME", home, ".cache").join(linux_name),
+    )
+  };
+  let names = profile_names(&user_data);
+  subdirs(&user_data)
+    .into_iter()
+    .filter(|profile| profile.join("Preferences").is_file())
+    .map(|profile| {
+      let dir = file_name(&profile);
+      let cache = cache_root.join(&dir);
+      let mut caches: Vec<PathBuf> = CHROMIUM_CACHES.iter().map(|dir| profile.join(dir)).collect();
+      if cache != profile {
+        caches.extend(CHROMIUM_CACHES.iter().map(|dir| cache.join(dir)));
+      }
+      ProfileDirs {
+        browser,
+        name: names.get(&dir).cloned().unwrap_or_else(|| dir.clone()),
+        site_data: ["IndexedDB", "Local Storage", "Session Storage"]
+          .iter()
+          .map(|part| profile.join(part))
+          .collect(),
+        service_workers: vec![profile.join("Service Worker")],
+        caches: caches.into_iter().filter(|dir| dir.is_dir()).collect(),
+        path: profile,
+      }
+    })
+    .collect()
+}
+
+/// Display names of Chrome and Edge profiles by directory, from `Local State`.
+fn profile_names(user_data: &Path) -> HashMap<String, String> {
+  let Ok(text) = std::fs::read(user_data.join("Local State")) else {
+    return HashMap::new();
+  };
+  let Ok(state) = serde_json::from_slice::<serde_json::Value>(&text) else {
+    return HashMap::new();
+  };
+  let Some(profiles) = state["profile"]["info_cache"].as_object() else {
+    return HashMap::new();
+  };
+  profiles
+    .iter()
+    .filter_map(|(dir, info)| Some((dir.clone(), info["name"].as_str()?.to_string())))
+    .collect()
+}
+
+/// Firefox profiles are the directories with a `prefs.js`; their caches sit
+/// in a directory of the same name under the local profile root.
+fn firefox_profiles(home: &Path) -> Vec<ProfileDirs> {
+  let library = home.join("Library");
+  let (profiles, cache_root) = if cfg!(windows) {
+    let roaming = std::env::var_os("APPDATA")
+      .map(PathBuf::from)
+      .unwrap_or_else(|| home.join("AppData").join("Roaming"));
+    (
+      roaming.join("Mozilla/Firefox/Profiles"),
+      local_app_data(home).join("Mozilla/Firefox/Profiles"),
+    )
+  } else if cfg!(target_os = "macos") {
+    (
+      library.join("Application Support/Firefox/Profiles"),
+      library.join("Caches/Firefox/Profiles"),
+    )
+  } else {
+    (home.join(".mozilla/firefox"), xdg("XDG_CACHE_HOME", home, ".cache").join("mozilla/firefox"))
+  };
+  subdirs(&profiles)
+    .into_iter()
+    .filter(|profile| profile.join("prefs.js").is_file())
+    .map(|profile| {
+      let name = file_name(&profile);
+      let caches = [cache_root.join(&name), profile.join("cache2")];
+      ProfileDirs {
+        browser: Browser::Firefox,
+        // Directories are named `<salt>.<profile name>`.
+        name: name.split_once('.').map_or(name.as_str(), |(_, name)| name).to_string(),
+        caches: caches.into_iter().filter(|dir| dir.is_dir() && *dir != profile).collect(),
+        site_data: vec![profile.join("storage")],
+        service_workers: Vec::new(),
+        path: profile,
+      }
+    })
+    .collect()
+}
+
+/// Safari has one profile, spread over `~/Library`. Reading it takes Full
+/// Disk Access.
+fn safari_profile(home: &Path) -> ProfileDirs {
+  let library = home.join("Library");
+  ProfileDirs {
+    browser: Browser::Safari,
+    name: "Safari".to_string(),
+    caches: vec![
+      library.join("Caches/com.apple.Safari"),
+      library.join("Containers/com.apple.Safari/Data/Library/Caches"),
+    ],
+    site_data: vec![
+      library.join("Safari/Databases"),
+      library.join("Safari/LocalStorage"),
+      library.join("WebKit/com.apple.Safari/WebsiteData"),
+    ],
+    service_workers: Vec::new(),
+    path: library.join("Safari"),
+  }
+}
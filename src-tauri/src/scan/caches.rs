@@ -0,0 +1,179 @@
+//! The global caches of package managers and build tools, which sit in the
+//! home directory wherever the projects using them are, and emptying them
+//! with the tools' own commands.
+
+use super::{blocking, command_output, dir_usage, tool_command};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PackageTool {
+  Cargo,
+  Npm,
+  Pip,
+  Maven,
+  Gradle,
+  Homebrew,
+  Nuget,
+}
+
+const TOOLS: [PackageTool; 7] = [
+  PackageTool::Cargo,
+  PackageTool::Npm,
+  PackageTool::Pip,
+  PackageTool::Maven,
+  PackageTool::Gradle,
+  PackageTool::Homebrew,
+  PackageTool::Nuget,
+];
+
+#[derive(Clone, Serialize)]
+pub struct PackageCache {
+  pub tool: PackageTool,
+  /// The cache's directories on this machine.
+  pub paths: Vec<String>,
+  pub bytes: u64,
+  pub files: u64,
+  /// Whether `clear_package_cache` can empty it.
+  pub clearable: bool,
+}
+
+impl PackageTool {
+  /// Where the tool keeps its cache here, whether or not it exists. The
+  /// tools' own variables for moving it are honored.
+  fn cache_dirs(self) -> Vec<PathBuf> {
+    let Some(home) = crate::paths::home_dir() else {
+      return Vec::new();
+    };
+    let var = |name: &str| std::env::var_os(name).filter(|v| !v.is_empty()).map(PathBuf::from);
+    match self {
+      Self::Cargo => {
+        let cargo_home = var("CARGO_HOME").unwrap_or_else(|| home.join(".cargo"));
+        vec![cargo_home.join("registry"), cargo_home.join("git")]
+      }
+      Self::Npm => vec![var("npm_config_cache").unwrap_or_else(|| npm_default(&home))],
+      Self::Pip => vec![var("PIP_CACHE_DIR").unwrap_or_else(|| user_cache(&home).join(pip_name()))],
+      Self::Maven => vec![home.join(".m2").join("repository")],
+      Self::Gradle => {
+        let gradle_home = var("GRADLE_USER_HOME").unwrap_or_else(|| home.join(".gradle"));
+        vec![gradle_home.join("caches"), gradle_home.join("wrapper").join("dists")]
+      }
+      Self::Homebrew => {
+        vec![var("HOMEBREW_CACHE").unwrap_or_else(|| user_cache(&home).join("Homebrew"))]
+      }
+      Self::Nuget => {
+        let nuget_home = home.join(".nuget").join("packages");
+        let mut dirs = vec![var("NUGET_PACKAGES").unwrap_or(nuget_home)];
+        dirs.extend(nuget_http_caches(&home));
+        dirs
+      }
+    }
+  }
+
+  /// The command that empties the cache, for tools that have one.
+  fn clear_command(self) -> Option<&'static [&'static str]> {
+    match self {
+      Self::Npm => Some(&["npm", "cache", "clean", "--force"]),
+      Self::Pip => Some(&["pip", "cache", "purge"]),
+      Self::Homebrew => Some(&["brew", "cleanup", "--prune=all"]),
+      Self::Nuget => Some(&["dotnet", "nuget", "locals", "all", "--clear"]),
+      Self::Cargo | Self::Maven | Self::Gradle => None,
+    }
+  }
+}
+
+/// The per-user cache directory of the OS.
+fn user_cache(home: &Path) -> PathBuf {
+  if cfg!(windows) {
+    local_app_data(home)
+  } else if cfg!(target_os = "macos") {
+    home.join("Library").join("Caches")
+  } else {
+    std::env::var_os("XDG_CACHE_HOME")
+      .filter(|dir| !dir.is_empty())
+      .map(PathBuf::from)
+      .unwrap_or_else(|| home.join(".cache"))
+  }
+}
+
+fn local_app_data(home: &Path) -> PathBuf {
+  std::env::var_os("LOCALAPPDATA")
+    .map(PathBuf::from)
+    .unwrap_or_else(|| home.join("AppData").join("Local"))
+}
+
+fn npm_default(home: &Path) -> PathBuf {
+  match cfg!(windows) {
+    true => local_app_data(home).join("npm-cache"),
+    false => home.join(".npm"),
+  }
+}
+
+/// pip's directory under the user cache: `pip\Cache` on Windows.
+fn pip_name() -> PathBuf {
+  match cfg!(windows) {
+    true => Path::new("pip").join("Cache"),
+    false => PathBuf::from("pip"),
+  }
+}
+
+/// NuGet's HTTP caches, under their current and older names.
+fn nuget_http_caches(home: &Path) -> Vec<PathBuf> {
+  let base = match cfg!(windows) {
+    true => local_app_data(home).join("NuGet"),
+    false => home.join(".local").join("share").join("NuGet"),
+  };
+  vec![base.join("http-cache"), base.join("v3-cache")]
+}
+
+/// The size of `tool`'s cache; `None` if none of its directories exist.
+fn measure(tool: PackageTool) -> Option<PackageCache> {
+  let dirs: Vec<PathBuf> = tool.cache_dirs().into_iter().filter(|dir| dir.is_dir()).collect();
+  if dirs.is_empty() {
+    return None;
+  }
+  let (mut bytes, mut files) = (0, 0);
+  for dir in &dirs {
+    let (dir_bytes, dir_files) = dir_usage(dir);
+    bytes += dir_bytes;
+    files += dir_files;
+  }
+  Some(PackageCache {
+    tool,
+    paths: dirs.iter().map(|dir| dir.to_string_lossy().to_string()).collect(),
+    bytes,
+    files,
+    clearable: tool.clear_command().is_some(),
+  })
+}
+
+/// The caches of the package managers and build tools found on this machine,
+/// largest first. They are measured wherever they are, not as part of a scan.
+#[tauri::command]
+pub async fn get_package_caches() -> Result<Vec<PackageCache>, String> {
+  blocking(|| {
+    let mut caches: Vec<PackageCache> = TOOLS.into_par_iter().filter_map(measure).collect();
+    caches.sort_by_key(|cache| std::cmp::Reverse(cache.bytes));
+    Ok(caches)
+  })
+  .await
+}
+
+/// Empties the cache of `tool` with the tool's own command, which knows what
+/// is safe to drop. Tools without one are refused rather than having their
+/// directories deleted.
+#[tauri::command]
+pub async fn clear_package_cache(tool: PackageTool) -> Result<(), String> {
+  let Some([program, args @ ..]) = tool.clear_command() else {
+    return Err("This cache has no command to clear it".to_string());
+  };
+  blocking(move || {
+    let mut command = tool_command(program);
+    command_output(command.args(args))
+      .map(|_| ())
+      .map_err(|e| format!("{}: {}", program, e))
+  })
+  .await
+}
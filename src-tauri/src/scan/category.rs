@@ -0,0 +1,113 @@
+//! Broad kinds of files, told apart by extension, so the UI can color and
+//! total them.
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::{collections::HashMap, path::Path};
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FileCategory {
+  Image,
+  Video,
+  Audio,
+  Document,
+  Code,
+  Archive,
+  Executable,
+  Other,
+}
+
+impl FileCategory {
+  pub const ALL: [FileCategory; 8] = [
+    FileCategory::Image,
+    FileCategory::Video,
+    FileCategory::Audio,
+    FileCategory::Document,
+    FileCategory::Code,
+    FileCategory::Archive,
+    FileCategory::Executable,
+    FileCategory::Other,
+  ];
+
+  pub fn of(path: &Path) -> Self {
+    match path.extension() {
+      Some(ext) => Self::of_extension(&ext.to_string_lossy().to_lowercase()),
+      None => FileCategory::Other,
+    }
+  }
+
+  /// The category of a lowercase extension without its dot.
+  pub fn of_extension(extension: &str) -> Self {
+    match extension {
+      "jpg" | "jpeg" | "png" | "gif" | "bmp" | "tif" | "tiff" | "webp" | "heic" | "heif"
+      | "avif" | "svg" | "ico" | "psd" | "raw" | "cr2" | "nef" | "arw" | "dng" => {
+        FileCategory::Image
+      }
+      "mp4" | "mkv" | "mov" | "avi" | "wmv" | "flv" | "webm" | "m4v" | "mpg" | "mpeg" | "m2ts"
+      | "3gp" | "vob" => FileCategory::Video,
+      "mp3" | "wav" | "flac" | "aac" | "ogg" | "opus" | "m4a" | "wma" | "aiff" | "alac"
+      | "mid" | "midi" => FileCategory::Audio,
+      "pdf" | "doc" | "docx" | "xls" | "xlsx" | "ppt" | "pptx" | "odt" | "ods" | "odp"
+      | "rtf" | "txt" | "md" | "epub" | "csv" | "pages" | "numbers" | "key" => {
+        FileCategory::Document
+      }
+      "rs" | "c" | "h" | "cpp" | "hpp" | "cc" | "cs" | "java" | "kt" | "go" | "py" | "rb"
+      | "js" | "jsx" | "mjs" | "ts" | "tsx" | "php" | "swift" | "m" | "sh" | "ps1" | "lua"
+      | "pl" | "sql" | "html" | "css" | "scss" | "json" | "toml" | "yaml" | "yml" | "xml" => {
+        FileCategory::Code
+      }
+      "zip" | "7z" | "rar" | "tar" | "gz" | "tgz" | "bz2" | "xz" | "zst" | "lz4" | "cab"
+      | "iso" | "dmg" | "jar" | "apk" | "deb" | "rpm" => FileCategory::Archive,
+      "exe" | "dll" | "msi" | "sys" | "so" | "dylib" | "app" | "bin" | "com" | "bat" | "cmd" => {
+        FileCategory::Executable
+      }
+      _ => FileCategory::Other,
+    }
+  }
+}
+
+/// Bytes per category in a subtree, serialized as a map of the non-zero ones.
+#[derive(Clone, Copy, Default)]
+pub struct CategoryTotals([u64; FileCategory::ALL.len()]);
+
+impl CategoryTotals {
+  pub fn add(&mut self, category: FileCategory, bytes: u64) {
+    let total = &mut self.0[category as usize];
+    *total = total.saturating_add(bytes);
+  }
+
+  pub fn merge(&mut self, other: &CategoryTotals) {
+    for category in FileCategory::ALL {
+      self.add(category, other.0[category as usize]);
+    }
+  }
+
+  /// The category with the most bytes, if any has some.
+  pub fn largest(&self) -> Option<FileCategory> {
+    FileCategory::ALL
+      .into_iter()
+      .filter(|&category| self.0[category as usize] > 0)
+      .max_by_key(|&category| self.0[category as usize])
+  }
+}
+
+impl Serialize for CategoryTotals {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_map(
+      FileCategory::ALL
+        .into_iter()
+        .map(|category| (category, self.0[category as usize]))
+        .filter(|&(_, bytes)| bytes > 0),
+    )
+  }
+}
+
+impl<'de> Deserialize<'de> for CategoryTotals {
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    let mut totals = CategoryTotals::default();
+    for (category, bytes) in HashMap::<FileCategory, u64>::deserialize(deserializer)? {
+      totals.add(category, bytes);
+    }
+    Ok(totals)
+  }
+}
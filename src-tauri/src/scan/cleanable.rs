@@ -0,0 +1,136 @@
+//! Places on Windows that fill up with files nothing needs once they've
+//! served their purpose: temp folders, downloaded updates, the previous
+//! installation, the Delivery Optimization cache, superseded driver
+//! packages and queued error reports. Disk Cleanup and Storage Sense empty
+//! most of them.
+
+use super::{blocking, dir_usage, display_name, subdirs};
+use rayon::prelude::*;
+use serde::Serialize;
+use std::{
+  collections::HashMap,
+  path::{Path, PathBuf},
+};
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CleanableKind {
+  UserTemp,
+  WindowsTemp,
+  UpdateDownloads,
+  WindowsOld,
+  DeliveryOptimization,
+  OldDrivers,
+  ErrorReports,
+}
+
+#[derive(Clone, Serialize)]
+pub struct CleanableItem {
+  pub kind: CleanableKind,
+  pub paths: Vec<String>,
+  pub bytes: u64,
+  pub files: u64,
+}
+
+#[derive(Clone, Serialize)]
+pub struct CleanableReport {
+  /// Bytes of every item together.
+  pub total_bytes: u64,
+  /// The items that hold anything, the most bytes first.
+  pub items: Vec<CleanableItem>,
+}
+
+/// The known reclaimable locations of this Windows installation and what
+/// they hold. Folders this process can't read count as empty. Elsewhere the
+/// report is empty.
+#[tauri::command]
+pub async fn get_cleanable_locations() -> Result<CleanableReport, String> {
+  blocking(|| {
+    let mut items: Vec<CleanableItem> = match cfg!(windows) {
+      true => locations().into_par_iter().map(|(kind, dirs)| measure(kind, dirs)).collect(),
+      false => Vec::new(),
+    };
+    items.retain(|item| item.bytes > 0);
+    items.sort_by_key(|item| std::cmp::Reverse(item.bytes));
+    Ok(CleanableReport {
+      total_bytes: items.iter().map(|item| item.bytes).sum(),
+      items,
+    })
+  })
+  .await
+}
+
+fn locations() -> Vec<(CleanableKind, Vec<PathBuf>)> {
+  let var = |name: &str| std::env::var_os(name).map(PathBuf::from);
+  let windows = var("SystemRoot").unwrap_or_else(|| PathBuf::from(r"C:\Windows"));
+  // `SystemDrive` is just `C:`, which names the drive's current directory.
+  let drive = std::env::var("SystemDrive").unwrap_or_else(|_| "C:".to_string());
+  let drive = PathBuf::from(format!("{}\\", drive));
+  let program_data = var("ProgramData").unwrap_or_else(|| drive.join("ProgramData"));
+  let mut error_reports = vec![
+    program_data.join(r"Microsoft\Windows\WER\ReportQueue"),
+    program_data.join(r"Microsoft\Windows\WER\ReportArchive"),
+  ];
+  if let Some(local) = var("LOCALAPPDATA") {
+    error_reports.push(local.join(r"Microsoft\Windows\WER\ReportQueue"));
+    error_reports.push(local.join(r"Microsoft\Windows\WER\ReportArchive"));
+  }
+  let network_service = windows.join(r"ServiceProfiles\NetworkService\AppData\Local");
+  vec![
+    (CleanableKind::UserTemp, var("TEMP").into_iter().collect()),
+    (CleanableKind::WindowsTemp, vec![windows.join("Temp")]),
+    (CleanableKind::UpdateDownloads, vec![windows.join(r"SoftwareDistribution\Download")]),
+    (CleanableKind::WindowsOld, vec![drive.join("Windows.old")]),
+    (
+      CleanableKind::DeliveryOptimization,
+      vec![
+        network_service.join(r"Microsoft\Windows\DeliveryOptimization\Cache"),
+        windows.join(r"SoftwareDistribution\DeliveryOptimization"),
+      ],
+    ),
+    (
+      CleanableKind::OldDrivers,
+      superseded_drivers(&windows.join(r"System32\DriverStore\FileRepository")),
+    ),
+    (CleanableKind::ErrorReports, error_reports),
+  ]
+}
+
+fn measure(kind: CleanableKind, dirs: Vec<PathBuf>) -> CleanableItem {
+  let dirs: Vec<PathBuf> = dirs.into_iter().filter(|dir| dir.is_dir()).collect();
+  let (mut bytes, mut files) = (0, 0);
+  for dir in &dirs {
+    let (dir_bytes, dir_files) = dir_usage(dir);
+    bytes += dir_bytes;
+    files += dir_files;
+  }
+  CleanableItem {
+    kind,
+    paths: dirs.iter().map(|dir| dir.to_string_lossy().to_string()).collect(),
+    bytes,
+    files,
+  }
+}
+
+/// Driver packages with a newer package of the same driver beside them.
+/// The store keeps each as `<inf name>_<arch>_<hash>`; `pnputil /delete-driver`
+/// removes the old ones once no device uses them.
+fn superseded_drivers(repository: &Path) -> Vec<PathBuf> {
+  let mut by_driver: HashMap<String, Vec<(PathBuf, std::time::SystemTime)>> = HashMap::new();
+  for package in subdirs(repository) {
+    let name = display_name(&package).to_lowercase();
+    let Some((driver, _hash)) = name.rsplit_once('_') else {
+      continue;
+    };
+    let modified = std::fs::metadata(&package).and_then(|md| md.modified());
+    let modified = modified.unwrap_or(std::time::UNIX_EPOCH);
+    by_driver.entry(driver.to_string()).or_default().push((package, modified));
+  }
+  by_driver
+    .into_values()
+    .flat_map(|mut packages| {
+      packages.sort_by_key(|(_, modified)| std::cmp::Reverse(*modified));
+      packages.into_iter().skip(1).map(|(package, _)| package)
+    })
+    .collect()
+}
@@ -0,0 +1,810 @@
+//! Google Drive and OneDrive, listed through their own APIs (Drive v3 and
+//! Microsoft Graph) once the user has signed in from the browser. Files are
+//! sized by what they count against the quota, so the tree shows what's
+//! filling it, trash and recycle bin included.
+//!
+//! Sign-in is OAuth with PKCE, redirected back to a port on 127.0.0.1. The
+//! app has to be registered with each provider; its client id is given by
+//! the caller or built in through `SPACE_USAGE_GOOGLE_CLIENT_ID` (with
+//! `SPACE_USAGE_GOOGLE_CLIENT_SECRET`) and `SPACE_USAGE_MICROSOFT_CLIENT_ID`.
+//! Only the refresh token is kept, in the OS keyring.
+
+use super::{
+  blocking, credentials, empty_dir_node,
+  http::{self, pause, percent_decode, percent_encode},
+  maybe_emit_progress,
+  object_tree::{self, parse_time, Object, ObjectTree},
+  spawn_scan_from, ScanContext, ScanErrorKind, ScanManager, ScanNode, ScanOptions, ScanSource,
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use serde::{
+  de::{DeserializeOwned, IgnoredAny},
+  Deserialize, Serialize,
+};
+use sha2::{Digest, Sha256};
+use std::{
+  collections::HashMap,
+  io::{Read, Write},
+  net::{TcpListener, TcpStream},
+  path::{Path, PathBuf},
+  process::Command,
+  sync::atomic::Ordering,
+  time::{Duration, Instant},
+};
+use tauri::{AppHandle, State};
+use ureq::Agent;
+use uuid::Uuid;
+
+const DRIVE_API: &str = "https://www.googleapis.com/drive/v3";
+const GRAPH_API: &str = "https://graph.microsoft.com/v1.0";
+const FOLDER_TYPE: &str = "application/vnd.google-apps.folder";
+
+/// How long the user has to sign in once the browser is open.
+const SIGN_IN_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Times a busy or rate-limited API is waited on for one page.
+const MAX_RETRIES: u32 = 5;
+
+/// Where trashed files are shown, as the folders they were in are gone.
+const TRASH: &str = "Trash";
+
+/// Where files are shown whose folder isn't listed: one someone else owns,
+/// or Drive's backups of computers.
+const ELSEWHERE: &str = "Elsewhere";
+
+/// Folders nested deeper than this are taken for a loop in the listing.
+const MAX_NESTING: usize = 1000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CloudProvider {
+  GoogleDrive,
+  OneDrive,
+}
+
+/// How the provider knows the app. Google wants the secret of a desktop
+/// client as well, though it can't be kept secret; Microsoft's public
+/// clients have none.
+#[derive(Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct OAuthClient {
+  pub client_id: Option<String>,
+  pub client_secret: Option<String>,
+}
+
+/// A drive that's been signed in to.
+#[derive(Clone, Serialize)]
+pub struct CloudDrive {
+  pub provider: CloudProvider,
+  /// The email or name of the account, to tell them apart.
+  pub account: String,
+}
+
+/// A sign-in as it's kept in the keyring.
+#[derive(Clone, Serialize, Deserialize)]
+struct Login {
+  client_id: String,
+  client_secret: Option<String>,
+  refresh_token: String,
+  account: String,
+}
+
+/// A drive to list, with the sign-in to it.
+pub(super) struct Drive {
+  provider: CloudProvider,
+  login: Login,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+  #[serde(default)]
+  access_token: String,
+  refresh_token: Option<String>,
+  error: Option<String>,
+  error_description: Option<String>,
+}
+
+impl CloudProvider {
+  const ALL: [CloudProvider; 2] = [CloudProvider::GoogleDrive, CloudProvider::OneDrive];
+
+  fn name(self) -> &'static str {
+    match self {
+      CloudProvider::GoogleDrive => "Google Drive",
+      CloudProvider::OneDrive => "OneDrive",
+    }
+  }
+
+  /// The authorization and token endpoints, and the access asked for.
+  fn endpoints(self) -> (&'static str, &'static str, &'static str) {
+    match self {
+      CloudProvider::GoogleDrive => (
+        "https://accounts.google.com/o/oauth2/v2/auth",
+        "https://oauth2.googleapis.com/token",
+        "https://www.googleapis.com/auth/drive.metadata.readonly",
+      ),
+      CloudProvider::OneDrive => (
+        "https://login.microsoftonline.com/common/oauth2/v2.0/authorize",
+        "https://login.microsoftonline.com/common/oauth2/v2.0/token",
+        "Files.Read offline_access",
+      ),
+    }
+  }
+
+  /// The client this build of the app was registered as, if any.
+  fn built_in_client(self) -> OAuthClient {
+    let (id, secret) = match self {
+      CloudProvider::GoogleDrive => (
+        option_env!("SPACE_USAGE_GOOGLE_CLIENT_ID"),
+        option_env!("SPACE_USAGE_GOOGLE_CLIENT_SECRET"),
+      ),
+      CloudProvider::OneDrive => (option_env!("SPACE_USAGE_MICROSOFT_CLIENT_ID"), None),
+    };
+    OAuthClient {
+      client_id: id.map(String::from),
+      client_secret: secret.map(String::from),
+    }
+  }
+
+  /// The keyring account of its sign-in.
+  fn account(self) -> &'static str {
+    match self {
+      CloudProvider::GoogleDrive => "cloud://google-drive",
+      CloudProvider::OneDrive => "cloud://onedrive",
+    }
+  }
+
+  /// What scans of it are shown under, before the account.
+  fn scheme(self) -> &'static str {
+    match self {
+      CloudProvider::GoogleDrive => "gdrive://",
+      CloudProvider::OneDrive => "onedrive://",
+    }
+  }
+}
+
+fn load_login(provider: CloudProvider) -> Option<Login> {
+  serde_json::from_slice(&credentials::load_secret(provider.account())?).ok()
+}
+
+fn save_login(provider: CloudProvider, login: &Login) -> Result<(), String> {
+  let bytes = serde_json::to_vec(login).map_err(|e| e.to_string())?;
+  credentials::save_secret(provider.account(), &bytes)
+}
+
+/// `pairs` as a query string or form body.
+fn form(pairs: &[(&str, &str)]) -> String {
+  let pairs: Vec<String> =
+    pairs.iter().map(|(key, value)| format!("{}={}", key, percent_encode(value))).collect();
+  pairs.join("&")
+}
+
+/// Opens `url` in the default browser.
+fn open_browser(url: &str) -> Result<(), String> {
+  // Not `cmd /C start` on Windows, which would split the query at its `&`s.
+  let (program, args): (&str, &[&str]) = if cfg!(windows) {
+    ("rundll32", &["url.dll,FileProtocolHandler"])
+  } else if cfg!(target_os = "macos") {
+    ("open", &[])
+  } else {
+    ("xdg-open", &[])
+  };
+  let mut command = Command::new(program);
+  command.args(args).arg(url);
+  #[cfg(windows)]
+  {
+    use std::os::windows::process::CommandExt;
+    const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+    command.creation_flags(CREATE_NO_WINDOW);
+  }
+  let mut child = command.spawn().map_err(|e| format!("Opening the browser: {}", e))?;
+  // Some openers stay until the browser closes; they're reaped whenever.
+  std::thread::spawn(move || child.wait());
+  Ok(())
+}
+
+/// Signs in to `provider` in the browser and trades the code it comes back
+/// with for tokens.
+fn sign_in(
+  provider: CloudProvider,
+  client_id: &str,
+  client_secret: Option<&str>,
+) -> Result<TokenResponse, String> {
+  let (authorize, token_url, scope) = provider.endpoints();
+  let listener =
+    TcpListener::bind("127.0.0.1:0").map_err(|e| format!("Waiting for the sign-in: {}", e))?;
+  let port = listener.local_addr().map_err(|e| e.to_string())?.port();
+  let redirect = format!("http://127.0.0.1:{}", port);
+  let verifier = format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple());
+  let challenge = URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()));
+  let state = Uuid::new_v4().simple().to_string();
+  let mut query = vec![
+    ("client_id", client_id),
+    ("redirect_uri", &redirect),
+    ("response_type", "code"),
+    ("scope", scope),
+    ("code_challenge", &challenge),
+    ("code_challenge_method", "S256"),
+    ("state", &state),
+  ];
+  if provider == CloudProvider::GoogleDrive {
+    // Otherwise Google only hands out a refresh token the first time.
+    query.extend([("access_type", "offline"), ("prompt", "consent")]);
+  }
+  open_browser(&format!("{}?{}", authorize, form(&query)))?;
+  let code = wait_for_code(&listener, &state)?;
+  let mut body = vec![
+    ("grant_type", "authorization_code"),
+    ("code", &code),
+    ("redirect_uri", &redirect),
+    ("client_id", client_id),
+    ("code_verifier", &verifier),
+  ];
+  if let Some(secret) = client_secret {
+    body.push(("client_secret", secret));
+  }
+  request_token(&http::agent(), token_url, &body)
+}
+
+/// Waits for the browser to come back to `listener` with the code of the
+/// sign-in, and tells the user they can close it.
+fn wait_for_code(listener: &TcpListener, state: &str) -> Result<String, String> {
+  listener.set_nonblocking(true).map_err(|e| e.to_string())?;
+  let deadline = Instant::now() + SIGN_IN_TIMEOUT;
+  while Instant::now() < deadline {
+    let mut stream = match listener.accept() {
+      Ok((stream, _)) => stream,
+      Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+        std::thread::sleep(Duration::from_millis(200));
+        continue;
+      }
+      Err(e) => return Err(e.to_string()),
+    };
+    // The browser may ask for a favicon, or something else may knock.
+    let Some(query) = read_query(&mut stream) else {
+      respond(&mut stream, "404 Not Found", "");
+      continue;
+    };
+    let params: HashMap<&str, String> = query
+      .split('&')
+      .filter_map(|pair| pair.split_once('='))
+      .map(|(key, value)| (key, percent_decode(&value.replace('+', " "))))
+      .collect();
+    if params.get("state").map(String::as_str) != Some(state) {
+      respond(&mut stream, "404 Not Found", "");
+      continue;
+    }
+    let result = match params.get("code") {
+      Some(code) => Ok(code.clone()),
+      None => {
+        let reason = params.get("error_description").or_else(|| params.get("error"));
+        Err(format!("The sign-in was refused: {}", reason.map_or("no reason given", |r| r)))
+      }
+    };
+    let page = match result {
+      Ok(_) => "Signed in. You can close this tab and go back to Space Usage.",
+      Err(_) => "The sign-in didn't go through. You can close this tab.",
+    };
+    respond(&mut stream, "200 OK", page);
+    return result;
+  }
+  Err("The sign-in timed out".to_string())
+}
+
+/// The query of the request on `stream`, `code=…&state=…`.
+fn read_query(stream: &mut TcpStream) -> Option<String> {
+  stream.set_nonblocking(false).ok()?;
+  stream.set_read_timeout(Some(Duration::from_secs(5))).ok()?;
+  let mut head = Vec::new();
+  let mut buffer = [0; 4096];
+  while !head.windows(4).any(|end| end == b"\r\n\r\n") && head.len() < 64 * 1024 {
+    match stream.read(&mut buffer).ok()? {
+      0 => break,
+      read => head.extend_from_slice(&buffer[..read]),
+    }
+  }
+  let head = String::from_utf8_lossy(&head);
+  // `GET /?code=…&state=… HTTP/1.1`
+  let target = head.lines().next()?.split(' ').nth(1)?;
+  Some(target.split_once('?')?.1.to_string())
+}
+
+fn respond(stream: &mut TcpStream, status: &str, text: &str) {
+  let body =
+    format!("<!doctype html><meta charset=\"utf-8\"><title>Space Usage</title><p>{}", text);
+  let _ = write!(
+    stream,
+    "HTTP/1.1 {}\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\n\
+     Connection: close\r\n\r\n{}",
+    status,
+    body.len(),
+    body
+  );
+}
+
+/// POSTs `body` to a token endpoint.
+fn request_token(agent: &Agent, url: &str, body: &[(&str, &str)]) -> Result<TokenResponse, String> {
+  let mut response = agent.post(url).send_form(body.iter().copied()).map_err(|e| e.to_string())?;
+  let status = response.status().as_u16();
+  let text = response.body_mut().read_to_string().map_err(|e| e.to_string())?;
+  let token: TokenResponse = serde_json::from_str(&text)
+    .map_err(|_| format!("HTTP {} from the sign-in: {}", status, text.trim()))?;
+  if let Some(error) = token.error {
+    return Err(match token.error_description {
+      Some(description) => format!("{}: {}", error, description),
+      None => error,
+    });
+  }
+  if token.access_token.is_empty() {
+    return Err(format!("HTTP {} from the sign-in, without a token", status));
+  }
+  Ok(token)
+}
+
+/// A fresh access token for `drive`. Microsoft rolls the refresh token over
+/// each time, so the new one replaces what's saved.
+fn access_token(agent: &Agent, drive: &Drive) -> Result<String, String> {
+  let login = &drive.login;
+  let (_, token_url, scope) = drive.provider.endpoints();
+  let mut body = vec![
+    ("grant_type", "refresh_token"),
+    ("refresh_token", login.refresh_token.as_str()),
+    ("client_id", login.client_id.as_str()),
+  ];
+  if let Some(secret) = &login.client_secret {
+    body.push(("client_secret", secret));
+  }
+  if drive.provider == CloudProvider::OneDrive {
+    body.push(("scope", scope));
+  }
+  let token = request_token(agent, token_url, &body)
+    .map_err(|e| format!("Sign in to {} again: {}", drive.provider.name(), e))?;
+  if let Some(refresh_token) = token.refresh_token.filter(|t| *t != login.refresh_token) {
+    let login = Login {
+      refresh_token,
+      ..login.clone()
+    };
+    let _ = save_login(drive.provider, &login);
+  }
+  Ok(token.access_token)
+}
+
+/// The message in an API's JSON error, `{"error": {"message": …}}` for
+/// both, or the start of the body.
+fn api_error(body: &str) -> String {
+  serde_json::from_str::<serde_json::Value>(body)
+    .ok()
+    .and_then(|value| Some(value.get("error")?.get("message")?.as_str()?.to_string()))
+    .unwrap_or_else(|| body.trim().chars().take(200).collect())
+}
+
+/// GETs `url` and reads the JSON it answers with, waiting out rate limits
+/// as long as the API asks to.
+fn get_json<T: DeserializeOwned>(
+  ctx: &ScanContext,
+  agent: &Agent,
+  url: &str,
+  token: &str,
+) -> Result<T, (ScanErrorKind, String)> {
+  let mut retries = 0;
+  loop {
+    let mut response = agent
+      .get(url)
+      .header("Authorization", format!("Bearer {}", token))
+      .call()
+      .map_err(|e| match e {
+        ureq::Error::Timeout(_) => (ScanErrorKind::IoTimeout, e.to_string()),
+        _ => (ScanErrorKind::Other, e.to_string()),
+      })?;
+    let status = response.status().as_u16();
+    let retry_after = response
+      .headers()
+      .get("retry-after")
+      .and_then(|value| value.to_str().ok()?.trim().parse::<u64>().ok());
+    let body = response
+      .body_mut()
+      .with_config()
+      .limit(64 * 1024 * 1024)
+      .read_to_string()
+      .map_err(|e| (ScanErrorKind::Other, e.to_string()))?;
+    // Drive says 403 when rate limited, telling it apart only in the body:
+    // `rateLimitExceeded` or `userRateLimitExceeded`.
+    let limited = matches!(status, 429 | 500 | 502 | 503 | 504)
+      || (status == 403 && body.to_lowercase().contains("ratelimitexceeded"));
+    if limited && retries < MAX_RETRIES && !ctx.cancelled() {
+      let wait = retry_after.unwrap_or(1 << retries);
+      retries += 1;
+      pause(ctx, Duration::from_secs(wait.min(60)));
+      continue;
+    }
+    return match status {
+      200 => serde_json::from_str(&body)
+        .map_err(|e| (ScanErrorKind::Other, format!("unreadable answer: {}", e))),
+      401 | 403 => Err((
+        ScanErrorKind::PermissionDenied,
+        format!("HTTP {}: {}", status, api_error(&body)),
+      )),
+      404 => Err((ScanErrorKind::NotFound, format!("HTTP 404: {}", api_error(&body)))),
+      _ => Err((ScanErrorKind::Other, format!("HTTP {}: {}", status, api_error(&body)))),
+    };
+  }
+}
+
+/// A file or folder of a drive.
+struct Item {
+  name: String,
+  /// The id of the folder it's in.
+  parent: Option<String>,
+  folder: bool,
+  size: u64,
+  mtime: Option<i64>,
+  trashed: bool,
+}
+
+/// Everything in a drive, by id.
+#[derive(Default)]
+struct Listing {
+  root: String,
+  items: HashMap<String, Item>,
+}
+
+/// Counts `item` into the scan's progress.
+fn count(ctx: &ScanContext, root: &Path, item: &Item) {
+  ctx.control.scanned_entries.fetch_add(1, Ordering::Relaxed);
+  if !item.folder {
+    ctx.control.scanned_bytes.fetch_add(item.size, Ordering::Relaxed);
+  }
+  maybe_emit_progress(ctx, &root.join(&item.name));
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DriveFile {
+  id: String,
+  name: String,
+  mime_type: String,
+  #[serde(default)]
+  parents: Vec<String>,
+  /// What it counts against the quota, as a string; Docs and Sheets count
+  /// nothing.
+  quota_bytes_used: Option<String>,
+  modified_time: Option<String>,
+  #[serde(default)]
+  trashed: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FileList {
+  #[serde(default)]
+  files: Vec<DriveFile>,
+  next_page_token: Option<String>,
+}
+
+/// The files the user owns in Google Drive; what others share with them
+/// counts against their own quota.
+fn list_google_drive(
+  ctx: &ScanContext,
+  agent: &Agent,
+  token: &str,
+  root: &Path,
+) -> Result<Listing, (ScanErrorKind, String)> {
+  let about: serde_json::Value =
+    get_json(ctx, agent, &format!("{}/about?fields=storageQuota", DRIVE_API), token)?;
+  let in_drive = about["storageQuota"]["usageInDrive"].as_str().and_then(|n| n.parse().ok());
+  if let Some(bytes) = in_drive {
+    let _ = ctx.control.expected_bytes.set(bytes);
+  }
+  let folder: serde_json::Value =
+    get_json(ctx, agent, &format!("{}/files/root?fields=id", DRIVE_API), token)?;
+  let mut listing = Listing {
+    root: folder["id"].as_str().unwrap_or_default().to_string(),
+    items: HashMap::new(),
+  };
+  let fields = "nextPageToken,files(id,name,mimeType,parents,quotaBytesUsed,modifiedTime,trashed)";
+  let first = format!(
+    "{}/files?q={}&fields={}&pageSize=1000&spaces=drive",
+    DRIVE_API,
+    percent_encode("'me' in owners"),
+    percent_encode(fields)
+  );
+  let mut url = first.clone();
+  while !ctx.cancelled() {
+    let page: FileList = match get_json(ctx, agent, &url, token) {
+      Ok(page) => page,
+      Err((kind, message)) => {
+        // What was listed so far is still shown.
+        ctx.record_error(root, kind, None, &message);
+        break;
+      }
+    };
+    for file in page.files {
+      let item = Item {
+        name: file.name,
+        parent: file.parents.into_iter().next(),
+        folder: file.mime_type == FOLDER_TYPE,
+        size: file.quota_bytes_used.and_then(|n| n.parse().ok()).unwrap_or(0),
+        mtime: file.modified_time.as_deref().and_then(parse_time),
+        trashed: file.trashed,
+      };
+      count(ctx, root, &item);
+      listing.items.insert(file.id, item);
+    }
+    match page.next_page_token {
+      Some(page_token) => url = format!("{}&pageToken={}", first, percent_encode(&page_token)),
+      None => break,
+    }
+  }
+  Ok(listing)
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DriveItem {
+  id: String,
+  #[serde(default)]
+  name: String,
+  #[serde(default)]
+  size: u64,
+  parent_reference: Option<ParentReference>,
+  folder: Option<IgnoredAny>,
+  /// OneNote notebooks and the like, whose contents are listed as well.
+  package: Option<IgnoredAny>,
+  root: Option<IgnoredAny>,
+  deleted: Option<IgnoredAny>,
+  last_modified_date_time: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ParentReference {
+  id: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct DeltaPage {
+  #[serde(default)]
+  value: Vec<DriveItem>,
+  #[serde(rename = "@odata.nextLink")]
+  next_link: Option<String>,
+}
+
+/// Everything in the user's OneDrive, from a delta query, which lists a
+/// whole drive in pages of hundreds where walking it folder by folder would
+/// take a request each. The recycle bin can't be listed, so it stands as
+/// one file of what it holds.
+fn list_onedrive(
+  ctx: &ScanContext,
+  agent: &Agent,
+  token: &str,
+  root: &Path,
+) -> Result<Listing, (ScanErrorKind, String)> {
+  let drive: serde_json::Value =
+    get_json(ctx, agent, &format!("{}/me/drive?$select=quota", GRAPH_API), token)?;
+  if let Some(bytes) = drive["quota"]["used"].as_u64() {
+    let _ = ctx.control.expected_bytes.set(bytes);
+  }
+  let select = "id,name,size,parentReference,folder,package,root,deleted,lastModifiedDateTime";
+  let mut url = format!("{}/me/drive/root/delta?$select={}", GRAPH_API, select);
+  let mut listing = Listing::default();
+  while !ctx.cancelled() {
+    let page: DeltaPage = match get_json(ctx, agent, &url, token) {
+      Ok(page) => page,
+      Err((kind, message)) => {
+        ctx.record_error(root, kind, None, &message);
+        break;
+      }
+    };
+    for entry in page.value.into_iter().filter(|entry| entry.deleted.is_none()) {
+      if entry.root.is_some() {
+        listing.root = entry.id;
+        continue;
+      }
+      let folder = entry.folder.is_some() || entry.package.is_some();
+      let item = Item {
+        name: entry.name,
+        parent: entry.parent_reference.and_then(|parent| parent.id),
+        folder,
+        // A folder's size is what's in it, which is listed too.
+        size: if folder { 0 } else { entry.size },
+        mtime: entry.last_modified_date_time.as_deref().and_then(parse_time),
+        trashed: false,
+      };
+      count(ctx, root, &item);
+      listing.items.insert(entry.id, item);
+    }
+    match page.next_link {
+      Some(next_link) => url = next_link,
+      None => break,
+    }
+  }
+  if let Some(bytes) = drive["quota"]["deleted"].as_u64().filter(|bytes| *bytes > 0) {
+    let bin = Item {
+      name: "Recycle bin".to_string(),
+      parent: Some(listing.root.clone()),
+      folder: false,
+      size: bytes,
+      mtime: None,
+      trashed: false,
+    };
+    listing.items.insert(String::new(), bin);
+  }
+  Ok(listing)
+}
+
+/// The paths of a listing's folders, worked out from their parents.
+struct Paths<'a> {
+  listing: &'a Listing,
+  known: HashMap<&'a str, String>,
+}
+
+impl<'a> Paths<'a> {
+  /// The path of the folder with `id` under the drive's root.
+  fn folder(&mut self, id: &'a str, nesting: usize) -> String {
+    if id == self.listing.root {
+      return String::new();
+    }
+    if let Some(path) = self.known.get(id) {
+      return path.clone();
+    }
+    let path = match self.listing.items.get(id) {
+      Some(folder) if nesting < MAX_NESTING => {
+        format!("{}/{}", self.parent(folder, nesting + 1), folder.name.replace('/', "∕"))
+      }
+      _ => ELSEWHERE.to_string(),
+    };
+    self.known.insert(id, path.clone());
+    path
+  }
+
+  /// The path of the folder `item` is in.
+  fn parent(&mut self, item: &'a Item, nesting: usize) -> String {
+    let parent = item.parent.as_deref();
+    let in_trashed = parent.and_then(|id| self.listing.items.get(id)).is_some_and(|p| p.trashed);
+    match parent {
+      _ if item.trashed && !in_trashed => TRASH.to_string(),
+      Some(id) => self.folder(id, nesting),
+      None => ELSEWHERE.to_string(),
+    }
+  }
+}
+
+/// The tree of `listing`, with trashed items in a folder of their own.
+fn tree_of(listing: &Listing) -> ObjectTree {
+  let mut paths = Paths {
+    listing,
+    known: HashMap::new(),
+  };
+  let mut tree = ObjectTree::default();
+  for item in listing.items.values() {
+    let relative = format!("{}/{}", paths.parent(item, 0), item.name.replace('/', "∕"));
+    if item.folder {
+      tree.folder(&relative);
+      continue;
+    }
+    let object = Object {
+      key: String::new(),
+      size: item.size,
+      mtime: item.mtime,
+      class: None,
+    };
+    tree.insert(&relative, object);
+  }
+  tree
+}
+
+/// Lists `drive` and builds its tree under `root`, the `gdrive://account`
+/// it's shown as. A failed sign-in or page is recorded like a local error.
+pub(super) fn scan(ctx: &ScanContext, drive: &Drive, root: &Path) -> ScanNode {
+  let agent = http::agent();
+  let token = match access_token(&agent, drive) {
+    Ok(token) => token,
+    Err(e) => {
+      ctx.record_error(root, ScanErrorKind::PermissionDenied, None, &e);
+      return empty_dir_node(root);
+    }
+  };
+  let listed = match drive.provider {
+    CloudProvider::GoogleDrive => list_google_drive(ctx, &agent, &token, root),
+    CloudProvider::OneDrive => list_onedrive(ctx, &agent, &token, root),
+  };
+  match listed {
+    Ok(listing) => object_tree::build(ctx, tree_of(&listing), &root.to_string_lossy()).0,
+    Err((kind, message)) => {
+      ctx.record_error(root, kind, None, &message);
+      empty_dir_node(root)
+    }
+  }
+}
+
+/// The email, or else the name, of who signed in.
+fn account_name(agent: &Agent, provider: CloudProvider, token: &str) -> Option<String> {
+  let url = match provider {
+    CloudProvider::GoogleDrive => format!("{}/about?fields=user", DRIVE_API),
+    CloudProvider::OneDrive => format!("{}/me/drive?$select=owner", GRAPH_API),
+  };
+  let mut response =
+    agent.get(&url).header("Authorization", format!("Bearer {}", token)).call().ok()?;
+  let body: serde_json::Value =
+    serde_json::from_str(&response.body_mut().read_to_string().ok()?).ok()?;
+  let user = match provider {
+    CloudProvider::GoogleDrive => &body["user"],
+    CloudProvider::OneDrive => &body["owner"]["user"],
+  };
+  let keys = ["emailAddress", "email", "displayName"];
+  keys.iter().find_map(|key| user[key].as_str()).map(String::from)
+}
+
+/// The drives signed in to.
+#[tauri::command]
+pub async fn list_cloud_drives() -> Result<Vec<CloudDrive>, String> {
+  let drives = CloudProvider::ALL
+    .into_iter()
+    .filter_map(|provider| {
+      let login = load_login(provider)?;
+      Some(CloudDrive {
+        provider,
+        account: login.account,
+      })
+    })
+    .collect();
+  Ok(drives)
+}
+
+/// Signs in to `provider` in the default browser and keeps the sign-in for
+/// scans. Returns once the user has allowed access, or gives up after five
+/// minutes. `client` overrides the client the app was built with.
+#[tauri::command]
+pub async fn connect_cloud_drive(
+  provider: CloudProvider,
+  client: Option<OAuthClient>,
+) -> Result<CloudDrive, String> {
+  let given = client.unwrap_or_default();
+  let built_in = provider.built_in_client();
+  let filled =
+    |value: Option<String>| value.map(|v| v.trim().to_string()).filter(|v| !v.is_empty());
+  let client_id = filled(given.client_id).or(built_in.client_id).ok_or_else(|| {
+    format!("This build has no client id for {}; enter the one you registered", provider.name())
+  })?;
+  let client_secret = filled(given.client_secret).or(built_in.client_secret);
+  // Waiting on the browser can take minutes.
+  blocking(move || {
+    let token = sign_in(provider, &client_id, client_secret.as_deref())?;
+    let refresh_token =
+      token.refresh_token.ok_or_else(|| format!("{} gave no refresh token", provider.name()))?;
+    let account = account_name(&http::agent(), provider, &token.access_token)
+      .unwrap_or_else(|| provider.name().to_string());
+    let login = Login {
+      client_id,
+      client_secret,
+      refresh_token,
+      account: account.clone(),
+    };
+    save_login(provider, &login)?;
+    Ok(CloudDrive { provider, account })
+  })
+  .await
+}
+
+/// Forgets the sign-in to `provider`. Access stays granted on the
+/// provider's side until the user removes the app from their account.
+#[tauri::command]
+pub async fn disconnect_cloud_drive(provider: CloudProvider) -> Result<(), String> {
+  credentials::forget(provider.account())
+}
+
+/// Scans the drive signed in to at `provider`. Returns the scan id;
+/// progress and the tree arrive as for a local scan, under a root shown as
+/// `gdrive://account` or `onedrive://account`.
+#[tauri::command]
+pub async fn start_cloud_scan(
+  app: AppHandle,
+  state: State<'_, ScanManager>,
+  provider: CloudProvider,
+  options: Option<ScanOptions>,
+) -> Result<String, String> {
+  let login = load_login(provider)
+    .ok_or_else(|| format!("Sign in to {} before scanning it", provider.name()))?;
+  let mut options = options.unwrap_or_default();
+  options.same_filesystem = false;
+  let root = PathBuf::from(format!("{}{}", provider.scheme(), login.account));
+  spawn_scan_from(&app, &state, root, options, ScanSource::Cloud(Drive { provider, login }))
+}
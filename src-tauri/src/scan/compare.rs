@@ -0,0 +1,157 @@
+//! What changed between two scans of the same folder: a tree of the nodes
+//! that were added, removed, grew or shrank, each with its change in bytes.
+//! Either scan can be one still kept in memory or one from the history.
+//!
+//! Nodes are matched by name under matching parents. Past `max_depth` only
+//! totals were kept, so a change there shows on the deepest folder both
+//! scans have.
+
+use super::{
+  blocking,
+  history::{self, History},
+  NodeKind, ScanManager, ScanNode,
+};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, path::Path};
+use tauri::{AppHandle, Manager};
+
+/// A scan to compare: the id of one kept since it finished, or the id of
+/// one in the history.
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum ScanRef {
+  Live(String),
+  Saved(i64),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Change {
+  Added,
+  Removed,
+  Grown,
+  Shrunk,
+  /// The same size, though something below it may have changed.
+  Unchanged,
+}
+
+#[derive(Serialize)]
+pub struct DeltaNode {
+  pub name: String,
+  pub path: String,
+  pub kind: NodeKind,
+  pub change: Change,
+  pub before: u64,
+  pub after: u64,
+  /// `after - before`.
+  pub delta: i64,
+  /// The children that changed by at least the least change asked for,
+  /// largest change first.
+  #[serde(skip_serializing_if = "Vec::is_empty")]
+  pub children: Vec<DeltaNode>,
+}
+
+impl DeltaNode {
+  /// Whether it's worth showing: it changed by `min_delta` or more, or
+  /// something below it did.
+  fn matters(&self, min_delta: u64) -> bool {
+    !self.children.is_empty() || (self.delta != 0 && self.delta.unsigned_abs() >= min_delta)
+  }
+}
+
+/// The root of `scan` and its tree.
+fn tree(
+  app: &AppHandle,
+  state: &ScanManager,
+  history: &History,
+  scan: &ScanRef,
+) -> Result<(String, ScanNode), String> {
+  match scan {
+    ScanRef::Live(scan_id) => {
+      let results = state.results(scan_id)?;
+      Ok((results.root.clone(), results.tree.clone()))
+    }
+    ScanRef::Saved(id) => history::saved_tree(app, history, *id),
+  }
+}
+
+fn children_by_name(node: Option<&ScanNode>) -> HashMap<&str, &ScanNode> {
+  let children = node.and_then(|node| node.children.as_deref()).unwrap_or_default();
+  children.iter().map(|child| (child.name.as_str(), child)).collect()
+}
+
+/// The changes to `node`, at `path`, from `before` to `after`, which it's
+/// one of; the other is missing when it was added or removed.
+fn diff(
+  node: &ScanNode,
+  path: &Path,
+  before: Option<&ScanNode>,
+  after: Option<&ScanNode>,
+  min_delta: u64,
+) -> DeltaNode {
+  let before_size = before.map_or(0, |node| node.size);
+  let after_size = after.map_or(0, |node| node.size);
+  let change = match (before, after) {
+    (None, _) => Change::Added,
+    (_, None) => Change::Removed,
+    _ if after_size > before_size => Change::Grown,
+    _ if after_size < before_size => Change::Shrunk,
+    _ => Change::Unchanged,
+  };
+
+  let (old, new) = (children_by_name(before), children_by_name(after));
+  // An aggregate node stands for entries of its parent, and goes by its path.
+  let path_of = |child: &ScanNode| match child.kind {
+    NodeKind::Other => path.to_path_buf(),
+    _ => path.join(&child.name),
+  };
+  let changed = new.values().map(|&child| {
+    let before = old.get(child.name.as_str()).copied();
+    diff(child, &path_of(child), before, Some(child), min_delta)
+  });
+  let removed = old
+    .values()
+    .filter(|child| !new.contains_key(child.name.as_str()))
+    .map(|&child| diff(child, &path_of(child), Some(child), None, min_delta));
+  let mut children: Vec<DeltaNode> =
+    changed.chain(removed).filter(|child| child.matters(min_delta)).collect();
+  children.sort_by(|a, b| {
+    let by_change = b.delta.unsigned_abs().cmp(&a.delta.unsigned_abs());
+    by_change.then_with(|| a.name.cmp(&b.name))
+  });
+
+  DeltaNode {
+    name: node.name.clone(),
+    path: path.to_string_lossy().to_string(),
+    kind: node.kind.clone(),
+    change,
+    before: before_size,
+    after: after_size,
+    delta: after_size as i64 - before_size as i64,
+    children,
+  }
+}
+
+/// What changed from `scan_a` to `scan_b`, both of the same folder. Changes
+/// smaller than `min_delta` bytes are left out of the tree; the root is
+/// always there.
+#[tauri::command]
+pub async fn compare_scans(
+  app: AppHandle,
+  scan_a: ScanRef,
+  scan_b: ScanRef,
+  min_delta: Option<u64>,
+) -> Result<DeltaNode, String> {
+  // Either tree may have to be read from the history, and both are walked.
+  blocking(move || {
+    let (state, history) = (app.state::<ScanManager>(), app.state::<History>());
+    let (root_a, before) = tree(&app, &state, &history, &scan_a)?;
+    let (root_b, after) = tree(&app, &state, &history, &scan_b)?;
+    if root_a != root_b {
+      return Err(format!("{} and {} aren't the same folder", root_a, root_b));
+    }
+    let path = Path::new(&root_b);
+    Ok(diff(&after, path, Some(&before), Some(&after), min_delta.unwrap_or(0)))
+  })
+  .await
+}
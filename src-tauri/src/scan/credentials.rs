@@ -0,0 +1,38 @@
+//! Passwords for network storage, kept in the OS keyring: the Credential
+//! Manager on Windows, the Keychain on macOS and the Secret Service on
+//! Linux. Entries are filed under the app's name, one per account, like
+//! `smb://alice@nas`.
+
+use keyring::Entry;
+
+const SERVICE: &str = "space-usage";
+
+/// The password saved for `account`, if any.
+pub(super) fn load(account: &str) -> Option<String> {
+  Entry::new(SERVICE, account).ok()?.get_password().ok()
+}
+
+pub(super) fn save(account: &str, password: &str) -> Result<(), String> {
+  let entry = Entry::new(SERVICE, account).map_err(|e| e.to_string())?;
+  entry.set_password(password).map_err(|e| format!("Saving the password: {}", e))
+}
+
+/// Forgets the password of `account`; one that was never saved is fine.
+pub(super) fn forget(account: &str) -> Result<(), String> {
+  let entry = Entry::new(SERVICE, account).map_err(|e| e.to_string())?;
+  match entry.delete_credential() {
+    Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+    Err(e) => Err(e.to_string()),
+  }
+}
+
+/// A secret that isn't typed in, like a sign-in token, kept as bytes; on
+/// Windows that fits twice as much as a password does.
+pub(super) fn load_secret(account: &str) -> Option<Vec<u8>> {
+  Entry::new(SERVICE, account).ok()?.get_secret().ok()
+}
+
+pub(super) fn save_secret(account: &str, secret: &[u8]) -> Result<(), String> {
+  let entry = Entry::new(SERVICE, account).map_err(|e| e.to_string())?;
+  entry.set_secret(secret).map_err(|e| format!("Saving the sign-in: {}", e))
+}
@@ -0,0 +1,286 @@
+//! Looking inside disk image files, which a scan only sees as single large
+//! files. ISO 9660 images are read directly; other images (VHD, VHDX, IMG,
+//! DMG) are attached read-only through the OS, their volumes walked like any
+//! directory, and detached again.
+
+use super::{
+  blocking, command_output as run, dir_node, iso, EventSink, ScanContext, ScanControl, ScanFilters,
+  ScanNode, ScanOptions,
+};
+use std::{
+  path::{Path, PathBuf},
+  process::Command,
+  time::Instant,
+};
+
+/// Extensions of the images `inspect_disk_image` accepts.
+const IMAGE_EXTENSIONS: [&str; 5] = ["iso", "img", "vhd", "vhdx", "dmg"];
+
+/// The contents of the disk image at `path` as a tree under a node named
+/// after it. An image with several volumes gets a directory per volume.
+/// `options` shape the tree like a scan's; nothing in the image is changed.
+#[tauri::command]
+pub async fn inspect_disk_image(
+  path: String,
+  options: Option<ScanOptions>,
+) -> Result<ScanNode, String> {
+  blocking(move || {
+    let options = options.unwrap_or_default();
+    let image = PathBuf::from(&path);
+    let extension = image.extension().map(|ext| ext.to_string_lossy().to_lowercase());
+    if !extension.is_some_and(|ext| IMAGE_EXTENSIONS.contains(&ext.as_str())) {
+      return Err(format!("{}: not a disk image this app can read", path));
+    }
+    let mut file = std::fs::File::open(&image).map_err(|e| format!("{}: {}", path, e))?;
+    if iso::is_iso(&mut file) {
+      let node = walk(&image, &options, |ctx| iso::read(ctx, &image))?;
+      return node.map_err(|e| format!("{}: {}", path, e));
+    }
+    drop(file);
+
+    let attached = Attached::attach(&image)?;
+    if attached.volumes.is_empty() {
+      return Err(format!("{}: no volume of the image could be mounted", path));
+    }
+    let mut volumes = Vec::new();
+    for (i, volume) in attached.volumes.iter().enumerate() {
+      let mut node = walk(volume, &options, |ctx| super::scan_tree(ctx, volume))?;
+      node.name = match attached.volumes.len() {
+        1 => super::display_name(&image),
+        _ => format!("Volume {}", i + 1),
+      };
+      volumes.push(node);
+    }
+    if volumes.len() == 1 {
+      return Ok(volumes.remove(0));
+    }
+    walk(&image, &options, |ctx| dir_node(ctx, &image, volumes, 0))
+  })
+  .await
+}
+
+/// Runs `read` with a context for a walk under `root` that reports nothing.
+pub(super) fn walk<T>(
+  root: &Path,
+  options: &ScanOptions,
+  read: impl FnOnce(&ScanContext) -> T + Send,
+) -> Result<T, String>
+where
+  T: Send,
+{
+  let events = EventSink::Quiet;
+  let control = ScanControl::new(root.to_path_buf());
+  let _ = control.started.set(Instant::now());
+  let filters = ScanFilters::new(options, root)?;
+  let ctx = ScanContext::new(&events, "", options, &control, filters);
+  Ok(ctx.install(|| read(&ctx)))
+}
+
+/// A disk image attached read-only, with where its volumes are mounted.
+/// Dropping it detaches the image.
+struct Attached {
+  #[cfg_attr(not(windows), allow(dead_code))]
+  image: PathBuf,
+  volumes: Vec<PathBuf>,
+  // What detaching needs: the loop device on Linux, the disk on macOS.
+  #[cfg_attr(windows, allow(dead_code))]
+  device: String,
+  #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+  mounted: Vec<String>,
+}
+
+#[cfg(target_os = "linux")]
+impl Attached {
+  fn attach(image: &Path) -> Result<Self, String> {
+    let mut setup = Command::new("udisksctl");
+    setup.args(["loop-setup", "--no-user-interaction", "--read-only", "--file"]).arg(image);
+    // "Mapped file <image> as /dev/loop5."
+    let output = run(&mut setup)?;
+    let device = output
+      .rsplit(" as ")
+      .next()
+      .map(|device| device.trim().trim_end_matches('.').to_string())
+      .filter(|device| device.starts_with("/dev/"))
+      .ok_or_else(|| format!("Unexpected udisksctl output: {}", output.trim()))?;
+    let mut attached = Self {
+      image: image.to_path_buf(),
+      volumes: Vec::new(),
+      device,
+      mounted: Vec::new(),
+    };
+    for block in attached.blocks() {
+      // The desktop may have mounted it already, which fails the mount.
+      let mut mount = Command::new("udisksctl");
+      mount.args(["mount", "--no-user-interaction", "--options", "ro", "--block-device", &block]);
+      let mounted = run(&mut mount).is_ok();
+      if let Some(point) = mount_point(&block) {
+        attached.volumes.push(point);
+        if mounted {
+          attached.mounted.push(block);
+        }
+      }
+    }
+    Ok(attached)
+  }
+
+  /// The partitions of the loop device, or the device itself when it has none.
+  fn blocks(&self) -> Vec<String> {
+    let name = self.device.trim_start_matches("/dev/");
+    let mut partitions: Vec<String> = std::fs::read_dir(Path::new("/sys/block").join(name))
+      .into_iter()
+      .flatten()
+      .flatten()
+      .map(|entry| entry.file_name().to_string_lossy().to_string())
+      .filter(|entry| entry.starts_with(&format!("{}p", name)))
+      .map(|entry| format!("/dev/{}", entry))
+      .collect();
+    partitions.sort();
+    if partitions.is_empty() {
+      partitions.push(self.device.clone());
+    }
+    partitions
+  }
+
+  fn detach(&self) {
+    for block in &self.mounted {
+      let mut unmount = Command::new("udisksctl");
+      let _ = run(unmount.args(["unmount", "--no-user-interaction", "-b", block]));
+    }
+    let _ = run(Command::new("udisksctl").args([
+      "loop-delete",
+      "--no-user-interaction",
+      "-b",
+      &self.device,
+    ]));
+  }
+}
+
+/// Where `device` is mounted, from /proc/mounts.
+#[cfg(target_os = "linux")]
+fn mount_point(device: &str) -> Option<PathBuf> {
+  let mounts = std::fs::read_to_string("/proc/mounts").ok()?;
+  mounts.lines().find_map(|line| {
+    let mut fields = line.split(' ');
+    if fields.next()? != device {
+      return None;
+    }
+    Some(PathBuf::from(unescape_mount(fields.next()?)))
+  })
+}
+
+/// Undoes the octal escapes of spaces and the like in /proc/mounts.
+#[cfg(target_os = "linux")]
+fn unescape_mount(field: &str) -> String {
+  let bytes = field.as_bytes();
+  let mut out = Vec::with_capacity(bytes.len());
+  let mut i = 0;
+  while i < bytes.len() {
+    let octal = bytes.get(i + 1..i + 4).and_then(|digits| {
+      u8::from_str_radix(std::str::from_utf8(digits).ok()?, 8).ok()
+    });
+    match octal {
+      Some(byte) if bytes[i] == b'\\' => {
+        out.push(byte);
+        i += 4;
+      }
+      _ => {
+        out.push(bytes[i]);
+        i += 1;
+      }
+    }
+  }
+  String::from_utf8_lossy(&out).to_string()
+}
+
+#[cfg(target_os = "macos")]
+impl Attached {
+  fn attach(image: &Path) -> Result<Self, String> {
+    let mut attach = Command::new("hdiutil");
+    attach.args(["attach", "-readonly", "-nobrowse", "-noautoopen", "-noverify", "-plist"]);
+    let plist = run(attach.arg(image))?;
+    // Each system entity has a dev-entry, and a mount-point if it has a
+    // volume; the whole disk is the shortest dev-entry.
+    let device = plist_strings(&plist, "dev-entry")
+      .into_iter()
+      .min_by_key(|device| device.len())
+      .ok_or_else(|| "hdiutil attached no disk".to_string())?;
+    Ok(Self {
+      image: image.to_path_buf(),
+      volumes: plist_strings(&plist, "mount-point").into_iter().map(PathBuf::from).collect(),
+      device,
+      mounted: Vec::new(),
+    })
+  }
+
+  fn detach(&self) {
+    let _ = run(Command::new("hdiutil").args(["detach", "-force", &self.device]));
+  }
+}
+
+/// The string values of `key` anywhere in `plist`.
+#[cfg(target_os = "macos")]
+fn plist_strings(plist: &str, key: &str) -> Vec<String> {
+  let marker = format!("<key>{}</key>", key);
+  plist
+    .split(&marker)
+    .skip(1)
+    .filter_map(|rest| {
+      let rest = rest.trim_start().strip_prefix("<string>")?;
+      Some(rest[..rest.find("</string>")?].replace("&amp;", "&"))
+    })
+    .collect()
+}
+
+#[cfg(windows)]
+impl Attached {
+  fn attach(image: &Path) -> Result<Self, String> {
+    // The path goes through the environment to keep quoting out of the script.
+    let script = "$ErrorActionPreference = 'Stop'; \
+      Mount-DiskImage -ImagePath $env:SPACE_USAGE_IMAGE -Access ReadOnly -PassThru | \
+      Get-Disk | Get-Partition | Get-Volume | \
+      ForEach-Object { if ($_.DriveLetter) { \"$($_.DriveLetter):\\\" } else { $_.Path } }";
+    let mut attach = powershell(image, script);
+    let output = run(&mut attach)?;
+    let mut volumes: Vec<PathBuf> =
+      output.lines().map(str::trim).filter(|line| !line.is_empty()).map(PathBuf::from).collect();
+    volumes.sort();
+    Ok(Self {
+      image: image.to_path_buf(),
+      volumes,
+      device: String::new(),
+      mounted: Vec::new(),
+    })
+  }
+
+  fn detach(&self) {
+    let script = "Dismount-DiskImage -ImagePath $env:SPACE_USAGE_IMAGE | Out-Null";
+    let _ = run(&mut powershell(&self.image, script));
+  }
+}
+
+#[cfg(windows)]
+fn powershell(image: &Path, script: &str) -> Command {
+  use std::os::windows::process::CommandExt;
+  const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+  let mut command = Command::new("powershell");
+  command
+    .args(["-NoProfile", "-NonInteractive", "-Command", script])
+    .env("SPACE_USAGE_IMAGE", image)
+    .creation_flags(CREATE_NO_WINDOW);
+  command
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+impl Attached {
+  fn attach(image: &Path) -> Result<Self, String> {
+    Err(format!("{}: disk images can't be attached on this platform", image.to_string_lossy()))
+  }
+
+  fn detach(&self) {}
+}
+
+impl Drop for Attached {
+  fn drop(&mut self) {
+    self.detach();
+  }
+}
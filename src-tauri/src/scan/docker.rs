@@ -0,0 +1,253 @@
+//! What Docker and Podman keep in their storage, which a scan only sees as
+//! layer directories with opaque names, and pruning what no container uses.
+//! Both engines are asked through their command line clients.
+
+use super::{blocking, command_output, tool_command};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ContainerEngine {
+  Docker,
+  Podman,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ContainerStorage {
+  Images,
+  Containers,
+  Volumes,
+  BuildCache,
+}
+
+#[derive(Clone, Serialize)]
+pub struct StorageUsage {
+  pub kind: ContainerStorage,
+  pub count: u64,
+  /// In use by a container, so not prunable.
+  pub active: u64,
+  pub bytes: u64,
+  pub reclaimable: u64,
+}
+
+#[derive(Clone, Serialize)]
+pub struct EngineUsage {
+  pub engine: ContainerEngine,
+  /// Where the engine keeps its storage, for telling it apart in a scan.
+  pub root_dir: Option<String>,
+  pub usage: Vec<StorageUsage>,
+}
+
+impl ContainerEngine {
+  fn program(self) -> &'static str {
+    match self {
+      Self::Docker => "docker",
+      Self::Podman => "podman",
+    }
+  }
+
+  fn run(self, args: &[&str]) -> Result<String, String> {
+    let mut command = tool_command(self.program());
+    command_output(command.args(args)).map_err(|e| format!("{}: {}", self.program(), e))
+  }
+
+  fn usage(self) -> Result<EngineUsage, String> {
+    let output = self.run(&["system", "df", "--format", "{{json .}}"])?;
+    let usage = output
+      .lines()
+      .filter_map(|line| serde_json::from_str::<Value>(line).ok())
+      .filter_map(|row| storage_usage(&row))
+      .collect();
+    let root_field = match self {
+      Self::Docker => "{{.DockerRootDir}}",
+      Self::Podman => "{{.Store.GraphRoot}}",
+    };
+    let root_dir = self
+      .run(&["info", "--format", root_field])
+      .ok()
+      .map(|dir| dir.trim().to_string())
+      .filter(|dir| !dir.is_empty());
+    Ok(EngineUsage {
+      engine: self,
+      root_dir,
+      usage,
+    })
+  }
+
+  /// The command removing what no container uses of `kind`; images only
+  /// when dangling, unless `all_images`.
+  fn prune_args(
+    self,
+    kind: ContainerStorage,
+    all_images: bool,
+  ) -> Result<Vec<&'static str>, String> {
+    let mut args = match kind {
+      ContainerStorage::Images => vec!["image", "prune"],
+      ContainerStorage::Containers => vec!["container", "prune"],
+      ContainerStorage::Volumes => vec!["volume", "prune"],
+      ContainerStorage::BuildCache if self == Self::Docker => vec!["builder", "prune"],
+      ContainerStorage::BuildCache => {
+        return Err("podman: build cache is pruned with the images it belongs to".to_string())
+      }
+    };
+    if kind == ContainerStorage::Images && all_images {
+      args.push("--all");
+    }
+    args.push("--force");
+    Ok(args)
+  }
+}
+
+/// One row of `system df`, as both engines print it.
+fn storage_usage(row: &Value) -> Option<StorageUsage> {
+  let kind = match row.get("Type")?.as_str()? {
+    "Images" => ContainerStorage::Images,
+    "Containers" => ContainerStorage::Containers,
+    "Local Volumes" => ContainerStorage::Volumes,
+    "Build Cache" => ContainerStorage::BuildCache,
+    _ => return None,
+  };
+  // Docker prints every field as text; Podman has numbers for the counts,
+  // and raw byte counts besides the text ones.
+  let number = |names: &[&str]| {
+    names.iter().filter_map(|name| row.get(*name)).find_map(|value| match value {
+      Value::Number(n) => n.as_u64(),
+      Value::String(s) => s.parse().ok().or_else(|| parse_size(s)),
+      _ => None,
+    })
+  };
+  Some(StorageUsage {
+    kind,
+    count: number(&["TotalCount", "Total"]).unwrap_or(0),
+    active: number(&["Active"]).unwrap_or(0),
+    bytes: number(&["RawSize", "Size"]).unwrap_or(0),
+    reclaimable: number(&["RawReclaimable", "Reclaimable"]).unwrap_or(0),
+  })
+}
+
+/// Bytes in sizes like `1.2GB`, `512kB (40%)` or `3MiB`.
+fn parse_size(text: &str) -> Option<u64> {
+  let text = text.split(" (").next()?.trim();
+  let split = text.find(|c: char| c.is_ascii_alphabetic())?;
+  let (value, unit) = text.split_at(split);
+  let value: f64 = value.trim().parse().ok()?;
+  let scale: f64 = match unit.to_ascii_lowercase().as_str() {
+    "b" => 1.0,
+    "kb" => 1e3,
+    "mb" => 1e6,
+    "gb" => 1e9,
+    "tb" => 1e12,
+    "pb" => 1e15,
+    "kib" => 1024.0,
+    "mib" => 1024f64.powi(2),
+    "gib" => 1024f64.powi(3),
+    "tib" => 1024f64.powi(4),
+    "pib" => 1024f64.powi(5),
+    _ => return None,
+  };
+  Some((value * scale) as u64)
+}
+
+/// Storage usage of the container engines on this machine that answer. An
+/// engine that's installed but not running is an error only when no other
+/// engine answers.
+#[tauri::command]
+pub async fn get_container_usage() -> Result<Vec<EngineUsage>, String> {
+  blocking(|| {
+    let mut found = Vec::new();
+    let mut errors = Vec::new();
+    for engine in [ContainerEngine::Docker, ContainerEngine::Podman] {
+      match engine.usage() {
+        Ok(usage) => found.push(usage),
+        Err(e) => errors.push(e),
+      }
+    }
+    if found.is_empty() {
+      return Err(errors.join("; "));
+    }
+    Ok(found)
+  })
+  .await
+}
+
+/// Removes what no container of `engine` uses from each of `kinds`, and
+/// returns the engine's usage afterwards.
+#[tauri::command]
+pub async fn docker_prune(
+  engine: ContainerEngine,
+  kinds: Vec<ContainerStorage>,
+  all_images: bool,
+) -> Result<EngineUsage, String> {
+  blocking(move || {
+    for kind in kinds {
+      engine.run(&engine.prune_args(kind, all_images)?)?;
+    }
+    engine.usage()
+  })
+  .await
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn sizes() {
+    assert_eq!(parse_size("1.2GB"), Some(1_200_000_000));
+    assert_eq!(parse_size("512kB (40%)"), Some(512_000));
+    assert_eq!(parse_size("3MiB"), Some(3 << 20));
+    assert_eq!(parse_size("0B"), Some(0));
+    assert_eq!(parse_size(" 2 TB "), Some(2_000_000_000_000));
+  }
+
+  #[test]
+  fn malformed_sizes() {
+    assert_eq!(parse_size(""), None);
+    assert_eq!(parse_size("12"), None);
+    assert_eq!(parse_size("GB"), None);
+    assert_eq!(parse_size("1.5XB"), None);
+    assert_eq!(parse_size("1..5GB"), None);
+    assert_eq!(parse_size("(40%)"), None);
+  }
+
+  #[test]
+  fn docker_row() {
+    let row = serde_json::json!({
+      "Type": "Images",
+      "TotalCount": "3",
+      "Active": "1",
+      "Size": "1.2GB",
+      "Reclaimable": "512MB (42%)",
+    });
+    let usage = storage_usage(&row).unwrap();
+    assert!(usage.kind == ContainerStorage::Images);
+    assert_eq!((usage.count, usage.active), (3, 1));
+    assert_eq!((usage.bytes, usage.reclaimable), (1_200_000_000, 512_000_000));
+  }
+
+  #[test]
+  fn podman_row() {
+    let row = serde_json::json!({
+      "Type": "Local Volumes",
+      "Total": 2,
+      "Active": 0,
+      "Size": "1.5kB",
+      "RawSize": 1536,
+      "RawReclaimable": 1024,
+    });
+    let usage = storage_usage(&row).unwrap();
+    assert!(usage.kind == ContainerStorage::Volumes);
+    assert_eq!((usage.count, usage.bytes, usage.reclaimable), (2, 1536, 1024));
+  }
+
+  #[test]
+  fn unknown_rows() {
+    assert!(storage_usage(&serde_json::json!({ "Type": "Networks" })).is_none());
+    assert!(storage_usage(&serde_json::json!({ "Size": "1GB" })).is_none());
+    let garbled = serde_json::json!({ "Type": "Build Cache", "Size": [], "Active": "many" });
+    let usage = storage_usage(&garbled).unwrap();
+    assert_eq!((usage.count, usage.active, usage.bytes), (0, 0, 0));
+  }
+}
@@ -0,0 +1,435 @@
+//! Files and whole directory trees with the same contents, for scans run with
+//! `find_duplicates`. Once the walk is done, files are narrowed down in
+//! stages: those sharing their size with another get their ends hashed, and
+//! only those still alike are hashed whole. A directory's contents are then
+//! the names and hashes of everything below it, so copied trees match
+//! whatever they are named.
+
+use super::{is_online_only, ScanContext, ScanManager};
+use rayon::prelude::*;
+use serde::Serialize;
+use std::{
+  collections::{HashMap, HashSet},
+  io::{Read, Seek, SeekFrom},
+  path::{Path, PathBuf},
+  sync::atomic::{AtomicU64, Ordering},
+};
+use tauri::State;
+use xxhash_rust::xxh3::{xxh3_128, Xxh3};
+
+type Digest = u128;
+
+const READ_BUFFER: usize = 256 * 1024;
+/// Bytes hashed at each end of a file by the partial stage; files up to
+/// twice that are hashed whole right away.
+const PARTIAL_BYTES: u64 = 64 * 1024;
+/// Least time between two `duplicate_progress` events.
+const PROGRESS_INTERVAL_MS: u64 = 120;
+
+/// What the duplicate search is doing.
+#[derive(Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HashStage {
+  /// Grouping files by size, which reads nothing.
+  Size,
+  /// Hashing the first and last 64 KiB of same-size files.
+  Partial,
+  /// Hashing whole files whose ends matched.
+  Full,
+  /// Decoding images to compare how they look, for `find_similar_images`.
+  Images,
+}
+
+/// Sent as `duplicate_progress` while a `find_duplicates` or
+/// `find_similar_images` scan hashes files, after its walk is done.
+#[derive(Clone, Serialize)]
+pub struct DuplicateProgressEvent {
+  pub scan_id: String,
+  pub stage: HashStage,
+  /// Files and bytes of the stage done so far, out of its totals.
+  pub done_files: u64,
+  pub total_files: u64,
+  pub done_bytes: u64,
+  pub total_bytes: u64,
+}
+
+/// Progress through one stage, shared by its workers.
+pub(super) struct StageProgress<'c, 'a> {
+  ctx: &'c ScanContext<'a>,
+  stage: HashStage,
+  total_files: u64,
+  total_bytes: u64,
+  done_files: AtomicU64,
+  done_bytes: AtomicU64,
+  last_emit_ms: AtomicU64,
+}
+
+impl<'c, 'a> StageProgress<'c, 'a> {
+  /// Starts `stage` with the given totals and reports it.
+  pub(super) fn start(ctx: &'c ScanContext<'a>, stage: HashStage, files: u64, bytes: u64) -> Self {
+    let progress = Self {
+      ctx,
+      stage,
+      total_files: files,
+      total_bytes: bytes,
+      done_files: AtomicU64::new(0),
+      done_bytes: AtomicU64::new(0),
+      last_emit_ms: AtomicU64::new(0),
+    };
+    progress.emit();
+    progress
+  }
+
+  pub(super) fn add_bytes(&self, bytes: u64) {
+    self.done_bytes.fetch_add(bytes, Ordering::Relaxed);
+    let now_ms = self.ctx.control.elapsed_ms();
+    let last = self.last_emit_ms.load(Ordering::Relaxed);
+    if now_ms.saturating_sub(last) >= PROGRESS_INTERVAL_MS
+      && self
+        .last_emit_ms
+        .compare_exchange(last, now_ms, Ordering::Relaxed, Ordering::Relaxed)
+        .is_ok()
+    {
+      self.emit();
+    }
+  }
+
+  pub(super) fn add_file(&self) {
+    self.done_files.fetch_add(1, Ordering::Relaxed);
+  }
+
+  pub(super) fn emit(&self) {
+    let payload = DuplicateProgressEvent {
+      scan_id: self.ctx.scan_id.to_string(),
+      stage: self.stage,
+      done_files: self.done_files.load(Ordering::Relaxed),
+      total_files: self.total_files,
+      done_bytes: self.done_bytes.load(Ordering::Relaxed),
+      total_bytes: self.total_bytes,
+    };
+    self.ctx.events.emit("duplicate_progress", payload);
+  }
+}
+
+/// Files with the same size and contents.
+#[derive(Clone, Serialize)]
+pub struct DuplicateFiles {
+  /// Of each copy.
+  pub size: u64,
+  pub paths: Vec<String>,
+}
+
+/// Directories holding the same files under the same names, all the way down.
+#[derive(Clone, Serialize)]
+pub struct DuplicateDirs {
+  /// Of each copy.
+  pub size: u64,
+  pub files: u64,
+  pub paths: Vec<String>,
+}
+
+/// What a `find_duplicates` scan found, the most space to reclaim first.
+#[derive(Clone)]
+pub(super) struct Duplicates {
+  pub(super) files: Vec<DuplicateFiles>,
+  pub(super) dirs: Vec<DuplicateDirs>,
+}
+
+/// What is known about a directory's contents so far.
+#[derive(Default)]
+struct DirContents {
+  // (name, is_dir, size, digest) per entry.
+  entries: Vec<(Vec<u8>, bool, u64, Digest)>,
+  size: u64,
+  files: u64,
+  // Something in it has no copy anywhere, so it can't have one either.
+  unique: bool,
+}
+
+/// Groups the non-empty `files` counted under `root`, given with their sizes,
+/// hashing on `hash_threads` workers if the options ask for a number.
+pub(super) fn find(ctx: &ScanContext, root: &Path, files: Vec<(PathBuf, u64)>) -> Duplicates {
+  let sizing = StageProgress::start(ctx, HashStage::Size, files.len() as u64, 0);
+  let mut by_size: HashMap<u64, Vec<usize>> = HashMap::new();
+  for (i, (_, size)) in files.iter().enumerate() {
+    by_size.entry(*size).or_default().push(i);
+  }
+  let same_size: Vec<usize> =
+    by_size.into_values().filter(|same| same.len() > 1).flatten().collect();
+  sizing.done_files.store(sizing.total_files, Ordering::Relaxed);
+  sizing.emit();
+
+  let pool = hash_pool(ctx);
+  let install = |stage: HashStage, candidates: Vec<usize>| match &pool {
+    Some(pool) => pool.install(|| hash_stage(ctx, &files, stage, candidates)),
+    None => hash_stage(ctx, &files, stage, candidates),
+  };
+  // Files no bigger than both ends together are hashed whole by the first
+  // pass; the rest need a second one if their ends match another's.
+  let ends = install(HashStage::Partial, same_size);
+  let mut hashed = Vec::with_capacity(ends.len());
+  let mut to_finish = Vec::new();
+  for same in group(&files, &ends) {
+    for (i, digest) in same {
+      match files[i].1 > 2 * PARTIAL_BYTES {
+        true => to_finish.push(i),
+        false => hashed.push((i, digest)),
+      }
+    }
+  }
+  hashed.extend(install(HashStage::Full, to_finish));
+
+  let by_contents = group(&files, &hashed);
+  // Only files with a copy can make up a copied directory.
+  let mut digests = vec![None; files.len()];
+  let mut file_groups = Vec::new();
+  for same in by_contents {
+    for &(i, digest) in &same {
+      digests[i] = Some(digest);
+    }
+    let paths = sorted_paths(same.iter().map(|&(i, _)| files[i].0.as_path()));
+    file_groups.push(DuplicateFiles {
+      size: files[same[0].0].1,
+      paths,
+    });
+  }
+  file_groups.sort_by_key(|group| std::cmp::Reverse(wasted(group.size, group.paths.len())));
+
+  Duplicates {
+    files: file_groups,
+    dirs: duplicate_dirs(root, &files, &digests),
+  }
+}
+
+/// The files of `hashed` that share their size and digest with another, by
+/// group.
+fn group(files: &[(PathBuf, u64)], hashed: &[(usize, Digest)]) -> Vec<Vec<(usize, Digest)>> {
+  let mut groups: HashMap<(u64, Digest), Vec<(usize, Digest)>> = HashMap::new();
+  for &(i, digest) in hashed {
+    groups.entry((files[i].1, digest)).or_default().push((i, digest));
+  }
+  groups.into_values().filter(|same| same.len() > 1).collect()
+}
+
+/// Workers of their own when the options set `hash_threads`.
+fn hash_pool(ctx: &ScanContext) -> Option<rayon::ThreadPool> {
+  if ctx.options.hash_threads == 0 {
+    return None;
+  }
+  let mut builder = rayon::ThreadPoolBuilder::new().num_threads(ctx.options.hash_threads);
+  if ctx.options.background {
+    builder = builder.start_handler(|_| crate::priority::lower_current_thread());
+  }
+  builder.build().ok()
+}
+
+/// Hashes the `candidates` of `files` as `stage` asks, on the current pool.
+/// Files that can't be read, or weren't after a cancel, are left out.
+fn hash_stage(
+  ctx: &ScanContext,
+  files: &[(PathBuf, u64)],
+  stage: HashStage,
+  candidates: Vec<usize>,
+) -> Vec<(usize, Digest)> {
+  let bytes = candidates.iter().map(|&i| stage_bytes(stage, files[i].1)).sum();
+  let progress = StageProgress::start(ctx, stage, candidates.len() as u64, bytes);
+  let hashed = candidates
+    .into_par_iter()
+    .filter_map(|i| {
+      if ctx.cancelled() {
+        return None;
+      }
+      let (path, size) = &files[i];
+      let ends = stage == HashStage::Partial && *size > 2 * PARTIAL_BYTES;
+      let digest = hash_file(path, ends, &progress);
+      progress.add_file();
+      match digest {
+        Ok(digest) => digest.map(|digest| (i, digest)),
+        Err(e) => {
+          ctx.record_io_error(path, &e);
+          None
+        }
+      }
+    })
+    .collect();
+  progress.emit();
+  hashed
+}
+
+/// Bytes `stage` reads of a file of `size`.
+fn stage_bytes(stage: HashStage, size: u64) -> u64 {
+  match stage {
+    HashStage::Size => 0,
+    HashStage::Partial => size.min(2 * PARTIAL_BYTES),
+    HashStage::Full | HashStage::Images => size,
+  }
+}
+
+/// Works out the contents of every directory with files, deepest first, and
+/// groups those that match.
+fn duplicate_dirs(
+  root: &Path,
+  files: &[(PathBuf, u64)],
+  digests: &[Option<Digest>],
+) -> Vec<DuplicateDirs> {
+  // Directories by depth below the root.
+  let mut levels: Vec<HashMap<PathBuf, DirContents>> = Vec::new();
+  for ((path, size), digest) in files.iter().zip(digests) {
+    let (Some(parent), Some(name)) = (path.parent(), path.file_name()) else {
+      continue;
+    };
+    let Ok(relative) = parent.strip_prefix(root) else {
+      continue;
+    };
+    let depth = relative.components().count();
+    if levels.len() <= depth {
+      levels.resize_with(depth + 1, HashMap::new);
+    }
+    let dir = levels[depth].entry(parent.to_path_buf()).or_default();
+    dir.size += size;
+    dir.files += 1;
+    match digest {
+      Some(digest) => {
+        dir.entries.push((name.as_encoded_bytes().to_vec(), false, *size, *digest));
+      }
+      None => dir.unique = true,
+    }
+  }
+
+  let mut finished: Vec<(PathBuf, Digest, u64, u64)> = Vec::new();
+  for depth in (1..levels.len()).rev() {
+    for (path, mut dir) in std::mem::take(&mut levels[depth]) {
+      let (Some(parent), Some(name)) = (path.parent(), path.file_name()) else {
+        continue;
+      };
+      let into = levels[depth - 1].entry(parent.to_path_buf()).or_default();
+      into.size += dir.size;
+      into.files += dir.files;
+      if dir.unique {
+        into.unique = true;
+        continue;
+      }
+      dir.entries.sort();
+      let digest = contents_digest(&dir.entries);
+      into.entries.push((name.as_encoded_bytes().to_vec(), true, dir.size, digest));
+      finished.push((path, digest, dir.size, dir.files));
+    }
+  }
+
+  let mut by_digest: HashMap<Digest, Vec<usize>> = HashMap::new();
+  for (i, (_, digest, _, _)) in finished.iter().enumerate() {
+    by_digest.entry(*digest).or_default().push(i);
+  }
+  let groups: Vec<Vec<usize>> = by_digest.into_values().filter(|same| same.len() > 1).collect();
+  // Copies inside copies are covered by the outer ones.
+  let copied: HashSet<&Path> = groups.iter().flatten().map(|&i| finished[i].0.as_path()).collect();
+  let mut dir_groups: Vec<DuplicateDirs> = groups
+    .into_iter()
+    .filter(|same| {
+      same
+        .iter()
+        .any(|&i| !finished[i].0.parent().is_some_and(|parent| copied.contains(parent)))
+    })
+    .map(|same| {
+      let (_, _, size, files) = finished[same[0]];
+      let paths = sorted_paths(same.iter().map(|&i| finished[i].0.as_path()));
+      DuplicateDirs { size, files, paths }
+    })
+    .collect();
+  dir_groups.sort_by_key(|group| std::cmp::Reverse(wasted(group.size, group.paths.len())));
+  dir_groups
+}
+
+fn contents_digest(entries: &[(Vec<u8>, bool, u64, Digest)]) -> Digest {
+  let mut bytes = Vec::with_capacity(entries.len() * 48);
+  for (name, is_dir, size, digest) in entries {
+    bytes.extend_from_slice(&(name.len() as u64).to_le_bytes());
+    bytes.extend_from_slice(name);
+    bytes.push(*is_dir as u8);
+    bytes.extend_from_slice(&size.to_le_bytes());
+    bytes.extend_from_slice(&digest.to_le_bytes());
+  }
+  xxh3_128(&bytes)
+}
+
+/// Bytes taken by all but one of `copies` copies of `size` bytes.
+fn wasted(size: u64, copies: usize) -> u64 {
+  size.saturating_mul(copies.saturating_sub(1) as u64)
+}
+
+fn sorted_paths<'a>(paths: impl Iterator<Item = &'a Path>) -> Vec<String> {
+  let mut paths: Vec<_> = paths.map(|path| path.to_string_lossy().to_string()).collect();
+  paths.sort();
+  paths
+}
+
+/// Hashes the file at `path`, or only `PARTIAL_BYTES` at either end of it
+/// with `ends`; `None` for online-only files, which are never read: that
+/// would download them.
+fn hash_file(path: &Path, ends: bool, progress: &StageProgress) -> std::io::Result<Option<Digest>> {
+  if is_online_only(&std::fs::symlink_metadata(path)?) {
+    return Ok(None);
+  }
+  let mut file = std::fs::File::open(path)?;
+  let mut hasher = Xxh3::new();
+  let mut buf = vec![0u8; READ_BUFFER];
+  if ends {
+    hash_part(&mut file, PARTIAL_BYTES, &mut hasher, &mut buf, progress)?;
+    file.seek(SeekFrom::End(-(PARTIAL_BYTES as i64)))?;
+    hash_part(&mut file, PARTIAL_BYTES, &mut hasher, &mut buf, progress)?;
+  } else {
+    hash_part(&mut file, u64::MAX, &mut hasher, &mut buf, progress)?;
+  }
+  Ok(Some(hasher.digest128()))
+}
+
+/// Hashes up to `limit` bytes from where `file` is.
+fn hash_part(
+  file: &mut std::fs::File,
+  limit: u64,
+  hasher: &mut Xxh3,
+  buf: &mut [u8],
+  progress: &StageProgress,
+) -> std::io::Result<()> {
+  let mut part = file.take(limit);
+  loop {
+    let n = part.read(buf)?;
+    if n == 0 {
+      return Ok(());
+    }
+    hasher.update(&buf[..n]);
+    progress.add_bytes(n as u64);
+  }
+}
+
+/// Groups of identical files in a finished `find_duplicates` scan, the most
+/// space to reclaim first. Empty files are left out.
+#[tauri::command]
+pub async fn get_duplicate_files(
+  state: State<'_, ScanManager>,
+  scan_id: String,
+) -> Result<Vec<DuplicateFiles>, String> {
+  let results = state.results(&scan_id)?;
+  let found = results
+    .duplicates
+    .as_ref()
+    .ok_or_else(|| "The scan didn't look for duplicates".to_string())?;
+  Ok(found.files.clone())
+}
+
+/// Groups of directories in a finished `find_duplicates` scan that hold the
+/// same files under the same names, the most space to reclaim first. Only the
+/// outermost copies are listed, not the matching directories inside them;
+/// empty files and directories, and entries the scan left out, don't count.
+#[tauri::command]
+pub async fn get_duplicate_dirs(
+  state: State<'_, ScanManager>,
+  scan_id: String,
+) -> Result<Vec<DuplicateDirs>, String> {
+  let results = state.results(&scan_id)?;
+  let found = results
+    .duplicates
+    .as_ref()
+    .ok_or_else(|| "The scan didn't look for duplicates".to_string())?;
+  Ok(found.dirs.clone())
+}
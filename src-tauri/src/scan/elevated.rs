@@ -0,0 +1,355 @@
+//! Scans with administrator rights for an app that runs without them. The app
+//! starts its own executable elevated (UAC, pkexec or osascript) as a headless
+//! helper that runs one scan and writes its events to a file, and relays those
+//! events to the UI as they come in, so the scan looks like any other.
+
+use super::{
+  execute_scan, EventSink, ScanControl, ScanFailedEvent, ScanFilters, ScanManager, ScanOptions,
+  ScanSource, ScanStartedEvent,
+};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::{
+  fs::{File, OpenOptions},
+  io::{self, BufRead, BufReader},
+  path::{Path, PathBuf},
+  sync::{atomic::Ordering, Arc},
+  time::{Duration, Instant},
+};
+use tauri::{AppHandle, Emitter, Manager, State};
+use uuid::Uuid;
+
+const HELPER_FLAG: &str = "--elevated-scan";
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// What the helper is asked to scan.
+#[derive(Serialize, Deserialize)]
+struct HelperRequest {
+  scan_id: String,
+  root: PathBuf,
+  options: ScanOptions,
+}
+
+/// The directory the app and its helper talk through. The helper is only
+/// given its path, which keeps quoting through the elevation prompt simple.
+struct Exchange {
+  dir: PathBuf,
+}
+
+impl Exchange {
+  fn create(scan_id: &str) -> io::Result<Self> {
+    let dir = std::env::temp_dir().join(format!("space-usage-{}", scan_id));
+    std::fs::create_dir(&dir)?;
+    Ok(Self { dir })
+  }
+
+  fn request(&self) -> PathBuf {
+    self.dir.join("request.json")
+  }
+
+  /// Written by the helper only. It creates the file itself, so it never
+  /// writes through a link someone planted in its place.
+  fn events(&self) -> PathBuf {
+    self.dir.join("events.jsonl")
+  }
+
+  /// Created by the app to cancel; an elevated process can't be signalled.
+  fn cancel(&self) -> PathBuf {
+    self.dir.join("cancel")
+  }
+}
+
+/// Starts a scan of `path` in an elevated helper, once the user approves the
+/// OS prompt, and returns its scan id. Its events arrive like a normal scan's;
+/// a declined prompt ends it with `scan_failed`. Elevated scans don't count
+/// against the concurrency limit.
+#[tauri::command]
+pub async fn start_elevated_scan(
+  app: AppHandle,
+  state: State<'_, ScanManager>,
+  path: String,
+  options: ScanOptions,
+) -> Result<String, String> {
+  if state.closing.load(Ordering::Relaxed) {
+    return Err("The app is shutting down".to_string());
+  }
+  // Not checked for existence: the helper may see what this process can't.
+  let root = crate::paths::normalize(&path)?;
+  // Bad patterns are reported here rather than by a helper that can't say so.
+  ScanFilters::new(&options, &root)?;
+  let scan_id = Uuid::new_v4().to_string();
+  let exchange = Exchange::create(&scan_id).map_err(|e| e.to_string())?;
+  let request = HelperRequest {
+    scan_id: scan_id.clone(),
+    root: root.clone(),
+    options,
+  };
+  let json = serde_json::to_vec(&request).map_err(|e| e.to_string())?;
+  std::fs::write(exchange.request(), json).map_err(|e| e.to_string())?;
+
+  let control = Arc::new(ScanControl::new(root.clone()));
+  let _ = control.started.set(Instant::now());
+  state
+    .scans
+    .lock()
+    .insert(scan_id.clone(), Arc::clone(&control));
+  let _ = app.emit(
+    "scan_started",
+    ScanStartedEvent {
+      scan_id: scan_id.clone(),
+      root: root.to_string_lossy().to_string(),
+    },
+  );
+
+  let thread_id = scan_id.clone();
+  std::thread::spawn(move || {
+    if let Err(message) = relay(&app, &exchange, &control) {
+      let _ = app.emit(
+        "scan_failed",
+        ScanFailedEvent {
+          scan_id: thread_id.clone(),
+          root: root.to_string_lossy().to_string(),
+          message,
+        },
+      );
+    }
+    if let Some(state) = app.try_state::<ScanManager>() {
+      state.scans.lock().remove(&thread_id);
+    }
+    let _ = std::fs::remove_dir_all(&exchange.dir);
+  });
+  Ok(scan_id)
+}
+
+/// Launches the helper and forwards its events until it finishes. Fails when
+/// it couldn't be launched or exited without reporting an outcome.
+fn relay(app: &AppHandle, exchange: &Exchange, control: &ScanControl) -> Result<(), String> {
+  let mut helper = launch(&exchange.dir).map_err(|e| match e.kind() {
+    io::ErrorKind::PermissionDenied => "Administrator rights were declined".to_string(),
+    _ => format!("Can't start the elevated scan: {}", e),
+  })?;
+
+  let mut reader = None;
+  let mut line = String::new();
+  let mut cancel_sent = false;
+  loop {
+    if control.cancel.load(Ordering::Relaxed) && !cancel_sent {
+      cancel_sent = File::create(exchange.cancel()).is_ok();
+    }
+    // Checked before reading, so everything written before the exit is read.
+    let exited = helper.exited();
+    if reader.is_none() {
+      reader = File::open(exchange.events()).ok().map(BufReader::new);
+    }
+    if let Some(reader) = reader.as_mut() {
+      // A line still being written stays in `line` until its newline arrives.
+      while reader.read_line(&mut line).map_err(|e| e.to_string())? > 0 {
+        if !line.ends_with('\n') {
+          break;
+        }
+        if forward(app, control, &line) {
+          return Ok(());
+        }
+        line.clear();
+      }
+    }
+    if exited {
+      return Err(if reader.is_none() {
+        "Administrator rights were declined".to_string()
+      } else {
+        "The elevated scan stopped without a result".to_string()
+      });
+    }
+    std::thread::sleep(POLL_INTERVAL);
+  }
+}
+
+/// Emits one helper event to the UI and returns whether it ended the scan.
+fn forward(app: &AppHandle, control: &ScanControl, line: &str) -> bool {
+  #[derive(Deserialize)]
+  struct Line {
+    event: String,
+    payload: serde_json::Value,
+  }
+  let Ok(Line { event, payload }) = serde_json::from_str::<Line>(line) else {
+    return false;
+  };
+  relay_event(app, control, &event, payload)
+}
+
+/// Emits an event of a scan run elsewhere, keeping get_scan_status current
+/// for it, and returns whether it ended the scan.
+pub(super) fn relay_event(
+  app: &AppHandle,
+  control: &ScanControl,
+  event: &str,
+  payload: serde_json::Value,
+) -> bool {
+  let count = |key: &str| payload.get(key).and_then(serde_json::Value::as_u64);
+  if let Some(entries) = count("scanned_entries") {
+    control.scanned_entries.store(entries, Ordering::Relaxed);
+  }
+  if let Some(bytes) = count("scanned_bytes") {
+    control.scanned_bytes.store(bytes, Ordering::Relaxed);
+  }
+  let _ = app.emit(event, payload);
+  matches!(event, "scan_done" | "scan_failed")
+}
+
+/// Runs the scan helper when the process was started as one, and returns its
+/// exit code; `None` for a normal app start.
+pub fn run_helper_from_args() -> Option<i32> {
+  let mut args = std::env::args_os().skip(1);
+  if args.next()? != HELPER_FLAG {
+    return None;
+  }
+  let Some(dir) = args.next() else {
+    return Some(2);
+  };
+  let exchange = Exchange {
+    dir: PathBuf::from(dir),
+  };
+  Some(match run_helper(&exchange) {
+    Ok(()) => 0,
+    Err(e) => {
+      eprintln!("{}", e);
+      1
+    }
+  })
+}
+
+fn run_helper(exchange: &Exchange) -> Result<(), String> {
+  let request = std::fs::read(exchange.request()).map_err(|e| e.to_string())?;
+  let request: HelperRequest = serde_json::from_slice(&request).map_err(|e| e.to_string())?;
+  let out = OpenOptions::new()
+    .write(true)
+    .create_new(true)
+    .open(exchange.events())
+    .map_err(|e| e.to_string())?;
+  let events = EventSink::Lines(Mutex::new(out));
+
+  let filters = ScanFilters::new(&request.options, &request.root)?;
+  let control = Arc::new(ScanControl::new(request.root.clone()));
+  let _ = control.started.set(Instant::now());
+  let watched = Arc::clone(&control);
+  let cancel = exchange.cancel();
+  std::thread::spawn(move || {
+    while !cancel.exists() {
+      std::thread::sleep(POLL_INTERVAL);
+    }
+    watched.cancel.store(true, Ordering::Relaxed);
+  });
+
+  execute_scan(
+    &events,
+    &request.scan_id,
+    &request.root,
+    &request.options,
+    &control,
+    filters,
+    &ScanSource::Local,
+  );
+  Ok(())
+}
+
+/// The process that runs the helper elevated: the helper itself, or the
+/// launcher waiting on it.
+enum Helper {
+  #[cfg(not(windows))]
+  Child(std::process::Child),
+  #[cfg(windows)]
+  Process(windows_sys::Win32::Foundation::HANDLE),
+}
+
+impl Helper {
+  fn exited(&mut self) -> bool {
+    match self {
+      #[cfg(not(windows))]
+      Helper::Child(child) => !matches!(child.try_wait(), Ok(None)),
+      #[cfg(windows)]
+      Helper::Process(handle) => {
+        use windows_sys::Win32::{Foundation::WAIT_TIMEOUT, System::Threading::WaitForSingleObject};
+        unsafe { WaitForSingleObject(*handle, 0) != WAIT_TIMEOUT }
+      }
+    }
+  }
+}
+
+#[cfg(windows)]
+impl Drop for Helper {
+  fn drop(&mut self) {
+    let Helper::Process(handle) = self;
+    unsafe { windows_sys::Win32::Foundation::CloseHandle(*handle) };
+  }
+}
+
+/// pkexec asks for the password through the desktop's polkit agent.
+#[cfg(target_os = "linux")]
+fn launch(dir: &Path) -> io::Result<Helper> {
+  let exe = std::env::current_exe()?;
+  let child = std::process::Command::new("pkexec")
+    .arg(exe)
+    .arg(HELPER_FLAG)
+    .arg(dir)
+    .stdin(std::process::Stdio::null())
+    .spawn()?;
+  Ok(Helper::Child(child))
+}
+
+/// osascript shows the standard administrator prompt and waits for the helper.
+#[cfg(target_os = "macos")]
+fn launch(dir: &Path) -> io::Result<Helper> {
+  let exe = std::env::current_exe()?;
+  let shell_quote = |s: &Path| format!("'{}'", s.to_string_lossy().replace('\'', r"'\''"));
+  let command = format!("{} {} {}", shell_quote(&exe), HELPER_FLAG, shell_quote(dir));
+  let script = format!(
+    "do shell script \"{}\" with administrator privileges",
+    command.replace('\\', r"\\").replace('"', "\\\"")
+  );
+  let child = std::process::Command::new("osascript")
+    .arg("-e")
+    .arg(script)
+    .stdin(std::process::Stdio::null())
+    .spawn()?;
+  Ok(Helper::Child(child))
+}
+
+/// The "runas" verb brings up the UAC prompt.
+#[cfg(windows)]
+fn launch(dir: &Path) -> io::Result<Helper> {
+  use windows_sys::Win32::{
+    Foundation::ERROR_CANCELLED,
+    UI::Shell::{ShellExecuteExW, SEE_MASK_NOASYNC, SEE_MASK_NOCLOSEPROCESS, SHELLEXECUTEINFOW},
+  };
+  let exe = std::env::current_exe()?;
+  let verb = crate::volume::to_wide(Path::new("runas"));
+  let file = crate::volume::to_wide(&exe);
+  let params = format!("{} \"{}\"", HELPER_FLAG, dir.display());
+  let params = crate::volume::to_wide(Path::new(&params));
+  let mut info = SHELLEXECUTEINFOW {
+    cbSize: std::mem::size_of::<SHELLEXECUTEINFOW>() as u32,
+    fMask: SEE_MASK_NOCLOSEPROCESS | SEE_MASK_NOASYNC,
+    lpVerb: verb.as_ptr(),
+    lpFile: file.as_ptr(),
+    lpParameters: params.as_ptr(),
+    // SW_HIDE: the helper has no window of its own to show.
+    nShow: 0,
+    ..Default::default()
+  };
+  if unsafe { ShellExecuteExW(&mut info) } == 0 {
+    let err = io::Error::last_os_error();
+    return Err(match err.raw_os_error() {
+      Some(code) if code == ERROR_CANCELLED as i32 => io::ErrorKind::PermissionDenied.into(),
+      _ => err,
+    });
+  }
+  if info.hProcess.is_null() {
+    return Err(io::Error::other("The elevated helper didn't start"));
+  }
+  Ok(Helper::Process(info.hProcess))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+fn launch(_dir: &Path) -> io::Result<Helper> {
+  Err(io::ErrorKind::Unsupported.into())
+}
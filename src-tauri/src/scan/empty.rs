@@ -0,0 +1,173 @@
+//! Directories holding nothing but other empty directories, settled as the
+//! walk finishes each one, and removing them in a batch.
+
+use super::{blocking, ScanManager};
+use serde::Serialize;
+use std::{
+  collections::{HashMap, HashSet},
+  path::{Path, PathBuf},
+};
+use tauri::State;
+
+#[derive(Clone, Serialize)]
+pub struct EmptyDir {
+  pub path: String,
+  /// Empty directories nested inside it.
+  pub dirs: u64,
+}
+
+/// A directory whose subdirectories aren't all read yet.
+struct Pending {
+  path: PathBuf,
+  parent: Option<usize>,
+  // Set once its own listing is read.
+  listed: bool,
+  // Subdirectories still to settle.
+  waiting: u64,
+  // Has something besides empty directories.
+  occupied: bool,
+  // Its empty subdirectories, each with the empty directories inside it.
+  empty: Vec<(PathBuf, u64)>,
+}
+
+/// Directories of one walk, by the id their `DirTask` carries.
+#[derive(Default)]
+pub(super) struct EmptyDirs {
+  next: usize,
+  pending: HashMap<usize, Pending>,
+  // Empty directories whose parent isn't, or that sit right below the root.
+  found: Vec<EmptyDir>,
+}
+
+impl EmptyDirs {
+  /// Registers a directory to be walked below the one with id `parent`, and
+  /// returns its own id.
+  pub(super) fn add(&mut self, path: PathBuf, parent: Option<usize>) -> usize {
+    let id = self.next;
+    self.next += 1;
+    if let Some(parent) = parent.and_then(|parent| self.pending.get_mut(&parent)) {
+      parent.waiting += 1;
+    }
+    let dir = Pending {
+      path,
+      parent,
+      listed: false,
+      waiting: 0,
+      occupied: false,
+      empty: Vec::new(),
+    };
+    self.pending.insert(id, dir);
+    id
+  }
+
+  /// Records that the listing of directory `id` was read, and whether it held
+  /// anything besides the subdirectories registered under it. Directories
+  /// never listed, as after a cancel, are never reported.
+  pub(super) fn listed(&mut self, id: usize, occupied: bool) {
+    if let Some(dir) = self.pending.get_mut(&id) {
+      dir.listed = true;
+      dir.occupied |= occupied;
+    }
+    self.settle(id);
+  }
+
+  /// Finishes directory `id` and then its parents, as long as nothing they
+  /// wait on is left.
+  fn settle(&mut self, mut id: usize) {
+    loop {
+      match self.pending.get(&id) {
+        Some(dir) if dir.listed && dir.waiting == 0 => {}
+        _ => return,
+      }
+      let dir = self.pending.remove(&id).expect("checked above");
+      let parent = dir.parent.and_then(|parent| Some((parent, self.pending.get_mut(&parent)?)));
+      let Some((parent_id, parent)) = parent else {
+        // The root itself is never offered for removal.
+        self.found.extend(dir.empty.into_iter().map(|(path, dirs)| empty_dir(path, dirs)));
+        return;
+      };
+      parent.waiting -= 1;
+      if dir.occupied {
+        parent.occupied = true;
+        self.found.extend(dir.empty.into_iter().map(|(path, dirs)| empty_dir(path, dirs)));
+      } else {
+        let dirs = dir.empty.iter().map(|(_, dirs)| dirs + 1).sum();
+        parent.empty.push((dir.path, dirs));
+      }
+      id = parent_id;
+    }
+  }
+
+  /// The empty directories found, outermost only, sorted by path.
+  pub(super) fn finish(&mut self) -> Vec<EmptyDir> {
+    let mut found = std::mem::take(&mut self.found);
+    found.sort_by(|a, b| a.path.cmp(&b.path));
+    found
+  }
+}
+
+fn empty_dir(path: PathBuf, dirs: u64) -> EmptyDir {
+  EmptyDir {
+    path: path.to_string_lossy().to_string(),
+    dirs,
+  }
+}
+
+/// Directories of a finished scan that hold nothing but other empty
+/// directories, outermost only, with how many empty directories each holds.
+#[tauri::command]
+pub async fn get_empty_dirs(
+  state: State<'_, ScanManager>,
+  scan_id: String,
+) -> Result<Vec<EmptyDir>, String> {
+  Ok(state.results(&scan_id)?.empty_dirs.clone())
+}
+
+#[derive(Serialize)]
+pub struct RemovedDirs {
+  pub removed: Vec<String>,
+  pub errors: Vec<String>,
+}
+
+/// Removes directories `get_empty_dirs` reported for a scan, with the empty
+/// directories inside them. Only empty directories are ever removed: one
+/// that got a file since the scan fails and is left as it is.
+#[tauri::command]
+pub async fn remove_empty_dirs(
+  state: State<'_, ScanManager>,
+  scan_id: String,
+  paths: Vec<String>,
+) -> Result<RemovedDirs, String> {
+  let reported: HashSet<String> =
+    state.results(&scan_id)?.empty_dirs.iter().map(|dir| dir.path.clone()).collect();
+  blocking(move || {
+    let mut outcome = RemovedDirs {
+      removed: Vec::new(),
+      errors: Vec::new(),
+    };
+    for path in paths {
+      if !reported.contains(&path) {
+        outcome.errors.push(format!("{}: not an empty directory of the scan", path));
+        continue;
+      }
+      match remove_empty_tree(Path::new(&path)) {
+        Ok(()) => outcome.removed.push(path),
+        Err(e) => outcome.errors.push(format!("{}: {}", path, e)),
+      }
+    }
+    Ok(outcome)
+  })
+  .await
+}
+
+/// Removes `path` after the directories inside it, without following links.
+/// `remove_dir` refuses any directory that isn't empty by then.
+fn remove_empty_tree(path: &Path) -> std::io::Result<()> {
+  for entry in std::fs::read_dir(path)? {
+    let entry = entry?;
+    if entry.file_type()?.is_dir() {
+      remove_empty_tree(&entry.path())?;
+    }
+  }
+  std::fs::remove_dir(path)
+}
@@ -0,0 +1,54 @@
+//! Going further down a folder than the scan did. Past `max_depth` a folder
+//! only has its totals, and `expand_node` walks it again for as many more
+//! levels as asked, so the tree can be drilled into without a new scan.
+
+use super::{
+  blocking, disk_image,
+  query::{self, ChildPage},
+  refresh, scan_tree, NodeKind, ScanManager, ScanOptions,
+};
+use tauri::{AppHandle, Manager};
+
+/// Walks the folder `node_id` of the kept scan `scan_id` again,
+/// `extra_depth` levels down (1 unless said) with `top_children` per folder
+/// (as many as the scan kept unless said), and puts it in the tree. Returns
+/// the first page of the children it has now, largest first; the rest come
+/// from `get_children`.
+#[tauri::command]
+pub async fn expand_node(
+  app: AppHandle,
+  scan_id: String,
+  node_id: u64,
+  extra_depth: Option<u32>,
+  top_children: Option<u32>,
+) -> Result<ChildPage, String> {
+  blocking(move || {
+    let state = app.state::<ScanManager>();
+    let updating = state.updating(&scan_id)?;
+    let _updating = updating.lock();
+    let results = state.results(&scan_id)?;
+    if !results.local {
+      return Err("Only folders of this machine can be walked again".to_string());
+    }
+    let (node, root) = query::node_of(&results, node_id)?;
+    if !matches!(node.kind, NodeKind::Dir) {
+      return Err(format!("{} isn't a folder", root.display()));
+    }
+    let options = ScanOptions {
+      max_depth: extra_depth.unwrap_or(1).max(1),
+      top_children: top_children.unwrap_or(results.options.top_children),
+      find_duplicates: false,
+      find_similar_images: false,
+      ..results.options.clone()
+    };
+    let expanded = disk_image::walk(&root, &options, |ctx| scan_tree(ctx, &root))?;
+    drop(results);
+    state.update_results(&scan_id, |results| {
+      refresh::graft(results, &root, expanded);
+      let (node, _) = query::node_of(results, node_id)?;
+      let children = node.children.as_deref().unwrap_or_default();
+      Ok(ChildPage::of(children, None, None, None, None))
+    })?
+  })
+  .await
+}
@@ -0,0 +1,371 @@
+//! Writing a finished scan out for other tools to read.
+//!
+//! ncdu's export is a nested JSON array, `[1, 2, {metadata}, dir]`, where a
+//! directory is `[{info}, child, child, …]` and a file just its `{info}`.
+//! ncdu adds the sizes up itself, so a directory's own info carries what its
+//! children don't account for: the levels past `max_depth` and the entries
+//! merged away, which would otherwise drop out of the totals.
+//!
+//! The CSV and TSV exports are flat instead, a row per node, for
+//! spreadsheets and the like; so is the WinDirStat one, in the columns of
+//! the CSV results WinDirStat 2 saves and opens.
+//!
+//! The du one is text to paste: the folders, down to a depth, the way
+//! `du -h --max-depth=N` prints them, their contents first.
+//!
+//! The HTML one is a page to send to someone without the app, with the tree
+//! and the top lists in it and a treemap to go through them.
+
+use super::{
+  blocking,
+  category::FileCategory,
+  iso::civil_from_days,
+  stats::{CategoryStat, ExtensionStat, FileEntry},
+  LinkKind, NodeKind, ScanManager, ScanNode, ScanResults, ATTR_COMPRESSED, ATTR_HIDDEN,
+  ATTR_OFFLINE, ATTR_READONLY, ATTR_SPARSE,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+  fs::File,
+  io::{self, BufWriter, Write},
+  path::Path,
+  time::{SystemTime, UNIX_EPOCH},
+};
+use tauri::State;
+
+#[derive(Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+  /// The JSON `ncdu -o` writes and `ncdu -f` reads.
+  Ncdu,
+  Csv,
+  Tsv,
+  #[serde(rename = "windirstat")]
+  WinDirStat,
+  Du,
+  Html,
+}
+
+/// Which nodes a flat export has rows for.
+#[derive(Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportRows {
+  #[default]
+  All,
+  Files,
+  Dirs,
+}
+
+/// What the flat exports leave out; the ncdu one always has everything.
+#[derive(Clone, Copy, Default, Deserialize)]
+#[serde(default)]
+pub struct ExportOptions {
+  pub rows: ExportRows,
+  /// Nodes smaller than this many bytes get no row.
+  pub min_size: u64,
+  /// Folder levels below the root the du export lists; all of them if unset.
+  pub max_depth: Option<u32>,
+}
+
+#[derive(Serialize)]
+struct NcduMeta {
+  progname: &'static str,
+  progver: &'static str,
+  timestamp: u64,
+}
+
+/// The info block of an entry; sizes are its own, not its subtree's.
+#[derive(Serialize)]
+struct NcduInfo<'a> {
+  name: &'a str,
+  asize: u64,
+  dsize: u64,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  mtime: Option<i64>,
+  /// Set for links, which ncdu shows as other than a regular file.
+  #[serde(skip_serializing_if = "std::ops::Not::not")]
+  notreg: bool,
+}
+
+fn write_ncdu(out: &mut impl Write, root: &ScanNode, root_path: &str) -> io::Result<()> {
+  let meta = NcduMeta {
+    progname: "space-usage",
+    progver: env!("CARGO_PKG_VERSION"),
+    timestamp: SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs()),
+  };
+  out.write_all(b"[1,2,")?;
+  serde_json::to_writer(&mut *out, &meta)?;
+  out.write_all(b",\n")?;
+  // The root goes by its full path, as ncdu names it.
+  write_ncdu_node(out, root, root_path)?;
+  out.write_all(b"]\n")
+}
+
+fn write_ncdu_node(out: &mut impl Write, node: &ScanNode, name: &str) -> io::Result<()> {
+  let mut info = NcduInfo {
+    name,
+    asize: node.size,
+    dsize: node.allocated,
+    mtime: node.mtime,
+    notreg: node.link_kind == LinkKind::Symlink,
+  };
+  if !matches!(node.kind, NodeKind::Dir) {
+    return serde_json::to_writer(&mut *out, &info).map_err(io::Error::from);
+  }
+  let children = node.children.as_deref().unwrap_or_default();
+  info.asize = node.size.saturating_sub(children.iter().map(|c| c.size).sum());
+  info.dsize = node.allocated.saturating_sub(children.iter().map(|c| c.allocated).sum());
+  out.write_all(b"[")?;
+  serde_json::to_writer(&mut *out, &info)?;
+  for child in children {
+    out.write_all(b",\n")?;
+    write_ncdu_node(out, child, &child.name)?;
+  }
+  out.write_all(b"]")
+}
+
+/// `2024-01-31 23:59:59`, in UTC, which spreadsheets read as a date.
+pub(super) fn date_time(secs: i64) -> String {
+  let (year, month, day) = civil_from_days(secs.div_euclid(86_400));
+  let time = secs.rem_euclid(86_400);
+  format!(
+    "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+    year,
+    month,
+    day,
+    time / 3600,
+    time / 60 % 60,
+    time % 60
+  )
+}
+
+/// `field` quoted if it has to be, the way RFC 4180 has it.
+fn quoted(field: &str, separator: char) -> std::borrow::Cow<'_, str> {
+  if field.contains([separator, '"', '\n', '\r']) {
+    format!("\"{}\"", field.replace('"', "\"\"")).into()
+  } else {
+    field.into()
+  }
+}
+
+fn write_rows(
+  out: &mut impl Write,
+  root: &ScanNode,
+  root_path: &str,
+  separator: char,
+  options: ExportOptions,
+) -> io::Result<()> {
+  let header = ["path", "size", "allocated", "kind", "mtime", "depth"];
+  writeln!(out, "{}", header.join(&separator.to_string()))?;
+  write_row(out, root, Path::new(root_path), 0, separator, options)
+}
+
+fn write_row(
+  out: &mut impl Write,
+  node: &ScanNode,
+  path: &Path,
+  depth: usize,
+  separator: char,
+  options: ExportOptions,
+) -> io::Result<()> {
+  let wanted = match options.rows {
+    ExportRows::All => true,
+    ExportRows::Files => matches!(node.kind, NodeKind::File),
+    ExportRows::Dirs => matches!(node.kind, NodeKind::Dir),
+  };
+  if wanted && node.size >= options.min_size {
+    let kind = match node.kind {
+      NodeKind::File => "file",
+      NodeKind::Dir => "dir",
+      NodeKind::Other => "other",
+    };
+    // A folder goes by its newest file.
+    let mtime = node.mtime.or(node.newest_mtime).map(date_time).unwrap_or_default();
+    let path = path.to_string_lossy();
+    let fields = [
+      quoted(&path, separator),
+      node.size.to_string().into(),
+      node.allocated.to_string().into(),
+      kind.into(),
+      mtime.into(),
+      depth.to_string().into(),
+    ];
+    writeln!(out, "{}", fields.join(&separator.to_string()))?;
+  }
+  for child in node.children.iter().flatten() {
+    write_row(out, child, &path.join(&child.name), depth + 1, separator, options)?;
+  }
+  Ok(())
+}
+
+/// The `FILE_ATTRIBUTE_*` bits of a node, as far as they're known.
+fn windows_attributes(node: &ScanNode) -> u32 {
+  const FILE_ATTRIBUTE_DIRECTORY: u32 = 0x10;
+  let bits = [
+    (ATTR_READONLY, 0x1),
+    (ATTR_HIDDEN, 0x2),
+    (ATTR_SPARSE, 0x200),
+    (ATTR_COMPRESSED, 0x800),
+    (ATTR_OFFLINE, 0x1000),
+  ];
+  let attributes = bits
+    .iter()
+    .filter(|(ours, _)| node.attributes & ours != 0)
+    .fold(0, |attributes, (_, theirs)| attributes | theirs);
+  match node.kind {
+    NodeKind::Dir => attributes | FILE_ATTRIBUTE_DIRECTORY,
+    _ => attributes,
+  }
+}
+
+fn write_windirstat(out: &mut impl Write, root: &ScanNode, root_path: &str) -> io::Result<()> {
+  writeln!(out, "Name,Files,Folders,Logical Size,Physical Size,Attributes,Last Change,Owner")?;
+  write_windirstat_row(out, root, Path::new(root_path))
+}
+
+fn write_windirstat_row(out: &mut impl Write, node: &ScanNode, path: &Path) -> io::Result<()> {
+  let (files, folders) = match node.kind {
+    NodeKind::File => (0, 0),
+    _ => (node.file_count.unwrap_or(0), node.dir_count.unwrap_or(0)),
+  };
+  let changed = node.mtime.or(node.newest_mtime).map(date_time);
+  writeln!(
+    out,
+    "{},{},{},{},{},{},{},{}",
+    quoted(&path.to_string_lossy(), ','),
+    files,
+    folders,
+    node.size,
+    node.allocated,
+    windows_attributes(node),
+    changed.map(|changed| format!("{}Z", changed.replacen(' ', "T", 1))).unwrap_or_default(),
+    quoted(node.owner.as_deref().unwrap_or_default(), ','),
+  )?;
+  for child in node.children.iter().flatten() {
+    write_windirstat_row(out, child, &path.join(&child.name))?;
+  }
+  Ok(())
+}
+
+/// `bytes` the way `du -h` prints it: rounded up, with one decimal below 10.
+fn du_size(bytes: u64) -> String {
+  const UNITS: [&str; 6] = ["K", "M", "G", "T", "P", "E"];
+  if bytes < 1024 {
+    return bytes.to_string();
+  }
+  let mut value = bytes as f64 / 1024.0;
+  let mut unit = 0;
+  while value.ceil() >= 1024.0 && unit + 1 < UNITS.len() {
+    value /= 1024.0;
+    unit += 1;
+  }
+  let tenths = (value * 10.0).ceil() / 10.0;
+  match tenths < 10.0 {
+    true => format!("{:.1}{}", tenths, UNITS[unit]),
+    false => format!("{}{}", value.ceil(), UNITS[unit]),
+  }
+}
+
+/// Like du, what a folder, the one at `path`, takes on disk.
+fn write_du(
+  out: &mut impl Write,
+  node: &ScanNode,
+  path: &Path,
+  depth: u32,
+  max_depth: u32,
+) -> io::Result<()> {
+  if depth < max_depth {
+    for child in node.children.iter().flatten() {
+      if matches!(child.kind, NodeKind::Dir) {
+        write_du(out, child, &path.join(&child.name), depth + 1, max_depth)?;
+      }
+    }
+  }
+  writeln!(out, "{}\t{}", du_size(node.allocated), path.display())
+}
+
+/// The page the HTML export fills in.
+const REPORT_PAGE: &str = include_str!("report.html");
+
+/// What the report page is given of a scan.
+#[derive(Serialize)]
+struct Report<'a> {
+  root: &'a str,
+  /// When the report was made, in seconds since the Unix epoch.
+  generated: u64,
+  tree: ReportNode<'a>,
+  top_files: &'a [FileEntry],
+  extensions: &'a [ExtensionStat],
+  categories: &'a [CategoryStat],
+}
+
+/// A node with only what the treemap needs; folders have `children`.
+#[derive(Serialize)]
+struct ReportNode<'a> {
+  name: &'a str,
+  size: u64,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  category: Option<FileCategory>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  children: Option<Vec<ReportNode<'a>>>,
+}
+
+impl<'a> ReportNode<'a> {
+  fn of(node: &'a ScanNode) -> Self {
+    // Folders past `max_depth` are shown whole.
+    let children = match node.kind {
+      NodeKind::Dir => node.children.as_ref().map(|c| c.iter().map(ReportNode::of).collect()),
+      _ => None,
+    };
+    ReportNode {
+      name: &node.name,
+      size: node.size,
+      category: node.category.or_else(|| node.categories.as_ref()?.largest()),
+      children,
+    }
+  }
+}
+
+fn write_html(out: &mut impl Write, results: &ScanResults) -> io::Result<()> {
+  let report = Report {
+    root: &results.root,
+    generated: SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs()),
+    tree: ReportNode::of(&results.tree),
+    top_files: &results.top_files,
+    extensions: &results.extensions[..results.extensions.len().min(25)],
+    categories: &results.categories,
+  };
+  // Nothing in the data may close the script it's in.
+  let data = serde_json::to_string(&report)?.replace('<', "\\u003c");
+  out.write_all(REPORT_PAGE.replacen("/*DATA*/", &data, 1).as_bytes())
+}
+
+/// Writes the tree of a finished scan to `path` in `format`.
+#[tauri::command]
+pub async fn export_scan(
+  state: State<'_, ScanManager>,
+  scan_id: String,
+  format: ExportFormat,
+  path: String,
+  options: Option<ExportOptions>,
+) -> Result<(), String> {
+  let options = options.unwrap_or_default();
+  let results = state.results(&scan_id)?;
+  blocking(move || {
+    let file = File::create(&path).map_err(|e| format!("{path}: {e}"))?;
+    let mut out = BufWriter::new(file);
+    let written = match format {
+      ExportFormat::Ncdu => write_ncdu(&mut out, &results.tree, &results.root),
+      ExportFormat::Csv => write_rows(&mut out, &results.tree, &results.root, ',', options),
+      ExportFormat::Tsv => write_rows(&mut out, &results.tree, &results.root, '\t', options),
+      ExportFormat::WinDirStat => write_windirstat(&mut out, &results.tree, &results.root),
+      ExportFormat::Du => {
+        let max_depth = options.max_depth.unwrap_or(u32::MAX);
+        write_du(&mut out, &results.tree, Path::new(&results.root), 0, max_depth)
+      }
+      ExportFormat::Html => write_html(&mut out, &results),
+    };
+    written.and_then(|()| out.flush()).map_err(|e| format!("{path}: {e}"))
+  })
+  .await
+}
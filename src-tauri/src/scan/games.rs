@@ -0,0 +1,179 @@
+//! Games installed by Steam, the Epic Games Launcher and GOG, found through
+//! the manifests each store keeps: `appmanifest_<id>.acf` files in a Steam
+//! library's `steamapps`, `goggame-<id>.info` files in GOG game folders, and
+//! the launcher's own list of Epic installs. Steam also keeps per-game
+//! shader caches and Workshop downloads apart from the game.
+
+use super::{subtree_bytes, ScanManager, ScanNode};
+use serde::Serialize;
+use std::{
+  collections::HashSet,
+  path::{Path, PathBuf},
+};
+use tauri::State;
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GameStore {
+  Steam,
+  Epic,
+  Gog,
+}
+
+#[derive(Clone, Serialize)]
+pub struct Game {
+  pub store: GameStore,
+  /// The store's id of the game.
+  pub id: String,
+  pub name: String,
+  pub path: String,
+  /// The library folder holding it, for Steam.
+  pub library: Option<String>,
+  pub install_bytes: u64,
+  pub shader_cache_bytes: u64,
+  pub workshop_bytes: u64,
+  /// When the store last updated it, in seconds since the Unix epoch.
+  pub last_updated: Option<i64>,
+}
+
+/// Whether the file at `path` is a manifest the walk should note.
+pub(super) fn is_manifest(path: &Path) -> bool {
+  let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+    return false;
+  };
+  let in_steamapps = || {
+    path.parent().and_then(Path::file_name).is_some_and(|dir| dir.eq_ignore_ascii_case("steamapps"))
+  };
+  (name.starts_with("appmanifest_") && name.ends_with(".acf") && in_steamapps())
+    || (name.starts_with("goggame-") && name.ends_with(".info"))
+}
+
+/// The games of the manifests the walk noted, and the Epic installs under
+/// the scan's root, the most bytes first.
+pub(super) fn report(root: &ScanNode, root_path: &Path, manifests: Vec<PathBuf>) -> Vec<Game> {
+  let mut games: Vec<Game> = manifests
+    .iter()
+    .filter_map(|manifest| {
+      let name = manifest.file_name()?.to_str()?;
+      if name.ends_with(".acf") {
+        steam_game(root, root_path, manifest)
+      } else {
+        gog_game(root, root_path, manifest)
+      }
+    })
+    .collect();
+  games.extend(epic_games(root, root_path));
+  let mut seen = HashSet::new();
+  games.retain(|game| seen.insert(game.path.clone()));
+  games.sort_by_key(|game| {
+    std::cmp::Reverse(game.install_bytes + game.shader_cache_bytes + game.workshop_bytes)
+  });
+  games
+}
+
+fn steam_game(root: &ScanNode, root_path: &Path, manifest: &Path) -> Option<Game> {
+  let text = std::fs::read_to_string(manifest).ok()?;
+  let steamapps = manifest.parent()?;
+  let id = vdf_value(&text, "appid")?;
+  let path = steamapps.join("common").join(vdf_value(&text, "installdir")?);
+  let cache = |dir: &Path| match dir.is_dir() {
+    true => subtree_bytes(root, root_path, dir),
+    false => 0,
+  };
+  Some(Game {
+    store: GameStore::Steam,
+    name: vdf_value(&text, "name").unwrap_or_else(|| id.clone()),
+    install_bytes: cache(&path),
+    shader_cache_bytes: cache(&steamapps.join("shadercache").join(&id)),
+    workshop_bytes: cache(&steamapps.join("workshop").join("content").join(&id)),
+    last_updated: vdf_value(&text, "LastUpdated").and_then(|time| time.parse().ok()),
+    library: steamapps.parent().map(|library| library.to_string_lossy().to_string()),
+    path: path.to_string_lossy().to_string(),
+    id,
+  })
+}
+
+/// The first value of `key` in a Valve KeyValues file, where each line of
+/// interest reads `"key"  "value"`.
+fn vdf_value(text: &str, key: &str) -> Option<String> {
+  text.lines().find_map(|line| {
+    let mut quoted = line.split('"').skip(1).step_by(2);
+    (quoted.next()? == key).then(|| quoted.next()).flatten().map(str::to_string)
+  })
+}
+
+fn gog_game(root: &ScanNode, root_path: &Path, manifest: &Path) -> Option<Game> {
+  let info = std::fs::read(manifest).ok()?;
+  let info: serde_json::Value = serde_json::from_slice(&info).ok()?;
+  let path = manifest.parent()?;
+  let id = match &info["gameId"] {
+    serde_json::Value::String(id) => id.clone(),
+    other => other.to_string(),
+  };
+  Some(Game {
+    store: GameStore::Gog,
+    name: info["name"].as_str().map(str::to_string).unwrap_or_else(|| id.clone()),
+    id,
+    path: path.to_string_lossy().to_string(),
+    library: None,
+    install_bytes: subtree_bytes(root, root_path, path),
+    shader_cache_bytes: 0,
+    workshop_bytes: 0,
+    last_updated: None,
+  })
+}
+
+/// Epic installs under the scan's root, from the `.item` manifests of the
+/// Epic Games Launcher.
+fn epic_games(root: &ScanNode, root_path: &Path) -> Vec<Game> {
+  let Some(dir) = epic_manifests() else {
+    return Vec::new();
+  };
+  std::fs::read_dir(dir)
+    .into_iter()
+    .flatten()
+    .flatten()
+    .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "item"))
+    .filter_map(|entry| {
+      let item = std::fs::read(entry.path()).ok()?;
+      let item: serde_json::Value = serde_json::from_slice(&item).ok()?;
+      let path = PathBuf::from(item["InstallLocation"].as_str()?);
+      if !path.starts_with(root_path) || !path.is_dir() {
+        return None;
+      }
+      let id = item["AppName"].as_str().unwrap_or_default().to_string();
+      Some(Game {
+        store: GameStore::Epic,
+        name: item["DisplayName"].as_str().map(str::to_string).unwrap_or_else(|| id.clone()),
+        id,
+        install_bytes: subtree_bytes(root, root_path, &path),
+        path: path.to_string_lossy().to_string(),
+        library: None,
+        shader_cache_bytes: 0,
+        workshop_bytes: 0,
+        last_updated: None,
+      })
+    })
+    .collect()
+}
+
+fn epic_manifests() -> Option<PathBuf> {
+  let data = if cfg!(windows) {
+    PathBuf::from(std::env::var_os("ProgramData")?).join("Epic")
+  } else if cfg!(target_os = "macos") {
+    crate::paths::home_dir()?.join("Library/Application Support/Epic")
+  } else {
+    return None;
+  };
+  Some(data.join("EpicGamesLauncher").join("Data").join("Manifests"))
+}
+
+/// Games found in a finished scan, the most bytes first, counting Steam's
+/// shader cache and Workshop downloads for each.
+#[tauri::command]
+pub async fn get_games(
+  state: State<'_, ScanManager>,
+  scan_id: String,
+) -> Result<Vec<Game>, String> {
+  Ok(state.results(&scan_id)?.games.clone())
+}
@@ -0,0 +1,116 @@
+//! Git repositories found by the walk, with how their space splits between
+//! the working tree and `.git`, and signs that `git gc` or a shallow clone
+//! would shrink them.
+
+use super::{subtree_bytes, visit_files, ScanContext, ScanManager, ScanNode};
+use rayon::prelude::*;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use tauri::State;
+
+/// Pack files listed per repository, largest first.
+const MAX_PACKS: usize = 5;
+/// Loose objects past which `git gc --auto` would pack them (`gc.auto`).
+const GC_LOOSE_OBJECTS: u64 = 6700;
+/// Packs past which `git gc --auto` would merge them (`gc.autoPackLimit`).
+const GC_PACKS: u64 = 50;
+
+#[derive(Clone, Serialize)]
+pub struct PackFile {
+  pub path: String,
+  pub size: u64,
+}
+
+#[derive(Clone, Serialize)]
+pub struct GitRepo {
+  pub path: String,
+  /// Bytes of the repository outside `.git`.
+  pub worktree_bytes: u64,
+  pub git_bytes: u64,
+  pub loose_objects: u64,
+  pub packs: u64,
+  pub largest_packs: Vec<PackFile>,
+  /// Cloned with `--depth`, so its history is already cut short.
+  pub shallow: bool,
+  /// Enough loose objects or packs that `git gc` would shrink `.git`.
+  pub gc_suggested: bool,
+}
+
+/// What `.git` holds, from reading it.
+#[derive(Default)]
+struct GitDirFacts {
+  bytes: u64,
+  loose_objects: u64,
+  packs: Vec<PackFile>,
+}
+
+/// Reports on the repositories of the `.git` directories the walk found,
+/// the most `.git` bytes first.
+pub(super) fn report(
+  ctx: &ScanContext,
+  root: &ScanNode,
+  root_path: &Path,
+  git_dirs: Vec<PathBuf>,
+) -> Vec<GitRepo> {
+  let mut repos: Vec<GitRepo> = git_dirs
+    .into_par_iter()
+    .filter_map(|git_dir| {
+      if ctx.cancelled() {
+        return None;
+      }
+      let repo = git_dir.parent()?;
+      let facts = read_git_dir(&git_dir);
+      let repo_bytes = subtree_bytes(root, root_path, repo);
+      let mut packs = facts.packs;
+      packs.sort_by_key(|pack| std::cmp::Reverse(pack.size));
+      let pack_count = packs.len() as u64;
+      packs.truncate(MAX_PACKS);
+      Some(GitRepo {
+        path: repo.to_string_lossy().to_string(),
+        worktree_bytes: repo_bytes.saturating_sub(facts.bytes),
+        git_bytes: facts.bytes,
+        loose_objects: facts.loose_objects,
+        packs: pack_count,
+        largest_packs: packs,
+        shallow: git_dir.join("shallow").is_file(),
+        gc_suggested: facts.loose_objects > GC_LOOSE_OBJECTS || pack_count > GC_PACKS,
+      })
+    })
+    .collect();
+  repos.sort_by_key(|repo| std::cmp::Reverse(repo.git_bytes));
+  repos
+}
+
+/// Sums up `.git`, counting loose objects and noting packs on the way.
+fn read_git_dir(git_dir: &Path) -> GitDirFacts {
+  let mut facts = GitDirFacts::default();
+  let objects = git_dir.join("objects");
+  let pack_dir = objects.join("pack");
+  visit_files(git_dir, &mut |path, size| {
+    facts.bytes += size;
+    let parent = path.parent();
+    if parent == Some(pack_dir.as_path()) {
+      if path.extension().is_some_and(|ext| ext == "pack") {
+        facts.packs.push(PackFile {
+          path: path.to_string_lossy().to_string(),
+          size,
+        });
+      }
+    } else if parent.and_then(Path::parent) == Some(objects.as_path()) {
+      // Loose objects sit in objects/<first two hex digits>/.
+      facts.loose_objects += 1;
+    }
+  });
+  facts
+}
+
+/// Git repositories of a finished scan, the most `.git` bytes first. Only
+/// those whose `.git` the walk came across are listed: with hidden entries
+/// left out, that takes `track_hidden_bytes`.
+#[tauri::command]
+pub async fn get_git_repos(
+  state: State<'_, ScanManager>,
+  scan_id: String,
+) -> Result<Vec<GitRepo>, String> {
+  Ok(state.results(&scan_id)?.git_repos.clone())
+}
@@ -0,0 +1,157 @@
+//! Telling apart the entries `.gitignore` and `.ignore` files ignore, for
+//! scans that leave them out or keep only them. Each directory's files are
+//! read once, the first time an entry below it is looked at; the deepest
+//! rule that matches an entry decides, as in git.
+
+use ignore::{
+  gitignore::{Gitignore, GitignoreBuilder},
+  Match,
+};
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::{
+  collections::{HashMap, HashSet},
+  path::{Path, PathBuf},
+  sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+  },
+};
+
+/// What a scan does with the entries ignore files match.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IgnoreMode {
+  /// Ignore files aren't read.
+  #[default]
+  Off,
+  /// Ignored entries are left out of the tree.
+  Exclude,
+  /// Only ignored entries make up the tree, with the directories holding
+  /// them.
+  Only,
+}
+
+/// Bytes and files on either side of the ignore rules, for scans with an
+/// `IgnoreMode` other than `Off`.
+#[derive(Clone, Copy, Serialize)]
+pub struct IgnoreTotals {
+  pub ignored_bytes: u64,
+  pub ignored_files: u64,
+  pub kept_bytes: u64,
+  pub kept_files: u64,
+}
+
+/// What the ignore files of one directory hold.
+#[derive(Clone)]
+struct DirRules {
+  rules: Option<Arc<Gitignore>>,
+  // It has a `.git`, so the rules of the directories above don't apply.
+  repo_root: bool,
+}
+
+/// The ignore rules of one scan, and the totals it found.
+pub(super) struct IgnoreRules {
+  mode: IgnoreMode,
+  // The rules of each directory looked at.
+  rules: RwLock<HashMap<PathBuf, DirRules>>,
+  // Ignored directories, whose entries are all ignored in turn.
+  ignored_dirs: RwLock<HashSet<PathBuf>>,
+  ignored: (AtomicU64, AtomicU64),
+  kept: (AtomicU64, AtomicU64),
+}
+
+impl IgnoreRules {
+  pub(super) fn new(mode: IgnoreMode) -> Option<Self> {
+    (mode != IgnoreMode::Off).then(|| Self {
+      mode,
+      rules: RwLock::default(),
+      ignored_dirs: RwLock::default(),
+      ignored: Default::default(),
+      kept: Default::default(),
+    })
+  }
+
+  /// Whether the entry at `path` is set aside from the tree: ignored ones
+  /// with `Exclude`, and files that aren't with `Only`. Directories that
+  /// aren't ignored are always walked, since with `Only` they may hold
+  /// ignored entries.
+  pub(super) fn sets_aside(&self, path: &Path, is_dir: bool) -> bool {
+    let ignored = self.is_ignored(path, is_dir);
+    match self.mode {
+      IgnoreMode::Off => false,
+      IgnoreMode::Exclude => ignored,
+      IgnoreMode::Only => !ignored && !is_dir,
+    }
+  }
+
+  fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+    let Some(parent) = path.parent() else {
+      return false;
+    };
+    let ignored =
+      self.ignored_dirs.read().contains(parent) || self.deepest_match(parent, path, is_dir);
+    if ignored && is_dir {
+      self.ignored_dirs.write().insert(path.to_path_buf());
+    }
+    ignored
+  }
+
+  /// Whether the deepest rule matching `path`, from `dir` up, ignores it.
+  fn deepest_match(&self, dir: &Path, path: &Path, is_dir: bool) -> bool {
+    for dir in dir.ancestors() {
+      let found = self.rules_of(dir);
+      if let Some(rules) = &found.rules {
+        match rules.matched(path, is_dir) {
+          Match::Ignore(_) => return true,
+          Match::Whitelist(_) => return false,
+          Match::None => {}
+        }
+      }
+      if found.repo_root {
+        break;
+      }
+    }
+    false
+  }
+
+  /// The rules the ignore files of `dir` hold, read on first use.
+  fn rules_of(&self, dir: &Path) -> DirRules {
+    if let Some(found) = self.rules.read().get(dir) {
+      return found.clone();
+    }
+    let mut builder = GitignoreBuilder::new(dir);
+    let mut any = false;
+    for name in [".gitignore", ".ignore", ".git/info/exclude"] {
+      let file = dir.join(name);
+      // A file that can't be read or parsed adds no rules.
+      any |= file.is_file() && builder.add(file).is_none();
+    }
+    let found = DirRules {
+      rules: any.then(|| builder.build().ok().map(Arc::new)).flatten(),
+      repo_root: dir.join(".git").exists(),
+    };
+    self.rules.write().insert(dir.to_path_buf(), found.clone());
+    found
+  }
+
+  /// Counts a file of `bytes` that was set aside or not.
+  pub(super) fn count(&self, aside: bool, bytes: u64) {
+    let ignored = match self.mode {
+      IgnoreMode::Only => !aside,
+      _ => aside,
+    };
+    let (total, files) = if ignored { &self.ignored } else { &self.kept };
+    total.fetch_add(bytes, Ordering::Relaxed);
+    files.fetch_add(1, Ordering::Relaxed);
+  }
+
+  pub(super) fn totals(&self) -> IgnoreTotals {
+    IgnoreTotals {
+      ignored_bytes: self.ignored.0.load(Ordering::Relaxed),
+      ignored_files: self.ignored.1.load(Ordering::Relaxed),
+      kept_bytes: self.kept.0.load(Ordering::Relaxed),
+      kept_files: self.kept.1.load(Ordering::Relaxed),
+    }
+  }
+}
@@ -0,0 +1,294 @@
+//! Finished scans kept across runs, in a SQLite database in the app's data
+//! folder: a row of totals per scan and, for scans that set
+//! `ScanOptions::history_tree`, the whole tree. Cancelled scans and imported
+//! captures aren't kept.
+
+use super::{
+  blocking, query::NodeSummary, report_error, ScanManager, ScanNode, ScanOptions, ScanResults,
+  ScanSource,
+};
+use parking_lot::Mutex;
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::Serialize;
+use std::{
+  path::Path,
+  time::{SystemTime, UNIX_EPOCH},
+};
+use tauri::{AppHandle, Manager, State};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS scans (
+  id INTEGER PRIMARY KEY,
+  root TEXT NOT NULL,
+  volume TEXT,
+  finished INTEGER NOT NULL,
+  elapsed_ms INTEGER NOT NULL,
+  size INTEGER NOT NULL,
+  allocated INTEGER NOT NULL,
+  files INTEGER NOT NULL,
+  dirs INTEGER NOT NULL,
+  errors INTEGER NOT NULL,
+  volume_total INTEGER,
+  volume_free INTEGER,
+  options TEXT NOT NULL,
+  tree BLOB
+);
+CREATE INDEX IF NOT EXISTS scans_by_root ON scans (root, finished);
+CREATE INDEX IF NOT EXISTS scans_by_volume ON scans (volume, finished);
+";
+
+/// What `entry` reads, in its order.
+const ENTRY_COLUMNS: &str = "id, root, volume, finished, elapsed_ms, size, allocated, files, \
+  dirs, errors, volume_total, volume_free, tree IS NOT NULL";
+
+/// A scan in the history, without its tree.
+#[derive(Clone, Serialize)]
+pub struct HistoryEntry {
+  pub id: i64,
+  pub root: String,
+  /// Where the volume holding a local root is mounted.
+  pub volume: Option<String>,
+  /// When the scan ended, in seconds since the Unix epoch.
+  pub finished: i64,
+  pub elapsed_ms: u64,
+  pub size: u64,
+  pub allocated: u64,
+  pub file_count: u64,
+  pub dir_count: u64,
+  pub error_count: u64,
+  /// Capacity and free space of that volume as the scan ended.
+  pub volume_total: Option<u64>,
+  pub volume_free: Option<u64>,
+  pub has_tree: bool,
+}
+
+#[derive(Serialize)]
+pub struct HistoryScan {
+  #[serde(flatten)]
+  pub entry: HistoryEntry,
+  /// The root of the stored tree, `None` unless the scan stored it. The tree
+  /// is kept like a finished scan's under `scan_id`, for `get_children`.
+  pub tree: Option<NodeSummary>,
+  pub scan_id: Option<String>,
+}
+
+/// The database, opened the first time it's needed.
+#[derive(Default)]
+pub struct History {
+  db: Mutex<Option<Connection>>,
+}
+
+impl History {
+  fn with<T>(
+    &self,
+    app: &AppHandle,
+    query: impl FnOnce(&Connection) -> rusqlite::Result<T>,
+  ) -> Result<T, String> {
+    let mut db = self.db.lock();
+    let conn = match db.take() {
+      Some(conn) => conn,
+      None => open(app)?,
+    };
+    query(db.insert(conn)).map_err(|e| format!("Scan history: {e}"))
+  }
+}
+
+/// `history.sqlite3` in the app's data folder, its tables made if it's new.
+fn open(app: &AppHandle) -> Result<Connection, String> {
+  let dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+  std::fs::create_dir_all(&dir).map_err(|e| format!("{}: {e}", dir.display()))?;
+  let path = dir.join("history.sqlite3");
+  let conn = Connection::open(&path).map_err(|e| format!("{}: {e}", path.display()))?;
+  conn.execute_batch(SCHEMA).map_err(|e| format!("{}: {e}", path.display()))?;
+  Ok(conn)
+}
+
+fn entry(row: &Row) -> rusqlite::Result<HistoryEntry> {
+  // SQLite's integers are signed; sizes fit them with room to spare.
+  let number = |at: usize| row.get::<_, i64>(at).map(|n| n as u64);
+  let maybe = |at: usize| row.get::<_, Option<i64>>(at).map(|n| n.map(|n| n as u64));
+  Ok(HistoryEntry {
+    id: row.get(0)?,
+    root: row.get(1)?,
+    volume: row.get(2)?,
+    finished: row.get(3)?,
+    elapsed_ms: number(4)?,
+    size: number(5)?,
+    allocated: number(6)?,
+    file_count: number(7)?,
+    dir_count: number(8)?,
+    error_count: number(9)?,
+    volume_total: maybe(10)?,
+    volume_free: maybe(11)?,
+    has_tree: row.get(12)?,
+  })
+}
+
+/// Adds a scan that just finished. A scan is over by then, so a history that
+/// can't be written is reported through `background_error`.
+pub(super) fn record(
+  app: &AppHandle,
+  source: &ScanSource,
+  results: &ScanResults,
+  options: &ScanOptions,
+  elapsed_ms: u64,
+) {
+  if results.partial || matches!(source, ScanSource::Import(_)) {
+    return;
+  }
+  let Some(history) = app.try_state::<History>() else {
+    return;
+  };
+  let root = &results.tree;
+  let volume = source.is_local().then(|| crate::volume::volume_of(Path::new(&results.root)));
+  let finished = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs() as i64);
+  let options_json = serde_json::to_string(options).unwrap_or_default();
+  let tree = match options.history_tree {
+    true => rmp_serde::to_vec_named(root).ok(),
+    false => None,
+  };
+  let inserted = history.with(app, |db| {
+    db.execute(
+      "INSERT INTO scans (root, volume, finished, elapsed_ms, size, allocated, files, dirs, \
+       errors, volume_total, volume_free, options, tree) \
+       VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+      params![
+        results.root,
+        volume.flatten(),
+        finished,
+        elapsed_ms as i64,
+        root.size as i64,
+        root.allocated as i64,
+        root.file_count.unwrap_or(0) as i64,
+        root.dir_count.unwrap_or(0) as i64,
+        results.error_summary.total as i64,
+        results.volume.map(|usage| usage.total_bytes as i64),
+        results.volume.map(|usage| usage.free_bytes as i64),
+        options_json,
+        tree,
+      ],
+    )
+  });
+  if let Err(e) = inserted {
+    report_error(app, e);
+  }
+}
+
+/// Past scans, newest first: those of the folder at `path`, those of
+/// anything on the volume mounted at `volume`, or all of them.
+#[tauri::command]
+pub async fn list_scan_history(
+  app: AppHandle,
+  history: State<'_, History>,
+  path: Option<String>,
+  volume: Option<String>,
+) -> Result<Vec<HistoryEntry>, String> {
+  history.with(&app, |db| {
+    let mut statement = db.prepare(&format!(
+      "SELECT {ENTRY_COLUMNS} FROM scans \
+       WHERE (?1 IS NULL OR root = ?1) AND (?2 IS NULL OR volume = ?2) \
+       ORDER BY finished DESC, id DESC"
+    ))?;
+    let entries = statement.query_map(params![path, volume], entry)?;
+    entries.collect()
+  })
+}
+
+fn load(
+  app: &AppHandle,
+  history: &History,
+  id: i64,
+) -> Result<(HistoryEntry, Option<ScanNode>), String> {
+  let found = history.with(app, |db| {
+    db.query_row(
+      &format!("SELECT {ENTRY_COLUMNS}, tree FROM scans WHERE id = ?1"),
+      [id],
+      |row| Ok((entry(row)?, row.get::<_, Option<Vec<u8>>>(13)?)),
+    )
+    .optional()
+  })?;
+  let (entry, tree) = found.ok_or("This scan isn't in the history")?;
+  let tree = tree
+    .map(|bytes| rmp_serde::from_slice(&bytes))
+    .transpose()
+    .map_err(|e| format!("The stored tree can't be read: {e}"))?;
+  Ok((entry, tree))
+}
+
+/// The root and tree of the scan `id` of the history, for scans that
+/// stored it.
+pub(super) fn saved_tree(
+  app: &AppHandle,
+  history: &History,
+  id: i64,
+) -> Result<(String, ScanNode), String> {
+  let (entry, tree) = load(app, history, id)?;
+  let tree = tree.ok_or("Only the totals of that scan were kept")?;
+  Ok((entry.root, tree))
+}
+
+/// When each of the latest `limit` scans of `root` that stored their tree
+/// ended, and that tree, oldest first.
+pub(super) fn saved_trees(
+  app: &AppHandle,
+  history: &History,
+  root: &str,
+  limit: usize,
+) -> Result<Vec<(i64, ScanNode)>, String> {
+  let rows = history.with(app, |db| {
+    let mut statement = db.prepare(
+      "SELECT finished, tree FROM scans WHERE root = ?1 AND tree IS NOT NULL \
+       ORDER BY finished DESC, id DESC LIMIT ?2",
+    )?;
+    let rows = statement.query_map(params![root, limit as i64], |row| {
+      Ok((row.get::<_, i64>(0)?, row.get::<_, Vec<u8>>(1)?))
+    })?;
+    rows.collect::<rusqlite::Result<Vec<_>>>()
+  })?;
+  let mut trees = Vec::with_capacity(rows.len());
+  for (finished, bytes) in rows.into_iter().rev() {
+    let tree = rmp_serde::from_slice(&bytes)
+      .map_err(|e| format!("A stored tree of {root} can't be read: {e}"))?;
+    trees.push((finished, tree));
+  }
+  Ok(trees)
+}
+
+/// A scan of the history, with the root of its tree if it was stored.
+#[tauri::command]
+pub async fn get_history_scan(app: AppHandle, id: i64) -> Result<HistoryScan, String> {
+  // A stored tree can take a while to read and unpack.
+  blocking(move || {
+    let (entry, tree) = load(&app, &app.state::<History>(), id)?;
+    let Some(tree) = tree else {
+      return Ok(HistoryScan {
+        entry,
+        tree: None,
+        scan_id: None,
+      });
+    };
+    let state = app.state::<ScanManager>();
+    let scan_id = format!("history-{}", id);
+    // Opened before and still kept, the ids the UI has stay good.
+    if state.results(&scan_id).is_err() {
+      state.keep_results(&scan_id, ScanResults::of_tree(entry.root.clone(), tree));
+    }
+    let root = NodeSummary::of(&state.results(&scan_id)?.tree);
+    Ok(HistoryScan {
+      entry,
+      tree: Some(root),
+      scan_id: Some(scan_id),
+    })
+  })
+  .await
+}
+
+#[tauri::command]
+pub async fn delete_history_scan(
+  app: AppHandle,
+  history: State<'_, History>,
+  id: i64,
+) -> Result<(), String> {
+  history.with(&app, |db| db.execute("DELETE FROM scans WHERE id = ?1", [id]))?;
+  Ok(())
+}
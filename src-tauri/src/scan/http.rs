@@ -0,0 +1,99 @@
+//! The HTTP client of the scans that list storage over the network, and the
+//! bits of URL and XML handling they share. TLS goes through the OS
+//! (SChannel, Secure Transport, OpenSSL) so its certificate store, corporate
+//! roots included, is trusted.
+
+use super::{iso::days_from_civil, ScanContext};
+use std::time::{Duration, Instant};
+use ureq::{
+  tls::{TlsConfig, TlsProvider},
+  Agent,
+};
+
+/// An agent that returns error statuses and redirects as responses, for the
+/// caller to read the service's own explanation from. It sends WebDAV's
+/// methods too.
+pub(super) fn agent() -> Agent {
+  let tls = TlsConfig::builder().provider(TlsProvider::NativeTls).build();
+  Agent::config_builder()
+    .tls_config(tls)
+    .allow_non_standard_methods(true)
+    .http_status_as_error(false)
+    .max_redirects(0)
+    .timeout_connect(Some(Duration::from_secs(15)))
+    .timeout_recv_response(Some(Duration::from_secs(60)))
+    .user_agent(concat!("space-usage/", env!("CARGO_PKG_VERSION")))
+    .build()
+    .into()
+}
+
+/// Percent-encodes all but the unreserved characters of RFC 3986.
+pub(super) fn percent_encode(text: &str) -> String {
+  let mut encoded = String::with_capacity(text.len());
+  for byte in text.bytes() {
+    match byte {
+      b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+        encoded.push(byte as char)
+      }
+      _ => encoded.push_str(&format!("%{:02X}", byte)),
+    }
+  }
+  encoded
+}
+
+/// Undoes `%XX` escapes; a `%` not followed by two hex digits stays.
+pub(super) fn percent_decode(text: &str) -> String {
+  let bytes = text.as_bytes();
+  let mut decoded = Vec::with_capacity(bytes.len());
+  let mut i = 0;
+  while i < bytes.len() {
+    let escaped = (bytes[i] == b'%')
+      .then(|| text.get(i + 1..i + 3))
+      .flatten()
+      .and_then(|digits| u8::from_str_radix(digits, 16).ok());
+    match escaped {
+      Some(byte) => {
+        decoded.push(byte);
+        i += 3;
+      }
+      None => {
+        decoded.push(bytes[i]);
+        i += 1;
+      }
+    }
+  }
+  String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// The text of the child element `name` of `node`, whatever its namespace.
+pub(super) fn xml_text(node: roxmltree::Node, name: &str) -> Option<String> {
+  let child = node.children().find(|child| child.has_tag_name(name))?;
+  Some(child.text().unwrap_or_default().to_string())
+}
+
+/// `Wed, 12 Oct 2016 09:21:43 GMT`, the date format of HTTP, in seconds
+/// since the Unix epoch.
+pub(super) fn parse_http_date(text: &str) -> Option<i64> {
+  const MONTHS: [&str; 12] =
+    ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+  let mut parts = text.split_whitespace().skip(1);
+  let day = parts.next()?.parse::<i64>().ok()?;
+  let month = parts.next()?;
+  let month = MONTHS.iter().position(|name| *name == month)? as i64 + 1;
+  let year = parts.next()?.parse::<i64>().ok()?;
+  let mut time = parts.next()?.split(':').map(|part| part.parse::<i64>().ok());
+  let (hour, minute, second) = (time.next()??, time.next()??, time.next()??);
+  Some(days_from_civil(year, month, day) * 86_400 + hour * 3600 + minute * 60 + second)
+}
+
+/// Sleeps for `duration` unless the scan is cancelled in the meantime.
+pub(super) fn pause(ctx: &ScanContext, duration: Duration) {
+  let until = Instant::now() + duration;
+  while !ctx.cancelled() {
+    let left = until.saturating_duration_since(Instant::now());
+    if left.is_zero() {
+      return;
+    }
+    std::thread::sleep(left.min(Duration::from_millis(100)));
+  }
+}
@@ -0,0 +1,216 @@
+//! Images that look alike though their bytes differ, such as one photo saved
+//! at several qualities or sizes, for scans run with `find_similar_images`.
+//! Each image is shrunk to a 9×8 grayscale thumbnail, and the 64 left-right
+//! brightness steps of it make up its hash; hashes a few bits apart are taken
+//! for the same picture.
+
+use super::{
+  duplicates::{HashStage, StageProgress},
+  is_online_only, ScanContext, ScanManager,
+};
+use image::{ImageError, ImageFormat, ImageReader};
+use rayon::prelude::*;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use tauri::State;
+
+/// Most bits two hashes may differ by for their images to count as alike.
+const MAX_DISTANCE: u32 = 5;
+
+#[derive(Clone, Serialize)]
+pub struct SimilarImage {
+  pub path: String,
+  pub size: u64,
+  pub width: u32,
+  pub height: u32,
+}
+
+/// Images that look alike, the most pixels first.
+#[derive(Clone, Serialize)]
+pub struct SimilarImages {
+  pub images: Vec<SimilarImage>,
+  /// Bytes freed by keeping only the first.
+  pub reclaimable: u64,
+}
+
+/// An image read, with its hash.
+struct Hashed {
+  file: usize,
+  hash: u64,
+  width: u32,
+  height: u32,
+}
+
+/// Groups the image files counted by the walk, given with their sizes, the
+/// most space to reclaim first. Formats that can't be decoded are left out.
+pub(super) fn find(ctx: &ScanContext, files: Vec<(PathBuf, u64)>) -> Vec<SimilarImages> {
+  let files: Vec<_> = files.into_iter().filter(|(path, _)| decodable(path)).collect();
+  let bytes = files.iter().map(|(_, size)| size).sum();
+  let progress = StageProgress::start(ctx, HashStage::Images, files.len() as u64, bytes);
+  let hashed: Vec<Hashed> = files
+    .par_iter()
+    .enumerate()
+    .filter_map(|(i, (path, size))| {
+      if ctx.cancelled() {
+        return None;
+      }
+      let hashed = hash_image(path);
+      progress.add_file();
+      progress.add_bytes(*size);
+      match hashed {
+        Ok(hashed) => hashed.map(|(hash, width, height)| Hashed {
+          file: i,
+          hash,
+          width,
+          height,
+        }),
+        Err(e) => {
+          ctx.record_io_error(path, &e);
+          None
+        }
+      }
+    })
+    .collect();
+  progress.emit();
+
+  let mut groups: Vec<SimilarImages> = clusters(&hashed)
+    .into_iter()
+    .map(|members| {
+      let mut images: Vec<SimilarImage> = members
+        .into_iter()
+        .map(|i| {
+          let image = &hashed[i];
+          let (path, size) = &files[image.file];
+          SimilarImage {
+            path: path.to_string_lossy().to_string(),
+            size: *size,
+            width: image.width,
+            height: image.height,
+          }
+        })
+        .collect();
+      images.sort_by(|a, b| {
+        let pixels = |image: &SimilarImage| image.width as u64 * image.height as u64;
+        pixels(b).cmp(&pixels(a)).then(b.size.cmp(&a.size)).then(a.path.cmp(&b.path))
+      });
+      let reclaimable = images[1..].iter().map(|image| image.size).sum();
+      SimilarImages {
+        images,
+        reclaimable,
+      }
+    })
+    .collect();
+  groups.sort_by_key(|group| std::cmp::Reverse(group.reclaimable));
+  groups
+}
+
+/// Whether `path` is named like an image this build can decode.
+fn decodable(path: &Path) -> bool {
+  ImageFormat::from_path(path).is_ok_and(|format| format.reading_enabled())
+}
+
+/// The hash and dimensions of the image at `path`; `None` for online-only
+/// files, which are never read, and for files that don't decode.
+fn hash_image(path: &Path) -> std::io::Result<Option<(u64, u32, u32)>> {
+  if is_online_only(&std::fs::symlink_metadata(path)?) {
+    return Ok(None);
+  }
+  let image = match ImageReader::open(path)?.with_guessed_format()?.decode() {
+    Ok(image) => image,
+    Err(ImageError::IoError(e)) => return Err(e),
+    Err(_) => return Ok(None),
+  };
+  let thumbnail = image.thumbnail_exact(9, 8).into_luma8();
+  let mut hash = 0u64;
+  for y in 0..8 {
+    for x in 0..8 {
+      let brighter = thumbnail.get_pixel(x + 1, y)[0] > thumbnail.get_pixel(x, y)[0];
+      hash = hash << 1 | brighter as u64;
+    }
+  }
+  Ok(Some((hash, image.width(), image.height())))
+}
+
+/// Groups of `hashed` indices whose hashes are within `MAX_DISTANCE` of one
+/// another, directly or through other members. A BK-tree keeps this from
+/// comparing every pair.
+fn clusters(hashed: &[Hashed]) -> Vec<Vec<usize>> {
+  // (index into `hashed`, children by distance) per node; the first is the root.
+  let mut tree: Vec<(usize, Vec<(u32, usize)>)> = Vec::with_capacity(hashed.len());
+  let mut parents: Vec<usize> = (0..hashed.len()).collect();
+  for i in 0..hashed.len() {
+    let hash = hashed[i].hash;
+    let mut near = Vec::new();
+    let mut stack = if tree.is_empty() { vec![] } else { vec![0] };
+    while let Some(node) = stack.pop() {
+      let (member, children) = &tree[node];
+      let distance = (hashed[*member].hash ^ hash).count_ones();
+      if distance <= MAX_DISTANCE {
+        near.push(*member);
+      }
+      for &(edge, child) in children {
+        if edge.abs_diff(distance) <= MAX_DISTANCE {
+          stack.push(child);
+        }
+      }
+    }
+    for other in near {
+      union(&mut parents, i, other);
+    }
+    insert(&mut tree, hashed, i);
+  }
+
+  let mut groups: std::collections::HashMap<usize, Vec<usize>> = Default::default();
+  for i in 0..hashed.len() {
+    let root = find_root(&mut parents, i);
+    groups.entry(root).or_default().push(i);
+  }
+  groups.into_values().filter(|members| members.len() > 1).collect()
+}
+
+fn insert(tree: &mut Vec<(usize, Vec<(u32, usize)>)>, hashed: &[Hashed], i: usize) {
+  let new = tree.len();
+  tree.push((i, Vec::new()));
+  if new == 0 {
+    return;
+  }
+  let mut node = 0;
+  loop {
+    let distance = (hashed[tree[node].0].hash ^ hashed[i].hash).count_ones();
+    match tree[node].1.iter().find(|(edge, _)| *edge == distance) {
+      Some(&(_, child)) => node = child,
+      None => {
+        tree[node].1.push((distance, new));
+        return;
+      }
+    }
+  }
+}
+
+fn find_root(parents: &mut [usize], mut i: usize) -> usize {
+  while parents[i] != i {
+    parents[i] = parents[parents[i]];
+    i = parents[i];
+  }
+  i
+}
+
+fn union(parents: &mut [usize], a: usize, b: usize) {
+  let (a, b) = (find_root(parents, a), find_root(parents, b));
+  parents[a.max(b)] = a.min(b);
+}
+
+/// Groups of images in a finished `find_similar_images` scan that look alike,
+/// the most space to reclaim first, each listed from the most pixels down.
+#[tauri::command]
+pub async fn get_similar_images(
+  state: State<'_, ScanManager>,
+  scan_id: String,
+) -> Result<Vec<SimilarImages>, String> {
+  let results = state.results(&scan_id)?;
+  let found = results
+    .similar_images
+    .as_ref()
+    .ok_or_else(|| "The scan didn't look for similar images".to_string())?;
+  Ok(found.clone())
+}
@@ -0,0 +1,436 @@
+//! Scans captured elsewhere, read back from a file: an ncdu export (`ncdu -o`),
+//! what `du -ab` prints or a WizTree CSV export. The file is read when the
+//! import is asked for, so a malformed one is refused right away; the tree is
+//! then built as a scan of its own, under the path it was captured at, with
+//! the filters and limits of the options applied as they would be to a walk.
+//!
+//! `du` and WizTree list every file and folder with its total. WizTree marks
+//! folders; with `du` a folder is whatever has entries below it, and an
+//! empty one looks like a small file.
+
+use super::{
+  blocking, dir_node, file_node, iso::days_from_civil, maybe_emit_progress, spawn_scan_from,
+  FileSizes, ScanContext, ScanErrorKind, ScanManager, ScanNode, ScanOptions, ScanSource,
+};
+use serde_json::Value;
+use std::{
+  collections::BTreeMap,
+  path::{Path, PathBuf},
+  sync::atomic::Ordering,
+};
+use tauri::{AppHandle, State};
+
+/// An entry of a capture, with sizes of its own: a folder's are those of the
+/// folder itself, not of what it holds.
+struct Entry {
+  name: String,
+  asize: u64,
+  dsize: u64,
+  mtime: Option<i64>,
+  /// `None` for a file.
+  children: Option<Vec<Entry>>,
+  /// The folder couldn't be read in full when it was captured.
+  read_error: bool,
+}
+
+/// A capture read from its file, ready to be built into a tree.
+pub(super) struct Capture {
+  root: Entry,
+  /// Between the parts of its paths: `\\` for WizTree, `/` otherwise.
+  separator: char,
+}
+
+/// `[1, minor, {metadata}, [{root info}, …]]`.
+fn parse_ncdu(text: &str) -> Result<Entry, String> {
+  let export: Value =
+    serde_json::from_str(text).map_err(|e| format!("Not a valid ncdu export: {e}"))?;
+  let parts = export.as_array().map(Vec::as_slice).unwrap_or_default();
+  match parts {
+    [major, _, _, root, ..] if major.as_u64() == Some(1) => {
+      ncdu_entry(root).ok_or_else(|| "The ncdu export has no root folder".to_string())
+    }
+    [major, ..] if major.is_u64() && major.as_u64() != Some(1) => {
+      Err(format!("ncdu export version {major} isn't supported"))
+    }
+    _ => Err("Not a valid ncdu export".to_string()),
+  }
+}
+
+/// A folder is an array led by its info; a file is its info alone. Entries
+/// ncdu left out (`excluded`) hold nothing and are skipped.
+fn ncdu_entry(value: &Value) -> Option<Entry> {
+  let (info, rest) = match value {
+    Value::Array(items) => {
+      let (info, rest) = items.split_first()?;
+      (info, Some(rest))
+    }
+    info => (info, None),
+  };
+  if info.get("excluded").is_some() {
+    return None;
+  }
+  let number = |key: &str| info.get(key).and_then(Value::as_u64).unwrap_or(0);
+  Some(Entry {
+    name: info.get("name")?.as_str()?.to_string(),
+    asize: number("asize"),
+    dsize: number("dsize"),
+    mtime: info.get("mtime").and_then(Value::as_i64),
+    children: rest.map(|rest| rest.iter().filter_map(ncdu_entry).collect()),
+    read_error: info.get("read_error").and_then(Value::as_bool).unwrap_or(false),
+  })
+}
+
+/// A path of a listing that gives totals, as `du` and WizTree do, and what's
+/// below it, before folders and files are told apart.
+#[derive(Default)]
+struct Totals {
+  asize: u64,
+  dsize: u64,
+  mtime: Option<i64>,
+  /// Known to be a folder, even with nothing below it.
+  dir: bool,
+  below: BTreeMap<String, Totals>,
+}
+
+impl Totals {
+  /// The totals at `relative`, made if they aren't there.
+  fn at(&mut self, relative: &str, separator: char) -> &mut Totals {
+    let mut at = self;
+    for part in relative.split(separator).filter(|part| !part.is_empty()) {
+      at = at.below.entry(part.to_string()).or_default();
+    }
+    at
+  }
+}
+
+/// `size<TAB>path` lines, the path `du` was given last.
+fn parse_du(text: &str) -> Result<Entry, String> {
+  let mut lines = Vec::new();
+  for line in text.lines().filter(|line| !line.trim().is_empty()) {
+    let parsed = line.split_once('\t').and_then(|(size, path)| Some((size.parse().ok()?, path)));
+    let Some((size, path)) = parsed else {
+      return Err(format!("Not du output: {line:?}"));
+    };
+    lines.push((size, path));
+  }
+  let Some(&(_, root)) = lines.last() else {
+    return Err("The du output is empty".to_string());
+  };
+  let prefix = format!("{}/", root.trim_end_matches('/'));
+  let mut top = Totals::default();
+  for (size, path) in lines {
+    let relative = match path == root {
+      true => "",
+      false => path.strip_prefix(&prefix).unwrap_or(path),
+    };
+    let at = top.at(relative, '/');
+    at.asize = size;
+    at.dsize = size;
+  }
+  Ok(totals_entry(root.to_string(), top))
+}
+
+/// The fields of a CSV line, unquoted.
+fn csv_fields(line: &str) -> Vec<String> {
+  let mut fields = Vec::new();
+  let mut field = String::new();
+  let mut quoted = false;
+  let mut chars = line.chars().peekable();
+  while let Some(c) = chars.next() {
+    match c {
+      '"' if quoted && chars.peek() == Some(&'"') => {
+        field.push('"');
+        chars.next();
+      }
+      '"' => quoted = !quoted,
+      ',' if !quoted => fields.push(std::mem::take(&mut field)),
+      c => field.push(c),
+    }
+  }
+  fields.push(field);
+  fields
+}
+
+/// `2024/01/31 23:59:59`, as WizTree writes it. It's the time where the
+/// capture was made, which isn't said, so it's taken as UTC.
+fn parse_wiztree_time(text: &str) -> Option<i64> {
+  let number = |at: usize, len: usize| text.get(at..at + len)?.parse::<i64>().ok();
+  let days = days_from_civil(number(0, 4)?, number(5, 2)?, number(8, 2)?);
+  Some(days * 86_400 + number(11, 2)? * 3600 + number(14, 2)? * 60 + number(17, 2).unwrap_or(0))
+}
+
+/// A WizTree CSV export: a line saying WizTree made it, then a header
+/// (`File Name,Size,Allocated,Modified,…`) and a row per file and folder,
+/// folders ending with `\` and holding their totals.
+fn parse_wiztree(text: &str) -> Result<Entry, String> {
+  let is_header = |line: &str| line.trim_start_matches('"').starts_with("File Name");
+  let mut lines = text.lines().skip_while(|line| !is_header(line));
+  let header = csv_fields(lines.next().ok_or("Not a WizTree export")?);
+  let column = |name: &str| header.iter().position(|column| column == name);
+  let (Some(name_at), Some(size_at)) = (column("File Name"), column("Size")) else {
+    return Err("The WizTree export has no File Name or Size column".to_string());
+  };
+  let (allocated_at, modified_at) = (column("Allocated"), column("Modified"));
+  let mut rows = Vec::new();
+  for line in lines.filter(|line| !line.trim().is_empty()) {
+    let mut fields = csv_fields(line);
+    let number = |at: Option<usize>| at.and_then(|at| fields.get(at)?.parse::<u64>().ok());
+    let size = number(Some(size_at)).unwrap_or(0);
+    let allocated = number(allocated_at).unwrap_or(size);
+    let mtime = modified_at.and_then(|at| parse_wiztree_time(fields.get(at)?));
+    if name_at < fields.len() {
+      rows.push((fields.swap_remove(name_at), size, allocated, mtime));
+    }
+  }
+  // The folder that was scanned is the shortest one.
+  let root = rows
+    .iter()
+    .filter(|(path, ..)| path.ends_with('\\'))
+    .map(|(path, ..)| path.clone())
+    .min_by_key(String::len)
+    .ok_or("The WizTree export has no folders")?;
+  let mut top = Totals::default();
+  for (path, size, allocated, mtime) in rows {
+    let Some(relative) = path.strip_prefix(root.as_str()) else {
+      continue;
+    };
+    let at = top.at(relative, '\\');
+    at.asize = size;
+    at.dsize = allocated;
+    at.mtime = mtime;
+    at.dir = path.ends_with('\\');
+  }
+  Ok(totals_entry(root, top))
+}
+
+/// What's left of a folder's totals once its entries are taken out is the
+/// folder itself.
+fn totals_entry(name: String, totals: Totals) -> Entry {
+  if totals.below.is_empty() && !totals.dir {
+    return Entry {
+      name,
+      asize: totals.asize,
+      dsize: totals.dsize,
+      mtime: totals.mtime,
+      children: None,
+      read_error: false,
+    };
+  }
+  let held = totals.below.values().fold((0, 0), |(a, d), below| (a + below.asize, d + below.dsize));
+  let children = totals.below.into_iter().map(|(name, below)| totals_entry(name, below)).collect();
+  Entry {
+    name,
+    asize: totals.asize.saturating_sub(held.0),
+    dsize: totals.dsize.saturating_sub(held.1),
+    mtime: None,
+    children: Some(children),
+    read_error: false,
+  }
+}
+
+fn join(parent: &str, name: &str, separator: char) -> String {
+  match parent.ends_with(separator) {
+    true => format!("{}{}", parent, name),
+    false => format!("{}{}{}", parent, separator, name),
+  }
+}
+
+/// The tree of `capture`, its root shown at `root`.
+pub(super) fn scan(ctx: &ScanContext, capture: &Capture, root: &Path) -> ScanNode {
+  build(ctx, capture, &capture.root, &root.to_string_lossy(), 0)
+}
+
+fn build(
+  ctx: &ScanContext,
+  capture: &Capture,
+  entry: &Entry,
+  path: &str,
+  depth: usize,
+) -> ScanNode {
+  let sizes = FileSizes {
+    apparent: entry.asize,
+    allocated: entry.dsize,
+  };
+  let Some(children) = &entry.children else {
+    ctx.control.scanned_entries.fetch_add(1, Ordering::Relaxed);
+    ctx.control.scanned_bytes.fetch_add(entry.asize, Ordering::Relaxed);
+    maybe_emit_progress(ctx, Path::new(path));
+    let mut node = file_node(Path::new(path), sizes);
+    node.mtime = entry.mtime;
+    return node;
+  };
+  if entry.read_error {
+    let message = "Couldn't be read in full when it was captured";
+    ctx.record_error(Path::new(path), ScanErrorKind::PermissionDenied, None, &message);
+  }
+  let mut nodes = Vec::with_capacity(children.len());
+  for child in children {
+    if ctx.cancelled() {
+      break;
+    }
+    let child_path = join(path, &child.name, capture.separator);
+    let at = Path::new(&child_path);
+    let is_file = child.children.is_none();
+    if ctx.filters.excludes(at) || (is_file && !ctx.filters.includes_file(at)) {
+      continue;
+    }
+    nodes.push(build(ctx, capture, child, &child_path, depth + 1));
+  }
+  let mut node = dir_node(ctx, Path::new(path), nodes, 0);
+  node.size += sizes.apparent;
+  node.allocated += sizes.allocated;
+  if depth >= ctx.options.max_depth as usize {
+    node.children = None;
+  }
+  node
+}
+
+/// Opens the ncdu export, `du -ab` output or WizTree CSV export at `path` as
+/// a finished scan.
+/// Returns the scan id; the tree arrives as for a local scan, under the
+/// path it was captured at.
+#[tauri::command]
+pub async fn import_scan(
+  app: AppHandle,
+  state: State<'_, ScanManager>,
+  path: String,
+  options: Option<ScanOptions>,
+) -> Result<String, String> {
+  // An export of a whole drive can run to gigabytes.
+  let capture = blocking(move || read_capture(&path)).await?;
+  let mut options = options.unwrap_or_default();
+  options.same_filesystem = false;
+  let root_path = PathBuf::from(&capture.root.name);
+  spawn_scan_from(&app, &state, root_path, options, ScanSource::Import(capture))
+}
+
+/// Reads the capture at `path`, telling the format from its first line.
+fn read_capture(path: &str) -> Result<Capture, String> {
+  let text = std::fs::read_to_string(path).map_err(|e| format!("{path}: {e}"))?;
+  let first_line = text.trim_start().lines().next().unwrap_or_default();
+  let (root, separator) = if first_line.starts_with('[') {
+    (parse_ncdu(&text)?, '/')
+  } else if first_line.contains("WizTree") || first_line.contains("File Name") {
+    (parse_wiztree(&text)?, '\\')
+  } else {
+    (parse_du(&text)?, '/')
+  };
+  Ok(Capture { root, separator })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn error(parsed: Result<Entry, String>) -> String {
+    match parsed {
+      Ok(entry) => panic!("{} was parsed", entry.name),
+      Err(e) => e,
+    }
+  }
+
+  fn child<'a>(entry: &'a Entry, name: &str) -> &'a Entry {
+    let children = entry.children.as_deref().unwrap_or_default();
+    children.iter().find(|child| child.name == name).unwrap_or_else(|| panic!("no {name}"))
+  }
+
+  #[test]
+  fn ncdu_export() {
+    let text = r#"[1,2,{"progname":"ncdu","progver":"2.3","timestamp":1700000000},
+      [{"name":"/home/u","asize":4096,"dsize":4096},
+        {"name":"a.txt","asize":100,"dsize":4096,"mtime":1690000000},
+        [{"name":"sub","asize":4096,"dsize":4096,"read_error":true},
+          {"name":"b","asize":5,"dsize":0}],
+        {"name":"cache","excluded":"pattern"}]]"#;
+    let root = parse_ncdu(text).unwrap();
+    assert_eq!(root.name, "/home/u");
+    assert_eq!(root.children.as_ref().map(Vec::len), Some(2));
+    let file = child(&root, "a.txt");
+    assert!(file.children.is_none());
+    assert_eq!((file.asize, file.dsize, file.mtime), (100, 4096, Some(1690000000)));
+    let sub = child(&root, "sub");
+    assert!(sub.read_error);
+    assert_eq!(child(sub, "b").asize, 5);
+  }
+
+  #[test]
+  fn malformed_ncdu_export() {
+    assert!(error(parse_ncdu("[1, 2, {}")).starts_with("Not a valid ncdu export"));
+    assert!(error(parse_ncdu(r#"{"name":"/"}"#)).starts_with("Not a valid ncdu export"));
+    assert!(error(parse_ncdu("[2, 0, {}, [{\"name\":\"/\"}]]")).contains("version 2"));
+    assert!(error(parse_ncdu("[1, 0, {}]")).starts_with("Not a valid ncdu export"));
+    assert!(error(parse_ncdu("[1, 0, {}, \"/\"]")).contains("no root folder"));
+    assert!(error(parse_ncdu("[1, 0, {}, [{\"asize\":1}]]")).contains("no root folder"));
+  }
+
+  #[test]
+  fn du_output() {
+    let text = "100\t/data/a/x.txt\n4196\t/data/a\n50\t/data/y\n\n8342\t/data\n";
+    let root = parse_du(text).unwrap();
+    assert_eq!(root.name, "/data");
+    assert_eq!(root.asize, 4096);
+    let a = child(&root, "a");
+    assert_eq!(a.asize, 4096);
+    assert_eq!(child(a, "x.txt").asize, 100);
+    let y = child(&root, "y");
+    assert!(y.children.is_none());
+    assert_eq!((y.asize, y.dsize), (50, 50));
+  }
+
+  #[test]
+  fn malformed_du_output() {
+    assert_eq!(error(parse_du("")), "The du output is empty");
+    assert_eq!(error(parse_du(" \n\n")), "The du output is empty");
+    assert!(error(parse_du("12 /data")).starts_with("Not du output"));
+    assert!(error(parse_du("big\t/data")).starts_with("Not du output"));
+    assert!(error(parse_du("-5\t/data")).starts_with("Not du output"));
+  }
+
+  #[test]
+  fn csv_quoting() {
+    assert_eq!(csv_fields(r#"a,"b ""q"", c","#), ["a", r#"b "q", c"#, ""]);
+    assert_eq!(csv_fields(""), [""]);
+  }
+
+  #[test]
+  fn wiztree_times() {
+    assert_eq!(parse_wiztree_time("2024/01/30 10:00:00"), Some(1706608800));
+    assert_eq!(parse_wiztree_time("2024/01/30 10:00"), Some(1706608800));
+    assert_eq!(parse_wiztree_time("2024/01"), None);
+    assert_eq!(parse_wiztree_time("yesterday at noon"), None);
+    assert_eq!(parse_wiztree_time("2024/01/3é 10:00:00"), None);
+  }
+
+  #[test]
+  fn wiztree_export() {
+    let text = "Generated by WizTree 4.15 (Free Version) Donate at https://wiztreefree.com\n\
+      File Name,Size,Allocated,Modified,Attributes,Files,Folders\n\
+      \"C:\\Data\\\",3000,4096,2024/01/31 23:59:59,16,2,1\n\
+      \"C:\\Data\\a.txt\",1000,4096,2024/01/30 10:00:00,32,0,0\n\
+      \"C:\\Data\\Sub\\\",2000,0,2024/01/31 23:59:59,16,1,0\n\
+      \"C:\\Data\\Sub\\b,c.bin\",2000,0,2024/01/31 12:00:00,32,0,0\n\
+      \"C:\\Data\\Empty\\\",0,0,2024/01/31 12:00:00,16,0,0\n";
+    let root = parse_wiztree(text).unwrap();
+    assert_eq!(root.name, "C:\\Data\\");
+    assert_eq!((root.asize, root.dsize), (0, 0));
+    let file = child(&root, "a.txt");
+    assert_eq!((file.asize, file.dsize, file.mtime), (1000, 4096, Some(1706608800)));
+    let sub = child(&root, "Sub");
+    assert_eq!(child(sub, "b,c.bin").asize, 2000);
+    assert_eq!(child(&root, "Empty").children.as_ref().map(Vec::len), Some(0));
+  }
+
+  #[test]
+  fn malformed_wiztree_export() {
+    assert_eq!(error(parse_wiztree("Generated by WizTree\n")), "Not a WizTree export");
+    let no_size = "File Name,Allocated\n\"C:\\\",1\n";
+    assert!(error(parse_wiztree(no_size)).contains("no File Name or Size column"));
+    let no_folders = "File Name,Size\n\"C:\\a.txt\",1\n";
+    assert_eq!(error(parse_wiztree(no_folders)), "The WizTree export has no folders");
+    // Rows it can't make sense of count for nothing rather than fail.
+    let garbled = "File Name,Size,Allocated\n\"C:\\\",lots,\n\"C:\\a\",-3,x\nshort\n";
+    let root = parse_wiztree(garbled).unwrap();
+    assert_eq!(root.asize, 0);
+    assert_eq!(child(&root, "a").asize, 0);
+  }
+}
@@ -0,0 +1,126 @@
+//! iOS, where the app only sees its own container and the folders the user
+//! hands it in the document picker: on the device, in iCloud Drive or with
+//! another Files provider. A picked folder is kept as a security-scoped
+//! bookmark by `StoragePlugin.swift`; for as long as a scan of it runs,
+//! access to it is held open, and its path is walked like any local one.
+//! Files a provider hasn't downloaded count as the placeholders they are.
+
+use super::{ScanManager, ScanOptions};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, State};
+#[cfg(target_os = "ios")]
+use {
+  super::{mobile, spawn_scan_from, ScanSource},
+  serde_json::json,
+  std::path::PathBuf,
+  tauri::{plugin::PluginHandle, Wry},
+};
+
+#[cfg(not(target_os = "ios"))]
+const IOS_ONLY: &str = "Only on iOS";
+
+/// Somewhere the app may scan.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct IosLocation {
+  /// The bookmark of a picked folder; `None` for the app's container.
+  pub id: Option<String>,
+  pub name: String,
+  pub path: String,
+}
+
+#[cfg(target_os = "ios")]
+#[derive(Deserialize)]
+struct Locations {
+  locations: Vec<IosLocation>,
+}
+
+#[cfg(target_os = "ios")]
+#[derive(Deserialize)]
+struct Opened {
+  path: String,
+}
+
+/// Access to a picked folder, given back when the scan is done with it.
+#[cfg(target_os = "ios")]
+pub(super) struct Access {
+  bridge: PluginHandle<Wry>,
+  id: String,
+}
+
+#[cfg(target_os = "ios")]
+impl Drop for Access {
+  fn drop(&mut self) {
+    let _ = mobile::call::<serde_json::Value>(&self.bridge, "stopAccess", json!({ "id": self.id }));
+  }
+}
+
+/// The app's container, then the folders picked so far that still resolve.
+#[tauri::command]
+#[cfg_attr(not(target_os = "ios"), allow(unused_variables))]
+pub async fn list_ios_locations(app: AppHandle) -> Result<Vec<IosLocation>, String> {
+  #[cfg(target_os = "ios")]
+  {
+    let mut locations = Vec::new();
+    if let Some(home) = std::env::var_os("HOME") {
+      locations.push(IosLocation {
+        id: None,
+        name: "This app's files".to_string(),
+        path: home.to_string_lossy().to_string(),
+      });
+    }
+    let picked: Locations = mobile::call(&mobile::bridge(&app)?, "listBookmarks", json!({}))?;
+    locations.extend(picked.locations);
+    Ok(locations)
+  }
+  #[cfg(not(target_os = "ios"))]
+  Err(IOS_ONLY.to_string())
+}
+
+/// Asks the user for a folder with the document picker and keeps a
+/// bookmark to it, so it can be scanned again after a restart.
+#[tauri::command]
+#[cfg_attr(not(target_os = "ios"), allow(unused_variables))]
+pub async fn pick_ios_folder(app: AppHandle) -> Result<IosLocation, String> {
+  #[cfg(target_os = "ios")]
+  return mobile::call(&mobile::bridge(&app)?, "pickFolder", json!({}));
+  #[cfg(not(target_os = "ios"))]
+  Err(IOS_ONLY.to_string())
+}
+
+/// Drops the bookmark of a picked folder.
+#[tauri::command]
+#[cfg_attr(not(target_os = "ios"), allow(unused_variables))]
+pub async fn forget_ios_folder(app: AppHandle, id: String) -> Result<(), String> {
+  #[cfg(target_os = "ios")]
+  return mobile::call::<serde_json::Value>(&mobile::bridge(&app)?, "forget", json!({ "id": id }))
+    .map(drop);
+  #[cfg(not(target_os = "ios"))]
+  Err(IOS_ONLY.to_string())
+}
+
+/// Scans the picked folder `id`, or the app's container when there's none.
+/// Returns the scan id; the scan runs as a local one does.
+#[tauri::command]
+#[cfg_attr(not(target_os = "ios"), allow(unused_variables))]
+pub async fn start_ios_scan(
+  app: AppHandle,
+  state: State<'_, ScanManager>,
+  id: Option<String>,
+  options: Option<ScanOptions>,
+) -> Result<String, String> {
+  #[cfg(target_os = "ios")]
+  {
+    let options = options.unwrap_or_default();
+    let Some(id) = id else {
+      let home = std::env::var_os("HOME").ok_or("The app's container wasn't found")?;
+      return spawn_scan_from(&app, &state, PathBuf::from(home), options, ScanSource::Local);
+    };
+    let bridge = mobile::bridge(&app)?;
+    let opened: Opened = mobile::call(&bridge, "startAccess", json!({ "id": id }))?;
+    // Given back when the scan ends, or right away if it can't start.
+    let access = Access { bridge, id };
+    spawn_scan_from(&app, &state, PathBuf::from(opened.path), options, ScanSource::Ios(access))
+  }
+  #[cfg(not(target_os = "ios"))]
+  Err(IOS_ONLY.to_string())
+}
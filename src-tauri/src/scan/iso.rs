@@ -0,0 +1,242 @@
+//! The directory tree of ISO 9660 images (CD and DVD images, most .iso files),
+//! read straight from the file without mounting it. Joliet names are used
+//! when the image has them, then Rock Ridge ones, then the short ISO names.
+
+use super::{dir_node, empty_dir_node, file_node, FileSizes, ScanContext, ScanNode};
+use std::{
+  collections::HashSet,
+  fs::File,
+  io::{self, Read, Seek, SeekFrom},
+  path::Path,
+};
+
+const SECTOR: u64 = 2048;
+/// Where the volume descriptors start.
+const FIRST_DESCRIPTOR: u64 = 16;
+/// Deeper directories than this are taken for a corrupt image.
+const MAX_NESTING: usize = 64;
+
+/// A directory record, less the `.` and `..` ones.
+struct Record {
+  name: String,
+  dir: bool,
+  extent: u64,
+  size: u64,
+  mtime: Option<i64>,
+  // The file goes on in the next record, with the same name.
+  continued: bool,
+}
+
+/// Whether `file` holds an ISO 9660 volume.
+pub(super) fn is_iso(file: &mut File) -> bool {
+  let mut magic = [0u8; 6];
+  file.seek(SeekFrom::Start(FIRST_DESCRIPTOR * SECTOR)).is_ok()
+    && file.read_exact(&mut magic).is_ok()
+    && &magic[1..] == b"CD001"
+}
+
+/// The tree of the ISO 9660 image at `path`, under a node named after the
+/// image. Directories that can't be read are left empty.
+pub(super) fn read(ctx: &ScanContext, path: &Path) -> io::Result<ScanNode> {
+  let mut file = File::open(path)?;
+  let (root, joliet) = root_record(&mut file)?;
+  let mut visited = HashSet::new();
+  Ok(read_dir(ctx, &mut file, path, &root, joliet, 0, &mut visited))
+}
+
+/// The root directory record of the volume to read, and whether it is the
+/// Joliet one.
+fn root_record(file: &mut File) -> io::Result<(Record, bool)> {
+  let mut primary = None;
+  let mut descriptor = [0u8; SECTOR as usize];
+  for sector in FIRST_DESCRIPTOR.. {
+    file.seek(SeekFrom::Start(sector * SECTOR))?;
+    file.read_exact(&mut descriptor)?;
+    if &descriptor[1..6] != b"CD001" {
+      break;
+    }
+    let root = || record_fields(&descriptor[156..190], String::new());
+    match descriptor[0] {
+      1 if primary.is_none() => primary = root(),
+      // A supplementary descriptor with a UCS-2 escape sequence is Joliet.
+      2 if matches!(&descriptor[88..91], b"%/@" | b"%/C" | b"%/E") => {
+        if let Some(root) = root() {
+          return Ok((root, true));
+        }
+      }
+      255 => break,
+      _ => {}
+    }
+  }
+  primary
+    .map(|root| (root, false))
+    .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "no ISO 9660 volume descriptor"))
+}
+
+fn read_dir(
+  ctx: &ScanContext,
+  file: &mut File,
+  path: &Path,
+  dir: &Record,
+  joliet: bool,
+  depth: usize,
+  visited: &mut HashSet<u64>,
+) -> ScanNode {
+  let records = match read_records(file, dir, joliet) {
+    Ok(records) if depth < MAX_NESTING && visited.insert(dir.extent) => records,
+    Ok(_) => return empty_dir_node(path),
+    Err(e) => {
+      ctx.record_io_error(path, &e);
+      return empty_dir_node(path);
+    }
+  };
+  let mut children: Vec<ScanNode> = Vec::new();
+  let mut continuing = false;
+  for record in records {
+    if ctx.cancelled() {
+      break;
+    }
+    let child_path = path.join(&record.name);
+    if record.dir {
+      children.push(read_dir(ctx, file, &child_path, &record, joliet, depth + 1, visited));
+    } else if let Some(last) = children.last_mut().filter(|_| continuing) {
+      // Another extent of a file over 4 GiB.
+      last.size += record.size;
+      last.allocated += allocated(record.size);
+    } else {
+      let sizes = FileSizes {
+        apparent: record.size,
+        allocated: allocated(record.size),
+      };
+      let mut node = file_node(&child_path, sizes);
+      node.sparse = false;
+      node.mtime = record.mtime;
+      children.push(node);
+    }
+    continuing = !record.dir && record.continued;
+  }
+  let mut node = dir_node(ctx, path, children, 0);
+  if depth >= ctx.options.max_depth as usize {
+    node.children = None;
+  }
+  node
+}
+
+/// The records of directory `dir`, which never cross a sector boundary.
+fn read_records(file: &mut File, dir: &Record, joliet: bool) -> io::Result<Vec<Record>> {
+  let mut data = Vec::new();
+  file.seek(SeekFrom::Start(dir.extent * SECTOR))?;
+  file.take(dir.size).read_to_end(&mut data)?;
+  let mut records = Vec::new();
+  let mut pos = 0;
+  while pos < data.len() {
+    let len = data[pos] as usize;
+    if len == 0 {
+      // The rest of the sector is padding.
+      pos = (pos / SECTOR as usize + 1) * SECTOR as usize;
+      continue;
+    }
+    if len < 34 || pos + len > data.len() {
+      break;
+    }
+    records.extend(parse_record(&data[pos..pos + len], joliet));
+    pos += len;
+  }
+  Ok(records)
+}
+
+/// Parses one directory record; `None` for `.` and `..`.
+fn parse_record(record: &[u8], joliet: bool) -> Option<Record> {
+  let name_len = record[32] as usize;
+  let raw_name = record.get(33..33 + name_len)?;
+  if raw_name == [0] || raw_name == [1] {
+    return None;
+  }
+  let name = if joliet {
+    let units: Vec<u16> =
+      raw_name.chunks_exact(2).map(|unit| u16::from_be_bytes([unit[0], unit[1]])).collect();
+    short_name(&String::from_utf16_lossy(&units))
+  } else {
+    // The system use area after the name, padded to an even offset.
+    let system_use = record.get(33 + name_len + (1 - name_len % 2)..).unwrap_or_default();
+    rock_ridge_name(system_use).unwrap_or_else(|| short_name(&String::from_utf8_lossy(raw_name)))
+  };
+  record_fields(record, name)
+}
+
+/// The fields of a record besides its name.
+fn record_fields(record: &[u8], name: String) -> Option<Record> {
+  let flags = record[25];
+  Some(Record {
+    name,
+    dir: flags & 0x02 != 0,
+    extent: u32::from_le_bytes(record[2..6].try_into().ok()?) as u64,
+    size: u32::from_le_bytes(record[10..14].try_into().ok()?) as u64,
+    mtime: record_time(&record[18..25]),
+    continued: flags & 0x80 != 0,
+  })
+}
+
+/// Drops the `;1` version and the dot of names without an extension.
+fn short_name(name: &str) -> String {
+  let name = name.split(';').next().unwrap_or(name);
+  name.strip_suffix('.').unwrap_or(name).to_string()
+}
+
+/// The name from the `NM` entries of a Rock Ridge record, if it has any.
+fn rock_ridge_name(mut area: &[u8]) -> Option<String> {
+  let mut name = Vec::new();
+  while area.len() >= 4 {
+    let len = area[2] as usize;
+    if len < 4 || len > area.len() {
+      break;
+    }
+    // Flags of 0 mean the name is given, rather than `.` or `..`.
+    if &area[..2] == b"NM" && len > 5 && area[4] & 0x06 == 0 {
+      name.extend_from_slice(&area[5..len]);
+    }
+    area = &area[len..];
+  }
+  (!name.is_empty()).then(|| String::from_utf8_lossy(&name).to_string())
+}
+
+/// A record's recording time, in seconds since the Unix epoch.
+fn record_time(time: &[u8]) -> Option<i64> {
+  let [year, month, day, hour, minute, second, offset] = time.try_into().ok()?;
+  if !(1..=12).contains(&month) || day == 0 {
+    return None;
+  }
+  let days = days_from_civil(1900 + year as i64, month as i64, day as i64);
+  let local = days * 86_400 + hour as i64 * 3600 + minute as i64 * 60 + second as i64;
+  // The offset from GMT is in 15-minute steps.
+  Some(local - (offset as i8) as i64 * 15 * 60)
+}
+
+/// Days from 1970-01-01 to the given date of the proleptic Gregorian calendar.
+pub(super) fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+  let year = if month <= 2 { year - 1 } else { year };
+  let era = year.div_euclid(400);
+  let year_of_era = year - era * 400;
+  let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+  let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+  era * 146_097 + day_of_era - 719_468
+}
+
+/// The date `days` after 1970-01-01; the inverse of `days_from_civil`.
+pub(super) fn civil_from_days(days: i64) -> (i64, i64, i64) {
+  let days = days + 719_468;
+  let era = days.div_euclid(146_097);
+  let day_of_era = days - era * 146_097;
+  let year_of_era =
+    (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+  let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+  let shifted_month = (5 * day_of_year + 2) / 153;
+  let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+  let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 };
+  (year_of_era + era * 400 + i64::from(month <= 2), month, day)
+}
+
+/// Sizes are rounded up to whole sectors.
+fn allocated(size: u64) -> u64 {
+  size.div_ceil(SECTOR) * SECTOR
+}
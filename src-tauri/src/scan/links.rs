@@ -0,0 +1,22 @@
+//! Symlinks whose targets are gone, noted as the walk comes across them.
+
+use super::ScanManager;
+use serde::Serialize;
+use tauri::State;
+
+#[derive(Clone, Serialize)]
+pub struct BrokenLink {
+  pub path: String,
+  /// Where the link points, as stored in it.
+  pub target: String,
+}
+
+/// Links of a finished scan whose targets don't resolve, sorted by path.
+/// Those in hidden subtrees left out of the tree aren't included.
+#[tauri::command]
+pub async fn get_broken_symlinks(
+  state: State<'_, ScanManager>,
+  scan_id: String,
+) -> Result<Vec<BrokenLink>, String> {
+  Ok(state.results(&scan_id)?.broken_links.clone())
+}
@@ -0,0 +1,345 @@
+//! Linux fast path for reading a directory: raw getdents64 with a large buffer,
+//! and statx asking only for the fields a scan uses, without forcing network
+//! filesystems to revalidate. With the `io-uring` feature the stat calls of a
+//! directory go to the kernel in batches instead of one syscall each.
+
+use super::{
+  listed_dir, listed_file, unix_attributes, unreadable_child, ChildEntry, DirTask, DirVisit,
+  FileCategory, FileFacts, FileSizes, LinkKind, ScanContext, WalkMode,
+};
+use rayon::prelude::*;
+use std::{
+  ffi::{CStr, CString, OsStr},
+  io,
+  os::{
+    fd::{AsRawFd, RawFd},
+    unix::{ffi::OsStrExt, fs::OpenOptionsExt},
+  },
+  path::PathBuf,
+  sync::OnceLock,
+};
+
+const DIRENT_BUFFER: usize = 256 * 1024;
+const STATX_MASK: u32 = libc::STATX_TYPE
+  | libc::STATX_MODE
+  | libc::STATX_UID
+  | libc::STATX_NLINK
+  | libc::STATX_INO
+  | libc::STATX_SIZE
+  | libc::STATX_BLOCKS
+  | libc::STATX_MTIME
+  | libc::STATX_ATIME;
+
+/// What a scan needs to know about an entry, from one statx call.
+struct Stat {
+  is_dir: bool,
+  is_link: bool,
+  mode: u32,
+  uid: u32,
+  size: u64,
+  allocated: u64,
+  dev: u64,
+  ino: u64,
+  nlink: u32,
+  mtime: i64,
+  atime: i64,
+}
+
+impl Stat {
+  fn from_statx(st: &libc::statx) -> Self {
+    Self {
+      is_dir: st.stx_mode as u32 & libc::S_IFMT == libc::S_IFDIR,
+      is_link: st.stx_mode as u32 & libc::S_IFMT == libc::S_IFLNK,
+      mode: st.stx_mode as u32,
+      uid: st.stx_uid,
+      size: st.stx_size,
+      // stx_blocks is always in 512-byte units, like st_blocks.
+      allocated: st.stx_blocks.saturating_mul(512),
+      dev: libc::makedev(st.stx_dev_major, st.stx_dev_minor),
+      ino: st.stx_ino,
+      nlink: st.stx_nlink,
+      mtime: st.stx_mtime.tv_sec,
+      atime: st.stx_atime.tv_sec,
+    }
+  }
+}
+
+/// An entry of the directory being visited.
+struct Child {
+  name: CString,
+  path: PathBuf,
+  d_type: u8,
+  mode: WalkMode,
+}
+
+/// Whether statx works here: it needs Linux 4.11 and can be blocked by seccomp.
+pub(super) fn available() -> bool {
+  static AVAILABLE: OnceLock<bool> = OnceLock::new();
+  *AVAILABLE.get_or_init(|| statx(libc::AT_FDCWD, c"/", 0).is_ok())
+}
+
+/// Reads the directory of `task` like the portable `visit_dir`, with the
+/// entry types from getdents64 deciding which entries need a stat at all.
+pub(super) fn visit_dir(ctx: &ScanContext, task: &DirTask) -> DirVisit {
+  let dir = match std::fs::OpenOptions::new()
+    .read(true)
+    .custom_flags(libc::O_DIRECTORY)
+    .open(&task.path)
+  {
+    Ok(dir) => dir,
+    Err(e) => {
+      ctx.record_io_error(&task.path, &e);
+      return DirVisit::default();
+    }
+  };
+  let entries = match read_entries(dir.as_raw_fd()) {
+    Ok(entries) => entries,
+    Err(e) => {
+      ctx.record_io_error(&task.path, &e);
+      return DirVisit::default();
+    }
+  };
+
+  let listed = entries.len() as u64;
+  let mut children = Vec::with_capacity(entries.len());
+  for (name, d_type) in entries {
+    let path = task.path.join(OsStr::from_bytes(name.to_bytes()));
+    if ctx.filters.excludes(&path) {
+      continue;
+    }
+    let mut mode = task.mode;
+    if mode != WalkMode::Hidden && !ctx.options.include_hidden && name.to_bytes()[0] == b'.' {
+      if !ctx.options.track_hidden_bytes {
+        continue;
+      }
+      mode = WalkMode::Hidden;
+    }
+    let mode = ctx.ignore_mode(&path, d_type == libc::DT_DIR, mode);
+    // Filtering a known regular file by extension needs no stat.
+    if d_type == libc::DT_REG && !ctx.filters.includes_file(&path) {
+      continue;
+    }
+    children.push(Child {
+      name,
+      path,
+      d_type,
+      mode,
+    });
+  }
+
+  // Directories only need a stat for the device and loop checks. Links are
+  // stat'ed either way: followed for their target, or counted as themselves.
+  let dir_ids = ctx.filters.root_device.is_some() || ctx.seen_dirs.is_some();
+  let (stat_children, plain_dirs): (Vec<Child>, Vec<Child>) = children
+    .into_iter()
+    .partition(|c| c.d_type != libc::DT_DIR || dir_ids);
+  let mut flags = libc::AT_STATX_DONT_SYNC;
+  if !ctx.options.follow_symlinks {
+    flags |= libc::AT_SYMLINK_NOFOLLOW;
+  }
+  let names: Vec<&CStr> = stat_children.iter().map(|c| c.name.as_c_str()).collect();
+  let stats = stat_all(dir.as_raw_fd(), &names, flags);
+
+  let depth = task.depth + 1;
+  let mut visit = DirVisit {
+    listed: Some(listed),
+    ..Default::default()
+  };
+  for child in plain_dirs {
+    let attributes = unix_attributes(&child.path, None);
+    let entry = listed_dir(ctx, child.path, None, attributes, depth, child.mode);
+    visit.add(entry, child.mode);
+  }
+  for (child, stat) in stat_children.into_iter().zip(stats) {
+    let mode = child.mode;
+    let entry = match stat {
+      Ok(stat) if stat.is_dir => {
+        let attributes = unix_attributes(&child.path, None);
+        listed_dir(ctx, child.path, Some((stat.dev, stat.ino)), attributes, depth, mode)
+      }
+      Ok(stat) => {
+        if stat.is_link {
+          ctx.check_link(&child.path, mode);
+        }
+        file_entry(ctx, child, &stat)
+      }
+      Err(e) => {
+        if child.d_type == libc::DT_LNK {
+          ctx.note_broken_link(&child.path, &e, mode);
+        }
+        unreadable_child(ctx, child.path, &e, mode)
+      }
+    };
+    visit.add(entry, mode);
+  }
+  visit
+}
+
+fn file_entry(ctx: &ScanContext, child: Child, stat: &Stat) -> ChildEntry {
+  // Regular files were filtered before the stat; the rest only now have a type.
+  if child.d_type != libc::DT_REG && !ctx.filters.includes_file(&child.path) {
+    return ChildEntry::Skipped;
+  }
+  let sizes = FileSizes {
+    apparent: stat.size,
+    allocated: stat.allocated,
+  };
+  let id = (stat.nlink > 1).then_some((stat.dev, stat.ino));
+  let facts = FileFacts {
+    category: FileCategory::of(&child.path),
+    mtime: Some(stat.mtime),
+    atime: Some(stat.atime),
+    owner: ctx.owner_of_uid(stat.uid),
+  };
+  let mut entry = listed_file(ctx, &child.path, sizes, id, facts, child.mode);
+  if let ChildEntry::Node(node) = &mut entry {
+    // A followed link is stat'ed as its target; only its d_type tells.
+    if stat.is_link || child.d_type == libc::DT_LNK {
+      node.link_kind = LinkKind::Symlink;
+    }
+    let is_file = stat.mode & libc::S_IFMT == libc::S_IFREG;
+    node.set_attributes(unix_attributes(&child.path, is_file.then_some(stat.mode)));
+  }
+  entry
+}
+
+/// Names and d_type of every entry of the open directory `fd`.
+fn read_entries(fd: RawFd) -> io::Result<Vec<(CString, u8)>> {
+  // u64 words keep the buffer aligned for the dirent64 records.
+  let mut buf = vec![0u64; DIRENT_BUFFER / 8];
+  let mut entries = Vec::new();
+  loop {
+    let n = unsafe { libc::syscall(libc::SYS_getdents64, fd, buf.as_mut_ptr(), DIRENT_BUFFER) };
+    if n < 0 {
+      return Err(io::Error::last_os_error());
+    }
+    if n == 0 {
+      return Ok(entries);
+    }
+    let bytes = unsafe { std::slice::from_raw_parts(buf.as_ptr() as *const u8, n as usize) };
+    let mut pos = 0;
+    // struct linux_dirent64 { u64 d_ino; i64 d_off; u16 d_reclen; u8 d_type; char d_name[]; }
+    while pos + 19 < bytes.len() {
+      let reclen = u16::from_ne_bytes([bytes[pos + 16], bytes[pos + 17]]) as usize;
+      if reclen < 20 || pos + reclen > bytes.len() {
+        return Err(io::Error::from(io::ErrorKind::InvalidData));
+      }
+      let d_type = bytes[pos + 18];
+      let name = CStr::from_bytes_until_nul(&bytes[pos + 19..pos + reclen])
+        .map_err(|_| io::Error::from(io::ErrorKind::InvalidData))?;
+      if name != c"." && name != c".." {
+        entries.push((name.to_owned(), d_type));
+      }
+      pos += reclen;
+    }
+  }
+}
+
+/// Stats each of `names` relative to `dir`, in order.
+fn stat_all(dir: RawFd, names: &[&CStr], flags: i32) -> Vec<io::Result<Stat>> {
+  #[cfg(feature = "io-uring")]
+  if let Some(stats) = uring::stat_all(dir, names, flags) {
+    return stats;
+  }
+  names
+    .par_iter()
+    .map(|name| statx(dir, name, flags))
+    .collect()
+}
+
+fn statx(dir: RawFd, name: &CStr, flags: i32) -> io::Result<Stat> {
+  let mut st: libc::statx = unsafe { std::mem::zeroed() };
+  if unsafe { libc::statx(dir, name.as_ptr(), flags, STATX_MASK, &mut st) } != 0 {
+    return Err(io::Error::last_os_error());
+  }
+  Ok(Stat::from_statx(&st))
+}
+
+#[cfg(feature = "io-uring")]
+mod uring {
+  use super::{Stat, STATX_MASK};
+  use io_uring::{opcode, types, IoUring};
+  use std::{cell::RefCell, ffi::CStr, io, os::fd::RawFd};
+
+  const RING_ENTRIES: usize = 256;
+
+  thread_local! {
+    // One ring per scan worker; `None` once the kernel turned io_uring down.
+    static RING: RefCell<Option<IoUring>> = RefCell::new(IoUring::new(RING_ENTRIES as u32).ok());
+  }
+
+  /// Stats `names` through this thread's ring, or `None` when io_uring (or its
+  /// statx op, Linux 5.6+) isn't available and plain syscalls must do.
+  pub(super) fn stat_all(dir: RawFd, names: &[&CStr], flags: i32) -> Option<Vec<io::Result<Stat>>> {
+    RING.with(|cell| {
+      let mut slot = cell.borrow_mut();
+      let ring = slot.as_mut()?;
+      let stats = submit_all(ring, dir, names, flags);
+      if stats.is_none() {
+        *slot = None;
+      }
+      stats
+    })
+  }
+
+  fn submit_all(
+    ring: &mut IoUring,
+    dir: RawFd,
+    names: &[&CStr],
+    flags: i32,
+  ) -> Option<Vec<io::Result<Stat>>> {
+    let mut bufs: Vec<libc::statx> = vec![unsafe { std::mem::zeroed() }; names.len()];
+    let mut results: Vec<Option<io::Result<Stat>>> = names.iter().map(|_| None).collect();
+    for start in (0..names.len()).step_by(RING_ENTRIES) {
+      let end = (start + RING_ENTRIES).min(names.len());
+      {
+        let mut sq = ring.submission();
+        for (i, buf) in bufs.iter_mut().enumerate().take(end).skip(start) {
+          let entry = opcode::Statx::new(
+            types::Fd(dir),
+            names[i].as_ptr(),
+            buf as *mut libc::statx as *mut types::statx,
+          )
+          .flags(flags)
+          .mask(STATX_MASK)
+          .build()
+          .user_data(i as u64);
+          // The ring has room for a whole batch, and each batch is drained
+          // before the next one is queued.
+          unsafe { sq.push(&entry) }.ok()?;
+        }
+      }
+      let mut pending = end - start;
+      let mut unsupported = false;
+      while pending > 0 {
+        if let Err(e) = ring.submit_and_wait(pending) {
+          if e.kind() == io::ErrorKind::Interrupted {
+            continue;
+          }
+          // Submitted ops may still write into `bufs`, so it must outlive them.
+          std::mem::forget(bufs);
+          return None;
+        }
+        for cqe in ring.completion() {
+          let i = cqe.user_data() as usize;
+          results[i] = Some(match cqe.result() {
+            r if r < 0 => Err(io::Error::from_raw_os_error(-r)),
+            _ => Ok(Stat::from_statx(&bufs[i])),
+          });
+          // Kernels without the statx op reject it outright.
+          unsupported |= cqe.result() == -libc::EINVAL;
+          pending -= 1;
+        }
+      }
+      if unsupported {
+        return None;
+      }
+    }
+    Some(
+      results
+        .into_iter()
+        .map(|r| r.unwrap_or_else(|| Err(io::Error::other("statx did not complete"))))
+        .collect(),
+    )
+  }
+}
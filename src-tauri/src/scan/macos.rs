@@ -0,0 +1,343 @@
+//! macOS fast path for reading a directory: getattrlistbulk returns the name,
+//! type, identity and sizes of a whole batch of entries per call, where
+//! read_dir needs another lstat for each of them.
+
+use super::{
+  listed_dir, listed_file, macos_flag_attributes, unix_attributes, unreadable_child, ChildEntry,
+  DirTask, DirVisit, FileCategory, FileFacts, FileSizes, LinkKind, ScanContext, WalkMode,
+};
+use rayon::prelude::*;
+use std::{
+  ffi::{CStr, OsStr},
+  io,
+  os::{
+    fd::{AsRawFd, RawFd},
+    macos::fs::MetadataExt as _,
+    unix::{
+      ffi::OsStrExt,
+      fs::{MetadataExt, OpenOptionsExt},
+    },
+  },
+  path::Path,
+};
+
+const ATTR_BUFFER: usize = 256 * 1024;
+// Not exported by libc.
+const ATTR_CMN_ERROR: libc::attrgroup_t = 0x2000_0000;
+const VREG: u32 = 1;
+const VDIR: u32 = 2;
+const VLNK: u32 = 5;
+
+/// One entry of a getattrlistbulk batch.
+struct Entry {
+  name: Vec<u8>,
+  error: i32,
+  obj_type: u32,
+  mode: u32,
+  flags: u32,
+  mtime: i64,
+  atime: i64,
+  uid: u32,
+  dev: u64,
+  ino: u64,
+  nlink: u32,
+  size: u64,
+  allocated: u64,
+}
+
+impl Entry {
+  /// The entry as seen through a followed symlink.
+  fn resolve(&self, path: &Path) -> io::Result<Self> {
+    let md = std::fs::metadata(path)?;
+    Ok(Self {
+      name: Vec::new(),
+      error: 0,
+      obj_type: match (md.is_dir(), md.is_file()) {
+        (true, _) => VDIR,
+        (_, true) => VREG,
+        _ => 0,
+      },
+      mode: md.mode(),
+      flags: md.st_flags(),
+      mtime: md.mtime(),
+      atime: md.atime(),
+      uid: md.uid(),
+      dev: md.dev(),
+      ino: md.ino(),
+      nlink: md.nlink() as u32,
+      size: md.len(),
+      allocated: md.blocks().saturating_mul(512),
+    })
+  }
+}
+
+/// Reads the directory of `task` like the portable `visit_dir`, or `None` when
+/// getattrlistbulk fails and the portable path has to do it instead.
+pub(super) fn visit_dir(ctx: &ScanContext, task: &DirTask) -> Option<DirVisit> {
+  let dir = std::fs::OpenOptions::new()
+    .read(true)
+    .custom_flags(libc::O_DIRECTORY)
+    .open(&task.path)
+    .ok()?;
+  let entries = read_entries(dir.as_raw_fd()).ok()?;
+
+  let listed = entries.len() as u64;
+  let mut children = Vec::with_capacity(entries.len());
+  for entry in entries {
+    let path = task.path.join(OsStr::from_bytes(&entry.name));
+    if ctx.filters.excludes(&path) {
+      continue;
+    }
+    let mut mode = task.mode;
+    if mode != WalkMode::Hidden && !ctx.options.include_hidden && entry.name.first() == Some(&b'.')
+    {
+      if !ctx.options.track_hidden_bytes {
+        continue;
+      }
+      mode = WalkMode::Hidden;
+    }
+    let mode = ctx.ignore_mode(&path, entry.obj_type == VDIR, mode);
+    children.push((entry, path, mode));
+  }
+
+  let depth = task.depth + 1;
+  let entries: Vec<(ChildEntry, WalkMode)> = children
+    .into_par_iter()
+    .map(|(entry, path, mode)| (visit_entry(ctx, entry, &path, depth, mode), mode))
+    .collect();
+  let mut visit = DirVisit {
+    listed: Some(listed),
+    ..Default::default()
+  };
+  for (entry, mode) in entries {
+    visit.add(entry, mode);
+  }
+  Some(visit)
+}
+
+fn visit_entry(
+  ctx: &ScanContext,
+  entry: Entry,
+  path: &Path,
+  depth: u32,
+  mode: WalkMode,
+) -> ChildEntry {
+  if entry.error != 0 {
+    return unreadable_child(
+      ctx,
+      path.to_path_buf(),
+      &io::Error::from_raw_os_error(entry.error),
+      mode,
+    );
+  }
+  let is_link = entry.obj_type == VLNK;
+  let entry = if is_link && ctx.options.follow_symlinks {
+    match entry.resolve(path) {
+      Ok(target) => target,
+      Err(e) => {
+        ctx.note_broken_link(path, &e, mode);
+        return unreadable_child(ctx, path.to_path_buf(), &e, mode);
+      }
+    }
+  } else {
+    if is_link {
+      ctx.check_link(path, mode);
+    }
+    entry
+  };
+  let file_mode = (entry.obj_type == VREG).then_some(entry.mode);
+  let attributes = unix_attributes(path, file_mode) | macos_flag_attributes(entry.flags);
+  if entry.obj_type == VDIR {
+    return listed_dir(
+      ctx,
+      path.to_path_buf(),
+      Some((entry.dev, entry.ino)),
+      attributes,
+      depth,
+      mode,
+    );
+  }
+  if !ctx.filters.includes_file(path) {
+    return ChildEntry::Skipped;
+  }
+  let sizes = FileSizes {
+    apparent: entry.size,
+    allocated: entry.allocated,
+  };
+  let id = (entry.nlink > 1).then_some((entry.dev, entry.ino));
+  let facts = FileFacts {
+    category: FileCategory::of(path),
+    mtime: Some(entry.mtime),
+    atime: Some(entry.atime),
+    owner: ctx.owner_of_uid(entry.uid),
+  };
+  let mut child = listed_file(ctx, path, sizes, id, facts, mode);
+  if let ChildEntry::Node(node) = &mut child {
+    if is_link {
+      node.link_kind = LinkKind::Symlink;
+    }
+    node.set_attributes(attributes);
+  }
+  child
+}
+
+/// Every entry of the open directory `fd`, in as few calls as the buffer allows.
+fn read_entries(fd: RawFd) -> io::Result<Vec<Entry>> {
+  let mut attrs = libc::attrlist {
+    bitmapcount: libc::ATTR_BIT_MAP_COUNT,
+    reserved: 0,
+    commonattr: libc::ATTR_CMN_RETURNED_ATTRS
+      | ATTR_CMN_ERROR
+      | libc::ATTR_CMN_NAME
+      | libc::ATTR_CMN_DEVID
+      | libc::ATTR_CMN_OBJTYPE
+      | libc::ATTR_CMN_MODTIME
+      | libc::ATTR_CMN_ACCTIME
+      | libc::ATTR_CMN_OWNERID
+      | libc::ATTR_CMN_ACCESSMASK
+      | libc::ATTR_CMN_FLAGS
+      | libc::ATTR_CMN_FILEID,
+    volattr: 0,
+    dirattr: 0,
+    fileattr: libc::ATTR_FILE_LINKCOUNT | libc::ATTR_FILE_ALLOCSIZE | libc::ATTR_FILE_DATALENGTH,
+    forkattr: 0,
+  };
+  // u64 words keep the buffer aligned for the records.
+  let mut buf = vec![0u64; ATTR_BUFFER / 8];
+  let mut entries = Vec::new();
+  loop {
+    let n = unsafe {
+      libc::getattrlistbulk(
+        fd,
+        &mut attrs as *mut libc::attrlist as *mut libc::c_void,
+        buf.as_mut_ptr() as *mut libc::c_void,
+        ATTR_BUFFER,
+        0,
+      )
+    };
+    if n < 0 {
+      return Err(io::Error::last_os_error());
+    }
+    if n == 0 {
+      return Ok(entries);
+    }
+    let bytes = unsafe { std::slice::from_raw_parts(buf.as_ptr() as *const u8, ATTR_BUFFER) };
+    let mut pos = 0;
+    for _ in 0..n {
+      let len = Fields::new(&bytes[pos..]).u32().unwrap_or(0) as usize;
+      let entry = bytes
+        .get(pos..pos + len)
+        .filter(|_| len >= 4)
+        .and_then(parse_entry)
+        .ok_or_else(|| io::Error::from(io::ErrorKind::InvalidData))?;
+      entries.push(entry);
+      pos += len;
+    }
+  }
+}
+
+/// Parses one record: its length, the attribute sets actually returned, then
+/// each returned attribute in bit order, except that the error comes first.
+fn parse_entry(record: &[u8]) -> Option<Entry> {
+  let mut fields = Fields::new(record);
+  fields.u32()?;
+  let common = fields.u32()?;
+  let _vol = fields.u32()?;
+  let _dir = fields.u32()?;
+  let file = fields.u32()?;
+  let _fork = fields.u32()?;
+
+  let error = if common & ATTR_CMN_ERROR != 0 {
+    fields.u32()? as i32
+  } else {
+    0
+  };
+  if common & libc::ATTR_CMN_NAME == 0 {
+    return None;
+  }
+  // attrreference_t: the name's offset from this field, and its length with the NUL.
+  let at = fields.pos;
+  let offset = fields.u32()? as i32 as isize;
+  let length = fields.u32()? as usize;
+  let start = at.checked_add_signed(offset)?;
+  let name = record.get(start..start.checked_add(length)?)?;
+  let name = CStr::from_bytes_until_nul(name).ok()?.to_bytes().to_vec();
+
+  let mut entry = Entry {
+    name,
+    error,
+    obj_type: 0,
+    mode: 0,
+    flags: 0,
+    mtime: 0,
+    atime: 0,
+    uid: 0,
+    dev: 0,
+    ino: 0,
+    nlink: 1,
+    size: 0,
+    allocated: 0,
+  };
+  if common & libc::ATTR_CMN_DEVID != 0 {
+    // dev_t is an i32; sign-extend it like `MetadataExt::dev` does.
+    entry.dev = fields.u32()? as i32 as u64;
+  }
+  if common & libc::ATTR_CMN_OBJTYPE != 0 {
+    entry.obj_type = fields.u32()?;
+  }
+  if common & libc::ATTR_CMN_MODTIME != 0 {
+    // struct timespec: seconds, then nanoseconds.
+    entry.mtime = fields.u64()? as i64;
+    fields.u64()?;
+  }
+  if common & libc::ATTR_CMN_ACCTIME != 0 {
+    entry.atime = fields.u64()? as i64;
+    fields.u64()?;
+  }
+  if common & libc::ATTR_CMN_OWNERID != 0 {
+    entry.uid = fields.u32()?;
+  }
+  if common & libc::ATTR_CMN_ACCESSMASK != 0 {
+    entry.mode = fields.u32()?;
+  }
+  if common & libc::ATTR_CMN_FLAGS != 0 {
+    entry.flags = fields.u32()?;
+  }
+  if common & libc::ATTR_CMN_FILEID != 0 {
+    entry.ino = fields.u64()?;
+  }
+  if file & libc::ATTR_FILE_LINKCOUNT != 0 {
+    entry.nlink = fields.u32()?;
+  }
+  if file & libc::ATTR_FILE_ALLOCSIZE != 0 {
+    entry.allocated = fields.u64()?;
+  }
+  if file & libc::ATTR_FILE_DATALENGTH != 0 {
+    entry.size = fields.u64()?;
+  }
+  Some(entry)
+}
+
+/// Reads the packed, only 4-byte aligned fields of a record in order.
+struct Fields<'a> {
+  record: &'a [u8],
+  pos: usize,
+}
+
+impl<'a> Fields<'a> {
+  fn new(record: &'a [u8]) -> Self {
+    Self { record, pos: 0 }
+  }
+
+  fn u32(&mut self) -> Option<u32> {
+    let bytes = self.record.get(self.pos..self.pos + 4)?;
+    self.pos += 4;
+    Some(u32::from_ne_bytes(bytes.try_into().ok()?))
+  }
+
+  fn u64(&mut self) -> Option<u64> {
+    let bytes = self.record.get(self.pos..self.pos + 8)?;
+    self.pos += 8;
+    Some(u64::from_ne_bytes(bytes.try_into().ok()?))
+  }
+}
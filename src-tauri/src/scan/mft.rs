@@ -0,0 +1,511 @@
+//! Scan backend that reads the NTFS Master File Table straight off the raw
+//! volume, the way WizTree does: one sequential read yields the name, parent,
+//! attributes and size of every file, instead of a directory listing and a
+//! stat per entry. Opening the raw volume takes administrator rights.
+
+use super::{
+  assemble_tree, dir_id, file_node, filetime_to_unix, maybe_emit_progress, record_visit,
+  tally_file, windows_attributes, ChildEntry, DirRole, DirTask, DirVisit, FileCategory, FileFacts,
+  FileSizes, LinkKind, PendingDir, ScanContext, ScanNode, WalkMode, ONLINE_ONLY_ATTRIBUTES,
+};
+use std::{
+  collections::{HashMap, HashSet},
+  fs::File,
+  io::{Read, Seek, SeekFrom},
+  path::Path,
+  sync::atomic::Ordering,
+};
+use windows_sys::Win32::{
+  Storage::FileSystem::{
+    FILE_ATTRIBUTE_COMPRESSED, FILE_ATTRIBUTE_REPARSE_POINT, FILE_ATTRIBUTE_SPARSE_FILE,
+  },
+  System::SystemServices::{IO_REPARSE_TAG_MOUNT_POINT, IO_REPARSE_TAG_SYMLINK},
+};
+
+// Records below this are NTFS metafiles ($MFT, $LogFile, $Extend, ...), which
+// directory listings don't show either.
+const FIRST_USER_RECORD: u64 = 24;
+// Record numbers in file references; the top 16 bits are a sequence number.
+const RECORD_MASK: u64 = 0xFFFF_FFFF_FFFF;
+const READ_CHUNK: usize = 1 << 20;
+
+const ATTR_STANDARD_INFORMATION: u32 = 0x10;
+const ATTR_FILE_NAME: u32 = 0x30;
+const ATTR_DATA: u32 = 0x80;
+const ATTR_REPARSE_POINT: u32 = 0xC0;
+const ATTR_END: u32 = 0xFFFF_FFFF;
+const ATTR_FLAG_COMPRESSED: u64 = 0x0001;
+const ATTR_FLAG_SPARSE: u64 = 0x8000;
+const RECORD_IN_USE: u16 = 0x01;
+const RECORD_IS_DIRECTORY: u16 = 0x02;
+const NAMESPACE_DOS: u8 = 2;
+
+/// One file or directory from the MFT.
+#[derive(Default)]
+struct Record {
+  // (parent record, name) per hard link, leaving out DOS 8.3 aliases.
+  links: Vec<(u64, String)>,
+  is_dir: bool,
+  attributes: u32,
+  reparse_tag: u32,
+  // Last write and last access as FILETIMEs.
+  mtime: i64,
+  atime: i64,
+  size: u64,
+  allocated: u64,
+}
+
+impl Record {
+  /// Symlinks, junctions and mounted folders, which aren't descended into:
+  /// their contents live elsewhere.
+  fn is_link(&self) -> bool {
+    self.attributes & FILE_ATTRIBUTE_REPARSE_POINT != 0
+      && matches!(self.reparse_tag, IO_REPARSE_TAG_SYMLINK | IO_REPARSE_TAG_MOUNT_POINT)
+  }
+}
+
+/// Builds the tree under `root` from the MFT of its volume. Fails when the
+/// volume isn't local NTFS or can't be opened raw; the caller then walks.
+pub(super) fn scan(ctx: &ScanContext, root: &Path) -> Result<ScanNode, String> {
+  if ctx.options.follow_symlinks {
+    return Err("The MFT backend doesn't follow symlinks".to_string());
+  }
+  if ctx.options.track_owners {
+    return Err("The MFT backend doesn't read file owners".to_string());
+  }
+  let md = std::fs::metadata(root).map_err(|e| e.to_string())?;
+  if !md.is_dir() {
+    return Err("The scan root is not a directory".to_string());
+  }
+  let (_, index) = dir_id(root, &md).ok_or("Can't identify the scan root")?;
+  let mut volume = Volume::open(root)?;
+  let records = volume.read_records(ctx)?;
+  Ok(build_tree(ctx, root, index & RECORD_MASK, &records))
+}
+
+/// Lays the records under `root_record` out as a tree, applying the scan
+/// options the same way the directory walker does.
+fn build_tree(ctx: &ScanContext, root: &Path, root_record: u64, records: &[Record]) -> ScanNode {
+  // Children of each directory, with the index of the link that names them.
+  let mut children: HashMap<u64, Vec<(u64, usize)>> = HashMap::new();
+  for (record, entry) in records.iter().enumerate().skip(FIRST_USER_RECORD as usize) {
+    for (link, (parent, _)) in entry.links.iter().enumerate() {
+      children.entry(*parent).or_default().push((record as u64, link));
+    }
+  }
+
+  ctx.control.scanned_entries.fetch_add(1, Ordering::Relaxed);
+  let truncated = ctx.options.max_depth == 0;
+  let attributes = records
+    .get(root_record as usize)
+    .map_or(0, |entry| windows_attributes(root, entry.attributes));
+  let mut dirs = vec![PendingDir::new(root.to_path_buf(), None, truncated, attributes)];
+  let mut stack = vec![(
+    root_record,
+    DirTask {
+      path: root.to_path_buf(),
+      depth: 0,
+      node: 0,
+      mode: if truncated { WalkMode::Total } else { WalkMode::Build },
+      id: ctx.empty_dirs.lock().add(root.to_path_buf(), None),
+    },
+  )];
+  let mut seen_files = HashSet::new();
+  while let Some((record, task)) = stack.pop() {
+    if ctx.cancelled() {
+      break;
+    }
+    let listing = children.get(&record).map_or(&[][..], Vec::as_slice);
+    let mut visit = DirVisit {
+      listed: Some(listing.len() as u64),
+      ..Default::default()
+    };
+    for &(child, link) in listing {
+      let entry = &records[child as usize];
+      let path = task.path.join(&entry.links[link].1);
+      if ctx.filters.excludes(&path) {
+        continue;
+      }
+      let mut mode = task.mode;
+      if mode != WalkMode::Hidden && !ctx.options.include_hidden && is_hidden(entry) {
+        if !ctx.options.track_hidden_bytes {
+          continue;
+        }
+        mode = WalkMode::Hidden;
+      }
+      let mode = ctx.ignore_mode(&path, entry.is_dir, mode);
+
+      let depth = task.depth + 1;
+      let link_kind = match entry.is_link() {
+        true => ctx.reparse_link_kind(&path, entry.reparse_tag),
+        false => LinkKind::None,
+      };
+      if !link_kind.is_none() {
+        ctx.check_link(&path, mode);
+      }
+      let child_entry = if entry.is_dir && link_kind.is_none() {
+        ctx.control.scanned_entries.fetch_add(1, Ordering::Relaxed);
+        maybe_emit_progress(ctx, &path);
+        let attributes = windows_attributes(&path, entry.attributes);
+        ChildEntry::Dir(child, path, attributes, DirRole::of(ctx, mode, depth))
+      } else if !ctx.filters.includes_file(&path) {
+        ChildEntry::Skipped
+      } else {
+        // Further hard links to a file already counted add nothing.
+        let repeat = entry.links.len() > 1
+          && ctx.options.dedupe_hardlinks
+          && !seen_files.insert(child);
+        let online_only = entry.attributes & ONLINE_ONLY_ATTRIBUTES != 0;
+        let sizes = if repeat {
+          FileSizes::default()
+        } else {
+          FileSizes {
+            apparent: entry.size,
+            allocated: if online_only { 0 } else { entry.allocated },
+          }
+        };
+        let facts = FileFacts {
+          category: FileCategory::of(&path),
+          mtime: filetime_to_unix(entry.mtime),
+          atime: filetime_to_unix(entry.atime),
+          owner: None,
+        };
+        tally_file(ctx, &path, sizes, &facts, mode);
+        match mode {
+          WalkMode::Build => {
+            let mut node = file_node(&path, sizes);
+            node.online_only = online_only;
+            node.compressed = entry.attributes & FILE_ATTRIBUTE_COMPRESSED != 0;
+            node.sparse = entry.attributes & FILE_ATTRIBUTE_SPARSE_FILE != 0;
+            node.link_kind = link_kind;
+            node.set_attributes(windows_attributes(&path, entry.attributes));
+            node.mtime = facts.mtime;
+            ChildEntry::Node(Box::new(node))
+          }
+          _ => ChildEntry::File(sizes, facts),
+        }
+      };
+      visit.add(child_entry, mode);
+    }
+    stack.extend(record_visit(ctx, &mut dirs, &task, visit));
+  }
+  assemble_tree(ctx, dirs)
+}
+
+fn is_hidden(entry: &Record) -> bool {
+  use windows_sys::Win32::Storage::FileSystem::{FILE_ATTRIBUTE_HIDDEN, FILE_ATTRIBUTE_SYSTEM};
+  entry.attributes & (FILE_ATTRIBUTE_HIDDEN | FILE_ATTRIBUTE_SYSTEM) != 0
+}
+
+/// A raw NTFS volume opened for reading its MFT.
+struct Volume {
+  file: File,
+  cluster_size: u64,
+  record_size: usize,
+  // Where the first record of the MFT lives, in bytes from the volume start.
+  mft_offset: u64,
+}
+
+impl Volume {
+  fn open(root: &Path) -> Result<Self, String> {
+    use std::os::windows::fs::OpenOptionsExt;
+    use windows_sys::Win32::Storage::FileSystem::{FILE_SHARE_READ, FILE_SHARE_WRITE};
+
+    if crate::paths::is_unc(root) {
+      return Err("Network shares have no MFT to read".to_string());
+    }
+    let mount_point =
+      crate::volume::mount_point(root).ok_or("Can't find the volume of the scan root")?;
+    if crate::volume::filesystem_name(&mount_point).as_deref() != Some("NTFS") {
+      return Err(format!("{} is not an NTFS volume", mount_point));
+    }
+    let device = crate::volume::device_path(&mount_point)
+      .ok_or_else(|| format!("Can't find the device of {}", mount_point))?;
+    let mut file = std::fs::OpenOptions::new()
+      .read(true)
+      .share_mode(FILE_SHARE_READ | FILE_SHARE_WRITE)
+      .open(&device)
+      .map_err(|e| match e.kind() {
+        std::io::ErrorKind::PermissionDenied => {
+          "Reading the MFT takes administrator rights".to_string()
+        }
+        _ => format!("{}: {}", device, e),
+      })?;
+
+    let mut boot = [0u8; 512];
+    file.read_exact(&mut boot).map_err(|e| e.to_string())?;
+    if &boot[3..11] != b"NTFS    " {
+      return Err(format!("{} has no NTFS boot sector", mount_point));
+    }
+    let sector_size = le(&boot, 0x0B, 2).unwrap_or(512);
+    // Values above 0x80 encode clusters of 2^(256 - n) sectors.
+    let sectors_per_cluster = match boot[0x0D] {
+      n if n > 0x80 => 1u64.checked_shl(256 - n as u32).unwrap_or(0),
+      n => n as u64,
+    };
+    let cluster_size = sector_size * sectors_per_cluster;
+    let mft_cluster = le(&boot, 0x30, 8).unwrap_or(0);
+    // Positive: clusters per record; negative: 2^-n bytes per record.
+    let record_size = match boot[0x40] as i8 {
+      n if n > 0 => n as u64 * cluster_size,
+      n => 1u64.checked_shl(n.unsigned_abs() as u32).unwrap_or(0),
+    };
+    if cluster_size == 0 || mft_cluster == 0 || !(512..=65536).contains(&record_size) {
+      return Err(format!("{} has an unexpected NTFS geometry", mount_point));
+    }
+
+    Ok(Self {
+      file,
+      cluster_size,
+      record_size: record_size as usize,
+      mft_offset: mft_cluster * cluster_size,
+    })
+  }
+
+  /// Reads and decodes every record of the MFT, indexed by record number.
+  fn read_records(&mut self, ctx: &ScanContext) -> Result<Vec<Record>, String> {
+    // Record 0 describes the MFT itself: where its extents are and how big it is.
+    let mut first = vec![0u8; self.record_size];
+    self.read_at(self.mft_offset, &mut first)?;
+    let mft = parse_record(&mut first, true).ok_or("The $MFT record is unreadable")?;
+    let (runs, mft_size) = mft.runs.zip(mft.data).ok_or("The $MFT record has no data")?;
+
+    let count = (mft_size.0 / self.record_size as u64) as usize;
+    let mut records = Vec::with_capacity(count);
+    records.resize_with(count, Record::default);
+
+    let chunk_records = READ_CHUNK / self.record_size;
+    let mut buf = vec![0u8; chunk_records * self.record_size];
+    let mut next = 0usize;
+    for (cluster, clusters) in runs {
+      let run_records = (clusters * self.cluster_size / self.record_size as u64) as usize;
+      let Some(cluster) = cluster else {
+        // A sparse run holds no records.
+        next += run_records;
+        continue;
+      };
+      let mut done = 0;
+      while done < run_records && next < count {
+        if ctx.cancelled() {
+          return Ok(records);
+        }
+        let n = chunk_records.min(run_records - done).min(count - next);
+        let chunk = &mut buf[..n * self.record_size];
+        let offset = cluster * self.cluster_size + (done * self.record_size) as u64;
+        self.read_at(offset, chunk)?;
+        for raw in chunk.chunks_exact_mut(self.record_size) {
+          if let Some(parsed) = parse_record(raw, false) {
+            merge(&mut records, next, parsed);
+          }
+          next += 1;
+        }
+        done += n;
+        maybe_emit_progress(ctx, &ctx.control.root);
+      }
+    }
+    Ok(records)
+  }
+
+  fn read_at(&mut self, offset: u64, buf: &mut [u8]) -> Result<(), String> {
+    self
+      .file
+      .seek(SeekFrom::Start(offset))
+      .and_then(|_| self.file.read_exact(buf))
+      .map_err(|e| format!("Reading the MFT: {}", e))
+  }
+}
+
+/// What one MFT record says about the file it belongs to.
+struct ParsedRecord {
+  // Record this one extends, for attributes that didn't fit the base record.
+  base: Option<u64>,
+  is_dir: bool,
+  attributes: Option<u32>,
+  // (last write, last access).
+  times: Option<(i64, i64)>,
+  reparse_tag: Option<u32>,
+  links: Vec<(u64, String)>,
+  // (size, allocated) of the unnamed data stream.
+  data: Option<(u64, u64)>,
+  // Extents of the unnamed data stream as (first cluster, clusters); only
+  // decoded when asked for.
+  runs: Option<Vec<(Option<u64>, u64)>>,
+}
+
+/// Folds a parsed record into the file it belongs to.
+fn merge(records: &mut [Record], number: usize, parsed: ParsedRecord) {
+  let target = match parsed.base {
+    Some(base) => base as usize,
+    None => number,
+  };
+  let Some(record) = records.get_mut(target) else {
+    return;
+  };
+  if parsed.base.is_none() {
+    record.is_dir = parsed.is_dir;
+  }
+  if let Some(attributes) = parsed.attributes {
+    record.attributes = attributes;
+  }
+  if let Some((mtime, atime)) = parsed.times {
+    record.mtime = mtime;
+    record.atime = atime;
+  }
+  if let Some(tag) = parsed.reparse_tag {
+    record.reparse_tag = tag;
+  }
+  record.links.extend(parsed.links);
+  if let Some((size, allocated)) = parsed.data {
+    record.size = size;
+    record.allocated = allocated;
+  }
+}
+
+/// Decodes a raw record in place; `None` for free or damaged records.
+fn parse_record(raw: &mut [u8], want_runs: bool) -> Option<ParsedRecord> {
+  if &raw[0..4] != b"FILE" || !apply_fixup(raw) {
+    return None;
+  }
+  let flags = le(raw, 0x16, 2)? as u16;
+  if flags & RECORD_IN_USE == 0 {
+    return None;
+  }
+  let base = le(raw, 0x20, 8)? & RECORD_MASK;
+  let mut parsed = ParsedRecord {
+    base: (base != 0).then_some(base),
+    is_dir: flags & RECORD_IS_DIRECTORY != 0,
+    attributes: None,
+    times: None,
+    reparse_tag: None,
+    links: Vec::new(),
+    data: None,
+    runs: None,
+  };
+
+  let used = (le(raw, 0x18, 4)? as usize).min(raw.len());
+  let mut pos = le(raw, 0x14, 2)? as usize;
+  while pos + 16 <= used {
+    let kind = le(raw, pos, 4)? as u32;
+    let len = le(raw, pos + 4, 4)? as usize;
+    if kind == ATTR_END || len == 0 || pos + len > used {
+      break;
+    }
+    let attr = &raw[pos..pos + len];
+    let non_resident = attr[8] != 0;
+    let unnamed = attr[9] == 0;
+    match kind {
+      ATTR_STANDARD_INFORMATION if !non_resident => {
+        let value = resident_value(attr);
+        parsed.attributes = value.and_then(|v| le(v, 0x20, 4)).map(|a| a as u32);
+        parsed.times = value
+          .and_then(|v| Some((le(v, 0x08, 8)? as i64, le(v, 0x18, 8)? as i64)));
+      }
+      ATTR_FILE_NAME if !non_resident => {
+        if let Some(link) = resident_value(attr).and_then(file_name) {
+          parsed.links.push(link);
+        }
+      }
+      // Later extents of a fragmented stream repeat no sizes.
+      ATTR_DATA if unnamed && non_resident && le(attr, 0x10, 8) == Some(0) => {
+        // Compressed and sparse streams have a header extended by the
+        // clusters they actually use; the usual allocated size counts holes.
+        let allocated_at = match le(attr, 0x0C, 2)? & (ATTR_FLAG_COMPRESSED | ATTR_FLAG_SPARSE) {
+          0 => 0x28,
+          _ => 0x40,
+        };
+        parsed.data = Some((le(attr, 0x30, 8)?, le(attr, allocated_at, 8)?));
+        if want_runs {
+          let runs_at = le(attr, 0x20, 2)? as usize;
+          parsed.runs = Some(data_runs(attr.get(runs_at..)?));
+        }
+      }
+      // The reparse data starts with its tag.
+      ATTR_REPARSE_POINT if !non_resident => {
+        parsed.reparse_tag = resident_value(attr).and_then(|v| le(v, 0, 4)).map(|t| t as u32);
+      }
+      // Small files live inside the record and take no clusters of their own.
+      ATTR_DATA if unnamed && !non_resident => {
+        parsed.data = Some((le(attr, 0x10, 4)?, 0));
+      }
+      _ => {}
+    }
+    pos += len;
+  }
+  Some(parsed)
+}
+
+/// Restores the last two bytes of every sector, which NTFS swaps for an update
+/// sequence number to detect torn writes. False if the record is torn.
+fn apply_fixup(raw: &mut [u8]) -> bool {
+  let (Some(offset), Some(count)) = (le(raw, 4, 2), le(raw, 6, 2)) else {
+    return false;
+  };
+  let (offset, count) = (offset as usize, count as usize);
+  if count < 2 || offset + count * 2 > raw.len() {
+    return false;
+  }
+  let stride = raw.len() / (count - 1);
+  let check = [raw[offset], raw[offset + 1]];
+  for i in 1..count {
+    let end = i * stride;
+    if raw[end - 2..end] != check {
+      return false;
+    }
+    let fix = offset + i * 2;
+    raw[end - 2] = raw[fix];
+    raw[end - 1] = raw[fix + 1];
+  }
+  true
+}
+
+fn resident_value(attr: &[u8]) -> Option<&[u8]> {
+  let len = le(attr, 0x10, 4)? as usize;
+  let offset = le(attr, 0x14, 2)? as usize;
+  attr.get(offset..offset + len)
+}
+
+/// (parent record, name) from a $FILE_NAME value, or `None` for DOS aliases.
+fn file_name(value: &[u8]) -> Option<(u64, String)> {
+  let parent = le(value, 0, 8)? & RECORD_MASK;
+  let len = *value.get(0x40)? as usize;
+  if *value.get(0x41)? == NAMESPACE_DOS {
+    return None;
+  }
+  let units: Vec<u16> = value
+    .get(0x42..0x42 + len * 2)?
+    .chunks_exact(2)
+    .map(|c| u16::from_le_bytes([c[0], c[1]]))
+    .collect();
+  Some((parent, String::from_utf16_lossy(&units)))
+}
+
+/// Decodes a mapping-pairs list into (first cluster, clusters) extents; sparse
+/// extents have no cluster.
+fn data_runs(mut runs: &[u8]) -> Vec<(Option<u64>, u64)> {
+  let mut extents = Vec::new();
+  let mut cluster = 0i64;
+  while let Some((&header, rest)) = runs.split_first() {
+    let (len_size, offset_size) = ((header & 0x0F) as usize, (header >> 4) as usize);
+    if header == 0 || len_size > 8 || offset_size > 8 || rest.len() < len_size + offset_size {
+      break;
+    }
+    let clusters = le(rest, 0, len_size).unwrap_or(0);
+    if offset_size == 0 {
+      extents.push((None, clusters));
+    } else {
+      // Offsets are signed and relative to the previous extent.
+      let raw = le(rest, len_size, offset_size).unwrap_or(0);
+      let shift = 64 - 8 * offset_size as u32;
+      cluster += ((raw << shift) as i64) >> shift;
+      extents.push((Some(cluster as u64), clusters));
+    }
+    runs = &rest[len_size + offset_size..];
+  }
+  extents
+}
+
+/// Little-endian unsigned integer of `n` bytes (at most 8) at `at`.
+pub(super) fn le(buf: &[u8], at: usize, n: usize) -> Option<u64> {
+  let bytes = buf.get(at..at.checked_add(n)?)?;
+  Some(bytes.iter().rev().fold(0u64, |acc, &b| (acc << 8) | b as u64))
+}
@@ -0,0 +1,52 @@
+//! The `storage` plugin, the way into the Kotlin and Swift halves of the
+//! mobile scans. It's registered everywhere so the app is built the same
+//! way; on the desktop it does nothing.
+
+use tauri::{
+  plugin::{Builder, TauriPlugin},
+  Wry,
+};
+#[cfg(mobile)]
+use {
+  serde::{de::DeserializeOwned, Serialize},
+  tauri::{plugin::PluginHandle, AppHandle, Manager},
+};
+
+/// The Android package of the app, after `identifier` in tauri.conf.json.
+#[cfg(target_os = "android")]
+const ANDROID_PACKAGE: &str = "com.example.space_usage_app";
+
+#[cfg(target_os = "ios")]
+tauri::ios_plugin_binding!(init_plugin_storage);
+
+/// The native side of the plugin, once it's registered.
+#[cfg(mobile)]
+struct Bridge(PluginHandle<Wry>);
+
+pub fn init() -> TauriPlugin<Wry> {
+  Builder::new("storage")
+    .setup(|_app, _api| {
+      #[cfg(target_os = "android")]
+      _app.manage(Bridge(_api.register_android_plugin(ANDROID_PACKAGE, "StoragePlugin")?));
+      #[cfg(target_os = "ios")]
+      _app.manage(Bridge(_api.register_ios_plugin(init_plugin_storage)?));
+      Ok(())
+    })
+    .build()
+}
+
+#[cfg(mobile)]
+pub(super) fn bridge(app: &AppHandle) -> Result<PluginHandle<Wry>, String> {
+  let bridge = app.try_state::<Bridge>().ok_or("The storage plugin isn't registered")?;
+  Ok(bridge.0.clone())
+}
+
+/// Runs `command` on the native side and waits for what it resolves with.
+#[cfg(mobile)]
+pub(super) fn call<T: DeserializeOwned>(
+  bridge: &PluginHandle<Wry>,
+  command: &str,
+  payload: impl Serialize,
+) -> Result<T, String> {
+  bridge.run_mobile_plugin(command, payload).map_err(|e| e.to_string())
+}
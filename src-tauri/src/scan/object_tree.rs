@@ -0,0 +1,133 @@
+//! Trees built from flat listings of `/`-separated paths, the way object
+//! stores and rclone list what they hold, with the bytes in each storage
+//! class added up along the way.
+
+use super::{dir_node, file_node, iso::days_from_civil, FileSizes, ScanContext, ScanNode};
+use serde::Serialize;
+use std::{collections::BTreeMap, path::Path};
+
+/// What one storage class holds, which is what object stores bill by.
+#[derive(Clone, Serialize)]
+pub struct StorageClassTotal {
+  /// As the service names it: `STANDARD`, `STANDARD_IA`, `GLACIER`, `Cool`…
+  pub class: String,
+  pub bytes: u64,
+  pub objects: u64,
+}
+
+pub(super) struct Object {
+  /// The whole path in a listing, the last part of it once in a tree.
+  pub key: String,
+  pub size: u64,
+  pub mtime: Option<i64>,
+  /// The storage class, where the service has them.
+  pub class: Option<String>,
+}
+
+/// The objects under one `/`-separated prefix.
+#[derive(Default)]
+pub(super) struct ObjectTree {
+  folders: BTreeMap<String, ObjectTree>,
+  objects: Vec<Object>,
+}
+
+impl ObjectTree {
+  /// The folder at `relative`, made if it isn't there. Empty parts, as in
+  /// `a//b`, are skipped.
+  pub(super) fn folder(&mut self, relative: &str) -> &mut ObjectTree {
+    let mut folder = self;
+    for part in relative.split('/').filter(|part| !part.is_empty()) {
+      folder = folder.folders.entry(part.to_string()).or_default();
+    }
+    folder
+  }
+
+  /// Files `object` at `relative`. A path ending with `/` only marks a
+  /// folder, as S3 consoles make them.
+  pub(super) fn insert(&mut self, relative: &str, mut object: Object) {
+    let (parent, name) = relative.rsplit_once('/').unwrap_or(("", relative));
+    let folder = self.folder(parent);
+    if !name.is_empty() {
+      object.key = name.to_string();
+      folder.objects.push(object);
+    }
+  }
+}
+
+/// The tree under `root`, with what the filters let through, and the
+/// storage classes of its objects, largest first.
+pub(super) fn build(
+  ctx: &ScanContext,
+  tree: ObjectTree,
+  root: &str,
+) -> (ScanNode, Vec<StorageClassTotal>) {
+  let mut classes = BTreeMap::new();
+  let node = build_folder(ctx, tree, root, 0, &mut classes);
+  let mut classes: Vec<StorageClassTotal> = classes
+    .into_iter()
+    .map(|(class, (bytes, objects))| StorageClassTotal { class, bytes, objects })
+    .collect();
+  classes.sort_by_key(|total| std::cmp::Reverse(total.bytes));
+  (node, classes)
+}
+
+/// The node of `folder` at `path`, adding its objects to `classes` as
+/// (bytes, objects).
+fn build_folder(
+  ctx: &ScanContext,
+  folder: ObjectTree,
+  path: &str,
+  depth: usize,
+  classes: &mut BTreeMap<String, (u64, u64)>,
+) -> ScanNode {
+  let mut nodes = Vec::with_capacity(folder.folders.len() + folder.objects.len());
+  for (name, inner) in folder.folders {
+    let inner_path = format!("{}/{}", path, name);
+    if !ctx.filters.excludes(Path::new(&inner_path)) {
+      nodes.push(build_folder(ctx, inner, &inner_path, depth + 1, classes));
+    }
+  }
+  for object in folder.objects {
+    let object_path = format!("{}/{}", path, object.key);
+    let object_path = Path::new(&object_path);
+    if ctx.filters.excludes(object_path) || !ctx.filters.includes_file(object_path) {
+      continue;
+    }
+    if let Some(class) = object.class {
+      let total = classes.entry(class).or_default();
+      total.0 += object.size;
+      total.1 += 1;
+    }
+    // An object takes its size, whatever the service stores it as.
+    let sizes = FileSizes {
+      apparent: object.size,
+      allocated: object.size,
+    };
+    let mut node = file_node(object_path, sizes);
+    node.mtime = object.mtime;
+    nodes.push(node);
+  }
+  let mut node = dir_node(ctx, Path::new(path), nodes, 0);
+  if depth >= ctx.options.max_depth as usize {
+    node.children = None;
+  }
+  node
+}
+
+/// An RFC 3339 time like `2009-10-12T17:50:30.000Z` or
+/// `2017-05-31T16:15:57.034468261+01:00`, in seconds since the Unix epoch.
+pub(super) fn parse_time(text: &str) -> Option<i64> {
+  let number = |at: usize, len: usize| text.get(at..at + len)?.parse::<i64>().ok();
+  let days = days_from_civil(number(0, 4)?, number(5, 2)?, number(8, 2)?);
+  let local = days * 86_400 + number(11, 2)? * 3600 + number(14, 2)? * 60 + number(17, 2)?;
+  let zone = text.get(19..)?.trim_start_matches(|c: char| c == '.' || c.is_ascii_digit());
+  let offset = match zone.split_at_checked(1) {
+    Some(("+", hours_minutes)) | Some(("-", hours_minutes)) => {
+      let (hours, minutes) = hours_minutes.split_once(':')?;
+      let offset = hours.parse::<i64>().ok()? * 3600 + minutes.parse::<i64>().ok()? * 60;
+      if zone.starts_with('-') { -offset } else { offset }
+    }
+    _ => 0,
+  };
+  Some(local - offset)
+}
@@ -0,0 +1,202 @@
+//! Who owns the bytes of a scan: file owners resolved to account names while
+//! walking, and per-owner totals for every directory node once it's done.
+
+use super::{FileSizes, ScanManager};
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::{collections::HashMap, sync::Arc};
+use tauri::State;
+
+/// What one owner holds in a subtree.
+#[derive(Clone, Copy, Default)]
+pub(super) struct OwnerUsage {
+  bytes: u64,
+  allocated: u64,
+  files: u64,
+}
+
+/// Usage per owner name.
+pub(super) type OwnerTotals = HashMap<Arc<str>, OwnerUsage>;
+
+/// Adds one file of `owner` to `totals`.
+pub(super) fn add_file(totals: &mut OwnerTotals, owner: &str, sizes: FileSizes) {
+  let usage = match totals.get_mut(owner) {
+    Some(usage) => usage,
+    None => totals.entry(Arc::from(owner)).or_default(),
+  };
+  usage.bytes = usage.bytes.saturating_add(sizes.apparent);
+  usage.allocated = usage.allocated.saturating_add(sizes.allocated);
+  usage.files += 1;
+}
+
+pub(super) fn merge(totals: &mut OwnerTotals, other: &OwnerTotals) {
+  for (owner, usage) in other {
+    let into = totals.entry(Arc::clone(owner)).or_default();
+    into.bytes = into.bytes.saturating_add(usage.bytes);
+    into.allocated = into.allocated.saturating_add(usage.allocated);
+    into.files += usage.files;
+  }
+}
+
+#[derive(Clone, Serialize)]
+pub struct OwnerStat {
+  pub owner: String,
+  pub bytes: u64,
+  pub allocated: u64,
+  pub files: u64,
+}
+
+/// Per-owner totals of the directory at `path` in a finished scan, or of its
+/// root when `path` is omitted, largest first. The scan must have been run
+/// with `track_owners`; directories past `max_depth` have no totals of their
+/// own.
+#[tauri::command]
+pub async fn get_owner_stats(
+  state: State<'_, ScanManager>,
+  scan_id: String,
+  path: Option<String>,
+) -> Result<Vec<OwnerStat>, String> {
+  let results = state.results(&scan_id)?;
+  let owners = results
+    .owners
+    .as_ref()
+    .ok_or_else(|| "The scan didn't track owners".to_string())?;
+  let path = path.unwrap_or_else(|| results.root.clone());
+  let totals = owners
+    .get(&path)
+    .ok_or_else(|| format!("{} is not a directory of the scan", path))?;
+  let mut stats: Vec<_> = totals
+    .iter()
+    .map(|(owner, usage)| OwnerStat {
+      owner: owner.to_string(),
+      bytes: usage.bytes,
+      allocated: usage.allocated,
+      files: usage.files,
+    })
+    .collect();
+  stats.sort_by(|a, b| b.bytes.cmp(&a.bytes).then_with(|| a.owner.cmp(&b.owner)));
+  Ok(stats)
+}
+
+/// Account names of file owners, each looked up once per scan.
+#[derive(Default)]
+pub(super) struct OwnerNames {
+  #[cfg(unix)]
+  names: Mutex<HashMap<u32, Arc<str>>>,
+  // Keyed by the bytes of the owner SID.
+  #[cfg(windows)]
+  names: Mutex<HashMap<Vec<u8>, Arc<str>>>,
+}
+
+impl OwnerNames {
+  /// The user name of `uid`, or the number itself when it has no account.
+  #[cfg(unix)]
+  pub(super) fn of_uid(&self, uid: u32) -> Arc<str> {
+    let mut names = self.names.lock();
+    let name = names.entry(uid).or_insert_with(|| match user_name(uid) {
+      Some(name) => Arc::from(name),
+      None => Arc::from(uid.to_string()),
+    });
+    Arc::clone(name)
+  }
+
+  /// `DOMAIN\user` of the owner of the file at `path`, its SID string when
+  /// the account is unknown, and `(unknown)` when the owner can't be read.
+  #[cfg(windows)]
+  pub(super) fn of_path(&self, path: &std::path::Path) -> Arc<str> {
+    use windows_sys::Win32::{
+      Foundation::{LocalFree, ERROR_SUCCESS},
+      Security::{
+        Authorization::{GetNamedSecurityInfoW, SE_FILE_OBJECT},
+        GetLengthSid, OWNER_SECURITY_INFORMATION, PSECURITY_DESCRIPTOR, PSID,
+      },
+    };
+    let wide = crate::volume::to_wide(path);
+    let mut owner: PSID = std::ptr::null_mut();
+    let mut descriptor: PSECURITY_DESCRIPTOR = std::ptr::null_mut();
+    let status = unsafe {
+      GetNamedSecurityInfoW(
+        wide.as_ptr(),
+        SE_FILE_OBJECT,
+        OWNER_SECURITY_INFORMATION,
+        &mut owner,
+        std::ptr::null_mut(),
+        std::ptr::null_mut(),
+        std::ptr::null_mut(),
+        &mut descriptor,
+      )
+    };
+    if status != ERROR_SUCCESS || owner.is_null() {
+      return Arc::from("(unknown)");
+    }
+    let sid = unsafe {
+      std::slice::from_raw_parts(owner as *const u8, GetLengthSid(owner) as usize).to_vec()
+    };
+    let name = {
+      let mut names = self.names.lock();
+      let name = names.entry(sid).or_insert_with(|| Arc::from(account_name(owner)));
+      Arc::clone(name)
+    };
+    unsafe { LocalFree(descriptor) };
+    name
+  }
+}
+
+#[cfg(unix)]
+fn user_name(uid: u32) -> Option<String> {
+  let mut buf = vec![0 as libc::c_char; 1024];
+  loop {
+    let mut pwd: libc::passwd = unsafe { std::mem::zeroed() };
+    let mut found = std::ptr::null_mut();
+    let err =
+      unsafe { libc::getpwuid_r(uid, &mut pwd, buf.as_mut_ptr(), buf.len(), &mut found) };
+    if err == libc::ERANGE && buf.len() < 1 << 20 {
+      buf.resize(buf.len() * 2, 0);
+      continue;
+    }
+    if err != 0 || found.is_null() {
+      return None;
+    }
+    let name = unsafe { std::ffi::CStr::from_ptr(pwd.pw_name) };
+    return Some(name.to_string_lossy().into_owned());
+  }
+}
+
+#[cfg(windows)]
+fn account_name(sid: windows_sys::Win32::Security::PSID) -> String {
+  use windows_sys::Win32::{
+    Foundation::LocalFree,
+    Security::{Authorization::ConvertSidToStringSidW, LookupAccountSidW},
+  };
+  let mut name = [0u16; 256];
+  let mut domain = [0u16; 256];
+  let (mut name_len, mut domain_len) = (name.len() as u32, domain.len() as u32);
+  let mut sid_use = 0;
+  let found = unsafe {
+    LookupAccountSidW(
+      std::ptr::null(),
+      sid,
+      name.as_mut_ptr(),
+      &mut name_len,
+      domain.as_mut_ptr(),
+      &mut domain_len,
+      &mut sid_use,
+    )
+  } != 0;
+  if found {
+    let name = String::from_utf16_lossy(&name[..name_len as usize]);
+    return match domain_len {
+      0 => name,
+      _ => format!("{}\\{}", String::from_utf16_lossy(&domain[..domain_len as usize]), name),
+    };
+  }
+  // Accounts of another machine or a deleted user only have their SID.
+  let mut text = std::ptr::null_mut();
+  if unsafe { ConvertSidToStringSidW(sid, &mut text) } == 0 {
+    return "(unknown)".to_string();
+  }
+  let len = (0..).take_while(|&i| unsafe { *text.add(i) } != 0).count();
+  let sid = String::from_utf16_lossy(unsafe { std::slice::from_raw_parts(text, len) });
+  unsafe { LocalFree(text.cast()) };
+  sid
+}
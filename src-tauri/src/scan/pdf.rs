@@ -0,0 +1,327 @@
+//! A printable summary of a finished scan, for attaching to tickets and
+//! change requests: the volume, the largest folders and files, the kinds of
+//! files and what couldn't be read.
+//!
+//! The PDF is written by hand. It only needs text in the fonts every reader
+//! has and a few filled bars, so pages are plain content streams in
+//! Helvetica, with text in its WinAnsi encoding.
+
+use super::{
+  blocking, category::FileCategory, export::date_time, NodeKind, ScanErrorKind, ScanManager,
+  ScanResults,
+};
+use std::{
+  fmt::Write as _,
+  time::{SystemTime, UNIX_EPOCH},
+};
+use tauri::State;
+
+// A4, in points.
+const PAGE_WIDTH: f32 = 595.0;
+const PAGE_HEIGHT: f32 = 842.0;
+const MARGIN: f32 = 50.0;
+/// Where the path or name of a row starts, after its size.
+const TEXT_COLUMN: f32 = MARGIN + 110.0;
+/// Characters of a path that fit between `TEXT_COLUMN` and the margin.
+const PATH_CHARS: usize = 78;
+
+const GRAY: (f32, f32, f32) = (0.45, 0.45, 0.45);
+const BLACK: (f32, f32, f32) = (0.0, 0.0, 0.0);
+
+/// Pages of text, laid out from the top down.
+struct Document {
+  pages: Vec<String>,
+  page: String,
+  /// The baseline of the next line.
+  y: f32,
+}
+
+impl Document {
+  fn new() -> Self {
+    Document {
+      pages: Vec::new(),
+      page: String::new(),
+      y: PAGE_HEIGHT - MARGIN,
+    }
+  }
+
+  /// Goes on to a new page unless `height` more fits on this one.
+  fn room(&mut self, height: f32) {
+    if self.y - height < MARGIN + 20.0 {
+      self.pages.push(std::mem::take(&mut self.page));
+      self.y = PAGE_HEIGHT - MARGIN;
+    }
+  }
+
+  fn text(&mut self, x: f32, y: f32, size: f32, bold: bool, color: (f32, f32, f32), text: &str) {
+    let font = if bold { "F2" } else { "F1" };
+    let (r, g, b) = color;
+    let _ = writeln!(
+      self.page,
+      "BT {r} {g} {b} rg /{font} {size} Tf {x} {y} Td ({}) Tj ET",
+      pdf_string(text)
+    );
+  }
+
+  fn title(&mut self, text: &str) {
+    self.room(30.0);
+    self.y -= 10.0;
+    self.text(MARGIN, self.y, 18.0, true, BLACK, text);
+    self.y -= 20.0;
+  }
+
+  fn heading(&mut self, text: &str) {
+    // Keeps a heading with the first row under it.
+    self.room(50.0);
+    self.y -= 14.0;
+    self.text(MARGIN, self.y, 13.0, true, BLACK, text);
+    self.y -= 18.0;
+  }
+
+  fn line(&mut self, text: &str, color: (f32, f32, f32)) {
+    self.room(14.0);
+    self.text(MARGIN, self.y, 10.0, false, color, text);
+    self.y -= 14.0;
+  }
+
+  /// A size, then a path or name; with a bar under it for `share`, 0–1.
+  fn row(&mut self, size: &str, text: &str, share: Option<(f32, (f32, f32, f32))>) {
+    self.room(18.0);
+    self.text(MARGIN, self.y, 9.5, true, BLACK, size);
+    self.text(TEXT_COLUMN, self.y, 9.5, false, BLACK, &shortened(text, PATH_CHARS));
+    if let Some((share, (r, g, b))) = share {
+      let width = (PAGE_WIDTH - MARGIN - TEXT_COLUMN) * share.clamp(0.0, 1.0);
+      let y = self.y - 5.0;
+      let _ = writeln!(self.page, "{r} {g} {b} rg {TEXT_COLUMN} {y} {width} 2.5 re f");
+      self.y -= 5.0;
+    }
+    self.y -= 13.0;
+  }
+
+  /// The file: the pages, each numbered at the foot, then the fonts, the
+  /// page tree and the table of where each object starts.
+  fn finish(mut self) -> Vec<u8> {
+    if !self.page.is_empty() || self.pages.is_empty() {
+      self.pages.push(std::mem::take(&mut self.page));
+    }
+    let count = self.pages.len();
+    // 1 is the catalog, 2 the page tree, 3 and 4 the fonts, then a page and
+    // its contents each.
+    let mut objects = vec![
+      "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+      format!(
+        "<< /Type /Pages /Kids [{}] /Count {} >>",
+        (0..count).map(|i| format!("{} 0 R", 5 + 2 * i)).collect::<Vec<_>>().join(" "),
+        count
+      ),
+      "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica /Encoding /WinAnsiEncoding >>"
+        .to_string(),
+      "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica-Bold /Encoding /WinAnsiEncoding >>"
+        .to_string(),
+    ];
+    for (i, mut content) in std::mem::take(&mut self.pages).into_iter().enumerate() {
+      let footer = format!("Page {} of {}", i + 1, count);
+      let (r, g, b) = GRAY;
+      let _ = writeln!(
+        content,
+        "BT {r} {g} {b} rg /F1 8 Tf {MARGIN} {} Td ({footer}) Tj ET",
+        MARGIN - 20.0
+      );
+      objects.push(format!(
+        "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {PAGE_WIDTH} {PAGE_HEIGHT}] \
+         /Resources << /Font << /F1 3 0 R /F2 4 0 R >> >> /Contents {} 0 R >>",
+        6 + 2 * i
+      ));
+      let length = content.chars().count();
+      objects.push(format!("<< /Length {} >>\nstream\n{}endstream", length, content));
+    }
+    let mut out = b"%PDF-1.4\n%\xe2\xe3\xcf\xd3\n".to_vec();
+    let mut offsets = Vec::with_capacity(objects.len());
+    for (i, object) in objects.iter().enumerate() {
+      offsets.push(out.len());
+      out.extend_from_slice(format!("{} 0 obj\n", i + 1).as_bytes());
+      // Text was encoded as WinAnsi bytes, one per char, on the way in.
+      out.extend(object.chars().map(|c| c as u32 as u8));
+      out.extend_from_slice(b"\nendobj\n");
+    }
+    let xref = out.len();
+    let mut table = format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1);
+    for offset in offsets {
+      let _ = writeln!(table, "{:010} 00000 n ", offset);
+    }
+    let _ = write!(
+      table,
+      "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+      objects.len() + 1,
+      xref
+    );
+    out.extend_from_slice(table.as_bytes());
+    out
+  }
+}
+
+/// `text` as a PDF string of WinAnsi bytes, kept one per char so the
+/// document can be put together as text. What WinAnsi lacks becomes `?`.
+fn pdf_string(text: &str) -> String {
+  let mut out = String::with_capacity(text.len());
+  for c in text.chars() {
+    let byte = match c {
+      '(' | ')' | '\\' => {
+        out.push('\\');
+        c as u8
+      }
+      ' '..='~' | '\u{a0}'..='\u{ff}' => c as u32 as u8,
+      '€' => 0x80,
+      '…' => 0x85,
+      '–' => 0x96,
+      '—' => 0x97,
+      _ => b'?',
+    };
+    out.push(byte as char);
+  }
+  out
+}
+
+/// `text` cut down to `max` chars, from the middle, where a path can best
+/// spare them.
+fn shortened(text: &str, max: usize) -> String {
+  let count = text.chars().count();
+  if count <= max {
+    return text.to_string();
+  }
+  let head = (max - 1) / 2;
+  let tail = max - 1 - head;
+  let start: String = text.chars().take(head).collect();
+  let end: String = text.chars().skip(count - tail).collect();
+  format!("{start}…{end}")
+}
+
+fn format_bytes(bytes: u64) -> String {
+  const UNITS: [&str; 6] = ["B", "KB", "MB", "GB", "TB", "PB"];
+  if bytes < 1024 {
+    return format!("{bytes} B");
+  }
+  let exp = ((bytes as f64).log(1024.0).floor() as usize).min(UNITS.len() - 1);
+  let value = bytes as f64 / 1024f64.powi(exp as i32);
+  format!("{:.*} {}", if value >= 10.0 { 1 } else { 2 }, value, UNITS[exp])
+}
+
+fn percent(part: u64, whole: u64) -> f32 {
+  match whole {
+    0 => 0.0,
+    _ => part as f32 / whole as f32 * 100.0,
+  }
+}
+
+fn category_label(category: FileCategory) -> (&'static str, (f32, f32, f32)) {
+  // The colors of the app's treemap.
+  match category {
+    FileCategory::Image => ("Images", (0.90, 0.65, 0.04)),
+    FileCategory::Video => ("Videos", (0.75, 0.11, 0.16)),
+    FileCategory::Audio => ("Audio", (0.57, 0.25, 0.67)),
+    FileCategory::Document => ("Documents", (0.11, 0.44, 0.85)),
+    FileCategory::Code => ("Code", (0.18, 0.76, 0.49)),
+    FileCategory::Archive => ("Archives", (0.53, 0.37, 0.24)),
+    FileCategory::Executable => ("Programs", (0.90, 0.38, 0.0)),
+    FileCategory::Other => ("Other", (0.47, 0.46, 0.48)),
+  }
+}
+
+fn error_label(kind: ScanErrorKind) -> &'static str {
+  match kind {
+    ScanErrorKind::PermissionDenied => "Permission denied",
+    ScanErrorKind::NotFound => "Gone while scanning",
+    ScanErrorKind::IoTimeout => "Timed out",
+    ScanErrorKind::FilesystemLoop => "Link loops",
+    ScanErrorKind::Other => "Other errors",
+  }
+}
+
+fn render(results: &ScanResults) -> Vec<u8> {
+  let tree = &results.tree;
+  let now = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs() as i64);
+  let mut doc = Document::new();
+  doc.title("Disk usage report");
+  doc.line(&shortened(&results.root, 95), BLACK);
+  doc.line(&format!("Made {} UTC", date_time(now)), GRAY);
+
+  doc.heading("Overview");
+  if let Some(volume) = results.volume {
+    let used = volume.used_bytes();
+    doc.row(&format_bytes(volume.total_bytes), "Volume capacity", None);
+    let text = format!("Used ({:.1}%)", percent(used, volume.total_bytes));
+    doc.row(&format_bytes(used), &text, Some((percent(used, volume.total_bytes) / 100.0, GRAY)));
+    doc.row(&format_bytes(volume.free_bytes), "Free", None);
+  }
+  doc.row(&format_bytes(tree.size), "Scanned, as the files' sizes add up", None);
+  doc.row(&format_bytes(tree.allocated), "Scanned, as taken on disk", None);
+  let counts = format!(
+    "{} files in {} folders",
+    tree.file_count.unwrap_or(0),
+    tree.dir_count.unwrap_or(0)
+  );
+  doc.line(&counts, GRAY);
+
+  let mut folders: Vec<_> = tree
+    .children
+    .iter()
+    .flatten()
+    .filter(|child| matches!(child.kind, NodeKind::Dir))
+    .collect();
+  if !folders.is_empty() {
+    folders.sort_by_key(|folder| std::cmp::Reverse(folder.size));
+    doc.heading("Largest folders");
+    for folder in folders.iter().take(15) {
+      let share = percent(folder.size, tree.size);
+      let size = format!("{}  {:.0}%", format_bytes(folder.size), share);
+      doc.row(&size, &folder.name, Some((share / 100.0, (0.35, 0.55, 0.85))));
+    }
+  }
+
+  if !results.top_files.is_empty() {
+    doc.heading("Largest files");
+    for file in results.top_files.iter().take(20) {
+      doc.row(&format_bytes(file.size), &file.path, None);
+    }
+  }
+
+  let total: u64 = results.categories.iter().map(|c| c.bytes).sum();
+  if total > 0 {
+    doc.heading("Kinds of files");
+    for stat in results.categories.iter().filter(|c| c.bytes > 0) {
+      let (label, color) = category_label(stat.category);
+      let share = percent(stat.bytes, total);
+      let text = format!("{label}, {} files, {:.1}%", stat.files, share);
+      doc.row(&format_bytes(stat.bytes), &text, Some((share / 100.0, color)));
+    }
+  }
+
+  doc.heading("Errors");
+  let errors = &results.error_summary;
+  if errors.total == 0 {
+    doc.line("Everything could be read.", GRAY);
+  } else {
+    doc.line(&format!("{} entries couldn't be read.", errors.total), BLACK);
+    for category in &errors.categories {
+      doc.row(&category.count.to_string(), error_label(category.kind), None);
+      for path in category.sample_paths.iter().take(3) {
+        doc.line(&format!("        {}", shortened(path, 90)), GRAY);
+      }
+    }
+  }
+  doc.finish()
+}
+
+/// Writes a printable PDF summary of a finished scan to `pdf_path`.
+#[tauri::command]
+pub async fn generate_report(
+  state: State<'_, ScanManager>,
+  scan_id: String,
+  pdf_path: String,
+) -> Result<(), String> {
+  let results = state.results(&scan_id)?;
+  blocking(move || {
+    std::fs::write(&pdf_path, render(&results)).map_err(|e| format!("{pdf_path}: {e}"))
+  })
+  .await
+}
@@ -0,0 +1,145 @@
+//! Space macOS counts as used but gives back on demand: purgeable files
+//! (iCloud copies, caches the system manages) and local Time Machine
+//! snapshots, which hold on to the blocks of files deleted since. A walk sees
+//! neither, so a volume can look fuller than its tree adds up to.
+
+use super::blocking;
+use serde::Serialize;
+
+#[derive(Clone, Serialize)]
+pub struct LocalSnapshot {
+  pub name: String,
+  /// When it was taken, local time, as in its name: `2024-05-01 13:45:10`.
+  pub date: Option<String>,
+  /// What it holds alone; macOS doesn't tell, so for now always `None`.
+  pub bytes: Option<u64>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct PurgeableSpace {
+  pub volume: String,
+  /// Free to use once macOS purges what it can, snapshots included.
+  pub purgeable_bytes: Option<u64>,
+  pub free_bytes: u64,
+  pub snapshots: Vec<LocalSnapshot>,
+}
+
+/// Prints the capacity available for important use, which counts purgeable
+/// space as free, and the plain available capacity.
+#[cfg(target_os = "macos")]
+const CAPACITY_SCRIPT: &str = "ObjC.import('Foundation'); \
+  const path = $.NSProcessInfo.processInfo.environment.objectForKey('SPACE_USAGE_VOLUME').js; \
+  const url = $.NSURL.fileURLWithPath(path); \
+  const value = (key) => { const out = Ref(); \
+    url.getResourceValueForKeyError(out, key, null); return out[0].js; }; \
+  `${value('NSURLVolumeAvailableCapacityForImportantUsageKey')} \
+${value('NSURLVolumeAvailableCapacityKey')}`";
+
+#[cfg(target_os = "macos")]
+fn purgeable_bytes(volume: &str) -> Option<u64> {
+  let mut command = std::process::Command::new("osascript");
+  command.args(["-l", "JavaScript", "-e", CAPACITY_SCRIPT]).env("SPACE_USAGE_VOLUME", volume);
+  let output = super::command_output(&mut command).ok()?;
+  let mut values = output.split_whitespace().map(|value| value.parse::<u64>().ok());
+  let (important, available) = (values.next()??, values.next()??);
+  Some(important.saturating_sub(available))
+}
+
+/// `tmutil listlocalsnapshots` prints one
+/// `com.apple.TimeMachine.2024-05-01-134510.local` per line, under a header.
+#[cfg(target_os = "macos")]
+fn local_snapshots(volume: &str) -> Result<Vec<LocalSnapshot>, String> {
+  let mut command = std::process::Command::new("tmutil");
+  let output = super::command_output(command.args(["listlocalsnapshots", volume]))
+    .map_err(|e| format!("tmutil: {}", e))?;
+  Ok(
+    output
+      .lines()
+      .map(str::trim)
+      .filter(|line| line.starts_with("com.apple."))
+      .map(|name| LocalSnapshot {
+        date: snapshot_date(name),
+        name: name.to_string(),
+        bytes: None,
+      })
+      .collect(),
+  )
+}
+
+/// `2024-05-01 13:45:10` from a name ending in `.2024-05-01-134510.local`.
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+fn snapshot_date(name: &str) -> Option<String> {
+  let stamp = name.strip_suffix(".local")?.rsplit('.').next()?;
+  let (day, time) = stamp.split_at_checked(10)?;
+  let time = time.strip_prefix('-').filter(|time| time.len() == 6)?;
+  Some(format!("{} {}:{}:{}", day, &time[..2], &time[2..4], &time[4..]))
+}
+
+/// Purgeable bytes of the volume mounted at `volume`, on macOS.
+pub(super) fn purgeable_on(volume: &std::path::Path) -> Option<u64> {
+  #[cfg(target_os = "macos")]
+  return purgeable_bytes(&volume.to_string_lossy());
+  #[cfg(not(target_os = "macos"))]
+  {
+    let _ = volume;
+    None
+  }
+}
+
+#[cfg(target_os = "macos")]
+fn purgeable_space(volume: &str) -> Result<PurgeableSpace, String> {
+  let usage = crate::volume::usage(std::path::Path::new(volume))
+    .ok_or_else(|| format!("{}: not a mounted volume", volume))?;
+  Ok(PurgeableSpace {
+    volume: volume.to_string(),
+    purgeable_bytes: purgeable_bytes(volume),
+    free_bytes: usage.free_bytes,
+    snapshots: local_snapshots(volume)?,
+  })
+}
+
+#[cfg(not(target_os = "macos"))]
+fn purgeable_space(_volume: &str) -> Result<PurgeableSpace, String> {
+  Err("Purgeable space and local snapshots are only reported on macOS".to_string())
+}
+
+/// Asks Time Machine to delete local snapshots of `volume`, oldest first,
+/// until `bytes` are free or `urgency` (1 to 4) allows no more.
+#[cfg(target_os = "macos")]
+fn thin_snapshots(volume: &str, bytes: u64, urgency: u8) -> Result<(), String> {
+  let mut command = std::process::Command::new("tmutil");
+  command.args(["thinlocalsnapshots", volume, &bytes.to_string(), &urgency.to_string()]);
+  super::command_output(&mut command).map(|_| ()).map_err(|e| format!("tmutil: {}", e))
+}
+
+#[cfg(not(target_os = "macos"))]
+fn thin_snapshots(_volume: &str, _bytes: u64, _urgency: u8) -> Result<(), String> {
+  Err("Local snapshots can only be thinned on macOS".to_string())
+}
+
+/// Purgeable space, free space and local Time Machine snapshots of the
+/// volume mounted at `volume`. macOS only.
+#[tauri::command]
+pub async fn get_purgeable_space(volume: String) -> Result<PurgeableSpace, String> {
+  blocking(move || purgeable_space(&volume)).await
+}
+
+/// Thins the local snapshots of `volume` to free `bytes`, as macOS does when
+/// space runs low, and returns what's left. A higher `urgency`, up to 4,
+/// lets more go.
+#[tauri::command]
+pub async fn thin_local_snapshots(
+  volume: String,
+  bytes: u64,
+  urgency: u8,
+) -> Result<PurgeableSpace, String> {
+  if !(1..=4).contains(&urgency) {
+    return Err("Urgency goes from 1 to 4".to_string());
+  }
+  // tmutil can take a while to let go of the space.
+  blocking(move || {
+    thin_snapshots(&volume, bytes, urgency)?;
+    purgeable_space(&volume)
+  })
+  .await
+}
@@ -0,0 +1,254 @@
+//! Reading the tree of a finished scan a piece at a time. The whole tree stays
+//! with the kept results of the scan, and scan_done only carries its root;
+//! the UI asks for the children of the folders it shows, a page at a time, so
+//! a scan of millions of files never goes through the IPC bridge at once.
+//!
+//! Nodes only carry their names. A kept tree numbers its nodes, and they're
+//! asked for by that id; `get_node_path` puts the path of one together when
+//! it's needed. An index of where each id sits keeps finding one down to the
+//! depth of the tree, whatever its size.
+
+use super::{NodeKind, ScanManager, ScanNode, ScanResults};
+use serde::{Deserialize, Serialize};
+use std::{cmp::Ordering, collections::HashMap, path::PathBuf};
+use tauri::State;
+
+/// Children per page unless `get_children` is given a `limit`.
+const DEFAULT_PAGE: u32 = 200;
+
+/// A node without the nodes below it.
+#[derive(Clone, Serialize)]
+pub struct NodeSummary {
+  #[serde(flatten)]
+  pub node: ScanNode,
+  /// Entries directly in a folder whose children were left out, to be
+  /// fetched with `get_children`. Unset for a folder past `max_depth`, which
+  /// has only its totals, and for a node sent with its children.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub child_count: Option<u64>,
+}
+
+impl NodeSummary {
+  /// `node` with its children left out.
+  pub(super) fn of(node: &ScanNode) -> Self {
+    Self {
+      node: ScanNode {
+        id: node.id,
+        name: node.name.clone(),
+        kind: node.kind.clone(),
+        size: node.size,
+        allocated: node.allocated,
+        children: None,
+        item_count: node.item_count,
+        file_count: node.file_count,
+        dir_count: node.dir_count,
+        mtime: node.mtime,
+        newest_mtime: node.newest_mtime,
+        oldest_mtime: node.oldest_mtime,
+        owner: node.owner.clone(),
+        category: node.category,
+        categories: node.categories,
+        hidden_bytes: node.hidden_bytes,
+        online_only: node.online_only,
+        compressed: node.compressed,
+        sparse: node.sparse,
+        link_kind: node.link_kind,
+        attributes: node.attributes,
+      },
+      child_count: node.children.as_ref().map(|children| children.len() as u64),
+    }
+  }
+
+  /// `node` as it is, children and all.
+  pub(super) fn whole(node: ScanNode) -> Self {
+    Self {
+      node,
+      child_count: None,
+    }
+  }
+}
+
+/// Where each node of a kept tree is, by id: its parent, and its place among
+/// the children of that parent. The root has no entry.
+#[derive(Clone, Default)]
+pub(super) struct NodeIndex {
+  /// The id the next node numbered gets.
+  next_id: u64,
+  places: HashMap<u64, (u64, usize)>,
+}
+
+impl NodeIndex {
+  /// Numbers `node` and the nodes below it, parents first.
+  pub(super) fn number(&mut self, node: &mut ScanNode) {
+    node.id = self.next_id;
+    self.next_id += 1;
+    self.number_below(node);
+  }
+
+  /// Numbers the nodes below `node`, which keeps its id.
+  pub(super) fn number_below(&mut self, node: &mut ScanNode) {
+    for (place, child) in node.children.iter_mut().flatten().enumerate() {
+      self.places.insert(self.next_id, (node.id, place));
+      self.number(child);
+    }
+  }
+
+  /// Drops the nodes below `node`, once it's been taken out of the tree.
+  pub(super) fn forget_below(&mut self, node: &ScanNode) {
+    for child in node.children.iter().flatten() {
+      self.places.remove(&child.id);
+      self.forget_below(child);
+    }
+  }
+}
+
+/// The node `id` of the kept `results`, with its path. An aggregate node
+/// goes by the path of the folder its entries are in.
+pub(super) fn node_of(results: &ScanResults, id: u64) -> Result<(&ScanNode, PathBuf), String> {
+  let gone = || "That node isn't in this scan any more".to_string();
+  let mut places = Vec::new();
+  let mut at = id;
+  while at != results.tree.id {
+    let &(parent, place) = results.index.places.get(&at).ok_or_else(gone)?;
+    places.push(place);
+    at = parent;
+  }
+  let mut node = &results.tree;
+  let mut path = PathBuf::from(&results.root);
+  for place in places.into_iter().rev() {
+    node = node.children.as_ref().and_then(|children| children.get(place)).ok_or_else(gone)?;
+    if !matches!(node.kind, NodeKind::Other) {
+      path.push(&node.name);
+    }
+  }
+  match node.id == id {
+    true => Ok((node, path)),
+    false => Err(gone()),
+  }
+}
+
+/// The order of the children `get_children` returns.
+#[derive(Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChildSort {
+  /// Largest first.
+  #[default]
+  Size,
+  /// Most space on disk first.
+  Allocated,
+  Name,
+  /// Folders, then files, then aggregate nodes; by name within each.
+  Kind,
+  /// Most recently modified first.
+  Modified,
+}
+
+impl ChildSort {
+  fn compare(self, a: &ScanNode, b: &ScanNode) -> Ordering {
+    let by_name = || a.name.to_lowercase().cmp(&b.name.to_lowercase());
+    let rank = |node: &ScanNode| match node.kind {
+      NodeKind::Dir => 0,
+      NodeKind::File => 1,
+      NodeKind::Other => 2,
+    };
+    let modified = |node: &ScanNode| node.mtime.or(node.newest_mtime);
+    match self {
+      ChildSort::Size => b.size.cmp(&a.size).then_with(by_name),
+      ChildSort::Allocated => b.allocated.cmp(&a.allocated).then_with(by_name),
+      ChildSort::Name => by_name(),
+      ChildSort::Kind => rank(a).cmp(&rank(b)).then_with(by_name),
+      ChildSort::Modified => modified(b).cmp(&modified(a)).then_with(by_name),
+    }
+  }
+}
+
+/// One page of the children of a folder.
+#[derive(Serialize)]
+pub struct ChildPage {
+  /// Children of the folder in all.
+  pub total: u64,
+  pub offset: u64,
+  pub children: Vec<NodeSummary>,
+}
+
+impl ChildPage {
+  /// `limit` of `children` (200 unless said) from `offset` on, in the order
+  /// of `sort_by` (largest first unless said), or the other way round with
+  /// `reverse`.
+  pub(super) fn of(
+    children: &[ScanNode],
+    offset: Option<u64>,
+    limit: Option<u32>,
+    sort_by: Option<ChildSort>,
+    reverse: Option<bool>,
+  ) -> Self {
+    let sort_by = sort_by.unwrap_or_default();
+    let mut sorted: Vec<&ScanNode> = children.iter().collect();
+    if reverse.unwrap_or(false) {
+      sorted.sort_by(|a, b| sort_by.compare(b, a));
+    } else {
+      sorted.sort_by(|a, b| sort_by.compare(a, b));
+    }
+    let offset = offset.unwrap_or(0);
+    ChildPage {
+      total: children.len() as u64,
+      offset,
+      children: sorted
+        .into_iter()
+        .skip(offset as usize)
+        .take(limit.unwrap_or(DEFAULT_PAGE) as usize)
+        .map(NodeSummary::of)
+        .collect(),
+    }
+  }
+}
+
+/// The node `node_id` of the kept scan `scan_id`, without its children.
+#[tauri::command]
+pub async fn get_node(
+  state: State<'_, ScanManager>,
+  scan_id: String,
+  node_id: u64,
+) -> Result<NodeSummary, String> {
+  let results = state.results(&scan_id)?;
+  let (node, _) = node_of(&results, node_id)?;
+  Ok(NodeSummary::of(node))
+}
+
+/// Where the node `node_id` of the kept scan `scan_id` is. For an aggregate
+/// node, that's the folder its entries are in.
+#[tauri::command]
+pub async fn get_node_path(
+  state: State<'_, ScanManager>,
+  scan_id: String,
+  node_id: u64,
+) -> Result<String, String> {
+  let results = state.results(&scan_id)?;
+  let (_, path) = node_of(&results, node_id)?;
+  let path = path.to_string_lossy().to_string();
+  // Remote paths joined on Windows come out with backslashes.
+  match results.local {
+    false if cfg!(windows) => Ok(path.replace('\\', "/")),
+    _ => Ok(path),
+  }
+}
+
+/// A page of the children of the folder `node_id` of the kept scan
+/// `scan_id`, as `ChildPage::of` cuts it.
+#[tauri::command]
+pub async fn get_children(
+  state: State<'_, ScanManager>,
+  scan_id: String,
+  node_id: u64,
+  offset: Option<u64>,
+  limit: Option<u32>,
+  sort_by: Option<ChildSort>,
+  reverse: Option<bool>,
+) -> Result<ChildPage, String> {
+  let results = state.results(&scan_id)?;
+  let (node, path) = node_of(&results, node_id)?;
+  let children = node.children.as_ref().ok_or_else(|| {
+    format!("{} wasn't walked this deep; expand it first", path.display())
+  })?;
+  Ok(ChildPage::of(children, offset, limit, sort_by, reverse))
+}
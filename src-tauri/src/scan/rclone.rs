@@ -0,0 +1,191 @@
+//! Any remote rclone is set up for (Google Drive, OneDrive, Dropbox, B2,
+//! Azure Blob, SFTP and dozens more), listed by running `rclone lsjson
+//! --recursive` and reading its entries as they're printed, one per line.
+//! rclone's own configuration and sign-ins are used; nothing is kept here.
+
+use super::{
+  blocking, empty_dir_node, maybe_emit_progress,
+  object_tree::{self, parse_time, Object, ObjectTree, StorageClassTotal},
+  spawn_scan_from, ScanContext, ScanErrorKind, ScanManager, ScanNode, ScanOptions, ScanSource,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+  io::{BufRead, BufReader, Read},
+  path::{Path, PathBuf},
+  process::{Command, Stdio},
+  sync::atomic::Ordering,
+};
+use tauri::{AppHandle, State};
+
+#[derive(Clone, Serialize)]
+pub struct RcloneRemote {
+  pub name: String,
+  /// The backend, like `drive`, `onedrive` or `s3`.
+  pub kind: String,
+}
+
+/// One entry of `lsjson`.
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Item {
+  path: String,
+  /// -1 where the backend doesn't know, as for Google Docs.
+  size: i64,
+  mod_time: Option<String>,
+  is_dir: bool,
+  /// The storage class, on backends that have them.
+  tier: Option<String>,
+}
+
+/// A remote folder to list, as `remote:path`.
+pub(super) struct Remote {
+  spec: String,
+}
+
+fn rclone(args: &[&str]) -> Command {
+  let mut command = Command::new("rclone");
+  command.args(args);
+  #[cfg(windows)]
+  {
+    use std::os::windows::process::CommandExt;
+    const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+    command.creation_flags(CREATE_NO_WINDOW);
+  }
+  command
+}
+
+fn not_installed(e: std::io::Error) -> String {
+  match e.kind() {
+    std::io::ErrorKind::NotFound => "rclone isn't installed, or isn't on the PATH".to_string(),
+    _ => format!("rclone: {}", e),
+  }
+}
+
+/// Lists `remote` and builds its tree under `root`, the `remote:path` it's
+/// shown as. What rclone complains about is recorded like local errors.
+pub(super) fn scan(
+  ctx: &ScanContext,
+  remote: &Remote,
+  root: &Path,
+) -> (ScanNode, Vec<StorageClassTotal>) {
+  // `--fast-list` lists bucket-based remotes in far fewer requests.
+  let args = ["lsjson", "--recursive", "--fast-list", "--no-mimetype", &remote.spec];
+  let mut command = rclone(&args);
+  command.stdin(Stdio::null()).stdout(Stdio::piped()).stderr(Stdio::piped());
+  let mut child = match command.spawn() {
+    Ok(child) => child,
+    Err(e) => {
+      ctx.record_error(root, ScanErrorKind::Other, None, &not_installed(e));
+      return (empty_dir_node(root), Vec::new());
+    }
+  };
+  // Read apart so neither pipe fills up while the other is waited on.
+  let stderr = child.stderr.take().map(|mut stderr| {
+    std::thread::spawn(move || {
+      let mut text = String::new();
+      let _ = stderr.read_to_string(&mut text);
+      text
+    })
+  });
+  let root_text = root.to_string_lossy();
+  let mut tree = ObjectTree::default();
+  let mut listed = false;
+  if let Some(stdout) = child.stdout.take() {
+    for line in BufReader::new(stdout).lines() {
+      let Ok(line) = line else {
+        break;
+      };
+      if ctx.cancelled() {
+        let _ = child.kill();
+        break;
+      }
+      // The listing is one JSON array, printed an element per line.
+      let line = line.trim().trim_end_matches(',');
+      let Ok(item) = serde_json::from_str::<Item>(line) else {
+        continue;
+      };
+      listed = true;
+      if item.is_dir {
+        tree.folder(&item.path);
+        continue;
+      }
+      let size = item.size.max(0) as u64;
+      ctx.control.scanned_entries.fetch_add(1, Ordering::Relaxed);
+      ctx.control.scanned_bytes.fetch_add(size, Ordering::Relaxed);
+      maybe_emit_progress(ctx, Path::new(&format!("{}/{}", root_text, item.path)));
+      let object = Object {
+        key: String::new(),
+        size,
+        mtime: item.mod_time.as_deref().and_then(parse_time),
+        class: item.tier.filter(|tier| !tier.is_empty()),
+      };
+      tree.insert(&item.path, object);
+    }
+  }
+  let status = child.wait();
+  let errors = stderr.and_then(|thread| thread.join().ok()).unwrap_or_default();
+  for line in errors.lines().filter(|line| line.contains("ERROR")) {
+    // `2024/01/31 12:00:00 ERROR : folder/file: message`
+    let message = line.split_once("ERROR : ").map_or(line, |(_, message)| message);
+    let kind = match message.contains("permission") || message.contains("403") {
+      true => ScanErrorKind::PermissionDenied,
+      false => ScanErrorKind::Other,
+    };
+    let path = message.split_once(": ").map_or("", |(path, _)| path);
+    let at = format!("{}/{}", root_text, path);
+    ctx.record_error(Path::new(at.trim_end_matches('/')), kind, None, &message);
+  }
+  let failed = status.is_ok_and(|status| !status.success());
+  if !listed && failed && !ctx.cancelled() && !errors.contains("ERROR") {
+    let message = format!("rclone: {}", errors.trim());
+    ctx.record_error(root, ScanErrorKind::Other, None, &message);
+  }
+  object_tree::build(ctx, tree, &root_text)
+}
+
+/// The remotes in rclone's configuration.
+#[tauri::command]
+pub async fn list_rclone_remotes() -> Result<Vec<RcloneRemote>, String> {
+  blocking(|| {
+    let output = rclone(&["listremotes", "--long"]).output().map_err(not_installed)?;
+    if !output.status.success() {
+      return Err(format!("rclone: {}", String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    // `gdrive:    drive`
+    let remotes = String::from_utf8_lossy(&output.stdout)
+      .lines()
+      .filter_map(|line| {
+        let (name, kind) = line.split_once(':')?;
+        Some(RcloneRemote {
+          name: name.trim().to_string(),
+          kind: kind.trim().to_string(),
+        })
+      })
+      .collect();
+    Ok(remotes)
+  })
+  .await
+}
+
+/// Scans `path` on a configured rclone `remote`. Returns the scan id;
+/// progress and the tree arrive as for a local scan, under `remote:path`,
+/// with storage classes where the backend has them.
+#[tauri::command]
+pub async fn start_rclone_scan(
+  app: AppHandle,
+  state: State<'_, ScanManager>,
+  remote: String,
+  path: Option<String>,
+  options: Option<ScanOptions>,
+) -> Result<String, String> {
+  let name = remote.trim().trim_end_matches(':');
+  if name.is_empty() {
+    return Err("Pick an rclone remote".to_string());
+  }
+  let path = path.unwrap_or_default();
+  let spec = format!("{}:{}", name, path.trim().trim_end_matches('/'));
+  let mut options = options.unwrap_or_default();
+  options.same_filesystem = false;
+  let root = PathBuf::from(&spec);
+  spawn_scan_from(&app, &state, root, options, ScanSource::Rclone(Remote { spec }))
+}
@@ -0,0 +1,67 @@
+//! Why a scanned volume doesn't add up to the used space the OS reports for
+//! it: after a scan of a volume root, the difference, with the parts of it
+//! that can be put a name to. What's left over sits in folders the scan
+//! couldn't read, entries it left out, or places nothing can see into.
+
+use super::ScanNode;
+use serde::Serialize;
+use std::path::Path;
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+// Some are only found on Windows.
+#[allow(dead_code)]
+pub enum UnaccountedKind {
+  /// Hidden entries the scan only counted (`track_hidden_bytes`).
+  Hidden,
+  /// NTFS metafiles (`$MFT`, `$LogFile`, the change journal) the tree
+  /// doesn't have.
+  FilesystemMetadata,
+  /// Volume Shadow Copy storage on the volume.
+  ShadowCopies,
+  /// What macOS can purge on demand, local snapshots among it.
+  Purgeable,
+}
+
+#[derive(Clone, Serialize)]
+pub struct UnaccountedPart {
+  pub kind: UnaccountedKind,
+  pub bytes: u64,
+}
+
+/// Bytes the volume mounted at `root_path` uses that the scanned `root`
+/// doesn't hold, and what is known of them; `None` unless `root_path` is the
+/// root of a volume. The parts may add up to more than the whole where
+/// they overlap with what the tree already has.
+pub(super) fn reconcile(
+  root_path: &Path,
+  root: &ScanNode,
+) -> Option<(u64, Vec<UnaccountedPart>)> {
+  if !crate::volume::is_volume_root(root_path) {
+    return None;
+  }
+  let used = crate::volume::usage(root_path)?.used_bytes();
+  let unaccounted = used.saturating_sub(root.allocated);
+  let mut parts = vec![UnaccountedPart {
+    kind: UnaccountedKind::Hidden,
+    bytes: root.hidden_bytes.unwrap_or(0),
+  }];
+  #[cfg(windows)]
+  {
+    let mount_point = root_path.to_string_lossy();
+    parts.push(UnaccountedPart {
+      kind: UnaccountedKind::FilesystemMetadata,
+      bytes: super::windows::metafile_bytes(root_path, root),
+    });
+    parts.push(UnaccountedPart {
+      kind: UnaccountedKind::ShadowCopies,
+      bytes: super::shadow_copies::bytes_on(&mount_point).unwrap_or(0),
+    });
+  }
+  parts.push(UnaccountedPart {
+    kind: UnaccountedKind::Purgeable,
+    bytes: super::purgeable::purgeable_on(root_path).unwrap_or(0),
+  });
+  parts.retain(|part| part.bytes > 0);
+  Some((unaccounted, parts))
+}
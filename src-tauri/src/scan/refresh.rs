@@ -0,0 +1,284 @@
+//! Bringing a finished scan up to date without walking all of it again. A
+//! folder that changed is walked afresh with the options of the scan, its new
+//! node takes the place of the old one, and the folders above it are totalled
+//! again from their children. What the scan keeps besides the tree (largest
+//! files, extensions, duplicates...) stays as the full scan found it.
+//!
+//! On Windows `refresh_scan` learns which folders changed from the NTFS
+//! change journal, so a daily refresh only walks what was touched that day.
+//! A watched scan (see `watch`) learns it from the OS as it happens, and
+//! `rescan_node` walks again the one folder it's asked to.
+
+use super::{
+  blocking, category_totals, disk_image,
+  query::{self, NodeSummary},
+  scan_tree, total_counts, widen_mtimes, NodeKind, ScanManager, ScanNode, ScanOptions, ScanResults,
+};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Emitter, Manager};
+
+#[derive(Serialize)]
+pub struct ScanRefresh {
+  pub scan_id: String,
+  /// The root as it is now; `get_children` has what's below it.
+  pub root: NodeSummary,
+  /// The folders that were walked again, outermost only.
+  pub rescanned: Vec<String>,
+}
+
+/// Emitted as `node_changed` when a folder of a kept scan was walked again.
+#[derive(Clone, Serialize)]
+pub struct NodeChangedEvent {
+  pub scan_id: String,
+  /// The folder as it is now; `get_children` has what's in it.
+  pub node: NodeSummary,
+  /// The folders above it, from the root down, with their new totals.
+  pub ancestors: Vec<NodeTotals>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct NodeTotals {
+  pub id: u64,
+  pub size: u64,
+  pub allocated: u64,
+  pub file_count: u64,
+  pub dir_count: u64,
+}
+
+impl NodeTotals {
+  fn of(node: &ScanNode) -> Self {
+    Self {
+      id: node.id,
+      size: node.size,
+      allocated: node.allocated,
+      file_count: node.file_count.unwrap_or(0),
+      dir_count: node.dir_count.unwrap_or(0),
+    }
+  }
+}
+
+/// The names from `at` down to `path`, or `None` when it isn't below it.
+fn names_below(at: &Path, path: &Path) -> Option<Vec<String>> {
+  let rest = path.strip_prefix(at).ok()?;
+  Some(rest.components().map(|name| name.as_os_str().to_string_lossy().to_string()).collect())
+}
+
+/// The `node_changed` event for the folder at `path` of `results`.
+fn changed_event(scan_id: &str, results: &ScanResults, path: &Path) -> Option<NodeChangedEvent> {
+  let mut ancestors = Vec::new();
+  let mut node = &results.tree;
+  for name in names_below(Path::new(&results.root), path)? {
+    ancestors.push(NodeTotals::of(node));
+    node = node.child(&name)?;
+  }
+  Some(NodeChangedEvent {
+    scan_id: scan_id.to_string(),
+    node: NodeSummary::of(node),
+    ancestors,
+  })
+}
+
+/// The path of the deepest folder of `results` that holds `path`: its own
+/// node, or the folder it was summed or merged into. `None` when it's outside
+/// the tree.
+fn holder(results: &ScanResults, path: &Path) -> Option<PathBuf> {
+  let mut held = PathBuf::from(&results.root);
+  let mut node = &results.tree;
+  for name in names_below(&held, path)? {
+    match node.child(&name) {
+      Some(child) if matches!(child.kind, NodeKind::Dir) => node = child,
+      _ => break,
+    }
+    held.push(name);
+  }
+  Some(held)
+}
+
+/// Walks the folder at `path` of `results` as the scan did, down to the
+/// same depth.
+fn rewalk(results: &ScanResults, path: &Path) -> Result<ScanNode, String> {
+  let depth = names_below(Path::new(&results.root), path)
+    .ok_or_else(|| format!("{} isn't in this scan", path.display()))?
+    .len() as u32;
+  let options = &results.options;
+  let options = ScanOptions {
+    max_depth: options.max_depth.saturating_sub(depth),
+    find_duplicates: false,
+    find_similar_images: false,
+    ..options.clone()
+  };
+  disk_image::walk(path, &options, |ctx| scan_tree(ctx, path))
+}
+
+/// Puts `node` in place of the node at `path` of `results`, keeping its id,
+/// numbers what's below it, and totals the folders above it again. Returns
+/// whether there was such a node.
+pub(super) fn graft(results: &mut ScanResults, path: &Path, mut node: ScanNode) -> bool {
+  let root = Path::new(&results.root);
+  let (Some(names), Some(old)) = (names_below(root, path), results.tree.find(root, path)) else {
+    return false;
+  };
+  node.id = old.id;
+  results.index.forget_below(old);
+  results.index.number_below(&mut node);
+  graft_below(&mut results.tree, &names, node)
+}
+
+/// Puts `node` in place of the node `names` lead to from `tree`.
+fn graft_below(tree: &mut ScanNode, names: &[String], node: ScanNode) -> bool {
+  let Some((name, below)) = names.split_first() else {
+    *tree = node;
+    return true;
+  };
+  let child = tree
+    .children
+    .iter_mut()
+    .flatten()
+    .filter(|child| matches!(child.kind, NodeKind::Dir))
+    .find(|child| child.name == *name);
+  let Some(child) = child else {
+    return false;
+  };
+  let hidden_before = child.hidden_bytes.unwrap_or(0);
+  if !graft_below(child, below, node) {
+    return false;
+  }
+  let hidden_after = child.hidden_bytes.unwrap_or(0);
+  let Some(children) = &tree.children else {
+    return true;
+  };
+  let (files, dirs) = total_counts(children);
+  let mtimes = children.iter().fold(None, |range, c| widen_mtimes(range, c.mtimes()));
+  let hidden = (tree.hidden_bytes.unwrap_or(0) + hidden_after).saturating_sub(hidden_before);
+  tree.size = children.iter().map(|c| c.size).sum();
+  tree.allocated = children.iter().map(|c| c.allocated).sum();
+  tree.file_count = Some(files);
+  tree.dir_count = Some(dirs);
+  tree.categories = Some(category_totals(children));
+  tree.hidden_bytes = (hidden > 0).then_some(hidden);
+  tree.set_mtimes(mtimes);
+  true
+}
+
+/// Walks again the folders of the tree of `results` that hold the paths in
+/// `changed`, leaving out those inside another one walked. Returns each
+/// folder with its new node.
+fn rewalk_changed(
+  results: &ScanResults,
+  changed: &[PathBuf],
+) -> Result<Vec<(PathBuf, ScanNode)>, String> {
+  let mut folders: Vec<PathBuf> = changed.iter().filter_map(|path| holder(results, path)).collect();
+  // A folder sorts right before everything in it.
+  folders.sort();
+  folders.dedup();
+  let mut outermost: Vec<PathBuf> = Vec::new();
+  for folder in folders {
+    if !outermost.iter().any(|outer| folder.starts_with(outer)) {
+      outermost.push(folder);
+    }
+  }
+  let mut walked = Vec::new();
+  for folder in outermost {
+    let node = rewalk(results, &folder)?;
+    walked.push((folder, node));
+  }
+  Ok(walked)
+}
+
+/// Puts the folders `rewalk_changed` walked in the tree of `results`, and
+/// returns their paths.
+fn graft_walked(results: &mut ScanResults, walked: Vec<(PathBuf, ScanNode)>) -> Vec<PathBuf> {
+  let mut folders = Vec::new();
+  for (folder, node) in walked {
+    graft(results, &folder, node);
+    folders.push(folder);
+  }
+  folders
+}
+
+/// Walks again the folders of the kept scan `scan_id` that hold the paths
+/// in `changed`, puts them in its tree, and returns the `node_changed`
+/// event of each folder walked.
+pub(super) fn refresh_folders(
+  state: &ScanManager,
+  scan_id: &str,
+  changed: &[PathBuf],
+) -> Result<Vec<NodeChangedEvent>, String> {
+  let updating = state.updating(scan_id)?;
+  let _updating = updating.lock();
+  let results = state.results(scan_id)?;
+  if !results.local {
+    return Err("Only folders of this machine can be walked again".to_string());
+  }
+  let walked = rewalk_changed(&results, changed)?;
+  drop(results);
+  state.update_results(scan_id, |results| {
+    let folders = graft_walked(results, walked);
+    folders.iter().filter_map(|folder| changed_event(scan_id, results, folder)).collect()
+  })
+}
+
+/// Catches the kept scan `scan_id` up with the change journal, and returns
+/// the folders walked. The caller holds its update lock.
+#[cfg(windows)]
+fn catch_up(state: &ScanManager, scan_id: &str) -> Result<Vec<PathBuf>, String> {
+  let results = state.results(scan_id)?;
+  let since = results.journal.ok_or(
+    "The change journal couldn't be read when this was scanned; that takes a local NTFS \
+     volume and administrator rights",
+  )?;
+  let (changed, now) = super::usn::changed_dirs(Path::new(&results.root), since)?;
+  let walked = rewalk_changed(&results, &changed)?;
+  drop(results);
+  state.update_results(scan_id, |results| {
+    results.journal = Some(now);
+    graft_walked(results, walked)
+  })
+}
+
+#[cfg(not(windows))]
+fn catch_up(_state: &ScanManager, _scan_id: &str) -> Result<Vec<PathBuf>, String> {
+  Err("Refreshing from the change journal takes NTFS, on Windows".to_string())
+}
+
+/// Catches the kept scan `scan_id` up with what changed on disk since it
+/// ran, walking only the folders the change journal says were touched.
+/// Windows only, for scans of a local NTFS volume made with administrator
+/// rights.
+#[tauri::command]
+pub async fn refresh_scan(app: AppHandle, scan_id: String) -> Result<ScanRefresh, String> {
+  blocking(move || {
+    let state = app.state::<ScanManager>();
+    let updating = state.updating(&scan_id)?;
+    let _updating = updating.lock();
+    let rescanned = catch_up(&state, &scan_id)?;
+    let root = NodeSummary::of(&state.results(&scan_id)?.tree);
+    Ok(ScanRefresh {
+      scan_id,
+      root,
+      rescanned: rescanned.iter().map(|folder| folder.to_string_lossy().to_string()).collect(),
+    })
+  })
+  .await
+}
+
+/// Walks the folder `node_id` of the kept scan `scan_id` again, for when
+/// files were deleted or added in it, and emits `node_changed` for it. A
+/// file or an aggregate node is walked with the folder it's in.
+#[tauri::command]
+pub async fn rescan_node(
+  app: AppHandle,
+  scan_id: String,
+  node_id: u64,
+) -> Result<NodeChangedEvent, String> {
+  blocking(move || {
+    let state = app.state::<ScanManager>();
+    let (_, path) = query::node_of(&*state.results(&scan_id)?, node_id)?;
+    let changed = refresh_folders(&state, &scan_id, &[path])?;
+    let event = changed.into_iter().next().ok_or("That node isn't in this scan any more")?;
+    let _ = app.emit("node_changed", event.clone());
+    Ok(event)
+  })
+  .await
+}
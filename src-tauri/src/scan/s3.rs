@@ -0,0 +1,442 @@
+//! Object storage: an S3 bucket, or a folder of one, listed with
+//! `ListObjectsV2` and shown as a tree whose folders are the `/`-separated
+//! parts of the keys. Requests are signed (Signature Version 4) with the keys
+//! given, the AWS environment variables or `~/.aws/credentials`, and
+//! `endpoint` points them at an S3-compatible service (MinIO, R2, Wasabi…).
+
+use super::{
+  credentials,
+  http::{self, percent_encode, xml_text},
+  iso::civil_from_days,
+  maybe_emit_progress,
+  object_tree::{self, parse_time, Object, ObjectTree, StorageClassTotal},
+  spawn_scan_from, ScanContext, ScanErrorKind, ScanManager, ScanNode, ScanOptions, ScanSource,
+};
+use hmac::{Hmac, Mac};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::{
+  path::{Path, PathBuf},
+  sync::atomic::Ordering,
+  time::{SystemTime, UNIX_EPOCH},
+};
+use tauri::{AppHandle, State};
+
+/// Where AWS buckets are looked for when nothing says otherwise.
+const DEFAULT_REGION: &str = "us-east-1";
+
+#[derive(Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct S3Target {
+  /// A bucket name, or `s3://bucket/folder`.
+  pub bucket: String,
+  /// A folder in the bucket to scan instead of all of it.
+  pub prefix: String,
+  /// From the AWS configuration when not given; AWS corrects a wrong one.
+  pub region: Option<String>,
+  /// The address of an S3-compatible service, like `https://minio.lan:9000`.
+  pub endpoint: Option<String>,
+  /// `None` uses `AWS_ACCESS_KEY_ID` or the profile's keys; without any,
+  /// public buckets can still be listed.
+  pub access_key_id: Option<String>,
+  /// `None` uses the secret saved for `access_key_id`.
+  pub secret_access_key: Option<String>,
+  /// Keep `secret_access_key` in the OS keyring for next time.
+  pub remember: bool,
+  /// The profile of `~/.aws/credentials` and `~/.aws/config`; `AWS_PROFILE`
+  /// or `default` when `None`.
+  pub profile: Option<String>,
+}
+
+struct Keys {
+  id: String,
+  secret: String,
+  /// For temporary credentials.
+  token: Option<String>,
+}
+
+/// A bucket to list, with its keys and region looked up.
+pub(super) struct Bucket {
+  name: String,
+  /// Keys start with this; empty, or ending with `/`.
+  prefix: String,
+  region: String,
+  /// Scheme and `host[:port]` of an S3-compatible service.
+  endpoint: Option<(String, String)>,
+  /// `None` sends the requests unsigned.
+  keys: Option<Keys>,
+}
+
+impl Bucket {
+  /// The scheme, host and path to ask for the bucket's keys in `region`.
+  fn location(&self, region: &str) -> (&str, String, String) {
+    match &self.endpoint {
+      Some((scheme, host)) => (scheme, host.clone(), format!("/{}", self.name)),
+      // Dotted names don't fit the wildcard certificate of `*.s3.amazonaws.com`.
+      None if self.name.contains('.') => {
+        ("https", format!("s3.{}.amazonaws.com", region), format!("/{}", self.name))
+      }
+      None => ("https", format!("{}.s3.{}.amazonaws.com", self.name, region), "/".to_string()),
+    }
+  }
+}
+
+/// A reply to a listing request.
+struct Reply {
+  status: u16,
+  body: String,
+  /// Sent by AWS along with a refusal when the bucket is in another region.
+  bucket_region: Option<String>,
+}
+
+/// Lists `bucket` and builds its tree under `root`, the `s3://bucket/folder`
+/// it's shown as. Refusals and failures are recorded like local errors, with
+/// what was listed before them kept.
+pub(super) fn scan(
+  ctx: &ScanContext,
+  bucket: &Bucket,
+  root: &Path,
+) -> (ScanNode, Vec<StorageClassTotal>) {
+  let agent = http::agent();
+  let root_text = root.to_string_lossy();
+  let mut region = bucket.region.clone();
+  let mut top = ObjectTree::default();
+  let mut token = None;
+  while !ctx.cancelled() {
+    let reply = match list_page(&agent, bucket, &region, token.as_deref()) {
+      Ok(reply) => reply,
+      Err(e) => {
+        ctx.record_error(root, ScanErrorKind::Other, None, &format!("s3: {}", e));
+        break;
+      }
+    };
+    if let Some(actual) = reply.bucket_region.filter(|r| reply.status != 200 && *r != region) {
+      region = actual;
+      continue;
+    }
+    if reply.status != 200 {
+      let (kind, message) = refusal(reply.status, &reply.body);
+      ctx.record_error(root, kind, None, &format!("s3: {}", message));
+      break;
+    }
+    let (objects, next) = match read_page(&reply.body) {
+      Ok(page) => page,
+      Err(e) => {
+        ctx.record_error(root, ScanErrorKind::Other, None, &format!("s3: {}", e));
+        break;
+      }
+    };
+    for object in objects {
+      let Some(relative) = object.key.strip_prefix(&bucket.prefix) else {
+        continue;
+      };
+      ctx.control.scanned_entries.fetch_add(1, Ordering::Relaxed);
+      ctx.control.scanned_bytes.fetch_add(object.size, Ordering::Relaxed);
+      maybe_emit_progress(ctx, Path::new(&format!("{}/{}", root_text, relative)));
+      let relative = relative.to_string();
+      top.insert(&relative, object);
+    }
+    token = next;
+    if token.is_none() {
+      break;
+    }
+  }
+  object_tree::build(ctx, top, &root_text)
+}
+
+/// Asks for the page of the listing after `token`, the first without one.
+fn list_page(
+  agent: &ureq::Agent,
+  bucket: &Bucket,
+  region: &str,
+  token: Option<&str>,
+) -> Result<Reply, String> {
+  // Keys come back URL-encoded, as some can't be put in XML.
+  let mut query = vec![
+    ("encoding-type", "url"),
+    ("list-type", "2"),
+    ("prefix", bucket.prefix.as_str()),
+  ];
+  if let Some(token) = token {
+    query.push(("continuation-token", token));
+  }
+  query.sort();
+  let query: Vec<String> = query
+    .iter()
+    .map(|(key, value)| format!("{}={}", percent_encode(key), percent_encode(value)))
+    .collect();
+  let query = query.join("&");
+  let (scheme, host, path) = bucket.location(region);
+  let mut request = agent.get(format!("{}://{}{}?{}", scheme, host, path, query));
+  if let Some(keys) = &bucket.keys {
+    for (name, value) in sign(keys, region, &host, &path, &query, SystemTime::now()) {
+      request = request.header(name, value);
+    }
+  }
+  let mut response = request.call().map_err(|e| e.to_string())?;
+  let bucket_region = response
+    .headers()
+    .get("x-amz-bucket-region")
+    .and_then(|value| value.to_str().ok())
+    .map(str::to_string);
+  let body = response.body_mut().read_to_string().map_err(|e| e.to_string())?;
+  Ok(Reply {
+    status: response.status().as_u16(),
+    body,
+    bucket_region,
+  })
+}
+
+/// The headers that sign a GET of `path?query` on `host`. `host` itself is
+/// signed but left out, as the client sets it from the URL.
+fn sign(
+  keys: &Keys,
+  region: &str,
+  host: &str,
+  path: &str,
+  query: &str,
+  now: SystemTime,
+) -> Vec<(&'static str, String)> {
+  let (date, stamp) = amz_date(now.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs() as i64));
+  let payload = hex(&Sha256::digest(b""));
+  let mut headers = vec![
+    ("host", host.to_string()),
+    ("x-amz-content-sha256", payload.clone()),
+    ("x-amz-date", stamp.clone()),
+  ];
+  if let Some(token) = &keys.token {
+    headers.push(("x-amz-security-token", token.clone()));
+  }
+  let signed: Vec<&str> = headers.iter().map(|(name, _)| *name).collect();
+  let signed = signed.join(";");
+  let canonical: String =
+    headers.iter().map(|(name, value)| format!("{}:{}\n", name, value)).collect();
+  let request = format!("GET\n{}\n{}\n{}\n{}\n{}", path, query, canonical, signed, payload);
+  let scope = format!("{}/{}/s3/aws4_request", date, region);
+  let to_sign = format!(
+    "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+    stamp,
+    scope,
+    hex(&Sha256::digest(request.as_bytes()))
+  );
+  let mut key = hmac(format!("AWS4{}", keys.secret).as_bytes(), date.as_bytes());
+  for part in [region, "s3", "aws4_request"] {
+    key = hmac(&key, part.as_bytes());
+  }
+  let authorization = format!(
+    "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+    keys.id,
+    scope,
+    signed,
+    hex(&hmac(&key, to_sign.as_bytes()))
+  );
+  headers.retain(|(name, _)| *name != "host");
+  headers.push(("authorization", authorization));
+  headers
+}
+
+fn hmac(key: &[u8], data: &[u8]) -> Vec<u8> {
+  let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC takes keys of any length");
+  mac.update(data);
+  mac.finalize().into_bytes().to_vec()
+}
+
+fn hex(bytes: &[u8]) -> String {
+  bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// A key as `encoding-type=url` sends it, with `+` for spaces.
+fn url_decode(text: &str) -> String {
+  http::percent_decode(&text.replace('+', " "))
+}
+
+/// `20240131` and `20240131T235959Z`, the date and time `secs` after the
+/// epoch as signatures have them.
+fn amz_date(secs: i64) -> (String, String) {
+  let (year, month, day) = civil_from_days(secs.div_euclid(86_400));
+  let time = secs.rem_euclid(86_400);
+  let date = format!("{:04}{:02}{:02}", year, month, day);
+  let stamp = format!("{}T{:02}{:02}{:02}Z", date, time / 3600, time / 60 % 60, time % 60);
+  (date, stamp)
+}
+
+/// The objects of a `ListBucketResult`, and the token of the next page if
+/// there is one.
+fn read_page(xml: &str) -> Result<(Vec<Object>, Option<String>), String> {
+  let document =
+    roxmltree::Document::parse(xml).map_err(|e| format!("unreadable listing: {}", e))?;
+  let result = document.root_element();
+  let objects = result
+    .children()
+    .filter(|node| node.has_tag_name("Contents"))
+    .map(|node| Object {
+      key: url_decode(&xml_text(node, "Key").unwrap_or_default()),
+      size: xml_text(node, "Size").and_then(|size| size.parse().ok()).unwrap_or(0),
+      mtime: xml_text(node, "LastModified").as_deref().and_then(parse_time),
+      class: Some(xml_text(node, "StorageClass").unwrap_or_else(|| "STANDARD".to_string())),
+    })
+    .collect();
+  let truncated = xml_text(result, "IsTruncated").as_deref() == Some("true");
+  Ok((objects, xml_text(result, "NextContinuationToken").filter(|_| truncated)))
+}
+
+/// What the service says went wrong, from the `<Error>` of a refusal.
+fn refusal(status: u16, body: &str) -> (ScanErrorKind, String) {
+  let document = roxmltree::Document::parse(body).ok();
+  let error = document.as_ref().map(|document| document.root_element());
+  let code = error.and_then(|error| xml_text(error, "Code"));
+  let message = error.and_then(|error| xml_text(error, "Message"));
+  let kind = match (code.as_deref(), status) {
+    (Some("NoSuchBucket"), _) | (None, 404) => ScanErrorKind::NotFound,
+    (Some("AccessDenied" | "InvalidAccessKeyId" | "SignatureDoesNotMatch"), _) | (None, 403) => {
+      ScanErrorKind::PermissionDenied
+    }
+    _ => ScanErrorKind::Other,
+  };
+  let message = match (code, message) {
+    (Some(code), Some(message)) => format!("{}: {}", code, message),
+    (Some(text), None) | (None, Some(text)) => text,
+    (None, None) => format!("HTTP {}", status),
+  };
+  (kind, message)
+}
+
+/// `key` in `[section]` of an AWS configuration file.
+fn ini_value(text: &str, section: &str, key: &str) -> Option<String> {
+  let mut inside = false;
+  for line in text.lines().map(str::trim) {
+    if let Some(name) = line.strip_prefix('[').and_then(|line| line.strip_suffix(']')) {
+      inside = name.trim() == section;
+    } else if let Some((name, value)) = line.split_once('=').filter(|_| inside) {
+      if name.trim() == key {
+        return Some(value.trim().to_string());
+      }
+    }
+  }
+  None
+}
+
+fn env_var(name: &str) -> Option<String> {
+  std::env::var(name).ok().filter(|value| !value.is_empty())
+}
+
+/// A file of `~/.aws`, unless the variable `env` points elsewhere.
+fn aws_file(env: &str, name: &str) -> String {
+  let path = env_var(env)
+    .map(PathBuf::from)
+    .or_else(|| Some(crate::paths::home_dir()?.join(".aws").join(name)));
+  path.and_then(|path| std::fs::read_to_string(path).ok()).unwrap_or_default()
+}
+
+fn account(access_key_id: &str) -> String {
+  format!("s3://{}", access_key_id)
+}
+
+/// The keys to sign with: those given, saved if asked to, or with the secret
+/// saved before; else those of the environment, then of the profile.
+fn keys(target: &S3Target, profile: &str) -> Result<Option<Keys>, String> {
+  let id = target.access_key_id.as_deref().map(str::trim).filter(|id| !id.is_empty());
+  if let Some(id) = id {
+    let secret = match target.secret_access_key.clone().filter(|secret| !secret.is_empty()) {
+      Some(secret) => {
+        if target.remember {
+          credentials::save(&account(id), &secret)?;
+        }
+        secret
+      }
+      None => credentials::load(&account(id))
+        .ok_or_else(|| format!("No secret key saved for {}", id))?,
+    };
+    let id = id.to_string();
+    return Ok(Some(Keys { id, secret, token: None }));
+  }
+  let from_env = env_var("AWS_ACCESS_KEY_ID").zip(env_var("AWS_SECRET_ACCESS_KEY"));
+  if let Some((id, secret)) = from_env {
+    return Ok(Some(Keys { id, secret, token: env_var("AWS_SESSION_TOKEN") }));
+  }
+  let file = aws_file("AWS_SHARED_CREDENTIALS_FILE", "credentials");
+  let value = |key: &str| ini_value(&file, profile, key);
+  let keys = value("aws_access_key_id").zip(value("aws_secret_access_key"));
+  Ok(keys.map(|(id, secret)| Keys { id, secret, token: value("aws_session_token") }))
+}
+
+/// The bucket and the folder to list in it, as a prefix ending with `/`.
+fn bucket_and_prefix(bucket: &str, prefix: &str) -> Result<(String, String), String> {
+  let bucket = bucket.trim();
+  let bucket = bucket.strip_prefix("s3://").unwrap_or(bucket);
+  let (name, folder) = bucket.split_once('/').unwrap_or((bucket, ""));
+  if name.is_empty() {
+    return Err("Enter a bucket name".to_string());
+  }
+  let parts: Vec<&str> =
+    folder.split('/').chain(prefix.trim().split('/')).filter(|part| !part.is_empty()).collect();
+  let prefix = match parts.is_empty() {
+    true => String::new(),
+    false => format!("{}/", parts.join("/")),
+  };
+  Ok((name.to_string(), prefix))
+}
+
+/// The scheme and `host[:port]` of `url`, without a default port or a path.
+fn endpoint(url: &str) -> Result<(String, String), String> {
+  let url = url.trim();
+  let (scheme, rest) = url.split_once("://").unwrap_or(("https", url));
+  let scheme = scheme.to_lowercase();
+  let authority = rest.split('/').next().unwrap_or_default();
+  let default_port = if scheme == "http" { ":80" } else { ":443" };
+  let authority = authority.strip_suffix(default_port).unwrap_or(authority);
+  match scheme.as_str() {
+    "http" | "https" if !authority.is_empty() => Ok((scheme, authority.to_string())),
+    _ => Err(format!("Not an http(s) address: {}", url)),
+  }
+}
+
+/// Scans an S3 bucket, or a folder of it. Returns the scan id; progress and
+/// the tree arrive as for a local scan, under `s3://bucket/folder`, and
+/// `scan_done` adds the bytes per storage class.
+#[tauri::command]
+pub async fn start_s3_scan(
+  app: AppHandle,
+  state: State<'_, ScanManager>,
+  target: S3Target,
+  options: Option<ScanOptions>,
+) -> Result<String, String> {
+  let (name, prefix) = bucket_and_prefix(&target.bucket, &target.prefix)?;
+  let profile = target.profile.clone().filter(|profile| !profile.trim().is_empty());
+  let profile = profile.or_else(|| env_var("AWS_PROFILE")).unwrap_or_else(|| "default".to_string());
+  let keys = keys(&target, profile.trim())?;
+  let url = target.endpoint.as_deref().filter(|url| !url.trim().is_empty());
+  let endpoint = url.map(endpoint).transpose()?;
+  let config_section = match profile.trim() {
+    "default" => "default".to_string(),
+    profile => format!("profile {}", profile),
+  };
+  let region = target
+    .region
+    .map(|region| region.trim().to_string())
+    .filter(|region| !region.is_empty())
+    .or_else(|| env_var("AWS_REGION"))
+    .or_else(|| env_var("AWS_DEFAULT_REGION"))
+    .or_else(|| ini_value(&aws_file("AWS_CONFIG_FILE", "config"), &config_section, "region"))
+    .unwrap_or_else(|| DEFAULT_REGION.to_string());
+  let shown = match prefix.strip_suffix('/') {
+    Some(folder) => format!("s3://{}/{}", name, folder),
+    None => format!("s3://{}", name),
+  };
+  let mut options = options.unwrap_or_default();
+  options.same_filesystem = false;
+  let bucket = Bucket {
+    name,
+    prefix,
+    region,
+    endpoint,
+    keys,
+  };
+  spawn_scan_from(&app, &state, PathBuf::from(shown), options, ScanSource::S3(bucket))
+}
+
+/// Forgets the secret key saved for `access_key_id`.
+#[tauri::command]
+pub async fn forget_s3_secret(access_key_id: String) -> Result<(), String> {
+  credentials::forget(&account(access_key_id.trim()))
+}
@@ -0,0 +1,216 @@
+//! Flatpak and Snap apps on Linux. Both keep what they install away from
+//! the usual places (`/var/lib/flatpak`, `/var/lib/snapd/snaps`, `/snap`)
+//! and hold on to earlier revisions after an update, which is space that
+//! can be taken back.
+
+use super::{blocking, dir_bytes, display_name as file_name, subdirs};
+use serde::Serialize;
+use std::{
+  collections::BTreeMap,
+  path::{Path, PathBuf},
+};
+
+const FLATPAK_SYSTEM: &str = "/var/lib/flatpak";
+const SNAPS: &str = "/var/lib/snapd/snaps";
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AppSource {
+  Flatpak,
+  Snap,
+}
+
+#[derive(Clone, Serialize)]
+pub struct AppRevision {
+  /// A deploy's commit for Flatpak, a revision number for Snap.
+  pub revision: String,
+  pub path: String,
+  pub bytes: u64,
+  /// The revision in use; the others are kept from earlier updates.
+  pub current: bool,
+}
+
+#[derive(Clone, Serialize)]
+pub struct InstalledApp {
+  pub source: AppSource,
+  pub name: String,
+  /// The Flatpak branch, like `stable` or `23.08`.
+  pub branch: Option<String>,
+  /// A Flatpak runtime rather than an app.
+  pub runtime: bool,
+  /// Installed for the current user only.
+  pub user: bool,
+  /// Bytes of every revision kept.
+  pub bytes: u64,
+  pub revisions: Vec<AppRevision>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct OldRevision {
+  pub source: AppSource,
+  pub name: String,
+  pub revision: String,
+  pub path: String,
+  pub bytes: u64,
+}
+
+/// Flatpak apps and runtimes and snaps installed here, the most bytes
+/// first. Flatpak deploys share files with the OSTree repository they come
+/// from, so their sizes are what each would take on its own.
+#[tauri::command]
+pub async fn get_app_storage() -> Result<Vec<InstalledApp>, String> {
+  blocking(|| Ok(installed_apps())).await
+}
+
+fn installed_apps() -> Vec<InstalledApp> {
+  if !cfg!(target_os = "linux") {
+    return Vec::new();
+  }
+  let mut apps = flatpaks();
+  apps.extend(snaps());
+  apps.sort_by(|a, b| b.bytes.cmp(&a.bytes).then(a.name.cmp(&b.name)));
+  apps
+}
+
+/// Revisions kept from earlier updates, and Flatpak deploys left for
+/// removal, the most bytes first. Snap keeps the last few revisions of each
+/// snap (`refresh.retain`) so an update can be reverted.
+#[tauri::command]
+pub async fn list_old_revisions() -> Result<Vec<OldRevision>, String> {
+  blocking(|| {
+    let mut old: Vec<OldRevision> = installed_apps()
+      .into_iter()
+      .flat_map(|app| {
+        let (source, name) = (app.source, app.name);
+        app.revisions.into_iter().filter(|revision| !revision.current).map(move |revision| {
+          OldRevision {
+            source,
+            name: name.clone(),
+            revision: revision.revision,
+            path: revision.path,
+            bytes: revision.bytes,
+          }
+        })
+      })
+      .collect();
+    if cfg!(target_os = "linux") {
+      old.extend(removed_flatpak_deploys());
+    }
+    old.sort_by_key(|revision| std::cmp::Reverse(revision.bytes));
+    Ok(old)
+  })
+  .await
+}
+
+/// The system installation, and the user's if there is one.
+fn flatpak_installations() -> Vec<(PathBuf, bool)> {
+  let mut installations = vec![(PathBuf::from(FLATPAK_SYSTEM), false)];
+  if let Some(home) = crate::paths::home_dir() {
+    installations.push((home.join(".local/share/flatpak"), true));
+  }
+  installations
+}
+
+/// Deploys are laid out as `{app,runtime}/<id>/<arch>/<branch>/<commit>`,
+/// with an `active` link to the one in use.
+fn flatpaks() -> Vec<InstalledApp> {
+  let mut apps = Vec::new();
+  for (root, user) in flatpak_installations() {
+    for (kind, runtime) in [("app", false), ("runtime", true)] {
+      for id in subdirs(&root.join(kind)) {
+        for branch in subdirs(&id).iter().flat_map(|arch| subdirs(arch)) {
+          let active = std::fs::read_link(branch.join("active")).ok();
+          let revisions: Vec<AppRevision> = subdirs(&branch)
+            .into_iter()
+            .map(|deploy| {
+              let commit = file_name(&deploy);
+              AppRevision {
+                current: active.as_deref().is_some_and(|active| active.ends_with(&commit)),
+                bytes: dir_bytes(&deploy),
+                path: deploy.to_string_lossy().to_string(),
+                revision: commit,
+              }
+            })
+            .collect();
+          if revisions.is_empty() {
+            continue;
+          }
+          apps.push(InstalledApp {
+            source: AppSource::Flatpak,
+            name: file_name(&id),
+            branch: Some(file_name(&branch)),
+            runtime,
+            user,
+            bytes: revisions.iter().map(|revision| revision.bytes).sum(),
+            revisions,
+          });
+        }
+      }
+    }
+  }
+  apps
+}
+
+/// Deploys Flatpak has moved aside to delete once nothing runs from them.
+fn removed_flatpak_deploys() -> Vec<OldRevision> {
+  flatpak_installations()
+    .into_iter()
+    .flat_map(|(root, _)| subdirs(&root.join(".removed")))
+    .map(|removed| OldRevision {
+      source: AppSource::Flatpak,
+      name: file_name(&removed),
+      revision: String::new(),
+      bytes: dir_bytes(&removed),
+      path: removed.to_string_lossy().to_string(),
+    })
+    .collect()
+}
+
+/// Each revision of a snap is a `<name>_<revision>.snap` image, with data
+/// under `/var/snap/<name>/<revision>` and `~/snap/<name>/<revision>`.
+fn snaps() -> Vec<InstalledApp> {
+  let Ok(entries) = std::fs::read_dir(SNAPS) else {
+    return Vec::new();
+  };
+  let home = crate::paths::home_dir();
+  let mut by_name: BTreeMap<String, Vec<AppRevision>> = BTreeMap::new();
+  for entry in entries.flatten() {
+    let path = entry.path();
+    let Some(stem) = path.file_name().and_then(|name| name.to_str()?.strip_suffix(".snap")) else {
+      continue;
+    };
+    let Some((name, revision)) = stem.rsplit_once('_') else {
+      continue;
+    };
+    let Ok(md) = entry.metadata() else {
+      continue;
+    };
+    let mut bytes = md.len() + dir_bytes(&Path::new("/var/snap").join(name).join(revision));
+    if let Some(home) = &home {
+      bytes += dir_bytes(&home.join("snap").join(name).join(revision));
+    }
+    let current = std::fs::read_link(Path::new("/snap").join(name).join("current"))
+      .is_ok_and(|current| current.as_os_str() == revision);
+    by_name.entry(name.to_string()).or_default().push(AppRevision {
+      revision: revision.to_string(),
+      path: path.to_string_lossy().to_string(),
+      bytes,
+      current,
+    });
+  }
+  by_name
+    .into_iter()
+    .map(|(name, mut revisions)| {
+      revisions.sort_by(|a, b| b.current.cmp(&a.current).then(b.revision.cmp(&a.revision)));
+      InstalledApp {
+        source: AppSource::Snap,
+        name,
+        branch: None,
+        runtime: false,
+        user: false,
+        bytes: revisions.iter().map(|revision| revision.bytes).sum(),
+        revisions,
+      }
+    })
+    .collect()
+}
@@ -0,0 +1,284 @@
+//! Scans that run by themselves every so often, for watching a file server
+//! or a build machine without anyone at it. The list is kept in
+//! `schedules.json` in the app's config folder; a thread starts each scan
+//! when it's due, as an ordinary scan, so it waits its turn in the queue and
+//! lands in the history like any other. When one finishes it emits
+//! `scheduled_scan_done` and shows a desktop notification.
+//!
+//! A scan that came due while the app was closed runs once it's opened.
+
+use super::{report_error, spawn_scan, ScanManager, ScanOptions, ScanResults};
+use parking_lot::{Condvar, Mutex};
+use serde::{Deserialize, Serialize};
+use std::{
+  collections::HashMap,
+  path::{Path, PathBuf},
+  sync::Arc,
+  time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tauri::{AppHandle, Emitter, Manager, State};
+use tauri_plugin_notification::NotificationExt;
+use uuid::Uuid;
+
+/// The shortest interval a scan can be scheduled at.
+const MIN_INTERVAL_SECS: u64 = 60;
+/// The longest the thread sleeps between looks, so a clock that jumped is
+/// noticed.
+const MAX_WAIT: Duration = Duration::from_secs(3600);
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ScheduledScan {
+  pub id: String,
+  pub path: String,
+  pub interval_secs: u64,
+  #[serde(default)]
+  pub options: ScanOptions,
+  #[serde(default = "enabled")]
+  pub enabled: bool,
+  /// When it was last started, in seconds since the Unix epoch.
+  #[serde(default)]
+  pub last_run: Option<i64>,
+}
+
+fn enabled() -> bool {
+  true
+}
+
+impl ScheduledScan {
+  fn due(&self) -> i64 {
+    self.last_run.map_or(i64::MIN, |last| last.saturating_add(self.interval_secs as i64))
+  }
+}
+
+#[derive(Clone, Serialize)]
+pub struct ScheduledScanEvent {
+  pub schedule_id: String,
+  pub scan_id: String,
+  pub root: String,
+  pub size: u64,
+  pub file_count: u64,
+  pub error_count: u64,
+}
+
+#[derive(Default)]
+struct Plan {
+  scans: Vec<ScheduledScan>,
+  /// Where `scans` is saved; `None` until the scheduler is started.
+  file: Option<PathBuf>,
+  /// The scheduled scans that are running: scan id to schedule id.
+  running: HashMap<String, String>,
+  stopped: bool,
+  /// Why the saved list couldn't be read, until `list_scheduled_scans`
+  /// passes it on.
+  load_error: Option<String>,
+}
+
+impl Plan {
+  fn save(&self) -> Result<(), String> {
+    let Some(file) = &self.file else {
+      return Err("The scheduler hasn't started".to_string());
+    };
+    let json = serde_json::to_string_pretty(&self.scans).map_err(|e| e.to_string())?;
+    std::fs::write(file, json).map_err(|e| format!("{}: {e}", file.display()))
+  }
+}
+
+/// The thread waits on `wake` until the next scan is due, so a change to the
+/// list is seen right away.
+#[derive(Default)]
+pub struct Scheduler {
+  plan: Arc<Mutex<Plan>>,
+  wake: Arc<Condvar>,
+}
+
+impl Scheduler {
+  /// Reads the saved list and starts the thread that runs it.
+  pub fn start(&self, app: AppHandle) {
+    let dir = match app.path().app_config_dir() {
+      Ok(dir) => dir,
+      Err(e) => {
+        self.plan.lock().load_error = Some(format!("No config folder for scheduled scans: {}", e));
+        return;
+      }
+    };
+    let _ = std::fs::create_dir_all(&dir);
+    let file = dir.join("schedules.json");
+    let mut plan = self.plan.lock();
+    match load(&file) {
+      Ok(scans) => plan.scans = scans,
+      Err(e) => plan.load_error = Some(e),
+    }
+    plan.file = Some(file);
+    let (shared, wake) = (self.plan.clone(), self.wake.clone());
+    std::thread::spawn(move || run(app, shared, wake));
+  }
+
+  /// Stops the thread, for app exit.
+  pub fn stop(&self) {
+    self.plan.lock().stopped = true;
+    self.wake.notify_all();
+  }
+}
+
+/// The saved list, or none if there's no file yet.
+fn load(file: &Path) -> Result<Vec<ScheduledScan>, String> {
+  let Ok(json) = std::fs::read_to_string(file) else {
+    return Ok(Vec::new());
+  };
+  serde_json::from_str(&json).map_err(|e| format!("{}: {}", file.display(), e))
+}
+
+fn now() -> i64 {
+  SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs() as i64)
+}
+
+fn run(app: AppHandle, plan: Arc<Mutex<Plan>>, wake: Arc<Condvar>) {
+  let mut plan = plan.lock();
+  while !plan.stopped {
+    let now = now();
+    let mut started = false;
+    for at in 0..plan.scans.len() {
+      let scan = &plan.scans[at];
+      if !scan.enabled || scan.due() > now {
+        continue;
+      }
+      // A run that's still going when the next is due makes that one skip.
+      let (id, path, options) = (scan.id.clone(), scan.path.clone(), scan.options.clone());
+      if !plan.running.values().any(|running| *running == id) {
+        match start_scan(&app, &path, options) {
+          Ok(scan_id) => {
+            plan.running.insert(scan_id, id);
+          }
+          Err(e) => report_error(&app, format!("Scheduled scan of {}: {}", path, e)),
+        }
+      }
+      plan.scans[at].last_run = Some(now);
+      started = true;
+    }
+    if started {
+      if let Err(e) = plan.save() {
+        report_error(&app, e);
+      }
+    }
+    let next = plan.scans.iter().filter(|scan| scan.enabled).map(ScheduledScan::due).min();
+    let wait = match next {
+      Some(due) => Duration::from_secs(due.saturating_sub(now).max(1) as u64).min(MAX_WAIT),
+      None => MAX_WAIT,
+    };
+    wake.wait_for(&mut plan, wait);
+  }
+}
+
+fn start_scan(app: &AppHandle, path: &str, options: ScanOptions) -> Result<String, String> {
+  let root = PathBuf::from(path);
+  if !root.exists() {
+    return Err("Path does not exist".to_string());
+  }
+  let manager = app.state::<ScanManager>();
+  spawn_scan(app, &manager, root, options)
+}
+
+/// Tells the window and the desktop that a scan finished, if the scheduler
+/// started it. `results` is `None` for a scan that failed, which the window
+/// hears of through scan_failed anyway.
+pub(super) fn finished(app: &AppHandle, scan_id: &str, results: Option<&ScanResults>) {
+  let Some(scheduler) = app.try_state::<Scheduler>() else {
+    return;
+  };
+  let Some(schedule_id) = scheduler.plan.lock().running.remove(scan_id) else {
+    return;
+  };
+  let Some(results) = results else {
+    return;
+  };
+  let event = ScheduledScanEvent {
+    schedule_id,
+    scan_id: scan_id.to_string(),
+    root: results.root.clone(),
+    size: results.tree.size,
+    file_count: results.tree.file_count.unwrap_or(0),
+    error_count: results.error_summary.total,
+  };
+  let body = format!(
+    "{:.1} GB in {} files{}",
+    event.size as f64 / 1e9,
+    event.file_count,
+    match event.error_count {
+      0 => String::new(),
+      count => format!(", {} couldn't be read", count),
+    }
+  );
+  let title = format!("Scheduled scan of {} finished", event.root);
+  let _ = app.notification().builder().title(title).body(body).show();
+  let _ = app.emit("scheduled_scan_done", event);
+}
+
+/// Scans that run by themselves, in the order they were added. Errs once
+/// if the saved list couldn't be read at startup.
+#[tauri::command]
+pub async fn list_scheduled_scans(
+  state: State<'_, Scheduler>,
+) -> Result<Vec<ScheduledScan>, String> {
+  let mut plan = state.plan.lock();
+  match plan.load_error.take() {
+    Some(e) => Err(e),
+    None => Ok(plan.scans.clone()),
+  }
+}
+
+/// Has the folder at `path` scanned with `options` every `interval_secs`,
+/// starting now. Returns the new schedule.
+#[tauri::command]
+pub async fn add_scheduled_scan(
+  state: State<'_, Scheduler>,
+  path: String,
+  interval_secs: u64,
+  options: Option<ScanOptions>,
+) -> Result<ScheduledScan, String> {
+  if interval_secs < MIN_INTERVAL_SECS {
+    return Err(format!("Scans can't run more often than every {MIN_INTERVAL_SECS} seconds"));
+  }
+  let root = crate::paths::canonicalize(&path)?;
+  let scan = ScheduledScan {
+    id: Uuid::new_v4().to_string(),
+    path: root.to_string_lossy().to_string(),
+    interval_secs,
+    options: options.unwrap_or_default(),
+    enabled: true,
+    last_run: None,
+  };
+  let mut plan = state.plan.lock();
+  plan.scans.push(scan.clone());
+  plan.save()?;
+  state.wake.notify_all();
+  Ok(scan)
+}
+
+/// Pauses or resumes a scheduled scan.
+#[tauri::command]
+pub async fn set_scheduled_scan_enabled(
+  state: State<'_, Scheduler>,
+  id: String,
+  enabled: bool,
+) -> Result<(), String> {
+  let mut plan = state.plan.lock();
+  let scan = plan.scans.iter_mut().find(|scan| scan.id == id).ok_or("No such scheduled scan")?;
+  scan.enabled = enabled;
+  plan.save()?;
+  state.wake.notify_all();
+  Ok(())
+}
+
+/// Stops scanning on schedule; a run that's going is left to finish.
+#[tauri::command]
+pub async fn remove_scheduled_scan(state: State<'_, Scheduler>, id: String) -> Result<(), String> {
+  let mut plan = state.plan.lock();
+  let before = plan.scans.len();
+  plan.scans.retain(|scan| scan.id != id);
+  if plan.scans.len() == before {
+    return Err("No such scheduled scan".to_string());
+  }
+  plan.save()?;
+  state.wake.notify_all();
+  Ok(())
+}
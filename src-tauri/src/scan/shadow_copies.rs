@@ -0,0 +1,115 @@
+//! Volume Shadow Copy storage on Windows: the diff area holding restore
+//! points and previous versions, kept in `System Volume Information` where a
+//! scan can't see into. It often explains GBs of used space the tree misses.
+//! WMI has it, for an elevated process.
+
+use super::blocking;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Serialize)]
+pub struct ShadowStorage {
+  /// Mount point of the volume the shadow copies are of.
+  pub volume: String,
+  /// Mount point of the volume they're kept on; usually the same.
+  pub storage_volume: String,
+  pub copies: u64,
+  pub used_bytes: u64,
+  /// Taken on `storage_volume`, used or not.
+  pub allocated_bytes: u64,
+  /// `None` when unbounded.
+  pub max_bytes: Option<u64>,
+}
+
+/// A row of what the script below prints.
+#[derive(Deserialize)]
+#[cfg_attr(not(windows), allow(dead_code))]
+#[serde(rename_all = "PascalCase")]
+struct StorageRow {
+  volume: String,
+  diff_volume: String,
+  copies: u64,
+  used: u64,
+  allocated: u64,
+  max: u64,
+}
+
+/// Shadow storage associations with the shadow copies of each volume counted,
+/// as JSON. Volumes are `\\?\Volume{...}\` device paths.
+#[cfg_attr(not(windows), allow(dead_code))]
+const SCRIPT: &str = "$ErrorActionPreference = 'Stop'; \
+  $copies = Get-CimInstance Win32_ShadowCopy | Group-Object VolumeName; \
+  @(Get-CimInstance Win32_ShadowStorage | ForEach-Object { \
+    $volume = $_.Volume.DeviceID; \
+    [pscustomobject]@{ \
+      Volume = $volume; DiffVolume = $_.DiffVolume.DeviceID; \
+      Copies = [uint64](($copies | Where-Object Name -eq $volume).Count); \
+      Used = [uint64]$_.UsedSpace; Allocated = [uint64]$_.AllocatedSpace; \
+      Max = [uint64]$_.MaxSpace } \
+  }) | ConvertTo-Json -Compress";
+
+#[cfg(windows)]
+fn shadow_storage() -> Result<Vec<ShadowStorage>, String> {
+  use std::os::windows::process::CommandExt;
+  const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+  let mut command = std::process::Command::new("powershell");
+  command
+    .args(["-NoProfile", "-NonInteractive", "-Command", SCRIPT])
+    .creation_flags(CREATE_NO_WINDOW);
+  let output = super::command_output(&mut command)
+    .map_err(|e| format!("Shadow copy storage can only be read elevated: {}", e))?;
+  // One row comes out as an object rather than an array.
+  let output = output.trim();
+  let rows: Vec<StorageRow> = if output.is_empty() {
+    Vec::new()
+  } else if output.starts_with('[') {
+    serde_json::from_str(output).map_err(|e| e.to_string())?
+  } else {
+    vec![serde_json::from_str(output).map_err(|e| e.to_string())?]
+  };
+  // Device paths to mount points, for showing and matching volumes.
+  let mounts: Vec<(String, String)> = crate::volume::distinct_mount_points()
+    .into_iter()
+    .filter_map(|mount| Some((crate::volume::device_path(&mount)?.to_lowercase(), mount)))
+    .collect();
+  let mount_of = |device: &str| {
+    let device = device.trim_end_matches('\\').to_lowercase();
+    let found = mounts.iter().find(|(known, _)| *known == device);
+    found.map_or_else(|| device.clone(), |(_, mount)| mount.clone())
+  };
+  Ok(
+    rows
+      .into_iter()
+      .map(|row| ShadowStorage {
+        volume: mount_of(&row.volume),
+        storage_volume: mount_of(&row.diff_volume),
+        copies: row.copies,
+        used_bytes: row.used,
+        allocated_bytes: row.allocated,
+        max_bytes: (row.max != u64::MAX).then_some(row.max),
+      })
+      .collect(),
+  )
+}
+
+#[cfg(not(windows))]
+fn shadow_storage() -> Result<Vec<ShadowStorage>, String> {
+  Ok(Vec::new())
+}
+
+/// Bytes the shadow storage on the volume mounted at `mount_point` takes,
+/// or `None` when it can't be read.
+#[cfg_attr(not(windows), allow(dead_code))]
+pub(super) fn bytes_on(mount_point: &str) -> Option<u64> {
+  let trim = |mount: &str| mount.trim_end_matches('\\').to_lowercase();
+  let storage = shadow_storage().ok()?;
+  let on_volume = storage.iter().filter(|s| trim(&s.storage_volume) == trim(mount_point));
+  Some(on_volume.map(|s| s.allocated_bytes).sum())
+}
+
+/// Shadow copy storage of each volume that has it set up, with its limit.
+/// Windows only, and only for an elevated process; elsewhere the list is
+/// empty.
+#[tauri::command]
+pub async fn get_shadow_storage() -> Result<Vec<ShadowStorage>, String> {
+  blocking(shadow_storage).await
+}
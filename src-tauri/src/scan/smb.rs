@@ -0,0 +1,438 @@
+//! SMB shares that aren't mounted yet: listing what a host shares and
+//! scanning one with a user and password. Windows connects to the share and
+//! the scan reads its UNC path; macOS mounts it with `mount_smbfs`, and Linux
+//! through GVfs, whose FUSE view of it is scanned. Passwords can be kept in
+//! the OS keyring so they're asked for once.
+
+use super::{blocking, credentials, spawn_scan, ScanManager, ScanOptions};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use tauri::{AppHandle, State};
+
+#[derive(Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct SmbCredentials {
+  pub user: String,
+  pub domain: Option<String>,
+  /// `None` uses the password saved for this user and host.
+  pub password: Option<String>,
+  /// Keep `password` in the OS keyring for next time.
+  pub remember: bool,
+}
+
+#[derive(Clone, Serialize)]
+pub struct SmbShare {
+  pub name: String,
+  pub comment: String,
+  /// `\\host\share` on Windows, `smb://host/share` elsewhere.
+  pub path: String,
+}
+
+/// Credentials with the password filled in.
+#[cfg_attr(not(any(windows, target_os = "linux", target_os = "macos")), allow(dead_code))]
+struct Login {
+  user: String,
+  domain: Option<String>,
+  password: String,
+}
+
+fn account(host: &str, user: &str) -> String {
+  format!("smb://{}@{}", user, host.to_lowercase())
+}
+
+/// What to log in to `host` with: the password given, saved if asked to,
+/// or the one saved before. No credentials logs in as the current user on
+/// Windows and as a guest elsewhere.
+fn login(host: &str, credentials: Option<SmbCredentials>) -> Result<Option<Login>, String> {
+  let Some(credentials) = credentials.filter(|c| !c.user.is_empty()) else {
+    return Ok(None);
+  };
+  let account = account(host, &credentials.user);
+  let password = match credentials.password {
+    Some(password) => {
+      if credentials.remember {
+        credentials::save(&account, &password)?;
+      }
+      password
+    }
+    None => credentials::load(&account)
+      .ok_or_else(|| format!("No password saved for {} on {}", credentials.user, host))?,
+  };
+  Ok(Some(Login {
+    user: credentials.user,
+    domain: credentials.domain.filter(|d| !d.is_empty()),
+    password,
+  }))
+}
+
+/// A host name or address as typed, without `\\`, `smb://` or a share.
+fn host_name(host: &str) -> Result<String, String> {
+  let host = host.trim();
+  let host = host.strip_prefix("smb://").unwrap_or(host).trim_start_matches(['\\', '/']);
+  let host = host.split(['\\', '/']).next().unwrap_or_default();
+  match host.is_empty() {
+    true => Err("Enter a host name or address".to_string()),
+    false => Ok(host.to_string()),
+  }
+}
+
+#[cfg(windows)]
+fn share_path(host: &str, share: &str) -> String {
+  format!(r"\\{}\{}", host, share)
+}
+
+#[cfg(not(windows))]
+fn share_path(host: &str, share: &str) -> String {
+  format!("smb://{}/{}", host, share)
+}
+
+/// Connects to `\\host\share` as `login`, for this session only.
+#[cfg(windows)]
+fn connect(host: &str, share: &str, login: Option<&Login>) -> Result<PathBuf, String> {
+  use windows_sys::Win32::NetworkManagement::WNet::{
+    WNetAddConnection2W, CONNECT_TEMPORARY, NETRESOURCEW, RESOURCETYPE_DISK,
+  };
+  // Already connected to the host as someone else.
+  const ERROR_SESSION_CREDENTIAL_CONFLICT: u32 = 1219;
+  let path = PathBuf::from(share_path(host, share));
+  let Some(login) = login else {
+    return Ok(path);
+  };
+  let wide = |text: &str| crate::volume::to_wide(std::path::Path::new(text));
+  let mut remote = wide(&path.to_string_lossy());
+  let user = match &login.domain {
+    Some(domain) => wide(&format!(r"{}\{}", domain, login.user)),
+    None => wide(&login.user),
+  };
+  let password = wide(&login.password);
+  let resource = NETRESOURCEW {
+    dwType: RESOURCETYPE_DISK,
+    lpRemoteName: remote.as_mut_ptr(),
+    ..Default::default()
+  };
+  let code = unsafe {
+    WNetAddConnection2W(&resource, password.as_ptr(), user.as_ptr(), CONNECT_TEMPORARY)
+  };
+  match code {
+    0 => Ok(path),
+    ERROR_SESSION_CREDENTIAL_CONFLICT => Err(format!(
+      "Windows is already connected to {} as another user; disconnect it first",
+      host
+    )),
+    code => Err(format!("{}: {}", path.display(), std::io::Error::from_raw_os_error(code as i32))),
+  }
+}
+
+#[cfg(windows)]
+fn disconnect(host: &str, share: &str) -> Result<(), String> {
+  use windows_sys::Win32::NetworkManagement::WNet::WNetCancelConnection2W;
+  let path = share_path(host, share);
+  let wide = crate::volume::to_wide(std::path::Path::new(&path));
+  match unsafe { WNetCancelConnection2W(wide.as_ptr(), 0, 1) } {
+    0 => Ok(()),
+    code => Err(format!("{}: {}", path, std::io::Error::from_raw_os_error(code as i32))),
+  }
+}
+
+/// The disk shares of `host`, asked of it after connecting to its `IPC$`
+/// as `login`.
+#[cfg(windows)]
+fn shares(host: &str, login: Option<&Login>) -> Result<Vec<SmbShare>, String> {
+  use windows_sys::Win32::{
+    NetworkManagement::NetManagement::{NetApiBufferFree, MAX_PREFERRED_LENGTH},
+    Storage::FileSystem::{NetShareEnum, SHARE_INFO_1, STYPE_DISKTREE, STYPE_MASK, STYPE_SPECIAL},
+  };
+  if login.is_some() {
+    connect(host, "IPC$", login)?;
+  }
+  let server = crate::volume::to_wide(std::path::Path::new(&format!(r"\\{}", host)));
+  let (mut buffer, mut read, mut total) = (std::ptr::null_mut(), 0u32, 0u32);
+  let code = unsafe {
+    NetShareEnum(
+      server.as_ptr(),
+      1,
+      &mut buffer,
+      MAX_PREFERRED_LENGTH,
+      &mut read,
+      &mut total,
+      std::ptr::null_mut(),
+    )
+  };
+  if code != 0 {
+    return Err(format!("{}: {}", host, std::io::Error::from_raw_os_error(code as i32)));
+  }
+  let text = |text: *mut u16| match text.is_null() {
+    true => String::new(),
+    false => String::from_utf16_lossy(unsafe { std::slice::from_raw_parts(text, wcslen(text)) }),
+  };
+  let mut shares = Vec::new();
+  if !buffer.is_null() {
+    let infos = unsafe { std::slice::from_raw_parts(buffer as *const SHARE_INFO_1, read as usize) };
+    for info in infos {
+      let kind = info.shi1_type;
+      if kind & STYPE_MASK != STYPE_DISKTREE || kind & STYPE_SPECIAL != 0 {
+        continue;
+      }
+      let name = text(info.shi1_netname);
+      shares.push(SmbShare {
+        path: share_path(host, &name),
+        comment: text(info.shi1_remark),
+        name,
+      });
+    }
+    unsafe { NetApiBufferFree(buffer as *const _) };
+  }
+  Ok(shares)
+}
+
+#[cfg(windows)]
+fn wcslen(text: *const u16) -> usize {
+  let mut len = 0;
+  while unsafe { *text.add(len) } != 0 {
+    len += 1;
+  }
+  len
+}
+
+/// `text` with everything but unreserved URL characters percent-encoded.
+#[cfg(target_os = "macos")]
+fn url_encode(text: &str) -> String {
+  text
+    .bytes()
+    .map(|byte| match byte {
+      b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+        (byte as char).to_string()
+      }
+      _ => format!("%{:02X}", byte),
+    })
+    .collect()
+}
+
+/// `//[domain;]user:password@host`, as `smbutil` and `mount_smbfs` take it.
+#[cfg(target_os = "macos")]
+fn smb_url(host: &str, login: Option<&Login>) -> String {
+  let Some(login) = login else {
+    return format!("//guest:@{}", host);
+  };
+  let domain = login.domain.as_deref().map(|d| format!("{};", url_encode(d))).unwrap_or_default();
+  let (user, password) = (url_encode(&login.user), url_encode(&login.password));
+  format!("//{}{}:{}@{}", domain, user, password, host)
+}
+
+/// Where `share` of `host` gets mounted: a folder of the app's own under
+/// the temporary directory, since `/Volumes` needs root.
+#[cfg(target_os = "macos")]
+fn mount_dir(host: &str, share: &str) -> PathBuf {
+  std::env::temp_dir().join("space-usage-smb").join(format!("{}-{}", host, share))
+}
+
+#[cfg(target_os = "macos")]
+fn connect(host: &str, share: &str, login: Option<&Login>) -> Result<PathBuf, String> {
+  let dir = mount_dir(host, share);
+  if crate::volume::is_volume_root(&dir) {
+    return Ok(dir);
+  }
+  std::fs::create_dir_all(&dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+  let url = format!("{}/{}", smb_url(host, login), url_encode(share));
+  let mut command = std::process::Command::new("mount_smbfs");
+  super::command_output(command.args(["-N", &url]).arg(&dir))
+    .map_err(|e| format!("{}: {}", share_path(host, share), e))?;
+  Ok(dir)
+}
+
+#[cfg(target_os = "macos")]
+fn disconnect(host: &str, share: &str) -> Result<(), String> {
+  let dir = mount_dir(host, share);
+  if crate::volume::is_volume_root(&dir) {
+    let mut command = std::process::Command::new("umount");
+    super::command_output(command.arg(&dir)).map_err(|e| format!("umount: {}", e))?;
+  }
+  let _ = std::fs::remove_dir(&dir);
+  Ok(())
+}
+
+/// `smbutil view` prints a table of name, type and comment, the name
+/// padded to where the `Type` heading starts.
+#[cfg(target_os = "macos")]
+fn shares(host: &str, login: Option<&Login>) -> Result<Vec<SmbShare>, String> {
+  let mut command = std::process::Command::new("smbutil");
+  let output = super::command_output(command.args(["view", "-N", &smb_url(host, login)]))
+    .map_err(|e| format!("{}: {}", host, e))?;
+  let mut lines = output.lines();
+  let Some(type_at) = lines.next().and_then(|header| header.find("Type")) else {
+    return Ok(Vec::new());
+  };
+  Ok(
+    lines
+      .filter(|line| line.len() > type_at && line.is_char_boundary(type_at))
+      .filter_map(|line| {
+        let (name, rest) = line.split_at(type_at);
+        let (kind, comment) = rest.trim_start().split_once(' ').unwrap_or((rest.trim(), ""));
+        let name = name.trim();
+        (kind == "Disk" && !name.ends_with('$')).then(|| SmbShare {
+          path: share_path(host, name),
+          comment: comment.trim().to_string(),
+          name: name.to_string(),
+        })
+      })
+      .collect(),
+  )
+}
+
+/// The share's URL for GVfs, with the user in it so that only the password
+/// is asked for.
+#[cfg(target_os = "linux")]
+fn gvfs_url(host: &str, share: &str, login: Option<&Login>) -> String {
+  match login {
+    Some(login) => {
+      let domain = login.domain.as_deref().unwrap_or("WORKGROUP");
+      format!("smb://{};{}@{}/{}", domain, login.user, host, share)
+    }
+    None => share_path(host, share),
+  }
+}
+
+/// Mounts the share with `gio mount`, feeding it the password, and returns
+/// where GVfs shows it.
+#[cfg(target_os = "linux")]
+fn connect(host: &str, share: &str, login: Option<&Login>) -> Result<PathBuf, String> {
+  use std::{io::Write, process::Stdio};
+  let url = gvfs_url(host, share, login);
+  let mut command = std::process::Command::new("gio");
+  command.arg("mount");
+  if login.is_none() {
+    command.arg("--anonymous");
+  }
+  let mut child = command
+    .arg(&url)
+    .stdin(Stdio::piped())
+    .stdout(Stdio::null())
+    .stderr(Stdio::piped())
+    .spawn()
+    .map_err(|e| format!("gio: {}", e))?;
+  if let (Some(mut stdin), Some(login)) = (child.stdin.take(), login) {
+    let _ = writeln!(stdin, "{}", login.password);
+  }
+  let output = child.wait_with_output().map_err(|e| format!("gio: {}", e))?;
+  let message = String::from_utf8_lossy(&output.stderr);
+  // Mounting one that is mounted already fails, which is fine.
+  if !output.status.success() && !message.contains("already mounted") {
+    return Err(format!("{}: {}", share_path(host, share), message.trim()));
+  }
+  let mut command = std::process::Command::new("gio");
+  let info = super::command_output(command.args(["info", &url]));
+  let info = info.map_err(|e| format!("gio: {}", e))?;
+  info
+    .lines()
+    .find_map(|line| line.trim().strip_prefix("local path: "))
+    .map(PathBuf::from)
+    .ok_or_else(|| format!("{} has no local path; is gvfs-fuse running?", share_path(host, share)))
+}
+
+#[cfg(target_os = "linux")]
+fn disconnect(host: &str, share: &str) -> Result<(), String> {
+  let mut command = std::process::Command::new("gio");
+  super::command_output(command.args(["mount", "--unmount", &share_path(host, share)]))
+    .map(|_| ())
+    .map_err(|e| format!("gio: {}", e))
+}
+
+/// The disk shares `smbclient -L` lists; `-g` prints them as
+/// `Disk|name|comment`. The password goes in through `PASSWD` rather than
+/// the command line.
+#[cfg(target_os = "linux")]
+fn shares(host: &str, login: Option<&Login>) -> Result<Vec<SmbShare>, String> {
+  let mut command = std::process::Command::new("smbclient");
+  command.args(["-g", "-L", host]);
+  match login {
+    Some(login) => {
+      let user = match &login.domain {
+        Some(domain) => format!("{}/{}", domain, login.user),
+        None => login.user.clone(),
+      };
+      command.args(["-U", &user]).env("PASSWD", &login.password);
+    }
+    None => {
+      command.arg("-N");
+    }
+  }
+  let output = super::command_output(&mut command).map_err(|e| match e.contains("No such file") {
+    true => "Listing SMB shares needs smbclient".to_string(),
+    false => format!("{}: {}", host, e),
+  })?;
+  Ok(
+    output
+      .lines()
+      .filter_map(|line| {
+        let mut fields = line.splitn(3, '|');
+        let (kind, name) = (fields.next()?, fields.next()?);
+        (kind == "Disk" && !name.ends_with('$')).then(|| SmbShare {
+          path: share_path(host, name),
+          comment: fields.next().unwrap_or_default().to_string(),
+          name: name.to_string(),
+        })
+      })
+      .collect(),
+  )
+}
+
+#[cfg(not(any(windows, target_os = "linux", target_os = "macos")))]
+fn shares(_host: &str, _login: Option<&Login>) -> Result<Vec<SmbShare>, String> {
+  Err("SMB shares can't be listed on this system".to_string())
+}
+
+#[cfg(not(any(windows, target_os = "linux", target_os = "macos")))]
+fn connect(_host: &str, _share: &str, _login: Option<&Login>) -> Result<PathBuf, String> {
+  Err("SMB shares can't be connected to on this system".to_string())
+}
+
+#[cfg(not(any(windows, target_os = "linux", target_os = "macos")))]
+fn disconnect(_host: &str, _share: &str) -> Result<(), String> {
+  Ok(())
+}
+
+/// The disk shares `host` offers, hidden (`$`) ones left out.
+#[tauri::command]
+pub async fn list_smb_shares(
+  host: String,
+  credentials: Option<SmbCredentials>,
+) -> Result<Vec<SmbShare>, String> {
+  blocking(move || {
+    let host = host_name(&host)?;
+    let login = login(&host, credentials)?;
+    let mut shares = shares(&host, login.as_ref())?;
+    shares.sort_by_key(|share| share.name.to_lowercase());
+    Ok(shares)
+  })
+  .await
+}
+
+/// Connects to `share` on `host` as the user in `credentials` and scans it
+/// like any other path. Returns the scan id; the share stays connected
+/// until `disconnect_smb_share`.
+#[tauri::command]
+pub async fn start_smb_scan(
+  app: AppHandle,
+  state: State<'_, ScanManager>,
+  host: String,
+  share: String,
+  credentials: Option<SmbCredentials>,
+  options: Option<ScanOptions>,
+) -> Result<String, String> {
+  let host = host_name(&host)?;
+  let login = login(&host, credentials)?;
+  let root_path = connect(&host, share.trim_matches(['\\', '/']), login.as_ref())?;
+  spawn_scan(&app, &state, root_path, options.unwrap_or_default())
+}
+
+/// Drops the connection or mount `start_smb_scan` made.
+#[tauri::command]
+pub async fn disconnect_smb_share(host: String, share: String) -> Result<(), String> {
+  disconnect(&host_name(&host)?, share.trim_matches(['\\', '/']))
+}
+
+/// Forgets the password saved for `user` on `host`.
+#[tauri::command]
+pub async fn forget_smb_password(host: String, user: String) -> Result<(), String> {
+  credentials::forget(&account(&host_name(&host)?, &user))
+}
@@ -0,0 +1,298 @@
+//! Scanning a server over SSH, with nothing to install on it: the system's
+//! `ssh` runs GNU `find` there, as the WSL listing does, and the listing is
+//! read as it arrives, so the scan reports progress like a local one. Keys
+//! and the agent work as they do in a terminal; a password goes to `ssh`
+//! through `SSH_ASKPASS`, answered by this same executable.
+
+use super::{
+  credentials, empty_dir_node, maybe_emit_progress, spawn_scan_from,
+  wsl::{read_listing, FIND_FORMAT},
+  ScanContext, ScanErrorKind, ScanManager, ScanNode, ScanOptions, ScanSource,
+};
+use serde::Deserialize;
+use std::{
+  io::{BufRead, BufReader, Read},
+  path::{Path, PathBuf},
+  process::{Command, Stdio},
+  sync::atomic::Ordering,
+};
+use tauri::{AppHandle, State};
+
+/// Set for `ssh`, it turns this executable into its password prompt: `ssh`
+/// runs `SSH_ASKPASS` and reads the password from what it prints.
+const ASKPASS_PASSWORD: &str = "SPACE_USAGE_SSH_PASSWORD";
+
+#[derive(Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct SshTarget {
+  pub host: String,
+  pub port: Option<u16>,
+  /// As in `~/.ssh/config` when not given.
+  pub user: Option<String>,
+  /// The folder on the server to scan, from `/` or `~`; `/` when empty.
+  pub path: String,
+  /// `None` uses keys, the agent, or the password saved for this account.
+  pub password: Option<String>,
+  /// Keep `password` in the OS keyring for next time.
+  pub remember: bool,
+  /// Accept the key of a server `ssh` hasn't seen before, as `accept-new`
+  /// does. A key that changed is refused either way.
+  pub trust_new_host: bool,
+}
+
+/// A server to list, with the password looked up.
+pub(super) struct Remote {
+  host: String,
+  port: Option<u16>,
+  user: Option<String>,
+  path: String,
+  password: Option<String>,
+  /// Stay on the filesystem of `path`, for `same_filesystem`.
+  one_filesystem: bool,
+  trust_new_host: bool,
+}
+
+impl Remote {
+  /// `user@host:`, what remote paths are shown after.
+  fn prefix(&self) -> String {
+    match &self.user {
+      Some(user) => format!("{}@{}:", user, self.host),
+      None => format!("{}:", self.host),
+    }
+  }
+}
+
+fn account(host: &str, port: Option<u16>, user: Option<&str>) -> String {
+  let user = user.map(|user| format!("{}@", user)).unwrap_or_default();
+  format!("ssh://{}{}:{}", user, host.to_lowercase(), port.unwrap_or(22))
+}
+
+/// Errs for a server `ssh` would take as one of its options.
+pub(super) fn check_host(host: &str) -> Result<(), String> {
+  match host.starts_with('-') {
+    true => Err(format!("{} isn't a server name", host)),
+    false => Ok(()),
+  }
+}
+
+/// What to say about `ssh` failing with `errors` on its stderr.
+pub(super) fn ssh_failure(errors: &str) -> String {
+  match errors.contains("Host key verification failed") {
+    true => "the server's host key isn't in known_hosts, or doesn't match it. Check the key \
+      and connect once from a terminal, or trust the key of a new server"
+      .to_string(),
+    false => errors.trim().to_string(),
+  }
+}
+
+/// `path` quoted for the remote shell, with a leading `~` left for it to
+/// expand.
+fn shell_quote(path: &str) -> String {
+  let quote = |text: &str| format!("'{}'", text.replace('\'', r"'\''"));
+  match path.strip_prefix('~') {
+    Some("") => "\"$HOME\"".to_string(),
+    Some(rest) if rest.starts_with('/') => format!("\"$HOME\"{}", quote(rest)),
+    _ => quote(path),
+  }
+}
+
+/// `ssh` with the options of every connection made from here; the caller
+/// adds how to sign in, the server and what to run on it. Host keys are
+/// checked as the user's `ssh_config` says, and with no terminal to ask in,
+/// a server not in `known_hosts` fails unless `trust_new_host`.
+pub(super) fn ssh_command(port: Option<u16>, user: Option<&str>, trust_new_host: bool) -> Command {
+  let mut command = Command::new("ssh");
+  #[cfg(windows)]
+  {
+    use std::os::windows::process::CommandExt;
+    const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+    command.creation_flags(CREATE_NO_WINDOW);
+  }
+  command.args(["-o", "ConnectTimeout=15"]);
+  if trust_new_host {
+    command.args(["-o", "StrictHostKeyChecking=accept-new"]);
+  }
+  if let Some(port) = port {
+    command.args(["-p", &port.to_string()]);
+  }
+  if let Some(user) = user {
+    command.args(["-l", user]);
+  }
+  command
+}
+
+/// The `ssh` running `find` on the server, its output piped back.
+fn find_command(remote: &Remote) -> Command {
+  let mut command = ssh_command(remote.port, remote.user.as_deref(), remote.trust_new_host);
+  match (&remote.password, std::env::current_exe()) {
+    (Some(password), Ok(exe)) => {
+      command.env("SSH_ASKPASS", exe).env("SSH_ASKPASS_REQUIRE", "force");
+      command.env(ASKPASS_PASSWORD, password);
+      // Before OpenSSH 8.4 the prompt was only used with a display set.
+      if std::env::var_os("DISPLAY").is_none() {
+        command.env("DISPLAY", ":0");
+      }
+    }
+    _ => {
+      command.args(["-o", "BatchMode=yes"]);
+    }
+  }
+  let xdev = if remote.one_filesystem { " -xdev" } else { "" };
+  let find = format!(
+    "LC_ALL=C find {}{} -printf '{}'",
+    shell_quote(&remote.path),
+    xdev,
+    FIND_FORMAT
+  );
+  command.arg(&remote.host).arg("--").arg(find);
+  command.stdin(Stdio::null()).stdout(Stdio::piped()).stderr(Stdio::piped());
+  command
+}
+
+/// Lists `remote` and builds its tree under `root`, the `user@host:/path`
+/// it's shown as. What the server can't read is recorded like local errors.
+pub(super) fn scan(ctx: &ScanContext, remote: &Remote, root: &Path) -> ScanNode {
+  let mut child = match find_command(remote).spawn() {
+    Ok(child) => child,
+    Err(e) => {
+      ctx.record_error(root, ScanErrorKind::Other, None, &format!("ssh: {}", e));
+      return empty_dir_node(root);
+    }
+  };
+  // Read apart so neither pipe fills up while the other is waited on.
+  let stderr = child.stderr.take().map(|mut stderr| {
+    std::thread::spawn(move || {
+      let mut text = String::new();
+      let _ = stderr.read_to_string(&mut text);
+      text
+    })
+  });
+  let mut listing = Vec::new();
+  if let Some(stdout) = child.stdout.take() {
+    for record in BufReader::new(stdout).split(0) {
+      let Ok(record) = record else {
+        break;
+      };
+      if ctx.cancelled() {
+        let _ = child.kill();
+        break;
+      }
+      count(ctx, root, &record);
+      listing.extend_from_slice(&record);
+      listing.push(0);
+    }
+  }
+  let status = child.wait();
+  let errors = stderr.and_then(|thread| thread.join().ok()).unwrap_or_default();
+  record_errors(ctx, &remote.prefix(), &errors);
+  // find exits with 1 after unreadable folders; ssh itself fails with 255.
+  if listing.is_empty() && !ctx.cancelled() {
+    let failed = status.is_ok_and(|status| status.code() == Some(255));
+    if failed || errors.contains("printf: unknown") || errors.contains("-printf") {
+      let message = match failed {
+        true => ssh_failure(&errors),
+        false => "the server's find doesn't support -printf; GNU find is needed".to_string(),
+      };
+      ctx.record_error(root, ScanErrorKind::Other, None, &format!("ssh: {}", message));
+    }
+    return empty_dir_node(root);
+  }
+  read_listing(ctx, root, &listing)
+}
+
+/// Counts one entry of the listing into the scan's progress.
+fn count(ctx: &ScanContext, root: &Path, record: &[u8]) {
+  let line = String::from_utf8_lossy(record);
+  let mut fields = line.splitn(7, '\t');
+  let kind = fields.next().unwrap_or_default();
+  let size = fields.nth(2).and_then(|size| size.parse::<u64>().ok()).unwrap_or(0);
+  ctx.control.scanned_entries.fetch_add(1, Ordering::Relaxed);
+  if kind == "f" {
+    ctx.control.scanned_bytes.fetch_add(size, Ordering::Relaxed);
+  }
+  let relative = fields.nth(2).unwrap_or_default();
+  maybe_emit_progress(ctx, &root.join(relative));
+}
+
+/// `find: '/root': Permission denied` and the like, one per line.
+fn record_errors(ctx: &ScanContext, prefix: &str, errors: &str) {
+  for line in errors.lines().filter_map(|line| line.strip_prefix("find: ")) {
+    let (path, message) = match line.strip_prefix('\'').and_then(|rest| rest.split_once("': ")) {
+      Some((path, message)) => (path, message),
+      None => ("", line),
+    };
+    let kind = match message {
+      "Permission denied" => ScanErrorKind::PermissionDenied,
+      "No such file or directory" => ScanErrorKind::NotFound,
+      _ => ScanErrorKind::Other,
+    };
+    ctx.record_error(Path::new(&format!("{}{}", prefix, path)), kind, None, &message);
+  }
+}
+
+/// Answers `ssh`'s password prompt when this executable runs as its
+/// `SSH_ASKPASS`; `None` otherwise.
+pub fn answer_askpass() -> Option<i32> {
+  let password = std::env::var_os(ASKPASS_PASSWORD)?;
+  println!("{}", password.to_string_lossy());
+  Some(0)
+}
+
+/// Scans `path` on a server over SSH. Returns the scan id; progress and
+/// the tree arrive through the same events as for a local scan, under a
+/// root shown as `user@host:/path`. The server needs GNU `find`.
+#[tauri::command]
+pub async fn start_ssh_scan(
+  app: AppHandle,
+  state: State<'_, ScanManager>,
+  target: SshTarget,
+  options: Option<ScanOptions>,
+) -> Result<String, String> {
+  let host = target.host.trim().to_string();
+  if host.is_empty() {
+    return Err("Enter a server to connect to".to_string());
+  }
+  check_host(&host)?;
+  let user = target.user.map(|user| user.trim().to_string()).filter(|user| !user.is_empty());
+  let account = account(&host, target.port, user.as_deref());
+  let password = match target.password.filter(|password| !password.is_empty()) {
+    Some(password) => {
+      if target.remember {
+        credentials::save(&account, &password)?;
+      }
+      Some(password)
+    }
+    None => credentials::load(&account),
+  };
+  let path = match target.path.trim() {
+    "" => "/".to_string(),
+    // Anything else would reach find as an expression, not a folder.
+    path if path.starts_with('/') || path == "~" || path.starts_with("~/") => path.to_string(),
+    path => return Err(format!("{} isn't a full path on the server", path)),
+  };
+  let mut options = options.unwrap_or_default();
+  // The server's filesystems can't be told apart from here; find does it.
+  let one_filesystem = std::mem::take(&mut options.same_filesystem);
+  let remote = Remote {
+    host,
+    port: target.port,
+    user,
+    path,
+    password,
+    one_filesystem,
+    trust_new_host: target.trust_new_host,
+  };
+  let shown = PathBuf::from(format!("{}{}", remote.prefix(), remote.path));
+  spawn_scan_from(&app, &state, shown, options, ScanSource::Ssh(remote))
+}
+
+/// Forgets the password saved for `user` on `host`.
+#[tauri::command]
+pub async fn forget_ssh_password(
+  host: String,
+  port: Option<u16>,
+  user: Option<String>,
+) -> Result<(), String> {
+  let user = user.filter(|user| !user.trim().is_empty());
+  credentials::forget(&account(host.trim(), port, user.as_deref().map(str::trim)))
+}
@@ -0,0 +1,376 @@
+//! Scan-wide figures about the files and directories counted into the tree,
+//! whatever the depth or node they ended up in.
+
+use super::{FileCategory, FileFacts, FileSizes, ScanManager, SizeMode};
+use serde::Serialize;
+use std::{
+  cmp::Reverse,
+  collections::{BinaryHeap, HashMap},
+  path::{Path, PathBuf},
+};
+use tauri::State;
+
+/// Extensions listed in the scan_done summary.
+const SUMMARY_EXTENSIONS: usize = 10;
+/// Largest files remembered per scan, the most `get_top_files` returns.
+const MAX_TOP_FILES: usize = 1000;
+/// Fullest directories remembered per scan, the most `get_top_dirs_by_count`
+/// returns.
+const MAX_TOP_DIRS: usize = 1000;
+/// Upper bounds of the size histogram buckets; one more bucket holds
+/// everything above the last.
+const SIZE_BUCKET_BOUNDS: [u64; 6] = [4 << 10, 64 << 10, 1 << 20, 16 << 20, 256 << 20, 1 << 30];
+/// Ages in days past which the largest files are remembered for
+/// `get_old_files`, up to `MAX_OLD_FILES` per age.
+const OLD_FILE_AGES: [u64; 7] = [0, 30, 90, 180, 365, 730, 1825];
+const MAX_OLD_FILES: usize = 1000;
+const DAY_SECS: i64 = 24 * 60 * 60;
+
+#[derive(Clone, Serialize)]
+pub struct ExtensionStat {
+  /// Lowercase, without the dot; empty for files without an extension.
+  pub extension: String,
+  pub bytes: u64,
+  pub allocated: u64,
+  pub files: u64,
+}
+
+/// A file picked out of a scan by one of the reports.
+#[derive(Clone, Serialize)]
+pub struct FileEntry {
+  pub path: String,
+  pub size: u64,
+  pub allocated: u64,
+}
+
+/// A file with the times it was last modified and accessed, in seconds
+/// since the Unix epoch.
+#[derive(Clone, Serialize)]
+pub struct OldFile {
+  pub path: String,
+  pub size: u64,
+  pub allocated: u64,
+  pub mtime: i64,
+  pub atime: Option<i64>,
+}
+
+/// The largest files past each of `OLD_FILE_AGES` when the scan ran.
+#[derive(Clone)]
+pub(super) struct OldFiles {
+  scanned_at: i64,
+  // Largest first, one list per age.
+  by_age: Vec<Vec<OldFile>>,
+}
+
+impl OldFiles {
+  /// The files not modified in the `days` before the scan, nor accessed when
+  /// `unaccessed` is set, of at least `min_size` bytes, largest first.
+  fn older_than(&self, days: u64, min_size: u64, unaccessed: bool) -> Vec<OldFile> {
+    let age = OLD_FILE_AGES.iter().rposition(|&age| age <= days).unwrap_or(0);
+    let cutoff = self
+      .scanned_at
+      .saturating_sub((days as i64).saturating_mul(DAY_SECS));
+    self.by_age[age]
+      .iter()
+      .filter(|file| file.mtime <= cutoff && file.size >= min_size)
+      .filter(|file| !unaccessed || file.atime.is_none_or(|atime| atime <= cutoff))
+      .cloned()
+      .collect()
+  }
+}
+
+/// Files whose size is at least `min` and below `max`.
+#[derive(Clone, Serialize)]
+pub struct SizeBucket {
+  pub min: u64,
+  /// `None` for the last bucket.
+  pub max: Option<u64>,
+  pub files: u64,
+  pub bytes: u64,
+}
+
+#[derive(Clone, Serialize)]
+pub struct DirCount {
+  pub path: String,
+  /// Files and subdirectories directly inside.
+  pub entries: u64,
+}
+
+#[derive(Clone, Serialize)]
+pub struct CategoryStat {
+  pub category: FileCategory,
+  pub bytes: u64,
+  pub allocated: u64,
+  pub files: u64,
+}
+
+/// The items with the largest keys of those offered, up to a limit.
+struct Largest<T> {
+  limit: usize,
+  // Smallest key on top.
+  heap: BinaryHeap<Reverse<(u64, T)>>,
+}
+
+impl<T: Ord> Largest<T> {
+  fn new(limit: usize) -> Self {
+    Self {
+      limit,
+      heap: BinaryHeap::new(),
+    }
+  }
+
+  /// Keeps what `make` returns if `key` ranks among the largest; `make` only
+  /// runs then. Zero keys never do.
+  fn offer(&mut self, key: u64, make: impl FnOnce() -> T) {
+    if key == 0 {
+      return;
+    }
+    if self.heap.len() >= self.limit {
+      if self.heap.peek().is_some_and(|Reverse((min, _))| *min >= key) {
+        return;
+      }
+      self.heap.pop();
+    }
+    self.heap.push(Reverse((key, make())));
+  }
+
+  /// Largest first.
+  fn sorted(&self) -> Vec<&(u64, T)> {
+    let mut items: Vec<_> = self.heap.iter().map(|Reverse(item)| item).collect();
+    items.sort_by(|a, b| b.cmp(a));
+    items
+  }
+}
+
+/// Collected while walking, one file or directory at a time.
+pub(super) struct ScanStats {
+  size_mode: SizeMode,
+  // (sizes, files) per lowercase extension, and per category.
+  extensions: HashMap<String, (FileSizes, u64)>,
+  categories: [(FileSizes, u64); FileCategory::ALL.len()],
+  // (files, bytes) per bucket of `SIZE_BUCKET_BOUNDS`.
+  histogram: [(u64, u64); SIZE_BUCKET_BOUNDS.len() + 1],
+  // By size in `size_mode`: (apparent, allocated, path).
+  largest: Largest<(u64, u64, PathBuf)>,
+  // By entries directly inside.
+  fullest: Largest<PathBuf>,
+  // When the scan started, and per age of `OLD_FILE_AGES` the files past it
+  // by size in `size_mode`.
+  started_at: i64,
+  old: Vec<Largest<DatedFile>>,
+}
+
+/// (apparent, allocated, mtime, atime, path).
+type DatedFile = (u64, u64, i64, Option<i64>, PathBuf);
+
+impl ScanStats {
+  pub(super) fn new(size_mode: SizeMode) -> Self {
+    Self {
+      size_mode,
+      extensions: HashMap::new(),
+      categories: Default::default(),
+      histogram: Default::default(),
+      largest: Largest::new(MAX_TOP_FILES),
+      fullest: Largest::new(MAX_TOP_DIRS),
+      started_at: super::unix_seconds(std::time::SystemTime::now()),
+      old: OLD_FILE_AGES.iter().map(|_| Largest::new(MAX_OLD_FILES)).collect(),
+    }
+  }
+
+  pub(super) fn add_file(&mut self, path: &Path, sizes: FileSizes, facts: &FileFacts) {
+    let size = self.size_mode.of_sizes(sizes);
+    self.largest.offer(size, || (sizes.apparent, sizes.allocated, path.to_path_buf()));
+    if let Some(mtime) = facts.mtime {
+      let age = (self.started_at.saturating_sub(mtime) / DAY_SECS).max(0) as u64;
+      let past = OLD_FILE_AGES.partition_point(|&days| days <= age);
+      let file = || (sizes.apparent, sizes.allocated, mtime, facts.atime, path.to_path_buf());
+      for old in &mut self.old[..past] {
+        old.offer(size, file);
+      }
+    }
+    let bucket = SIZE_BUCKET_BOUNDS.partition_point(|&bound| bound <= sizes.apparent);
+    let (files, bytes) = &mut self.histogram[bucket];
+    *files += 1;
+    *bytes = bytes.saturating_add(sizes.apparent);
+    let extension = path
+      .extension()
+      .map_or_else(String::new, |ext| ext.to_string_lossy().to_lowercase());
+    let category = FileCategory::of_extension(&extension);
+    for (total, files) in [
+      self.extensions.entry(extension).or_default(),
+      &mut self.categories[category as usize],
+    ] {
+      total.add(sizes);
+      *files += 1;
+    }
+  }
+
+  /// Records a directory read with `entries` directly inside.
+  pub(super) fn add_dir(&mut self, path: &Path, entries: u64) {
+    self.fullest.offer(entries, || path.to_path_buf());
+  }
+
+  /// The largest files, largest first.
+  pub(super) fn largest(&self) -> Vec<FileEntry> {
+    self
+      .largest
+      .sorted()
+      .into_iter()
+      .map(|(_, (apparent, allocated, path))| FileEntry {
+        path: path.to_string_lossy().to_string(),
+        size: *apparent,
+        allocated: *allocated,
+      })
+      .collect()
+  }
+
+  pub(super) fn old_files(&self) -> OldFiles {
+    let by_age = self.old.iter().map(|old| {
+      old
+        .sorted()
+        .into_iter()
+        .map(|(_, (apparent, allocated, mtime, atime, path))| OldFile {
+          path: path.to_string_lossy().to_string(),
+          size: *apparent,
+          allocated: *allocated,
+          mtime: *mtime,
+          atime: *atime,
+        })
+        .collect()
+    });
+    OldFiles {
+      scanned_at: self.started_at,
+      by_age: by_age.collect(),
+    }
+  }
+
+  /// The directories with the most entries, fullest first.
+  pub(super) fn fullest(&self) -> Vec<DirCount> {
+    self
+      .fullest
+      .sorted()
+      .into_iter()
+      .map(|(entries, path)| DirCount {
+        path: path.to_string_lossy().to_string(),
+        entries: *entries,
+      })
+      .collect()
+  }
+
+  /// How many files fall in each size bucket, and the bytes they add up to.
+  pub(super) fn histogram(&self) -> Vec<SizeBucket> {
+    self
+      .histogram
+      .iter()
+      .enumerate()
+      .map(|(i, &(files, bytes))| SizeBucket {
+        min: if i == 0 { 0 } else { SIZE_BUCKET_BOUNDS[i - 1] },
+        max: SIZE_BUCKET_BOUNDS.get(i).copied(),
+        files,
+        bytes,
+      })
+      .collect()
+  }
+
+  /// Every category with files, largest first.
+  pub(super) fn categories(&self) -> Vec<CategoryStat> {
+    let mut stats: Vec<_> = FileCategory::ALL
+      .into_iter()
+      .zip(self.categories)
+      .filter(|&(_, (_, files))| files > 0)
+      .map(|(category, (total, files))| CategoryStat {
+        category,
+        bytes: total.apparent,
+        allocated: total.allocated,
+        files,
+      })
+      .collect();
+    stats.sort_by_key(|stat| std::cmp::Reverse(stat.bytes));
+    stats
+  }
+
+  /// Every extension seen, largest first.
+  pub(super) fn extensions(&self) -> Vec<ExtensionStat> {
+    let mut stats: Vec<_> = self
+      .extensions
+      .iter()
+      .map(|(extension, (total, files))| ExtensionStat {
+        extension: extension.clone(),
+        bytes: total.apparent,
+        allocated: total.allocated,
+        files: *files,
+      })
+      .collect();
+    stats.sort_by(|a, b| b.bytes.cmp(&a.bytes).then_with(|| a.extension.cmp(&b.extension)));
+    stats
+  }
+}
+
+/// The largest extensions of `extensions`, which are sorted largest first.
+pub(super) fn extension_summary(extensions: &[ExtensionStat]) -> Vec<ExtensionStat> {
+  extensions.iter().take(SUMMARY_EXTENSIONS).cloned().collect()
+}
+
+/// Bytes and file counts per extension over a whole finished scan, largest
+/// first.
+#[tauri::command]
+pub async fn get_extension_stats(
+  state: State<'_, ScanManager>,
+  scan_id: String,
+) -> Result<Vec<ExtensionStat>, String> {
+  Ok(state.results(&scan_id)?.extensions.clone())
+}
+
+/// The `n` largest files of a finished scan in its size mode, largest first,
+/// wherever they are in the tree; at most 1000.
+#[tauri::command]
+pub async fn get_top_files(
+  state: State<'_, ScanManager>,
+  scan_id: String,
+  n: usize,
+) -> Result<Vec<FileEntry>, String> {
+  let results = state.results(&scan_id)?;
+  Ok(results.top_files.iter().take(n).cloned().collect())
+}
+
+/// The largest files of a finished scan not modified in the
+/// `older_than_days` before it ran, and with `unaccessed` not accessed
+/// either, of at least `min_size` bytes; largest first in the scan's size
+/// mode. Files without an access time count as unaccessed. Drawn from the
+/// 1000 largest files past the nearest shorter age of 30, 90, 180, 365, 730
+/// or 1825 days, or of all files, so windows in between may list fewer.
+#[tauri::command]
+pub async fn get_old_files(
+  state: State<'_, ScanManager>,
+  scan_id: String,
+  older_than_days: u64,
+  min_size: u64,
+  unaccessed: Option<bool>,
+) -> Result<Vec<OldFile>, String> {
+  let results = state.results(&scan_id)?;
+  Ok(results.old_files.older_than(older_than_days, min_size, unaccessed.unwrap_or(false)))
+}
+
+/// The `n` directories of a finished scan with the most files and
+/// subdirectories directly inside, fullest first, wherever they are in the
+/// tree; at most 1000.
+#[tauri::command]
+pub async fn get_top_dirs_by_count(
+  state: State<'_, ScanManager>,
+  scan_id: String,
+  n: usize,
+) -> Result<Vec<DirCount>, String> {
+  let results = state.results(&scan_id)?;
+  Ok(results.top_dirs.iter().take(n).cloned().collect())
+}
+
+/// Bytes and file counts per `FileCategory` over a whole finished scan,
+/// largest first. Directory nodes carry the same figures for their subtree
+/// in `categories`.
+#[tauri::command]
+pub async fn get_category_stats(
+  state: State<'_, ScanManager>,
+  scan_id: String,
+) -> Result<Vec<CategoryStat>, String> {
+  Ok(state.results(&scan_id)?.categories.clone())
+}
@@ -0,0 +1,201 @@
+//! Files the OS keeps for virtual memory and hibernation: `pagefile.sys`,
+//! `swapfile.sys` and `hiberfil.sys` on Windows, swap files and partitions on
+//! Linux, and the swap files and sleep image of macOS. They are hidden or
+//! locked, so a scan tends to miss them, yet often take several GB.
+
+use super::blocking;
+use serde::Serialize;
+use std::path::Path;
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+// Each OS has only some of them.
+#[allow(dead_code)]
+pub enum SystemFileKind {
+  Pagefile,
+  /// `swapfile.sys` on Windows, which holds suspended apps; swap files
+  /// elsewhere.
+  Swapfile,
+  SwapPartition,
+  Hibernation,
+}
+
+#[derive(Clone, Serialize)]
+pub struct SystemFile {
+  pub kind: SystemFileKind,
+  pub path: String,
+  pub bytes: u64,
+  /// How much of it holds swapped out memory, where the OS says.
+  pub used_bytes: Option<u64>,
+}
+
+/// The size of the file at `path`, from its directory entry: these files are
+/// locked, but the entry can be read.
+fn system_file(kind: SystemFileKind, path: &Path) -> Option<SystemFile> {
+  let md = std::fs::metadata(path).ok()?;
+  Some(SystemFile {
+    kind,
+    path: path.to_string_lossy().to_string(),
+    bytes: md.len(),
+    used_bytes: None,
+  })
+}
+
+#[cfg(windows)]
+fn system_files() -> Vec<SystemFile> {
+  let mut files = Vec::new();
+  for mount in crate::volume::distinct_mount_points() {
+    let root = Path::new(&mount);
+    files.extend(system_file(SystemFileKind::Pagefile, &root.join("pagefile.sys")));
+    files.extend(system_file(SystemFileKind::Swapfile, &root.join("swapfile.sys")));
+    files.extend(system_file(SystemFileKind::Hibernation, &root.join("hiberfil.sys")));
+  }
+  files
+}
+
+/// The swap areas in use, from `/proc/swaps`, and the swap files of
+/// `/etc/fstab` that are switched off.
+#[cfg(target_os = "linux")]
+fn system_files() -> Vec<SystemFile> {
+  let swaps = std::fs::read_to_string("/proc/swaps").unwrap_or_default();
+  // Filename Type Size Used Priority, with sizes in KiB.
+  let mut files: Vec<SystemFile> = swaps
+    .lines()
+    .skip(1)
+    .filter_map(|line| {
+      let fields: Vec<&str> = line.split_whitespace().collect();
+      let [path, kind, size, used, ..] = fields.as_slice() else {
+        return None;
+      };
+      let kind = match *kind {
+        "partition" => SystemFileKind::SwapPartition,
+        _ => SystemFileKind::Swapfile,
+      };
+      // Spaces in the path are escaped as in mountinfo.
+      Some(SystemFile {
+        kind,
+        path: path.replace("\\040", " "),
+        bytes: size.parse::<u64>().ok()? * 1024,
+        used_bytes: used.parse::<u64>().ok().map(|used| used * 1024),
+      })
+    })
+    .collect();
+  let fstab = std::fs::read_to_string("/etc/fstab").unwrap_or_default();
+  for line in fstab.lines().filter(|line| !line.trim_start().starts_with('#')) {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    let [path, _, "swap", ..] = fields.as_slice() else {
+      continue;
+    };
+    let path = path.replace("\\040", " ");
+    if !path.starts_with('/') || files.iter().any(|file| file.path == path) {
+      continue;
+    }
+    let off = system_file(SystemFileKind::Swapfile, Path::new(&path));
+    files.extend(off.filter(|_| Path::new(&path).is_file()));
+  }
+  files
+}
+
+/// The swap files and sleep image in the VM volume, or `/private/var/vm`
+/// before macOS had one.
+#[cfg(target_os = "macos")]
+fn system_files() -> Vec<SystemFile> {
+  let dir = [Path::new("/System/Volumes/VM"), Path::new("/private/var/vm")]
+    .into_iter()
+    .find(|dir| dir.join("sleepimage").exists() || dir.join("swapfile0").exists());
+  let Some(Ok(entries)) = dir.map(std::fs::read_dir) else {
+    return Vec::new();
+  };
+  let mut files: Vec<SystemFile> = entries
+    .flatten()
+    .filter_map(|entry| {
+      let name = entry.file_name();
+      let name = name.to_string_lossy();
+      let kind = match name.as_ref() {
+        "sleepimage" => SystemFileKind::Hibernation,
+        _ if name.starts_with("swapfile") => SystemFileKind::Swapfile,
+        _ => return None,
+      };
+      system_file(kind, &entry.path())
+    })
+    .collect();
+  files.sort_by(|a, b| a.path.cmp(&b.path));
+  files
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+fn system_files() -> Vec<SystemFile> {
+  Vec::new()
+}
+
+/// Runs `powercfg /hibernate off` elevated, which brings up the UAC prompt,
+/// and waits for it.
+#[cfg(windows)]
+fn turn_off_hibernation() -> Result<(), String> {
+  use windows_sys::Win32::{
+    Foundation::{CloseHandle, ERROR_CANCELLED},
+    System::Threading::{GetExitCodeProcess, WaitForSingleObject, INFINITE},
+    UI::Shell::{ShellExecuteExW, SEE_MASK_NOASYNC, SEE_MASK_NOCLOSEPROCESS, SHELLEXECUTEINFOW},
+  };
+  let verb = crate::volume::to_wide(Path::new("runas"));
+  let file = crate::volume::to_wide(Path::new("powercfg.exe"));
+  let params = crate::volume::to_wide(Path::new("/hibernate off"));
+  let mut info = SHELLEXECUTEINFOW {
+    cbSize: std::mem::size_of::<SHELLEXECUTEINFOW>() as u32,
+    fMask: SEE_MASK_NOCLOSEPROCESS | SEE_MASK_NOASYNC,
+    lpVerb: verb.as_ptr(),
+    lpFile: file.as_ptr(),
+    lpParameters: params.as_ptr(),
+    nShow: 0,
+    ..Default::default()
+  };
+  if unsafe { ShellExecuteExW(&mut info) } == 0 {
+    let err = std::io::Error::last_os_error();
+    return Err(match err.raw_os_error() {
+      Some(code) if code == ERROR_CANCELLED as i32 => "Hibernation was left on".to_string(),
+      _ => format!("powercfg: {}", err),
+    });
+  }
+  if info.hProcess.is_null() {
+    return Err("powercfg didn't start".to_string());
+  }
+  let mut code = 0u32;
+  unsafe {
+    WaitForSingleObject(info.hProcess, INFINITE);
+    GetExitCodeProcess(info.hProcess, &mut code);
+    CloseHandle(info.hProcess);
+  }
+  match code {
+    0 => Ok(()),
+    code => Err(format!("powercfg exited with code {}", code)),
+  }
+}
+
+#[cfg(not(windows))]
+fn turn_off_hibernation() -> Result<(), String> {
+  Err("Hibernation can only be turned off from here on Windows".to_string())
+}
+
+fn largest_system_files() -> Vec<SystemFile> {
+  let mut files = system_files();
+  files.sort_by_key(|file| std::cmp::Reverse(file.bytes));
+  files
+}
+
+/// Page, swap and hibernation files on this machine, the largest first.
+#[tauri::command]
+pub async fn get_system_files() -> Result<Vec<SystemFile>, String> {
+  blocking(|| Ok(largest_system_files())).await
+}
+
+/// Turns hibernation off on Windows, which deletes `hiberfil.sys`, and
+/// returns the files left. Fast startup goes with it, since it hibernates
+/// the kernel.
+#[tauri::command]
+pub async fn disable_hibernation() -> Result<Vec<SystemFile>, String> {
+  blocking(|| {
+    turn_off_hibernation()?;
+    Ok(largest_system_files())
+  })
+  .await
+}
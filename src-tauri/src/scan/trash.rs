@@ -0,0 +1,215 @@
+//! What the Recycle Bin or Trash of each volume holds, and emptying it.
+//! Deleted files keep their space until then, and a scan sees them only as a
+//! hidden folder at the volume root, if at all.
+
+use super::blocking;
+use serde::Serialize;
+use std::path::Path;
+#[cfg(unix)]
+use {super::dir_usage, std::path::PathBuf};
+
+#[derive(Clone, Serialize)]
+pub struct TrashBin {
+  /// Where the volume is mounted.
+  pub volume: String,
+  pub path: String,
+  pub bytes: u64,
+  /// Deleted files and folders, each counted once however much it holds.
+  pub items: u64,
+}
+
+/// The Recycle Bin of the current user on each volume, asked of the shell so
+/// that its own count is used.
+#[cfg(windows)]
+fn trash_bins() -> Vec<TrashBin> {
+  let mut bins = Vec::new();
+  for mount in crate::volume::distinct_mount_points() {
+    if let Some((bytes, items)) = query_recycle_bin(&mount) {
+      bins.push(TrashBin {
+        path: Path::new(&mount).join("$Recycle.Bin").to_string_lossy().to_string(),
+        volume: mount,
+        bytes,
+        items,
+      });
+    }
+  }
+  bins
+}
+
+#[cfg(windows)]
+fn query_recycle_bin(mount: &str) -> Option<(u64, u64)> {
+  use windows_sys::Win32::UI::Shell::{SHQueryRecycleBinW, SHQUERYRBINFO};
+  let wide = crate::volume::to_wide(Path::new(mount));
+  let mut info = SHQUERYRBINFO {
+    cbSize: std::mem::size_of::<SHQUERYRBINFO>() as u32,
+    ..Default::default()
+  };
+  if unsafe { SHQueryRecycleBinW(wide.as_ptr(), &mut info) } != 0 {
+    return None;
+  }
+  Some((info.i64Size as u64, info.i64NumItems as u64))
+}
+
+#[cfg(windows)]
+fn empty_bin(bin: &TrashBin) -> Result<(), String> {
+  use windows_sys::Win32::UI::Shell::{
+    SHEmptyRecycleBinW, SHERB_NOCONFIRMATION, SHERB_NOPROGRESSUI, SHERB_NOSOUND,
+  };
+  let wide = crate::volume::to_wide(Path::new(&bin.volume));
+  let flags = SHERB_NOCONFIRMATION | SHERB_NOPROGRESSUI | SHERB_NOSOUND;
+  match unsafe { SHEmptyRecycleBinW(std::ptr::null_mut(), wide.as_ptr(), flags) } {
+    0 => Ok(()),
+    code => Err(format!("{}: error {:#x}", bin.path, code)),
+  }
+}
+
+/// The trash of the current user in the home directory and at the root of
+/// each other volume that has one.
+#[cfg(unix)]
+fn trash_bins() -> Vec<TrashBin> {
+  let mounts = crate::volume::mount_points();
+  let uid = unsafe { libc::getuid() };
+  let mut dirs = Vec::new();
+  if let Some(home) = home_trash() {
+    dirs.push(home);
+  }
+  for mount in &mounts {
+    if cfg!(target_os = "macos") {
+      dirs.push(mount.join(".Trashes").join(uid.to_string()));
+    } else {
+      dirs.push(mount.join(".Trash").join(uid.to_string()));
+      dirs.push(mount.join(format!(".Trash-{}", uid)));
+    }
+  }
+  dirs.dedup();
+  dirs
+    .into_iter()
+    .filter(|dir| dir.is_dir())
+    .map(|dir| {
+      let (bytes, _) = dir_usage(&dir);
+      TrashBin {
+        volume: volume_of(&dir, &mounts).to_string_lossy().to_string(),
+        items: std::fs::read_dir(items_dir(&dir)).map_or(0, |entries| {
+          let names = entries.flatten().map(|entry| entry.file_name());
+          names.filter(|name| name != ".DS_Store").count() as u64
+        }),
+        path: dir.to_string_lossy().to_string(),
+        bytes,
+      }
+    })
+    .collect()
+}
+
+#[cfg(target_os = "macos")]
+fn home_trash() -> Option<PathBuf> {
+  Some(crate::paths::home_dir()?.join(".Trash"))
+}
+
+/// `$XDG_DATA_HOME/Trash`, as the freedesktop.org trash spec has it.
+#[cfg(all(unix, not(target_os = "macos")))]
+fn home_trash() -> Option<PathBuf> {
+  let data_home = std::env::var_os("XDG_DATA_HOME").filter(|dir| !dir.is_empty());
+  let data_home = match data_home {
+    Some(dir) => PathBuf::from(dir),
+    None => crate::paths::home_dir()?.join(".local").join("share"),
+  };
+  Some(data_home.join("Trash"))
+}
+
+/// Where the deleted items themselves are: `files` beside their `info`
+/// records on Linux, the trash itself on macOS.
+#[cfg(unix)]
+fn items_dir(trash: &Path) -> PathBuf {
+  match cfg!(target_os = "macos") {
+    true => trash.to_path_buf(),
+    false => trash.join("files"),
+  }
+}
+
+/// The mount point of the volume holding `path`: the longest one it's under.
+#[cfg(unix)]
+fn volume_of<'a>(path: &Path, mounts: &'a [PathBuf]) -> &'a Path {
+  mounts
+    .iter()
+    .filter(|mount| path.starts_with(mount))
+    .max_by_key(|mount| mount.as_os_str().len())
+    .map_or(Path::new("/"), |mount| mount.as_path())
+}
+
+/// Removes what the trash holds, leaving the trash itself in place. On
+/// Linux that's the deleted items, their `info` records and the size cache.
+#[cfg(unix)]
+fn empty_bin(bin: &TrashBin) -> Result<(), String> {
+  let trash = Path::new(&bin.path);
+  let mut errors = Vec::new();
+  let dirs = match cfg!(target_os = "macos") {
+    true => vec![trash.to_path_buf()],
+    false => vec![trash.join("files"), trash.join("info"), trash.join("expunged")],
+  };
+  for dir in dirs {
+    let Ok(entries) = std::fs::read_dir(&dir) else {
+      continue;
+    };
+    for entry in entries.flatten() {
+      let path = entry.path();
+      let removed = match entry.file_type() {
+        Ok(kind) if kind.is_dir() => std::fs::remove_dir_all(&path),
+        _ => std::fs::remove_file(&path),
+      };
+      if let Err(e) = removed {
+        errors.push(format!("{}: {}", path.display(), e));
+      }
+    }
+  }
+  let _ = std::fs::remove_file(trash.join("directorysizes"));
+  match errors.is_empty() {
+    true => Ok(()),
+    false => Err(errors.join("; ")),
+  }
+}
+
+#[cfg(not(any(unix, windows)))]
+fn trash_bins() -> Vec<TrashBin> {
+  Vec::new()
+}
+
+#[cfg(not(any(unix, windows)))]
+fn empty_bin(_bin: &TrashBin) -> Result<(), String> {
+  Ok(())
+}
+
+/// The bins of every volume, the most bytes first.
+fn largest_bins() -> Vec<TrashBin> {
+  let mut bins = trash_bins();
+  bins.sort_by_key(|bin| std::cmp::Reverse(bin.bytes));
+  bins
+}
+
+/// The Recycle Bin or Trash of the current user on each volume, the most
+/// bytes first.
+#[tauri::command]
+pub async fn get_trash_usage() -> Result<Vec<TrashBin>, String> {
+  blocking(|| Ok(largest_bins())).await
+}
+
+/// Empties the Recycle Bin or Trash on `volume`, or on every volume, for
+/// good, and returns what the bins hold afterwards.
+#[tauri::command]
+pub async fn empty_trash(volume: Option<String>) -> Result<Vec<TrashBin>, String> {
+  blocking(move || {
+    let mut errors = Vec::new();
+    for bin in trash_bins() {
+      if volume.as_ref().is_some_and(|volume| *volume != bin.volume) || bin.items == 0 {
+        continue;
+      }
+      if let Err(e) = empty_bin(&bin) {
+        errors.push(e);
+      }
+    }
+    if !errors.is_empty() {
+      return Err(errors.join("; "));
+    }
+    Ok(largest_bins())
+  })
+  .await
+}
@@ -0,0 +1,95 @@
+//! How fast the folders of a scanned root grow, from the trees the scan
+//! history kept of it. A folder is followed through every scan it's in, so
+//! one that only showed up lately is rated from then on.
+
+use super::{
+  blocking,
+  history::{self, History},
+  NodeKind, ScanNode,
+};
+use serde::Serialize;
+use std::{collections::HashMap, path::Path};
+use tauri::{AppHandle, Manager};
+
+/// The latest scans a trend is worked out from.
+const TREND_SCANS: usize = 30;
+const DEFAULT_TRENDS: usize = 20;
+const SECS_PER_DAY: f64 = 86_400.0;
+
+#[derive(Clone, Copy, Serialize)]
+pub struct TrendPoint {
+  /// When the scan ended, in seconds since the Unix epoch.
+  pub time: i64,
+  pub size: u64,
+}
+
+#[derive(Serialize)]
+pub struct GrowthTrend {
+  pub path: String,
+  /// From the first point to the last.
+  pub growth: i64,
+  pub bytes_per_day: f64,
+  /// Oldest first, one per scan the folder is in.
+  pub points: Vec<TrendPoint>,
+}
+
+/// Adds the size of every folder below `node`, the one at `path`, by path,
+/// to `points`.
+fn collect_dirs(
+  node: &ScanNode,
+  path: &Path,
+  time: i64,
+  points: &mut HashMap<String, Vec<TrendPoint>>,
+) {
+  for child in node.children.iter().flatten() {
+    if matches!(child.kind, NodeKind::Dir) {
+      let path = path.join(&child.name);
+      let point = TrendPoint { time, size: child.size };
+      points.entry(path.to_string_lossy().to_string()).or_default().push(point);
+      collect_dirs(child, &path, time, points);
+    }
+  }
+}
+
+fn trend(path: String, points: Vec<TrendPoint>) -> Option<GrowthTrend> {
+  let (first, last) = (points.first()?, points.last()?);
+  let days = (last.time - first.time) as f64 / SECS_PER_DAY;
+  if days <= 0.0 {
+    return None;
+  }
+  let growth = last.size as i64 - first.size as i64;
+  Some(GrowthTrend {
+    path,
+    growth,
+    bytes_per_day: growth as f64 / days,
+    points,
+  })
+}
+
+/// The folders below `path` that grew fastest across its scans in the
+/// history, `limit` of them (20 unless said). Only scans that kept their
+/// tree count, so there are none until two such scans were made.
+#[tauri::command]
+pub async fn get_growth_trends(
+  app: AppHandle,
+  path: String,
+  limit: Option<usize>,
+) -> Result<Vec<GrowthTrend>, String> {
+  // Up to `TREND_SCANS` stored trees are read and unpacked.
+  blocking(move || {
+    let mut points = HashMap::new();
+    let trees = history::saved_trees(&app, &app.state::<History>(), &path, TREND_SCANS)?;
+    for (time, tree) in trees {
+      collect_dirs(&tree, Path::new(&path), time, &mut points);
+    }
+    let mut trends: Vec<GrowthTrend> = points
+      .into_iter()
+      .filter_map(|(path, points)| trend(path, points))
+      .filter(|trend| trend.growth > 0)
+      .collect();
+    trends.sort_by(|a, b| b.bytes_per_day.total_cmp(&a.bytes_per_day).then(a.path.cmp(&b.path)));
+    trends.truncate(limit.unwrap_or(DEFAULT_TRENDS));
+    Ok(trends)
+  })
+  .await
+}
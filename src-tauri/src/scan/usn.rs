@@ -0,0 +1,218 @@
+//! The NTFS change journal (the USN journal), which logs every file created,
+//! written, renamed or deleted on a volume. A scan notes how far the journal
+//! had got as it started; reading what was logged after that tells which
+//! folders changed since. Opening the journal takes administrator rights,
+//! like reading the MFT.
+
+use super::mft::le;
+use std::{
+  collections::HashSet,
+  fs::File,
+  io,
+  os::windows::{
+    fs::OpenOptionsExt,
+    io::{AsRawHandle, FromRawHandle},
+  },
+  path::{Path, PathBuf},
+};
+use windows_sys::Win32::{
+  Foundation::{HANDLE, INVALID_HANDLE_VALUE},
+  Storage::FileSystem::{
+    FileIdType, GetFinalPathNameByHandleW, OpenFileById, FILE_FLAG_BACKUP_SEMANTICS,
+    FILE_ID_DESCRIPTOR, FILE_ID_DESCRIPTOR_0, FILE_NAME_NORMALIZED, FILE_READ_ATTRIBUTES,
+    FILE_SHARE_DELETE, FILE_SHARE_READ, FILE_SHARE_WRITE, VOLUME_NAME_DOS,
+  },
+  System::{
+    Ioctl::{
+      FSCTL_QUERY_USN_JOURNAL, FSCTL_READ_USN_JOURNAL, READ_USN_JOURNAL_DATA_V0,
+      USN_JOURNAL_DATA_V0,
+    },
+    IO::DeviceIoControl,
+  },
+};
+
+const READ_BUFFER: usize = 64 * 1024;
+/// The smallest record worth looking into: up to its parent reference.
+const RECORD_HEADER: usize = 24;
+
+/// A point in the journal of a volume.
+#[derive(Clone, Copy)]
+pub(super) struct Position {
+  // A journal that's deleted and made again gets a new id, and the old
+  // positions mean nothing in it.
+  journal: u64,
+  next: i64,
+}
+
+/// The journal of one volume, opened through the volume itself.
+struct Journal {
+  volume: File,
+  data: USN_JOURNAL_DATA_V0,
+}
+
+impl Journal {
+  fn open(root: &Path) -> Result<Self, String> {
+    if crate::paths::is_unc(root) {
+      return Err("Network shares keep no change journal to read".to_string());
+    }
+    let mount_point =
+      crate::volume::mount_point(root).ok_or("Can't find the volume of the scan root")?;
+    let device = crate::volume::device_path(&mount_point)
+      .ok_or_else(|| format!("Can't find the device of {}", mount_point))?;
+    let volume = std::fs::OpenOptions::new()
+      .read(true)
+      .share_mode(FILE_SHARE_READ | FILE_SHARE_WRITE)
+      .open(&device)
+      .map_err(|e| match e.kind() {
+        io::ErrorKind::PermissionDenied => {
+          "Reading the change journal takes administrator rights".to_string()
+        }
+        _ => format!("{}: {}", device, e),
+      })?;
+    let mut data = USN_JOURNAL_DATA_V0::default();
+    let mut returned = 0u32;
+    let ok = unsafe {
+      DeviceIoControl(
+        volume.as_raw_handle() as HANDLE,
+        FSCTL_QUERY_USN_JOURNAL,
+        std::ptr::null(),
+        0,
+        &mut data as *mut _ as *mut _,
+        std::mem::size_of::<USN_JOURNAL_DATA_V0>() as u32,
+        &mut returned,
+        std::ptr::null_mut(),
+      )
+    };
+    if ok == 0 {
+      let e = io::Error::last_os_error();
+      return Err(format!("{} has no change journal: {}", mount_point, e));
+    }
+    Ok(Self { volume, data })
+  }
+
+  fn handle(&self) -> HANDLE {
+    self.volume.as_raw_handle() as HANDLE
+  }
+
+  fn position(&self) -> Position {
+    Position {
+      journal: self.data.UsnJournalID,
+      next: self.data.NextUsn,
+    }
+  }
+
+  /// File references of the folders holding the files logged from `since`
+  /// up to where the journal was when it was opened.
+  fn parents_since(&self, since: Position) -> Result<HashSet<u64>, String> {
+    let mut read = READ_USN_JOURNAL_DATA_V0 {
+      StartUsn: since.next,
+      ReasonMask: u32::MAX,
+      UsnJournalID: since.journal,
+      ..Default::default()
+    };
+    // u64 words keep the records 8-byte aligned, as they're written.
+    let mut buf = vec![0u64; READ_BUFFER / 8];
+    let mut parents = HashSet::new();
+    while read.StartUsn < self.data.NextUsn {
+      let mut returned = 0u32;
+      let ok = unsafe {
+        DeviceIoControl(
+          self.handle(),
+          FSCTL_READ_USN_JOURNAL,
+          &read as *const _ as *const _,
+          std::mem::size_of::<READ_USN_JOURNAL_DATA_V0>() as u32,
+          buf.as_mut_ptr() as *mut _,
+          READ_BUFFER as u32,
+          &mut returned,
+          std::ptr::null_mut(),
+        )
+      };
+      if ok == 0 {
+        return Err(format!("Reading the change journal: {}", io::Error::last_os_error()));
+      }
+      let bytes =
+        unsafe { std::slice::from_raw_parts(buf.as_ptr() as *const u8, returned as usize) };
+      // The buffer starts with where the next read should go on from.
+      let Some(next) = le(bytes, 0, 8).map(|next| next as i64) else {
+        break;
+      };
+      let mut at = 8;
+      while at + RECORD_HEADER <= bytes.len() {
+        let length = le(bytes, at, 4).unwrap_or(0) as usize;
+        if length == 0 {
+          break;
+        }
+        // Version 2 records, the ones with 64-bit references, are all NTFS
+        // returns to a version 0 read.
+        if le(bytes, at + 4, 2) == Some(2) {
+          parents.extend(le(bytes, at + 16, 8));
+        }
+        at += length;
+      }
+      if next <= read.StartUsn {
+        break;
+      }
+      read.StartUsn = next;
+    }
+    Ok(parents)
+  }
+
+  /// Where the file with reference `id` is now, unless it's gone.
+  fn path_of(&self, id: u64) -> Option<PathBuf> {
+    let descriptor = FILE_ID_DESCRIPTOR {
+      dwSize: std::mem::size_of::<FILE_ID_DESCRIPTOR>() as u32,
+      Type: FileIdType,
+      Anonymous: FILE_ID_DESCRIPTOR_0 { FileId: id as i64 },
+    };
+    let handle = unsafe {
+      OpenFileById(
+        self.handle(),
+        &descriptor,
+        FILE_READ_ATTRIBUTES,
+        FILE_SHARE_READ | FILE_SHARE_WRITE | FILE_SHARE_DELETE,
+        std::ptr::null(),
+        FILE_FLAG_BACKUP_SEMANTICS,
+      )
+    };
+    if handle == INVALID_HANDLE_VALUE {
+      return None;
+    }
+    // Closes the handle when it goes.
+    let file = unsafe { File::from_raw_handle(handle as _) };
+    let mut buf = vec![0u16; 32 * 1024];
+    let len = unsafe {
+      GetFinalPathNameByHandleW(
+        file.as_raw_handle() as HANDLE,
+        buf.as_mut_ptr(),
+        buf.len() as u32,
+        FILE_NAME_NORMALIZED | VOLUME_NAME_DOS,
+      )
+    } as usize;
+    if len == 0 || len >= buf.len() {
+      return None;
+    }
+    crate::paths::canonicalize(&String::from_utf16_lossy(&buf[..len])).ok()
+  }
+}
+
+/// Where the journal of the volume holding `root` is at, or `None` when it
+/// can't be read.
+pub(super) fn position(root: &Path) -> Option<Position> {
+  Journal::open(root).ok().map(|journal| journal.position())
+}
+
+/// The folders on the volume of `root` that something was created in,
+/// written, renamed or deleted from since `since`, those that still exist,
+/// and where the journal is at now.
+pub(super) fn changed_dirs(
+  root: &Path,
+  since: Position,
+) -> Result<(Vec<PathBuf>, Position), String> {
+  let journal = Journal::open(root)?;
+  if journal.data.UsnJournalID != since.journal || since.next < journal.data.FirstUsn {
+    return Err("The change journal no longer goes back to this scan; scan it again".to_string());
+  }
+  let parents = journal.parents_since(since)?;
+  let dirs = parents.into_iter().filter_map(|id| journal.path_of(id)).collect();
+  Ok((dirs, journal.position()))
+}
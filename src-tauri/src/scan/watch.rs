@@ -0,0 +1,118 @@
+//! Keeping a finished scan current while files change under it, from outside
+//! the app as well: the root is watched through the OS (inotify, FSEvents,
+//! ReadDirectoryChangesW), and once a burst of changes settles, the folders
+//! they touched are walked again and `node_changed` is emitted for each.
+//!
+//! A watch lasts until it's stopped or the scan's results are no longer
+//! kept. On Linux every folder below the root takes an inotify watch, so a
+//! large tree can run into the system's limit; the watch then fails to start.
+
+use super::{refresh, report_error, ScanManager};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use parking_lot::Mutex;
+use std::{
+  collections::{HashMap, HashSet},
+  path::{Path, PathBuf},
+  sync::mpsc::{channel, Receiver, RecvTimeoutError},
+  time::{Duration, Instant},
+};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+/// How long changes are gathered after the first, so an unzip or a build
+/// walks its folders once rather than per file.
+const SETTLE: Duration = Duration::from_secs(2);
+
+/// The watchers of the scans being watched, by scan id. Dropping one ends
+/// its thread.
+#[derive(Default)]
+pub struct Watches {
+  watchers: Mutex<HashMap<String, RecommendedWatcher>>,
+}
+
+/// Starts watching `root` for the kept scan `scan_id`.
+pub(super) fn start(app: &AppHandle, scan_id: &str, root: &Path) -> Result<(), String> {
+  let watches = app.try_state::<Watches>().ok_or("Watching isn't available")?;
+  let (sender, events) = channel();
+  let mut watcher = notify::recommended_watcher(sender).map_err(|e| e.to_string())?;
+  watcher
+    .watch(root, RecursiveMode::Recursive)
+    .map_err(|e| format!("Watching {}: {}", root.display(), e))?;
+  watches.watchers.lock().insert(scan_id.to_string(), watcher);
+  let (app, scan_id) = (app.clone(), scan_id.to_string());
+  std::thread::spawn(move || follow(app, scan_id, events));
+  Ok(())
+}
+
+/// Adds the folders whose listing `event` changed to `changed`. A watcher
+/// error is passed on to the UI.
+fn note(app: &AppHandle, changed: &mut HashSet<PathBuf>, event: notify::Result<Event>) {
+  match event {
+    Ok(event) if matches!(event.kind, EventKind::Access(_)) => {}
+    Ok(event) => {
+      changed.extend(event.paths.iter().filter_map(|path| path.parent()).map(Path::to_path_buf))
+    }
+    Err(e) => report_error(app, e.to_string()),
+  }
+}
+
+/// Walks again what changes, until the watcher is dropped.
+fn follow(app: AppHandle, scan_id: String, events: Receiver<notify::Result<Event>>) {
+  while let Ok(first) = events.recv() {
+    let mut changed = HashSet::new();
+    note(&app, &mut changed, first);
+    let settled = Instant::now() + SETTLE;
+    loop {
+      match events.recv_timeout(settled.saturating_duration_since(Instant::now())) {
+        Ok(event) => note(&app, &mut changed, event),
+        Err(RecvTimeoutError::Timeout) => break,
+        Err(RecvTimeoutError::Disconnected) => return,
+      }
+    }
+    let Some(state) = app.try_state::<ScanManager>() else {
+      return;
+    };
+    let changed: Vec<PathBuf> = changed.into_iter().collect();
+    match refresh::refresh_folders(&state, &scan_id, &changed) {
+      Ok(events) => {
+        for event in events {
+          let _ = app.emit("node_changed", event);
+        }
+      }
+      // The results went, and with them anything to keep current.
+      Err(_) if state.results(&scan_id).is_err() => {
+        if let Some(watches) = app.try_state::<Watches>() {
+          watches.watchers.lock().remove(&scan_id);
+        }
+        return;
+      }
+      Err(e) => report_error(&app, e),
+    }
+  }
+}
+
+/// Keeps the finished scan `scan_id` current from now on, as
+/// `ScanOptions::watch` does for a scan started with it.
+#[tauri::command]
+pub async fn watch_scan(
+  app: AppHandle,
+  state: State<'_, ScanManager>,
+  watches: State<'_, Watches>,
+  scan_id: String,
+) -> Result<(), String> {
+  let results = state.results(&scan_id)?;
+  if !results.local {
+    return Err("Only folders of this machine can be watched".to_string());
+  }
+  if watches.watchers.lock().contains_key(&scan_id) {
+    return Ok(());
+  }
+  start(&app, &scan_id, Path::new(&results.root))
+}
+
+#[tauri::command]
+pub async fn unwatch_scan(watches: State<'_, Watches>, scan_id: String) -> Result<(), String> {
+  match watches.watchers.lock().remove(&scan_id) {
+    Some(_) => Ok(()),
+    None => Err("This scan isn't watched".to_string()),
+  }
+}
@@ -0,0 +1,325 @@
+//! WebDAV folders (Nextcloud, ownCloud, SharePoint, a NAS): walked with one
+//! `PROPFIND` of depth 1 per folder, since most servers refuse infinite
+//! depth. Requests are spaced out to stay under the server's rate limits,
+//! and a server that asks to slow down with 429 or 503 is waited on and
+//! asked less often from then on. Sign-in is Basic, over HTTPS; SharePoint
+//! only takes it where legacy authentication is allowed.
+
+use super::{
+  credentials, dir_node, empty_dir_node, file_node,
+  http::{self, parse_http_date, pause, percent_decode, percent_encode, xml_text},
+  maybe_emit_progress, spawn_scan_from, FileSizes, ScanContext, ScanErrorKind, ScanManager,
+  ScanNode, ScanOptions, ScanSource,
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::Deserialize;
+use std::{
+  path::{Path, PathBuf},
+  sync::atomic::Ordering,
+  time::{Duration, Instant},
+};
+use tauri::{AppHandle, State};
+
+const DEFAULT_REQUESTS_PER_SECOND: f64 = 10.0;
+
+/// Times a server that keeps saying it's busy is waited on for one folder.
+const MAX_RETRIES: u32 = 5;
+
+const PROPFIND: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<d:propfind xmlns:d="DAV:">
+  <d:prop><d:resourcetype/><d:getcontentlength/><d:getlastmodified/></d:prop>
+</d:propfind>"#;
+
+#[derive(Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct WebDavTarget {
+  /// The folder to scan, like
+  /// `https://cloud.example.com/remote.php/dav/files/alice/Photos`.
+  pub url: String,
+  pub user: Option<String>,
+  /// `None` uses the password saved for `user` on this server.
+  pub password: Option<String>,
+  /// Keep `password` in the OS keyring for next time.
+  pub remember: bool,
+  /// At most this many requests a second; 10 when `None`.
+  pub requests_per_second: Option<f64>,
+}
+
+/// A folder to walk, with the password looked up.
+pub(super) struct Server {
+  /// `https://host[:port]`, which hrefs are relative to.
+  origin: String,
+  /// The escaped path of the folder to scan.
+  path: String,
+  /// The `Authorization` header, unless signing in anonymously.
+  authorization: Option<String>,
+  requests_per_second: f64,
+}
+
+/// Spaces requests out.
+struct Throttle {
+  interval: Duration,
+  last: Option<Instant>,
+}
+
+impl Throttle {
+  fn wait(&mut self, ctx: &ScanContext) {
+    if let Some(last) = self.last {
+      pause(ctx, self.interval.saturating_sub(last.elapsed()));
+    }
+    self.last = Some(Instant::now());
+  }
+}
+
+/// An entry of a folder, from its `<response>`.
+struct Entry {
+  /// Escaped, as the server sent it.
+  href: String,
+  name: String,
+  folder: bool,
+  size: u64,
+  mtime: Option<i64>,
+}
+
+/// Walks `server` and builds its tree under `root`, the URL it's shown as.
+/// Folders the server won't list are recorded like local errors.
+pub(super) fn scan(ctx: &ScanContext, server: &Server, root: &Path) -> ScanNode {
+  let agent = http::agent();
+  let mut throttle = Throttle {
+    interval: Duration::from_secs_f64(1.0 / server.requests_per_second),
+    last: None,
+  };
+  let root = root.to_string_lossy();
+  walk(ctx, &agent, server, &mut throttle, &server.path, &root, 0)
+}
+
+fn walk(
+  ctx: &ScanContext,
+  agent: &ureq::Agent,
+  server: &Server,
+  throttle: &mut Throttle,
+  href: &str,
+  path: &str,
+  depth: usize,
+) -> ScanNode {
+  let entries = match list(ctx, agent, server, throttle, href) {
+    Ok(entries) => entries,
+    Err((kind, message)) => {
+      ctx.record_error(Path::new(path), kind, None, &format!("webdav: {}", message));
+      return empty_dir_node(Path::new(path));
+    }
+  };
+  let mut nodes = Vec::with_capacity(entries.len());
+  for entry in entries {
+    if ctx.cancelled() {
+      break;
+    }
+    let entry_path = format!("{}/{}", path, entry.name);
+    if ctx.filters.excludes(Path::new(&entry_path)) {
+      continue;
+    }
+    if entry.folder {
+      nodes.push(walk(ctx, agent, server, throttle, &entry.href, &entry_path, depth + 1));
+      continue;
+    }
+    if !ctx.filters.includes_file(Path::new(&entry_path)) {
+      continue;
+    }
+    ctx.control.scanned_entries.fetch_add(1, Ordering::Relaxed);
+    ctx.control.scanned_bytes.fetch_add(entry.size, Ordering::Relaxed);
+    let sizes = FileSizes {
+      apparent: entry.size,
+      allocated: entry.size,
+    };
+    let mut node = file_node(Path::new(&entry_path), sizes);
+    node.mtime = entry.mtime;
+    nodes.push(node);
+  }
+  maybe_emit_progress(ctx, Path::new(path));
+  let mut node = dir_node(ctx, Path::new(path), nodes, 0);
+  if depth >= ctx.options.max_depth as usize {
+    node.children = None;
+  }
+  node
+}
+
+/// The entries of the folder at `href`, waiting out a busy server.
+fn list(
+  ctx: &ScanContext,
+  agent: &ureq::Agent,
+  server: &Server,
+  throttle: &mut Throttle,
+  href: &str,
+) -> Result<Vec<Entry>, (ScanErrorKind, String)> {
+  let mut retries = 0;
+  loop {
+    throttle.wait(ctx);
+    let mut request = ureq::http::Request::builder()
+      .method("PROPFIND")
+      .uri(format!("{}{}", server.origin, href))
+      .header("Depth", "1")
+      .header("Content-Type", "application/xml; charset=utf-8");
+    if let Some(authorization) = &server.authorization {
+      request = request.header("Authorization", authorization);
+    }
+    let request = request.body(PROPFIND).map_err(|e| (ScanErrorKind::Other, e.to_string()))?;
+    let mut response = agent.run(request).map_err(|e| match e {
+      ureq::Error::Timeout(_) => (ScanErrorKind::IoTimeout, e.to_string()),
+      _ => (ScanErrorKind::Other, e.to_string()),
+    })?;
+    let status = response.status().as_u16();
+    if matches!(status, 429 | 503) && retries < MAX_RETRIES && !ctx.cancelled() {
+      let retry_after = response
+        .headers()
+        .get("retry-after")
+        .and_then(|value| value.to_str().ok()?.trim().parse::<u64>().ok())
+        .unwrap_or(5);
+      retries += 1;
+      throttle.interval = (throttle.interval * 2).min(Duration::from_secs(5));
+      pause(ctx, Duration::from_secs(retry_after.min(60)));
+      continue;
+    }
+    let body = response
+      .body_mut()
+      .with_config()
+      .limit(64 * 1024 * 1024)
+      .read_to_string()
+      .map_err(|e| (ScanErrorKind::Other, e.to_string()))?;
+    return match status {
+      207 => read_multistatus(&body, href).map_err(|e| (ScanErrorKind::Other, e)),
+      401 | 403 => {
+        Err((ScanErrorKind::PermissionDenied, format!("HTTP {}: access denied", status)))
+      }
+      404 => Err((ScanErrorKind::NotFound, "HTTP 404: not found".to_string())),
+      _ => Err((ScanErrorKind::Other, format!("HTTP {}", status))),
+    };
+  }
+}
+
+/// The entries of a `<multistatus>` listing `href`, leaving out the folder
+/// itself.
+fn read_multistatus(xml: &str, href: &str) -> Result<Vec<Entry>, String> {
+  let document =
+    roxmltree::Document::parse(xml).map_err(|e| format!("unreadable listing: {}", e))?;
+  let own = percent_decode(href);
+  let mut entries = Vec::new();
+  for response in document.root_element().children().filter(|node| node.has_tag_name("response")) {
+    let Some(href) = xml_text(response, "href").map(|href| path_of(&href).to_string()) else {
+      continue;
+    };
+    let decoded = percent_decode(&href);
+    if decoded.trim_end_matches('/') == own.trim_end_matches('/') {
+      continue;
+    }
+    let Some(name) = decoded.trim_end_matches('/').rsplit('/').next().map(str::to_string) else {
+      continue;
+    };
+    // Properties a server doesn't have come in a propstat of their own.
+    let found = response.descendants().filter(|node| node.has_tag_name("prop"));
+    let (mut folder, mut size, mut mtime) = (false, 0, None);
+    for prop in found {
+      let kinds = prop.children().filter(|node| node.has_tag_name("resourcetype"));
+      folder |= kinds.flat_map(|kind| kind.children()).any(|kind| kind.has_tag_name("collection"));
+      if let Some(length) = xml_text(prop, "getcontentlength").and_then(|l| l.trim().parse().ok()) {
+        size = length;
+      }
+      mtime = mtime.or_else(|| parse_http_date(&xml_text(prop, "getlastmodified")?));
+    }
+    entries.push(Entry {
+      href,
+      name,
+      folder,
+      size,
+      mtime,
+    });
+  }
+  Ok(entries)
+}
+
+/// The path of an href that may be a whole URL.
+fn path_of(href: &str) -> &str {
+  match href.split_once("://") {
+    Some((_, rest)) => rest.find('/').map_or("/", |slash| &rest[slash..]),
+    None => href,
+  }
+}
+
+fn account(user: &str, origin: &str) -> String {
+  let host = origin.split_once("://").map_or(origin, |(_, host)| host);
+  format!("webdav://{}@{}", user, host.to_lowercase())
+}
+
+/// The origin and the escaped path, ending with `/`, of the folder at
+/// `url`; `https://` is assumed when no scheme is given.
+fn split_url(url: &str) -> Result<(String, String), String> {
+  let url = url.trim();
+  let (scheme, rest) = url.split_once("://").unwrap_or(("https", url));
+  let scheme = scheme.to_lowercase();
+  let (host, path) = rest.split_at(rest.find('/').unwrap_or(rest.len()));
+  if host.is_empty() || !matches!(scheme.as_str(), "http" | "https") {
+    return Err(format!("Not an http(s) address: {}", url));
+  }
+  // Typed or pasted, the path is escaped the same way.
+  let segments: Vec<String> = path
+    .split('/')
+    .filter(|segment| !segment.is_empty())
+    .map(|segment| percent_encode(&percent_decode(segment)))
+    .collect();
+  let path = match segments.is_empty() {
+    true => "/".to_string(),
+    false => format!("/{}/", segments.join("/")),
+  };
+  Ok((format!("{}://{}", scheme, host), path))
+}
+
+/// Walks a WebDAV folder. Returns the scan id; progress and the tree arrive
+/// as for a local scan, under the folder's URL.
+#[tauri::command]
+pub async fn start_webdav_scan(
+  app: AppHandle,
+  state: State<'_, ScanManager>,
+  target: WebDavTarget,
+  options: Option<ScanOptions>,
+) -> Result<String, String> {
+  let (origin, path) = split_url(&target.url)?;
+  let user = target.user.as_deref().map(str::trim).filter(|user| !user.is_empty());
+  let authorization = match user {
+    Some(user) => {
+      let account = account(user, &origin);
+      let password = match target.password.filter(|password| !password.is_empty()) {
+        Some(password) => {
+          if target.remember {
+            credentials::save(&account, &password)?;
+          }
+          password
+        }
+        None => credentials::load(&account)
+          .ok_or_else(|| format!("No password saved for {} on {}", user, origin))?,
+      };
+      Some(format!("Basic {}", STANDARD.encode(format!("{}:{}", user, password))))
+    }
+    None => None,
+  };
+  let requests_per_second = target
+    .requests_per_second
+    .filter(|rate| rate.is_finite() && *rate > 0.0)
+    .unwrap_or(DEFAULT_REQUESTS_PER_SECOND)
+    .max(0.1);
+  let shown = format!("{}{}", origin, percent_decode(path.trim_end_matches('/')));
+  let mut options = options.unwrap_or_default();
+  options.same_filesystem = false;
+  let server = Server {
+    origin,
+    path,
+    authorization,
+    requests_per_second,
+  };
+  spawn_scan_from(&app, &state, PathBuf::from(shown), options, ScanSource::WebDav(server))
+}
+
+/// Forgets the password saved for `user` on the server of `url`.
+#[tauri::command]
+pub async fn forget_webdav_password(url: String, user: String) -> Result<(), String> {
+  let (origin, _) = split_url(&url)?;
+  credentials::forget(&account(user.trim(), &origin))
+}
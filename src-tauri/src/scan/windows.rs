@@ -0,0 +1,437 @@
+//! Windows directory reader: lists a directory through a handle opened with
+//! backup semantics, so a scan holding SeBackupPrivilege can enumerate folders
+//! whose ACLs would otherwise deny it. The listing also carries the sizes and
+//! file ids of every entry, so files don't have to be opened one by one.
+
+use super::{
+  compressed_size, count_file, counted_file_node, dir_id, file_facts, filetime_to_unix, listed_dir,
+  listed_file, metadata_attributes, open_for_query, unreadable_child, windows_attributes,
+  ChildEntry, DirTask, DirVisit, FileCategory, FileFacts, FileSizes, LinkKind, ScanContext,
+  ScanNode, WalkMode, ONLINE_ONLY_ATTRIBUTES,
+};
+use rayon::prelude::*;
+use std::{
+  ffi::OsString,
+  io,
+  os::windows::{ffi::OsStringExt, fs::OpenOptionsExt, io::AsRawHandle},
+  path::{Path, PathBuf},
+  sync::Once,
+};
+use windows_sys::Win32::{
+  Foundation::{GetLastError, ERROR_NO_MORE_FILES, HANDLE, INVALID_HANDLE_VALUE},
+  Storage::FileSystem::{
+    FileIdBothDirectoryInfo, FileStandardInfo, FindClose, FindFirstFileW,
+    GetFileInformationByHandle, GetFileInformationByHandleEx, BY_HANDLE_FILE_INFORMATION,
+    FILE_ATTRIBUTE_COMPRESSED, FILE_ATTRIBUTE_DIRECTORY, FILE_ATTRIBUTE_HIDDEN,
+    FILE_ATTRIBUTE_REPARSE_POINT, FILE_ATTRIBUTE_SPARSE_FILE, FILE_ATTRIBUTE_SYSTEM,
+    FILE_FLAG_BACKUP_SEMANTICS, FILE_ID_BOTH_DIR_INFO, FILE_LIST_DIRECTORY, FILE_STANDARD_INFO,
+    WIN32_FIND_DATAW,
+  },
+  System::SystemServices::{IO_REPARSE_TAG_MOUNT_POINT, IO_REPARSE_TAG_SYMLINK},
+};
+
+const LIST_BUFFER: usize = 64 * 1024;
+/// NTFS metafiles at the root of a volume, which listings leave out, with the
+/// stream holding the data of those that keep it in a named one.
+const ROOT_METAFILES: [(&str, &str); 7] = [
+  ("$MFT", ""),
+  ("$MFTMirr", ""),
+  ("$LogFile", ""),
+  ("$Bitmap", ""),
+  ("$Boot", ""),
+  ("$UpCase", ""),
+  ("$Secure", ":$SDS"),
+];
+/// The change journal keeps its records in `$J`; its unnamed stream is empty.
+const USN_JOURNAL: (&str, &str) = ("$UsnJrnl", ":$J");
+
+/// One entry of a FileIdBothDirectoryInfo listing.
+struct Entry {
+  name: OsString,
+  attributes: u32,
+  reparse_tag: u32,
+  mtime: Option<i64>,
+  atime: Option<i64>,
+  size: u64,
+  allocated: u64,
+  index: u64,
+}
+
+impl Entry {
+  /// Symlinks and junctions, which std also treats as links. Other reparse
+  /// points (cloud files, dedup) are plain files and directories.
+  fn is_link(&self) -> bool {
+    self.attributes & FILE_ATTRIBUTE_REPARSE_POINT != 0
+      && matches!(self.reparse_tag, IO_REPARSE_TAG_SYMLINK | IO_REPARSE_TAG_MOUNT_POINT)
+  }
+}
+
+/// The reparse tag of the entry at `path`, or `None` if it isn't a reparse
+/// point. Read from its directory listing, so the link itself isn't opened.
+pub(super) fn reparse_tag(path: &Path) -> Option<u32> {
+  let wide = crate::volume::to_wide(path);
+  let mut data = WIN32_FIND_DATAW::default();
+  let find = unsafe { FindFirstFileW(wide.as_ptr(), &mut data) };
+  if find == INVALID_HANDLE_VALUE {
+    return None;
+  }
+  unsafe { FindClose(find) };
+  (data.dwFileAttributes & FILE_ATTRIBUTE_REPARSE_POINT != 0).then_some(data.dwReserved0)
+}
+
+/// Enables SeBackupPrivilege for the process, once. Only elevated
+/// administrators hold it; for everyone else this does nothing.
+pub(super) fn enable_backup_privilege() {
+  use windows_sys::Win32::{
+    Foundation::{CloseHandle, LUID},
+    Security::{
+      AdjustTokenPrivileges, LookupPrivilegeValueW, LUID_AND_ATTRIBUTES, SE_BACKUP_NAME,
+      SE_PRIVILEGE_ENABLED, TOKEN_ADJUST_PRIVILEGES, TOKEN_PRIVILEGES,
+    },
+    System::Threading::{GetCurrentProcess, OpenProcessToken},
+  };
+
+  static ENABLE: Once = Once::new();
+  ENABLE.call_once(|| unsafe {
+    let mut token: HANDLE = std::ptr::null_mut();
+    if OpenProcessToken(GetCurrentProcess(), TOKEN_ADJUST_PRIVILEGES, &mut token) == 0 {
+      return;
+    }
+    let mut privileges = TOKEN_PRIVILEGES {
+      PrivilegeCount: 1,
+      Privileges: [LUID_AND_ATTRIBUTES {
+        Luid: LUID::default(),
+        Attributes: SE_PRIVILEGE_ENABLED,
+      }],
+    };
+    if LookupPrivilegeValueW(
+      std::ptr::null(),
+      SE_BACKUP_NAME,
+      &mut privileges.Privileges[0].Luid,
+    ) != 0
+    {
+      // Without the privilege in the token this still succeeds, leaving the
+      // scan with the user's own access.
+      AdjustTokenPrivileges(
+        token,
+        0,
+        &privileges,
+        0,
+        std::ptr::null_mut(),
+        std::ptr::null_mut(),
+      );
+    }
+    CloseHandle(token);
+  });
+}
+
+/// Reads the directory of `task` like the portable `visit_dir`, or `None` when
+/// it can't be opened or listed this way and the portable path has to try.
+pub(super) fn visit_dir(ctx: &ScanContext, task: &DirTask) -> Option<DirVisit> {
+  let dir = std::fs::OpenOptions::new()
+    .access_mode(FILE_LIST_DIRECTORY)
+    .custom_flags(FILE_FLAG_BACKUP_SEMANTICS)
+    .open(&task.path)
+    .ok()?;
+  let handle = dir.as_raw_handle() as HANDLE;
+  let mut info = BY_HANDLE_FILE_INFORMATION::default();
+  if unsafe { GetFileInformationByHandle(handle, &mut info) } == 0 {
+    return None;
+  }
+  let volume = info.dwVolumeSerialNumber as u64;
+  let mut entries = read_entries(handle).ok()?;
+
+  let listed = entries.len() as u64;
+  add_metafiles(task, &mut entries);
+  let mut children = Vec::with_capacity(entries.len());
+  for entry in entries {
+    let path = task.path.join(&entry.name);
+    if ctx.filters.excludes(&path) {
+      continue;
+    }
+    let mut mode = task.mode;
+    let hidden = entry.attributes & (FILE_ATTRIBUTE_HIDDEN | FILE_ATTRIBUTE_SYSTEM) != 0;
+    if mode != WalkMode::Hidden && !ctx.options.include_hidden && hidden {
+      if !ctx.options.track_hidden_bytes {
+        continue;
+      }
+      mode = WalkMode::Hidden;
+    }
+    let is_dir = entry.attributes & FILE_ATTRIBUTE_DIRECTORY != 0;
+    let mode = ctx.ignore_mode(&path, is_dir, mode);
+    children.push((entry, path, mode));
+  }
+
+  let depth = task.depth + 1;
+  let entries: Vec<(ChildEntry, WalkMode)> = children
+    .into_par_iter()
+    .map(|(entry, path, mode)| (visit_entry(ctx, &entry, &path, volume, depth, mode), mode))
+    .collect();
+  let mut visit = DirVisit {
+    listed: Some(listed),
+    ..Default::default()
+  };
+  for (entry, mode) in entries {
+    visit.add(entry, mode);
+  }
+  Some(visit)
+}
+
+fn visit_entry(
+  ctx: &ScanContext,
+  entry: &Entry,
+  path: &Path,
+  volume: u64,
+  depth: u32,
+  mode: WalkMode,
+) -> ChildEntry {
+  if ctx.cancelled() {
+    return ChildEntry::Skipped;
+  }
+  let link_kind = match entry.is_link() {
+    true => ctx.reparse_link_kind(path, entry.reparse_tag),
+    false => LinkKind::None,
+  };
+  // A followed link takes the type and size of its target, which the
+  // listing doesn't have.
+  if !link_kind.is_none() && ctx.follows(link_kind) {
+    let md = match std::fs::metadata(path) {
+      Ok(m) => m,
+      Err(e) => {
+        ctx.note_broken_link(path, &e, mode);
+        return unreadable_child(ctx, path.to_path_buf(), &e, mode);
+      }
+    };
+    if md.is_dir() {
+      let attributes = metadata_attributes(path, &md);
+      return listed_dir(ctx, path.to_path_buf(), dir_id(path, &md), attributes, depth, mode);
+    }
+    if !ctx.filters.includes_file(path) {
+      return ChildEntry::Skipped;
+    }
+    return match mode {
+      WalkMode::Build => {
+        let mut node = counted_file_node(ctx, path, &md);
+        node.link_kind = link_kind;
+        ChildEntry::Node(Box::new(node))
+      }
+      _ => {
+        let facts = file_facts(ctx, path, &md);
+        ChildEntry::File(count_file(ctx, path, &md, &facts, mode), facts)
+      }
+    };
+  }
+
+  if !link_kind.is_none() {
+    ctx.check_link(path, mode);
+  }
+  if entry.attributes & FILE_ATTRIBUTE_DIRECTORY != 0 && link_kind.is_none() {
+    let id = Some((volume, entry.index));
+    let attributes = windows_attributes(path, entry.attributes);
+    return listed_dir(ctx, path.to_path_buf(), id, attributes, depth, mode);
+  }
+  if !ctx.filters.includes_file(path) {
+    return ChildEntry::Skipped;
+  }
+  // Placeholders keep their logical size but take no local space, and are
+  // never opened, which could download them.
+  let online_only = entry.attributes & ONLINE_ONLY_ATTRIBUTES != 0;
+  let compressed = entry.attributes & FILE_ATTRIBUTE_COMPRESSED != 0;
+  // The listing gives a compressed file the allocation of its uncompressed data.
+  let allocated = match (online_only, compressed) {
+    (true, _) => 0,
+    (false, true) => compressed_size(path).unwrap_or(entry.allocated),
+    (false, false) => entry.allocated,
+  };
+  let sizes = FileSizes {
+    apparent: entry.size,
+    allocated,
+  };
+  // The listing has no link count, so only look it up when hardlinks are
+  // being deduplicated.
+  let linked = ctx.seen_files.is_some() && !online_only && link_count(path) > 1;
+  let id = linked.then_some((volume, entry.index));
+  let facts = FileFacts {
+    category: FileCategory::of(path),
+    mtime: entry.mtime,
+    atime: entry.atime,
+    owner: ctx.owner_of_path(path),
+  };
+  let mut child = listed_file(ctx, path, sizes, id, facts, mode);
+  if let ChildEntry::Node(node) = &mut child {
+    node.online_only = online_only;
+    node.compressed = compressed;
+    node.sparse = entry.attributes & FILE_ATTRIBUTE_SPARSE_FILE != 0;
+    node.link_kind = link_kind;
+    node.set_attributes(windows_attributes(path, entry.attributes));
+  }
+  child
+}
+
+/// Adds the metafiles of a scanned volume root, and `$Extend` where they
+/// continue, to its `entries`, and gives the change journal in `$Extend` the
+/// size of its records. Their sizes come from opening them for query, which
+/// only an elevated scan holding SeBackupPrivilege can; they're left out
+/// otherwise.
+fn add_metafiles(task: &DirTask, entries: &mut Vec<Entry>) {
+  let in_extend = task.depth == 1
+    && task.path.file_name().is_some_and(|name| name == "$Extend")
+    && task.path.parent().is_some_and(crate::volume::is_volume_root);
+  if in_extend {
+    let journal = entries.iter_mut().find(|entry| entry.name == USN_JOURNAL.0);
+    if let Some(entry) = journal {
+      let stream = format!("{}{}", USN_JOURNAL.0, USN_JOURNAL.1);
+      if let Some((size, allocated)) = stream_sizes(&task.path.join(stream)) {
+        entry.size = size;
+        entry.allocated = allocated;
+      }
+    }
+    return;
+  }
+  if task.depth != 0 || !crate::volume::is_volume_root(&task.path) {
+    return;
+  }
+  for (name, stream) in ROOT_METAFILES {
+    let path = task.path.join(format!("{}{}", name, stream));
+    let Some((size, allocated)) = stream_sizes(&path) else {
+      continue;
+    };
+    entries.push(Entry {
+      name: name.into(),
+      attributes: FILE_ATTRIBUTE_HIDDEN | FILE_ATTRIBUTE_SYSTEM,
+      reparse_tag: 0,
+      mtime: None,
+      atime: None,
+      size,
+      allocated,
+      index: 0,
+    });
+  }
+  let extend = task.path.join("$Extend");
+  let listed = entries.iter().any(|entry| entry.name == "$Extend");
+  if !listed && open_for_query(&extend).is_ok() {
+    entries.push(Entry {
+      name: "$Extend".into(),
+      attributes: FILE_ATTRIBUTE_DIRECTORY | FILE_ATTRIBUTE_HIDDEN | FILE_ATTRIBUTE_SYSTEM,
+      reparse_tag: 0,
+      mtime: None,
+      atime: None,
+      size: 0,
+      allocated: 0,
+      // Its MFT record, which never changes.
+      index: 11,
+    });
+  }
+}
+
+/// Bytes the metafiles of the volume mounted at `root` take that `tree`
+/// doesn't have, as far as they can be opened.
+pub(super) fn metafile_bytes(root: &Path, tree: &ScanNode) -> u64 {
+  let extend = root.join("$Extend");
+  let mut files: Vec<(PathBuf, PathBuf)> = ROOT_METAFILES
+    .iter()
+    .map(|(name, stream)| (root.join(name), root.join(format!("{}{}", name, stream))))
+    .collect();
+  let journal = format!("{}{}", USN_JOURNAL.0, USN_JOURNAL.1);
+  files.push((extend.clone(), extend.join(journal)));
+  files
+    .into_iter()
+    .filter(|(node, _)| tree.find(root, node).is_none())
+    .filter_map(|(_, stream)| stream_sizes(&stream))
+    .map(|(_, allocated)| allocated)
+    .sum()
+}
+
+/// (size, allocated) of the file or stream at `path`.
+fn stream_sizes(path: &Path) -> Option<(u64, u64)> {
+  let file = open_for_query(path).ok()?;
+  let mut standard = FILE_STANDARD_INFO::default();
+  let ok = unsafe {
+    GetFileInformationByHandleEx(
+      file.as_raw_handle() as HANDLE,
+      FileStandardInfo,
+      &mut standard as *mut _ as *mut _,
+      std::mem::size_of::<FILE_STANDARD_INFO>() as u32,
+    )
+  };
+  (ok != 0).then(|| (standard.EndOfFile.max(0) as u64, standard.AllocationSize.max(0) as u64))
+}
+
+fn link_count(path: &Path) -> u32 {
+  let Ok(file) = open_for_query(path) else {
+    return 1;
+  };
+  let mut standard = FILE_STANDARD_INFO::default();
+  let ok = unsafe {
+    GetFileInformationByHandleEx(
+      file.as_raw_handle() as HANDLE,
+      FileStandardInfo,
+      &mut standard as *mut _ as *mut _,
+      std::mem::size_of::<FILE_STANDARD_INFO>() as u32,
+    )
+  };
+  if ok == 0 {
+    return 1;
+  }
+  standard.NumberOfLinks
+}
+
+/// Every entry of the open directory `dir`, without `.` and `..`.
+fn read_entries(dir: HANDLE) -> io::Result<Vec<Entry>> {
+  // u64 words keep the buffer aligned for the records, which are 8-byte aligned.
+  let mut buf = vec![0u64; LIST_BUFFER / 8];
+  let mut entries = Vec::new();
+  loop {
+    let ok = unsafe {
+      GetFileInformationByHandleEx(
+        dir,
+        FileIdBothDirectoryInfo,
+        buf.as_mut_ptr() as *mut _,
+        LIST_BUFFER as u32,
+      )
+    };
+    if ok == 0 {
+      let err = unsafe { GetLastError() };
+      if err == ERROR_NO_MORE_FILES {
+        return Ok(entries);
+      }
+      return Err(io::Error::from_raw_os_error(err as i32));
+    }
+
+    let base = buf.as_ptr() as *const u8;
+    let name_offset = std::mem::offset_of!(FILE_ID_BOTH_DIR_INFO, FileName);
+    let mut offset = 0;
+    loop {
+      if offset + std::mem::size_of::<FILE_ID_BOTH_DIR_INFO>() > LIST_BUFFER {
+        return Err(io::Error::from(io::ErrorKind::InvalidData));
+      }
+      let record = unsafe { base.add(offset) } as *const FILE_ID_BOTH_DIR_INFO;
+      let info = unsafe { record.read_unaligned() };
+      let name_len = info.FileNameLength as usize / 2;
+      if offset + name_offset + name_len * 2 > LIST_BUFFER {
+        return Err(io::Error::from(io::ErrorKind::InvalidData));
+      }
+      let name = unsafe {
+        std::slice::from_raw_parts(base.add(offset + name_offset) as *const u16, name_len)
+      };
+      if name != [b'.' as u16] && name != [b'.' as u16, b'.' as u16] {
+        let reparse = info.FileAttributes & FILE_ATTRIBUTE_REPARSE_POINT != 0;
+        entries.push(Entry {
+          name: OsString::from_wide(name),
+          attributes: info.FileAttributes,
+          // For reparse points the EA size field holds the reparse tag.
+          reparse_tag: if reparse { info.EaSize } else { 0 },
+          mtime: filetime_to_unix(info.LastWriteTime),
+          atime: filetime_to_unix(info.LastAccessTime),
+          size: info.EndOfFile.max(0) as u64,
+          allocated: info.AllocationSize.max(0) as u64,
+          index: info.FileId as u64,
+        });
+      }
+      if info.NextEntryOffset == 0 {
+        break;
+      }
+      offset += info.NextEntryOffset as usize;
+    }
+  }
+}
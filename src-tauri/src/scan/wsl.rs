@@ -0,0 +1,316 @@
+//! WSL distributions on Windows. Their files can be scanned from Windows
+//! through `\\wsl$`, which only sees what the 9P server passes on, or listed
+//! by `find` running as root inside the distribution, which has the real
+//! sizes, allocation and hard links. WSL 2 keeps each distribution in a
+//! virtual disk that grows but never shrinks on its own, so its size is
+//! reported next to the space the distribution uses.
+
+use super::{
+  blocking, dir_node, empty_dir_node, file_node, spawn_scan_from, FileSizes, ScanContext,
+  ScanErrorKind, ScanManager, ScanNode, ScanOptions, ScanSource, SPARSE_MIN_HOLE,
+};
+use serde::Serialize;
+use std::{
+  collections::{HashMap, HashSet},
+  path::Path,
+};
+use tauri::{AppHandle, State};
+
+/// What `find` prints per entry: type, links, inode, size, 512-byte blocks,
+/// modification time and the path below `/`.
+pub(super) const FIND_FORMAT: &str = "%y\\t%n\\t%i\\t%s\\t%b\\t%T@\\t%P\\0";
+
+#[derive(Clone, Serialize)]
+pub struct WslDistro {
+  pub name: String,
+  /// 1 or 2.
+  pub version: u32,
+  pub default: bool,
+  /// Where Windows reaches its files while it runs.
+  pub path: String,
+  /// The virtual disk of a WSL 2 distribution.
+  pub vhdx_path: Option<String>,
+  pub vhdx_bytes: Option<u64>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct WslUsage {
+  /// Space used inside the distribution's root filesystem.
+  pub used_bytes: u64,
+  pub total_bytes: u64,
+  pub vhdx_bytes: Option<u64>,
+}
+
+/// One line of the `find` listing.
+struct Listed<'a> {
+  dir: bool,
+  links: u64,
+  inode: u64,
+  size: u64,
+  blocks: u64,
+  mtime: Option<i64>,
+  path: &'a str,
+}
+
+/// The WSL distributions registered for the current user.
+#[tauri::command]
+pub async fn list_wsl_distros() -> Result<Vec<WslDistro>, String> {
+  Ok(distros())
+}
+
+fn distros() -> Vec<WslDistro> {
+  #[cfg(windows)]
+  return registry::distros();
+  #[cfg(not(windows))]
+  Vec::new()
+}
+
+/// How much of its root filesystem the distribution `name` uses, next to the
+/// size of its virtual disk. Starts the distribution if it isn't running.
+#[tauri::command]
+pub async fn get_wsl_usage(name: String) -> Result<WslUsage, String> {
+  // Starting the distribution can take seconds.
+  blocking(move || {
+    let output = run_inside(&name, &["df", "-B1", "--output=used,size", "/"])?;
+    let output = String::from_utf8_lossy(&output);
+    // A header line, then "  <used> <size>".
+    let mut numbers = output.lines().nth(1).unwrap_or_default().split_whitespace();
+    let mut next = || numbers.next().and_then(|n| n.parse().ok());
+    let (Some(used_bytes), Some(total_bytes)) = (next(), next()) else {
+      return Err(format!("Unexpected df output: {}", output.trim()));
+    };
+    let vhdx_bytes = distros()
+      .into_iter()
+      .find(|distro| distro.name == name)
+      .and_then(|distro| distro.vhdx_bytes);
+    Ok(WslUsage {
+      used_bytes,
+      total_bytes,
+      vhdx_bytes,
+    })
+  })
+  .await
+}
+
+/// Scans the distribution `name`, listed from inside it, rooted at its
+/// `\\wsl$` share. Only its root filesystem is listed, so Windows drives
+/// mounted in it are left out. Returns the scan id; the tree arrives as for
+/// a local scan.
+#[tauri::command]
+pub async fn scan_wsl_distro(
+  app: AppHandle,
+  state: State<'_, ScanManager>,
+  name: String,
+  options: Option<ScanOptions>,
+) -> Result<String, String> {
+  let root = Path::new(r"\\wsl$").join(&name);
+  spawn_scan_from(&app, &state, root, options.unwrap_or_default(), ScanSource::Wsl(name))
+}
+
+/// Lists the distribution `name` from inside and builds its tree under
+/// `root`. When it can't be listed, that's recorded against the root.
+pub(super) fn scan(ctx: &ScanContext, name: &str, root: &Path) -> ScanNode {
+  match run_inside(name, &["find", "/", "-xdev", "-printf", FIND_FORMAT]) {
+    Ok(listing) => read_listing(ctx, root, &listing),
+    Err(e) => {
+      ctx.record_error(root, ScanErrorKind::Other, None, &e);
+      empty_dir_node(root)
+    }
+  }
+}
+
+/// Runs `args` as root inside the distribution `name` and returns what it
+/// printed. A listing cut short by unreadable entries still counts.
+fn run_inside(name: &str, args: &[&str]) -> Result<Vec<u8>, String> {
+  #[cfg(windows)]
+  {
+    use std::os::windows::process::CommandExt;
+    const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+    let output = std::process::Command::new("wsl.exe")
+      .args(["--distribution", name, "--user", "root", "--exec"])
+      .args(args)
+      .creation_flags(CREATE_NO_WINDOW)
+      .output()
+      .map_err(|e| format!("wsl.exe: {}", e))?;
+    if !output.status.success() && output.stdout.is_empty() {
+      // wsl.exe itself reports in UTF-16.
+      let units: Vec<u16> =
+        output.stderr.chunks_exact(2).map(|unit| u16::from_le_bytes([unit[0], unit[1]])).collect();
+      let message = String::from_utf16_lossy(&units).replace('\0', "");
+      return Err(format!("{}: {}", name, message.trim()));
+    }
+    Ok(output.stdout)
+  }
+  #[cfg(not(windows))]
+  {
+    let _ = args;
+    Err(format!("{}: WSL is only available on Windows", name))
+  }
+}
+
+/// Builds the tree under `root` from a `find` listing.
+pub(super) fn read_listing(ctx: &ScanContext, root: &Path, listing: &[u8]) -> ScanNode {
+  let entries: Vec<Listed> = listing
+    .split(|&byte| byte == 0)
+    .filter_map(|line| parse_line(std::str::from_utf8(line).ok()?))
+    .collect();
+  let mut children: HashMap<&str, Vec<usize>> = HashMap::new();
+  for (i, entry) in entries.iter().enumerate().filter(|(_, entry)| !entry.path.is_empty()) {
+    let parent = entry.path.rsplit_once('/').map_or("", |(parent, _)| parent);
+    children.entry(parent).or_default().push(i);
+  }
+  let mut seen = ctx.options.dedupe_hardlinks.then(HashSet::new);
+  read_dir(ctx, &entries, &children, "", root, 0, &mut seen)
+}
+
+fn parse_line(line: &str) -> Option<Listed<'_>> {
+  let mut fields = line.splitn(7, '\t');
+  let kind = fields.next()?;
+  let mut number = || fields.next()?.parse::<u64>().ok();
+  let (links, inode, size, blocks) = (number()?, number()?, number()?, number()?);
+  let mtime = fields.next()?.split('.').next()?.parse().ok();
+  let path = fields.next()?;
+  // Links, sockets and devices take no space of their own.
+  matches!(kind, "d" | "f").then_some(Listed {
+    dir: kind == "d",
+    links,
+    inode,
+    size,
+    blocks,
+    mtime,
+    path,
+  })
+}
+
+fn read_dir(
+  ctx: &ScanContext,
+  entries: &[Listed],
+  children: &HashMap<&str, Vec<usize>>,
+  dir: &str,
+  path: &Path,
+  depth: usize,
+  seen: &mut Option<HashSet<u64>>,
+) -> ScanNode {
+  let Some(listed) = children.get(dir) else {
+    return empty_dir_node(path);
+  };
+  let mut nodes = Vec::with_capacity(listed.len());
+  for &i in listed {
+    if ctx.cancelled() {
+      break;
+    }
+    let entry = &entries[i];
+    let name = entry.path.rsplit('/').next().unwrap_or(entry.path);
+    let child_path = path.join(name);
+    if entry.dir {
+      nodes.push(read_dir(ctx, entries, children, entry.path, &child_path, depth + 1, seen));
+      continue;
+    }
+    if let Some(seen) = seen.as_mut().filter(|_| entry.links > 1) {
+      if !seen.insert(entry.inode) {
+        continue;
+      }
+    }
+    let sizes = FileSizes {
+      apparent: entry.size,
+      allocated: entry.blocks * 512,
+    };
+    let mut node = file_node(&child_path, sizes);
+    node.sparse = sizes.allocated.saturating_add(SPARSE_MIN_HOLE) <= sizes.apparent;
+    node.mtime = entry.mtime;
+    nodes.push(node);
+  }
+  let mut node = dir_node(ctx, path, nodes, 0);
+  if depth >= ctx.options.max_depth as usize {
+    node.children = None;
+  }
+  node
+}
+
+#[cfg(windows)]
+mod registry {
+  use super::WslDistro;
+  use crate::volume::to_wide;
+  use std::{
+    path::{Path, PathBuf},
+    ptr::{null, null_mut},
+  };
+  use windows_sys::Win32::System::Registry::{
+    RegCloseKey, RegEnumKeyExW, RegGetValueW, RegOpenKeyExW, HKEY, HKEY_CURRENT_USER, KEY_READ,
+    RRF_RT_REG_DWORD, RRF_RT_REG_SZ,
+  };
+
+  const LXSS: &str = r"Software\Microsoft\Windows\CurrentVersion\Lxss";
+
+  /// The distributions under the Lxss key, one subkey per distribution.
+  pub(super) fn distros() -> Vec<WslDistro> {
+    let mut key: HKEY = null_mut();
+    let lxss = to_wide(Path::new(LXSS));
+    if unsafe { RegOpenKeyExW(HKEY_CURRENT_USER, lxss.as_ptr(), 0, KEY_READ, &mut key) } != 0 {
+      return Vec::new();
+    }
+    let default = string(key, None, "DefaultDistribution");
+    let mut distros = Vec::new();
+    for index in 0.. {
+      let mut id = [0u16; 64];
+      let mut len = id.len() as u32;
+      let (class, written) = (null_mut(), null_mut());
+      let status = unsafe {
+        RegEnumKeyExW(key, index, id.as_mut_ptr(), &mut len, null(), class, null_mut(), written)
+      };
+      if status != 0 {
+        break;
+      }
+      let id = String::from_utf16_lossy(&id[..len as usize]);
+      let Some(name) = string(key, Some(&id), "DistributionName") else {
+        continue;
+      };
+      let version = dword(key, Some(&id), "Version").unwrap_or(1);
+      let vhdx = string(key, Some(&id), "BasePath").filter(|_| version >= 2).map(|base| {
+        let base = PathBuf::from(base.trim_start_matches(r"\\?\"));
+        base.join(string(key, Some(&id), "VhdFileName").unwrap_or_else(|| "ext4.vhdx".into()))
+      });
+      distros.push(WslDistro {
+        path: format!(r"\\wsl$\{}", name),
+        default: default.as_deref() == Some(id.as_str()),
+        vhdx_bytes: vhdx.as_ref().and_then(|vhdx| std::fs::metadata(vhdx).ok()).map(|md| md.len()),
+        vhdx_path: vhdx.map(|vhdx| vhdx.to_string_lossy().to_string()),
+        name,
+        version,
+      });
+    }
+    unsafe { RegCloseKey(key) };
+    distros
+  }
+
+  fn string(key: HKEY, subkey: Option<&str>, value: &str) -> Option<String> {
+    let mut buf = [0u16; 1024];
+    let mut size = std::mem::size_of_val(&buf) as u32;
+    read(key, subkey, value, RRF_RT_REG_SZ, buf.as_mut_ptr().cast(), &mut size)?;
+    let len = (size as usize / 2).min(buf.len());
+    Some(String::from_utf16_lossy(&buf[..len]).trim_end_matches('\0').to_string())
+  }
+
+  fn dword(key: HKEY, subkey: Option<&str>, value: &str) -> Option<u32> {
+    let mut data = 0u32;
+    let mut size = std::mem::size_of::<u32>() as u32;
+    read(key, subkey, value, RRF_RT_REG_DWORD, (&mut data as *mut u32).cast(), &mut size)?;
+    Some(data)
+  }
+
+  fn read(
+    key: HKEY,
+    subkey: Option<&str>,
+    value: &str,
+    flags: u32,
+    data: *mut core::ffi::c_void,
+    size: &mut u32,
+  ) -> Option<()> {
+    let subkey = subkey.map(|subkey| to_wide(Path::new(subkey)));
+    let value = to_wide(Path::new(value));
+    let subkey_ptr = subkey.as_ref().map_or(null(), |subkey| subkey.as_ptr());
+    let status =
+      unsafe { RegGetValueW(key, subkey_ptr, value.as_ptr(), flags, null_mut(), data, size) };
+    (status == 0).then_some(())
+  }
+}
@@ -0,0 +1,622 @@
+//! Volume-level facts (capacity, usage, mount roots) used alongside scans.
+
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Copy)]
+pub struct VolumeUsage {
+  pub total_bytes: u64,
+  pub free_bytes: u64,
+}
+
+impl VolumeUsage {
+  pub fn used_bytes(&self) -> u64 {
+    self.total_bytes.saturating_sub(self.free_bytes)
+  }
+}
+
+/// Capacity and free space of the volume holding `path`.
+#[cfg(unix)]
+pub fn usage(path: &Path) -> Option<VolumeUsage> {
+  use std::os::unix::ffi::OsStrExt;
+  let c_path = std::ffi::CString::new(path.as_os_str().as_bytes()).ok()?;
+  let mut st: libc::statvfs = unsafe { std::mem::zeroed() };
+  if unsafe { libc::statvfs(c_path.as_ptr(), &mut st) } != 0 {
+    return None;
+  }
+  let frsize = st.f_frsize as u64;
+  Some(VolumeUsage {
+    total_bytes: (st.f_blocks as u64).saturating_mul(frsize),
+    free_bytes: (st.f_bfree as u64).saturating_mul(frsize),
+  })
+}
+
+#[cfg(windows)]
+pub fn usage(path: &Path) -> Option<VolumeUsage> {
+  use windows_sys::Win32::Storage::FileSystem::GetDiskFreeSpaceExW;
+  // Shares are only accepted with a trailing backslash (`\\server\share\`).
+  let mut dir = path.as_os_str().to_owned();
+  if crate::paths::is_unc(path) && !path.to_string_lossy().ends_with('\\') {
+    dir.push("\\");
+  }
+  let wide = to_wide(Path::new(&dir));
+  let (mut available, mut total, mut free) = (0u64, 0u64, 0u64);
+  if unsafe { GetDiskFreeSpaceExW(wide.as_ptr(), &mut available, &mut total, &mut free) } == 0 {
+    return None;
+  }
+  Some(VolumeUsage {
+    total_bytes: total,
+    free_bytes: free,
+  })
+}
+
+#[cfg(not(any(unix, windows)))]
+pub fn usage(_path: &Path) -> Option<VolumeUsage> {
+  None
+}
+
+/// Whether `path` is the root of a mounted volume (`/`, `/mnt/data`, `D:\`).
+#[cfg(unix)]
+pub fn is_volume_root(path: &Path) -> bool {
+  use std::os::unix::fs::MetadataExt;
+  let Ok(md) = std::fs::metadata(path) else {
+    return false;
+  };
+  match std::fs::metadata(path.join("..")) {
+    // `/..` is `/` itself, so compare inodes as well as devices.
+    Ok(parent) => parent.dev() != md.dev() || parent.ino() == md.ino(),
+    Err(_) => false,
+  }
+}
+
+#[cfg(windows)]
+pub fn is_volume_root(path: &Path) -> bool {
+  let Some(volume) = mount_point(path) else {
+    return false;
+  };
+  let trim = |s: &str| s.trim_end_matches('\\').to_lowercase();
+  trim(&volume) == trim(&path.to_string_lossy())
+}
+
+#[cfg(not(any(unix, windows)))]
+pub fn is_volume_root(_path: &Path) -> bool {
+  false
+}
+
+/// Whether `path` is on a network filesystem: NFS, SMB and the like, and
+/// GVfs's FUSE view of shares on Linux.
+#[cfg(target_os = "linux")]
+pub fn is_network(path: &Path) -> bool {
+  // Superblock magic numbers from linux/magic.h and the filesystems' own.
+  const NETWORK_MAGICS: [u32; 7] = [
+    0x6969,     // NFS
+    0x517b,     // SMB
+    0xff534d42, // CIFS
+    0xfe534d42, // SMB2
+    0x564c,     // NCP
+    0x00c36400, // Ceph
+    0x6b414653, // AFS
+  ];
+  const FUSE_MAGIC: u32 = 0x65735546;
+  let Ok(c_path) = std::ffi::CString::new(path.as_os_str().as_encoded_bytes()) else {
+    return false;
+  };
+  let mut st: libc::statfs = unsafe { std::mem::zeroed() };
+  if unsafe { libc::statfs(c_path.as_ptr(), &mut st) } != 0 {
+    return false;
+  }
+  let magic = st.f_type as u32;
+  NETWORK_MAGICS.contains(&magic)
+    || (magic == FUSE_MAGIC && path.components().any(|part| part.as_os_str() == "gvfs"))
+}
+
+#[cfg(target_os = "macos")]
+pub fn is_network(path: &Path) -> bool {
+  let Ok(c_path) = std::ffi::CString::new(path.as_os_str().as_encoded_bytes()) else {
+    return false;
+  };
+  let mut st: libc::statfs = unsafe { std::mem::zeroed() };
+  if unsafe { libc::statfs(c_path.as_ptr(), &mut st) } != 0 {
+    return false;
+  }
+  st.f_flags & libc::MNT_LOCAL as u32 == 0
+}
+
+#[cfg(windows)]
+pub fn is_network(path: &Path) -> bool {
+  use windows_sys::Win32::Storage::FileSystem::GetDriveTypeW;
+  const DRIVE_REMOTE: u32 = 4;
+  if crate::paths::is_unc(path) {
+    return true;
+  }
+  let root = path.ancestors().last().unwrap_or(path);
+  unsafe { GetDriveTypeW(to_wide(root).as_ptr()) == DRIVE_REMOTE }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+pub fn is_network(_path: &Path) -> bool {
+  false
+}
+
+/// Every place a filesystem is mounted: `/proc/self/mountinfo` on Linux, the
+/// mount table on macOS, and the drive letters and mounted folders of every
+/// volume on Windows.
+#[cfg(target_os = "linux")]
+pub fn mount_points() -> Vec<PathBuf> {
+  use std::os::unix::ffi::OsStringExt;
+  let Ok(info) = std::fs::read_to_string("/proc/self/mountinfo") else {
+    return Vec::new();
+  };
+  info
+    .lines()
+    .filter_map(|line| line.split(' ').nth(4))
+    .map(|field| PathBuf::from(std::ffi::OsString::from_vec(unescape_mount_field(field))))
+    .collect()
+}
+
+/// Undoes the octal escapes (`\040` for a space) of a mountinfo field.
+#[cfg(target_os = "linux")]
+fn unescape_mount_field(field: &str) -> Vec<u8> {
+  let bytes = field.as_bytes();
+  let mut out = Vec::with_capacity(bytes.len());
+  let mut i = 0;
+  while i < bytes.len() {
+    let octal = bytes.get(i + 1..i + 4).and_then(|digits| {
+      let digits = std::str::from_utf8(digits).ok()?;
+      u8::from_str_radix(digits, 8).ok()
+    });
+    match (bytes[i], octal) {
+      (b'\\', Some(byte)) => {
+        out.push(byte);
+        i += 4;
+      }
+      (byte, _) => {
+        out.push(byte);
+        i += 1;
+      }
+    }
+  }
+  out
+}
+
+#[cfg(target_os = "macos")]
+pub fn mount_points() -> Vec<PathBuf> {
+  mount_table().iter().map(|m| c_path(&m.f_mntonname)).collect()
+}
+
+/// The mount table, one `statfs` per mounted filesystem.
+#[cfg(target_os = "macos")]
+fn mount_table() -> Vec<libc::statfs> {
+  // getmntinfo shares one buffer across threads; getfsstat fills our own.
+  let count = unsafe { libc::getfsstat(std::ptr::null_mut(), 0, libc::MNT_NOWAIT) };
+  if count <= 0 {
+    return Vec::new();
+  }
+  let mut mounts: Vec<libc::statfs> = Vec::with_capacity(count as usize);
+  let size = (mounts.capacity() * std::mem::size_of::<libc::statfs>()) as libc::c_int;
+  let count = unsafe { libc::getfsstat(mounts.as_mut_ptr(), size, libc::MNT_NOWAIT) };
+  if count <= 0 {
+    return Vec::new();
+  }
+  unsafe { mounts.set_len((count as usize).min(mounts.capacity())) };
+  mounts
+}
+
+#[cfg(target_os = "macos")]
+fn c_path(name: &[libc::c_char]) -> PathBuf {
+  use std::os::unix::ffi::OsStrExt;
+  let name = unsafe { std::ffi::CStr::from_ptr(name.as_ptr()) };
+  PathBuf::from(std::ffi::OsStr::from_bytes(name.to_bytes()))
+}
+
+#[cfg(windows)]
+pub fn mount_points() -> Vec<PathBuf> {
+  use windows_sys::Win32::{
+    Foundation::INVALID_HANDLE_VALUE,
+    Storage::FileSystem::{
+      FindFirstVolumeW, FindNextVolumeW, FindVolumeClose, GetVolumePathNamesForVolumeNameW,
+    },
+  };
+  let mut volume = [0u16; 64];
+  let find = unsafe { FindFirstVolumeW(volume.as_mut_ptr(), volume.len() as u32) };
+  if find == INVALID_HANDLE_VALUE {
+    return Vec::new();
+  }
+  let mut points = Vec::new();
+  loop {
+    let mut names = [0u16; 1024];
+    let mut len = 0u32;
+    let ok = unsafe {
+      GetVolumePathNamesForVolumeNameW(
+        volume.as_ptr(),
+        names.as_mut_ptr(),
+        names.len() as u32,
+        &mut len,
+      )
+    };
+    if ok != 0 {
+      // NUL-terminated names, ended by an empty one.
+      let names = &names[..(len as usize).min(names.len())];
+      for name in names.split(|&c| c == 0).filter(|name| !name.is_empty()) {
+        points.push(PathBuf::from(String::from_utf16_lossy(name)));
+      }
+    }
+    if unsafe { FindNextVolumeW(find, volume.as_mut_ptr(), volume.len() as u32) } == 0 {
+      break;
+    }
+  }
+  unsafe { FindVolumeClose(find) };
+  points
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+pub fn mount_points() -> Vec<PathBuf> {
+  Vec::new()
+}
+
+/// The first mount point of each volume, for volumes mounted at a drive
+/// letter and a folder besides.
+#[cfg(windows)]
+pub fn distinct_mount_points() -> Vec<String> {
+  let mut devices = Vec::new();
+  let mut points = Vec::new();
+  for point in mount_points() {
+    let point = point.to_string_lossy().to_string();
+    let device = device_path(&point);
+    if device.is_some() && devices.contains(&device) {
+      continue;
+    }
+    devices.push(device);
+    points.push(point);
+  }
+  points
+}
+
+/// Where the volume holding `path` is mounted, with a trailing backslash
+/// (`C:\`, `D:\mnt\data\`).
+#[cfg(windows)]
+pub fn mount_point(path: &Path) -> Option<String> {
+  use windows_sys::Win32::Storage::FileSystem::GetVolumePathNameW;
+  let wide = to_wide(path);
+  let mut buf = [0u16; 1024];
+  if unsafe { GetVolumePathNameW(wide.as_ptr(), buf.as_mut_ptr(), buf.len() as u32) } == 0 {
+    return None;
+  }
+  Some(from_wide(&buf))
+}
+
+/// Where the volume holding `path` is mounted, as `mount_point` has it.
+#[cfg(windows)]
+pub fn volume_of(path: &Path) -> Option<String> {
+  mount_point(path)
+}
+
+/// The deepest mount point above `path`.
+#[cfg(not(windows))]
+pub fn volume_of(path: &Path) -> Option<String> {
+  mount_points()
+    .into_iter()
+    .filter(|point| path.starts_with(point))
+    .max_by_key(|point| point.components().count())
+    .map(|point| point.to_string_lossy().to_string())
+}
+
+/// File system of the volume mounted at `mount_point` (`NTFS`, `ReFS`, `FAT32`).
+#[cfg(windows)]
+pub fn filesystem_name(mount_point: &str) -> Option<String> {
+  volume_names(mount_point).1
+}
+
+/// Label and file system of the volume mounted at `mount_point`.
+#[cfg(windows)]
+fn volume_names(mount_point: &str) -> (Option<String>, Option<String>) {
+  use windows_sys::Win32::Storage::FileSystem::GetVolumeInformationW;
+  let wide = to_wide(Path::new(mount_point));
+  let mut label = [0u16; 261];
+  let mut name = [0u16; 64];
+  let ok = unsafe {
+    GetVolumeInformationW(
+      wide.as_ptr(),
+      label.as_mut_ptr(),
+      label.len() as u32,
+      std::ptr::null_mut(),
+      std::ptr::null_mut(),
+      std::ptr::null_mut(),
+      name.as_mut_ptr(),
+      name.len() as u32,
+    )
+  };
+  match ok {
+    0 => (None, None),
+    _ => (Some(from_wide(&label)), Some(from_wide(&name))),
+  }
+}
+
+/// Device path of the raw volume mounted at `mount_point`
+/// (`\\?\Volume{...}`), for opening the volume itself.
+#[cfg(windows)]
+pub fn device_path(mount_point: &str) -> Option<String> {
+  use windows_sys::Win32::Storage::FileSystem::GetVolumeNameForVolumeMountPointW;
+  let wide = to_wide(Path::new(mount_point));
+  let mut buf = [0u16; 64];
+  if unsafe { GetVolumeNameForVolumeMountPointW(wide.as_ptr(), buf.as_mut_ptr(), buf.len() as u32) }
+    == 0
+  {
+    return None;
+  }
+  Some(from_wide(&buf).trim_end_matches('\\').to_string())
+}
+
+#[cfg(windows)]
+fn from_wide(buf: &[u16]) -> String {
+  let len = buf.iter().position(|&c| c == 0).unwrap_or(buf.len());
+  String::from_utf16_lossy(&buf[..len])
+}
+
+/// NUL-terminated UTF-16 copy of `path` for Win32 calls.
+#[cfg(windows)]
+pub fn to_wide(path: &Path) -> Vec<u16> {
+  use std::os::windows::ffi::OsStrExt;
+  path.as_os_str().encode_wide().chain(Some(0)).collect()
+}
+
+#[derive(Clone, Serialize)]
+pub struct VolumeInfo {
+  /// Where it's mounted: `/`, `/media/usb`, `C:\`, `D:\mnt\data\`.
+  pub mount_point: String,
+  pub label: Option<String>,
+  /// `ext4`, `apfs`, `NTFS`, `nfs4` and so on.
+  pub filesystem: Option<String>,
+  pub total_bytes: u64,
+  pub free_bytes: u64,
+  pub used_bytes: u64,
+  /// USB drives, SD cards, optical discs and other ejectable media.
+  pub removable: bool,
+  pub network: bool,
+  /// Holds the running OS: the system drive, `/` and `/boot`.
+  pub system: bool,
+}
+
+/// A volume with the capacity of its filesystem; `None` for one that isn't
+/// ready, like an empty card reader.
+fn volume_info(
+  mount_point: &Path,
+  label: Option<String>,
+  filesystem: Option<String>,
+  removable: bool,
+  network: bool,
+  system: bool,
+) -> Option<VolumeInfo> {
+  let usage = usage(mount_point)?;
+  Some(VolumeInfo {
+    mount_point: mount_point.to_string_lossy().to_string(),
+    label: label.filter(|label| !label.is_empty()),
+    filesystem,
+    total_bytes: usage.total_bytes,
+    free_bytes: usage.free_bytes,
+    used_bytes: usage.used_bytes(),
+    removable,
+    network,
+    system,
+  })
+}
+
+/// Filesystems that live on the network, by their mountinfo type.
+#[cfg(target_os = "linux")]
+const NETWORK_FILESYSTEMS: [&str; 10] = [
+  "nfs",
+  "nfs4",
+  "cifs",
+  "smb3",
+  "smbfs",
+  "ceph",
+  "glusterfs",
+  "davfs",
+  "fuse.sshfs",
+  "fuse.rclone",
+];
+
+/// The mounts of block devices and network filesystems in mountinfo, once
+/// per device: bind mounts repeat one.
+#[cfg(target_os = "linux")]
+pub fn volumes() -> Vec<VolumeInfo> {
+  use std::os::unix::ffi::OsStringExt;
+  let Ok(info) = std::fs::read_to_string("/proc/self/mountinfo") else {
+    return Vec::new();
+  };
+  let labels = linux_labels();
+  let mut devices = std::collections::HashSet::new();
+  let mut volumes = Vec::new();
+  for line in info.lines() {
+    // id parent major:minor root mount-point options [optional...] - type source super-options
+    let Some((mount, fs)) = line.split_once(" - ") else {
+      continue;
+    };
+    let mount: Vec<&str> = mount.split(' ').collect();
+    let fs: Vec<&str> = fs.split(' ').collect();
+    let ([_, _, device, _, point, ..], [fs_type, source, ..]) = (mount.as_slice(), fs.as_slice())
+    else {
+      continue;
+    };
+    let network = NETWORK_FILESYSTEMS.contains(fs_type);
+    // Snaps mount a squashfs image each.
+    let block = source.starts_with("/dev/") && *fs_type != "squashfs";
+    if !(network || block || *fs_type == "zfs") {
+      continue;
+    }
+    if !devices.insert(*device) {
+      continue;
+    }
+    let point = PathBuf::from(std::ffi::OsString::from_vec(unescape_mount_field(point)));
+    let source = std::fs::canonicalize(source).unwrap_or_else(|_| PathBuf::from(source));
+    let system = point == Path::new("/") || point.starts_with("/boot");
+    let removable = *fs_type == "iso9660" || (block && linux_removable(&source));
+    volumes.extend(volume_info(
+      &point,
+      labels.get(&source).cloned(),
+      Some(fs_type.to_string()),
+      removable,
+      network,
+      system,
+    ));
+  }
+  volumes
+}
+
+/// Filesystem labels by device, from the links udev keeps.
+#[cfg(target_os = "linux")]
+fn linux_labels() -> std::collections::HashMap<PathBuf, String> {
+  let Ok(links) = std::fs::read_dir("/dev/disk/by-label") else {
+    return Default::default();
+  };
+  links
+    .flatten()
+    .filter_map(|link| {
+      let device = std::fs::canonicalize(link.path()).ok()?;
+      // udev writes unsafe bytes as `\x20`.
+      let name = link.file_name().to_string_lossy().to_string();
+      Some((device, unescape_hex(&name)))
+    })
+    .collect()
+}
+
+#[cfg(target_os = "linux")]
+fn unescape_hex(name: &str) -> String {
+  let mut bytes = Vec::with_capacity(name.len());
+  let mut rest = name.as_bytes();
+  while let Some((&byte, tail)) = rest.split_first() {
+    let hex = tail.strip_prefix(b"x").and_then(|hex| hex.get(..2));
+    let value = hex.and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+    match (byte, value) {
+      (b'\\', Some(value)) => {
+        bytes.push(value);
+        rest = &tail[3..];
+      }
+      _ => {
+        bytes.push(byte);
+        rest = tail;
+      }
+    }
+  }
+  String::from_utf8_lossy(&bytes).to_string()
+}
+
+/// Whether the disk holding the block `device` is removable, or attached
+/// over USB, which many card readers and sticks don't call removable.
+#[cfg(target_os = "linux")]
+fn linux_removable(device: &Path) -> bool {
+  let Some(name) = device.file_name() else {
+    return false;
+  };
+  let Ok(sys) = std::fs::canonicalize(Path::new("/sys/class/block").join(name)) else {
+    return false;
+  };
+  // A partition sits inside the directory of its disk.
+  let disk = match sys.join("partition").exists() {
+    true => sys.parent().unwrap_or(&sys),
+    false => &sys,
+  };
+  let removable = std::fs::read_to_string(disk.join("removable"));
+  removable.is_ok_and(|flag| flag.trim() == "1") || disk.to_string_lossy().contains("/usb")
+}
+
+/// The browsable filesystems of the mount table: the VM, preboot and other
+/// internal volumes of APFS, and pseudo filesystems, are left out.
+#[cfg(target_os = "macos")]
+pub fn volumes() -> Vec<VolumeInfo> {
+  let mut volumes = Vec::new();
+  for m in mount_table() {
+    let fs_type = c_path(&m.f_fstypename).to_string_lossy().to_string();
+    let flags = m.f_flags as libc::c_int;
+    if flags & libc::MNT_DONTBROWSE != 0 || matches!(fs_type.as_str(), "devfs" | "autofs") {
+      continue;
+    }
+    let point = c_path(&m.f_mntonname);
+    let network = flags & libc::MNT_LOCAL == 0;
+    let system = flags & libc::MNT_ROOTFS != 0;
+    let label = match system {
+      // The root shows up under the name of its volume in /Volumes.
+      true => mac_volume_name(&point),
+      false => point.file_name().map(|name| name.to_string_lossy().to_string()),
+    };
+    let removable = !network && !system && mac_ejectable(&point);
+    volumes.extend(volume_info(&point, label, Some(fs_type), removable, network, system));
+  }
+  volumes
+}
+
+#[cfg(target_os = "macos")]
+fn mac_volume_name(point: &Path) -> Option<String> {
+  let mut command = std::process::Command::new("diskutil");
+  let output = command.args(["info", "-plist"]).arg(point).output().ok()?;
+  let plist = String::from_utf8_lossy(&output.stdout);
+  let rest = plist.split("<key>VolumeName</key>").nth(1)?.trim_start();
+  let rest = rest.strip_prefix("<string>")?;
+  Some(rest[..rest.find("</string>")?].replace("&amp;", "&"))
+}
+
+#[cfg(target_os = "macos")]
+fn mac_ejectable(point: &Path) -> bool {
+  let mut command = std::process::Command::new("diskutil");
+  let Ok(output) = command.args(["info", "-plist"]).arg(point).output() else {
+    return false;
+  };
+  let plist = String::from_utf8_lossy(&output.stdout);
+  let value = plist.split("<key>Ejectable</key>").nth(1);
+  value.is_some_and(|value| value.trim_start().starts_with("<true/>"))
+}
+
+/// Drive letters, mapped network drives among them, and the folders other
+/// volumes are mounted at.
+#[cfg(windows)]
+pub fn volumes() -> Vec<VolumeInfo> {
+  use windows_sys::Win32::Storage::FileSystem::{GetDriveTypeW, GetLogicalDriveStringsW};
+  // What GetDriveTypeW returns for them.
+  const DRIVE_REMOVABLE: u32 = 2;
+  const DRIVE_REMOTE: u32 = 4;
+  const DRIVE_CDROM: u32 = 5;
+  let mut buf = [0u16; 512];
+  let len = unsafe { GetLogicalDriveStringsW(buf.len() as u32, buf.as_mut_ptr()) } as usize;
+  let mut points: Vec<String> = buf[..len.min(buf.len())]
+    .split(|&c| c == 0)
+    .filter(|letter| !letter.is_empty())
+    .map(String::from_utf16_lossy)
+    .collect();
+  for point in distinct_mount_points() {
+    if !points.iter().any(|known| known.eq_ignore_ascii_case(&point)) {
+      points.push(point);
+    }
+  }
+  let system_drive = std::env::var("SystemDrive").unwrap_or_else(|_| "C:".to_string());
+  points
+    .into_iter()
+    .filter_map(|point| {
+      let wide = to_wide(Path::new(&point));
+      let kind = unsafe { GetDriveTypeW(wide.as_ptr()) };
+      let (label, filesystem) = volume_names(&point);
+      let system = point.trim_end_matches('\\').eq_ignore_ascii_case(&system_drive);
+      volume_info(
+        Path::new(&point),
+        label,
+        filesystem,
+        matches!(kind, DRIVE_REMOVABLE | DRIVE_CDROM),
+        kind == DRIVE_REMOTE,
+        system,
+      )
+    })
+    .collect()
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+pub fn volumes() -> Vec<VolumeInfo> {
+  Vec::new()
+}
+
+/// Every mounted volume with its capacity and what kind of volume it is, by
+/// mount point.
+#[tauri::command]
+pub async fn list_volumes() -> Result<Vec<VolumeInfo>, String> {
+  let mut volumes = volumes();
+  volumes.sort_by(|a, b| a.mount_point.cmp(&b.mount_point));
+  Ok(volumes)
+}
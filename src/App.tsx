@@ -1,35 +1,608 @@
 import { useEffect, useMemo, useRef, useState } from "react";
 import { listen } from "@tauri-apps/api/event";
 import { invoke } from "@tauri-apps/api/core";
-import { open } from "@tauri-apps/plugin-dialog";
+import { open, save } from "@tauri-apps/plugin-dialog";
 
-type NodeKind = "file" | "dir";
+type NodeKind = "file" | "dir" | "other";
 
 type ScanProgressEvent = {
   scan_id: string;
   scanned_entries: number;
   scanned_bytes: number;
   current_path?: string;
+  elapsed_ms: number;
+  entries_per_sec: number;
+  bytes_per_sec: number;
+  eta_ms?: number;
+  estimated_total_bytes?: number;
+  percent?: number;
+};
+
+type ScanErrorKind =
+  | "permission_denied"
+  | "not_found"
+  | "io_timeout"
+  | "filesystem_loop"
+  | "other";
+
+type ErrorSummary = {
+  total: number;
+  categories: { kind: ScanErrorKind; count: number; sample_paths: string[] }[];
 };
 
 type ScanDoneEvent = {
   scan_id: string;
+  root_path: string;
   root: ScanNode;
   errors: string[];
+  error_summary: ErrorSummary;
+  excluded_entries: number;
+  filtered_entries: number;
+  skipped_mounts: string[];
+  top_extensions: ExtensionStat[];
+  size_histogram: SizeBucket[];
+  ignore_totals?: IgnoreTotals;
+  unaccounted_bytes?: number;
+  unaccounted_parts?: UnaccountedPart[];
+  storage_classes?: StorageClassTotal[];
+  partial: boolean;
+  scanned_entries: number;
+  scanned_bytes: number;
+};
+
+type UnaccountedPart = {
+  kind: "hidden" | "filesystem_metadata" | "shadow_copies" | "purgeable";
+  bytes: number;
+};
+
+type Unaccounted = { bytes: number; parts: UnaccountedPart[]; deniedFolders: number };
+
+const UNACCOUNTED_LABELS: Record<UnaccountedPart["kind"], string> = {
+  hidden: "hidden entries",
+  filesystem_metadata: "filesystem metadata",
+  shadow_copies: "shadow copies",
+  purgeable: "purgeable",
+};
+
+// What a bucket keeps in each S3 storage class.
+type StorageClassTotal = { class: string; bytes: number; objects: number };
+
+type IgnoreMode = "off" | "exclude" | "only";
+
+type IgnoreTotals = {
+  ignored_bytes: number;
+  ignored_files: number;
+  kept_bytes: number;
+  kept_files: number;
+};
+
+type ExtensionStat = {
+  extension: string;
+  bytes: number;
+  allocated: number;
+  files: number;
+};
+
+type OldFile = {
+  path: string;
+  size: number;
+  allocated: number;
+  mtime: number;
+  atime: number | null;
+};
+
+type EmptyDir = {
+  path: string;
+  dirs: number;
+};
+
+type BrokenLink = {
+  path: string;
+  target: string;
+};
+
+type DuplicateFiles = {
+  size: number;
+  paths: string[];
+};
+
+type DuplicateDirs = {
+  size: number;
+  files: number;
+  paths: string[];
+};
+
+type SimilarImage = {
+  path: string;
+  size: number;
+  width: number;
+  height: number;
+};
+
+type SimilarImages = {
+  images: SimilarImage[];
+  reclaimable: number;
+};
+
+type GitRepo = {
+  path: string;
+  worktree_bytes: number;
+  git_bytes: number;
+  loose_objects: number;
+  packs: number;
+  largest_packs: { path: string; size: number }[];
+  shallow: boolean;
+  gc_suggested: boolean;
+};
+
+type Game = {
+  store: "steam" | "epic" | "gog";
+  id: string;
+  name: string;
+  path: string;
+  library: string | null;
+  install_bytes: number;
+  shader_cache_bytes: number;
+  workshop_bytes: number;
+  last_updated: number | null;
+};
+
+const GAME_STORES: Record<Game["store"], string> = {
+  steam: "Steam",
+  epic: "Epic",
+  gog: "GOG",
+};
+
+type DevProject = {
+  path: string;
+  bytes: number;
+  artifacts: { path: string; kind: string; bytes: number }[];
+};
+
+type PackageCache = {
+  tool: string;
+  paths: string[];
+  bytes: number;
+  files: number;
+  clearable: boolean;
+};
+
+const PACKAGE_TOOLS: Record<string, string> = {
+  cargo: "Cargo",
+  npm: "npm",
+  pip: "pip",
+  maven: "Maven",
+  gradle: "Gradle",
+  homebrew: "Homebrew",
+  nuget: "NuGet",
+};
+
+type BrowserProfile = {
+  browser: "chrome" | "edge" | "firefox" | "safari";
+  name: string;
+  path: string;
+  cache_bytes: number;
+  site_data_bytes: number;
+  service_worker_bytes: number;
+  other_bytes: number;
+};
+
+const BROWSERS: Record<BrowserProfile["browser"], string> = {
+  chrome: "Chrome",
+  edge: "Edge",
+  firefox: "Firefox",
+  safari: "Safari",
+};
+
+type CleanableReport = {
+  total_bytes: number;
+  items: { kind: string; paths: string[]; bytes: number; files: number }[];
+};
+
+const CLEANABLE_KINDS: Record<string, string> = {
+  user_temp: "Temporary files",
+  windows_temp: "Windows temporary files",
+  update_downloads: "Downloaded Windows updates",
+  windows_old: "Previous Windows installation",
+  delivery_optimization: "Delivery Optimization cache",
+  old_drivers: "Superseded driver packages",
+  error_reports: "Error reports",
+};
+
+type SystemFile = {
+  kind: "pagefile" | "swapfile" | "swap_partition" | "hibernation";
+  path: string;
+  bytes: number;
+  used_bytes: number | null;
+};
+
+const SYSTEM_FILE_KINDS: Record<SystemFile["kind"], string> = {
+  pagefile: "Page file",
+  swapfile: "Swap file",
+  swap_partition: "Swap partition",
+  hibernation: "Hibernation file",
+};
+
+type ShadowStorage = {
+  volume: string;
+  storage_volume: string;
+  copies: number;
+  used_bytes: number;
+  allocated_bytes: number;
+  max_bytes: number | null;
+};
+
+type PurgeableSpace = {
+  volume: string;
+  purgeable_bytes: number | null;
+  free_bytes: number;
+  snapshots: { name: string; date: string | null; bytes: number | null }[];
+};
+
+type VolumeInfo = {
+  mount_point: string;
+  label: string | null;
+  filesystem: string | null;
+  total_bytes: number;
+  free_bytes: number;
+  used_bytes: number;
+  removable: boolean;
+  network: boolean;
+  system: boolean;
+};
+
+type MonitorSettings = {
+  interval_secs: number;
+  min_free_bytes: number | null;
+  min_free_percent: number | null;
+};
+
+type LowSpaceEvent = {
+  mount_point: string;
+  label: string | null;
+  free_bytes: number;
+  total_bytes: number;
+};
+
+type VolumesChangedEvent = {
+  mounted: VolumeInfo[];
+  unmounted: VolumeInfo[];
+  volumes: VolumeInfo[];
+};
+
+type SmbShare = { name: string; comment: string; path: string };
+
+type RcloneRemote = { name: string; kind: string };
+
+type CloudProvider = "google_drive" | "one_drive";
+
+type CloudDrive = { provider: CloudProvider; account: string };
+
+type AndroidFolder = { uri: string; name: string };
+
+// A folder picked in Files has the `id` of its bookmark; the app's own
+// container has none.
+type IosLocation = { id: string | null; name: string; path: string };
+
+type DriveScan = { scan_id: string; volume: VolumeInfo };
+
+type HistoryEntry = {
+  id: number;
+  root: string;
+  volume: string | null;
+  finished: number;
+  elapsed_ms: number;
+  size: number;
+  allocated: number;
+  file_count: number;
+  dir_count: number;
+  error_count: number;
+  volume_total: number | null;
+  volume_free: number | null;
+  has_tree: boolean;
+};
+
+// A stored tree is kept under `scan_id` and read a page at a time.
+type HistoryScan = HistoryEntry & { tree: ScanNode | null; scan_id: string | null };
+
+type ScheduledScan = {
+  id: string;
+  path: string;
+  interval_secs: number;
+  enabled: boolean;
+  last_run: number | null;
+};
+
+type ScheduledScanEvent = {
+  schedule_id: string;
+  scan_id: string;
+  root: string;
+  size: number;
+  file_count: number;
+  error_count: number;
+};
+
+type WatchRule = {
+  id: string;
+  path: string;
+  max_growth: number | null;
+  max_size: number | null;
+  baseline: number | null;
+  baseline_time: number | null;
+  size: number | null;
+  tripped: boolean;
+};
+
+type WatchAlertEvent = {
+  rule_id: string;
+  path: string;
+  size: number;
+  growth: number;
+  reason: string;
+};
+
+const SCHEDULE_INTERVALS: Record<number, string> = {
+  3600: "hour",
+  86400: "day",
+  604800: "week",
+};
+
+type TrendPoint = { time: number; size: number };
+
+type GrowthTrend = { path: string; growth: number; bytes_per_day: number; points: TrendPoint[] };
+
+type Change = "added" | "removed" | "grown" | "shrunk" | "unchanged";
+
+type DeltaNode = {
+  name: string;
+  path: string;
+  kind: NodeKind;
+  change: Change;
+  before: number;
+  after: number;
+  delta: number;
+  children?: DeltaNode[];
+};
+
+type ScanRefresh = { scan_id: string; root: ScanNode; rescanned: string[] };
+
+type NodeTotals = {
+  id: number;
+  size: number;
+  allocated: number;
+  file_count: number;
+  dir_count: number;
+};
+
+type NodeChangedEvent = { scan_id: string; node: ScanNode; ancestors: NodeTotals[] };
+
+type ExportFormat = "ncdu" | "csv" | "tsv" | "windirstat" | "du" | "html";
+
+// Which nodes the CSV and TSV exports have rows for.
+type ExportRows = "all" | "files" | "dirs";
+
+// What each export is offered as in the save dialog.
+const EXPORT_FORMATS: Record<ExportFormat, { label: string; extension: string }> = {
+  ncdu: { label: "ncdu", extension: "json" },
+  csv: { label: "CSV", extension: "csv" },
+  tsv: { label: "TSV", extension: "tsv" },
+  windirstat: { label: "WinDirStat", extension: "csv" },
+  du: { label: "du -h", extension: "txt" },
+  html: { label: "HTML report", extension: "html" },
+};
+
+type TrashBin = { volume: string; path: string; bytes: number; items: number };
+
+type EngineUsage = {
+  engine: "docker" | "podman";
+  root_dir: string | null;
+  usage: {
+    kind: "images" | "containers" | "volumes" | "build_cache";
+    count: number;
+    active: number;
+    bytes: number;
+    reclaimable: number;
+  }[];
+};
+
+type WslDistro = {
+  name: string;
+  version: number;
+  default: boolean;
+  path: string;
+  vhdx_path: string | null;
+  vhdx_bytes: number | null;
+};
+
+type WslUsage = {
+  used_bytes: number;
+  total_bytes: number;
+  vhdx_bytes: number | null;
+};
+
+type InstalledApp = {
+  source: "flatpak" | "snap";
+  name: string;
+  branch: string | null;
+  runtime: boolean;
+  user: boolean;
+  bytes: number;
+  revisions: { revision: string; path: string; bytes: number; current: boolean }[];
+};
+
+type OldRevision = {
+  source: "flatpak" | "snap";
+  name: string;
+  revision: string;
+  path: string;
+  bytes: number;
+};
+
+// Flatpak and Snap only exist on Linux.
+const IS_LINUX = navigator.userAgent.includes("Linux");
+const IS_WINDOWS = navigator.userAgent.includes("Windows");
+const IS_IOS = /iPhone|iPad/.test(navigator.userAgent);
+// iOS says it's "like Mac OS X".
+const IS_MAC = navigator.userAgent.includes("Mac OS") && !IS_IOS;
+const IS_ANDROID = navigator.userAgent.includes("Android");
+const TRASH_NAME = IS_WINDOWS ? "Recycle Bin" : "Trash";
+
+const CONTAINER_STORAGE: Record<string, string> = {
+  images: "Images",
+  containers: "Containers",
+  volumes: "Volumes",
+  build_cache: "Build cache",
+};
+
+const ARTIFACT_KINDS: Record<string, string> = {
+  node_modules: "node_modules",
+  cargo_target: "Cargo target",
+  maven_target: "Maven target",
+  virtualenv: "virtualenv",
+  pycache: "__pycache__",
+  gradle: ".gradle",
+  build_output: "build output",
+};
+
+type DuplicateProgressEvent = {
+  scan_id: string;
+  stage: "size" | "partial" | "full" | "images";
+  done_files: number;
+  total_files: number;
+  done_bytes: number;
+  total_bytes: number;
+};
+
+const HASH_STAGE_LABELS: Record<DuplicateProgressEvent["stage"], string> = {
+  size: "Grouping by size",
+  partial: "Comparing file ends",
+  full: "Comparing whole files",
+  images: "Comparing images",
+};
+
+type RemovedDirs = {
+  removed: string[];
+  errors: string[];
+};
+
+type SizeBucket = {
+  min: number;
+  max: number | null;
+  files: number;
+  bytes: number;
+};
+
+type FileEntry = {
+  path: string;
+  size: number;
+  allocated: number;
 };
 
+type DirCount = {
+  path: string;
+  entries: number;
+};
+
+type ScanFailedEvent = {
+  scan_id: string;
+  root: string;
+  message: string;
+};
+
+// Something that went wrong in the background, outside any call.
+type BackgroundErrorEvent = { message: string };
+
 type SortField = "size" | "name" | "type";
 type SortDirection = "asc" | "desc";
 
+type DiskAccess = {
+  full_disk_access: boolean | null;
+  probed_path: string | null;
+};
+
 type ScanNode = {
+  // Numbers the nodes of a scan; get_node_path tells where one is.
+  id: number;
   name: string;
-  path: string;
   kind: NodeKind;
   size: number;
+  allocated: number;
   children?: ScanNode[];
-  omitted_children?: number;
+  item_count?: number;
+  file_count?: number;
+  dir_count?: number;
+  mtime?: number;
+  newest_mtime?: number;
+  oldest_mtime?: number;
+  hidden_bytes?: number;
+  online_only?: boolean;
+  compressed?: boolean;
+  sparse?: boolean;
+  link_kind?: "symlink" | "junction" | "mountpoint";
+  attributes: number;
+  owner?: string;
+  category?: FileCategory;
+  categories?: Partial<Record<FileCategory, number>>;
+  // Set when the children were left out, to be fetched with get_children.
+  child_count?: number;
+};
+
+type ChildPage = { total: number; offset: number; children: ScanNode[] };
+
+type FileCategory =
+  | "image"
+  | "video"
+  | "audio"
+  | "document"
+  | "code"
+  | "archive"
+  | "executable"
+  | "other";
+
+const CATEGORY_COLORS: Record<FileCategory, string> = {
+  image: "#e5a50a",
+  video: "#c01c28",
+  audio: "#9141ac",
+  document: "#1c71d8",
+  code: "#2ec27e",
+  archive: "#865e3c",
+  executable: "#e66100",
+  other: "#77767b",
+};
+
+// A file's category, or the one taking the most bytes under a folder.
+function mainCategory(node: ScanNode): FileCategory | undefined {
+  if (node.category) return node.category;
+  let best: FileCategory | undefined;
+  for (const [category, bytes] of Object.entries(node.categories ?? {})) {
+    if (best === undefined || bytes > (node.categories![best] ?? 0)) {
+      best = category as FileCategory;
+    }
+  }
+  return best;
+}
+
+type OwnerStat = {
+  owner: string;
+  bytes: number;
+  allocated: number;
+  files: number;
 };
 
+// Files inspect_disk_image can look inside.
+const DISK_IMAGE_PATTERN = /\.(iso|img|vhdx?|dmg)$/i;
+
+// Children fetched at a time for the table of a scan.
+const PAGE_SIZE = 200;
+
+// Bits of ScanNode.attributes.
+const ATTR_HIDDEN = 1 << 0;
+const ATTR_SYSTEM = 1 << 1;
+
+function formatDate(seconds: number | undefined) {
+  return seconds === undefined ? "" : new Date(seconds * 1000).toLocaleDateString();
+}
+
 function formatBytes(bytes: number) {
   const b = Math.max(0, bytes);
   const units = ["B", "KB", "MB", "GB", "TB", "PB"];
@@ -39,24 +612,223 @@ function formatBytes(bytes: number) {
   return `${val.toFixed(val >= 10 ? 1 : 2)} ${units[exp]}`;
 }
 
+// The `points` attribute of a polyline drawing `points` in a width × height box.
+function sparkline(points: TrendPoint[], width: number, height: number) {
+  const times = points.map((p) => p.time);
+  const sizes = points.map((p) => p.size);
+  const [t0, t1] = [Math.min(...times), Math.max(...times)];
+  const [s0, s1] = [Math.min(...sizes), Math.max(...sizes)];
+  return points
+    .map((p) => {
+      const x = t1 > t0 ? ((p.time - t0) / (t1 - t0)) * width : 0;
+      const y = s1 > s0 ? height - ((p.size - s0) / (s1 - s0)) * height : height / 2;
+      return `${x.toFixed(1)},${y.toFixed(1)}`;
+    })
+    .join(" ");
+}
+
+// The get_children arguments for a table sorted by `field` in `dir`; the
+// backend lists sizes largest first and names from A.
+function childOrder(field: SortField, dir: SortDirection) {
+  const sortBy = field === "type" ? "kind" : field;
+  return { sortBy, reverse: field === "size" ? dir === "asc" : dir === "desc" };
+}
+
+function bucketLabel(bucket: SizeBucket) {
+  if (bucket.max === null) return `> ${formatBytes(bucket.min)}`;
+  if (bucket.min === 0) return `< ${formatBytes(bucket.max)}`;
+  return `${formatBytes(bucket.min)}–${formatBytes(bucket.max)}`;
+}
+
 export default function App() {
   const [paths, setPaths] = useState<string[]>([""]);
-  const [maxDepth, setMaxDepth] = useState<number>(6);
-  const [topChildren, setTopChildren] = useState<number>(200);
+  const [volumes, setVolumes] = useState<VolumeInfo[]>([]);
+  const [monitor, setMonitor] = useState<MonitorSettings | null>(null);
+  const [monitorGb, setMonitorGb] = useState(10);
+  const [monitorPercent, setMonitorPercent] = useState(5);
+  const [lowSpace, setLowSpace] = useState<LowSpaceEvent[]>([]);
+  const [smbHost, setSmbHost] = useState("");
+  const [smbUser, setSmbUser] = useState("");
+  const [smbDomain, setSmbDomain] = useState("");
+  const [smbPassword, setSmbPassword] = useState("");
+  const [smbRemember, setSmbRemember] = useState(false);
+  const [smbShares, setSmbShares] = useState<SmbShare[] | null>(null);
+  const [listingShares, setListingShares] = useState(false);
+  const [sshHost, setSshHost] = useState("");
+  const [sshPort, setSshPort] = useState("");
+  const [sshUser, setSshUser] = useState("");
+  const [sshPath, setSshPath] = useState("");
+  const [sshPassword, setSshPassword] = useState("");
+  const [sshRemember, setSshRemember] = useState(false);
+  const [sshTrustNewHost, setSshTrustNewHost] = useState(false);
+  const [s3Bucket, setS3Bucket] = useState("");
+  const [s3Region, setS3Region] = useState("");
+  const [s3Endpoint, setS3Endpoint] = useState("");
+  const [s3KeyId, setS3KeyId] = useState("");
+  const [s3Secret, setS3Secret] = useState("");
+  const [s3Remember, setS3Remember] = useState(false);
+  const [davUrl, setDavUrl] = useState("");
+  const [davUser, setDavUser] = useState("");
+  const [davPassword, setDavPassword] = useState("");
+  const [davRemember, setDavRemember] = useState(false);
+  const [davRate, setDavRate] = useState(10);
+  const [rcloneRemotes, setRcloneRemotes] = useState<RcloneRemote[] | null>(null);
+  const [rcloneRemote, setRcloneRemote] = useState("");
+  const [rclonePath, setRclonePath] = useState("");
+  const [cloudDrives, setCloudDrives] = useState<CloudDrive[]>([]);
+  const [cloudProvider, setCloudProvider] = useState<CloudProvider>("google_drive");
+  const [cloudClientId, setCloudClientId] = useState("");
+  const [signingIn, setSigningIn] = useState(false);
+  const [agentAddress, setAgentAddress] = useState("");
+  const [agentOverSsh, setAgentOverSsh] = useState(true);
+  const [agentToken, setAgentToken] = useState("");
+  const [agentPath, setAgentPath] = useState("");
+  const [androidFolders, setAndroidFolders] = useState<AndroidFolder[]>([]);
+  const [iosLocations, setIosLocations] = useState<IosLocation[]>([]);
+  // The drives of the last "Scan all drives", for the machine overview.
+  const [driveScans, setDriveScans] = useState<DriveScan[]>([]);
+  // Removable and network volumes mounted since the app started.
+  const [newDrives, setNewDrives] = useState<VolumeInfo[]>([]);
+  // Null walks every level.
+  const [maxDepth, setMaxDepth] = useState<number | null>(null);
+  // 0 keeps every child; the table fetches them a page at a time.
+  const [topChildren, setTopChildren] = useState<number>(0);
+  const [trackOwners, setTrackOwners] = useState<boolean>(false);
+  const [groupFiles, setGroupFiles] = useState<boolean>(false);
+  const [findDuplicates, setFindDuplicates] = useState<boolean>(false);
+  const [findSimilarImages, setFindSimilarImages] = useState<boolean>(false);
+  const [historyTrees, setHistoryTrees] = useState<boolean>(false);
+  // Finished scans kept current as their files change.
+  const [watched, setWatched] = useState<Set<string>>(new Set());
+  const [ignoreMode, setIgnoreMode] = useState<IgnoreMode>("off");
+  const [exportRows, setExportRows] = useState<ExportRows>("all");
+  const [exportMinMb, setExportMinMb] = useState(0);
+  const [exportDepth, setExportDepth] = useState(1);
   const [scanIds, setScanIds] = useState<string[]>([]);
 
   const [progress, setProgress] = useState<Map<string, ScanProgressEvent>>(new Map());
+  const [hashProgress, setHashProgress] = useState<Map<string, DuplicateProgressEvent>>(
+    new Map()
+  );
   const [roots, setRoots] = useState<Map<string, ScanNode>>(new Map());
+  // The children of each scan's root fetched so far, in the table's order.
+  const [pages, setPages] = useState<Map<string, ChildPage>>(new Map());
+  // Per-owner totals of each scan's root, for scans that tracked owners.
+  const [owners, setOwners] = useState<Map<string, OwnerStat[]>>(new Map());
+  const [extensions, setExtensions] = useState<Map<string, ExtensionStat[]>>(new Map());
+  const [histograms, setHistograms] = useState<Map<string, SizeBucket[]>>(new Map());
+  const [ignoreTotals, setIgnoreTotals] = useState<Map<string, IgnoreTotals>>(new Map());
+  const [unaccounted, setUnaccounted] = useState<Map<string, Unaccounted>>(new Map());
+  const [storageClasses, setStorageClasses] = useState<Map<string, StorageClassTotal[]>>(
+    new Map()
+  );
+  const [topFiles, setTopFiles] = useState<Map<string, FileEntry[]>>(new Map());
+  // Files of each scan untouched for a year.
+  const [oldFiles, setOldFiles] = useState<Map<string, OldFile[]>>(new Map());
+  const [emptyDirs, setEmptyDirs] = useState<Map<string, EmptyDir[]>>(new Map());
+  const [brokenLinks, setBrokenLinks] = useState<Map<string, BrokenLink[]>>(new Map());
+  const [duplicateFiles, setDuplicateFiles] = useState<Map<string, DuplicateFiles[]>>(new Map());
+  const [duplicateDirs, setDuplicateDirs] = useState<Map<string, DuplicateDirs[]>>(new Map());
+  const [similarImages, setSimilarImages] = useState<Map<string, SimilarImages[]>>(new Map());
+  const [gitRepos, setGitRepos] = useState<Map<string, GitRepo[]>>(new Map());
+  const [devArtifacts, setDevArtifacts] = useState<Map<string, DevProject[]>>(new Map());
+  const [games, setGames] = useState<Map<string, Game[]>>(new Map());
+  const [topDirs, setTopDirs] = useState<Map<string, DirCount[]>>(new Map());
   const [scanIdToPath, setScanIdToPath] = useState<Map<string, string>>(new Map());
   const [activeTab, setActiveTab] = useState<string | null>(null);
   const [sortBy, setSortBy] = useState<Map<string, { field: SortField; dir: SortDirection }>>(
     new Map()
   );
   const [errors, setErrors] = useState<string[]>([]);
+  // The disk image looked into, and what it holds.
+  const [inspectedImage, setInspectedImage] = useState<{ path: string; node: ScanNode } | null>(
+    null
+  );
+  // A folder the scan only totalled, walked further down on request.
+  const [expanded, setExpanded] = useState<{
+    scanId: string;
+    node: ScanNode;
+    page: ChildPage;
+  } | null>(null);
+  // The id of the node of the disk image being looked into.
+  const [inspecting, setInspecting] = useState<number | null>(null);
+  // Null until measured.
+  const [packageCaches, setPackageCaches] = useState<PackageCache[] | null>(null);
+  const [measuringCaches, setMeasuringCaches] = useState(false);
+  const [browserProfiles, setBrowserProfiles] = useState<BrowserProfile[] | null>(null);
+  const [measuringBrowsers, setMeasuringBrowsers] = useState(false);
+  const [cleanable, setCleanable] = useState<CleanableReport | null>(null);
+  const [measuringCleanable, setMeasuringCleanable] = useState(false);
+  const [trashBins, setTrashBins] = useState<TrashBin[] | null>(null);
+  const [measuringTrash, setMeasuringTrash] = useState(false);
+  const [shadowStorage, setShadowStorage] = useState<ShadowStorage[] | null>(null);
+  const [measuringShadows, setMeasuringShadows] = useState(false);
+  const [purgeable, setPurgeable] = useState<PurgeableSpace | null>(null);
+  const [measuringPurgeable, setMeasuringPurgeable] = useState(false);
+  const [systemFiles, setSystemFiles] = useState<SystemFile[] | null>(null);
+  const [measuringSystemFiles, setMeasuringSystemFiles] = useState(false);
+  const [containerUsage, setContainerUsage] = useState<EngineUsage[] | null>(null);
+  const [measuringContainers, setMeasuringContainers] = useState(false);
+  const [installedApps, setInstalledApps] = useState<InstalledApp[] | null>(null);
+  const [oldRevisions, setOldRevisions] = useState<OldRevision[]>([]);
+  const [measuringApps, setMeasuringApps] = useState(false);
+  const [wslDistros, setWslDistros] = useState<WslDistro[]>([]);
+  const [wslUsage, setWslUsage] = useState<Map<string, WslUsage>>(new Map());
+  const [wslBusy, setWslBusy] = useState<string | null>(null);
+  const [history, setHistory] = useState<HistoryEntry[]>([]);
+  const [schedules, setSchedules] = useState<ScheduledScan[]>([]);
+  const [scheduleInterval, setScheduleInterval] = useState(86400);
+  const [watchRules, setWatchRules] = useState<WatchRule[]>([]);
+  const [watchPath, setWatchPath] = useState("");
+  const [watchGrowthGb, setWatchGrowthGb] = useState(20);
+  const [watchSizeGb, setWatchSizeGb] = useState(0);
+  // The past scan whose tree is shown.
+  const [historyScan, setHistoryScan] = useState<HistoryScan | null>(null);
+  // What changed from a past scan to the one in the active tab.
+  const [comparison, setComparison] = useState<DeltaNode | null>(null);
+  // The fastest-growing folders of a root scanned more than once.
+  const [trends, setTrends] = useState<{ root: string; trends: GrowthTrend[] } | null>(null);
   const [status, setStatus] = useState<"idle" | "scanning" | "done">("idle");
+  // False when macOS Full Disk Access is missing; null elsewhere or unknown.
+  const [fullDiskAccess, setFullDiskAccess] = useState<boolean | null>(null);
 
   const unlistenRefs = useRef<(() => void)[]>([]);
 
+  useEffect(() => {
+    invoke<DiskAccess>("check_disk_access")
+      .then((access) => setFullDiskAccess(access.full_disk_access))
+      .catch(() => {});
+    invoke<WslDistro[]>("list_wsl_distros")
+      .then(setWslDistros)
+      .catch(() => {});
+    invoke<VolumeInfo[]>("list_volumes")
+      .then(setVolumes)
+      .catch(() => {});
+    invoke<MonitorSettings | null>("get_space_monitor")
+      .then(setMonitor)
+      .catch(() => {});
+    invoke<CloudDrive[]>("list_cloud_drives")
+      .then(setCloudDrives)
+      .catch(() => {});
+    loadHistory();
+    invoke<ScheduledScan[]>("list_scheduled_scans")
+      .then(setSchedules)
+      .catch((e) => setErrors((prev) => [...prev, String(e)]));
+    invoke<WatchRule[]>("list_watch_rules")
+      .then(setWatchRules)
+      .catch((e) => setErrors((prev) => [...prev, String(e)]));
+    if (IS_ANDROID) {
+      invoke<AndroidFolder[]>("list_android_folders")
+        .then(setAndroidFolders)
+        .catch(() => {});
+    }
+    if (IS_IOS) {
+      invoke<IosLocation[]>("list_ios_locations")
+        .then(setIosLocations)
+        .catch(() => {});
+    }
+  }, []);
+
   useEffect(() => {
     let mounted = true;
 
@@ -71,7 +843,7 @@ export default function App() {
       });
       const unlistenDone = await listen<ScanDoneEvent>("scan_done", (e) => {
         if (!mounted) return;
-        const rootPath = e.payload.root.path;
+        const rootPath = e.payload.root_path;
         setRoots((prev) => {
           const next = new Map(prev);
           next.set(e.payload.scan_id, e.payload.root);
@@ -83,6 +855,82 @@ export default function App() {
           return next;
         });
         setErrors((prev) => [...prev, ...(e.payload.errors ?? [])]);
+        setExtensions((prev) => new Map(prev).set(e.payload.scan_id, e.payload.top_extensions));
+        setHistograms((prev) => new Map(prev).set(e.payload.scan_id, e.payload.size_histogram));
+        const ignored = e.payload.ignore_totals;
+        if (ignored) {
+          setIgnoreTotals((prev) => new Map(prev).set(e.payload.scan_id, ignored));
+        }
+        const unaccountedBytes = e.payload.unaccounted_bytes;
+        if (unaccountedBytes !== undefined) {
+          const denied = e.payload.error_summary.categories.find(
+            (c) => c.kind === "permission_denied"
+          );
+          setUnaccounted((prev) =>
+            new Map(prev).set(e.payload.scan_id, {
+              bytes: unaccountedBytes,
+              parts: e.payload.unaccounted_parts ?? [],
+              deniedFolders: denied?.count ?? 0,
+            })
+          );
+        }
+        const classes = e.payload.storage_classes;
+        if (classes) {
+          setStorageClasses((prev) => new Map(prev).set(e.payload.scan_id, classes));
+        }
+        invoke<FileEntry[]>("get_top_files", { scanId: e.payload.scan_id, n: 20 })
+          .then((files) => setTopFiles((prev) => new Map(prev).set(e.payload.scan_id, files)))
+          .catch(() => {});
+        invoke<OldFile[]>("get_old_files", {
+          scanId: e.payload.scan_id,
+          olderThanDays: 365,
+          minSize: 1024 * 1024,
+        })
+          .then((files) => setOldFiles((prev) => new Map(prev).set(e.payload.scan_id, files)))
+          .catch(() => {});
+        invoke<EmptyDir[]>("get_empty_dirs", { scanId: e.payload.scan_id })
+          .then((dirs) => setEmptyDirs((prev) => new Map(prev).set(e.payload.scan_id, dirs)))
+          .catch(() => {});
+        invoke<BrokenLink[]>("get_broken_symlinks", { scanId: e.payload.scan_id })
+          .then((links) => setBrokenLinks((prev) => new Map(prev).set(e.payload.scan_id, links)))
+          .catch(() => {});
+        // Both fail for scans that didn't look for duplicates.
+        invoke<DuplicateFiles[]>("get_duplicate_files", { scanId: e.payload.scan_id })
+          .then((groups) =>
+            setDuplicateFiles((prev) => new Map(prev).set(e.payload.scan_id, groups))
+          )
+          .catch(() => {});
+        invoke<DuplicateDirs[]>("get_duplicate_dirs", { scanId: e.payload.scan_id })
+          .then((groups) =>
+            setDuplicateDirs((prev) => new Map(prev).set(e.payload.scan_id, groups))
+          )
+          .catch(() => {});
+        // Fails for scans that didn't look for similar images.
+        invoke<SimilarImages[]>("get_similar_images", { scanId: e.payload.scan_id })
+          .then((groups) =>
+            setSimilarImages((prev) => new Map(prev).set(e.payload.scan_id, groups))
+          )
+          .catch(() => {});
+        invoke<GitRepo[]>("get_git_repos", { scanId: e.payload.scan_id })
+          .then((repos) => setGitRepos((prev) => new Map(prev).set(e.payload.scan_id, repos)))
+          .catch(() => {});
+        invoke<Game[]>("get_games", { scanId: e.payload.scan_id })
+          .then((found) => setGames((prev) => new Map(prev).set(e.payload.scan_id, found)))
+          .catch(() => {});
+        invoke<DevProject[]>("get_dev_artifacts", { scanId: e.payload.scan_id })
+          .then((projects) =>
+            setDevArtifacts((prev) => new Map(prev).set(e.payload.scan_id, projects))
+          )
+          .catch(() => {});
+        invoke<DirCount[]>("get_top_dirs_by_count", { scanId: e.payload.scan_id, n: 20 })
+          .then((dirs) => setTopDirs((prev) => new Map(prev).set(e.payload.scan_id, dirs)))
+          .catch(() => {});
+        // Fails for scans that didn't track owners, which then show none.
+        invoke<OwnerStat[]>("get_owner_stats", { scanId: e.payload.scan_id, path: null })
+          .then((stats) =>
+            setOwners((prev) => new Map(prev).set(e.payload.scan_id, stats))
+          )
+          .catch(() => {});
         setScanIds((prev) => {
           const remaining = prev.filter((id) => id !== e.payload.scan_id);
           if (remaining.length === 0) {
@@ -93,7 +941,78 @@ export default function App() {
         // Set active tab if none selected
         setActiveTab((prev) => prev || rootPath);
       });
-      unlistenRefs.current = [unlistenProgress, unlistenDone];
+      const unlistenHashing = await listen<DuplicateProgressEvent>("duplicate_progress", (e) => {
+        if (!mounted) return;
+        setHashProgress((prev) => new Map(prev).set(e.payload.scan_id, e.payload));
+      });
+      const unlistenFailed = await listen<ScanFailedEvent>("scan_failed", (e) => {
+        if (!mounted) return;
+        setErrors((prev) => [...prev, `${e.payload.root}: scan failed: ${e.payload.message}`]);
+        setScanIds((prev) => {
+          const remaining = prev.filter((id) => id !== e.payload.scan_id);
+          if (remaining.length === 0) {
+            setStatus("done");
+          }
+          return remaining;
+        });
+      });
+      const unlistenBackground = await listen<BackgroundErrorEvent>("background_error", (e) => {
+        if (!mounted) return;
+        setErrors((prev) => [...prev, e.payload.message]);
+      });
+      const unlistenLowSpace = await listen<LowSpaceEvent>("low_space", (e) => {
+        if (!mounted) return;
+        setLowSpace((prev) => [
+          ...prev.filter((v) => v.mount_point !== e.payload.mount_point),
+          e.payload,
+        ]);
+      });
+      // The scan itself arrives through scan_done like any other.
+      const unlistenScheduled = await listen<ScheduledScanEvent>("scheduled_scan_done", () => {
+        if (!mounted) return;
+        loadHistory();
+        invoke<ScheduledScan[]>("list_scheduled_scans")
+          .then(setSchedules)
+          .catch(() => {});
+      });
+      const unlistenWatch = await listen<WatchAlertEvent>("watch_alert", (e) => {
+        if (!mounted) return;
+        setWatchRules((prev) =>
+          prev.map((rule) =>
+            rule.id === e.payload.rule_id ? { ...rule, size: e.payload.size, tripped: true } : rule
+          )
+        );
+      });
+      const unlistenChanged = await listen<NodeChangedEvent>("node_changed", (e) => {
+        if (!mounted) return;
+        const { scan_id, node, ancestors } = e.payload;
+        setRoots((prev) => {
+          const root = prev.get(scan_id);
+          if (!root) return prev;
+          const changed = root.id === node.id ? node : { ...root, ...ancestors[0] };
+          return new Map(prev).set(scan_id, changed);
+        });
+      });
+      const unlistenVolumes = await listen<VolumesChangedEvent>("volumes_changed", (e) => {
+        if (!mounted) return;
+        setVolumes(e.payload.volumes);
+        const gone = (v: VolumeInfo) =>
+          e.payload.unmounted.some((u) => u.mount_point === v.mount_point);
+        const added = e.payload.mounted.filter((v) => v.removable || v.network);
+        setNewDrives((prev) => [...prev.filter((v) => !gone(v)), ...added]);
+      });
+      unlistenRefs.current = [
+        unlistenProgress,
+        unlistenDone,
+        unlistenHashing,
+        unlistenFailed,
+        unlistenBackground,
+        unlistenLowSpace,
+        unlistenScheduled,
+        unlistenWatch,
+        unlistenChanged,
+        unlistenVolumes,
+      ];
     })();
 
     return () => {
@@ -103,12 +1022,41 @@ export default function App() {
     };
   }, []);
 
+  // Fetches the first page of children again whenever a root or the order
+  // of its table changes.
+  useEffect(() => {
+    for (const [scanId, root] of roots.entries()) {
+      if (root.child_count === undefined) continue;
+      const sort = sortBy.get(scanIdToPath.get(scanId) ?? "");
+      invoke<ChildPage>("get_children", {
+        scanId,
+        nodeId: root.id,
+        offset: 0,
+        limit: PAGE_SIZE,
+        ...childOrder(sort?.field ?? "size", sort?.dir ?? "desc"),
+      })
+        .then((page) => setPages((prev) => new Map(prev).set(scanId, page)))
+        .catch((e) => setErrors((prev) => [...prev, String(e)]));
+    }
+  }, [roots, scanIdToPath, sortBy]);
+
   const tabResults = useMemo(() => {
-    const results = new Map<string, { root: ScanNode; children: Array<{ node: ScanNode; pct: number }> }>();
+    const results = new Map<
+      string,
+      {
+        scanId: string;
+        path: string;
+        root: ScanNode;
+        children: Array<{ node: ScanNode; pct: number }>;
+        total: number;
+      }
+    >();
 
     for (const [scanId, root] of roots.entries()) {
-      const path = scanIdToPath.get(scanId) || root.path;
-      const children = root.children ?? [];
+      const path = scanIdToPath.get(scanId) ?? root.name;
+      const page = pages.get(scanId);
+      // A scan relayed from an elevated helper or an agent comes whole.
+      const children = root.children ?? page?.children ?? [];
       const total = Math.max(1, root.size);
 
       let sorted = children.map((node) => ({
@@ -116,29 +1064,64 @@ export default function App() {
         pct: (node.size / total) * 100,
       }));
 
-      // Apply sorting
+      // Apply sorting; fetched pages come sorted already
       const sort = sortBy.get(path) || { field: "size" as SortField, dir: "desc" as SortDirection };
-      sorted.sort((a, b) => {
-        let cmp = 0;
-        switch (sort.field) {
-          case "size":
-            cmp = a.node.size - b.node.size;
-            break;
-          case "name":
-            cmp = a.node.name.localeCompare(b.node.name);
-            break;
-          case "type":
-            cmp = a.node.kind.localeCompare(b.node.kind);
-            break;
-        }
-        return sort.dir === "asc" ? cmp : -cmp;
-      });
+      if (root.children) {
+        sorted.sort((a, b) => {
+          let cmp = 0;
+          switch (sort.field) {
+            case "size":
+              cmp = a.node.size - b.node.size;
+              break;
+            case "name":
+              cmp = a.node.name.localeCompare(b.node.name);
+              break;
+            case "type":
+              cmp = a.node.kind.localeCompare(b.node.kind);
+              break;
+          }
+          return sort.dir === "asc" ? cmp : -cmp;
+        });
+      }
 
-      results.set(path, { root, children: sorted });
+      const count = page?.total ?? sorted.length;
+      results.set(path, { scanId, path, root, children: sorted, total: count });
     }
 
     return results;
-  }, [roots, scanIdToPath, sortBy]);
+  }, [roots, pages, scanIdToPath, sortBy]);
+
+  const historyPage = historyScan?.scan_id ? pages.get(historyScan.scan_id) : undefined;
+
+  const scanOptions = {
+    max_depth: maxDepth ?? undefined,
+    top_children: topChildren,
+    track_owners: trackOwners,
+    group_files: groupFiles,
+    find_duplicates: findDuplicates,
+    find_similar_images: findSimilarImages,
+    ignore_mode: ignoreMode,
+    history_tree: historyTrees,
+  };
+
+  async function toggleMonitor(on: boolean) {
+    try {
+      if (!on) {
+        await invoke("stop_space_monitor");
+        setMonitor(null);
+        return;
+      }
+      const settings: MonitorSettings = {
+        interval_secs: 300,
+        min_free_bytes: monitorGb > 0 ? monitorGb * 1e9 : null,
+        min_free_percent: monitorPercent > 0 ? monitorPercent : null,
+      };
+      await invoke("start_space_monitor", { settings });
+      setMonitor(settings);
+    } catch (e) {
+      setErrors((prev) => [...prev, `Free-space monitor: ${String(e)}`]);
+    }
+  }
 
   async function startScan() {
     const validPaths = paths.map((p) => p.trim()).filter((p) => p.length > 0);
@@ -148,24 +1131,56 @@ export default function App() {
       return;
     }
 
+    // Resolve typed paths up front so every scan id maps to a real location.
+    const resolvedPaths: string[] = [];
+    const pathErrors: string[] = [];
+    for (const p of validPaths) {
+      try {
+        resolvedPaths.push(await invoke<string>("canonicalize_path", { path: p }));
+      } catch (e) {
+        pathErrors.push(String(e));
+      }
+    }
+    if (resolvedPaths.length === 0) {
+      setErrors(pathErrors);
+      return;
+    }
+
     setStatus("scanning");
     setRoots(new Map());
+    setOwners(new Map());
+    setExtensions(new Map());
+    setHistograms(new Map());
+    setIgnoreTotals(new Map());
+    setUnaccounted(new Map());
+    setStorageClasses(new Map());
+    setTopFiles(new Map());
+    setTopDirs(new Map());
+    setOldFiles(new Map());
+    setEmptyDirs(new Map());
+    setBrokenLinks(new Map());
+    setDuplicateFiles(new Map());
+    setDuplicateDirs(new Map());
+    setSimilarImages(new Map());
+    setGitRepos(new Map());
+    setDevArtifacts(new Map());
+    setGames(new Map());
     setScanIdToPath(new Map());
-    setErrors([]);
+    setErrors(pathErrors);
     setProgress(new Map());
+    setHashProgress(new Map());
     setActiveTab(null);
     setSortBy(new Map());
 
     const ids = await invoke<string[]>("start_multi_scan", {
-      paths: validPaths,
-      maxDepth,
-      topChildren,
+      paths: resolvedPaths,
+      options: scanOptions,
     });
     setScanIds(ids);
     // Map scan IDs to paths (assuming order matches)
     const idToPathMap = new Map<string, string>();
-    for (let i = 0; i < ids.length && i < validPaths.length; i++) {
-      idToPathMap.set(ids[i], validPaths[i]);
+    for (let i = 0; i < ids.length && i < resolvedPaths.length; i++) {
+      idToPathMap.set(ids[i], resolvedPaths[i]);
     }
     setScanIdToPath(idToPathMap);
   }
@@ -176,67 +1191,2082 @@ export default function App() {
     setPaths([trimmed]);
     setStatus("scanning");
     setRoots(new Map());
+    setOwners(new Map());
+    setExtensions(new Map());
+    setHistograms(new Map());
+    setIgnoreTotals(new Map());
+    setUnaccounted(new Map());
+    setStorageClasses(new Map());
+    setTopFiles(new Map());
+    setTopDirs(new Map());
+    setOldFiles(new Map());
+    setEmptyDirs(new Map());
+    setBrokenLinks(new Map());
+    setDuplicateFiles(new Map());
+    setDuplicateDirs(new Map());
+    setSimilarImages(new Map());
+    setGitRepos(new Map());
+    setDevArtifacts(new Map());
+    setGames(new Map());
     setErrors([]);
     setProgress(new Map());
+    setHashProgress(new Map());
     const ids = await invoke<string[]>("start_multi_scan", {
       paths: [trimmed],
-      maxDepth,
-      topChildren,
+      options: scanOptions,
     });
     setScanIds(ids);
   }
 
-  async function cancelScan() {
-    if (scanIds.length === 0) return;
-    for (const id of scanIds) {
-      await invoke("cancel_scan", { scanId: id }).catch(() => {});
-    }
-    setScanIds([]);
-    setStatus("idle");
-  }
+  async function scanAllDrives() {
+    setStatus("scanning");
+    setRoots(new Map());
+    setOwners(new Map());
+    setExtensions(new Map());
+    setHistograms(new Map());
+    setIgnoreTotals(new Map());
+    setUnaccounted(new Map());
+    setStorageClasses(new Map());
+    setTopFiles(new Map());
+    setTopDirs(new Map());
+    setOldFiles(new Map());
+    setEmptyDirs(new Map());
+    setBrokenLinks(new Map());
+    setDuplicateFiles(new Map());
+    setDuplicateDirs(new Map());
+    setSimilarImages(new Map());
+    setGitRepos(new Map());
+    setDevArtifacts(new Map());
+    setGames(new Map());
+    setErrors([]);
+    setProgress(new Map());
+    setHashProgress(new Map());
+    setActiveTab(null);
+    setSortBy(new Map());
+    try {
+      const scans = await invoke<DriveScan[]>("scan_fixed_drives", {
+        options: { ...scanOptions, size_mode: "allocated" },
+      });
+      setDriveScans(scans);
+      setPaths(scans.map((s) => s.volume.mount_point));
+      setScanIds(scans.map((s) => s.scan_id));
+      setScanIdToPath(new Map(scans.map((s) => [s.scan_id, s.volume.mount_point])));
+      if (scans.length === 0) setStatus("idle");
+    } catch (e) {
+      setErrors([String(e)]);
+      setStatus("idle");
+    }
+  }
+
+  // An empty password uses the one saved in the keyring for this user and host.
+  function smbCredentials() {
+    if (!smbUser.trim()) return null;
+    return {
+      user: smbUser.trim(),
+      domain: smbDomain.trim() || null,
+      password: smbPassword || null,
+      remember: smbRemember,
+    };
+  }
+
+  async function listSmbShares() {
+    setListingShares(true);
+    try {
+      setSmbShares(
+        await invoke<SmbShare[]>("list_smb_shares", {
+          host: smbHost,
+          credentials: smbCredentials(),
+        })
+      );
+    } catch (e) {
+      setSmbShares(null);
+      setErrors([String(e)]);
+    } finally {
+      setListingShares(false);
+    }
+  }
+
+  async function scanSmbShare(share: SmbShare) {
+    setStatus("scanning");
+    setRoots(new Map());
+    setOwners(new Map());
+    setExtensions(new Map());
+    setHistograms(new Map());
+    setIgnoreTotals(new Map());
+    setUnaccounted(new Map());
+    setStorageClasses(new Map());
+    setTopFiles(new Map());
+    setTopDirs(new Map());
+    setOldFiles(new Map());
+    setEmptyDirs(new Map());
+    setBrokenLinks(new Map());
+    setDuplicateFiles(new Map());
+    setDuplicateDirs(new Map());
+    setSimilarImages(new Map());
+    setGitRepos(new Map());
+    setDevArtifacts(new Map());
+    setGames(new Map());
+    setErrors([]);
+    setProgress(new Map());
+    setHashProgress(new Map());
+    setActiveTab(null);
+    setSortBy(new Map());
+    try {
+      const id = await invoke<string>("start_smb_scan", {
+        host: smbHost,
+        share: share.name,
+        credentials: smbCredentials(),
+        options: scanOptions,
+      });
+      setSmbPassword("");
+      setScanIds([id]);
+      setScanIdToPath(new Map([[id, share.path]]));
+    } catch (e) {
+      setErrors([String(e)]);
+      setStatus("idle");
+    }
+  }
+
+  // Scans a folder on a server over SSH; a blank password uses keys or the saved one.
+  async function scanSsh() {
+    const port = parseInt(sshPort, 10);
+    setStatus("scanning");
+    setRoots(new Map());
+    setOwners(new Map());
+    setExtensions(new Map());
+    setHistograms(new Map());
+    setIgnoreTotals(new Map());
+    setUnaccounted(new Map());
+    setStorageClasses(new Map());
+    setTopFiles(new Map());
+    setTopDirs(new Map());
+    setOldFiles(new Map());
+    setEmptyDirs(new Map());
+    setBrokenLinks(new Map());
+    setDuplicateFiles(new Map());
+    setDuplicateDirs(new Map());
+    setSimilarImages(new Map());
+    setGitRepos(new Map());
+    setDevArtifacts(new Map());
+    setGames(new Map());
+    setErrors([]);
+    setProgress(new Map());
+    setHashProgress(new Map());
+    setActiveTab(null);
+    setSortBy(new Map());
+    try {
+      const target = {
+        host: sshHost.trim(),
+        port: Number.isFinite(port) ? port : null,
+        user: sshUser.trim() || null,
+        path: sshPath.trim() || "/",
+        password: sshPassword || null,
+        remember: sshRemember,
+        trust_new_host: sshTrustNewHost,
+      };
+      const id = await invoke<string>("start_ssh_scan", { target, options: scanOptions });
+      setSshPassword("");
+      const shown = `${target.user ? `${target.user}@` : ""}${target.host}:${target.path}`;
+      setScanIds([id]);
+      setScanIdToPath(new Map([[id, shown]]));
+    } catch (e) {
+      setErrors([String(e)]);
+      setStatus("idle");
+    }
+  }
+
+  // Scans a bucket, or a folder of one; without a key, the AWS configuration is used.
+  async function scanS3() {
+    setStatus("scanning");
+    setRoots(new Map());
+    setOwners(new Map());
+    setExtensions(new Map());
+    setHistograms(new Map());
+    setIgnoreTotals(new Map());
+    setUnaccounted(new Map());
+    setStorageClasses(new Map());
+    setTopFiles(new Map());
+    setTopDirs(new Map());
+    setOldFiles(new Map());
+    setEmptyDirs(new Map());
+    setBrokenLinks(new Map());
+    setDuplicateFiles(new Map());
+    setDuplicateDirs(new Map());
+    setSimilarImages(new Map());
+    setGitRepos(new Map());
+    setDevArtifacts(new Map());
+    setGames(new Map());
+    setErrors([]);
+    setProgress(new Map());
+    setHashProgress(new Map());
+    setActiveTab(null);
+    setSortBy(new Map());
+    try {
+      const id = await invoke<string>("start_s3_scan", {
+        target: {
+          bucket: s3Bucket.trim(),
+          region: s3Region.trim() || null,
+          endpoint: s3Endpoint.trim() || null,
+          access_key_id: s3KeyId.trim() || null,
+          secret_access_key: s3Secret || null,
+          remember: s3Remember,
+        },
+        options: scanOptions,
+      });
+      setS3Secret("");
+      const bucket = s3Bucket.trim().replace(/^s3:\/\//, "").replace(/\/+$/, "");
+      setScanIds([id]);
+      setScanIdToPath(new Map([[id, `s3://${bucket}`]]));
+    } catch (e) {
+      setErrors([String(e)]);
+      setStatus("idle");
+    }
+  }
+
+  // Walks a WebDAV folder (Nextcloud and the like); a blank password uses the saved one.
+  async function scanWebDav() {
+    setStatus("scanning");
+    setRoots(new Map());
+    setOwners(new Map());
+    setExtensions(new Map());
+    setHistograms(new Map());
+    setIgnoreTotals(new Map());
+    setUnaccounted(new Map());
+    setStorageClasses(new Map());
+    setTopFiles(new Map());
+    setTopDirs(new Map());
+    setOldFiles(new Map());
+    setEmptyDirs(new Map());
+    setBrokenLinks(new Map());
+    setDuplicateFiles(new Map());
+    setDuplicateDirs(new Map());
+    setSimilarImages(new Map());
+    setGitRepos(new Map());
+    setDevArtifacts(new Map());
+    setGames(new Map());
+    setErrors([]);
+    setProgress(new Map());
+    setHashProgress(new Map());
+    setActiveTab(null);
+    setSortBy(new Map());
+    try {
+      const id = await invoke<string>("start_webdav_scan", {
+        target: {
+          url: davUrl.trim(),
+          user: davUser.trim() || null,
+          password: davPassword || null,
+          remember: davRemember,
+          requests_per_second: davRate > 0 ? davRate : null,
+        },
+        options: scanOptions,
+      });
+      setDavPassword("");
+      setScanIds([id]);
+      setScanIdToPath(new Map([[id, davUrl.trim().replace(/\/+$/, "")]]));
+    } catch (e) {
+      setErrors([String(e)]);
+      setStatus("idle");
+    }
+  }
+
+  async function listRcloneRemotes() {
+    try {
+      const remotes = await invoke<RcloneRemote[]>("list_rclone_remotes");
+      setRcloneRemotes(remotes);
+      if (!remotes.some((r) => r.name === rcloneRemote)) setRcloneRemote(remotes[0]?.name ?? "");
+    } catch (e) {
+      setRcloneRemotes(null);
+      setErrors([String(e)]);
+    }
+  }
+
+  // Lists a folder of any remote set up in rclone, through rclone itself.
+  async function scanRclone() {
+    setStatus("scanning");
+    setRoots(new Map());
+    setOwners(new Map());
+    setExtensions(new Map());
+    setHistograms(new Map());
+    setIgnoreTotals(new Map());
+    setUnaccounted(new Map());
+    setStorageClasses(new Map());
+    setTopFiles(new Map());
+    setTopDirs(new Map());
+    setOldFiles(new Map());
+    setEmptyDirs(new Map());
+    setBrokenLinks(new Map());
+    setDuplicateFiles(new Map());
+    setDuplicateDirs(new Map());
+    setSimilarImages(new Map());
+    setGitRepos(new Map());
+    setDevArtifacts(new Map());
+    setGames(new Map());
+    setErrors([]);
+    setProgress(new Map());
+    setHashProgress(new Map());
+    setActiveTab(null);
+    setSortBy(new Map());
+    try {
+      const path = rclonePath.trim().replace(/\/+$/, "");
+      const id = await invoke<string>("start_rclone_scan", {
+        remote: rcloneRemote,
+        path,
+        options: scanOptions,
+      });
+      setScanIds([id]);
+      setScanIdToPath(new Map([[id, `${rcloneRemote}:${path}`]]));
+    } catch (e) {
+      setErrors([String(e)]);
+      setStatus("idle");
+    }
+  }
+
+  // Signs in from the browser; the backend waits for it to come back.
+  async function connectCloudDrive() {
+    setSigningIn(true);
+    try {
+      const client = cloudClientId.trim() ? { client_id: cloudClientId.trim() } : null;
+      const drive = await invoke<CloudDrive>("connect_cloud_drive", {
+        provider: cloudProvider,
+        client,
+      });
+      setCloudDrives((drives) => [...drives.filter((d) => d.provider !== drive.provider), drive]);
+    } catch (e) {
+      setErrors([String(e)]);
+    } finally {
+      setSigningIn(false);
+    }
+  }
+
+  async function disconnectCloudDrive() {
+    try {
+      await invoke("disconnect_cloud_drive", { provider: cloudProvider });
+      setCloudDrives((drives) => drives.filter((d) => d.provider !== cloudProvider));
+    } catch (e) {
+      setErrors([String(e)]);
+    }
+  }
+
+  // Lists the whole drive through its API, sized by what counts against the quota.
+  async function scanCloudDrive() {
+    const drive = cloudDrives.find((d) => d.provider === cloudProvider);
+    if (!drive) return;
+    setStatus("scanning");
+    setRoots(new Map());
+    setOwners(new Map());
+    setExtensions(new Map());
+    setHistograms(new Map());
+    setIgnoreTotals(new Map());
+    setUnaccounted(new Map());
+    setStorageClasses(new Map());
+    setTopFiles(new Map());
+    setTopDirs(new Map());
+    setOldFiles(new Map());
+    setEmptyDirs(new Map());
+    setBrokenLinks(new Map());
+    setDuplicateFiles(new Map());
+    setDuplicateDirs(new Map());
+    setSimilarImages(new Map());
+    setGitRepos(new Map());
+    setDevArtifacts(new Map());
+    setGames(new Map());
+    setErrors([]);
+    setProgress(new Map());
+    setHashProgress(new Map());
+    setActiveTab(null);
+    setSortBy(new Map());
+    try {
+      const id = await invoke<string>("start_cloud_scan", {
+        provider: cloudProvider,
+        options: scanOptions,
+      });
+      const scheme = cloudProvider === "google_drive" ? "gdrive://" : "onedrive://";
+      setScanIds([id]);
+      setScanIdToPath(new Map([[id, `${scheme}${drive.account}`]]));
+    } catch (e) {
+      setErrors([String(e)]);
+      setStatus("idle");
+    }
+  }
+
+  // Has an agent on another machine scan it, started over SSH or already listening.
+  async function scanAgent() {
+    setStatus("scanning");
+    setRoots(new Map());
+    setOwners(new Map());
+    setExtensions(new Map());
+    setHistograms(new Map());
+    setIgnoreTotals(new Map());
+    setUnaccounted(new Map());
+    setStorageClasses(new Map());
+    setTopFiles(new Map());
+    setTopDirs(new Map());
+    setOldFiles(new Map());
+    setEmptyDirs(new Map());
+    setBrokenLinks(new Map());
+    setDuplicateFiles(new Map());
+    setDuplicateDirs(new Map());
+    setSimilarImages(new Map());
+    setGitRepos(new Map());
+    setDevArtifacts(new Map());
+    setGames(new Map());
+    setErrors([]);
+    setProgress(new Map());
+    setHashProgress(new Map());
+    setActiveTab(null);
+    setSortBy(new Map());
+    try {
+      const where = agentAddress.trim();
+      const target = agentOverSsh
+        ? { ssh_host: where, ssh_trust_new_host: sshTrustNewHost, path: agentPath }
+        : { address: where, token: agentToken, path: agentPath };
+      const id = await invoke<string>("start_agent_scan", { target, options: scanOptions });
+      setScanIds([id]);
+      setScanIdToPath(new Map([[id, agentPath.trim()]]));
+    } catch (e) {
+      setErrors([String(e)]);
+      setStatus("idle");
+    }
+  }
+
+  async function pickAndroidFolder() {
+    try {
+      const folder = await invoke<AndroidFolder>("pick_android_folder");
+      setAndroidFolders((folders) => [...folders.filter((f) => f.uri !== folder.uri), folder]);
+    } catch (e) {
+      setErrors([String(e)]);
+    }
+  }
+
+  // A picked folder through the Storage Access Framework, or with no folder
+  // all of shared storage through MediaStore.
+  async function scanAndroid(folder: AndroidFolder | null) {
+    setStatus("scanning");
+    setRoots(new Map());
+    setOwners(new Map());
+    setExtensions(new Map());
+    setHistograms(new Map());
+    setIgnoreTotals(new Map());
+    setUnaccounted(new Map());
+    setStorageClasses(new Map());
+    setTopFiles(new Map());
+    setTopDirs(new Map());
+    setOldFiles(new Map());
+    setEmptyDirs(new Map());
+    setBrokenLinks(new Map());
+    setDuplicateFiles(new Map());
+    setDuplicateDirs(new Map());
+    setSimilarImages(new Map());
+    setGitRepos(new Map());
+    setDevArtifacts(new Map());
+    setGames(new Map());
+    setErrors([]);
+    setProgress(new Map());
+    setHashProgress(new Map());
+    setActiveTab(null);
+    setSortBy(new Map());
+    try {
+      const id = await invoke<string>("start_android_scan", {
+        uri: folder?.uri ?? null,
+        options: scanOptions,
+      });
+      setScanIds([id]);
+      setScanIdToPath(new Map([[id, folder?.uri ?? "/storage/emulated/0"]]));
+    } catch (e) {
+      setErrors([String(e)]);
+      setStatus("idle");
+    }
+  }
+
+  async function pickIosFolder() {
+    try {
+      const location = await invoke<IosLocation>("pick_ios_folder");
+      setIosLocations((locations) => [...locations, location]);
+    } catch (e) {
+      setErrors([String(e)]);
+    }
+  }
+
+  async function forgetIosFolder(location: IosLocation) {
+    if (location.id === null) return;
+    try {
+      await invoke("forget_ios_folder", { id: location.id });
+      setIosLocations((locations) => locations.filter((l) => l.id !== location.id));
+    } catch (e) {
+      setErrors([String(e)]);
+    }
+  }
+
+  // The app's container, or a folder picked in Files, kept open while it's walked.
+  async function scanIos(location: IosLocation) {
+    setStatus("scanning");
+    setRoots(new Map());
+    setOwners(new Map());
+    setExtensions(new Map());
+    setHistograms(new Map());
+    setIgnoreTotals(new Map());
+    setUnaccounted(new Map());
+    setStorageClasses(new Map());
+    setTopFiles(new Map());
+    setTopDirs(new Map());
+    setOldFiles(new Map());
+    setEmptyDirs(new Map());
+    setBrokenLinks(new Map());
+    setDuplicateFiles(new Map());
+    setDuplicateDirs(new Map());
+    setSimilarImages(new Map());
+    setGitRepos(new Map());
+    setDevArtifacts(new Map());
+    setGames(new Map());
+    setErrors([]);
+    setProgress(new Map());
+    setHashProgress(new Map());
+    setActiveTab(null);
+    setSortBy(new Map());
+    try {
+      const id = await invoke<string>("start_ios_scan", {
+        id: location.id,
+        options: scanOptions,
+      });
+      setScanIds([id]);
+      setScanIdToPath(new Map([[id, location.path]]));
+    } catch (e) {
+      setErrors([String(e)]);
+      setStatus("idle");
+    }
+  }
+
+  // A scan captured elsewhere, by `ncdu -o`, `du -ab` or WizTree, opened like one run here.
+  async function importScan() {
+    const file = await open({
+      multiple: false,
+      title: "Open an ncdu export, du output or WizTree export",
+      filters: [
+        { name: "Captured scans", extensions: ["json", "txt", "du", "csv"] },
+        { name: "All files", extensions: ["*"] },
+      ],
+    });
+    if (typeof file !== "string") return;
+    setStatus("scanning");
+    setRoots(new Map());
+    setOwners(new Map());
+    setExtensions(new Map());
+    setHistograms(new Map());
+    setIgnoreTotals(new Map());
+    setUnaccounted(new Map());
+    setStorageClasses(new Map());
+    setTopFiles(new Map());
+    setTopDirs(new Map());
+    setOldFiles(new Map());
+    setEmptyDirs(new Map());
+    setBrokenLinks(new Map());
+    setDuplicateFiles(new Map());
+    setDuplicateDirs(new Map());
+    setSimilarImages(new Map());
+    setGitRepos(new Map());
+    setDevArtifacts(new Map());
+    setGames(new Map());
+    setErrors([]);
+    setProgress(new Map());
+    setHashProgress(new Map());
+    setActiveTab(null);
+    setSortBy(new Map());
+    try {
+      const id = await invoke<string>("import_scan", { path: file, options: scanOptions });
+      setScanIds([id]);
+      setScanIdToPath(new Map([[id, file]]));
+    } catch (e) {
+      setErrors([String(e)]);
+      setStatus("idle");
+    }
+  }
+
+  // Writes the tree of a finished scan to a file other tools can open.
+  async function exportScan(scanId: string, format: ExportFormat) {
+    const { label, extension } = EXPORT_FORMATS[format];
+    const path = await save({ filters: [{ name: label, extensions: [extension] }] });
+    if (!path) return;
+    try {
+      await invoke("export_scan", {
+        scanId,
+        format,
+        path,
+        options: {
+          rows: exportRows,
+          min_size: Math.round(exportMinMb * 1024 * 1024),
+          max_depth: exportDepth,
+        },
+      });
+    } catch (e) {
+      setErrors([String(e)]);
+    }
+  }
+
+  // Catches a finished scan up with what changed on disk since, from the
+  // NTFS change journal.
+  async function refreshScan(scanId: string) {
+    try {
+      const refresh = await invoke<ScanRefresh>("refresh_scan", { scanId });
+      setRoots((prev) => new Map(prev).set(scanId, refresh.root));
+    } catch (e) {
+      setErrors([String(e)]);
+    }
+  }
+
+  // Walks one folder of a finished scan again; node_changed brings the tree
+  // up to date.
+  async function rescanNode(scanId: string, node: ScanNode) {
+    try {
+      await invoke("rescan_node", { scanId, nodeId: node.id });
+    } catch (e) {
+      setErrors([String(e)]);
+    }
+  }
+
+  // Starts or stops keeping a finished scan current as files change.
+  async function toggleWatch(scanId: string) {
+    const on = !watched.has(scanId);
+    try {
+      await invoke(on ? "watch_scan" : "unwatch_scan", { scanId });
+      setWatched((prev) => {
+        const next = new Set(prev);
+        if (on) next.add(scanId);
+        else next.delete(scanId);
+        return next;
+      });
+    } catch (e) {
+      setErrors([String(e)]);
+    }
+  }
+
+  // A printable summary of a finished scan, to attach to a ticket.
+  async function generateReport(scanId: string) {
+    const pdfPath = await save({ filters: [{ name: "PDF", extensions: ["pdf"] }] });
+    if (!pdfPath) return;
+    try {
+      await invoke("generate_report", { scanId, pdfPath });
+    } catch (e) {
+      setErrors([String(e)]);
+    }
+  }
+
+  // Runs the first location through an elevated helper, after the OS prompt.
+  async function startElevatedScan() {
+    const target = paths.map((p) => p.trim()).find((p) => p.length > 0);
+    if (!target) return;
+    setStatus("scanning");
+    setRoots(new Map());
+    setOwners(new Map());
+    setExtensions(new Map());
+    setHistograms(new Map());
+    setIgnoreTotals(new Map());
+    setUnaccounted(new Map());
+    setStorageClasses(new Map());
+    setTopFiles(new Map());
+    setTopDirs(new Map());
+    setOldFiles(new Map());
+    setEmptyDirs(new Map());
+    setBrokenLinks(new Map());
+    setDuplicateFiles(new Map());
+    setDuplicateDirs(new Map());
+    setSimilarImages(new Map());
+    setGitRepos(new Map());
+    setDevArtifacts(new Map());
+    setGames(new Map());
+    setErrors([]);
+    setProgress(new Map());
+    setHashProgress(new Map());
+    setActiveTab(null);
+    try {
+      const id = await invoke<string>("start_elevated_scan", {
+        path: target,
+        options: scanOptions,
+      });
+      setScanIds([id]);
+      setScanIdToPath(new Map([[id, target]]));
+    } catch (e) {
+      setErrors([String(e)]);
+      setStatus("idle");
+    }
+  }
+
+  async function cancelScan() {
+    if (scanIds.length === 0) return;
+    await invoke("cancel_all_scans").catch(() => {});
+    setScanIds([]);
+    setStatus("idle");
+  }
+
+  async function removeEmptyDirs(scanId: string) {
+    const dirs = emptyDirs.get(scanId) ?? [];
+    if (!confirm(`Remove ${dirs.length} empty folders?`)) return;
+    try {
+      const result = await invoke<RemovedDirs>("remove_empty_dirs", {
+        scanId,
+        paths: dirs.map((d) => d.path),
+      });
+      setEmptyDirs((prev) =>
+        new Map(prev).set(scanId, dirs.filter((d) => !result.removed.includes(d.path)))
+      );
+      setErrors((prev) => [...prev, ...result.errors]);
+    } catch (e) {
+      setErrors((prev) => [...prev, String(e)]);
+    }
+  }
+
+  async function removeDevArtifacts(scanId: string) {
+    const projects = devArtifacts.get(scanId) ?? [];
+    const paths = projects.flatMap((p) => p.artifacts.map((a) => a.path));
+    const bytes = projects.reduce((sum, p) => sum + p.bytes, 0);
+    if (!confirm(`Remove ${paths.length} folders (${formatBytes(bytes)})? They can be rebuilt.`)) {
+      return;
+    }
+    try {
+      const result = await invoke<RemovedDirs>("remove_dev_artifacts", { scanId, paths });
+      setDevArtifacts((prev) =>
+        new Map(prev).set(
+          scanId,
+          projects
+            .map((p) => {
+              const artifacts = p.artifacts.filter((a) => !result.removed.includes(a.path));
+              return { ...p, artifacts, bytes: artifacts.reduce((sum, a) => sum + a.bytes, 0) };
+            })
+            .filter((p) => p.artifacts.length > 0)
+        )
+      );
+      setErrors((prev) => [...prev, ...result.errors]);
+    } catch (e) {
+      setErrors((prev) => [...prev, String(e)]);
+    }
+  }
+
+  async function measurePackageCaches() {
+    setMeasuringCaches(true);
+    try {
+      setPackageCaches(await invoke<PackageCache[]>("get_package_caches"));
+    } catch (e) {
+      setErrors((prev) => [...prev, String(e)]);
+    } finally {
+      setMeasuringCaches(false);
+    }
+  }
+
+  async function clearPackageCache(cache: PackageCache) {
+    const name = PACKAGE_TOOLS[cache.tool] ?? cache.tool;
+    if (!confirm(`Clear the ${name} cache (${formatBytes(cache.bytes)})?`)) return;
+    try {
+      await invoke("clear_package_cache", { tool: cache.tool });
+    } catch (e) {
+      setErrors((prev) => [...prev, String(e)]);
+    }
+    await measurePackageCaches();
+  }
+
+  async function measureBrowsers() {
+    setMeasuringBrowsers(true);
+    try {
+      setBrowserProfiles(await invoke<BrowserProfile[]>("get_browser_storage"));
+    } catch (e) {
+      setErrors((prev) => [...prev, String(e)]);
+    } finally {
+      setMeasuringBrowsers(false);
+    }
+  }
+
+  async function measureCleanable() {
+    setMeasuringCleanable(true);
+    try {
+      setCleanable(await invoke<CleanableReport>("get_cleanable_locations"));
+    } catch (e) {
+      setErrors((prev) => [...prev, String(e)]);
+    } finally {
+      setMeasuringCleanable(false);
+    }
+  }
+
+  async function measureTrash() {
+    setMeasuringTrash(true);
+    try {
+      setTrashBins(await invoke<TrashBin[]>("get_trash_usage"));
+    } catch (e) {
+      setErrors((prev) => [...prev, String(e)]);
+    } finally {
+      setMeasuringTrash(false);
+    }
+  }
+
+  async function emptyTrash(bin: TrashBin | null) {
+    const what = bin ? `the ${TRASH_NAME} on ${bin.volume}` : `every ${TRASH_NAME}`;
+    if (!confirm(`Empty ${what}? Its files can't be restored afterwards.`)) return;
+    try {
+      setTrashBins(await invoke<TrashBin[]>("empty_trash", { volume: bin?.volume ?? null }));
+    } catch (e) {
+      setErrors((prev) => [...prev, String(e)]);
+      await measureTrash();
+    }
+  }
+
+  async function measureSystemFiles() {
+    setMeasuringSystemFiles(true);
+    try {
+      setSystemFiles(await invoke<SystemFile[]>("get_system_files"));
+    } catch (e) {
+      setErrors((prev) => [...prev, String(e)]);
+    } finally {
+      setMeasuringSystemFiles(false);
+    }
+  }
+
+  async function disableHibernation() {
+    if (!confirm("Turn off hibernation? Fast startup is turned off with it.")) return;
+    try {
+      setSystemFiles(await invoke<SystemFile[]>("disable_hibernation"));
+    } catch (e) {
+      setErrors((prev) => [...prev, String(e)]);
+    }
+  }
+
+  async function measureShadowStorage() {
+    setMeasuringShadows(true);
+    try {
+      setShadowStorage(await invoke<ShadowStorage[]>("get_shadow_storage"));
+    } catch (e) {
+      setErrors((prev) => [...prev, String(e)]);
+    } finally {
+      setMeasuringShadows(false);
+    }
+  }
+
+  async function measurePurgeable() {
+    setMeasuringPurgeable(true);
+    try {
+      setPurgeable(await invoke<PurgeableSpace>("get_purgeable_space", { volume: "/" }));
+    } catch (e) {
+      setErrors((prev) => [...prev, String(e)]);
+    } finally {
+      setMeasuringPurgeable(false);
+    }
+  }
+
+  async function thinSnapshots() {
+    if (!confirm("Let Time Machine delete local snapshots, oldest first?")) return;
+    setMeasuringPurgeable(true);
+    try {
+      // As much as can go: the purge amount is only an upper bound.
+      setPurgeable(
+        await invoke<PurgeableSpace>("thin_local_snapshots", {
+          volume: "/",
+          bytes: Number.MAX_SAFE_INTEGER,
+          urgency: 4,
+        })
+      );
+    } catch (e) {
+      setErrors((prev) => [...prev, String(e)]);
+    } finally {
+      setMeasuringPurgeable(false);
+    }
+  }
+
+  async function measureContainers() {
+    setMeasuringContainers(true);
+    try {
+      setContainerUsage(await invoke<EngineUsage[]>("get_container_usage"));
+    } catch (e) {
+      setContainerUsage([]);
+      setErrors((prev) => [...prev, String(e)]);
+    } finally {
+      setMeasuringContainers(false);
+    }
+  }
+
+  async function pruneContainers(engine: EngineUsage) {
+    const prunable = engine.usage.filter((u) => u.reclaimable > 0);
+    const bytes = prunable.reduce((sum, u) => sum + u.reclaimable, 0);
+    const question = `Remove everything ${engine.engine} has no container using`;
+    if (!confirm(`${question} (${formatBytes(bytes)})?`)) return;
+    try {
+      const updated = await invoke<EngineUsage>("docker_prune", {
+        engine: engine.engine,
+        kinds: prunable.map((u) => u.kind),
+        allImages: true,
+      });
+      setContainerUsage((prev) =>
+        (prev ?? []).map((e) => (e.engine === updated.engine ? updated : e))
+      );
+    } catch (e) {
+      setErrors((prev) => [...prev, String(e)]);
+    }
+  }
+
+  async function measureApps() {
+    setMeasuringApps(true);
+    try {
+      const [apps, old] = await Promise.all([
+        invoke<InstalledApp[]>("get_app_storage"),
+        invoke<OldRevision[]>("list_old_revisions"),
+      ]);
+      setInstalledApps(apps);
+      setOldRevisions(old);
+    } catch (e) {
+      setErrors((prev) => [...prev, String(e)]);
+    } finally {
+      setMeasuringApps(false);
+    }
+  }
+
+  async function measureWsl(name: string) {
+    setWslBusy(name);
+    try {
+      const usage = await invoke<WslUsage>("get_wsl_usage", { name });
+      setWslUsage((prev) => new Map(prev).set(name, usage));
+    } catch (e) {
+      setErrors((prev) => [...prev, String(e)]);
+    } finally {
+      setWslBusy(null);
+    }
+  }
+
+  // Scans a distribution as listed from inside it; the tree comes in a tab like any scan.
+  async function scanWslInside(name: string) {
+    setWslBusy(name);
+    setStatus("scanning");
+    setRoots(new Map());
+    setOwners(new Map());
+    setExtensions(new Map());
+    setHistograms(new Map());
+    setIgnoreTotals(new Map());
+    setUnaccounted(new Map());
+    setStorageClasses(new Map());
+    setTopFiles(new Map());
+    setTopDirs(new Map());
+    setOldFiles(new Map());
+    setEmptyDirs(new Map());
+    setBrokenLinks(new Map());
+    setDuplicateFiles(new Map());
+    setDuplicateDirs(new Map());
+    setSimilarImages(new Map());
+    setGitRepos(new Map());
+    setDevArtifacts(new Map());
+    setGames(new Map());
+    setErrors([]);
+    setProgress(new Map());
+    setHashProgress(new Map());
+    setActiveTab(null);
+    setSortBy(new Map());
+    try {
+      const id = await invoke<string>("scan_wsl_distro", { name, options: scanOptions });
+      setScanIds([id]);
+      setScanIdToPath(new Map([[id, `\\\\wsl$\\${name}`]]));
+    } catch (e) {
+      setErrors([String(e)]);
+      setStatus("idle");
+    } finally {
+      setWslBusy(null);
+    }
+  }
+
+  // Keeps the whole tree of every run, for comparisons and trends.
+  async function scheduleScan() {
+    const path = paths.find((p) => p.trim());
+    if (!path) return;
+    try {
+      const scan = await invoke<ScheduledScan>("add_scheduled_scan", {
+        path,
+        intervalSecs: scheduleInterval,
+        options: { ...scanOptions, history_tree: true },
+      });
+      setSchedules((prev) => [...prev, scan]);
+    } catch (e) {
+      setErrors((prev) => [...prev, String(e)]);
+    }
+  }
+
+  async function setScheduleEnabled(id: string, enabled: boolean) {
+    try {
+      await invoke("set_scheduled_scan_enabled", { id, enabled });
+      setSchedules((prev) => prev.map((s) => (s.id === id ? { ...s, enabled } : s)));
+    } catch (e) {
+      setErrors((prev) => [...prev, String(e)]);
+    }
+  }
+
+  async function removeSchedule(id: string) {
+    try {
+      await invoke("remove_scheduled_scan", { id });
+      setSchedules((prev) => prev.filter((s) => s.id !== id));
+    } catch (e) {
+      setErrors((prev) => [...prev, String(e)]);
+    }
+  }
+
+  async function addWatchRule() {
+    try {
+      const rule = await invoke<WatchRule>("add_watch_rule", {
+        path: watchPath,
+        maxGrowth: watchGrowthGb > 0 ? watchGrowthGb * 1e9 : null,
+        maxSize: watchSizeGb > 0 ? watchSizeGb * 1e9 : null,
+      });
+      setWatchRules((prev) => [...prev, rule]);
+      setWatchPath("");
+    } catch (e) {
+      setErrors((prev) => [...prev, String(e)]);
+    }
+  }
+
+  async function removeWatchRule(id: string) {
+    try {
+      await invoke("remove_watch_rule", { id });
+      setWatchRules((prev) => prev.filter((rule) => rule.id !== id));
+    } catch (e) {
+      setErrors((prev) => [...prev, String(e)]);
+    }
+  }
+
+  async function loadHistory() {
+    try {
+      setHistory(await invoke<HistoryEntry[]>("list_scan_history", { path: null, volume: null }));
+    } catch (e) {
+      setErrors((prev) => [...prev, String(e)]);
+    }
+  }
+
+  async function showHistoryScan(id: number) {
+    try {
+      const scan = await invoke<HistoryScan>("get_history_scan", { id });
+      if (scan.scan_id && scan.tree) {
+        const page = await invoke<ChildPage>("get_children", {
+          scanId: scan.scan_id,
+          nodeId: scan.tree.id,
+          offset: 0,
+          limit: PAGE_SIZE,
+        });
+        setPages((prev) => new Map(prev).set(scan.scan_id!, page));
+      }
+      setHistoryScan(scan);
+    } catch (e) {
+      setErrors((prev) => [...prev, String(e)]);
+    }
+  }
+
+  // Changes under a megabyte are left out.
+  async function compareWithHistory(id: number, scanId: string) {
+    try {
+      setComparison(
+        await invoke<DeltaNode>("compare_scans", { scanA: id, scanB: scanId, minDelta: 1 << 20 })
+      );
+    } catch (e) {
+      setErrors((prev) => [...prev, String(e)]);
+    }
+  }
+
+  async function loadTrends(root: string) {
+    try {
+      setTrends({ root, trends: await invoke<GrowthTrend[]>("get_growth_trends", { path: root }) });
+    } catch (e) {
+      setErrors((prev) => [...prev, String(e)]);
+    }
+  }
+
+  async function deleteHistoryScan(id: number) {
+    try {
+      await invoke("delete_history_scan", { id });
+      setHistory((prev) => prev.filter((entry) => entry.id !== id));
+      setHistoryScan((prev) => (prev?.id === id ? null : prev));
+    } catch (e) {
+      setErrors((prev) => [...prev, String(e)]);
+    }
+  }
+
+  // Where `node` of the scan `scanId` is. Nothing is kept here of a scan
+  // relayed from an elevated helper or an agent, but its table only lists
+  // the children of its root.
+  async function nodePath(scanId: string, node: ScanNode) {
+    if (roots.get(scanId)?.children) {
+      const dir = scanIdToPath.get(scanId) ?? "";
+      const sep = dir.includes("\\") ? "\\" : "/";
+      return dir.endsWith(sep) ? dir + node.name : dir + sep + node.name;
+    }
+    return invoke<string>("get_node_path", { scanId, nodeId: node.id });
+  }
+
+  async function scanChild(scanId: string, node: ScanNode) {
+    try {
+      await scanPath(await nodePath(scanId, node));
+    } catch (e) {
+      setErrors((prev) => [...prev, String(e)]);
+    }
+  }
+
+  async function inspectDiskImage(scanId: string, image: ScanNode) {
+    setInspecting(image.id);
+    try {
+      const path = await nodePath(scanId, image);
+      const node = await invoke<ScanNode>("inspect_disk_image", { path, options: scanOptions });
+      setInspectedImage({ path, node });
+    } catch (e) {
+      setErrors((prev) => [...prev, String(e)]);
+    } finally {
+      setInspecting(null);
+    }
+  }
+
+  // Appends the next page of children to the table of a scan.
+  async function showMoreChildren(scanId: string, root: ScanNode) {
+    const page = pages.get(scanId);
+    if (!page) return;
+    const sort = sortBy.get(scanIdToPath.get(scanId) ?? "");
+    try {
+      const next = await invoke<ChildPage>("get_children", {
+        scanId,
+        nodeId: root.id,
+        offset: page.children.length,
+        limit: PAGE_SIZE,
+        ...childOrder(sort?.field ?? "size", sort?.dir ?? "desc"),
+      });
+      setPages((prev) =>
+        new Map(prev).set(scanId, { ...next, children: [...page.children, ...next.children] })
+      );
+    } catch (e) {
+      setErrors((prev) => [...prev, String(e)]);
+    }
+  }
+
+  async function expandNode(scanId: string, node: ScanNode) {
+    try {
+      const page = await invoke<ChildPage>("expand_node", {
+        scanId,
+        nodeId: node.id,
+        extraDepth: 1,
+      });
+      setExpanded({ scanId, node, page });
+    } catch (e) {
+      setErrors((prev) => [...prev, String(e)]);
+    }
+  }
+
+  // Appends the next page of children, largest first, to the folder walked further down.
+  async function showMoreExpanded() {
+    if (!expanded) return;
+    const { scanId, node, page } = expanded;
+    try {
+      const next = await invoke<ChildPage>("get_children", {
+        scanId,
+        nodeId: node.id,
+        offset: page.children.length,
+        limit: PAGE_SIZE,
+      });
+      setExpanded({
+        scanId,
+        node,
+        page: { ...next, children: [...page.children, ...next.children] },
+      });
+    } catch (e) {
+      setErrors((prev) => [...prev, String(e)]);
+    }
+  }
+
+  function addPath() {
+    if (paths.length >= 10) {
+      alert("Maximum 10 locations allowed");
+      return;
+    }
+    setPaths([...paths, ""]);
+  }
+
+  function setSort(path: string, field: SortField) {
+    setSortBy((prev) => {
+      const next = new Map(prev);
+      const current = next.get(path);
+      if (current?.field === field) {
+        // Toggle direction
+        next.set(path, { field, dir: current.dir === "asc" ? "desc" : "asc" });
+      } else {
+        // Default to desc for size, asc for name/type
+        next.set(path, { field, dir: field === "size" ? "desc" : "asc" });
+      }
+      return next;
+    });
+  }
+
+  function removePath(index: number) {
+    setPaths(paths.filter((_, i) => i !== index));
+  }
+
+  function updatePath(index: number, value: string) {
+    const updated = [...paths];
+    updated[index] = value;
+    setPaths(updated);
+  }
+
+  return (
+    <div className="app">
+      <div className="card">
+        <h2 style={{ margin: "0 0 6px 0" }}>Space Usage</h2>
+        <div className="muted" style={{ marginBottom: 14 }}>
+          Scan a folder or mounted network path. Example: <code>/</code>, <code>/home</code>,{" "}
+          <code>/mnt/nas</code>, <code>C:\</code>, <code>\\server\share</code>
+        </div>
+
+        {fullDiskAccess === false && (
+          <div className="row" style={{ marginBottom: 14 }}>
+            <span className="muted" style={{ marginRight: "auto" }}>
+              Without Full Disk Access, Mail, Messages, Safari and other protected folders
+              can't be measured.
+            </span>
+            <button onClick={() => invoke("open_disk_access_settings").catch(() => {})}>
+              Open Privacy settings
+            </button>
+          </div>
+        )}
+
+        {volumes.length > 0 && (
+          <div className="row" style={{ marginBottom: 14, gap: 8, flexWrap: "wrap" }}>
+            {volumes.map((volume) => (
+              <button
+                key={volume.mount_point}
+                onClick={() =>
+                  setPaths([...paths.filter((p) => p.trim()), volume.mount_point].slice(0, 10))
+                }
+                disabled={status === "scanning" || paths.includes(volume.mount_point)}
+                title={[
+                  volume.filesystem,
+                  volume.system && "system",
+                  volume.removable && "removable",
+                  volume.network && "network",
+                ]
+                  .filter(Boolean)
+                  .join(", ")}
+              >
+                {volume.label ? `${volume.label} (${volume.mount_point})` : volume.mount_point}{" "}
+                <span className="muted">
+                  {formatBytes(volume.free_bytes)} free of {formatBytes(volume.total_bytes)}
+                </span>
+              </button>
+            ))}
+            <button onClick={scanAllDrives} disabled={status === "scanning"}>
+              Scan all drives
+            </button>
+          </div>
+        )}
+
+        <div className="card" style={{ marginBottom: 14 }}>
+          <div className="row" style={{ gap: 8, flexWrap: "wrap" }}>
+            <strong style={{ marginRight: "auto" }}>Network share</strong>
+            <input
+              value={smbHost}
+              onChange={(e) => setSmbHost(e.target.value)}
+              placeholder="Host"
+              spellCheck={false}
+            />
+            <input
+              value={smbUser}
+              onChange={(e) => setSmbUser(e.target.value)}
+              placeholder="User (optional)"
+              spellCheck={false}
+            />
+            <input
+              value={smbDomain}
+              onChange={(e) => setSmbDomain(e.target.value)}
+              placeholder="Domain"
+              spellCheck={false}
+              style={{ width: 110, minWidth: 110 }}
+            />
+            <input
+              type="password"
+              value={smbPassword}
+              onChange={(e) => setSmbPassword(e.target.value)}
+              placeholder="Password (blank: saved)"
+            />
+            <label className="muted" title="Keep the password in the system keyring">
+              <input
+                type="checkbox"
+                checked={smbRemember}
+                onChange={(e) => setSmbRemember(e.target.checked)}
+              />{" "}
+              Remember
+            </label>
+            <button onClick={listSmbShares} disabled={!smbHost.trim() || listingShares}>
+              {listingShares ? "Listing…" : "List shares"}
+            </button>
+            {smbUser.trim() && (
+              <button
+                onClick={() =>
+                  invoke("forget_smb_password", { host: smbHost, user: smbUser }).catch((e) =>
+                    setErrors([String(e)])
+                  )
+                }
+                disabled={!smbHost.trim()}
+              >
+                Forget password
+              </button>
+            )}
+          </div>
+          {smbShares?.length === 0 && (
+            <div className="muted" style={{ marginTop: 8 }}>
+              No shared folders found.
+            </div>
+          )}
+          {smbShares?.map((share) => (
+            <div key={share.name} className="row" style={{ marginTop: 8, gap: 12 }}>
+              <span style={{ marginRight: "auto" }}>
+                {share.path} <span className="muted">{share.comment}</span>
+              </span>
+              <button onClick={() => scanSmbShare(share)} disabled={status === "scanning"}>
+                Scan
+              </button>
+              <button
+                onClick={() =>
+                  invoke("disconnect_smb_share", { host: smbHost, share: share.name }).catch((e) =>
+                    setErrors([String(e)])
+                  )
+                }
+                disabled={status === "scanning"}
+              >
+                Disconnect
+              </button>
+            </div>
+          ))}
+        </div>
+
+        <div className="card" style={{ marginBottom: 14 }}>
+          <div className="row" style={{ gap: 8, flexWrap: "wrap" }}>
+            <strong style={{ marginRight: "auto" }}>Server over SSH</strong>
+            <input
+              value={sshHost}
+              onChange={(e) => setSshHost(e.target.value)}
+              placeholder="Host"
+              spellCheck={false}
+            />
+            <input
+              value={sshPort}
+              onChange={(e) => setSshPort(e.target.value)}
+              placeholder="Port"
+              inputMode="numeric"
+              style={{ width: 70, minWidth: 70 }}
+            />
+            <input
+              value={sshUser}
+              onChange={(e) => setSshUser(e.target.value)}
+              placeholder="User (optional)"
+              spellCheck={false}
+            />
+            <input
+              value={sshPath}
+              onChange={(e) => setSshPath(e.target.value)}
+              placeholder="Path (default /)"
+              spellCheck={false}
+            />
+            <input
+              type="password"
+              value={sshPassword}
+              onChange={(e) => setSshPassword(e.target.value)}
+              placeholder="Password (blank: keys)"
+            />
+            <label className="muted" title="Keep the password in the system keyring">
+              <input
+                type="checkbox"
+                checked={sshRemember}
+                onChange={(e) => setSshRemember(e.target.checked)}
+              />{" "}
+              Remember
+            </label>
+            <label
+              className="muted"
+              title="Accept the key of a server ssh hasn't connected to before"
+            >
+              <input
+                type="checkbox"
+                checked={sshTrustNewHost}
+                onChange={(e) => setSshTrustNewHost(e.target.checked)}
+              />{" "}
+              Trust new server
+            </label>
+            <button onClick={scanSsh} disabled={!sshHost.trim() || status === "scanning"}>
+              Scan
+            </button>
+            <button
+              onClick={() =>
+                invoke("forget_ssh_password", {
+                  host: sshHost,
+                  port: parseInt(sshPort, 10) || null,
+                  user: sshUser || null,
+                }).catch((e) => setErrors([String(e)]))
+              }
+              disabled={!sshHost.trim()}
+            >
+              Forget password
+            </button>
+          </div>
+        </div>
+
+        <div className="card" style={{ marginBottom: 14 }}>
+          <div className="row" style={{ gap: 8, flexWrap: "wrap" }}>
+            <strong style={{ marginRight: "auto" }}>S3 bucket</strong>
+            <input
+              value={s3Bucket}
+              onChange={(e) => setS3Bucket(e.target.value)}
+              placeholder="bucket or s3://bucket/folder"
+              spellCheck={false}
+            />
+            <input
+              value={s3Region}
+              onChange={(e) => setS3Region(e.target.value)}
+              placeholder="Region"
+              spellCheck={false}
+              style={{ width: 110, minWidth: 110 }}
+            />
+            <input
+              value={s3Endpoint}
+              onChange={(e) => setS3Endpoint(e.target.value)}
+              placeholder="Endpoint (not AWS)"
+              spellCheck={false}
+            />
+            <input
+              value={s3KeyId}
+              onChange={(e) => setS3KeyId(e.target.value)}
+              placeholder="Access key (blank: AWS config)"
+              spellCheck={false}
+            />
+            <input
+              type="password"
+              value={s3Secret}
+              onChange={(e) => setS3Secret(e.target.value)}
+              placeholder="Secret (blank: saved)"
+            />
+            <label className="muted" title="Keep the secret key in the system keyring">
+              <input
+                type="checkbox"
+                checked={s3Remember}
+                onChange={(e) => setS3Remember(e.target.checked)}
+              />{" "}
+              Remember
+            </label>
+            <button onClick={scanS3} disabled={!s3Bucket.trim() || status === "scanning"}>
+              Scan
+            </button>
+            {s3KeyId.trim() && (
+              <button
+                onClick={() =>
+                  invoke("forget_s3_secret", { accessKeyId: s3KeyId }).catch((e) =>
+                    setErrors([String(e)])
+                  )
+                }
+              >
+                Forget secret
+              </button>
+            )}
+          </div>
+        </div>
+
+        <div className="card" style={{ marginBottom: 14 }}>
+          <div className="row" style={{ gap: 8, flexWrap: "wrap" }}>
+            <strong style={{ marginRight: "auto" }}>WebDAV folder</strong>
+            <input
+              value={davUrl}
+              onChange={(e) => setDavUrl(e.target.value)}
+              placeholder="https://cloud.example.com/remote.php/dav/files/me"
+              spellCheck={false}
+            />
+            <input
+              value={davUser}
+              onChange={(e) => setDavUser(e.target.value)}
+              placeholder="User (optional)"
+              spellCheck={false}
+            />
+            <input
+              type="password"
+              value={davPassword}
+              onChange={(e) => setDavPassword(e.target.value)}
+              placeholder="Password (blank: saved)"
+            />
+            <label className="muted" title="Keep the password in the system keyring">
+              <input
+                type="checkbox"
+                checked={davRemember}
+                onChange={(e) => setDavRemember(e.target.checked)}
+              />{" "}
+              Remember
+            </label>
+            <label className="muted" title="Requests a second, to stay under the server's limits">
+              <input
+                type="number"
+                min={1}
+                value={davRate}
+                onChange={(e) => setDavRate(Number(e.target.value))}
+                style={{ width: 60, minWidth: 60 }}
+              />{" "}
+              req/s
+            </label>
+            <button onClick={scanWebDav} disabled={!davUrl.trim() || status === "scanning"}>
+              Scan
+            </button>
+            {davUser.trim() && (
+              <button
+                onClick={() =>
+                  invoke("forget_webdav_password", { url: davUrl, user: davUser }).catch((e) =>
+                    setErrors([String(e)])
+                  )
+                }
+                disabled={!davUrl.trim()}
+              >
+                Forget password
+              </button>
+            )}
+          </div>
+        </div>
+
+        <div className="card" style={{ marginBottom: 14 }}>
+          <div className="row" style={{ gap: 8, flexWrap: "wrap" }}>
+            <strong style={{ marginRight: "auto" }}>rclone remote</strong>
+            {rcloneRemotes && rcloneRemotes.length > 0 ? (
+              <select value={rcloneRemote} onChange={(e) => setRcloneRemote(e.target.value)}>
+                {rcloneRemotes.map((remote) => (
+                  <option key={remote.name} value={remote.name}>
+                    {remote.name} ({remote.kind})
+                  </option>
+                ))}
+              </select>
+            ) : (
+              <span className="muted">
+                {rcloneRemotes ? "No remotes configured" : "Remotes set up with rclone config"}
+              </span>
+            )}
+            <button onClick={listRcloneRemotes}>
+              {rcloneRemotes ? "Refresh" : "List remotes"}
+            </button>
+            <input
+              value={rclonePath}
+              onChange={(e) => setRclonePath(e.target.value)}
+              placeholder="Folder (default: all)"
+              spellCheck={false}
+            />
+            <button onClick={scanRclone} disabled={!rcloneRemote || status === "scanning"}>
+              Scan
+            </button>
+          </div>
+        </div>
+
+        <div className="card" style={{ marginBottom: 14 }}>
+          <div className="row" style={{ gap: 8, flexWrap: "wrap" }}>
+            <strong style={{ marginRight: "auto" }}>Cloud drive</strong>
+            <select
+              value={cloudProvider}
+              onChange={(e) => setCloudProvider(e.target.value as CloudProvider)}
+            >
+              <option value="google_drive">Google Drive</option>
+              <option value="one_drive">OneDrive</option>
+            </select>
+            {cloudDrives.some((d) => d.provider === cloudProvider) ? (
+              <>
+                <span className="muted">
+                  {cloudDrives.find((d) => d.provider === cloudProvider)?.account}
+                </span>
+                <button onClick={disconnectCloudDrive}>Sign out</button>
+                <button onClick={scanCloudDrive} disabled={status === "scanning"}>
+                  Scan
+                </button>
+              </>
+            ) : (
+              <>
+                <input
+                  value={cloudClientId}
+                  onChange={(e) => setCloudClientId(e.target.value)}
+                  placeholder="OAuth client id (optional)"
+                  spellCheck={false}
+                />
+                <button onClick={connectCloudDrive} disabled={signingIn}>
+                  {signingIn ? "Waiting for the browser…" : "Sign in"}
+                </button>
+              </>
+            )}
+          </div>
+        </div>
+
+        <div className="card" style={{ marginBottom: 14 }}>
+          <div className="row" style={{ gap: 8, flexWrap: "wrap" }}>
+            <strong style={{ marginRight: "auto" }}>Remote agent</strong>
+            <input
+              value={agentAddress}
+              onChange={(e) => setAgentAddress(e.target.value)}
+              placeholder={agentOverSsh ? "user@server" : "host:port"}
+              spellCheck={false}
+            />
+            <label className="muted" title="Run space-usage --agent there through ssh">
+              <input
+                type="checkbox"
+                checked={agentOverSsh}
+                onChange={(e) => setAgentOverSsh(e.target.checked)}
+              />{" "}
+              Start over SSH
+            </label>
+            {agentOverSsh && (
+              <label
+                className="muted"
+                title="Accept the key of a server ssh hasn't connected to before"
+              >
+                <input
+                  type="checkbox"
+                  checked={sshTrustNewHost}
+                  onChange={(e) => setSshTrustNewHost(e.target.checked)}
+                />{" "}
+                Trust new server
+              </label>
+            )}
+            {!agentOverSsh && (
+              <input
+                type="password"
+                value={agentToken}
+                onChange={(e) => setAgentToken(e.target.value)}
+                placeholder="Token"
+              />
+            )}
+            <input
+              value={agentPath}
+              onChange={(e) => setAgentPath(e.target.value)}
+              placeholder="Folder on that machine"
+              spellCheck={false}
+            />
+            <button
+              onClick={scanAgent}
+              disabled={!agentAddress.trim() || !agentPath.trim() || status === "scanning"}
+            >
+              Scan
+            </button>
+          </div>
+        </div>
+
+        {IS_ANDROID && (
+          <div className="card" style={{ marginBottom: 14 }}>
+            <div className="row" style={{ gap: 8, flexWrap: "wrap" }}>
+              <strong style={{ marginRight: "auto" }}>Phone storage</strong>
+              <button onClick={() => scanAndroid(null)} disabled={status === "scanning"}>
+                Scan shared storage
+              </button>
+              <button onClick={pickAndroidFolder}>Pick a folder…</button>
+              <button
+                onClick={() => invoke("open_android_usage_access").catch(() => {})}
+                title="Lets app sizes be included in shared storage scans"
+              >
+                Allow app sizes
+              </button>
+            </div>
+            {androidFolders.map((folder) => (
+              <div key={folder.uri} className="row" style={{ gap: 8, marginTop: 6 }}>
+                <span style={{ marginRight: "auto" }}>{folder.name}</span>
+                <button onClick={() => scanAndroid(folder)} disabled={status === "scanning"}>
+                  Scan
+                </button>
+              </div>
+            ))}
+          </div>
+        )}
+
+        {IS_IOS && (
+          <div className="card" style={{ marginBottom: 14 }}>
+            <div className="row" style={{ gap: 8 }}>
+              <strong style={{ marginRight: "auto" }}>Files on this device</strong>
+              <button onClick={pickIosFolder}>Add a folder…</button>
+            </div>
+            {iosLocations.map((location) => (
+              <div key={location.id ?? ""} className="row" style={{ gap: 8, marginTop: 6 }}>
+                <span style={{ marginRight: "auto" }} title={location.path}>
+                  {location.name}
+                </span>
+                {location.id !== null && (
+                  <button onClick={() => forgetIosFolder(location)}>Forget</button>
+                )}
+                <button onClick={() => scanIos(location)} disabled={status === "scanning"}>
+                  Scan
+                </button>
+              </div>
+            ))}
+          </div>
+        )}
+
+        <div className="card" style={{ marginBottom: 14 }}>
+          <div className="row" style={{ gap: 8 }}>
+            <strong style={{ marginRight: "auto" }}>Captured scan</strong>
+            <span className="muted">An ncdu export, the output of du -ab or a WizTree CSV</span>
+            <button onClick={importScan} disabled={status === "scanning"}>
+              Open…
+            </button>
+          </div>
+        </div>
+
+        <div className="card" style={{ marginTop: 14 }}>
+          <strong>Scheduled scans</strong>
+          <div className="row" style={{ gap: 8, marginTop: 8 }}>
+            <span className="muted">Scan the first location every</span>
+            <select
+              value={scheduleInterval}
+              onChange={(e) => setScheduleInterval(Number(e.target.value))}
+            >
+              {Object.entries(SCHEDULE_INTERVALS).map(([secs, label]) => (
+                <option key={secs} value={secs}>
+                  {label}
+                </option>
+              ))}
+            </select>
+            <button onClick={scheduleScan} disabled={!paths.some((p) => p.trim())}>
+              Schedule
+            </button>
+          </div>
+          {schedules.length > 0 && (
+            <table className="table">
+              <tbody>
+                {schedules.map((schedule) => (
+                  <tr key={schedule.id}>
+                    <td>{schedule.path}</td>
+                    <td>
+                      every{" "}
+                      {SCHEDULE_INTERVALS[schedule.interval_secs] ??
+                        `${Math.round(schedule.interval_secs / 60)} minutes`}
+                    </td>
+                    <td className="muted">
+                      {schedule.last_run
+                        ? `last ${new Date(schedule.last_run * 1000).toLocaleString()}`
+                        : "not run yet"}
+                    </td>
+                    <td>
+                      <button onClick={() => setScheduleEnabled(schedule.id, !schedule.enabled)}>
+                        {schedule.enabled ? "Pause" : "Resume"}
+                      </button>{" "}
+                      <button onClick={() => removeSchedule(schedule.id)}>Remove</button>
+                    </td>
+                  </tr>
+                ))}
+              </tbody>
+            </table>
+          )}
+        </div>
+
+        <div className="card" style={{ marginTop: 14 }}>
+          <strong>Folder alerts</strong>
+          <div className="muted">Checked after every scan that includes the folder.</div>
+          <div className="row" style={{ gap: 8, marginTop: 8 }}>
+            <input
+              placeholder="Folder"
+              value={watchPath}
+              onChange={(e) => setWatchPath(e.target.value)}
+            />
+            <span className="muted">grows by</span>
+            <input
+              style={{ width: 70, minWidth: 70 }}
+              type="number"
+              min={0}
+              value={watchGrowthGb}
+              onChange={(e) => setWatchGrowthGb(Number(e.target.value || 0))}
+            />
+            <span className="muted">GB or exceeds</span>
+            <input
+              style={{ width: 70, minWidth: 70 }}
+              type="number"
+              min={0}
+              value={watchSizeGb}
+              onChange={(e) => setWatchSizeGb(Number(e.target.value || 0))}
+            />
+            <span className="muted">GB</span>
+            <button
+              onClick={addWatchRule}
+              disabled={!watchPath.trim() || (watchGrowthGb <= 0 && watchSizeGb <= 0)}
+            >
+              Add
+            </button>
+          </div>
+          {watchRules.length > 0 && (
+            <table className="table">
+              <tbody>
+                {watchRules.map((rule) => (
+                  <tr key={rule.id}>
+                    <td>{rule.path}</td>
+                    <td className="muted">
+                      {[
+                        rule.max_growth != null && `grows by ${formatBytes(rule.max_growth)}`,
+                        rule.max_size != null && `exceeds ${formatBytes(rule.max_size)}`,
+                      ]
+                        .filter(Boolean)
+                        .join(" or ")}
+                    </td>
+                    <td>
+                      {rule.size != null ? formatBytes(rule.size) : "not scanned yet"}
+                      {rule.baseline != null && rule.baseline_time != null && (
+                        <span className="muted">
+                          {" "}
+                          (was {formatBytes(rule.baseline)} on {formatDate(rule.baseline_time)})
+                        </span>
+                      )}
+                    </td>
+                    <td>{rule.tripped && <strong>Over the limit</strong>}</td>
+                    <td>
+                      <button onClick={() => removeWatchRule(rule.id)}>Remove</button>
+                    </td>
+                  </tr>
+                ))}
+              </tbody>
+            </table>
+          )}
+        </div>
 
-  function addPath() {
-    if (paths.length >= 10) {
-      alert("Maximum 10 locations allowed");
-      return;
-    }
-    setPaths([...paths, ""]);
-  }
+        <div className="card" style={{ marginTop: 14 }}>
+          <strong>Scan history</strong>{" "}
+          <button onClick={loadHistory}>Refresh</button>
+          {history.length === 0 ? (
+            <div className="muted">Finished scans show up here.</div>
+          ) : (
+            <table className="table">
+              <tbody>
+                {history.map((entry) => {
+                  const current = activeTab ? tabResults.get(activeTab) : undefined;
+                  const comparable = current?.path === entry.root ? current : undefined;
+                  return (
+                    <tr key={entry.id}>
+                      <td>{new Date(entry.finished * 1000).toLocaleString()}</td>
+                      <td title={entry.volume ?? undefined}>{entry.root}</td>
+                      <td>{formatBytes(entry.size)}</td>
+                      <td>{entry.file_count.toLocaleString()} files</td>
+                      <td>
+                        {entry.volume_free != null ? `${formatBytes(entry.volume_free)} free` : ""}
+                      </td>
+                      <td>
+                        <button
+                          onClick={() => showHistoryScan(entry.id)}
+                          disabled={!entry.has_tree}
+                          title={entry.has_tree ? undefined : "Only the totals were kept"}
+                        >
+                          Show
+                        </button>{" "}
+                        <button
+                          onClick={() =>
+                            comparable && compareWithHistory(entry.id, comparable.scanId)
+                          }
+                          disabled={!entry.has_tree || !comparable}
+                          title="What changed from then to the scan shown now"
+                        >
+                          Compare
+                        </button>{" "}
+                        <button
+                          onClick={() => loadTrends(entry.root)}
+                          disabled={!entry.has_tree}
+                          title="The folders of this location that grew fastest across its scans"
+                        >
+                          Growth
+                        </button>{" "}
+                        <button onClick={() => deleteHistoryScan(entry.id)}>Delete</button>
+                      </td>
+                    </tr>
+                  );
+                })}
+              </tbody>
+            </table>
+          )}
+          {historyScan?.tree && (
+            <div style={{ marginTop: 12 }}>
+              <strong>{historyScan.root}</strong>{" "}
+              <span className="muted">
+                on {new Date(historyScan.finished * 1000).toLocaleString()}:{" "}
+                {formatBytes(historyScan.size)} in {historyScan.file_count.toLocaleString()} files
+              </span>{" "}
+              <button onClick={() => setHistoryScan(null)}>Close</button>
+              <table className="table">
+                <tbody>
+                  {(historyPage?.children ?? []).map((child) => (
+                    <tr key={child.id}>
+                      <td>{child.name}</td>
+                      <td>{child.kind}</td>
+                      <td>{formatBytes(child.size)}</td>
+                      <td>{child.file_count?.toLocaleString() ?? ""}</td>
+                    </tr>
+                  ))}
+                </tbody>
+              </table>
+              {historyPage && historyPage.children.length < historyPage.total && (
+                <button
+                  style={{ marginTop: 8 }}
+                  onClick={() => showMoreChildren(historyScan.scan_id!, historyScan.tree!)}
+                >
+                  Show more
+                </button>
+              )}
+            </div>
+          )}
+        </div>
 
-  function setSort(path: string, field: SortField) {
-    setSortBy((prev) => {
-      const next = new Map(prev);
-      const current = next.get(path);
-      if (current?.field === field) {
-        // Toggle direction
-        next.set(path, { field, dir: current.dir === "asc" ? "desc" : "asc" });
-      } else {
-        // Default to desc for size, asc for name/type
-        next.set(path, { field, dir: field === "size" ? "desc" : "asc" });
-      }
-      return next;
-    });
-  }
+        {trends && (
+          <div className="card" style={{ marginTop: 14 }}>
+            <strong>Growing fastest in {trends.root}</strong>{" "}
+            <button onClick={() => setTrends(null)}>Close</button>
+            {trends.trends.length === 0 ? (
+              <div className="muted">
+                Nothing grew, or fewer than two scans of it kept their tree.
+              </div>
+            ) : (
+              <table className="table">
+                <tbody>
+                  {trends.trends.map((trend) => (
+                    <tr key={trend.path}>
+                      <td>{trend.path}</td>
+                      <td>+{formatBytes(Math.round(trend.bytes_per_day))}/day</td>
+                      <td>+{formatBytes(trend.growth)}</td>
+                      <td>
+                        <svg width={120} height={24} style={{ overflow: "visible" }}>
+                          <polyline
+                            points={sparkline(trend.points, 120, 24)}
+                            fill="none"
+                            stroke="currentColor"
+                            strokeWidth={1.5}
+                          />
+                        </svg>
+                      </td>
+                    </tr>
+                  ))}
+                </tbody>
+              </table>
+            )}
+          </div>
+        )}
 
-  function removePath(index: number) {
-    setPaths(paths.filter((_, i) => i !== index));
-  }
+        {comparison && (
+          <div className="card" style={{ marginTop: 14 }}>
+            <strong>Changes in {comparison.path}</strong>{" "}
+            <span className="muted">
+              {formatBytes(comparison.before)} → {formatBytes(comparison.after)}
+            </span>{" "}
+            <button onClick={() => setComparison(null)}>Close</button>
+            <table className="table">
+              <tbody>
+                {(comparison.children ?? []).map((child) => (
+                  <tr key={child.path}>
+                    <td>{child.name}</td>
+                    <td>{child.change}</td>
+                    <td>
+                      {child.delta >= 0 ? "+" : "−"}
+                      {formatBytes(Math.abs(child.delta))}
+                    </td>
+                    <td className="muted">
+                      {formatBytes(child.before)} → {formatBytes(child.after)}
+                    </td>
+                  </tr>
+                ))}
+              </tbody>
+            </table>
+          </div>
+        )}
 
-  function updatePath(index: number, value: string) {
-    const updated = [...paths];
-    updated[index] = value;
-    setPaths(updated);
-  }
+        {driveScans.length > 0 && (
+          <div className="card" style={{ marginBottom: 14 }}>
+            <div className="row" style={{ marginBottom: 8 }}>
+              <strong style={{ marginRight: "auto" }}>This machine</strong>
+              <span className="muted">
+                {formatBytes(driveScans.reduce((sum, s) => sum + s.volume.used_bytes, 0))}{" "}
+                used of{" "}
+                {formatBytes(driveScans.reduce((sum, s) => sum + s.volume.total_bytes, 0))}
+              </span>
+              <button onClick={() => setDriveScans([])}>Hide</button>
+            </div>
+            {driveScans.map(({ scan_id, volume }) => (
+              <div key={scan_id} className="row" style={{ gap: 12 }}>
+                <span style={{ marginRight: "auto" }}>
+                  {volume.label ?? volume.mount_point}{" "}
+                  <span className="muted">
+                    {volume.label && `${volume.mount_point} `}
+                    {volume.filesystem}
+                  </span>
+                </span>
+                <span>
+                  {formatBytes(volume.used_bytes)}{" "}
+                  <span className="muted">used of {formatBytes(volume.total_bytes)}</span>
+                </span>
+                <span className="muted">
+                  {roots.has(scan_id)
+                    ? `${formatBytes(roots.get(scan_id)!.allocated)} found`
+                    : "scanning…"}
+                </span>
+              </div>
+            ))}
+          </div>
+        )}
 
-  return (
-    <div className="app">
-      <div className="card">
-        <h2 style={{ margin: "0 0 6px 0" }}>Space Usage</h2>
-        <div className="muted" style={{ marginBottom: 14 }}>
-          Scan a folder or mounted network path. Example: <code>/</code>, <code>/home</code>,{" "}
-          <code>/mnt/nas</code>, <code>C:\</code>, <code>\\server\share</code>
+        <div className="row" style={{ marginBottom: 14, gap: 8 }}>
+          <label className="muted" title="Looks at every drive every 5 minutes while the app runs">
+            <input
+              type="checkbox"
+              checked={monitor !== null}
+              onChange={(e) => toggleMonitor(e.target.checked)}
+            />{" "}
+            Warn when a drive has less than
+          </label>
+          <input
+            style={{ width: 70, minWidth: 70 }}
+            type="number"
+            min={0}
+            value={monitorGb}
+            disabled={monitor !== null}
+            onChange={(e) => setMonitorGb(Number(e.target.value || 0))}
+          />
+          <span className="muted">GB or</span>
+          <input
+            style={{ width: 60, minWidth: 60 }}
+            type="number"
+            min={0}
+            max={100}
+            value={monitorPercent}
+            disabled={monitor !== null}
+            onChange={(e) => setMonitorPercent(Number(e.target.value || 0))}
+          />
+          <span className="muted">% free</span>
         </div>
 
+        {newDrives.map((volume) => (
+          <div key={volume.mount_point} className="row" style={{ marginBottom: 14, gap: 8 }}>
+            <span style={{ marginRight: "auto" }}>
+              {volume.network ? "Network volume" : "Drive"}{" "}
+              <strong>{volume.label ?? volume.mount_point}</strong> connected:{" "}
+              {formatBytes(volume.used_bytes)} used of {formatBytes(volume.total_bytes)}.
+            </span>
+            <button
+              onClick={() => {
+                setPaths([...paths.filter((p) => p.trim()), volume.mount_point].slice(0, 10));
+                setNewDrives((prev) => prev.filter((v) => v.mount_point !== volume.mount_point));
+              }}
+              disabled={status === "scanning" || paths.includes(volume.mount_point)}
+            >
+              Add to scan
+            </button>
+            <button
+              onClick={() =>
+                setNewDrives((prev) => prev.filter((v) => v.mount_point !== volume.mount_point))
+              }
+            >
+              Dismiss
+            </button>
+          </div>
+        ))}
+
+        {lowSpace.map((volume) => (
+          <div key={volume.mount_point} className="row" style={{ marginBottom: 14, gap: 8 }}>
+            <span style={{ marginRight: "auto" }}>
+              <strong>{volume.label ?? volume.mount_point}</strong> is almost full:{" "}
+              {formatBytes(volume.free_bytes)} free of {formatBytes(volume.total_bytes)}.
+            </span>
+            <button
+              onClick={() => {
+                setPaths([...paths.filter((p) => p.trim()), volume.mount_point].slice(0, 10));
+                setLowSpace((prev) => prev.filter((v) => v.mount_point !== volume.mount_point));
+              }}
+              disabled={status === "scanning" || paths.includes(volume.mount_point)}
+            >
+              Add to scan
+            </button>
+            <button
+              onClick={() =>
+                setLowSpace((prev) => prev.filter((v) => v.mount_point !== volume.mount_point))
+              }
+            >
+              Dismiss
+            </button>
+          </div>
+        ))}
+
         <div style={{ display: "flex", flexDirection: "column", gap: 8 }}>
           {paths.map((path, index) => (
             <div key={index} className="row">
@@ -288,6 +3318,16 @@ export default function App() {
             >
               Start scan
             </button>
+            <button
+              onClick={startElevatedScan}
+              disabled={
+                status === "scanning" ||
+                paths.every((p) => !p.trim())
+              }
+              title="Asks for administrator rights to read protected folders"
+            >
+              Scan as administrator
+            </button>
             <button
               onClick={cancelScan}
               disabled={status !== "scanning" || scanIds.length === 0}
@@ -305,21 +3345,70 @@ export default function App() {
               type="number"
               min={1}
               max={50}
-              value={maxDepth}
-              onChange={(e) => setMaxDepth(Number(e.target.value || 6))}
+              placeholder="All"
+              value={maxDepth ?? ""}
+              onChange={(e) => setMaxDepth(e.target.value ? Number(e.target.value) : null)}
             />
           </label>
-          <label className="muted">
+          <label className="muted" title="0 keeps them all">
             Top children per folder{" "}
             <input
               style={{ width: 110, minWidth: 110 }}
               type="number"
-              min={10}
+              min={0}
               max={2000}
               value={topChildren}
-              onChange={(e) => setTopChildren(Number(e.target.value || 200))}
+              onChange={(e) => setTopChildren(Number(e.target.value || 0))}
             />
           </label>
+          <label className="muted" title="Look up who owns each file">
+            <input
+              type="checkbox"
+              checked={trackOwners}
+              onChange={(e) => setTrackOwners(e.target.checked)}
+            />{" "}
+            Track owners
+          </label>
+          <label className="muted" title="Show each folder's own files as one [Files] entry">
+            <input
+              type="checkbox"
+              checked={groupFiles}
+              onChange={(e) => setGroupFiles(e.target.checked)}
+            />{" "}
+            Group files
+          </label>
+          <label className="muted" title="Read .gitignore and .ignore files on the way down">
+            Ignored files:{" "}
+            <select value={ignoreMode} onChange={(e) => setIgnoreMode(e.target.value as IgnoreMode)}>
+              <option value="off">include</option>
+              <option value="exclude">leave out</option>
+              <option value="only">show only</option>
+            </select>
+          </label>
+          <label className="muted" title="Hash files of the same size to find copies; slower">
+            <input
+              type="checkbox"
+              checked={findDuplicates}
+              onChange={(e) => setFindDuplicates(e.target.checked)}
+            />{" "}
+            Find duplicates
+          </label>
+          <label className="muted" title="Decode images to find ones that look alike; slower">
+            <input
+              type="checkbox"
+              checked={findSimilarImages}
+              onChange={(e) => setFindSimilarImages(e.target.checked)}
+            />{" "}
+            Find similar images
+          </label>
+          <label className="muted" title="Stores the whole tree in the scan history too">
+            <input
+              type="checkbox"
+              checked={historyTrees}
+              onChange={(e) => setHistoryTrees(e.target.checked)}
+            />{" "}
+            Keep tree in history
+          </label>
           {status === "scanning" && (
             <span className="muted">
               {progress.size > 0 && (
@@ -335,7 +3424,16 @@ export default function App() {
                   {Array.from(progress.values())
                     .reduce((acc, p) => acc + (p.scanned_entries ?? 0), 0)
                     .toLocaleString()}{" "}
-                  entries ({progress.size} location{progress.size !== 1 ? "s" : ""})
+                  entries ({progress.size} location{progress.size !== 1 ? "s" : ""}) at{" "}
+                  {formatBytes(
+                    Math.round(
+                      Array.from(progress.values()).reduce(
+                        (acc, p) => acc + (p.bytes_per_sec ?? 0),
+                        0
+                      )
+                    )
+                  )}
+                  /s
                 </>
               )}
             </span>
@@ -347,11 +3445,449 @@ export default function App() {
             .filter((p) => p.current_path)
             .map((p, i) => (
               <div key={i} className="muted" style={{ marginTop: 10 }}>
+                {p.percent != null && <>{p.percent.toFixed(1)}% · </>}
                 Current: <code>{p.current_path}</code>
               </div>
             ))}
+        {status === "scanning" &&
+          Array.from(hashProgress.entries())
+            .filter(([id]) => scanIds.includes(id))
+            .map(([id, h]) => (
+              <div key={id} className="muted" style={{ marginTop: 10 }}>
+                {HASH_STAGE_LABELS[h.stage]}: {h.done_files.toLocaleString()} of{" "}
+                {h.total_files.toLocaleString()} files
+                {h.total_bytes > 0 && (
+                  <>
+                    {" "}
+                    ({formatBytes(h.done_bytes)} of {formatBytes(h.total_bytes)})
+                  </>
+                )}
+              </div>
+            ))}
+      </div>
+
+      <div className="card" style={{ marginTop: 14 }}>
+        <div className="row">
+          <strong style={{ marginRight: "auto" }}>Package caches</strong>
+          <button onClick={measurePackageCaches} disabled={measuringCaches}>
+            {measuringCaches ? "Measuring…" : packageCaches ? "Measure again" : "Measure"}
+          </button>
+        </div>
+        {packageCaches && packageCaches.length === 0 && (
+          <div className="muted" style={{ marginTop: 10 }}>
+            No package manager caches found.
+          </div>
+        )}
+        {packageCaches && packageCaches.length > 0 && (
+          <table className="table">
+            <tbody>
+              {packageCaches.map((cache) => (
+                <tr key={cache.tool}>
+                  <td>{PACKAGE_TOOLS[cache.tool] ?? cache.tool}</td>
+                  <td>
+                    {cache.paths.map((path) => (
+                      <div key={path}>
+                        <code>{path}</code>
+                      </div>
+                    ))}
+                  </td>
+                  <td>{formatBytes(cache.bytes)}</td>
+                  <td className="muted">{cache.files.toLocaleString()} files</td>
+                  <td>
+                    {cache.clearable && (
+                      <button onClick={() => clearPackageCache(cache)}>Clear</button>
+                    )}
+                  </td>
+                </tr>
+              ))}
+            </tbody>
+          </table>
+        )}
+      </div>
+
+      <div className="card" style={{ marginTop: 14 }}>
+        <div className="row">
+          <strong style={{ marginRight: "auto" }}>Browsers</strong>
+          <button onClick={measureBrowsers} disabled={measuringBrowsers}>
+            {measuringBrowsers ? "Measuring…" : browserProfiles ? "Measure again" : "Measure"}
+          </button>
+        </div>
+        {browserProfiles && browserProfiles.length === 0 && (
+          <div className="muted" style={{ marginTop: 10 }}>
+            No browser profiles found.
+          </div>
+        )}
+        {browserProfiles && browserProfiles.length > 0 && (
+          <table className="table">
+            <thead>
+              <tr>
+                <th>Profile</th>
+                <th>Cache</th>
+                <th>Site data</th>
+                <th>Service workers</th>
+                <th>Other</th>
+              </tr>
+            </thead>
+            <tbody>
+              {browserProfiles.map((profile) => (
+                <tr key={profile.path}>
+                  <td title={profile.path}>
+                    {BROWSERS[profile.browser]} <span className="muted">{profile.name}</span>
+                  </td>
+                  <td>{formatBytes(profile.cache_bytes)}</td>
+                  <td>{formatBytes(profile.site_data_bytes)}</td>
+                  <td>{formatBytes(profile.service_worker_bytes)}</td>
+                  <td className="muted">{formatBytes(profile.other_bytes)}</td>
+                </tr>
+              ))}
+            </tbody>
+          </table>
+        )}
+      </div>
+
+      <div className="card" style={{ marginTop: 14 }}>
+        <div className="row">
+          <strong style={{ marginRight: "auto" }}>{TRASH_NAME}</strong>
+          {trashBins && trashBins.some((bin) => bin.items > 0) && (
+            <button onClick={() => emptyTrash(null)}>Empty all</button>
+          )}
+          <button onClick={measureTrash} disabled={measuringTrash}>
+            {measuringTrash ? "Measuring…" : trashBins ? "Measure again" : "Measure"}
+          </button>
+        </div>
+        {trashBins && trashBins.length === 0 && (
+          <div className="muted" style={{ marginTop: 10 }}>
+            No {TRASH_NAME} found.
+          </div>
+        )}
+        {trashBins && trashBins.length > 0 && (
+          <table className="table">
+            <tbody>
+              {trashBins.map((bin) => (
+                <tr key={bin.path}>
+                  <td title={bin.path}>
+                    <code>{bin.volume}</code>
+                  </td>
+                  <td>{formatBytes(bin.bytes)}</td>
+                  <td className="muted">{bin.items.toLocaleString()} items</td>
+                  <td>
+                    {bin.items > 0 && <button onClick={() => emptyTrash(bin)}>Empty</button>}
+                  </td>
+                </tr>
+              ))}
+            </tbody>
+          </table>
+        )}
+      </div>
+
+      <div className="card" style={{ marginTop: 14 }}>
+        <div className="row">
+          <strong style={{ marginRight: "auto" }}>Page, swap and hibernation files</strong>
+          <button onClick={measureSystemFiles} disabled={measuringSystemFiles}>
+            {measuringSystemFiles ? "Measuring…" : systemFiles ? "Measure again" : "Measure"}
+          </button>
+        </div>
+        {systemFiles && systemFiles.length === 0 && (
+          <div className="muted" style={{ marginTop: 10 }}>
+            None found.
+          </div>
+        )}
+        {systemFiles && systemFiles.length > 0 && (
+          <table className="table">
+            <tbody>
+              {systemFiles.map((file) => (
+                <tr key={file.path}>
+                  <td>{SYSTEM_FILE_KINDS[file.kind]}</td>
+                  <td>
+                    <code>{file.path}</code>
+                  </td>
+                  <td>{formatBytes(file.bytes)}</td>
+                  <td className="muted">
+                    {file.used_bytes !== null && `${formatBytes(file.used_bytes)} used`}
+                  </td>
+                  <td>
+                    {IS_WINDOWS && file.kind === "hibernation" && (
+                      <button onClick={disableHibernation}>Turn off hibernation</button>
+                    )}
+                  </td>
+                </tr>
+              ))}
+            </tbody>
+          </table>
+        )}
+      </div>
+
+      {IS_WINDOWS && (
+        <div className="card" style={{ marginTop: 14 }}>
+          <div className="row">
+            <strong style={{ marginRight: "auto" }}>
+              Cleanable Windows files
+              {cleanable && (
+                <span className="muted">
+                  {" "}
+                  · {formatBytes(cleanable.total_bytes)} could be reclaimed
+                </span>
+              )}
+            </strong>
+            <button onClick={measureCleanable} disabled={measuringCleanable}>
+              {measuringCleanable ? "Measuring…" : cleanable ? "Measure again" : "Measure"}
+            </button>
+          </div>
+          {cleanable && cleanable.items.length === 0 && (
+            <div className="muted" style={{ marginTop: 10 }}>
+              Nothing to clean up.
+            </div>
+          )}
+          {cleanable && cleanable.items.length > 0 && (
+            <table className="table">
+              <tbody>
+                {cleanable.items.map((item) => (
+                  <tr key={item.kind}>
+                    <td title={item.paths.join("\n")}>{CLEANABLE_KINDS[item.kind] ?? item.kind}</td>
+                    <td>{formatBytes(item.bytes)}</td>
+                    <td className="muted">{item.files.toLocaleString()} files</td>
+                  </tr>
+                ))}
+              </tbody>
+            </table>
+          )}
+        </div>
+      )}
+
+      {IS_MAC && (
+        <div className="card" style={{ marginTop: 14 }}>
+          <div className="row">
+            <strong style={{ marginRight: "auto" }}>Purgeable space and local snapshots</strong>
+            {purgeable && purgeable.snapshots.length > 0 && (
+              <button onClick={thinSnapshots} disabled={measuringPurgeable}>
+                Thin snapshots
+              </button>
+            )}
+            <button onClick={measurePurgeable} disabled={measuringPurgeable}>
+              {measuringPurgeable ? "Measuring…" : purgeable ? "Measure again" : "Measure"}
+            </button>
+          </div>
+          {purgeable && (
+            <div style={{ marginTop: 10 }}>
+              {formatBytes(purgeable.free_bytes)} free
+              {purgeable.purgeable_bytes !== null && (
+                <span className="muted">
+                  {" "}
+                  and {formatBytes(purgeable.purgeable_bytes)} purgeable
+                </span>
+              )}
+              {purgeable.snapshots.length === 0 && (
+                <div className="muted">No local Time Machine snapshots.</div>
+              )}
+              {purgeable.snapshots.map((snapshot) => (
+                <div key={snapshot.name} title={snapshot.name}>
+                  Snapshot <span className="muted">{snapshot.date ?? snapshot.name}</span>
+                </div>
+              ))}
+            </div>
+          )}
+        </div>
+      )}
+
+      {IS_WINDOWS && (
+        <div className="card" style={{ marginTop: 14 }}>
+          <div className="row">
+            <strong style={{ marginRight: "auto" }}>Shadow copies</strong>
+            <button onClick={measureShadowStorage} disabled={measuringShadows}>
+              {measuringShadows ? "Measuring…" : shadowStorage ? "Measure again" : "Measure"}
+            </button>
+          </div>
+          {shadowStorage && shadowStorage.length === 0 && (
+            <div className="muted" style={{ marginTop: 10 }}>
+              No shadow copy storage set up.
+            </div>
+          )}
+          {shadowStorage && shadowStorage.length > 0 && (
+            <table className="table">
+              <tbody>
+                {shadowStorage.map((storage) => (
+                  <tr key={storage.volume}>
+                    <td>
+                      <code>{storage.volume}</code>
+                      {storage.storage_volume !== storage.volume && (
+                        <span className="muted"> on {storage.storage_volume}</span>
+                      )}
+                    </td>
+                    <td className="muted">{storage.copies.toLocaleString()} copies</td>
+                    <td>{formatBytes(storage.used_bytes)} used</td>
+                    <td className="muted">
+                      {formatBytes(storage.allocated_bytes)} allocated of{" "}
+                      {storage.max_bytes === null ? "no limit" : formatBytes(storage.max_bytes)}
+                    </td>
+                  </tr>
+                ))}
+              </tbody>
+            </table>
+          )}
+        </div>
+      )}
+
+      <div className="card" style={{ marginTop: 14 }}>
+        <div className="row">
+          <strong style={{ marginRight: "auto" }}>Containers</strong>
+          <button onClick={measureContainers} disabled={measuringContainers}>
+            {measuringContainers ? "Measuring…" : containerUsage ? "Measure again" : "Measure"}
+          </button>
+        </div>
+        {(containerUsage ?? []).map((engine) => (
+          <div key={engine.engine} style={{ marginTop: 10 }}>
+            <div className="row">
+              <span style={{ marginRight: "auto" }}>
+                {engine.engine === "docker" ? "Docker" : "Podman"}
+                {engine.root_dir && (
+                  <span className="muted">
+                    {" "}
+                    in <code>{engine.root_dir}</code>
+                  </span>
+                )}
+              </span>
+              <button
+                onClick={() => pruneContainers(engine)}
+                disabled={engine.usage.every((u) => u.reclaimable === 0)}
+              >
+                Prune unused
+              </button>
+            </div>
+            <table className="table">
+              <tbody>
+                {engine.usage.map((u) => (
+                  <tr key={u.kind}>
+                    <td>{CONTAINER_STORAGE[u.kind]}</td>
+                    <td>{formatBytes(u.bytes)}</td>
+                    <td className="muted">
+                      {u.count.toLocaleString()} ({u.active.toLocaleString()} in use)
+                    </td>
+                    <td className="muted">{formatBytes(u.reclaimable)} reclaimable</td>
+                  </tr>
+                ))}
+              </tbody>
+            </table>
+          </div>
+        ))}
       </div>
 
+      {IS_LINUX && (
+        <div className="card" style={{ marginTop: 14 }}>
+          <div className="row">
+            <strong style={{ marginRight: "auto" }}>Flatpak and Snap</strong>
+            <button onClick={measureApps} disabled={measuringApps}>
+              {measuringApps ? "Measuring…" : installedApps ? "Measure again" : "Measure"}
+            </button>
+          </div>
+          {installedApps && installedApps.length === 0 && (
+            <div className="muted" style={{ marginTop: 10 }}>
+              No Flatpak or Snap apps installed.
+            </div>
+          )}
+          {installedApps && installedApps.length > 0 && (
+            <table className="table">
+              <tbody>
+                {installedApps.map((app) => (
+                  <tr key={`${app.source}:${app.user}:${app.name}:${app.branch}`}>
+                    <td>
+                      {app.name}
+                      {app.branch && <span className="muted"> {app.branch}</span>}
+                    </td>
+                    <td className="muted">
+                      {app.source === "flatpak" ? "Flatpak" : "Snap"}
+                      {app.runtime ? " runtime" : ""}
+                      {app.user ? ", user" : ""}
+                    </td>
+                    <td>{formatBytes(app.bytes)}</td>
+                    <td className="muted">
+                      {app.revisions.length > 1 ? `${app.revisions.length} revisions` : ""}
+                    </td>
+                  </tr>
+                ))}
+              </tbody>
+            </table>
+          )}
+          {oldRevisions.length > 0 && (
+            <details style={{ marginTop: 12 }}>
+              <summary className="muted">
+                Old revisions ({formatBytes(oldRevisions.reduce((sum, r) => sum + r.bytes, 0))}{" "}
+                reclaimable)
+              </summary>
+              <table className="table">
+                <tbody>
+                  {oldRevisions.map((old) => (
+                    <tr key={old.path}>
+                      <td>{old.name}</td>
+                      <td>
+                        {old.source === "snap" ? (
+                          <code>
+                            snap remove {old.name} --revision={old.revision}
+                          </code>
+                        ) : (
+                          <code>{old.path}</code>
+                        )}
+                      </td>
+                      <td>{formatBytes(old.bytes)}</td>
+                    </tr>
+                  ))}
+                </tbody>
+              </table>
+            </details>
+          )}
+        </div>
+      )}
+
+      {wslDistros.length > 0 && (
+        <div className="card" style={{ marginTop: 14 }}>
+          <strong>WSL distributions</strong>
+          <table className="table">
+            <tbody>
+              {wslDistros.map((distro) => {
+                const usage = wslUsage.get(distro.name);
+                return (
+                  <tr key={distro.name}>
+                    <td>
+                      {distro.name}
+                      {distro.default && <span className="muted"> (default)</span>}
+                    </td>
+                    <td className="muted">WSL {distro.version}</td>
+                    <td title={distro.vhdx_path ?? undefined}>
+                      {distro.vhdx_bytes != null ? `${formatBytes(distro.vhdx_bytes)} disk` : ""}
+                    </td>
+                    <td>
+                      {usage ? `${formatBytes(usage.used_bytes)} used inside` : ""}
+                    </td>
+                    <td>
+                      <button
+                        onClick={() => measureWsl(distro.name)}
+                        disabled={wslBusy !== null}
+                        title="Starts the distribution if it isn't running"
+                      >
+                        Usage
+                      </button>{" "}
+                      <button
+                        onClick={() => setPaths([...paths.filter((p) => p.trim()), distro.path])}
+                        disabled={status === "scanning" || paths.length >= 10}
+                        title={`Adds ${distro.path} to the locations to scan`}
+                      >
+                        Add to scan
+                      </button>{" "}
+                      <button
+                        onClick={() => scanWslInside(distro.name)}
+                        disabled={wslBusy !== null}
+                        title="Lists the files from inside the distribution, with Linux sizes"
+                      >
+                        {wslBusy === distro.name ? "Working…" : "Scan inside"}
+                      </button>
+                    </td>
+                  </tr>
+                );
+              })}
+            </tbody>
+          </table>
+        </div>
+      )}
+
       {tabResults.size > 0 && (
         <div className="card" style={{ marginTop: 14 }}>
           <div className="tabs">
@@ -381,7 +3917,7 @@ export default function App() {
                 <div className="row" style={{ justifyContent: "space-between", marginTop: 16, marginBottom: 12 }}>
                   <div>
                     <div className="muted">Location</div>
-                    <div style={{ fontWeight: 600 }}>{result.root.path}</div>
+                    <div style={{ fontWeight: 600 }}>{result.path}</div>
                   </div>
                   <div style={{ textAlign: "right" }}>
                     <div className="muted">Total size</div>
@@ -389,6 +3925,147 @@ export default function App() {
                   </div>
                 </div>
 
+                <div className="row" style={{ marginBottom: 12, gap: 8 }}>
+                  <span className="muted">Export:</span>
+                  {(Object.keys(EXPORT_FORMATS) as ExportFormat[]).map((format) => (
+                    <button key={format} onClick={() => exportScan(result.scanId, format)}>
+                      {EXPORT_FORMATS[format].label}
+                    </button>
+                  ))}
+                  <button onClick={() => generateReport(result.scanId)}>PDF summary</button>
+                  <button
+                    title="Walks only the folders the NTFS change journal says changed"
+                    onClick={() => refreshScan(result.scanId)}
+                  >
+                    Refresh
+                  </button>
+                  <button
+                    title="Walks folders again as files in them change, from outside the app too"
+                    onClick={() => toggleWatch(result.scanId)}
+                  >
+                    {watched.has(result.scanId) ? "Stop watching" : "Watch for changes"}
+                  </button>
+                  <label className="muted" title="Which rows CSV and TSV exports have">
+                    Rows{" "}
+                    <select
+                      value={exportRows}
+                      onChange={(e) => setExportRows(e.target.value as ExportRows)}
+                    >
+                      <option value="all">Everything</option>
+                      <option value="files">Files</option>
+                      <option value="dirs">Folders</option>
+                    </select>
+                  </label>
+                  <label className="muted" title="Leaves smaller rows out of CSV and TSV exports">
+                    From{" "}
+                    <input
+                      style={{ width: 70, minWidth: 70 }}
+                      type="number"
+                      min={0}
+                      value={exportMinMb}
+                      onChange={(e) => setExportMinMb(Number(e.target.value || 0))}
+                    />{" "}
+                    MB
+                  </label>
+                  <label className="muted" title="Folder levels the du -h export goes down">
+                    Depth{" "}
+                    <input
+                      style={{ width: 60, minWidth: 60 }}
+                      type="number"
+                      min={0}
+                      value={exportDepth}
+                      onChange={(e) => setExportDepth(Number(e.target.value || 0))}
+                    />
+                  </label>
+                </div>
+
+                {(extensions.get(result.scanId)?.length ?? 0) > 0 && (
+                  <div className="row" style={{ marginBottom: 12, gap: 12 }}>
+                    <span className="muted">Top types:</span>
+                    {extensions.get(result.scanId)!.map((x) => (
+                      <span key={x.extension} title={`${x.files.toLocaleString()} files`}>
+                        {x.extension ? `.${x.extension}` : "(none)"}{" "}
+                        <span className="muted">{formatBytes(x.bytes)}</span>
+                      </span>
+                    ))}
+                  </div>
+                )}
+
+                {histograms.get(result.scanId)?.some((b) => b.files > 0) && (
+                  <div className="row" style={{ marginBottom: 12, gap: 12 }}>
+                    <span className="muted">File sizes:</span>
+                    {histograms.get(result.scanId)!.filter((b) => b.files > 0).map((b) => (
+                      <span key={b.min} title={formatBytes(b.bytes)}>
+                        {bucketLabel(b)}{" "}
+                        <span className="muted">{b.files.toLocaleString()} files</span>
+                      </span>
+                    ))}
+                  </div>
+                )}
+
+                {ignoreTotals.has(result.scanId) && (
+                  <div className="row" style={{ marginBottom: 12, gap: 12 }}>
+                    <span className="muted">Ignore files:</span>
+                    <span>
+                      {formatBytes(ignoreTotals.get(result.scanId)!.ignored_bytes)}{" "}
+                      <span className="muted">
+                        ignored in {ignoreTotals.get(result.scanId)!.ignored_files.toLocaleString()}{" "}
+                        files
+                      </span>
+                    </span>
+                    <span>
+                      {formatBytes(ignoreTotals.get(result.scanId)!.kept_bytes)}{" "}
+                      <span className="muted">
+                        kept in {ignoreTotals.get(result.scanId)!.kept_files.toLocaleString()} files
+                      </span>
+                    </span>
+                  </div>
+                )}
+
+                {(unaccounted.get(result.scanId)?.bytes ?? 0) > 0 && (
+                  <div className="row" style={{ marginBottom: 12, gap: 12 }}>
+                    <span className="muted">Unaccounted:</span>
+                    <span>
+                      {formatBytes(unaccounted.get(result.scanId)!.bytes)}{" "}
+                      <span className="muted">used on this volume but not in the tree</span>
+                    </span>
+                    {unaccounted.get(result.scanId)!.parts.map((part) => (
+                      <span key={part.kind}>
+                        {formatBytes(part.bytes)}{" "}
+                        <span className="muted">{UNACCOUNTED_LABELS[part.kind]}</span>
+                      </span>
+                    ))}
+                    {unaccounted.get(result.scanId)!.deniedFolders > 0 && (
+                      <span className="muted">
+                        {unaccounted.get(result.scanId)!.deniedFolders.toLocaleString()} folders
+                        couldn't be read
+                      </span>
+                    )}
+                  </div>
+                )}
+
+                {(storageClasses.get(result.scanId)?.length ?? 0) > 0 && (
+                  <div className="row" style={{ marginBottom: 12, gap: 12 }}>
+                    <span className="muted">Storage classes:</span>
+                    {storageClasses.get(result.scanId)!.map((c) => (
+                      <span key={c.class} title={`${c.objects.toLocaleString()} objects`}>
+                        {formatBytes(c.bytes)} <span className="muted">{c.class}</span>
+                      </span>
+                    ))}
+                  </div>
+                )}
+
+                {(owners.get(result.scanId)?.length ?? 0) > 0 && (
+                  <div className="row" style={{ marginBottom: 12, gap: 12 }}>
+                    <span className="muted">Owners:</span>
+                    {owners.get(result.scanId)!.slice(0, 8).map((o) => (
+                      <span key={o.owner} title={`${o.files.toLocaleString()} files`}>
+                        {o.owner} <span className="muted">{formatBytes(o.bytes)}</span>
+                      </span>
+                    ))}
+                  </div>
+                )}
+
                 <div className="row" style={{ marginBottom: 10, gap: 8 }}>
                   <span className="muted">Sort by:</span>
                   <button
@@ -417,13 +4094,20 @@ export default function App() {
                       <th style={{ width: "50%" }}>Name</th>
                       <th>Type</th>
                       <th>Size</th>
+                      <th>Files</th>
+                      <th>Modified</th>
                       <th style={{ width: "30%" }}>Share</th>
                     </tr>
                   </thead>
                   <tbody>
                     {result.children.map(({ node, pct }) => (
-                      <tr key={node.path}>
-                        <td title={node.path}>
+                      <tr key={node.id}>
+                        <td
+                          title={node.owner ? `Owner: ${node.owner}` : undefined}
+                          className={
+                            node.attributes & (ATTR_HIDDEN | ATTR_SYSTEM) ? "muted" : undefined
+                          }
+                        >
                           {node.kind === "dir" ? (
                             <button
                               style={{
@@ -435,7 +4119,7 @@ export default function App() {
                                 cursor: "pointer",
                                 font: "inherit",
                               }}
-                              onClick={() => scanPath(node.path)}
+                              onClick={() => scanChild(result.scanId, node)}
                               disabled={status === "scanning"}
                               title="Click to scan this folder"
                             >
@@ -444,12 +4128,96 @@ export default function App() {
                           ) : (
                             node.name
                           )}
+                          {node.kind === "file" && DISK_IMAGE_PATTERN.test(node.name) && (
+                            <>
+                              {" "}
+                              <button
+                                onClick={() => inspectDiskImage(result.scanId, node)}
+                                disabled={inspecting !== null}
+                                title="Show what this disk image holds; nothing in it is changed"
+                              >
+                                {inspecting === node.id ? "Inspecting…" : "Inspect"}
+                              </button>
+                            </>
+                          )}
+                          {node.kind === "dir" &&
+                            !node.children &&
+                            node.child_count === undefined && (
+                              <>
+                                {" "}
+                                <button
+                                  onClick={() => expandNode(result.scanId, node)}
+                                  title="The scan stopped at this level; walks one more"
+                                >
+                                  Expand
+                                </button>
+                              </>
+                            )}
+                          {node.kind === "dir" && (
+                            <>
+                              {" "}
+                              <button
+                                onClick={() => rescanNode(result.scanId, node)}
+                                title="Walks just this folder again, after files in it changed"
+                              >
+                                Rescan
+                              </button>
+                            </>
+                          )}
+                          {node.online_only && (
+                            <span className="muted" title="Stored only in the cloud">
+                              {" "}
+                              (online only)
+                            </span>
+                          )}
+                          {node.compressed && (
+                            <span className="muted" title="NTFS-compressed; size on disk is smaller">
+                              {" "}
+                              (compressed)
+                            </span>
+                          )}
+                          {node.sparse && (
+                            <span className="muted" title="Sparse; holes take no disk space">
+                              {" "}
+                              (sparse)
+                            </span>
+                          )}
+                          {node.link_kind && (
+                            <span className="muted" title="Its contents live elsewhere">
+                              {" "}
+                              ({node.link_kind === "mountpoint" ? "mount point" : node.link_kind})
+                            </span>
+                          )}
                         </td>
                         <td>{node.kind}</td>
                         <td>{formatBytes(node.size)}</td>
+                        <td
+                          title={
+                            node.dir_count !== undefined
+                              ? `${node.dir_count.toLocaleString()} folders`
+                              : undefined
+                          }
+                        >
+                          {node.file_count?.toLocaleString() ?? ""}
+                        </td>
+                        <td
+                          title={
+                            node.oldest_mtime !== undefined
+                              ? `Oldest file: ${formatDate(node.oldest_mtime)}`
+                              : undefined
+                          }
+                        >
+                          {formatDate(node.mtime ?? node.newest_mtime)}
+                        </td>
                         <td>
                           <div className="bar" aria-label={`${pct.toFixed(2)}%`}>
-                            <div style={{ width: `${Math.max(0.4, pct)}%` }} />
+                            <div
+                              title={mainCategory(node)}
+                              style={{
+                                width: `${Math.max(0.4, pct)}%`,
+                                background: CATEGORY_COLORS[mainCategory(node) ?? "other"],
+                              }}
+                            />
                           </div>
                         </td>
                       </tr>
@@ -457,12 +4225,344 @@ export default function App() {
                   </tbody>
                 </table>
 
-                {result.root.omitted_children ? (
-                  <div className="muted" style={{ marginTop: 10 }}>
-                    Omitted {result.root.omitted_children.toLocaleString()} children from the root
-                    (increase "Top children per folder" to include more).
+                {result.children.length < result.total && (
+                  <div className="row" style={{ marginTop: 8 }}>
+                    <span className="muted">
+                      {result.children.length.toLocaleString()} of{" "}
+                      {result.total.toLocaleString()} shown
+                    </span>
+                    <button onClick={() => showMoreChildren(result.scanId, result.root)}>
+                      Show more
+                    </button>
+                  </div>
+                )}
+
+                {expanded?.scanId === result.scanId && (
+                  <div style={{ marginTop: 12 }}>
+                    <strong>Inside {expanded.node.name}</strong>{" "}
+                    <span className="muted">{formatBytes(expanded.node.size)}</span>{" "}
+                    <button onClick={() => setExpanded(null)}>Close</button>
+                    <table className="table">
+                      <tbody>
+                        {expanded.page.children.map((child) => (
+                          <tr key={child.id}>
+                            <td>{child.name}</td>
+                            <td>{child.kind}</td>
+                            <td>{formatBytes(child.size)}</td>
+                            <td>{child.file_count?.toLocaleString() ?? ""}</td>
+                          </tr>
+                        ))}
+                      </tbody>
+                    </table>
+                    {expanded.page.children.length < expanded.page.total && (
+                      <button onClick={showMoreExpanded}>Show more</button>
+                    )}
+                  </div>
+                )}
+
+                {inspectedImage && inspectedImage.path.startsWith(result.path) && (
+                  <div style={{ marginTop: 12 }}>
+                    <strong>Inside {inspectedImage.node.name}</strong>{" "}
+                    <span className="muted">
+                      {formatBytes(inspectedImage.node.size)} in{" "}
+                      {(inspectedImage.node.file_count ?? 0).toLocaleString()} files
+                    </span>{" "}
+                    <button onClick={() => setInspectedImage(null)}>Close</button>
+                    <table className="table">
+                      <tbody>
+                        {(inspectedImage.node.children ?? []).map((child) => (
+                          <tr key={child.name}>
+                            <td>{child.name}</td>
+                            <td>{child.kind}</td>
+                            <td>{formatBytes(child.size)}</td>
+                            <td>{child.file_count?.toLocaleString() ?? ""}</td>
+                          </tr>
+                        ))}
+                      </tbody>
+                    </table>
                   </div>
-                ) : null}
+                )}
+
+                {(topFiles.get(result.scanId)?.length ?? 0) > 0 && (
+                  <details style={{ marginTop: 12 }}>
+                    <summary className="muted">Largest files</summary>
+                    <table className="table">
+                      <tbody>
+                        {topFiles.get(result.scanId)!.map((f) => (
+                          <tr key={f.path}>
+                            <td>
+                              <code>{f.path}</code>
+                            </td>
+                            <td>{formatBytes(f.size)}</td>
+                          </tr>
+                        ))}
+                      </tbody>
+                    </table>
+                  </details>
+                )}
+
+                {(oldFiles.get(result.scanId)?.length ?? 0) > 0 && (
+                  <details style={{ marginTop: 12 }}>
+                    <summary className="muted">Not modified for a year</summary>
+                    <table className="table">
+                      <tbody>
+                        {oldFiles.get(result.scanId)!.slice(0, 50).map((f) => (
+                          <tr key={f.path}>
+                            <td>
+                              <code>{f.path}</code>
+                            </td>
+                            <td>{new Date(f.mtime * 1000).toLocaleDateString()}</td>
+                            <td>{formatBytes(f.size)}</td>
+                          </tr>
+                        ))}
+                      </tbody>
+                    </table>
+                  </details>
+                )}
+
+                {(emptyDirs.get(result.scanId)?.length ?? 0) > 0 && (
+                  <details style={{ marginTop: 12 }}>
+                    <summary className="muted">
+                      Empty folders ({emptyDirs.get(result.scanId)!.length.toLocaleString()})
+                    </summary>
+                    <button style={{ marginTop: 8 }} onClick={() => removeEmptyDirs(result.scanId)}>
+                      Remove all
+                    </button>
+                    <table className="table">
+                      <tbody>
+                        {emptyDirs.get(result.scanId)!.slice(0, 100).map((d) => (
+                          <tr key={d.path}>
+                            <td>
+                              <code>{d.path}</code>
+                            </td>
+                            <td className="muted">
+                              {d.dirs > 0 ? `${d.dirs.toLocaleString()} inside` : ""}
+                            </td>
+                          </tr>
+                        ))}
+                      </tbody>
+                    </table>
+                  </details>
+                )}
+
+                {(brokenLinks.get(result.scanId)?.length ?? 0) > 0 && (
+                  <details style={{ marginTop: 12 }}>
+                    <summary className="muted">
+                      Broken links ({brokenLinks.get(result.scanId)!.length.toLocaleString()})
+                    </summary>
+                    <table className="table">
+                      <tbody>
+                        {brokenLinks.get(result.scanId)!.slice(0, 100).map((l) => (
+                          <tr key={l.path}>
+                            <td>
+                              <code>{l.path}</code>
+                            </td>
+                            <td className="muted">→ {l.target}</td>
+                          </tr>
+                        ))}
+                      </tbody>
+                    </table>
+                  </details>
+                )}
+
+                {(duplicateDirs.get(result.scanId)?.length ?? 0) > 0 && (
+                  <details style={{ marginTop: 12 }}>
+                    <summary className="muted">Duplicate folders</summary>
+                    <table className="table">
+                      <tbody>
+                        {duplicateDirs.get(result.scanId)!.slice(0, 50).map((g) => (
+                          <tr key={g.paths[0]}>
+                            <td>
+                              {g.paths.map((p) => (
+                                <div key={p}>
+                                  <code>{p}</code>
+                                </div>
+                              ))}
+                            </td>
+                            <td>
+                              {formatBytes(g.size)}{" "}
+                              <span className="muted">{g.files.toLocaleString()} files</span>
+                            </td>
+                          </tr>
+                        ))}
+                      </tbody>
+                    </table>
+                  </details>
+                )}
+
+                {(duplicateFiles.get(result.scanId)?.length ?? 0) > 0 && (
+                  <details style={{ marginTop: 12 }}>
+                    <summary className="muted">Duplicate files</summary>
+                    <table className="table">
+                      <tbody>
+                        {duplicateFiles.get(result.scanId)!.slice(0, 50).map((g) => (
+                          <tr key={g.paths[0]}>
+                            <td>
+                              {g.paths.map((p) => (
+                                <div key={p}>
+                                  <code>{p}</code>
+                                </div>
+                              ))}
+                            </td>
+                            <td>{formatBytes(g.size)}</td>
+                          </tr>
+                        ))}
+                      </tbody>
+                    </table>
+                  </details>
+                )}
+
+                {(similarImages.get(result.scanId)?.length ?? 0) > 0 && (
+                  <details style={{ marginTop: 12 }}>
+                    <summary className="muted">Similar images</summary>
+                    <table className="table">
+                      <tbody>
+                        {similarImages.get(result.scanId)!.slice(0, 50).map((g) => (
+                          <tr key={g.images[0].path}>
+                            <td>
+                              {g.images.map((image) => (
+                                <div key={image.path}>
+                                  <code>{image.path}</code>{" "}
+                                  <span className="muted">
+                                    {image.width}×{image.height}, {formatBytes(image.size)}
+                                  </span>
+                                </div>
+                              ))}
+                            </td>
+                            <td>{formatBytes(g.reclaimable)}</td>
+                          </tr>
+                        ))}
+                      </tbody>
+                    </table>
+                  </details>
+                )}
+
+                {(gitRepos.get(result.scanId)?.length ?? 0) > 0 && (
+                  <details style={{ marginTop: 12 }}>
+                    <summary className="muted">Git repositories</summary>
+                    <table className="table">
+                      <thead>
+                        <tr>
+                          <th>Repository</th>
+                          <th>Working tree</th>
+                          <th>.git</th>
+                          <th>Largest pack</th>
+                          <th></th>
+                        </tr>
+                      </thead>
+                      <tbody>
+                        {gitRepos.get(result.scanId)!.map((repo) => (
+                          <tr key={repo.path}>
+                            <td>
+                              <code>{repo.path}</code>
+                            </td>
+                            <td>{formatBytes(repo.worktree_bytes)}</td>
+                            <td>{formatBytes(repo.git_bytes)}</td>
+                            <td title={`${repo.packs} packs, ${repo.loose_objects} loose objects`}>
+                              {repo.largest_packs.length > 0
+                                ? formatBytes(repo.largest_packs[0].size)
+                                : ""}
+                            </td>
+                            <td className="muted">
+                              {[repo.gc_suggested && "git gc would help", repo.shallow && "shallow"]
+                                .filter(Boolean)
+                                .join(", ")}
+                            </td>
+                          </tr>
+                        ))}
+                      </tbody>
+                    </table>
+                  </details>
+                )}
+
+                {(devArtifacts.get(result.scanId)?.length ?? 0) > 0 && (
+                  <details style={{ marginTop: 12 }}>
+                    <summary className="muted">
+                      Developer artifacts (
+                      {formatBytes(
+                        devArtifacts.get(result.scanId)!.reduce((sum, p) => sum + p.bytes, 0)
+                      )}{" "}
+                      reclaimable)
+                    </summary>
+                    <button
+                      style={{ marginTop: 8 }}
+                      onClick={() => removeDevArtifacts(result.scanId)}
+                    >
+                      Remove all
+                    </button>
+                    <table className="table">
+                      <tbody>
+                        {devArtifacts.get(result.scanId)!.map((project) => (
+                          <tr key={project.path}>
+                            <td>
+                              <code>{project.path}</code>
+                            </td>
+                            <td>{formatBytes(project.bytes)}</td>
+                            <td className="muted">
+                              {project.artifacts
+                                .map(
+                                  (a) => `${ARTIFACT_KINDS[a.kind] ?? a.kind} ${formatBytes(a.bytes)}`
+                                )
+                                .join(", ")}
+                            </td>
+                          </tr>
+                        ))}
+                      </tbody>
+                    </table>
+                  </details>
+                )}
+
+                {(games.get(result.scanId)?.length ?? 0) > 0 && (
+                  <details style={{ marginTop: 12 }}>
+                    <summary className="muted">Games</summary>
+                    <table className="table">
+                      <thead>
+                        <tr>
+                          <th>Game</th>
+                          <th>Store</th>
+                          <th>Installed</th>
+                          <th>Shader cache</th>
+                          <th>Workshop</th>
+                        </tr>
+                      </thead>
+                      <tbody>
+                        {games.get(result.scanId)!.map((game) => (
+                          <tr key={game.path}>
+                            <td title={game.path}>{game.name}</td>
+                            <td className="muted">{GAME_STORES[game.store]}</td>
+                            <td>{formatBytes(game.install_bytes)}</td>
+                            <td>
+                              {game.shader_cache_bytes > 0
+                                ? formatBytes(game.shader_cache_bytes)
+                                : ""}
+                            </td>
+                            <td>
+                              {game.workshop_bytes > 0 ? formatBytes(game.workshop_bytes) : ""}
+                            </td>
+                          </tr>
+                        ))}
+                      </tbody>
+                    </table>
+                  </details>
+                )}
+
+                {(topDirs.get(result.scanId)?.length ?? 0) > 0 && (
+                  <details style={{ marginTop: 12 }}>
+                    <summary className="muted">Folders with the most entries</summary>
+                    <table className="table">
+                      <tbody>
+                        {topDirs.get(result.scanId)!.map((d) => (
+                          <tr key={d.path}>
+                            <td>
+                              <code>{d.path}</code>
+                            </td>
+                            <td>{d.entries.toLocaleString()}</td>
+                          </tr>
+                        ))}
+                      </tbody>
+                    </table>
+                  </details>
+                )}
               </div>
             );
           })()}